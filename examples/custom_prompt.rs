@@ -34,6 +34,11 @@ impl Prompt for CustomPrompt {
         Cow::Owned(">".to_string())
     }
 
+    // Accepted lines keep only a short prompt in the scrollback
+    fn render_prompt_transient(&self) -> Option<Cow<str>> {
+        Some(Cow::Borrowed("$ "))
+    }
+
     fn render_prompt_multiline_indicator(&self) -> Cow<str> {
         Cow::Borrowed(DEFAULT_MULTILINE_INDICATOR)
     }
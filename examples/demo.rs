@@ -1,8 +1,8 @@
 use {
     crossterm::{
         cursor::SetCursorStyle,
-        event::{DisableBracketedPaste, KeyCode, KeyModifiers},
-        execute, Result,
+        event::{KeyCode, KeyModifiers},
+        Result,
     },
     nu_ansi_term::{Color, Style},
     reedline::{
@@ -11,12 +11,12 @@ use {
         EditCommand, EditMode, Emacs, ExampleHighlighter, Keybindings, ListMenu, Reedline,
         ReedlineEvent, ReedlineMenu, Signal, Vi,
     },
-    std::io::stdout,
 };
 
 use reedline::CursorConfig;
 #[cfg(not(any(feature = "sqlite", feature = "sqlite-dynlib")))]
 use reedline::FileBackedHistory;
+use std::time::Duration;
 
 fn main() -> Result<()> {
     println!("Ctrl-D to quit");
@@ -63,7 +63,7 @@ fn main() -> Result<()> {
     let cursor_config = CursorConfig {
         vi_insert: Some(SetCursorStyle::BlinkingBar),
         vi_normal: Some(SetCursorStyle::SteadyBlock),
-        emacs: None,
+        ..Default::default()
     };
 
     // Setting history_per_session to true will allow the history to be isolated to the current session
@@ -89,12 +89,6 @@ fn main() -> Result<()> {
         ))
         .with_validator(Box::new(DefaultValidator))
         .with_ansi_colors(true);
-    let res = line_editor.enable_bracketed_paste();
-    let bracketed_paste_enabled = res.is_ok();
-    if !bracketed_paste_enabled {
-        println!("Warn: failed to enable bracketed paste mode: {res:?}");
-    }
-
     // Adding default menus for the compiled reedline
     line_editor = line_editor
         .with_menu(ReedlineMenu::EngineCompleter(Box::new(
@@ -113,7 +107,11 @@ fn main() -> Result<()> {
 
         add_newline_keybinding(&mut insert_keybindings);
 
-        Box::new(Vi::new(insert_keybindings, normal_keybindings))
+        // Typing `jk` quickly leaves insert mode like Esc
+        Box::new(
+            Vi::new(insert_keybindings, normal_keybindings)
+                .with_insert_escape_sequence("jk", Duration::from_millis(300)),
+        )
     } else {
         let mut keybindings = default_emacs_keybindings();
         add_menu_keybindings(&mut keybindings);
@@ -217,9 +215,6 @@ fn main() -> Result<()> {
         }
     }
 
-    if bracketed_paste_enabled {
-        let _ = execute!(stdout(), DisableBracketedPaste);
-    }
     println!();
     Ok(())
 }
@@ -244,6 +239,7 @@ fn add_menu_keybindings(keybindings: &mut Keybindings) {
         KeyModifiers::NONE,
         KeyCode::Tab,
         ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::Edit(vec![EditCommand::NextTabStop]),
             ReedlineEvent::Menu("completion_menu".to_string()),
             ReedlineEvent::Edit(vec![EditCommand::Complete]),
         ]),
@@ -252,7 +248,10 @@ fn add_menu_keybindings(keybindings: &mut Keybindings) {
     keybindings.add_binding(
         KeyModifiers::SHIFT,
         KeyCode::BackTab,
-        ReedlineEvent::MenuPrevious,
+        ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::Edit(vec![EditCommand::PrevTabStop]),
+            ReedlineEvent::MenuPrevious,
+        ]),
     );
 }
 
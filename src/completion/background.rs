@@ -0,0 +1,190 @@
+use std::{
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+};
+
+use crate::{Completer, Suggestion};
+
+/// Line and cursor position the completions are computed for
+type Request = (String, usize);
+
+/// A completer adapter that runs a slow completer on a worker thread
+///
+/// A call to [`Completer::complete`] only schedules the completions for the line
+/// and returns right away. Until they are computed no values are returned and
+/// [`Completer::is_loading`] is true, so the menu shows its loading message.
+/// [`Reedline`](crate::Reedline) polls the adapter while waiting for input and
+/// refreshes the active menu once the completions arrive.
+///
+/// While the worker is busy only the latest scheduled line is kept, the lines typed
+/// in between are never completed.
+///
+/// # Example
+///
+/// ```rust
+/// use reedline::{BackgroundCompleter, DefaultCompleter, Reedline};
+///
+/// let commands = vec!["test".into(), "hello world".into()];
+/// let completer = DefaultCompleter::new_with_wordlen(commands, 2);
+/// let completer = Box::new(BackgroundCompleter::new(Box::new(completer)));
+/// let mut line_editor = Reedline::create().with_completer(completer);
+/// ```
+pub struct BackgroundCompleter {
+    requests: Sender<Request>,
+    results: Receiver<(Request, Vec<Suggestion>)>,
+    // Request sent to the worker whose completions haven't arrived yet
+    pending: Option<Request>,
+    // Last completions received from the worker
+    completed: Option<(Request, Vec<Suggestion>)>,
+    // The pending completions arrived since the last call to complete
+    ready: bool,
+}
+
+impl BackgroundCompleter {
+    /// Construct the adapter, spawning the worker thread that owns `completer`
+    ///
+    /// The worker stops once the adapter is dropped
+    pub fn new(completer: Box<dyn Completer>) -> Self {
+        let (requests, request_receiver) = channel::<Request>();
+        let (result_sender, results) = channel();
+
+        thread::spawn(move || {
+            let mut completer = completer;
+            while let Ok(mut request) = request_receiver.recv() {
+                // Only the latest request is still of interest
+                while let Ok(newer) = request_receiver.try_recv() {
+                    request = newer;
+                }
+
+                let suggestions = completer.complete(&request.0, request.1);
+                if result_sender.send((request, suggestions)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            requests,
+            results,
+            pending: None,
+            completed: None,
+            ready: false,
+        }
+    }
+
+    fn receive(&mut self) {
+        while let Ok((request, suggestions)) = self.results.try_recv() {
+            if self.pending.as_ref() == Some(&request) {
+                self.pending = None;
+                self.ready = true;
+            }
+            self.completed = Some((request, suggestions));
+        }
+    }
+}
+
+impl Completer for BackgroundCompleter {
+    fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+        self.receive();
+        self.ready = false;
+
+        if let Some(((completed_line, completed_pos), suggestions)) = &self.completed {
+            if completed_line == line && *completed_pos == pos {
+                self.pending = None;
+                return suggestions.clone();
+            }
+        }
+
+        let scheduled = matches!(
+            &self.pending,
+            Some((pending_line, pending_pos)) if pending_line == line && *pending_pos == pos
+        );
+        if !scheduled {
+            let request = (line.to_string(), pos);
+            // A worker that panicked leaves nothing to wait for
+            self.pending = self.requests.send(request.clone()).ok().map(|_| request);
+        }
+
+        Vec::new()
+    }
+
+    fn is_loading(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    fn poll_completions(&mut self) -> bool {
+        self.receive();
+        std::mem::take(&mut self.ready)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Span;
+    use pretty_assertions::assert_eq;
+    use std::time::{Duration, Instant};
+
+    struct EchoCompleter;
+
+    impl Completer for EchoCompleter {
+        fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+            vec![Suggestion::new(line.to_string(), Span::new(0, pos))]
+        }
+    }
+
+    fn wait_for_completions(completer: &mut BackgroundCompleter) {
+        let start = Instant::now();
+        while !completer.poll_completions() {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "completions never arrived"
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn values(suggestions: &[Suggestion]) -> Vec<&str> {
+        suggestions.iter().map(|s| s.value.as_str()).collect()
+    }
+
+    #[test]
+    fn completions_are_returned_once_computed() {
+        let mut completer = BackgroundCompleter::new(Box::new(EchoCompleter));
+
+        assert!(completer.complete("git", 3).is_empty());
+        assert!(completer.is_loading());
+
+        wait_for_completions(&mut completer);
+
+        assert_eq!(values(&completer.complete("git", 3)), vec!["git"]);
+        assert!(!completer.is_loading());
+        assert!(!completer.poll_completions());
+    }
+
+    #[test]
+    fn only_the_latest_line_is_waited_for() {
+        let mut completer = BackgroundCompleter::new(Box::new(EchoCompleter));
+
+        assert!(completer.complete("g", 1).is_empty());
+        assert!(completer.complete("gi", 2).is_empty());
+
+        wait_for_completions(&mut completer);
+
+        assert_eq!(values(&completer.complete("gi", 2)), vec!["gi"]);
+    }
+
+    #[test]
+    fn new_line_is_scheduled_again() {
+        let mut completer = BackgroundCompleter::new(Box::new(EchoCompleter));
+
+        completer.complete("git", 3);
+        wait_for_completions(&mut completer);
+
+        assert!(completer.complete("cargo", 5).is_empty());
+        assert!(completer.is_loading());
+
+        wait_for_completions(&mut completer);
+        assert_eq!(values(&completer.complete("cargo", 5)), vec!["cargo"]);
+    }
+}
@@ -1,3 +1,7 @@
+use crate::history::HistoryItemId;
+use nu_ansi_term::Style;
+use std::ops::Range;
+
 /// A span of source code, with positions in bytes
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Hash)]
 pub struct Span {
@@ -51,21 +55,347 @@ pub trait Completer: Send {
     fn total_completions(&mut self, line: &str, pos: usize) -> usize {
         self.complete(line, pos).len()
     }
+
+    /// Whether the last call to [`Completer::complete`] returned before the
+    /// completions were computed, e.g. because they are computed in the background
+    ///
+    /// Menus show a loading message instead of the empty values while it is true
+    fn is_loading(&self) -> bool {
+        false
+    }
+
+    /// Checks if completions computed in the background became available since
+    /// the last call to [`Completer::complete`]
+    ///
+    /// [`Reedline`](crate::Reedline) calls it periodically while waiting for input
+    /// and refreshes the active menu when it returns true, without blocking the
+    /// render loop
+    fn poll_completions(&mut self) -> bool {
+        false
+    }
+
+    /// The suggestions that arrived since the last call to [`Completer::complete`] or
+    /// to this method, for a completer that finds its suggestions gradually
+    ///
+    /// [`Reedline`](crate::Reedline) appends them to the values of the menu when
+    /// [`Completer::poll_completions`] returns true, so the suggestions received
+    /// before aren't copied again. Returns `None` if the completer doesn't stream its
+    /// suggestions, then the menu asks for all of them again
+    fn take_new_suggestions(&mut self) -> Option<Vec<Suggestion>> {
+        None
+    }
+}
+
+/// What is added after the value of a [`Suggestion`] when it is inserted in the buffer
+///
+/// The cursor is placed after the value and the appended text
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Append {
+    /// Nothing is added after the value
+    #[default]
+    None,
+    /// A space is added, e.g. to start the next argument
+    Space,
+    /// The character is added, e.g. a path separator after a directory
+    Char(char),
+    /// Nothing is added, but the cursor moves past the character if it follows the
+    /// value, e.g. a closing quote
+    MoveAfter(char),
+}
+
+/// Text with placeholders inserted in place of the value of a [`Suggestion`]
+///
+/// It is parsed from a template where `${1:default}` is a placeholder with its default
+/// text and `$1` an empty one. After the insertion the cursor moves through the
+/// placeholders in the order of their numbers with
+/// [`EditCommand::NextTabStop`](crate::EditCommand::NextTabStop), ending at `$0` if
+/// present. A literal `$` is written as `\$`.
+///
+/// ```
+/// use reedline::Snippet;
+///
+/// let snippet = Snippet::parse("foo(${1:arg1}, ${2:arg2})$0");
+///
+/// assert_eq!(snippet.text, "foo(arg1, arg2)");
+/// assert_eq!(snippet.tab_stops, vec![4..8, 10..14, 15..15]);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    /// Text inserted in the buffer, with the default text of the placeholders
+    pub text: String,
+    /// Byte ranges of the placeholders in the text, in the order the cursor visits them
+    pub tab_stops: Vec<Range<usize>>,
+}
+
+impl Snippet {
+    /// Parses a snippet template
+    ///
+    /// A `$` that doesn't start a valid placeholder is kept as it is
+    pub fn parse(template: &str) -> Self {
+        let mut text = String::with_capacity(template.len());
+        let mut tab_stops: Vec<(usize, Range<usize>)> = Vec::new();
+        let mut rest = template;
+
+        while let Some(index) = rest.find(['$', '\\']) {
+            text.push_str(&rest[..index]);
+            rest = &rest[index..];
+
+            if let Some(after) = rest.strip_prefix("\\$") {
+                text.push('$');
+                rest = after;
+            } else if let Some((number, default, after)) = parse_placeholder(rest) {
+                let start = text.len();
+                text.push_str(default);
+                tab_stops.push((number, start..text.len()));
+                rest = after;
+            } else {
+                let c = rest.chars().next().unwrap_or_default();
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        text.push_str(rest);
+
+        // `$0` is the final position, after all numbered placeholders
+        tab_stops.sort_by_key(|(number, _)| if *number == 0 { usize::MAX } else { *number });
+        Self {
+            text,
+            tab_stops: tab_stops.into_iter().map(|(_, range)| range).collect(),
+        }
+    }
+}
+
+/// Parses `$1` or `${1:default}` at the start of `text` into its number, default text
+/// and the text after it
+fn parse_placeholder(text: &str) -> Option<(usize, &str, &str)> {
+    let text = text.strip_prefix('$')?;
+    let number_end = |text: &str| {
+        text.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len())
+    };
+
+    if let Some(braced) = text.strip_prefix('{') {
+        let end = number_end(braced);
+        let number = braced[..end].parse().ok()?;
+        let rest = &braced[end..];
+        if let Some(after) = rest.strip_prefix('}') {
+            return Some((number, "", after));
+        }
+        let default_and_after = rest.strip_prefix(':')?;
+        let close = default_and_after.find('}')?;
+        Some((
+            number,
+            &default_and_after[..close],
+            &default_and_after[close + 1..],
+        ))
+    } else {
+        let end = number_end(text);
+        let number = text[..end].parse().ok()?;
+        Some((number, "", &text[end..]))
+    }
 }
 
 /// Suggestion returned by the Completer
+///
+/// Prefer building it with [`Suggestion::new`] and the `with_` methods over a struct
+/// literal, so the completer keeps compiling when new fields are added.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Suggestion {
     /// String replacement that will be introduced to the the buffer
     pub value: String,
     /// Optional description for the replacement
     pub description: Option<String>,
+    /// Optional documentation for the replacement. Unlike the one line description,
+    /// it can span multiple lines and is shown in a separate panel by menus that
+    /// support it, e.g. [`ColumnarMenu::with_documentation`](crate::ColumnarMenu::with_documentation)
+    pub documentation: Option<String>,
     /// Optional vector of strings in the suggestion. These can be used to
     /// represent examples coming from a suggestion
     pub extra: Option<Vec<String>>,
     /// Replacement span in the buffer
     pub span: Span,
-    /// Whether to append a space after selecting this suggestion.
-    /// This helps to avoid that a completer repeats the complete suggestion.
+    /// What to append after selecting this suggestion.
+    /// Appending a space helps to avoid that a completer repeats the complete suggestion.
+    pub append: Append,
+    /// Whether to append a space after selecting this suggestion, in case
+    /// [`Suggestion::append`] is [`Append::None`]
+    #[deprecated(note = "use `append` with `Append::Space` instead")]
     pub append_whitespace: bool,
+    /// Span of the buffer, once this suggestion is inserted, to start a new completion
+    /// from. The completer is asked to complete only the text of the span, with the
+    /// cursor moved into it. This allows alias-style suggestions to expand and
+    /// immediately show the completions for the expanded text, tokenized on its own.
+    pub recomplete: Option<Span>,
+    /// Optional style of the value, used by menus that support it in place of their
+    /// text style, e.g. to color directories and files differently. The selected value
+    /// keeps the style of the menu selection
+    pub style: Option<Style>,
+    /// Optional relevance of the suggestion, higher is better. Menus that rank the
+    /// values, e.g. with [`ColumnarMenu::with_score_sort`](crate::ColumnarMenu::with_score_sort),
+    /// show the values with the highest score first
+    pub score: Option<i64>,
+    /// Optional snippet inserted in place of the value, with placeholders the cursor
+    /// moves through. The value is still shown by the menus
+    pub snippet: Option<Snippet>,
+    /// Id of the history entry the value comes from, set by the history menu. The
+    /// entry is the one deleted with
+    /// [`ReedlineEvent::MenuDelete`](crate::ReedlineEvent::MenuDelete)
+    pub history_id: Option<HistoryItemId>,
+}
+
+impl Suggestion {
+    /// Creates a suggestion that replaces the `span` of the buffer with `value`
+    ///
+    /// The other fields are empty or disabled and can be set with the builder methods
+    ///
+    /// ```
+    /// use reedline::{Append, Span, Suggestion};
+    ///
+    /// let suggestion = Suggestion::new("status", Span::new(4, 6))
+    ///     .with_description("Show the working tree status")
+    ///     .with_append(Append::Space);
+    ///
+    /// assert_eq!(suggestion.value, "status");
+    /// assert_eq!(
+    ///     suggestion.description.as_deref(),
+    ///     Some("Show the working tree status")
+    /// );
+    /// assert_eq!(suggestion.append, Append::Space);
+    /// assert!(suggestion.extra.is_none());
+    /// ```
+    pub fn new(value: impl Into<String>, span: Span) -> Self {
+        Self {
+            value: value.into(),
+            span,
+            ..Self::default()
+        }
+    }
+
+    /// Suggestion builder with a description
+    #[must_use]
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Suggestion builder with a documentation
+    #[must_use]
+    pub fn with_documentation(mut self, documentation: impl Into<String>) -> Self {
+        self.documentation = Some(documentation.into());
+        self
+    }
+
+    /// Suggestion builder with extra strings
+    #[must_use]
+    pub fn with_extra(mut self, extra: Vec<String>) -> Self {
+        self.extra = Some(extra);
+        self
+    }
+
+    /// Suggestion builder with what is appended after the value is inserted
+    #[must_use]
+    pub fn with_append(mut self, append: Append) -> Self {
+        self.append = append;
+        self
+    }
+
+    /// Suggestion builder that appends a whitespace after the value is inserted
+    #[must_use]
+    pub fn with_append_whitespace(mut self, append_whitespace: bool) -> Self {
+        self.append = if append_whitespace {
+            Append::Space
+        } else {
+            Append::None
+        };
+        self
+    }
+
+    /// What is appended after the value is inserted, taking the deprecated
+    /// [`Suggestion::append_whitespace`] into account
+    #[allow(deprecated)]
+    pub(crate) fn append_policy(&self) -> Append {
+        match self.append {
+            Append::None if self.append_whitespace => Append::Space,
+            append => append,
+        }
+    }
+
+    /// Suggestion builder with the style of the value
+    #[must_use]
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    /// Suggestion builder with the relevance score of the value
+    #[must_use]
+    pub fn with_score(mut self, score: i64) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    /// Suggestion builder with a snippet inserted in place of the value, parsed from
+    /// the `template` with [`Snippet::parse`]
+    #[must_use]
+    pub fn with_snippet(mut self, template: &str) -> Self {
+        self.snippet = Some(Snippet::parse(template));
+        self
+    }
+
+    /// Suggestion builder that starts a new completion of the text of `span` after the
+    /// value is inserted, see [`Suggestion::recomplete`]
+    #[must_use]
+    pub fn with_recomplete(mut self, span: Span) -> Self {
+        self.recomplete = Some(span);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    // rstest doesn't pass an `allow` on the test on to its cases
+    #[allow(deprecated)]
+    fn suggestion_with_append_whitespace(append: Append, append_whitespace: bool) -> Suggestion {
+        Suggestion {
+            append,
+            append_whitespace,
+            ..Suggestion::default()
+        }
+    }
+
+    #[rstest]
+    #[case(Append::None, false, Append::None)]
+    #[case(Append::None, true, Append::Space)]
+    #[case(Append::Char('/'), true, Append::Char('/'))]
+    fn deprecated_append_whitespace_maps_onto_append(
+        #[case] append: Append,
+        #[case] append_whitespace: bool,
+        #[case] expected: Append,
+    ) {
+        let suggestion = suggestion_with_append_whitespace(append, append_whitespace);
+        assert_eq!(suggestion.append_policy(), expected);
+    }
+
+    #[rstest]
+    #[case("foo(${1:arg1}, ${2:arg2})", "foo(arg1, arg2)", vec![4..8, 10..14])]
+    #[case("$0 end $1", " end ", vec![5..5, 0..0])]
+    #[case("a ${2} b ${1:x}", "a  b x", vec![5..6, 2..2])]
+    #[case("cost \\$5 $", "cost $5 $", vec![])]
+    #[case("${x:y} ${1:open", "${x:y} ${1:open", vec![])]
+    fn parse_snippet(
+        #[case] template: &str,
+        #[case] text: &str,
+        #[case] tab_stops: Vec<Range<usize>>,
+    ) {
+        assert_eq!(
+            Snippet::parse(template),
+            Snippet {
+                text: text.to_string(),
+                tab_stops,
+            }
+        );
+    }
 }
@@ -55,17 +55,17 @@ impl Completer for DefaultCompleter {
     /// assert_eq!(
     ///     completions.complete("bat",3),
     ///     vec![
-    ///         Suggestion {value: "batcave".into(), description: None, extra: None, span: Span { start: 0, end: 3 }, append_whitespace: false},
-    ///         Suggestion {value: "batman".into(), description: None, extra: None, span: Span { start: 0, end: 3 }, append_whitespace: false},
-    ///         Suggestion {value: "batmobile".into(), description: None, extra: None, span: Span { start: 0, end: 3 }, append_whitespace: false},
+    ///         Suggestion {value: "batcave".into(), span: Span { start: 0, end: 3 }, ..Default::default()},
+    ///         Suggestion {value: "batman".into(), span: Span { start: 0, end: 3 }, ..Default::default()},
+    ///         Suggestion {value: "batmobile".into(), span: Span { start: 0, end: 3 }, ..Default::default()},
     ///     ]);
     ///
     /// assert_eq!(
     ///     completions.complete("to the bat",10),
     ///     vec![
-    ///         Suggestion {value: "batcave".into(), description: None, extra: None, span: Span { start: 7, end: 10 }, append_whitespace: false},
-    ///         Suggestion {value: "batman".into(), description: None, extra: None, span: Span { start: 7, end: 10 }, append_whitespace: false},
-    ///         Suggestion {value: "batmobile".into(), description: None, extra: None, span: Span { start: 7, end: 10 }, append_whitespace: false},
+    ///         Suggestion {value: "batcave".into(), span: Span { start: 7, end: 10 }, ..Default::default()},
+    ///         Suggestion {value: "batman".into(), span: Span { start: 7, end: 10 }, ..Default::default()},
+    ///         Suggestion {value: "batmobile".into(), span: Span { start: 7, end: 10 }, ..Default::default()},
     ///     ]);
     /// ```
     fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
@@ -98,10 +98,8 @@ impl Completer for DefaultCompleter {
 
                                     Suggestion {
                                         value: format!("{span_line}{ext}"),
-                                        description: None,
-                                        extra: None,
                                         span,
-                                        append_whitespace: false,
+                                        ..Suggestion::default()
                                     }
                                 })
                                 .filter(|t| t.value.len() > (t.span.end - t.span.start))
@@ -173,15 +171,15 @@ impl DefaultCompleter {
     /// completions.insert(vec!["test-hyphen","test_underscore"].iter().map(|s| s.to_string()).collect());
     /// assert_eq!(
     ///     completions.complete("te",2),
-    ///     vec![Suggestion {value: "test".into(), description: None, extra: None, span: Span { start: 0, end: 2 }, append_whitespace: false}]);
+    ///     vec![Suggestion {value: "test".into(), span: Span { start: 0, end: 2 }, ..Default::default()}]);
     ///
     /// let mut completions = DefaultCompleter::with_inclusions(&['-', '_']);
     /// completions.insert(vec!["test-hyphen","test_underscore"].iter().map(|s| s.to_string()).collect());
     /// assert_eq!(
     ///     completions.complete("te",2),
     ///     vec![
-    ///         Suggestion {value: "test-hyphen".into(), description: None, extra: None, span: Span { start: 0, end: 2 }, append_whitespace: false},
-    ///         Suggestion {value: "test_underscore".into(), description: None, extra: None, span: Span { start: 0, end: 2 }, append_whitespace: false},
+    ///         Suggestion {value: "test-hyphen".into(), span: Span { start: 0, end: 2 }, ..Default::default()},
+    ///         Suggestion {value: "test_underscore".into(), span: Span { start: 0, end: 2 }, ..Default::default()},
     ///     ]);
     /// ```
     pub fn with_inclusions(incl: &[char]) -> Self {
@@ -370,24 +368,18 @@ mod tests {
             vec![
                 Suggestion {
                     value: "ｎｕｌｌ".into(),
-                    description: None,
-                    extra: None,
                     span: Span { start: 0, end: 3 },
-                    append_whitespace: false,
+                    ..Suggestion::default()
                 },
                 Suggestion {
                     value: "ｎｕｍｂｅｒ".into(),
-                    description: None,
-                    extra: None,
                     span: Span { start: 0, end: 3 },
-                    append_whitespace: false,
+                    ..Suggestion::default()
                 },
                 Suggestion {
                     value: "ｎｕｓｈｅｌｌ".into(),
-                    description: None,
-                    extra: None,
                     span: Span { start: 0, end: 3 },
-                    append_whitespace: false,
+                    ..Suggestion::default()
                 },
             ]
         );
@@ -0,0 +1,261 @@
+use std::path::{is_separator, Path, PathBuf, MAIN_SEPARATOR};
+
+use crate::{Completer, Span, Suggestion};
+
+/// A completer for filesystem paths
+///
+/// The word under the cursor is treated as a path. Relative paths are resolved
+/// against the configured base directory while absolute paths are used as is.
+/// Only the entries of the last path component are completed, the directory part
+/// typed by the user is kept untouched in the suggestion.
+///
+/// Directories are suggested with a trailing [`MAIN_SEPARATOR`] (`/` on unix, `\`
+/// on windows) and don't append a whitespace, so the completion can continue into
+/// the directory. Files append a whitespace after being selected.
+/// While parsing the typed path every separator accepted by the platform is
+/// recognized, e.g. both `/` and `\` on windows.
+///
+/// # Example
+///
+/// ```rust
+/// use reedline::{FileCompleter, Reedline};
+///
+/// let completer = Box::new(FileCompleter::new(".").with_show_hidden(true));
+/// let mut line_editor = Reedline::create().with_completer(completer);
+/// ```
+pub struct FileCompleter {
+    base_dir: PathBuf,
+    show_hidden: bool,
+    case_sensitive: bool,
+}
+
+impl Default for FileCompleter {
+    fn default() -> Self {
+        Self::new(".")
+    }
+}
+
+impl FileCompleter {
+    /// Construct a completer that resolves relative paths against `base_dir`
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            base_dir: base_dir.into(),
+            show_hidden: false,
+            case_sensitive: true,
+        }
+    }
+
+    /// Completer builder with a new base directory for relative paths
+    #[must_use]
+    pub fn with_base_dir(mut self, base_dir: impl Into<PathBuf>) -> Self {
+        self.base_dir = base_dir.into();
+        self
+    }
+
+    /// Completer builder that includes hidden files (starting with `.`)
+    ///
+    /// Hidden files are always suggested if the typed component starts with `.`
+    #[must_use]
+    pub fn with_show_hidden(mut self, show_hidden: bool) -> Self {
+        self.show_hidden = show_hidden;
+        self
+    }
+
+    /// Completer builder with case sensitive matching of the typed component
+    #[must_use]
+    pub fn with_case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    fn matches(&self, name: &str, prefix: &str) -> bool {
+        if !self.show_hidden && name.starts_with('.') && !prefix.starts_with('.') {
+            return false;
+        }
+
+        if self.case_sensitive {
+            name.starts_with(prefix)
+        } else {
+            name.to_lowercase().starts_with(&prefix.to_lowercase())
+        }
+    }
+}
+
+impl Completer for FileCompleter {
+    /// Returns the entries that complete the path found before `pos`
+    ///
+    /// # Arguments
+    ///
+    /// * `line`    The line to complete
+    /// * `pos`   The cursor position
+    ///
+    /// ```
+    /// use reedline::{Completer, FileCompleter, Span};
+    ///
+    /// let dir = std::env::temp_dir().join("reedline_file_completer_doc");
+    /// std::fs::create_dir_all(dir.join("src")).unwrap();
+    ///
+    /// let mut completer = FileCompleter::new(&dir);
+    /// let suggestions = completer.complete("ls sr", 5);
+    ///
+    /// assert_eq!(suggestions.len(), 1);
+    /// assert_eq!(suggestions[0].value, format!("src{}", std::path::MAIN_SEPARATOR));
+    /// assert_eq!(suggestions[0].span, Span::new(3, 5));
+    /// ```
+    fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+        let line = &line[..pos];
+        let start = line
+            .rfind(char::is_whitespace)
+            .map(|index| index + 1)
+            .unwrap_or(0);
+        let token = &line[start..];
+
+        let (dir_part, prefix) = match token.rfind(is_separator) {
+            Some(index) => token.split_at(index + 1),
+            None => ("", token),
+        };
+
+        let dir = if Path::new(dir_part).is_absolute() {
+            PathBuf::from(dir_part)
+        } else {
+            self.base_dir.join(dir_part)
+        };
+
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut suggestions: Vec<Suggestion> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                if !self.matches(&name, prefix) {
+                    return None;
+                }
+
+                let is_dir = entry.path().is_dir();
+                let mut value = format!("{dir_part}{name}");
+                if is_dir {
+                    value.push(MAIN_SEPARATOR);
+                }
+
+                Some(Suggestion::new(value, Span::new(start, pos)).with_append_whitespace(!is_dir))
+            })
+            .collect();
+
+        suggestions.sort_by(|a, b| a.value.cmp(&b.value));
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Append;
+    use pretty_assertions::assert_eq;
+    use std::fs::{create_dir, File};
+    use tempfile::TempDir;
+
+    fn test_dir() -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        create_dir(dir.path().join("src")).unwrap();
+        create_dir(dir.path().join(".git")).unwrap();
+        File::create(dir.path().join("Cargo.toml")).unwrap();
+        File::create(dir.path().join("src").join("lib.rs")).unwrap();
+        File::create(dir.path().join("src").join("main.rs")).unwrap();
+        dir
+    }
+
+    fn values(suggestions: &[Suggestion]) -> Vec<&str> {
+        suggestions.iter().map(|s| s.value.as_str()).collect()
+    }
+
+    #[test]
+    fn completes_entries_of_base_dir() {
+        let dir = test_dir();
+        let mut completer = FileCompleter::new(dir.path());
+
+        let suggestions = completer.complete("cat ", 4);
+
+        assert_eq!(
+            values(&suggestions),
+            vec!["Cargo.toml".to_string(), format!("src{MAIN_SEPARATOR}")]
+        );
+        assert_eq!(suggestions[0].append, Append::Space);
+        assert_eq!(suggestions[1].append, Append::None);
+    }
+
+    #[test]
+    fn completes_inside_nested_directory() {
+        let dir = test_dir();
+        let mut completer = FileCompleter::new(dir.path());
+
+        let suggestions = completer.complete("cat src/m", 9);
+
+        assert_eq!(values(&suggestions), vec!["src/main.rs"]);
+        assert_eq!(suggestions[0].span, Span::new(4, 9));
+    }
+
+    #[test]
+    fn completes_only_up_to_the_cursor() {
+        let dir = test_dir();
+        let mut completer = FileCompleter::new(dir.path());
+
+        let suggestions = completer.complete("Ca --flag", 2);
+
+        assert_eq!(values(&suggestions), vec!["Cargo.toml"]);
+        assert_eq!(suggestions[0].span, Span::new(0, 2));
+    }
+
+    #[test]
+    fn hidden_files() {
+        let dir = test_dir();
+
+        let mut completer = FileCompleter::new(dir.path());
+        assert_eq!(completer.complete("", 0).len(), 2);
+        assert_eq!(
+            values(&completer.complete(".", 1)),
+            vec![format!(".git{MAIN_SEPARATOR}")]
+        );
+
+        let mut completer = completer.with_show_hidden(true);
+        assert_eq!(completer.complete("", 0).len(), 3);
+    }
+
+    #[test]
+    fn case_sensitivity() {
+        let dir = test_dir();
+
+        let mut completer = FileCompleter::new(dir.path());
+        assert!(completer.complete("cargo", 5).is_empty());
+
+        let mut completer = completer.with_case_sensitive(false);
+        assert_eq!(values(&completer.complete("cargo", 5)), vec!["Cargo.toml"]);
+    }
+
+    #[test]
+    fn absolute_paths_ignore_base_dir() {
+        let dir = test_dir();
+        let mut completer = FileCompleter::new("/nonexistent");
+
+        let line = format!("{}{MAIN_SEPARATOR}Ca", dir.path().display());
+        let suggestions = completer.complete(&line, line.len());
+
+        assert_eq!(
+            values(&suggestions),
+            vec![format!(
+                "{}{MAIN_SEPARATOR}Cargo.toml",
+                dir.path().display()
+            )]
+        );
+    }
+
+    #[test]
+    fn missing_directory_returns_nothing() {
+        let dir = test_dir();
+        let mut completer = FileCompleter::new(dir.path());
+
+        assert!(completer.complete("missing/", 8).is_empty());
+    }
+}
@@ -1,8 +1,6 @@
-use std::ops::Deref;
-
 use crate::{
-    history::SearchQuery, menu_functions::parse_selection_char, Completer, History, Span,
-    Suggestion,
+    history::SearchQuery, menu_functions::parse_selection_char, Completer, History, HistoryItem,
+    Span, Suggestion,
 };
 
 const SELECTION_CHAR: char = '!';
@@ -27,11 +25,28 @@ impl<'menu> Completer for HistoryCompleter<'menu> {
 
         values
             .into_iter()
-            .map(|value| self.create_suggestion(line, pos, value.command_line.deref()))
+            .map(|value| self.create_suggestion(line, pos, value))
             .collect()
     }
 
-    // TODO: Implement `fn partial_complete()`
+    fn partial_complete(
+        &mut self,
+        line: &str,
+        pos: usize,
+        start: usize,
+        offset: usize,
+    ) -> Vec<Suggestion> {
+        let parsed = parse_selection_char(line, SELECTION_CHAR);
+        let mut query = SearchQuery::all_that_contain_rev(parsed.remainder.to_string());
+        query.limit = Some((start + offset) as i64);
+        let values = self.0.search(query).expect("todo: error handling");
+
+        values
+            .into_iter()
+            .skip(start)
+            .map(|value| self.create_suggestion(line, pos, value))
+            .collect()
+    }
 
     fn total_completions(&mut self, line: &str, _pos: usize) -> usize {
         let parsed = parse_selection_char(line, SELECTION_CHAR);
@@ -50,18 +65,17 @@ impl<'menu> HistoryCompleter<'menu> {
         Self(history)
     }
 
-    fn create_suggestion(&self, line: &str, pos: usize, value: &str) -> Suggestion {
+    fn create_suggestion(&self, line: &str, pos: usize, item: HistoryItem) -> Suggestion {
         let span = Span {
             start: pos,
             end: pos + line.len(),
         };
 
         Suggestion {
-            value: value.to_string(),
-            description: None,
-            extra: None,
+            value: item.command_line,
             span,
-            append_whitespace: false,
+            history_id: item.id,
+            ..Suggestion::default()
         }
     }
 }
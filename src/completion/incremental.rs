@@ -0,0 +1,369 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, Sender, TryRecvError},
+        Arc,
+    },
+    thread,
+};
+
+use crate::{Completer, Suggestion};
+
+/// Line and cursor position the completions are computed for
+type Request = (String, usize);
+
+/// A request for the worker with its id and the flag cancelling it
+type Job = (u64, Request, Arc<AtomicBool>);
+
+/// A batch of suggestions for the request with the id, `None` once all were sent
+type Batch = (u64, Option<Vec<Suggestion>>);
+
+/// A completer that finds its suggestions gradually, e.g. by walking a large
+/// directory tree or querying a remote index
+///
+/// Run it with an [`IncrementalCompleter`], which shows the suggestions in the menu
+/// while the remaining ones are still searched for.
+pub trait StreamingCompleter: Send {
+    /// Sends the suggestions for the line and cursor position to `sender` in batches
+    ///
+    /// The completion ends when the method returns. Once [`SuggestionSender::send`]
+    /// returns false the line changed and the method should return early, the
+    /// remaining suggestions aren't needed anymore.
+    fn complete_stream(&mut self, line: &str, pos: usize, sender: &SuggestionSender);
+}
+
+/// Hands the batches of suggestions of a [`StreamingCompleter`] to the menu
+pub struct SuggestionSender {
+    id: u64,
+    batches: Sender<Batch>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl SuggestionSender {
+    /// Appends the suggestions to the values of the menu
+    ///
+    /// Returns false without sending them if the completion was cancelled
+    pub fn send(&self, suggestions: Vec<Suggestion>) -> bool {
+        !self.is_cancelled() && self.batches.send((self.id, Some(suggestions))).is_ok()
+    }
+
+    /// Whether the completion was cancelled, because the line changed or the
+    /// completer was dropped
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// A completer adapter that streams the suggestions of a [`StreamingCompleter`]
+/// into the menu
+///
+/// Like the [`BackgroundCompleter`](crate::BackgroundCompleter), a call to
+/// [`Completer::complete`] schedules the completions for the line on a worker
+/// thread. The following calls for the same line return the suggestions received
+/// so far, and [`Reedline`](crate::Reedline) refreshes the active menu whenever a
+/// batch arrives, keeping its selection. Only the suggestions of the new batches are
+/// handed to the menu, see [`Completer::take_new_suggestions`].
+/// [`Completer::is_loading`] is true until the completer is done, or until its
+/// worker thread stopped because the completer panicked.
+///
+/// Completing another line cancels the stream of the previous one.
+///
+/// # Example
+///
+/// ```rust
+/// use reedline::{
+///     IncrementalCompleter, Reedline, Span, StreamingCompleter, Suggestion, SuggestionSender,
+/// };
+///
+/// struct Numbers;
+///
+/// impl StreamingCompleter for Numbers {
+///     fn complete_stream(&mut self, line: &str, pos: usize, sender: &SuggestionSender) {
+///         for batch in 0..10 {
+///             let suggestions = (0..100)
+///                 .map(|n| Suggestion::new(format!("{line}{}", batch * 100 + n), Span::new(0, pos)))
+///                 .collect();
+///             if !sender.send(suggestions) {
+///                 return;
+///             }
+///         }
+///     }
+/// }
+///
+/// let completer = Box::new(IncrementalCompleter::new(Box::new(Numbers)));
+/// let mut line_editor = Reedline::create().with_completer(completer);
+/// ```
+pub struct IncrementalCompleter {
+    requests: Sender<Job>,
+    batches: Receiver<Batch>,
+    // Latest request sent to the worker with its id
+    current: Option<(u64, Request)>,
+    cancelled: Arc<AtomicBool>,
+    // Suggestions received for the latest request
+    suggestions: Vec<Suggestion>,
+    // Number of suggestions already handed to the menu
+    handed_over: usize,
+    // The worker is still sending suggestions for the latest request
+    streaming: bool,
+    // Batches arrived since the last call to complete
+    ready: bool,
+}
+
+impl IncrementalCompleter {
+    /// Construct the adapter, spawning the worker thread that owns `completer`
+    ///
+    /// The worker stops once the adapter is dropped
+    pub fn new(completer: Box<dyn StreamingCompleter>) -> Self {
+        let (requests, job_receiver) = channel::<Job>();
+        let (batch_sender, batches) = channel();
+
+        thread::spawn(move || {
+            let mut completer = completer;
+            while let Ok(mut job) = job_receiver.recv() {
+                // Only the latest request is still of interest
+                while let Ok(newer) = job_receiver.try_recv() {
+                    job = newer;
+                }
+
+                let (id, (line, pos), cancelled) = job;
+                let sender = SuggestionSender {
+                    id,
+                    batches: batch_sender.clone(),
+                    cancelled,
+                };
+                completer.complete_stream(&line, pos, &sender);
+                if batch_sender.send((id, None)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            requests,
+            batches,
+            current: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            suggestions: Vec::new(),
+            handed_over: 0,
+            streaming: false,
+            ready: false,
+        }
+    }
+
+    fn receive(&mut self) {
+        let current_id = self.current.as_ref().map(|(id, _)| *id);
+        loop {
+            match self.batches.try_recv() {
+                // Batches of cancelled requests may still be queued
+                Ok((id, _)) if Some(id) != current_id => {}
+                Ok((_, Some(suggestions))) => {
+                    self.suggestions.extend(suggestions);
+                    self.ready = true;
+                }
+                Ok((_, None)) => {
+                    self.streaming = false;
+                    self.ready = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                // The worker panicked, no more suggestions will arrive
+                Err(TryRecvError::Disconnected) => {
+                    if self.streaming {
+                        self.streaming = false;
+                        self.ready = true;
+                    }
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Completer for IncrementalCompleter {
+    fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+        self.receive();
+        self.ready = false;
+
+        if let Some((_, (current_line, current_pos))) = &self.current {
+            if current_line == line && *current_pos == pos {
+                self.handed_over = self.suggestions.len();
+                return self.suggestions.clone();
+            }
+        }
+
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.cancelled = Arc::new(AtomicBool::new(false));
+        self.suggestions.clear();
+        self.handed_over = 0;
+
+        let id = self.current.as_ref().map_or(0, |(id, _)| id + 1);
+        let request = (line.to_string(), pos);
+        // A worker that panicked leaves nothing to wait for
+        self.streaming = self
+            .requests
+            .send((id, request.clone(), self.cancelled.clone()))
+            .is_ok();
+        self.current = Some((id, request));
+
+        Vec::new()
+    }
+
+    fn is_loading(&self) -> bool {
+        self.streaming
+    }
+
+    fn poll_completions(&mut self) -> bool {
+        self.receive();
+        std::mem::take(&mut self.ready)
+    }
+
+    fn take_new_suggestions(&mut self) -> Option<Vec<Suggestion>> {
+        let new = self.suggestions[self.handed_over..].to_vec();
+        self.handed_over = self.suggestions.len();
+        Some(new)
+    }
+}
+
+impl Drop for IncrementalCompleter {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Span;
+    use pretty_assertions::assert_eq;
+    use std::time::{Duration, Instant};
+
+    // Sends a suggestion for every signal of the gate, up to two
+    struct GatedCompleter {
+        gate: Receiver<()>,
+        stopped: Arc<AtomicBool>,
+    }
+
+    impl StreamingCompleter for GatedCompleter {
+        fn complete_stream(&mut self, line: &str, pos: usize, sender: &SuggestionSender) {
+            for n in 0..2 {
+                if self.gate.recv().is_err() {
+                    return;
+                }
+                let suggestion = Suggestion::new(format!("{line}{n}"), Span::new(0, pos));
+                if !sender.send(vec![suggestion]) {
+                    self.stopped.store(true, Ordering::Relaxed);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn gated_completer() -> (IncrementalCompleter, Sender<()>, Arc<AtomicBool>) {
+        let (gate, gate_receiver) = channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let completer = GatedCompleter {
+            gate: gate_receiver,
+            stopped: stopped.clone(),
+        };
+        (
+            IncrementalCompleter::new(Box::new(completer)),
+            gate,
+            stopped,
+        )
+    }
+
+    fn wait_for_batch(completer: &mut IncrementalCompleter) {
+        let start = Instant::now();
+        while !completer.poll_completions() {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "suggestions never arrived"
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn values(suggestions: &[Suggestion]) -> Vec<&str> {
+        suggestions.iter().map(|s| s.value.as_str()).collect()
+    }
+
+    #[test]
+    fn batches_are_appended_as_they_arrive() {
+        let (mut completer, gate, _) = gated_completer();
+
+        assert!(completer.complete("git", 3).is_empty());
+        assert!(completer.is_loading());
+
+        gate.send(()).unwrap();
+        wait_for_batch(&mut completer);
+        assert_eq!(values(&completer.complete("git", 3)), vec!["git0"]);
+        assert!(completer.is_loading());
+
+        gate.send(()).unwrap();
+        let start = Instant::now();
+        while completer.is_loading() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            wait_for_batch(&mut completer);
+        }
+        assert_eq!(values(&completer.complete("git", 3)), vec!["git0", "git1"]);
+    }
+
+    #[test]
+    fn only_new_suggestions_are_handed_over() {
+        let (mut completer, gate, _) = gated_completer();
+
+        completer.complete("git", 3);
+        gate.send(()).unwrap();
+        wait_for_batch(&mut completer);
+        assert_eq!(
+            values(&completer.take_new_suggestions().unwrap()),
+            vec!["git0"]
+        );
+
+        gate.send(()).unwrap();
+        wait_for_batch(&mut completer);
+        assert_eq!(
+            values(&completer.take_new_suggestions().unwrap()),
+            vec!["git1"]
+        );
+        assert!(completer.take_new_suggestions().unwrap().is_empty());
+    }
+
+    struct PanickingCompleter;
+
+    impl StreamingCompleter for PanickingCompleter {
+        fn complete_stream(&mut self, _line: &str, _pos: usize, _sender: &SuggestionSender) {
+            panic!("the completer failed");
+        }
+    }
+
+    #[test]
+    fn panicking_completer_stops_loading() {
+        let mut completer = IncrementalCompleter::new(Box::new(PanickingCompleter));
+
+        assert!(completer.complete("git", 3).is_empty());
+        assert!(completer.is_loading());
+
+        wait_for_batch(&mut completer);
+        assert!(!completer.is_loading());
+        assert!(completer.complete("git", 3).is_empty());
+    }
+
+    #[test]
+    fn new_line_cancels_the_stream() {
+        let (mut completer, gate, stopped) = gated_completer();
+
+        completer.complete("g", 1);
+        gate.send(()).unwrap();
+        wait_for_batch(&mut completer);
+        assert_eq!(values(&completer.complete("g", 1)), vec!["g0"]);
+
+        assert!(completer.complete("gi", 2).is_empty());
+        // The stream of `g` stops at its next batch, the stream of `gi` follows
+        gate.send(()).unwrap();
+        gate.send(()).unwrap();
+        wait_for_batch(&mut completer);
+
+        assert!(stopped.load(Ordering::Relaxed));
+        assert_eq!(values(&completer.complete("gi", 2)), vec!["gi0"]);
+    }
+}
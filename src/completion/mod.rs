@@ -1,6 +1,14 @@
+mod background;
 mod base;
 mod default;
+#[cfg(feature = "file_completer")]
+mod file;
 pub(crate) mod history;
+mod incremental;
 
-pub use base::{Completer, Span, Suggestion};
+pub use background::BackgroundCompleter;
+pub use base::{Append, Completer, Snippet, Span, Suggestion};
 pub use default::DefaultCompleter;
+#[cfg(feature = "file_completer")]
+pub use file::FileCompleter;
+pub use incremental::{IncrementalCompleter, StreamingCompleter, SuggestionSender};
@@ -1,17 +1,40 @@
+use std::io;
+
 /// Defines an interface to interact with a Clipboard for cut and paste.
 ///
 /// Mutable reference requirements are stricter than always necessary, but the currently used system clipboard API demands them for exclusive access.
+///
+/// Implementations can be set with [`crate::Reedline::with_clipboard`], e.g. to copy over SSH
+/// using OSC 52. Failures of their backend are returned as errors, the editor then
+/// silently falls back to its internal cut buffer.
 pub trait Clipboard: Send {
-    fn set(&mut self, content: &str, mode: ClipboardMode);
+    /// Store cut or copied content
+    fn set(&mut self, content: &str, mode: ClipboardMode) -> io::Result<()>;
 
-    fn get(&mut self) -> (String, ClipboardMode);
+    /// Get the content to paste
+    fn get(&mut self) -> io::Result<(String, ClipboardMode)>;
 
-    fn clear(&mut self) {
-        self.set("", ClipboardMode::Normal);
+    /// Empty the clipboard
+    fn clear(&mut self) -> io::Result<()> {
+        self.set("", ClipboardMode::Normal)
     }
 
+    /// Length of the content in bytes, 0 if it can't be read
     fn len(&mut self) -> usize {
-        self.get().0.len()
+        self.get().map_or(0, |(content, _)| content.len())
+    }
+
+    /// The clipboard has no content or can't be read
+    fn is_empty(&mut self) -> bool {
+        self.len() == 0
+    }
+
+    /// Escape sequences to write to the terminal, e.g. to set its clipboard
+    ///
+    /// The engine takes them after every event and writes them together with the
+    /// rest of its output
+    fn take_terminal_output(&mut self) -> Option<String> {
+        None
     }
 }
 
@@ -33,23 +56,113 @@ pub struct LocalClipboard {
 }
 
 impl LocalClipboard {
-    #[allow(dead_code)]
+    /// Create an empty clipboard
     pub fn new() -> Self {
         Self::default()
     }
 }
 
 impl Clipboard for LocalClipboard {
-    fn set(&mut self, content: &str, mode: ClipboardMode) {
+    fn set(&mut self, content: &str, mode: ClipboardMode) -> io::Result<()> {
         self.content = content.to_owned();
         self.mode = mode;
+        Ok(())
     }
 
-    fn get(&mut self) -> (String, ClipboardMode) {
-        (self.content.clone(), self.mode)
+    fn get(&mut self) -> io::Result<(String, ClipboardMode)> {
+        Ok((self.content.clone(), self.mode))
     }
 }
 
+/// Default limit of the content [`Osc52Clipboard`] sends to the terminal, in bytes
+pub const DEFAULT_OSC52_MAX_SIZE: usize = 100_000;
+
+/// Clipboard that sets the terminal's clipboard with the OSC 52 escape sequence
+///
+/// Works in remote sessions, e.g. over SSH, if the terminal supports OSC 52.
+/// Terminals generally don't allow reading their clipboard, so pasting uses the
+/// content cut or copied within the application, kept by the editor's internal
+/// cut buffer. Content larger than the size limit is only kept there.
+///
+/// The escape sequences are written by the painter of [`Reedline`](crate::Reedline),
+/// to the same output as the prompt and the buffer.
+pub struct Osc52Clipboard {
+    // escape sequences not written to the terminal yet
+    pending: String,
+    max_size: usize,
+}
+
+impl Default for Osc52Clipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Osc52Clipboard {
+    /// Create a clipboard setting the terminal's clipboard
+    pub fn new() -> Self {
+        Self {
+            pending: String::new(),
+            max_size: DEFAULT_OSC52_MAX_SIZE,
+        }
+    }
+
+    /// Builder to limit the size in bytes of the content sent to the terminal
+    #[must_use]
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+}
+
+impl Clipboard for Osc52Clipboard {
+    fn set(&mut self, content: &str, _mode: ClipboardMode) -> io::Result<()> {
+        if content.len() > self.max_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "content exceeds the OSC 52 size limit",
+            ));
+        }
+        self.pending.push_str("\x1b]52;c;");
+        self.pending.push_str(&base64(content.as_bytes()));
+        self.pending.push('\x07');
+        Ok(())
+    }
+
+    fn get(&mut self) -> io::Result<(String, ClipboardMode)> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the terminal clipboard can't be read",
+        ))
+    }
+
+    fn take_terminal_output(&mut self) -> Option<String> {
+        (!self.pending.is_empty()).then(|| std::mem::take(&mut self.pending))
+    }
+}
+
+/// Standard base64 encoding with padding, as expected by OSC 52
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | u32::from(*byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(
+                    ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize],
+                ));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
 #[cfg(feature = "system_clipboard")]
 pub use system_clipboard::SystemClipboard;
 
@@ -80,39 +193,62 @@ mod system_clipboard {
 
     /// Wrapper around [`clipboard`](https://docs.rs/clipboard) crate
     ///
-    /// Requires that the feature `system_clipboard` is enabled
+    /// Requires that the feature `system_clipboard` is enabled.
+    /// If the system clipboard is not available or fails, the editor silently keeps
+    /// the content only within the application.
     pub struct SystemClipboard {
-        cb: ClipboardContext,
+        cb: Option<ClipboardContext>,
         local_copy: String,
         mode: ClipboardMode,
     }
 
+    impl Default for SystemClipboard {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
     impl SystemClipboard {
+        /// Connect to the system clipboard
         pub fn new() -> Self {
-            let cb = ClipboardProvider::new().unwrap();
+            let cb = ClipboardProvider::new().ok();
             SystemClipboard {
                 cb,
                 local_copy: String::new(),
                 mode: ClipboardMode::Normal,
             }
         }
+
+        fn context(&mut self) -> io::Result<&mut ClipboardContext> {
+            self.cb.as_mut().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "the system clipboard isn't available",
+                )
+            })
+        }
     }
 
     impl Clipboard for SystemClipboard {
-        fn set(&mut self, content: &str, mode: ClipboardMode) {
+        fn set(&mut self, content: &str, mode: ClipboardMode) -> io::Result<()> {
             self.local_copy = content.to_owned();
-            let _ = self.cb.set_contents(content.to_owned());
             self.mode = mode;
+            self.context()?
+                .set_contents(content.to_owned())
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))
         }
 
-        fn get(&mut self) -> (String, ClipboardMode) {
-            let system_content = self.cb.get_contents().unwrap_or_default();
+        fn get(&mut self) -> io::Result<(String, ClipboardMode)> {
+            let system_content = self
+                .context()?
+                .get_contents()
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
             if system_content == self.local_copy {
                 // We assume the content was yanked inside the line editor and the last yank determined the mode.
-                (system_content, self.mode)
+                Ok((system_content, self.mode))
             } else {
                 // Content has changed, default to direct insertion.
-                (system_content, ClipboardMode::Normal)
+                Ok((system_content, ClipboardMode::Normal))
             }
         }
     }
@@ -120,22 +256,53 @@ mod system_clipboard {
 
 #[cfg(test)]
 mod tests {
-    use super::{get_default_clipboard, Clipboard, ClipboardMode};
+    use super::{base64, get_default_clipboard, Clipboard, ClipboardMode, Osc52Clipboard};
+    use rstest::rstest;
+
     #[test]
     fn reads_back() {
         let mut cb = get_default_clipboard();
         // If the system clipboard is used we want to persist it for the user
-        let previous_state = cb.get().0;
+        let previous_state = match cb.get() {
+            Ok((previous_state, _)) => previous_state,
+            // Without a system clipboard the editor falls back to its cut buffer
+            Err(_) => return,
+        };
 
         // Actual test
-        cb.set("test", ClipboardMode::Normal);
+        cb.set("test", ClipboardMode::Normal).unwrap();
         assert_eq!(cb.len(), 4);
-        assert_eq!(cb.get().0, "test".to_owned());
-        cb.clear();
-        assert_eq!(cb.get().0, String::new());
+        assert_eq!(cb.get().unwrap().0, "test".to_owned());
+        cb.clear().unwrap();
+        assert!(cb.is_empty());
 
         // Restore!
 
-        cb.set(&previous_state, ClipboardMode::Normal);
+        let _ = cb.set(&previous_state, ClipboardMode::Normal);
+    }
+
+    #[rstest]
+    #[case("", "")]
+    #[case("f", "Zg==")]
+    #[case("fo", "Zm8=")]
+    #[case("foo", "Zm9v")]
+    #[case("foobar", "Zm9vYmFy")]
+    #[case("ü€", "w7zigqw=")]
+    fn base64_encodes(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(base64(input.as_bytes()), expected);
+    }
+
+    #[test]
+    fn osc52_sets_the_terminal_clipboard() {
+        let mut cb = Osc52Clipboard::new().with_max_size(5);
+
+        assert!(cb.set("foo", ClipboardMode::Lines).is_ok());
+        assert!(cb.set("too long", ClipboardMode::Normal).is_err());
+        assert!(cb.get().is_err());
+        assert_eq!(
+            cb.take_terminal_output().as_deref(),
+            Some("\x1b]52;c;Zm9v\x07")
+        );
+        assert_eq!(cb.take_terminal_output(), None);
     }
 }
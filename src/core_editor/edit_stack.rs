@@ -2,6 +2,10 @@
 pub struct EditStack<T> {
     internal_list: Vec<T>,
     index: usize,
+    // maximum number of entries kept before the current one
+    capacity: usize,
+    // number of the oldest entries dropped to stay within the capacity
+    evicted: usize,
 }
 
 impl<T> EditStack<T> {
@@ -12,6 +16,8 @@ impl<T> EditStack<T> {
         EditStack {
             internal_list: vec![T::default()],
             index: 0,
+            capacity: usize::MAX,
+            evicted: 0,
         }
     }
 }
@@ -47,12 +53,58 @@ where
         }
         self.internal_list.push(value);
         self.index += 1;
+        self.evict();
+    }
+
+    /// Limit the number of undo steps, evicting the oldest entries when it is exceeded
+    ///
+    /// A capacity of 0 keeps only the current entry, so undo and redo do nothing.
+    pub(super) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    /// Drop the oldest entries exceeding the capacity, the entries after the current
+    /// one stay available for redo
+    fn evict(&mut self) {
+        let excess = self.index.saturating_sub(self.capacity);
+        if excess > 0 {
+            self.internal_list.drain(..excess);
+            self.index -= excess;
+            self.evicted += excess;
+        }
     }
 
     /// Reset the stack to the initial state
     pub(super) fn reset(&mut self) {
+        self.reset_to(T::default());
+    }
+
+    /// Reset the stack with the given value as its only entry
+    pub(super) fn reset_to(&mut self, value: T) {
         self.index = 0;
-        self.internal_list = vec![T::default()];
+        self.evicted = 0;
+        self.internal_list = vec![value];
+    }
+
+    /// Index of the entry currently being pointed to
+    ///
+    /// Counts the evicted entries as well, so it stays valid while older entries are evicted.
+    pub(super) fn index(&self) -> usize {
+        self.evicted + self.index
+    }
+
+    /// Merges the entries after `index` into a single entry holding the current value
+    ///
+    /// If the entry at `index` was evicted, all remaining entries after the oldest one are merged.
+    pub(super) fn squash_after(&mut self, index: usize) {
+        let index = index.saturating_sub(self.evicted);
+        if self.index > index + 1 {
+            let current = self.internal_list[self.index].clone();
+            self.internal_list.truncate(index + 1);
+            self.internal_list.push(current);
+            self.index = index + 1;
+        }
     }
 
     /// Return the entry currently being pointed to
@@ -74,6 +126,8 @@ mod test {
         EditStack {
             internal_list: values.to_vec(),
             index,
+            capacity: usize::MAX,
+            evicted: 0,
         }
     }
 
@@ -110,4 +164,72 @@ mod test {
         stack.insert(value_to_insert);
         assert_eq!(stack, expected_stack);
     }
+
+    #[rstest]
+    #[case(edit_stack(&[1, 2, 3, 4][..], 3), 0, edit_stack(&[1, 4], 1))]
+    #[case(edit_stack(&[1, 2, 3, 4][..], 3), 2, edit_stack(&[1, 2, 3, 4], 3))]
+    #[case(edit_stack(&[1, 2, 3, 4][..], 2), 0, edit_stack(&[1, 3], 1))]
+    fn squash_after_works(
+        #[case] old_stack: EditStack<isize>,
+        #[case] index: usize,
+        #[case] expected_stack: EditStack<isize>,
+    ) {
+        let mut stack = old_stack;
+
+        stack.squash_after(index);
+        assert_eq!(stack, expected_stack);
+    }
+
+    #[test]
+    fn insert_evicts_the_oldest_entries() {
+        let mut stack = edit_stack(&[1, 2][..], 1);
+        stack.set_capacity(2);
+
+        stack.insert(3);
+        stack.insert(4);
+        assert_eq!(stack.internal_list, [2, 3, 4]);
+        assert_eq!(stack.index(), 3);
+
+        assert_eq!(*stack.undo(), 3);
+        assert_eq!(*stack.undo(), 2);
+        assert_eq!(*stack.undo(), 2);
+    }
+
+    #[test]
+    fn eviction_keeps_the_redo_branch() {
+        let mut stack = edit_stack(&[1, 2, 3, 4, 5][..], 3);
+
+        stack.set_capacity(1);
+        assert_eq!(stack.internal_list, [3, 4, 5]);
+        assert_eq!(*stack.current(), 4);
+        assert_eq!(*stack.redo(), 5);
+        assert_eq!(*stack.undo(), 4);
+        assert_eq!(*stack.undo(), 3);
+        assert_eq!(*stack.undo(), 3);
+    }
+
+    #[test]
+    fn zero_capacity_disables_undo() {
+        let mut stack = edit_stack(&[1][..], 0);
+        stack.set_capacity(0);
+
+        stack.insert(2);
+        stack.insert(3);
+        assert_eq!(*stack.undo(), 3);
+        assert_eq!(*stack.redo(), 3);
+    }
+
+    #[test]
+    fn squash_after_an_evicted_index_merges_the_remaining_entries() {
+        let mut stack = edit_stack(&[1, 2][..], 1);
+        stack.set_capacity(2);
+        let group_start = stack.index();
+
+        for value in 3..=5 {
+            stack.insert(value);
+        }
+        stack.squash_after(group_start);
+        assert_eq!(stack.internal_list, [3, 5]);
+        assert_eq!(*stack.undo(), 3);
+    }
 }
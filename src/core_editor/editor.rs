@@ -1,6 +1,11 @@
-use super::{edit_stack::EditStack, Clipboard, ClipboardMode, LineBuffer};
-use crate::enums::{EditType, UndoBehavior};
+use super::{
+    edit_stack::EditStack, CharClass, Clipboard, ClipboardMode, KillDirection, KillRing,
+    LineBuffer, Registers,
+};
+use crate::enums::{EditType, TextObject, UndoBehavior};
 use crate::{core_editor::get_default_clipboard, EditCommand};
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
 
 /// Stateful editor executing changes to the underlying [`LineBuffer`]
 ///
@@ -8,19 +13,46 @@ use crate::{core_editor::get_default_clipboard, EditCommand};
 /// the undo/redo history and has facilities for cut/copy/yank/paste
 pub struct Editor {
     line_buffer: LineBuffer,
-    cut_buffer: Box<dyn Clipboard>,
+    cut_buffer: Registers,
+    kill_ring: KillRing,
 
     edit_stack: EditStack<LineBuffer>,
     last_undo_behavior: UndoBehavior,
+    tab_stops: Option<TabStops>,
+    selection: Option<Selection>,
+}
+
+/// Text selected between an anchor and the cursor, e.g. by the vi visual mode
+#[derive(Clone, Copy)]
+struct Selection {
+    /// Position of the cursor when the selection started
+    anchor: usize,
+    /// Whole lines are selected instead of the characters in between
+    linewise: bool,
+    /// The whole buffer is selected wherever the cursor moves
+    all: bool,
+}
+
+/// Placeholders of an inserted snippet the cursor moves through
+struct TabStops {
+    /// Byte ranges of the placeholders in the buffer, in the order they are visited
+    stops: Vec<Range<usize>>,
+    /// Index of the placeholder at the cursor
+    current: usize,
+    /// The text of the current placeholder is replaced by the next typed text
+    selected: bool,
 }
 
 impl Default for Editor {
     fn default() -> Self {
         Editor {
             line_buffer: LineBuffer::new(),
-            cut_buffer: Box::new(get_default_clipboard()),
+            cut_buffer: Registers::new(Box::new(get_default_clipboard())),
+            kill_ring: KillRing::default(),
             edit_stack: EditStack::new(),
             last_undo_behavior: UndoBehavior::CreateUndoPoint,
+            tab_stops: None,
+            selection: None,
         }
     }
 }
@@ -34,16 +66,66 @@ impl Editor {
     /// Set the current [`LineBuffer`].
     /// [`UndoBehavior`] specifies how this change should be reflected on the undo stack.
     pub(crate) fn set_line_buffer(&mut self, line_buffer: LineBuffer, undo_behavior: UndoBehavior) {
-        self.line_buffer = line_buffer;
+        self.replace_line_buffer(line_buffer);
         self.update_undo_state(undo_behavior);
     }
 
-    pub(crate) fn run_edit_command(&mut self, command: &EditCommand) {
+    /// Replace the [`LineBuffer`] while keeping its word predicate
+    fn replace_line_buffer(&mut self, mut line_buffer: LineBuffer) {
+        line_buffer.set_word_char_class(self.line_buffer.word_char_class());
+        self.line_buffer = line_buffer;
+        self.tab_stops = None;
+        self.selection = None;
+    }
+
+    /// Set the predicate classifying characters for the word movements and deletions
+    pub(crate) fn set_word_char_class(&mut self, word_char_class: Option<fn(char) -> CharClass>) {
+        self.line_buffer.set_word_char_class(word_char_class);
+    }
+
+    /// Set the clipboard used by cut, copy and paste
+    pub(crate) fn set_clipboard(&mut self, clipboard: Box<dyn Clipboard>) {
+        self.cut_buffer.set_clipboard(clipboard);
+    }
+
+    /// Escape sequences the clipboard wants written to the terminal
+    pub(crate) fn take_clipboard_output(&mut self) -> Option<String> {
+        self.cut_buffer.take_terminal_output()
+    }
+
+    /// Set the number of kills kept for yank-pop
+    pub(crate) fn set_kill_ring_size(&mut self, size: usize) {
+        self.kill_ring.set_capacity(size);
+    }
+
+    /// Set whether consecutive kills in the same direction are merged in the kill ring
+    /// and the cut buffer, as done by Emacs
+    pub(crate) fn set_kill_coalescing(&mut self, coalesce: bool) {
+        self.kill_ring.set_coalesce(coalesce);
+    }
+
+    /// Set the number of undo steps kept, the oldest are dropped first
+    pub(crate) fn set_undo_stack_size(&mut self, size: usize) {
+        self.edit_stack.set_capacity(size);
+    }
+
+    /// Run an [`EditCommand`] over the buffer, as the edit modes do
+    pub fn run_edit_command(&mut self, command: &EditCommand) {
+        if self.delete_selected_placeholder(command) {
+            self.selection = None;
+            self.update_undo_state(UndoBehavior::CreateUndoPoint);
+            return;
+        }
+        let previous_insertion_point = self.line_buffer.insertion_point();
+        let previous_len = self.line_buffer.get_buffer().len();
         match command {
             EditCommand::MoveToStart => self.line_buffer.move_to_start(),
             EditCommand::MoveToLineStart => self.line_buffer.move_to_line_start(),
             EditCommand::MoveToEnd => self.line_buffer.move_to_end(),
             EditCommand::MoveToLineEnd => self.line_buffer.move_to_line_end(),
+            EditCommand::MoveToMatchingBracket => self.line_buffer.move_to_matching_bracket(),
+            EditCommand::NextTabStop => self.next_tab_stop(),
+            EditCommand::PrevTabStop => self.previous_tab_stop(),
             EditCommand::MoveToPosition(pos) => self.line_buffer.set_insertion_point(*pos),
             EditCommand::MoveLeft => self.line_buffer.move_left(),
             EditCommand::MoveRight => self.line_buffer.move_right(),
@@ -57,6 +139,8 @@ impl Editor {
             EditCommand::InsertChar(c) => self.line_buffer.insert_char(*c),
             EditCommand::Complete => {}
             EditCommand::InsertString(str) => self.line_buffer.insert_str(str),
+            // The generators are registered on the engine which inserts their text
+            EditCommand::InsertGenerated(_) => {}
             EditCommand::InsertNewline => self.line_buffer.insert_newline(),
             EditCommand::ReplaceChar(chr) => self.replace_char(*chr),
             EditCommand::ReplaceChars(n_chars, str) => self.replace_chars(*n_chars, str),
@@ -64,9 +148,14 @@ impl Editor {
             EditCommand::Delete => self.line_buffer.delete_right_grapheme(),
             EditCommand::CutChar => self.cut_char(),
             EditCommand::BackspaceWord => self.line_buffer.delete_word_left(),
+            EditCommand::BackspaceBigWord => self.line_buffer.delete_big_word_left(),
             EditCommand::DeleteWord => self.line_buffer.delete_word_right(),
+            EditCommand::DeleteBigWord => self.line_buffer.delete_big_word_right(),
             EditCommand::Clear => self.line_buffer.clear(),
-            EditCommand::ClearToLineEnd => self.line_buffer.clear_to_line_end(),
+            EditCommand::ClearToLineEnd => self.cut_to_line_end(),
+            EditCommand::ClearToLineStart => self.cut_from_line_start(),
+            EditCommand::ClearToBufferEnd => self.cut_from_end(),
+            EditCommand::ClearToBufferStart => self.cut_from_start(),
             EditCommand::CutCurrentLine => self.cut_current_line(),
             EditCommand::CutFromStart => self.cut_from_start(),
             EditCommand::CutFromLineStart => self.cut_from_line_start(),
@@ -80,11 +169,14 @@ impl Editor {
             EditCommand::CutBigWordRightToNext => self.cut_big_word_right_to_next(),
             EditCommand::PasteCutBufferBefore => self.insert_cut_buffer_before(),
             EditCommand::PasteCutBufferAfter => self.insert_cut_buffer_after(),
+            EditCommand::YankPop => self.yank_pop(),
             EditCommand::UppercaseWord => self.line_buffer.uppercase_word(),
             EditCommand::LowercaseWord => self.line_buffer.lowercase_word(),
             EditCommand::SwitchcaseChar => self.line_buffer.switchcase_char(),
             EditCommand::CapitalizeChar => self.line_buffer.capitalize_char(),
+            EditCommand::CapitalizeWord => self.line_buffer.capitalize_word(),
             EditCommand::SwapWords => self.line_buffer.swap_words(),
+            EditCommand::TransposeWords => self.line_buffer.transpose_words(),
             EditCommand::SwapGraphemes => self.line_buffer.swap_graphemes(),
             EditCommand::Undo => self.undo(),
             EditCommand::Redo => self.redo(),
@@ -96,7 +188,46 @@ impl Editor {
             EditCommand::CutLeftBefore(c) => self.cut_left_until_char(*c, true, true),
             EditCommand::MoveLeftUntil(c) => self.move_left_until_char(*c, false, true),
             EditCommand::MoveLeftBefore(c) => self.move_left_until_char(*c, true, true),
+            EditCommand::CutTextObject(text_object) => self.cut_text_object(*text_object),
+            EditCommand::CopyTextObject(text_object) => self.copy_text_object(*text_object),
+            EditCommand::CopyCurrentLine => self.copy_current_line(),
+            EditCommand::CopyWordRightToNext => {
+                self.copy_right_until(self.line_buffer.word_right_start_index());
+            }
+            EditCommand::CopyBigWordRightToNext => {
+                self.copy_right_until(self.line_buffer.big_word_right_start_index());
+            }
+            EditCommand::CopyToLineEnd => {
+                self.copy_right_until(self.line_buffer.find_current_line_end());
+            }
+            EditCommand::CopyWordLeft => self.copy_left_from(self.line_buffer.word_left_index()),
+            EditCommand::CopyBigWordLeft => {
+                self.copy_left_from(self.line_buffer.big_word_left_index());
+            }
+            EditCommand::CopyFromLineStart => {
+                self.copy_left_from(self.line_buffer.current_line_range().start);
+            }
+            EditCommand::CutToMatchingBracket => self.cut_to_matching_bracket(),
+            EditCommand::CopyToMatchingBracket => self.copy_to_matching_bracket(),
+            EditCommand::StartSelection => self.start_selection(false),
+            EditCommand::StartLineSelection => self.start_selection(true),
+            EditCommand::ClearSelection => self.selection = None,
+            EditCommand::CutSelection => self.cut_selection(false),
+            EditCommand::ChangeSelection => self.cut_selection(true),
+            EditCommand::CopySelection => self.copy_selection(),
+            // Only applies to the next command, the buffer and undo stack stay untouched
+            EditCommand::SelectRegister(register) => {
+                self.cut_buffer.select(*register);
+                return;
+            }
         }
+        self.cut_buffer.clear_selection();
+        // The anchor is only kept while the text stays the same
+        if command.edit_type() != EditType::MoveCursor {
+            self.selection = None;
+        }
+        self.update_kill_ring(command, previous_len);
+        self.update_tab_stops(command, previous_insertion_point, previous_len);
 
         let new_undo_behavior = match (command, command.edit_type()) {
             (_, EditType::MoveCursor) => UndoBehavior::MoveCursor,
@@ -136,6 +267,7 @@ impl Editor {
         F: FnOnce(&mut LineBuffer),
     {
         self.update_undo_state(undo_behavior);
+        self.selection = None;
         func(&mut self.line_buffer);
     }
 
@@ -143,14 +275,18 @@ impl Editor {
     /// Insertion point update to the end of the buffer.
     pub(crate) fn set_buffer(&mut self, buffer: String, undo_behavior: UndoBehavior) {
         self.line_buffer.set_buffer(buffer);
+        self.tab_stops = None;
+        self.selection = None;
         self.update_undo_state(undo_behavior);
     }
 
-    pub(crate) fn insertion_point(&self) -> usize {
+    /// Get the cursor position as a byte offset into the buffer
+    pub fn insertion_point(&self) -> usize {
         self.line_buffer.insertion_point()
     }
 
-    pub(crate) fn is_empty(&self) -> bool {
+    /// Check if the buffer is empty
+    pub fn is_empty(&self) -> bool {
         self.line_buffer.is_empty()
     }
 
@@ -170,6 +306,22 @@ impl Editor {
         self.edit_stack.reset();
     }
 
+    /// Sets the buffer as the only entry of the undo stack
+    pub(crate) fn set_buffer_as_baseline(&mut self, buffer: String) {
+        self.line_buffer.set_buffer(buffer);
+        self.tab_stops = None;
+        self.selection = None;
+        self.edit_stack.reset_to(self.line_buffer.clone());
+        self.last_undo_behavior = UndoBehavior::CreateUndoPoint;
+    }
+
+    /// Sets the [`LineBuffer`] including its cursor as the only entry of the undo stack
+    pub(crate) fn set_line_buffer_as_baseline(&mut self, line_buffer: LineBuffer) {
+        self.replace_line_buffer(line_buffer);
+        self.edit_stack.reset_to(self.line_buffer.clone());
+        self.last_undo_behavior = UndoBehavior::CreateUndoPoint;
+    }
+
     pub(crate) fn move_to_start(&mut self, undo_behavior: UndoBehavior) {
         self.line_buffer.move_to_start();
         self.update_undo_state(undo_behavior);
@@ -191,14 +343,244 @@ impl Editor {
         self.update_undo_state(undo_behavior);
     }
 
+    /// Starts a group of edits that are undone at once, see [`Editor::end_undo_group`]
+    pub(crate) fn start_undo_group(&mut self) -> usize {
+        // The first edit of the group must not be merged with the previous edits
+        self.last_undo_behavior = UndoBehavior::CreateUndoPoint;
+        self.edit_stack.index()
+    }
+
+    /// Merges the undo points created since the group was started
+    pub(crate) fn end_undo_group(&mut self, group_start: usize) {
+        self.edit_stack.squash_after(group_start);
+        self.last_undo_behavior = UndoBehavior::CreateUndoPoint;
+    }
+
+    /// Sets the placeholders the cursor moves through with
+    /// [`EditCommand::NextTabStop`] and [`EditCommand::PrevTabStop`], and moves the
+    /// cursor to the first one
+    pub(crate) fn set_tab_stops(&mut self, stops: Vec<Range<usize>>) {
+        let len = self.line_buffer.len();
+        let stops: Vec<_> = stops
+            .into_iter()
+            .filter(|stop| stop.start <= stop.end && stop.end <= len)
+            .collect();
+        self.tab_stops = None;
+        if !stops.is_empty() {
+            self.tab_stops = Some(TabStops {
+                stops,
+                current: 0,
+                selected: false,
+            });
+            self.select_tab_stop(0);
+        }
+    }
+
+    /// There are placeholders left to move to
+    pub(crate) fn has_tab_stops(&self) -> bool {
+        self.tab_stops.is_some()
+    }
+
+    /// Byte range of the placeholder whose text is replaced by the next typed text
+    pub(crate) fn selected_placeholder(&self) -> Option<Range<usize>> {
+        self.tab_stops
+            .as_ref()
+            .filter(|tab_stops| tab_stops.selected)
+            .map(|tab_stops| tab_stops.stops[tab_stops.current].clone())
+    }
+
+    /// Moves the cursor to the start of the placeholder and selects its text
+    fn select_tab_stop(&mut self, index: usize) {
+        if let Some(tab_stops) = self.tab_stops.as_mut() {
+            let stop = tab_stops.stops[index].clone();
+            tab_stops.current = index;
+            tab_stops.selected = !stop.is_empty();
+            self.line_buffer.set_insertion_point(stop.start);
+        }
+    }
+
+    /// Moves to the next placeholder, the placeholders are done after the last one
+    fn next_tab_stop(&mut self) {
+        if let Some(tab_stops) = &self.tab_stops {
+            let next = tab_stops.current + 1;
+            if next < tab_stops.stops.len() {
+                self.select_tab_stop(next);
+            } else {
+                self.tab_stops = None;
+            }
+        }
+    }
+
+    fn previous_tab_stop(&mut self) {
+        if let Some(tab_stops) = &self.tab_stops {
+            self.select_tab_stop(tab_stops.current.saturating_sub(1));
+        }
+    }
+
+    /// Removes the selected placeholder text when it is overwritten by typing or deleted.
+    /// Returns true if the command was a deletion, which is done by the removal
+    fn delete_selected_placeholder(&mut self, command: &EditCommand) -> bool {
+        let is_deletion = match command {
+            EditCommand::Backspace | EditCommand::Delete => true,
+            EditCommand::InsertChar(_) | EditCommand::InsertString(_) => false,
+            _ => return false,
+        };
+        let stop = match &self.tab_stops {
+            Some(tab_stops) if tab_stops.selected => tab_stops.stops[tab_stops.current].clone(),
+            _ => return false,
+        };
+        if self.line_buffer.insertion_point() != stop.start {
+            return false;
+        }
+
+        self.line_buffer.replace_range(stop.clone(), "");
+        self.line_buffer.set_insertion_point(stop.start);
+        self.shift_tab_stops(stop.start, -(stop.len() as isize));
+        is_deletion
+    }
+
+    /// Keeps the placeholders on their text while the buffer is edited
+    fn update_tab_stops(
+        &mut self,
+        command: &EditCommand,
+        previous_insertion_point: usize,
+        previous_len: usize,
+    ) {
+        let tab_stops = match self.tab_stops.as_mut() {
+            Some(tab_stops) => tab_stops,
+            None => return,
+        };
+        match command {
+            EditCommand::NextTabStop | EditCommand::PrevTabStop => return,
+            _ => tab_stops.selected = false,
+        }
+
+        let delta = self.line_buffer.len() as isize - previous_len as isize;
+        if delta != 0 {
+            // The text changed at the cursor, before or after its move
+            let position = previous_insertion_point.min(self.line_buffer.insertion_point());
+            self.shift_tab_stops(position, delta);
+        }
+    }
+
+    /// Moves the placeholders after `position` by `delta` bytes and resizes the one
+    /// containing it
+    fn shift_tab_stops(&mut self, position: usize, delta: isize) {
+        let shift = |offset: usize| (offset as isize + delta).max(position as isize) as usize;
+        if let Some(tab_stops) = self.tab_stops.as_mut() {
+            for stop in &mut tab_stops.stops {
+                if stop.start > position {
+                    *stop = shift(stop.start)..shift(stop.end);
+                } else if stop.end >= position {
+                    stop.end = shift(stop.end);
+                }
+            }
+        }
+    }
+
+    /// Starts a selection at the cursor, an active selection keeps its anchor
+    fn start_selection(&mut self, linewise: bool) {
+        let anchor = self.selection.map_or_else(
+            || self.line_buffer.insertion_point(),
+            |selection| selection.anchor,
+        );
+        self.selection = Some(Selection {
+            anchor,
+            linewise,
+            all: false,
+        });
+    }
+
+    /// Selects the whole buffer without moving the cursor
+    pub(crate) fn select_all(&mut self) {
+        self.selection = Some(Selection {
+            anchor: 0,
+            linewise: false,
+            all: true,
+        });
+    }
+
+    /// Byte range of the selected text, the text under the cursor included
+    ///
+    /// A linewise selection spans the whole lines of the anchor and the cursor,
+    /// including the final newline.
+    pub(crate) fn selection_range(&self) -> Option<Range<usize>> {
+        let selection = self.selection?;
+        let buffer = self.line_buffer.get_buffer();
+        if selection.all {
+            return Some(0..buffer.len());
+        }
+        let cursor = self.line_buffer.insertion_point();
+        let start = selection.anchor.min(cursor).min(buffer.len());
+        let end = selection.anchor.max(cursor).min(buffer.len());
+
+        if selection.linewise {
+            let start = buffer[..start].rfind('\n').map_or(0, |offset| offset + 1);
+            let end = buffer[end..]
+                .find('\n')
+                .map_or(buffer.len(), |offset| end + offset + 1);
+            Some(start..end)
+        } else {
+            let end = buffer[end..]
+                .graphemes(true)
+                .next()
+                .map_or(end, |grapheme| end + grapheme.len());
+            Some(start..end)
+        }
+    }
+
+    /// Ends the selection, returning its range and how it is pasted
+    fn take_selection(&mut self) -> Option<(Range<usize>, ClipboardMode)> {
+        let range = self.selection_range()?;
+        let mode = match self.selection.take() {
+            Some(Selection { linewise: true, .. }) => ClipboardMode::Lines,
+            _ => ClipboardMode::Normal,
+        };
+        Some((range, mode))
+    }
+
+    /// Cuts the selection, a linewise one either leaves an empty line behind (`change`)
+    /// or takes the line break before the last line of the buffer with it
+    fn cut_selection(&mut self, change: bool) {
+        if let Some((range, mode)) = self.take_selection() {
+            let buffer = self.line_buffer.get_buffer();
+            self.cut_buffer.set(&buffer[range.clone()], mode);
+
+            let linewise = matches!(mode, ClipboardMode::Lines);
+            let mut deletion_range = range;
+            if linewise {
+                let ends_with_newline = buffer[deletion_range.clone()].ends_with('\n');
+                if change && ends_with_newline {
+                    deletion_range.end -= 1;
+                } else if !change && !ends_with_newline && deletion_range.start > 0 {
+                    deletion_range.start -= 1;
+                }
+            }
+            self.line_buffer.clear_range(deletion_range.clone());
+            self.line_buffer.set_insertion_point(deletion_range.start);
+            if linewise && !change {
+                // Like `dd` the cursor stays at the start of a line
+                self.line_buffer.move_to_line_start();
+            }
+        }
+    }
+
+    fn copy_selection(&mut self) {
+        if let Some((range, mode)) = self.take_selection() {
+            self.cut_buffer
+                .copy(&self.line_buffer.get_buffer()[range.clone()], mode);
+            self.line_buffer.set_insertion_point(range.start);
+        }
+    }
+
     fn undo(&mut self) {
-        let val = self.edit_stack.undo();
-        self.line_buffer = val.clone();
+        let val = self.edit_stack.undo().clone();
+        self.replace_line_buffer(val);
     }
 
     fn redo(&mut self) {
-        let val = self.edit_stack.redo();
-        self.line_buffer = val.clone();
+        let val = self.edit_stack.redo().clone();
+        self.replace_line_buffer(val);
     }
 
     fn update_undo_state(&mut self, undo_behavior: UndoBehavior) {
@@ -213,6 +595,36 @@ impl Editor {
         self.last_undo_behavior = undo_behavior;
     }
 
+    /// Tracks kills and yanks in the kill ring, any other command ends a chain of them
+    fn update_kill_ring(&mut self, command: &EditCommand, previous_len: usize) {
+        if let Some(killed) = self.cut_buffer.take_killed() {
+            if let Some(newest) = self.kill_ring.kill(&killed, kill_direction(command)) {
+                if newest != killed {
+                    self.cut_buffer.replace_killed(newest);
+                }
+            }
+        } else if matches!(
+            command,
+            EditCommand::PasteCutBufferBefore | EditCommand::PasteCutBufferAfter
+        ) {
+            let end = self.line_buffer.insertion_point();
+            let start = end.saturating_sub(self.line_buffer.get_buffer().len() - previous_len);
+            if let Some(yanked) = self.line_buffer.get_buffer().get(start..end) {
+                self.kill_ring.yanked(start..end, yanked);
+            }
+        } else if !matches!(command, EditCommand::YankPop) {
+            self.kill_ring.break_chain();
+        }
+    }
+
+    fn yank_pop(&mut self) {
+        if let Some((range, text)) = self.kill_ring.yank_pop(self.line_buffer.get_buffer()) {
+            self.line_buffer.clear_range(range.clone());
+            self.line_buffer.set_insertion_point(range.start);
+            self.line_buffer.insert_str(text);
+        }
+    }
+
     fn cut_current_line(&mut self) {
         let deletion_range = self.line_buffer.current_line_range();
 
@@ -224,6 +636,38 @@ impl Editor {
         }
     }
 
+    fn copy_current_line(&mut self) {
+        let range = self.line_buffer.current_line_range();
+
+        let copy_slice = &self.line_buffer.get_buffer()[range];
+        if !copy_slice.is_empty() {
+            self.cut_buffer.copy(copy_slice, ClipboardMode::Lines);
+        }
+    }
+
+    /// Copies from the cursor up to `right_index`, the cursor stays
+    fn copy_right_until(&mut self, right_index: usize) {
+        let insertion_offset = self.line_buffer.insertion_point();
+        if right_index > insertion_offset {
+            self.cut_buffer.copy(
+                &self.line_buffer.get_buffer()[insertion_offset..right_index],
+                ClipboardMode::Normal,
+            );
+        }
+    }
+
+    /// Copies from `left_index` up to the cursor, the cursor moves to `left_index`
+    fn copy_left_from(&mut self, left_index: usize) {
+        let insertion_offset = self.line_buffer.insertion_point();
+        if left_index < insertion_offset {
+            self.cut_buffer.copy(
+                &self.line_buffer.get_buffer()[left_index..insertion_offset],
+                ClipboardMode::Normal,
+            );
+            self.line_buffer.set_insertion_point(left_index);
+        }
+    }
+
     fn cut_from_start(&mut self) {
         let insertion_offset = self.line_buffer.insertion_point();
         if insertion_offset > 0 {
@@ -410,12 +854,11 @@ impl Editor {
     }
 
     fn cut_right_until_char(&mut self, c: char, before_char: bool, current_line: bool) {
-        if let Some(index) = self.line_buffer.find_char_right(c, current_line) {
+        if let Some(range) = self.line_buffer.find_grapheme_right(c, current_line) {
             // Saving the section of the string that will be deleted to be
             // stored into the buffer
-            let extra = if before_char { 0 } else { c.len_utf8() };
-            let cut_slice =
-                &self.line_buffer.get_buffer()[self.line_buffer.insertion_point()..index + extra];
+            let end = if before_char { range.start } else { range.end };
+            let cut_slice = &self.line_buffer.get_buffer()[self.line_buffer.insertion_point()..end];
 
             if !cut_slice.is_empty() {
                 self.cut_buffer.set(cut_slice, ClipboardMode::Normal);
@@ -430,12 +873,12 @@ impl Editor {
     }
 
     fn cut_left_until_char(&mut self, c: char, before_char: bool, current_line: bool) {
-        if let Some(index) = self.line_buffer.find_char_left(c, current_line) {
+        if let Some(range) = self.line_buffer.find_grapheme_left(c, current_line) {
             // Saving the section of the string that will be deleted to be
             // stored into the buffer
-            let extra = if before_char { c.len_utf8() } else { 0 };
+            let start = if before_char { range.end } else { range.start };
             let cut_slice =
-                &self.line_buffer.get_buffer()[index + extra..self.line_buffer.insertion_point()];
+                &self.line_buffer.get_buffer()[start..self.line_buffer.insertion_point()];
 
             if !cut_slice.is_empty() {
                 self.cut_buffer.set(cut_slice, ClipboardMode::Normal);
@@ -449,6 +892,49 @@ impl Editor {
         }
     }
 
+    fn cut_text_object(&mut self, text_object: TextObject) {
+        if let Some(range) = self.line_buffer.text_object_range(text_object) {
+            self.cut_buffer.set(
+                &self.line_buffer.get_buffer()[range.clone()],
+                ClipboardMode::Normal,
+            );
+            self.line_buffer.clear_range(range.clone());
+            // An empty inner text object still moves the cursor inside, like `ci(` on `()`
+            self.line_buffer.set_insertion_point(range.start);
+        }
+    }
+
+    fn copy_text_object(&mut self, text_object: TextObject) {
+        if let Some(range) = self.line_buffer.text_object_range(text_object) {
+            self.cut_buffer.copy(
+                &self.line_buffer.get_buffer()[range.clone()],
+                ClipboardMode::Normal,
+            );
+            self.line_buffer.set_insertion_point(range.start);
+        }
+    }
+
+    fn cut_to_matching_bracket(&mut self) {
+        if let Some(range) = self.line_buffer.matching_bracket_range() {
+            self.cut_buffer.set(
+                &self.line_buffer.get_buffer()[range.clone()],
+                ClipboardMode::Normal,
+            );
+            self.line_buffer.clear_range(range.clone());
+            self.line_buffer.set_insertion_point(range.start);
+        }
+    }
+
+    fn copy_to_matching_bracket(&mut self) {
+        if let Some(range) = self.line_buffer.matching_bracket_range() {
+            self.cut_buffer.copy(
+                &self.line_buffer.get_buffer()[range.clone()],
+                ClipboardMode::Normal,
+            );
+            self.line_buffer.set_insertion_point(range.start);
+        }
+    }
+
     fn replace_char(&mut self, character: char) {
         self.line_buffer.delete_right_grapheme();
 
@@ -464,6 +950,20 @@ impl Editor {
     }
 }
 
+fn kill_direction(command: &EditCommand) -> KillDirection {
+    match command {
+        EditCommand::CutWordLeft
+        | EditCommand::CutBigWordLeft
+        | EditCommand::CutFromStart
+        | EditCommand::CutFromLineStart
+        | EditCommand::ClearToLineStart
+        | EditCommand::ClearToBufferStart
+        | EditCommand::CutLeftUntil(_)
+        | EditCommand::CutLeftBefore(_) => KillDirection::Backward,
+        _ => KillDirection::Forward,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -532,6 +1032,108 @@ mod test {
         assert_eq!(editor.get_buffer(), expected);
     }
 
+    #[rstest]
+    #[case(EditCommand::ClearToLineEnd, "one\ntwo \nfour", "three")]
+    #[case(EditCommand::ClearToLineStart, "one\nthree\nfour", "two ")]
+    #[case(EditCommand::ClearToBufferEnd, "one\ntwo ", "three\nfour")]
+    #[case(EditCommand::ClearToBufferStart, "three\nfour", "one\ntwo ")]
+    fn test_clear_to_line_and_buffer_boundaries(
+        #[case] command: EditCommand,
+        #[case] expected: &str,
+        #[case] expected_cut: &str,
+    ) {
+        let mut editor = editor_with("one\ntwo three\nfour");
+        editor.line_buffer.set_insertion_point(8);
+
+        editor.run_edit_command(&command);
+
+        assert_eq!(editor.get_buffer(), expected);
+        assert_eq!(editor.cut_buffer.get().0, expected_cut);
+    }
+
+    #[rstest]
+    #[case(EditCommand::CutToMatchingBracket, 5, "echo  d", 5, "(a (b) c)")]
+    #[case(EditCommand::CutToMatchingBracket, 13, "echo  d", 5, "(a (b) c)")]
+    #[case(EditCommand::CutToMatchingBracket, 2, "ec d", 2, "ho (a (b) c)")]
+    #[case(EditCommand::CutToMatchingBracket, 15, "echo (a (b) c) d", 15, "")]
+    #[case(
+        EditCommand::CopyToMatchingBracket,
+        13,
+        "echo (a (b) c) d",
+        5,
+        "(a (b) c)"
+    )]
+    fn cut_and_copy_to_matching_bracket(
+        #[case] command: EditCommand,
+        #[case] position: usize,
+        #[case] expected: &str,
+        #[case] expected_position: usize,
+        #[case] expected_cut: &str,
+    ) {
+        let mut editor = editor_with("echo (a (b) c) d");
+        editor.line_buffer.set_insertion_point(position);
+
+        editor.run_edit_command(&command);
+
+        assert_eq!(editor.get_buffer(), expected);
+        assert_eq!(editor.insertion_point(), expected_position);
+        assert_eq!(editor.cut_buffer.get().0, expected_cut);
+    }
+
+    #[rstest]
+    #[case(EditCommand::CopyWordRightToNext, 0, "echo ")]
+    #[case(EditCommand::CopyWordRightToNext, 5, "foo ")]
+    #[case(EditCommand::CopyBigWordRightToNext, 9, "bar.baz ")]
+    #[case(EditCommand::CopyToLineEnd, 5, "foo bar.baz qux")]
+    #[case(EditCommand::CopyToLineEnd, 20, "")]
+    fn copy_right_keeps_the_buffer_and_cursor(
+        #[case] command: EditCommand,
+        #[case] position: usize,
+        #[case] expected_cut: &str,
+    ) {
+        let mut editor = editor_with("echo foo bar.baz qux\nnext");
+        editor.line_buffer.set_insertion_point(position);
+
+        editor.run_edit_command(&command);
+
+        assert_eq!(editor.get_buffer(), "echo foo bar.baz qux\nnext");
+        assert_eq!(editor.insertion_point(), position);
+        assert_eq!(editor.cut_buffer.get().0, expected_cut);
+    }
+
+    #[rstest]
+    #[case(EditCommand::CopyWordLeft, 8, "foo", 5)]
+    #[case(EditCommand::CopyWordLeft, 9, "foo ", 5)]
+    #[case(EditCommand::CopyBigWordLeft, 16, "bar.baz", 9)]
+    #[case(EditCommand::CopyFromLineStart, 8, "echo foo", 0)]
+    #[case(EditCommand::CopyFromLineStart, 24, "nex", 21)]
+    #[case(EditCommand::CopyFromLineStart, 21, "", 21)]
+    fn copy_left_keeps_the_buffer_and_moves_to_the_start(
+        #[case] command: EditCommand,
+        #[case] position: usize,
+        #[case] expected_cut: &str,
+        #[case] expected_position: usize,
+    ) {
+        let mut editor = editor_with("echo foo bar.baz qux\nnext");
+        editor.line_buffer.set_insertion_point(position);
+
+        editor.run_edit_command(&command);
+
+        assert_eq!(editor.get_buffer(), "echo foo bar.baz qux\nnext");
+        assert_eq!(editor.insertion_point(), expected_position);
+        assert_eq!(editor.cut_buffer.get().0, expected_cut);
+    }
+
+    #[test]
+    fn test_clear_to_line_end_keeps_crlf() {
+        let mut editor = editor_with("one\r\ntwo\r\nthree");
+        editor.line_buffer.set_insertion_point(6);
+
+        editor.run_edit_command(&EditCommand::ClearToLineEnd);
+
+        assert_eq!(editor.get_buffer(), "one\r\nt\r\nthree");
+    }
+
     #[rstest]
     #[case("abc", 1, 'X', "aXc")]
     #[case("abc", 1, '🔄', "a🔄c")]
@@ -568,6 +1170,66 @@ mod test {
         assert_eq!(editor.get_buffer(), "This is  a test");
     }
 
+    #[rstest]
+    #[case::cursor_move(EditCommand::MoveLeft, "foo", "")]
+    #[case::delete(EditCommand::Backspace, "fo", "foo")]
+    #[case::whitespace(EditCommand::InsertChar(' '), "foo", "")]
+    fn test_undo_insert_stops_at_boundary(
+        #[case] boundary: EditCommand,
+        #[case] after_first_undo: &str,
+        #[case] after_second_undo: &str,
+    ) {
+        let mut editor = editor_with("");
+        for cmd in str_to_edit_commands("foo") {
+            editor.run_edit_command(&cmd);
+        }
+        editor.run_edit_command(&boundary);
+        for cmd in str_to_edit_commands("bar") {
+            editor.run_edit_command(&cmd);
+        }
+
+        editor.run_edit_command(&EditCommand::Undo);
+        assert_eq!(editor.get_buffer(), after_first_undo);
+        editor.run_edit_command(&EditCommand::Undo);
+        assert_eq!(editor.get_buffer(), after_second_undo);
+    }
+
+    #[test]
+    fn test_undo_completion_is_a_separate_step() {
+        let mut editor = editor_with("");
+        for cmd in str_to_edit_commands("ech") {
+            editor.run_edit_command(&cmd);
+        }
+        let mut completed = editor.line_buffer().clone();
+        completed.set_buffer("echo".to_string());
+        editor.set_line_buffer(completed, UndoBehavior::CreateUndoPoint);
+        for cmd in str_to_edit_commands("es") {
+            editor.run_edit_command(&cmd);
+        }
+
+        editor.run_edit_command(&EditCommand::Undo);
+        assert_eq!(editor.get_buffer(), "echo");
+        editor.run_edit_command(&EditCommand::Undo);
+        assert_eq!(editor.get_buffer(), "ech");
+        editor.run_edit_command(&EditCommand::Undo);
+        assert_eq!(editor.get_buffer(), "");
+    }
+
+    #[test]
+    fn test_word_char_class_survives_undo() {
+        let mut editor = editor_with("");
+        editor.set_word_char_class(Some(|c| match c {
+            '-' => CharClass::Word,
+            c => CharClass::of(c),
+        }));
+        editor.run_edit_command(&EditCommand::InsertString("rm -rf".into()));
+        editor.run_edit_command(&EditCommand::Clear);
+        editor.run_edit_command(&EditCommand::Undo);
+
+        editor.run_edit_command(&EditCommand::BackspaceWord);
+        assert_eq!(editor.get_buffer(), "rm ");
+    }
+
     #[test]
     fn test_undo_backspace_works_on_word_boundaries() {
         let mut editor = editor_with("This is  a test");
@@ -671,4 +1333,263 @@ mod test {
         editor.run_edit_command(&EditCommand::Undo);
         assert_eq!(editor.get_buffer(), "This \r\n is a test");
     }
+
+    #[test]
+    fn test_paste_from_selected_register() {
+        let mut editor = editor_with("abc");
+        editor.line_buffer.set_insertion_point(0);
+
+        editor.run_edit_command(&EditCommand::SelectRegister('a'));
+        editor.run_edit_command(&EditCommand::CutChar);
+        editor.run_edit_command(&EditCommand::CutChar);
+        assert_eq!(editor.get_buffer(), "c");
+
+        editor.run_edit_command(&EditCommand::SelectRegister('a'));
+        editor.run_edit_command(&EditCommand::PasteCutBufferBefore);
+        assert_eq!(editor.get_buffer(), "ac");
+        editor.run_edit_command(&EditCommand::PasteCutBufferBefore);
+        assert_eq!(editor.get_buffer(), "abc");
+    }
+
+    #[test]
+    fn test_consecutive_kills_are_pasted_together() {
+        let mut editor = editor_with("foo bar baz");
+
+        editor.run_edit_command(&EditCommand::CutWordLeft);
+        editor.run_edit_command(&EditCommand::CutWordLeft);
+        assert_eq!(editor.get_buffer(), "foo ");
+
+        editor.run_edit_command(&EditCommand::PasteCutBufferBefore);
+        assert_eq!(editor.get_buffer(), "foo bar baz");
+    }
+
+    #[test]
+    fn test_yank_pop() {
+        let mut editor = editor_with("foo bar baz");
+
+        editor.run_edit_command(&EditCommand::CutWordLeft);
+        editor.run_edit_command(&EditCommand::MoveLeft);
+        editor.run_edit_command(&EditCommand::CutWordLeft);
+        assert_eq!(editor.get_buffer(), "foo  ");
+
+        // Yank-pop only works right after a yank
+        editor.run_edit_command(&EditCommand::YankPop);
+        assert_eq!(editor.get_buffer(), "foo  ");
+
+        editor.run_edit_command(&EditCommand::MoveToEnd);
+        editor.run_edit_command(&EditCommand::PasteCutBufferBefore);
+        assert_eq!(editor.get_buffer(), "foo  bar");
+        editor.run_edit_command(&EditCommand::YankPop);
+        assert_eq!(editor.get_buffer(), "foo  baz");
+        editor.run_edit_command(&EditCommand::YankPop);
+        assert_eq!(editor.get_buffer(), "foo  bar");
+
+        editor.run_edit_command(&EditCommand::InsertChar('!'));
+        editor.run_edit_command(&EditCommand::YankPop);
+        assert_eq!(editor.get_buffer(), "foo  bar!");
+    }
+
+    fn editor_with_tab_stops() -> Editor {
+        // As inserted from the snippet `foo(${1:arg1}, ${2:arg2})$0`
+        let mut editor = editor_with("foo(arg1, arg2)");
+        editor.set_tab_stops(vec![4..8, 10..14, 15..15]);
+        editor
+    }
+
+    #[test]
+    fn tab_stops_are_visited_in_order() {
+        let mut editor = editor_with_tab_stops();
+        assert_eq!(editor.insertion_point(), 4);
+
+        editor.run_edit_command(&EditCommand::NextTabStop);
+        assert_eq!(editor.insertion_point(), 10);
+        editor.run_edit_command(&EditCommand::PrevTabStop);
+        assert_eq!(editor.insertion_point(), 4);
+        editor.run_edit_command(&EditCommand::PrevTabStop);
+        assert_eq!(editor.insertion_point(), 4);
+
+        editor.run_edit_command(&EditCommand::NextTabStop);
+        editor.run_edit_command(&EditCommand::NextTabStop);
+        assert_eq!(editor.insertion_point(), 15);
+        assert!(editor.has_tab_stops());
+        editor.run_edit_command(&EditCommand::NextTabStop);
+        assert!(!editor.has_tab_stops());
+        assert_eq!(editor.insertion_point(), 15);
+    }
+
+    #[test]
+    fn typing_overwrites_the_selected_placeholder() {
+        let mut editor = editor_with_tab_stops();
+        assert_eq!(editor.selected_placeholder(), Some(4..8));
+
+        editor.run_edit_command(&EditCommand::InsertString("first".into()));
+        editor.run_edit_command(&EditCommand::InsertChar('!'));
+        assert_eq!(editor.get_buffer(), "foo(first!, arg2)");
+        assert_eq!(editor.selected_placeholder(), None);
+
+        // The following placeholders moved with the edit
+        editor.run_edit_command(&EditCommand::NextTabStop);
+        assert_eq!(editor.insertion_point(), 12);
+        assert_eq!(editor.selected_placeholder(), Some(12..16));
+        editor.run_edit_command(&EditCommand::Delete);
+        assert_eq!(editor.get_buffer(), "foo(first!, )");
+        editor.run_edit_command(&EditCommand::InsertChar('2'));
+        assert_eq!(editor.get_buffer(), "foo(first!, 2)");
+
+        editor.run_edit_command(&EditCommand::NextTabStop);
+        assert_eq!(editor.insertion_point(), 14);
+    }
+
+    #[test]
+    fn moving_the_cursor_keeps_the_placeholder_text() {
+        let mut editor = editor_with_tab_stops();
+
+        editor.run_edit_command(&EditCommand::MoveRight);
+        editor.run_edit_command(&EditCommand::InsertChar('X'));
+        assert_eq!(editor.get_buffer(), "foo(aXrg1, arg2)");
+
+        // Replacing the buffer ends the snippet
+        editor.run_edit_command(&EditCommand::Undo);
+        assert!(!editor.has_tab_stops());
+    }
+
+    fn editor_with_selection(buffer: &str, anchor: usize, command: EditCommand) -> Editor {
+        let mut editor = editor_with(buffer);
+        editor.line_buffer.set_insertion_point(anchor);
+        editor.run_edit_command(&command);
+        editor
+    }
+
+    #[rstest]
+    #[case("hello world", 4, EditCommand::MoveWordRightEnd, "o world")]
+    #[case("hello world", 4, EditCommand::MoveWordLeft, "hello")]
+    #[case("one\ntwo\nthree", 1, EditCommand::MoveToEnd, "one\ntwo\nthree")]
+    #[case("one\ntwo\nthree", 5, EditCommand::MoveRight, "two\n")]
+    fn selection_includes_the_cursor(
+        #[case] buffer: &str,
+        #[case] anchor: usize,
+        #[case] motion: EditCommand,
+        #[case] expected: &str,
+    ) {
+        let linewise = buffer.contains('\n');
+        let mut editor = editor_with_selection(
+            buffer,
+            anchor,
+            if linewise {
+                EditCommand::StartLineSelection
+            } else {
+                EditCommand::StartSelection
+            },
+        );
+        editor.run_edit_command(&motion);
+
+        let range = editor.selection_range().unwrap();
+        let expected_start = buffer.find(expected).unwrap();
+        assert_eq!(range, expected_start..expected_start + expected.len());
+    }
+
+    #[test]
+    fn cut_selection() {
+        let mut editor = editor_with_selection("hello world", 6, EditCommand::StartSelection);
+        editor.run_edit_command(&EditCommand::MoveLeft);
+        editor.run_edit_command(&EditCommand::MoveLeft);
+
+        editor.run_edit_command(&EditCommand::CutSelection);
+        assert_eq!(editor.get_buffer(), "hellorld");
+        assert_eq!(editor.insertion_point(), 4);
+        assert_eq!(editor.cut_buffer.get().0, "o w");
+        assert_eq!(editor.selection_range(), None);
+    }
+
+    #[rstest]
+    #[case(
+        "one\ntwo\nthree",
+        5,
+        EditCommand::CutSelection,
+        "one\nthree",
+        4,
+        "two\n"
+    )]
+    #[case(
+        "one\ntwo\nthree",
+        9,
+        EditCommand::CutSelection,
+        "one\ntwo",
+        4,
+        "three"
+    )]
+    #[case(
+        "one\ntwo\nthree",
+        1,
+        EditCommand::CutSelection,
+        "two\nthree",
+        0,
+        "one\n"
+    )]
+    #[case("one", 1, EditCommand::CutSelection, "", 0, "one")]
+    #[case(
+        "one\ntwo\nthree",
+        5,
+        EditCommand::ChangeSelection,
+        "one\n\nthree",
+        4,
+        "two\n"
+    )]
+    #[case(
+        "one\ntwo\nthree",
+        9,
+        EditCommand::ChangeSelection,
+        "one\ntwo\n",
+        8,
+        "three"
+    )]
+    fn cut_line_selection(
+        #[case] buffer: &str,
+        #[case] anchor: usize,
+        #[case] command: EditCommand,
+        #[case] expected_buffer: &str,
+        #[case] expected_cursor: usize,
+        #[case] expected_cut: &str,
+    ) {
+        let mut editor = editor_with_selection(buffer, anchor, EditCommand::StartLineSelection);
+
+        editor.run_edit_command(&command);
+        assert_eq!(editor.get_buffer(), expected_buffer);
+        assert_eq!(editor.insertion_point(), expected_cursor);
+        assert!(matches!(
+            editor.cut_buffer.get(),
+            (text, ClipboardMode::Lines) if text == expected_cut
+        ));
+    }
+
+    #[test]
+    fn copy_line_selection() {
+        let mut editor =
+            editor_with_selection("one\ntwo\nthree", 5, EditCommand::StartLineSelection);
+        editor.run_edit_command(&EditCommand::MoveLeft);
+        // Switching to a character selection keeps the anchor
+        editor.run_edit_command(&EditCommand::StartSelection);
+        assert_eq!(editor.selection_range(), Some(4..6));
+        editor.run_edit_command(&EditCommand::StartLineSelection);
+
+        editor.run_edit_command(&EditCommand::CopySelection);
+        assert_eq!(editor.get_buffer(), "one\ntwo\nthree");
+        assert_eq!(editor.insertion_point(), 4);
+        assert!(matches!(
+            editor.cut_buffer.get(),
+            (text, ClipboardMode::Lines) if text == "two\n"
+        ));
+    }
+
+    #[test]
+    fn editing_the_text_clears_the_selection() {
+        let mut editor = editor_with_selection("hello", 0, EditCommand::StartSelection);
+        editor.run_edit_command(&EditCommand::MoveToLineEnd);
+        assert_eq!(editor.selection_range(), Some(0..5));
+
+        editor.run_edit_command(&EditCommand::InsertChar('!'));
+        assert_eq!(editor.selection_range(), None);
+        editor.run_edit_command(&EditCommand::CutSelection);
+        assert_eq!(editor.get_buffer(), "hello!");
+    }
 }
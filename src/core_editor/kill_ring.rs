@@ -0,0 +1,182 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// Default number of kills kept by the [`KillRing`]
+pub(crate) const DEFAULT_KILL_RING_SIZE: usize = 60;
+
+/// Direction in which a kill removed text relative to the cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum KillDirection {
+    Forward,
+    Backward,
+}
+
+/// Emacs style ring of killed text, cycled through by yank-pop
+pub(crate) struct KillRing {
+    // newest kill first
+    entries: VecDeque<String>,
+    capacity: usize,
+    // consecutive kills in the same direction are merged, like in Emacs
+    coalesce: bool,
+    // direction of the previous command if it killed text
+    last_kill: Option<KillDirection>,
+    // text inserted by the previous yank or yank-pop and the entry it came from
+    last_yank: Option<Yank>,
+}
+
+struct Yank {
+    range: Range<usize>,
+    text: String,
+    entry: Option<usize>,
+}
+
+impl Default for KillRing {
+    fn default() -> Self {
+        Self::new(DEFAULT_KILL_RING_SIZE)
+    }
+}
+
+impl KillRing {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+            coalesce: true,
+            last_kill: None,
+            last_yank: None,
+        }
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.entries.truncate(capacity);
+    }
+
+    /// Sets whether consecutive kills in the same direction are merged
+    pub fn set_coalesce(&mut self, coalesce: bool) {
+        self.coalesce = coalesce;
+    }
+
+    /// Adds killed text to the ring and returns the newest entry
+    ///
+    /// Text killed right after another kill in the same direction is merged into its
+    /// entry, unless coalescing was turned off.
+    pub fn kill(&mut self, text: &str, direction: KillDirection) -> Option<&str> {
+        if self.capacity == 0 {
+            return None;
+        }
+
+        match (self.last_kill, self.entries.front_mut()) {
+            (Some(last), Some(newest)) if self.coalesce && last == direction => match direction {
+                KillDirection::Forward => newest.push_str(text),
+                KillDirection::Backward => newest.insert_str(0, text),
+            },
+            _ => {
+                self.entries.push_front(text.to_owned());
+                self.entries.truncate(self.capacity);
+            }
+        }
+        self.last_kill = Some(direction);
+        self.last_yank = None;
+
+        self.entries.front().map(String::as_str)
+    }
+
+    /// Remembers the text a yank inserted at `range`, starting a yank-pop chain
+    pub fn yanked(&mut self, range: Range<usize>, text: &str) {
+        let entry = (self.entries.front().map(String::as_str) == Some(text)).then_some(0);
+        self.last_yank = Some(Yank {
+            range,
+            text: text.to_owned(),
+            entry,
+        });
+        self.last_kill = None;
+    }
+
+    /// Returns the range inserted by the previous yank if it is still found in `buffer`,
+    /// together with the next older entry to replace it with
+    pub fn yank_pop(&mut self, buffer: &str) -> Option<(Range<usize>, &str)> {
+        let yank = self.last_yank.as_mut()?;
+        if self.entries.is_empty() || buffer.get(yank.range.clone()) != Some(yank.text.as_str()) {
+            return None;
+        }
+
+        let entry = yank
+            .entry
+            .map_or(0, |entry| (entry + 1) % self.entries.len());
+        let text = &self.entries[entry];
+        let range = yank.range.clone();
+
+        yank.range = range.start..range.start + text.len();
+        yank.text = text.clone();
+        yank.entry = Some(entry);
+
+        Some((range, text))
+    }
+
+    /// Ends any kill or yank chain, called for all other commands
+    pub fn break_chain(&mut self) {
+        self.last_kill = None;
+        self.last_yank = None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn kills_in_the_same_direction_coalesce() {
+        let mut ring = KillRing::default();
+
+        ring.kill("foo", KillDirection::Backward);
+        assert_eq!(ring.kill("bar ", KillDirection::Backward), Some("bar foo"));
+        assert_eq!(ring.kill("baz", KillDirection::Forward), Some("baz"));
+        ring.break_chain();
+        assert_eq!(ring.kill("qux", KillDirection::Forward), Some("qux"));
+        assert_eq!(ring.entries, ["qux", "baz", "bar foo"]);
+    }
+
+    #[test]
+    fn kills_stay_apart_without_coalescing() {
+        let mut ring = KillRing::default();
+        ring.set_coalesce(false);
+
+        ring.kill("a", KillDirection::Forward);
+        assert_eq!(ring.kill("b", KillDirection::Forward), Some("b"));
+        assert_eq!(ring.entries, ["b", "a"]);
+    }
+
+    #[test]
+    fn ring_is_bounded() {
+        let mut ring = KillRing::new(2);
+
+        for text in ["a", "b", "c"] {
+            ring.kill(text, KillDirection::Forward);
+            ring.break_chain();
+        }
+        assert_eq!(ring.entries, ["c", "b"]);
+
+        ring.set_capacity(0);
+        assert_eq!(ring.kill("d", KillDirection::Forward), None);
+    }
+
+    #[test]
+    fn yank_pop_cycles_through_older_entries() {
+        let mut ring = KillRing::default();
+        for text in ["a", "bb", "ccc"] {
+            ring.kill(text, KillDirection::Forward);
+            ring.break_chain();
+        }
+
+        assert_eq!(ring.yank_pop("x ccc"), None);
+
+        ring.yanked(2..5, "ccc");
+        assert_eq!(ring.yank_pop("x ccc"), Some((2..5, "bb")));
+        assert_eq!(ring.yank_pop("x bb"), Some((2..4, "a")));
+        assert_eq!(ring.yank_pop("x a"), Some((2..3, "ccc")));
+        // The yanked text was changed in the meantime
+        assert_eq!(ring.yank_pop("x cxc"), None);
+    }
+}
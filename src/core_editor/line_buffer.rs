@@ -1,16 +1,27 @@
 use {
-    itertools::Itertools,
+    crate::{enums::TextObject, utils::brackets::bracket_partners},
+    itertools::{Either, Itertools},
     std::{convert::From, ops::Range},
     unicode_segmentation::UnicodeSegmentation,
 };
 
 /// In memory representation of the entered line(s) including a cursor position to facilitate cursor based editing.
-#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct LineBuffer {
     lines: String,
     insertion_point: usize,
+    word_char_class: Option<fn(char) -> CharClass>,
 }
 
+// The word predicate is configuration, buffers are equal if their content and cursor are
+impl PartialEq for LineBuffer {
+    fn eq(&self, other: &Self) -> bool {
+        self.lines == other.lines && self.insertion_point == other.insertion_point
+    }
+}
+
+impl Eq for LineBuffer {}
+
 impl From<&str> for LineBuffer {
     fn from(input: &str) -> Self {
         let mut line_buffer = LineBuffer::new();
@@ -25,6 +36,20 @@ impl LineBuffer {
         Self::default()
     }
 
+    /// Set the predicate classifying characters for the word movements and deletions
+    ///
+    /// Words are runs of [`CharClass::Word`] characters, every [`CharClass::Punctuation`]
+    /// character stands on its own. Without a predicate (default) the Unicode word
+    /// boundaries are used.
+    pub fn set_word_char_class(&mut self, word_char_class: Option<fn(char) -> CharClass>) {
+        self.word_char_class = word_char_class;
+    }
+
+    /// The predicate classifying characters for the word movements and deletions
+    pub fn word_char_class(&self) -> Option<fn(char) -> CharClass> {
+        self.word_char_class
+    }
+
     /// Check to see if the line buffer is empty
     pub fn is_empty(&self) -> bool {
         self.lines.is_empty()
@@ -170,9 +195,8 @@ impl LineBuffer {
 
     /// Cursor position *behind* the next word to the right
     pub fn word_right_index(&self) -> usize {
-        self.lines[self.insertion_point..]
-            .split_word_bound_indices()
-            .find(|(_, word)| !is_whitespace_str(word))
+        self.word_bound_indices(&self.lines[self.insertion_point..])
+            .find(|(_, word)| !self.is_blank(word))
             .map(|(i, word)| self.insertion_point + i + word.len())
             .unwrap_or_else(|| self.lines.len())
     }
@@ -181,11 +205,10 @@ impl LineBuffer {
     pub fn big_word_right_index(&self) -> usize {
         let mut found_ws = false;
 
-        self.lines[self.insertion_point..]
-            .split_word_bound_indices()
+        self.word_bound_indices(&self.lines[self.insertion_point..])
             .find(|(_, word)| {
-                found_ws = found_ws || is_whitespace_str(word);
-                found_ws && !is_whitespace_str(word)
+                found_ws = found_ws || self.is_blank(word);
+                found_ws && !self.is_blank(word)
             })
             .map(|(i, word)| self.insertion_point + i + word.len())
             .unwrap_or_else(|| self.lines.len())
@@ -193,13 +216,12 @@ impl LineBuffer {
 
     /// Cursor position *at end of* the next word to the right
     pub fn word_right_end_index(&self) -> usize {
-        self.lines[self.insertion_point..]
-            .split_word_bound_indices()
+        self.word_bound_indices(&self.lines[self.insertion_point..])
             .find_map(|(i, word)| {
                 word.grapheme_indices(true)
                     .next_back()
                     .map(|x| self.insertion_point + x.0 + i)
-                    .filter(|x| !is_whitespace_str(word) && *x != self.insertion_point)
+                    .filter(|x| !self.is_blank(word) && *x != self.insertion_point)
             })
             .unwrap_or_else(|| {
                 self.lines
@@ -212,11 +234,10 @@ impl LineBuffer {
 
     /// Cursor position *at end of* the next WORD to the right
     pub fn big_word_right_end_index(&self) -> usize {
-        self.lines[self.insertion_point..]
-            .split_word_bound_indices()
+        self.word_bound_indices(&self.lines[self.insertion_point..])
             .tuple_windows()
             .find_map(|((prev_i, prev_word), (_, word))| {
-                if is_whitespace_str(word) {
+                if self.is_blank(word) {
                     prev_word
                         .grapheme_indices(true)
                         .next_back()
@@ -237,9 +258,8 @@ impl LineBuffer {
 
     /// Cursor position *in front of* the next word to the right
     pub fn word_right_start_index(&self) -> usize {
-        self.lines[self.insertion_point..]
-            .split_word_bound_indices()
-            .find(|(i, word)| *i != 0 && !is_whitespace_str(word))
+        self.word_bound_indices(&self.lines[self.insertion_point..])
+            .find(|(i, word)| *i != 0 && !self.is_blank(word))
             .map(|(i, _)| self.insertion_point + i)
             .unwrap_or_else(|| self.lines.len())
     }
@@ -248,11 +268,10 @@ impl LineBuffer {
     pub fn big_word_right_start_index(&self) -> usize {
         let mut found_ws = false;
 
-        self.lines[self.insertion_point..]
-            .split_word_bound_indices()
+        self.word_bound_indices(&self.lines[self.insertion_point..])
             .find(|(i, word)| {
-                found_ws = found_ws || *i != 0 && is_whitespace_str(word);
-                found_ws && *i != 0 && !is_whitespace_str(word)
+                found_ws = found_ws || *i != 0 && self.is_blank(word);
+                found_ws && *i != 0 && !self.is_blank(word)
             })
             .map(|(i, _)| self.insertion_point + i)
             .unwrap_or_else(|| self.lines.len())
@@ -260,9 +279,8 @@ impl LineBuffer {
 
     /// Cursor position *in front of* the next word to the left
     pub fn word_left_index(&self) -> usize {
-        self.lines[..self.insertion_point]
-            .split_word_bound_indices()
-            .filter(|(_, word)| !is_whitespace_str(word))
+        self.word_bound_indices(&self.lines[..self.insertion_point])
+            .filter(|(_, word)| !self.is_blank(word))
             .last()
             .map(|(i, _)| i)
             .unwrap_or(0)
@@ -270,10 +288,9 @@ impl LineBuffer {
 
     /// Cursor position *in front of* the next WORD to the left
     pub fn big_word_left_index(&self) -> usize {
-        self.lines[..self.insertion_point]
-            .split_word_bound_indices()
+        self.word_bound_indices(&self.lines[..self.insertion_point])
             .fold(None, |last_word_index, (i, word)| {
-                match (last_word_index, is_whitespace_str(word)) {
+                match (last_word_index, self.is_blank(word)) {
                     (None, true) => None,
                     (None, false) => Some(i),
                     (Some(_), true) => None,
@@ -283,11 +300,26 @@ impl LineBuffer {
             .unwrap_or(0)
     }
 
+    /// Word boundaries of `text` according to the word predicate
+    fn word_bound_indices<'a>(&self, text: &'a str) -> impl Iterator<Item = (usize, &'a str)> {
+        match self.word_char_class {
+            Some(char_class) => Either::Right(split_by_char_class(text, char_class)),
+            None => Either::Left(text.split_word_bound_indices()),
+        }
+    }
+
+    /// Whether the word boundary segment only consists of whitespace
+    fn is_blank(&self, word: &str) -> bool {
+        match self.word_char_class {
+            Some(char_class) => word.chars().all(|c| char_class(c) == CharClass::Whitespace),
+            None => is_whitespace_str(word),
+        }
+    }
+
     /// Cursor position on the next whitespace
     pub fn next_whitespace(&self) -> usize {
-        self.lines[self.insertion_point..]
-            .split_word_bound_indices()
-            .find(|(i, word)| *i != 0 && is_whitespace_str(word))
+        self.word_bound_indices(&self.lines[self.insertion_point..])
+            .find(|(i, word)| *i != 0 && self.is_blank(word))
             .map(|(i, _)| self.insertion_point + i)
             .unwrap_or_else(|| self.lines.len())
     }
@@ -432,9 +464,9 @@ impl LineBuffer {
     /// Gets the range of the word the current edit position is pointing to
     pub fn current_word_range(&self) -> Range<usize> {
         let right_index = self.word_right_index();
-        let left_index = self.lines[..right_index]
-            .split_word_bound_indices()
-            .filter(|(_, word)| !is_whitespace_str(word))
+        let left_index = self
+            .word_bound_indices(&self.lines[..right_index])
+            .filter(|(_, word)| !self.is_blank(word))
             .last()
             .map(|(i, _)| i)
             .unwrap_or(0);
@@ -458,20 +490,226 @@ impl LineBuffer {
         left_index..right_index
     }
 
-    /// Uppercases the current word
+    /// Range of the [`TextObject`] at the insertion point
+    ///
+    /// Returns `None` if there is no such text object around the insertion point,
+    /// e.g. no enclosing pair of parentheses
+    pub fn text_object_range(&self, text_object: TextObject) -> Option<Range<usize>> {
+        match text_object {
+            TextObject::InnerWord => self.word_object_range(false, false),
+            TextObject::AroundWord => self.word_object_range(false, true),
+            TextObject::InnerBigWord => self.word_object_range(true, false),
+            TextObject::AroundBigWord => self.word_object_range(true, true),
+            TextObject::InnerPair(open, close) => self
+                .enclosing_pair_range(open, close)
+                .map(|range| range.start + open.len_utf8()..range.end - close.len_utf8()),
+            TextObject::AroundPair(open, close) => self.enclosing_pair_range(open, close),
+            TextObject::InnerQuote(quote) => self
+                .quote_range(quote)
+                .map(|range| range.start + quote.len_utf8()..range.end - quote.len_utf8()),
+            TextObject::AroundQuote(quote) => self
+                .quote_range(quote)
+                .map(|range| self.with_surrounding_whitespace(range)),
+        }
+    }
+
+    /// Offset of the character the text objects are anchored on: the one under the cursor
+    /// or the last one if the cursor is at the end of the buffer
+    fn text_object_anchor(&self) -> Option<usize> {
+        if self.insertion_point < self.lines.len() {
+            Some(self.insertion_point)
+        } else {
+            self.lines.char_indices().last().map(|(i, _)| i)
+        }
+    }
+
+    fn word_object_range(&self, big_word: bool, around: bool) -> Option<Range<usize>> {
+        let anchor = self.text_object_anchor()?;
+        let class = |c: char| RunClass::of(c, big_word);
+        let anchor_class = class(self.lines[anchor..].chars().next()?);
+        if anchor_class == RunClass::Newline {
+            return None;
+        }
+
+        let start = self.run_start(anchor, |c| class(c) == anchor_class);
+        let end = self.run_end(anchor, |c| class(c) == anchor_class);
+        if !around {
+            return Some(start..end);
+        }
+
+        if anchor_class == RunClass::Whitespace {
+            // The whitespace together with the following word
+            return match self.lines[end..].chars().next().map(class) {
+                Some(next_class) if next_class != RunClass::Newline => {
+                    Some(start..self.run_end(end, |c| class(c) == next_class))
+                }
+                _ => Some(start..end),
+            };
+        }
+        Some(self.with_surrounding_whitespace(start..end))
+    }
+
+    /// Extends the range over its trailing whitespace, or its leading whitespace
+    /// if there is none, without crossing lines
+    fn with_surrounding_whitespace(&self, range: Range<usize>) -> Range<usize> {
+        let is_blank = |c: char| RunClass::of(c, true) == RunClass::Whitespace;
+        let end = self.run_end(range.end, is_blank);
+        if end > range.end {
+            range.start..end
+        } else {
+            self.run_start(range.start, is_blank)..range.end
+        }
+    }
+
+    /// Start of the run of characters matching `pred` that ends right before or at `offset`
+    fn run_start(&self, offset: usize, pred: impl Fn(char) -> bool) -> usize {
+        self.lines[..offset]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| pred(*c))
+            .last()
+            .map_or(offset, |(i, _)| i)
+    }
+
+    /// End of the run of characters matching `pred` that starts at `offset`
+    fn run_end(&self, offset: usize, pred: impl Fn(char) -> bool) -> usize {
+        self.lines[offset..]
+            .char_indices()
+            .find(|(_, c)| !pred(*c))
+            .map_or(self.lines.len(), |(i, _)| offset + i)
+    }
+
+    /// Range of the innermost `open`/`close` pair enclosing the cursor, delimiters included
+    fn enclosing_pair_range(&self, open: char, close: char) -> Option<Range<usize>> {
+        let anchor = self.text_object_anchor()?;
+        let anchor_end = anchor + self.lines[anchor..].chars().next()?.len_utf8();
+
+        let mut depth = 0;
+        let (open_index, _) = self.lines[..anchor_end]
+            .char_indices()
+            .rev()
+            .find(|&(i, c)| {
+                if c == open {
+                    if depth == 0 {
+                        return true;
+                    }
+                    depth -= 1;
+                } else if c == close && i != anchor {
+                    depth += 1;
+                }
+                false
+            })?;
+
+        let inner_start = open_index + open.len_utf8();
+        let mut depth = 0;
+        let (close_index, _) = self.lines[inner_start..].char_indices().find(|&(_, c)| {
+            if c == close {
+                if depth == 0 {
+                    return true;
+                }
+                depth -= 1;
+            } else if c == open {
+                depth += 1;
+            }
+            false
+        })?;
+
+        Some(open_index..inner_start + close_index + close.len_utf8())
+    }
+
+    /// Range of the pair of `quote`s on the current line around the cursor, or of the
+    /// first pair after the cursor, quotes included. Escaped quotes are skipped.
+    fn quote_range(&self, quote: char) -> Option<Range<usize>> {
+        let anchor = self.text_object_anchor()?;
+        let line_start = self.lines[..anchor].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.lines[anchor..]
+            .find('\n')
+            .map_or(self.lines.len(), |i| anchor + i);
+
+        let line = &self.lines[line_start..line_end];
+        let quotes: Vec<usize> = line
+            .char_indices()
+            .filter(|&(i, c)| c == quote && !line[..i].ends_with('\\'))
+            .map(|(i, _)| line_start + i)
+            .collect();
+
+        quotes
+            .chunks_exact(2)
+            .find(|pair| pair[1] >= anchor)
+            .map(|pair| pair[0]..pair[1] + quote.len_utf8())
+    }
+
+    /// Position of the partner of the bracket under the cursor, or of the next
+    /// bracket on the current line
+    ///
+    /// `()`, `[]` and `{}` are matched accounting for nesting, brackets inside quotes
+    /// or escaped with a backslash are skipped.
+    pub fn matching_bracket_index(&self) -> Option<usize> {
+        let line_end = self.find_current_line_end();
+        bracket_partners(&self.lines)
+            .into_iter()
+            .find(|(index, _)| (self.insertion_point..line_end).contains(index))
+            .and_then(|(_, partner)| partner)
+    }
+
+    /// Range from the cursor to the partner of the bracket under the cursor, or of the
+    /// next bracket on the current line, with the character at either end included
+    pub fn matching_bracket_range(&self) -> Option<Range<usize>> {
+        let index = self.matching_bracket_index()?;
+        let start = self.insertion_point.min(index);
+        let last = self.insertion_point.max(index);
+        // The later end is a bracket or the character under the cursor
+        let end = self.lines[last..]
+            .chars()
+            .next()
+            .map_or(last, |c| last + c.len_utf8());
+
+        Some(start..end)
+    }
+
+    /// Moves the cursor to the partner of the bracket under the cursor, or of the
+    /// next bracket on the current line. Stays in place if there is none.
+    pub fn move_to_matching_bracket(&mut self) {
+        if let Some(index) = self.matching_bracket_index() {
+            self.insertion_point = index;
+        }
+    }
+
+    /// Uppercases the text from the insertion point to the end of the word and
+    /// moves the insertion point behind it
     pub fn uppercase_word(&mut self) {
-        let change_range = self.current_word_range();
-        let uppercased = self.get_buffer()[change_range.clone()].to_uppercase();
-        self.replace_range(change_range, &uppercased);
-        self.move_word_right();
+        self.transform_word_right(str::to_uppercase);
     }
 
-    /// Lowercases the current word
+    /// Lowercases the text from the insertion point to the end of the word and
+    /// moves the insertion point behind it
     pub fn lowercase_word(&mut self) {
-        let change_range = self.current_word_range();
-        let uppercased = self.get_buffer()[change_range.clone()].to_lowercase();
-        self.replace_range(change_range, &uppercased);
-        self.move_word_right();
+        self.transform_word_right(str::to_lowercase);
+    }
+
+    /// Uppercases the first letter from the insertion point, lowercases the rest of
+    /// the word and moves the insertion point behind it
+    pub fn capitalize_word(&mut self) {
+        self.transform_word_right(|text| {
+            match text.char_indices().find(|(_, c)| c.is_alphabetic()) {
+                Some((i, c)) => {
+                    let rest = &text[i + c.len_utf8()..];
+                    format!("{}{}{}", &text[..i], c.to_uppercase(), rest.to_lowercase())
+                }
+                None => text.to_string(),
+            }
+        });
+    }
+
+    /// Replaces the text from the insertion point to the end of the next word
+    ///
+    /// The case conversions can change the length of the text, the insertion point
+    /// is moved behind the replacement.
+    fn transform_word_right(&mut self, transform: impl Fn(&str) -> String) {
+        let change_range = self.insertion_point..self.word_right_index();
+        let transformed = transform(&self.lines[change_range.clone()]);
+        self.insertion_point = change_range.start + transformed.len();
+        self.lines.replace_range(change_range, &transformed);
     }
 
     /// Switches the ASCII case of the current char
@@ -541,12 +779,31 @@ impl LineBuffer {
         self.insertion_point = left_word_index;
     }
 
+    /// Deletes one WORD (whitespace delimited) to the left
+    pub fn delete_big_word_left(&mut self) {
+        let left_word_index = self.big_word_left_index();
+        self.clear_range(left_word_index..self.insertion_point());
+        self.insertion_point = left_word_index;
+    }
+
     /// Deletes one word to the right
     pub fn delete_word_right(&mut self) {
         let right_word_index = self.word_right_index();
         self.clear_range(self.insertion_point()..right_word_index);
     }
 
+    /// Deletes one WORD (whitespace delimited) to the right
+    pub fn delete_big_word_right(&mut self) {
+        let right_word_index = self
+            .word_bound_indices(&self.lines[self.insertion_point..])
+            .skip_while(|(_, word)| self.is_blank(word))
+            .take_while(|(_, word)| !self.is_blank(word))
+            .last()
+            .map(|(i, word)| self.insertion_point + i + word.len())
+            .unwrap_or_else(|| self.lines.len());
+        self.clear_range(self.insertion_point()..right_word_index);
+    }
+
     /// Swaps current word with word on right
     pub fn swap_words(&mut self) {
         let word_1_range = self.current_word_range();
@@ -563,6 +820,42 @@ impl LineBuffer {
         }
     }
 
+    /// Swaps the word before the cursor, or the word the cursor is in, with the word
+    /// after it and moves the cursor behind both
+    ///
+    /// Behind the last word the last two words are swapped. The whitespace between
+    /// the words is kept.
+    pub fn transpose_words(&mut self) {
+        let words: Vec<Range<usize>> = self
+            .word_bound_indices(&self.lines)
+            .filter(|(_, word)| !self.is_blank(word))
+            .map(|(i, word)| i..i + word.len())
+            .collect();
+        if words.len() < 2 {
+            return;
+        }
+
+        let second = match words
+            .iter()
+            .position(|word| word.end > self.insertion_point)
+        {
+            Some(current) if words[current].start < self.insertion_point => current + 1,
+            Some(next) => next,
+            None => words.len() - 1,
+        }
+        .clamp(1, words.len() - 1);
+        let (first, second) = (words[second - 1].clone(), words[second].clone());
+
+        let transposed = format!(
+            "{}{}{}",
+            &self.lines[second.clone()],
+            &self.lines[first.end..second.start],
+            &self.lines[first.clone()]
+        );
+        self.replace_range(first.start..second.end, &transposed);
+        self.insertion_point = second.end;
+    }
+
     /// Swaps current grapheme with grapheme on right
     pub fn swap_graphemes(&mut self) {
         let initial_offset = self.insertion_point();
@@ -654,24 +947,46 @@ impl LineBuffer {
 
     /// Finds index for the first occurrence of a char to the right of offset
     pub fn find_char_right(&self, c: char, current_line: bool) -> Option<usize> {
+        self.find_grapheme_right(c, current_line)
+            .map(|range| range.start)
+    }
+
+    /// Finds index for the first occurrence of a char to the left of offset
+    pub fn find_char_left(&self, c: char, current_line: bool) -> Option<usize> {
+        self.find_grapheme_left(c, current_line)
+            .map(|range| range.start)
+    }
+
+    /// Finds the range of the first grapheme to the right of the cursor starting with `c`
+    ///
+    /// Whole graphemes are matched, so a char followed by combining marks is found
+    /// together with them.
+    pub fn find_grapheme_right(&self, c: char, current_line: bool) -> Option<Range<usize>> {
         // Skip current grapheme
-        let char_offset = self.grapheme_right_index();
-        let range = if current_line {
-            char_offset..self.current_line_range().end
+        let offset = self.grapheme_right_index();
+        let end = if current_line {
+            self.current_line_range().end
         } else {
-            char_offset..self.lines.len()
+            self.lines.len()
         };
-        self.lines[range].find(c).map(|index| index + char_offset)
+        self.lines[offset..end]
+            .grapheme_indices(true)
+            .find(|(_, grapheme)| grapheme.starts_with(c))
+            .map(|(index, grapheme)| offset + index..offset + index + grapheme.len())
     }
 
-    /// Finds index for the first occurrence of a char to the left of offset
-    pub fn find_char_left(&self, c: char, current_line: bool) -> Option<usize> {
-        let range = if current_line {
-            self.current_line_range().start..self.insertion_point()
+    /// Finds the range of the first grapheme to the left of the cursor starting with `c`
+    pub fn find_grapheme_left(&self, c: char, current_line: bool) -> Option<Range<usize>> {
+        let start = if current_line {
+            self.current_line_range().start
         } else {
-            0..self.insertion_point()
+            0
         };
-        self.lines[range.clone()].rfind(c).map(|i| i + range.start)
+        self.lines[start..self.insertion_point()]
+            .grapheme_indices(true)
+            .rev()
+            .find(|(_, grapheme)| grapheme.starts_with(c))
+            .map(|(index, grapheme)| start + index..start + index + grapheme.len())
     }
 
     /// Moves the insertion point until the next char to the right
@@ -704,8 +1019,8 @@ impl LineBuffer {
 
     /// Moves the insertion point before the next char to the left of offset
     pub fn move_left_before(&mut self, c: char, current_line: bool) -> usize {
-        if let Some(index) = self.find_char_left(c, current_line) {
-            self.insertion_point = index + c.len_utf8();
+        if let Some(range) = self.find_grapheme_left(c, current_line) {
+            self.insertion_point = range.end;
         }
 
         self.insertion_point
@@ -713,8 +1028,8 @@ impl LineBuffer {
 
     /// Deletes until first character to the right of offset
     pub fn delete_right_until_char(&mut self, c: char, current_line: bool) {
-        if let Some(index) = self.find_char_right(c, current_line) {
-            self.clear_range(self.insertion_point()..index + c.len_utf8());
+        if let Some(range) = self.find_grapheme_right(c, current_line) {
+            self.clear_range(self.insertion_point()..range.end);
         }
     }
 
@@ -735,9 +1050,79 @@ impl LineBuffer {
 
     /// Deletes before first character to the left of offset
     pub fn delete_left_before_char(&mut self, c: char, current_line: bool) {
-        if let Some(index) = self.find_char_left(c, current_line) {
-            self.clear_range(index + c.len_utf8()..self.insertion_point());
-            self.insertion_point = index + c.len_utf8();
+        if let Some(range) = self.find_grapheme_left(c, current_line) {
+            self.clear_range(range.end..self.insertion_point());
+            self.insertion_point = range.end;
+        }
+    }
+}
+
+/// Class of a character deciding where words begin and end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharClass {
+    /// Separates words, skipped by the word movements
+    Whitespace,
+    /// Part of a word
+    Word,
+    /// A word of its own
+    Punctuation,
+}
+
+impl CharClass {
+    /// Classifies alphanumeric characters and `_` as word characters, a starting point
+    /// for custom predicates
+    pub fn of(c: char) -> Self {
+        if c.is_whitespace() {
+            CharClass::Whitespace
+        } else if c.is_alphanumeric() || c == '_' {
+            CharClass::Word
+        } else {
+            CharClass::Punctuation
+        }
+    }
+}
+
+/// Split `text` into runs of word characters, runs of whitespace and single punctuation graphemes
+fn split_by_char_class(
+    text: &str,
+    char_class: fn(char) -> CharClass,
+) -> impl Iterator<Item = (usize, &str)> {
+    let class_of = move |grapheme: &str| grapheme.chars().next().map(char_class);
+    let mut graphemes = text.grapheme_indices(true).peekable();
+    std::iter::from_fn(move || {
+        let (start, first) = graphemes.next()?;
+        let class = class_of(first);
+        let mut end = start + first.len();
+        if class != Some(CharClass::Punctuation) {
+            while let Some((i, grapheme)) =
+                graphemes.next_if(|(_, grapheme)| class_of(grapheme) == class)
+            {
+                end = i + grapheme.len();
+            }
+        }
+        Some((start, &text[start..end]))
+    })
+}
+
+/// Classes of characters that make up the runs selected by the word text objects
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RunClass {
+    Newline,
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+impl RunClass {
+    fn of(c: char, big_word: bool) -> Self {
+        if c == '\n' || c == '\r' {
+            RunClass::Newline
+        } else if c.is_whitespace() {
+            RunClass::Whitespace
+        } else if big_word || c.is_alphanumeric() || c == '_' {
+            RunClass::Word
+        } else {
+            RunClass::Punctuation
         }
     }
 }
@@ -822,9 +1207,55 @@ mod test {
         line_buffer.assert_valid();
     }
 
+    #[rstest]
+    #[case("foo bar  baz", 5, TextObject::InnerWord, Some("bar"))]
+    #[case("foo bar  baz", 5, TextObject::AroundWord, Some("bar  "))]
+    #[case("foo bar", 5, TextObject::AroundWord, Some(" bar"))]
+    #[case("foo bar  baz", 7, TextObject::InnerWord, Some("  "))]
+    #[case("foo bar  baz", 7, TextObject::AroundWord, Some("  baz"))]
+    #[case("cd ../foo", 4, TextObject::InnerWord, Some("../"))]
+    #[case("cd ../foo bar", 4, TextObject::InnerBigWord, Some("../foo"))]
+    #[case("cd ../foo bar", 4, TextObject::AroundBigWord, Some("../foo "))]
+    #[case("foo\nbar", 5, TextObject::AroundWord, Some("bar"))]
+    #[case("f(a, (b), c)", 10, TextObject::InnerPair('(', ')'), Some("a, (b), c"))]
+    #[case("f(a, (b), c)", 6, TextObject::InnerPair('(', ')'), Some("b"))]
+    #[case("f(a, (b), c)", 5, TextObject::AroundPair('(', ')'), Some("(b)"))]
+    #[case("f(a, (b), c)", 7, TextObject::AroundPair('(', ')'), Some("(b)"))]
+    #[case("f(a, (b), c)", 0, TextObject::InnerPair('(', ')'), None)]
+    #[case("f()", 2, TextObject::InnerPair('(', ')'), Some(""))]
+    #[case("{ [x] }", 3, TextObject::InnerPair('{', '}'), Some(" [x] "))]
+    #[case(r#"echo "a b" "c""#, 7, TextObject::InnerQuote('"'), Some("a b"))]
+    #[case(
+        r#"echo "a b" "c""#,
+        7,
+        TextObject::AroundQuote('"'),
+        Some(r#""a b" "#)
+    )]
+    #[case(r#"echo "a b" "c""#, 1, TextObject::InnerQuote('"'), Some("a b"))]
+    #[case(r#"echo "a b" "c""#, 12, TextObject::InnerQuote('"'), Some("c"))]
+    #[case(r#"echo "a \" b""#, 9, TextObject::InnerQuote('"'), Some(r#"a \" b"#))]
+    #[case("echo 'a'\n'b'", 10, TextObject::InnerQuote('\''), Some("b"))]
+    #[case("echo a", 2, TextObject::InnerQuote('"'), None)]
+    fn text_object_range_works(
+        #[case] input: &str,
+        #[case] position: usize,
+        #[case] text_object: TextObject,
+        #[case] expected: Option<&str>,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(position);
+
+        let range = line_buffer.text_object_range(text_object);
+
+        assert_eq!(range.map(|range| &input[range]), expected);
+    }
+
     #[rstest]
     #[case("This is a test", "This is a tes")]
     #[case("This is a test 😊", "This is a test ")]
+    #[case("family 👨\u{200d}👩\u{200d}👧", "family ")]
+    #[case("cafe\u{301}", "caf")]
+    #[case("flag 🇩🇪", "flag ")]
     #[case("", "")]
     fn delete_left_grapheme_works(#[case] input: &str, #[case] expected: &str) {
         let mut line_buffer = buffer_with(input);
@@ -839,6 +1270,8 @@ mod test {
     #[rstest]
     #[case("This is a test", "This is a tes")]
     #[case("This is a test 😊", "This is a test ")]
+    #[case("family 👨\u{200d}👩\u{200d}👧", "family ")]
+    #[case("cafe\u{301}", "caf")]
     #[case("", "")]
     fn delete_right_grapheme_works(#[case] input: &str, #[case] expected: &str) {
         let mut line_buffer = buffer_with(input);
@@ -851,6 +1284,36 @@ mod test {
         line_buffer.assert_valid();
     }
 
+    #[rstest]
+    #[case("👨\u{200d}👩\u{200d}👧 ok", &[0, 18, 19, 20, 21])]
+    #[case("e\u{301}a\u{308}\u{304}", &[0, 3, 8])]
+    #[case("🇩🇪🇫🇷", &[0, 8, 16])]
+    fn moving_steps_over_grapheme_clusters(#[case] input: &str, #[case] stops: &[usize]) {
+        let mut line_buffer = buffer_with("");
+        line_buffer.set_buffer(input.to_string());
+        line_buffer.move_to_start();
+
+        for &stop in &stops[1..] {
+            line_buffer.move_right();
+            assert_eq!(line_buffer.insertion_point(), stop);
+        }
+        for &stop in stops.iter().rev().skip(1) {
+            line_buffer.move_left();
+            assert_eq!(line_buffer.insertion_point(), stop);
+        }
+    }
+
+    #[test]
+    fn char_search_matches_whole_graphemes() {
+        let mut line_buffer = buffer_with("cafe\u{301} x");
+
+        assert_eq!(line_buffer.move_left_before('e', true), 6);
+        line_buffer.move_to_start();
+        line_buffer.delete_right_until_char('e', true);
+        assert_eq!(line_buffer.get_buffer(), " x");
+        line_buffer.assert_valid();
+    }
+
     #[test]
     fn delete_word_left_works() {
         let mut line_buffer = buffer_with("This is a test");
@@ -862,6 +1325,75 @@ mod test {
         line_buffer.assert_valid();
     }
 
+    #[test]
+    fn delete_big_word_left_works() {
+        let mut line_buffer = buffer_with("cd /usr/local/bin");
+        line_buffer.delete_big_word_left();
+
+        let expected_line_buffer = buffer_with("cd ");
+
+        assert_eq!(expected_line_buffer, line_buffer);
+        line_buffer.assert_valid();
+    }
+
+    #[rstest]
+    #[case("cd /usr/local/bin", 0, " /usr/local/bin")]
+    #[case("cd /usr/local/bin ", 2, "cd ")]
+    #[case("foo-bar.baz qux", 4, "foo- qux")]
+    #[case("ls   ", 2, "ls")]
+    fn delete_big_word_right_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] expected: &str,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+        line_buffer.delete_big_word_right();
+
+        assert_eq!(line_buffer.get_buffer(), expected);
+        line_buffer.assert_valid();
+    }
+
+    fn dash_in_word(c: char) -> CharClass {
+        match c {
+            '-' => CharClass::Word,
+            c => CharClass::of(c),
+        }
+    }
+
+    #[rstest]
+    #[case(None, "cd foo-bar.baz", "cd foo-")]
+    #[case(Some(dash_in_word as fn(char) -> CharClass), "cd foo-bar.baz", "cd foo-bar.")]
+    #[case(Some(dash_in_word as fn(char) -> CharClass), "cd foo-bar", "cd ")]
+    #[case(None, "cd foo-bar", "cd foo-")]
+    #[case(Some(dash_in_word as fn(char) -> CharClass), "ls ../", "ls ..")]
+    fn word_char_class_decides_word_boundaries(
+        #[case] word_char_class: Option<fn(char) -> CharClass>,
+        #[case] input: &str,
+        #[case] expected: &str,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_word_char_class(word_char_class);
+        line_buffer.delete_word_left();
+
+        assert_eq!(line_buffer.get_buffer(), expected);
+        line_buffer.assert_valid();
+    }
+
+    #[test]
+    fn word_char_class_moves_over_whole_words() {
+        let mut line_buffer = buffer_with("git commit --amend -m");
+        line_buffer.set_word_char_class(Some(dash_in_word));
+        line_buffer.move_to_start();
+
+        let mut stops = vec![];
+        for _ in 0..4 {
+            line_buffer.move_word_right_start();
+            stops.push(line_buffer.insertion_point());
+        }
+        assert_eq!(stops, [4, 11, 19, 21]);
+    }
+
     #[test]
     fn delete_word_right_works() {
         let mut line_buffer = buffer_with("This is a test");
@@ -925,11 +1457,76 @@ mod test {
     }
 
     #[rstest]
-    #[case("This is a test", 13, "This is a TEST", 14)]
+    #[case("(a (b) c)", 0, 8)]
+    #[case("(a (b) c)", 8, 0)]
+    #[case("(a (b) c)", 1, 5)]
+    #[case("f[x] = {'}'}", 5, 11)]
+    #[case("echo \"(\" (x)", 0, 11)]
+    #[case("(a\nb)", 3, 0)]
+    #[case("(a)\nb", 4, 4)]
+    #[case("(a]", 0, 0)]
+    #[case("ä(ö)", 0, 5)]
+    fn move_to_matching_bracket_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] out_location: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+        line_buffer.move_to_matching_bracket();
+
+        assert_eq!(line_buffer.insertion_point(), out_location);
+        line_buffer.assert_valid();
+    }
+
+    #[rstest]
+    #[case("(a (b) c)", 0, Some(0..9))]
+    #[case("(a (b) c)", 8, Some(0..9))]
+    #[case("(a (b) c)", 1, Some(1..6))]
+    #[case("ä(ö)", 0, Some(0..6))]
+    #[case("(a]", 0, None)]
+    fn matching_bracket_range_includes_both_ends(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] expected: Option<Range<usize>>,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+
+        assert_eq!(line_buffer.matching_bracket_range(), expected);
+    }
+
+    #[rstest]
+    #[case("this is a TEST", 0, "This is a TEST", 4)]
+    #[case("this is a TEST", 9, "this is a Test", 14)]
+    #[case("this is a TEST", 12, "this is a TESt", 14)]
+    #[case("  élan", 0, "  Élan", 7)]
+    #[case("", 0, "", 0)]
+    fn capitalize_word_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] output: &str,
+        #[case] out_location: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+        line_buffer.capitalize_word();
+
+        let mut expected = buffer_with(output);
+        expected.set_insertion_point(out_location);
+
+        assert_eq!(expected, line_buffer);
+        line_buffer.assert_valid();
+    }
+
+    #[rstest]
+    #[case("This is a test", 13, "This is a tesT", 14)]
     #[case("This is a test", 10, "This is a TEST", 14)]
+    #[case("This is a test", 9, "This is a TEST", 14)]
     #[case("", 0, "", 0)]
     #[case("This", 0, "THIS", 4)]
-    #[case("This", 4, "THIS", 4)]
+    #[case("This", 4, "This", 4)]
+    #[case("straße", 0, "STRASSE", 7)]
     fn uppercase_word_works(
         #[case] input: &str,
         #[case] in_location: usize,
@@ -948,11 +1545,12 @@ mod test {
     }
 
     #[rstest]
-    #[case("This is a TEST", 13, "This is a test", 14)]
+    #[case("This is a TEST", 13, "This is a TESt", 14)]
     #[case("This is a TEST", 10, "This is a test", 14)]
     #[case("", 0, "", 0)]
     #[case("THIS", 0, "this", 4)]
-    #[case("THIS", 4, "this", 4)]
+    #[case("THIS", 4, "THIS", 4)]
+    #[case("ÄÖÜ x", 0, "äöü x", 6)]
     fn lowercase_word_works(
         #[case] input: &str,
         #[case] in_location: usize,
@@ -1019,6 +1617,33 @@ mod test {
         line_buffer.assert_valid();
     }
 
+    #[rstest]
+    #[case("foo bar", 7, "bar foo", 7)]
+    #[case("foo bar", 3, "bar foo", 7)]
+    #[case("foo bar", 1, "bar foo", 7)]
+    #[case("foo bar", 0, "bar foo", 7)]
+    #[case("foo  bar baz", 5, "bar  foo baz", 8)]
+    #[case("foo bar baz  ", 13, "foo baz bar  ", 11)]
+    #[case("ä ö ü", 3, "ö ä ü", 5)]
+    #[case("foo", 3, "foo", 3)]
+    fn transpose_words_works(
+        #[case] input: &str,
+        #[case] in_location: usize,
+        #[case] output: &str,
+        #[case] out_location: usize,
+    ) {
+        let mut line_buffer = buffer_with(input);
+        line_buffer.set_insertion_point(in_location);
+
+        line_buffer.transpose_words();
+
+        let mut expected = buffer_with(output);
+        expected.set_insertion_point(out_location);
+
+        assert_eq!(line_buffer, expected);
+        line_buffer.assert_valid();
+    }
+
     #[rstest]
     #[case("This is a test", 8, "This is test a", 8)]
     #[case("This is a test", 0, "is This a test", 0)]
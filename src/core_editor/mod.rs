@@ -1,8 +1,17 @@
 mod clip_buffer;
 mod edit_stack;
 mod editor;
+mod kill_ring;
 mod line_buffer;
+mod registers;
 
-pub(crate) use clip_buffer::{get_default_clipboard, Clipboard, ClipboardMode};
+pub(crate) use clip_buffer::get_default_clipboard;
+#[cfg(feature = "system_clipboard")]
+pub use clip_buffer::SystemClipboard;
+pub use clip_buffer::{
+    Clipboard, ClipboardMode, LocalClipboard, Osc52Clipboard, DEFAULT_OSC52_MAX_SIZE,
+};
 pub use editor::Editor;
-pub use line_buffer::LineBuffer;
+pub(crate) use kill_ring::{KillDirection, KillRing};
+pub use line_buffer::{CharClass, LineBuffer};
+pub(crate) use registers::Registers;
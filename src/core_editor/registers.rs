@@ -0,0 +1,237 @@
+use super::{Clipboard, ClipboardMode};
+use std::collections::HashMap;
+
+/// Vi style registers used by the cut, copy and paste commands of the [`Editor`](super::Editor)
+///
+/// Without a selected register the unnamed register is used, which is backed by the
+/// clipboard. Cuts additionally shift the numbered registers `1` to `9`, copies are kept in
+/// register `0`. The clipboard register `+` reads and writes the clipboard directly.
+/// A copy of the clipboard content is kept and pasted while the clipboard fails.
+pub(crate) struct Registers {
+    clipboard: Box<dyn Clipboard>,
+    // content last written to the clipboard and whether writing it failed
+    local_copy: (String, ClipboardMode),
+    clipboard_failed: bool,
+    registers: HashMap<char, (String, ClipboardMode)>,
+    selected: Option<char>,
+    // text of the last cut into the unnamed register, for the kill ring
+    killed: Option<String>,
+}
+
+impl Registers {
+    pub fn new(clipboard: Box<dyn Clipboard>) -> Self {
+        Self {
+            clipboard,
+            local_copy: (String::new(), ClipboardMode::Normal),
+            clipboard_failed: false,
+            registers: HashMap::new(),
+            selected: None,
+            killed: None,
+        }
+    }
+
+    /// Replace the clipboard backing the unnamed register
+    pub fn set_clipboard(&mut self, clipboard: Box<dyn Clipboard>) {
+        self.clipboard = clipboard;
+        self.clipboard_failed = false;
+    }
+
+    /// Select the register used by the next cut, copy or paste
+    pub fn select(&mut self, register: char) {
+        self.selected = Some(register);
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selected = None;
+    }
+
+    /// Store cut text in the selected register or shift it into the numbered registers
+    pub fn set(&mut self, content: &str, mode: ClipboardMode) {
+        if !self.store_selected(content, mode) {
+            for register in ('1'..='8').rev() {
+                if let Some(value) = self.registers.remove(&register) {
+                    let next = char::from(register as u8 + 1);
+                    self.registers.insert(next, value);
+                }
+            }
+            self.registers.insert('1', (content.to_owned(), mode));
+            self.killed = Some(content.to_owned());
+        }
+        self.set_clipboard_content(content, mode);
+    }
+
+    /// Take the text cut into the unnamed register since the last call
+    pub fn take_killed(&mut self) -> Option<String> {
+        self.killed.take()
+    }
+
+    /// Replace the content of the latest cut, used when kills are merged
+    pub fn replace_killed(&mut self, content: &str) {
+        if let Some((text, mode)) = self.registers.get_mut(&'1') {
+            *text = content.to_owned();
+            let mode = *mode;
+            self.set_clipboard_content(content, mode);
+        }
+    }
+
+    /// Store copied text in the selected register or in register `0`
+    pub fn copy(&mut self, content: &str, mode: ClipboardMode) {
+        if !self.store_selected(content, mode) {
+            self.registers.insert('0', (content.to_owned(), mode));
+        }
+        self.set_clipboard_content(content, mode);
+    }
+
+    /// Get the content of the selected register, empty if nothing was stored in it
+    pub fn get(&mut self) -> (String, ClipboardMode) {
+        match self.selected.take() {
+            Some(register) if !is_clipboard(register) => {
+                self.registers.get(&register).cloned().unwrap_or_default()
+            }
+            _ => self.clipboard_content(),
+        }
+    }
+
+    /// Escape sequences the clipboard wants written to the terminal
+    pub fn take_terminal_output(&mut self) -> Option<String> {
+        self.clipboard.take_terminal_output()
+    }
+
+    /// Write to the clipboard, keeping the local copy in case it fails
+    fn set_clipboard_content(&mut self, content: &str, mode: ClipboardMode) {
+        self.local_copy = (content.to_owned(), mode);
+        self.clipboard_failed = self.clipboard.set(content, mode).is_err();
+    }
+
+    /// Read the clipboard, or the local copy if writing or reading it failed
+    fn clipboard_content(&mut self) -> (String, ClipboardMode) {
+        if !self.clipboard_failed {
+            if let Ok(content) = self.clipboard.get() {
+                return content;
+            }
+        }
+        self.local_copy.clone()
+    }
+
+    /// Returns `false` if no named register is selected
+    fn store_selected(&mut self, content: &str, mode: ClipboardMode) -> bool {
+        match self.selected.take() {
+            Some(register) if is_clipboard(register) => false,
+            // Uppercase registers append to their lowercase counterpart
+            Some(register) if register.is_ascii_uppercase() => {
+                let (text, _) = self
+                    .registers
+                    .entry(register.to_ascii_lowercase())
+                    .or_default();
+                text.push_str(content);
+                true
+            }
+            Some(register) => {
+                self.registers.insert(register, (content.to_owned(), mode));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn is_clipboard(register: char) -> bool {
+    matches!(register, '"' | '+')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core_editor::clip_buffer::LocalClipboard;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use std::io;
+
+    fn registers() -> Registers {
+        Registers::new(Box::new(LocalClipboard::new()))
+    }
+
+    #[test]
+    fn named_registers_keep_their_content() {
+        let mut registers = registers();
+
+        registers.select('a');
+        registers.copy("foo", ClipboardMode::Lines);
+        registers.set("bar", ClipboardMode::Normal);
+
+        registers.select('a');
+        assert_eq!(registers.get().0, "foo");
+        assert_eq!(registers.get().0, "bar");
+        registers.select('b');
+        assert_eq!(registers.get().0, "");
+    }
+
+    #[test]
+    fn uppercase_register_appends() {
+        let mut registers = registers();
+
+        registers.select('a');
+        registers.copy("foo", ClipboardMode::Normal);
+        registers.select('A');
+        registers.copy("bar", ClipboardMode::Normal);
+
+        registers.select('a');
+        assert_eq!(registers.get().0, "foobar");
+    }
+
+    #[test]
+    fn cuts_shift_numbered_registers() {
+        let mut registers = registers();
+
+        registers.copy("yanked", ClipboardMode::Normal);
+        for i in 1..=10 {
+            registers.set(&i.to_string(), ClipboardMode::Normal);
+        }
+
+        for (register, expected) in [('0', "yanked"), ('1', "10"), ('2', "9"), ('9', "2")] {
+            registers.select(register);
+            assert_eq!(registers.get().0, expected);
+        }
+        registers.select('+');
+        assert_eq!(registers.get().0, "10");
+    }
+
+    // Stores the content, failing on every write or on every read
+    struct FailingClipboard {
+        content: String,
+        fail_set: bool,
+    }
+
+    impl Clipboard for FailingClipboard {
+        fn set(&mut self, content: &str, _mode: ClipboardMode) -> io::Result<()> {
+            if self.fail_set {
+                return Err(io::Error::new(io::ErrorKind::Other, "set failed"));
+            }
+            self.content = content.to_owned();
+            Ok(())
+        }
+
+        fn get(&mut self) -> io::Result<(String, ClipboardMode)> {
+            if self.fail_set {
+                Ok((self.content.clone(), ClipboardMode::Normal))
+            } else {
+                Err(io::Error::new(io::ErrorKind::Other, "get failed"))
+            }
+        }
+    }
+
+    #[rstest]
+    #[case::failing_set(true)]
+    #[case::failing_get(false)]
+    fn failing_clipboard_falls_back_to_the_local_copy(#[case] fail_set: bool) {
+        let mut registers = Registers::new(Box::new(FailingClipboard {
+            content: "stale".into(),
+            fail_set,
+        }));
+
+        registers.set("cut", ClipboardMode::Lines);
+        let (content, mode) = registers.get();
+        assert_eq!(content, "cut");
+        assert!(matches!(mode, ClipboardMode::Lines));
+    }
+}
@@ -1,3 +1,6 @@
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use std::time::Duration;
+
 use crate::{
     enums::{ReedlineEvent, ReedlineRawEvent},
     PromptEditMode,
@@ -13,4 +16,29 @@ pub trait EditMode: Send {
 
     /// What to display in the prompt indicator
     fn edit_mode(&self) -> PromptEditMode;
+
+    /// Time left until the input held back by the edit mode expires, e.g. the
+    /// beginning of a key sequence. The engine calls [`EditMode::handle_timeout`]
+    /// once it has passed without new input
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Resolves the input held back by the edit mode once its timeout has expired
+    fn handle_timeout(&mut self) -> ReedlineEvent {
+        ReedlineEvent::None
+    }
+}
+
+/// Translates the mouse events reported with mouse capture. Clicks select the menu
+/// values and the scroll wheel scrolls the menu by pages
+pub(crate) fn parse_mouse_event(event: MouseEvent) -> ReedlineEvent {
+    match event.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            ReedlineEvent::MenuClick(event.column, event.row)
+        }
+        MouseEventKind::ScrollDown => ReedlineEvent::MenuPageNext,
+        MouseEventKind::ScrollUp => ReedlineEvent::MenuPagePrevious,
+        _ => ReedlineEvent::Mouse,
+    }
 }
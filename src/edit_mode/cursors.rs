@@ -1,6 +1,7 @@
+use crate::{PromptEditMode, PromptViMode};
 use crossterm::cursor::SetCursorStyle;
 
-/// Maps cursor shapes to each edit mode (emacs, vi normal & vi insert).
+/// Maps cursor shapes to each edit mode (emacs, vi normal, vi insert & vi visual).
 /// If any of the fields is `None`, the cursor won't get changed by Reedline for that mode.
 #[derive(Default)]
 pub struct CursorConfig {
@@ -8,6 +9,77 @@ pub struct CursorConfig {
     pub vi_insert: Option<SetCursorStyle>,
     /// The cursor to be used when in vi normal mode
     pub vi_normal: Option<SetCursorStyle>,
+    /// The cursor to be used when in vi visual mode, the vi normal one if `None`
+    pub vi_visual: Option<SetCursorStyle>,
     /// The cursor to be used when in emacs mode
     pub emacs: Option<SetCursorStyle>,
 }
+
+impl CursorConfig {
+    /// The cursor shape configured for `mode`, if any
+    pub(crate) fn shape(&self, mode: &PromptEditMode) -> Option<SetCursorStyle> {
+        match mode {
+            PromptEditMode::Emacs => self.emacs,
+            PromptEditMode::Vi(PromptViMode::Insert) => self.vi_insert,
+            PromptEditMode::Vi(PromptViMode::Normal) => self.vi_normal,
+            PromptEditMode::Vi(PromptViMode::Visual) => self.vi_visual.or(self.vi_normal),
+            PromptEditMode::Default | PromptEditMode::Custom(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::Command;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn escape(shape: Option<SetCursorStyle>) -> Option<String> {
+        shape.map(|shape| {
+            let mut escape = String::new();
+            shape.write_ansi(&mut escape).unwrap();
+            escape
+        })
+    }
+
+    #[rstest]
+    #[case(PromptEditMode::Emacs, Some("\x1b[6 q"))]
+    #[case(PromptEditMode::Vi(PromptViMode::Insert), Some("\x1b[5 q"))]
+    #[case(PromptEditMode::Vi(PromptViMode::Normal), Some("\x1b[2 q"))]
+    #[case(PromptEditMode::Vi(PromptViMode::Visual), Some("\x1b[3 q"))]
+    #[case(PromptEditMode::Default, None)]
+    #[case(PromptEditMode::Custom("custom".into()), None)]
+    fn shape_follows_the_edit_mode(#[case] mode: PromptEditMode, #[case] expected: Option<&str>) {
+        let config = CursorConfig {
+            vi_insert: Some(SetCursorStyle::BlinkingBar),
+            vi_normal: Some(SetCursorStyle::SteadyBlock),
+            emacs: Some(SetCursorStyle::SteadyBar),
+            vi_visual: Some(SetCursorStyle::BlinkingUnderScore),
+        };
+
+        assert_eq!(escape(config.shape(&mode)).as_deref(), expected);
+    }
+
+    #[test]
+    fn unset_shapes_are_left_alone() {
+        let config = CursorConfig {
+            vi_normal: Some(SetCursorStyle::SteadyUnderScore),
+            ..CursorConfig::default()
+        };
+
+        assert_eq!(
+            escape(config.shape(&PromptEditMode::Vi(PromptViMode::Normal))).as_deref(),
+            Some("\x1b[4 q")
+        );
+        assert_eq!(
+            escape(config.shape(&PromptEditMode::Vi(PromptViMode::Insert))),
+            None
+        );
+        // The visual mode falls back to the normal mode cursor
+        assert_eq!(
+            escape(config.shape(&PromptEditMode::Vi(PromptViMode::Visual))).as_deref(),
+            Some("\x1b[4 q")
+        );
+    }
+}
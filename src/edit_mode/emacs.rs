@@ -2,16 +2,25 @@ use crate::{
     edit_mode::{
         keybindings::{
             add_common_control_bindings, add_common_edit_bindings, add_common_navigation_bindings,
-            edit_bind, Keybindings,
+            combine_events, edit_bind, key_combination, KeyCombination, KeySequenceFallback,
+            KeySequenceState, Keybindings, SequenceOutput,
         },
-        EditMode,
+        parse_mouse_event, EditMode,
     },
     enums::{EditCommand, ReedlineEvent, ReedlineRawEvent},
     PromptEditMode,
 };
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use std::time::Duration;
 
 /// Returns the current default emacs keybindings
+///
+/// `Ctrl+U` and `Ctrl+K` clear to the start and to the end of the logical line under
+/// the cursor ([`EditCommand::ClearToLineStart`] and [`EditCommand::ClearToLineEnd`]),
+/// so in a multiline buffer the other lines are kept. The cleared text goes to the
+/// cut buffer and `Ctrl+Y` pastes it back, `Alt+Y` right after replaces the pasted text
+/// with older kills.
+/// `Ctrl+X Ctrl+E` opens the buffer in the external editor.
 pub fn default_emacs_keybindings() -> Keybindings {
     use EditCommand as EC;
     use KeyCode as KC;
@@ -50,9 +59,9 @@ pub fn default_emacs_keybindings() -> Keybindings {
         KC::Char('y'),
         edit_bind(EC::PasteCutBufferBefore),
     );
-    kb.add_binding(KM::CONTROL, KC::Char('w'), edit_bind(EC::CutWordLeft));
-    kb.add_binding(KM::CONTROL, KC::Char('k'), edit_bind(EC::CutToEnd));
-    kb.add_binding(KM::CONTROL, KC::Char('u'), edit_bind(EC::CutFromStart));
+    kb.add_binding(KM::CONTROL, KC::Char('w'), edit_bind(EC::CutBigWordLeft));
+    kb.add_binding(KM::CONTROL, KC::Char('k'), edit_bind(EC::ClearToLineEnd));
+    kb.add_binding(KM::CONTROL, KC::Char('u'), edit_bind(EC::ClearToLineStart));
     // Edits
     kb.add_binding(KM::CONTROL, KC::Char('t'), edit_bind(EC::SwapGraphemes));
 
@@ -86,10 +95,27 @@ pub fn default_emacs_keybindings() -> Keybindings {
     );
     // Cutting
     kb.add_binding(KM::ALT, KC::Char('d'), edit_bind(EC::CutWordRight));
+    kb.add_binding(KM::ALT, KC::Char('y'), edit_bind(EC::YankPop));
     // Case changes
     kb.add_binding(KM::ALT, KC::Char('u'), edit_bind(EC::UppercaseWord));
     kb.add_binding(KM::ALT, KC::Char('l'), edit_bind(EC::LowercaseWord));
-    kb.add_binding(KM::ALT, KC::Char('c'), edit_bind(EC::CapitalizeChar));
+    kb.add_binding(KM::ALT, KC::Char('c'), edit_bind(EC::CapitalizeWord));
+    kb.add_binding(KM::ALT, KC::Char('t'), edit_bind(EC::TransposeWords));
+
+    // *** SEQUENCES ***
+    kb.add_sequence_binding(
+        vec![
+            KeyCombination {
+                modifier: KM::CONTROL,
+                key_code: KC::Char('x'),
+            },
+            KeyCombination {
+                modifier: KM::CONTROL,
+                key_code: KC::Char('e'),
+            },
+        ],
+        ReedlineEvent::OpenEditor,
+    );
 
     kb
 }
@@ -97,13 +123,13 @@ pub fn default_emacs_keybindings() -> Keybindings {
 /// This parses the incoming Events like a emacs style-editor
 pub struct Emacs {
     keybindings: Keybindings,
+    // keys that start a bound key sequence, held back until it is complete
+    key_sequence: KeySequenceState,
 }
 
 impl Default for Emacs {
     fn default() -> Self {
-        Emacs {
-            keybindings: default_emacs_keybindings(),
-        }
+        Emacs::new(default_emacs_keybindings())
     }
 }
 
@@ -112,51 +138,9 @@ impl EditMode for Emacs {
         match event.into() {
             Event::Key(KeyEvent {
                 code, modifiers, ..
-            }) => match (modifiers, code) {
-                (modifier, KeyCode::Char(c)) => {
-                    // Note. The modifier can also be a combination of modifiers, for
-                    // example:
-                    //     KeyModifiers::CONTROL | KeyModifiers::ALT
-                    //     KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT
-                    //
-                    // Mixed modifiers are used by non american keyboards that have extra
-                    // keys like 'alt gr'. Keep this in mind if in the future there are
-                    // cases where an event is not being captured
-                    let c = match modifier {
-                        KeyModifiers::NONE => c,
-                        _ => c.to_ascii_lowercase(),
-                    };
-
-                    self.keybindings
-                        .find_binding(modifier, KeyCode::Char(c))
-                        .unwrap_or_else(|| {
-                            if modifier == KeyModifiers::NONE
-                                || modifier == KeyModifiers::SHIFT
-                                || modifier == KeyModifiers::CONTROL | KeyModifiers::ALT
-                                || modifier
-                                    == KeyModifiers::CONTROL
-                                        | KeyModifiers::ALT
-                                        | KeyModifiers::SHIFT
-                            {
-                                ReedlineEvent::Edit(vec![EditCommand::InsertChar(
-                                    if modifier == KeyModifiers::SHIFT {
-                                        c.to_ascii_uppercase()
-                                    } else {
-                                        c
-                                    },
-                                )])
-                            } else {
-                                ReedlineEvent::None
-                            }
-                        })
-                }
-                _ => self
-                    .keybindings
-                    .find_binding(modifiers, code)
-                    .unwrap_or(ReedlineEvent::None),
-            },
+            }) => self.parse_key_sequence(key_combination(modifiers, code)),
 
-            Event::Mouse(_) => ReedlineEvent::Mouse,
+            Event::Mouse(event) => parse_mouse_event(event),
             Event::Resize(width, height) => ReedlineEvent::Resize(width, height),
             Event::FocusGained => ReedlineEvent::None,
             Event::FocusLost => ReedlineEvent::None,
@@ -169,19 +153,143 @@ impl EditMode for Emacs {
     fn edit_mode(&self) -> PromptEditMode {
         PromptEditMode::Emacs
     }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.key_sequence.time_left()
+    }
+
+    fn handle_timeout(&mut self) -> ReedlineEvent {
+        let outputs = self.key_sequence.handle_timeout();
+        self.resolve(outputs)
+    }
 }
 
 impl Emacs {
     /// Emacs style input parsing constructor if you want to use custom keybindings
     pub const fn new(keybindings: Keybindings) -> Self {
-        Emacs { keybindings }
+        Emacs {
+            keybindings,
+            key_sequence: KeySequenceState::new(),
+        }
+    }
+
+    /// Emacs builder that abandons an incomplete key sequence after `timeout`
+    ///
+    /// Without a timeout (default) the keys of a sequence wait for the next key.
+    /// The `fallback` decides what happens to the held back keys of an abandoned sequence.
+    #[must_use]
+    pub fn with_sequence_timeout(
+        mut self,
+        timeout: Option<Duration>,
+        fallback: KeySequenceFallback,
+    ) -> Self {
+        self.key_sequence.set_timeout(timeout, fallback);
+        self
+    }
+
+    /// The keybindings used to parse the input
+    pub fn keybindings(&self) -> &Keybindings {
+        &self.keybindings
+    }
+
+    fn parse_key_sequence(&mut self, key: KeyCombination) -> ReedlineEvent {
+        let outputs = self.key_sequence.push(&self.keybindings, key);
+        self.resolve(outputs)
+    }
+
+    fn resolve(&self, outputs: Vec<SequenceOutput>) -> ReedlineEvent {
+        combine_events(
+            outputs
+                .into_iter()
+                .map(|output| match output {
+                    SequenceOutput::Event(event) => event,
+                    SequenceOutput::Key(key) => self.parse_key(key),
+                })
+                .collect(),
+        )
+    }
+
+    fn parse_key(&self, key: KeyCombination) -> ReedlineEvent {
+        let KeyCombination {
+            modifier,
+            key_code: code,
+        } = key;
+        match code {
+            KeyCode::Char(c) => self
+                .keybindings
+                .find_binding(modifier, KeyCode::Char(c))
+                .unwrap_or_else(|| {
+                    if modifier == KeyModifiers::NONE
+                        || modifier == KeyModifiers::SHIFT
+                        || modifier == KeyModifiers::CONTROL | KeyModifiers::ALT
+                        || modifier
+                            == KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT
+                    {
+                        ReedlineEvent::Edit(vec![EditCommand::InsertChar(
+                            if modifier == KeyModifiers::SHIFT {
+                                c.to_ascii_uppercase()
+                            } else {
+                                c
+                            },
+                        )])
+                    } else {
+                        ReedlineEvent::None
+                    }
+                }),
+            _ => self
+                .keybindings
+                .find_binding(modifier, code)
+                .unwrap_or(ReedlineEvent::None),
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{core_editor::Editor, UndoBehavior};
+    use crossterm::event::{MouseButton, MouseEventKind};
     use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[test]
+    fn bracketed_paste_inserts_lines_at_once() {
+        let mut emacs = Emacs::default();
+        let paste =
+            ReedlineRawEvent::convert_from(Event::Paste("ls\r\ncd /\rpwd".to_string())).unwrap();
+        let result = emacs.parse_event(paste);
+
+        assert_eq!(
+            result,
+            ReedlineEvent::Edit(vec![EditCommand::InsertString("ls\ncd /\npwd".to_string())])
+        );
+    }
+
+    #[rstest]
+    #[case(
+        MouseEventKind::Down(MouseButton::Left),
+        ReedlineEvent::MenuClick(4, 2)
+    )]
+    #[case(MouseEventKind::ScrollDown, ReedlineEvent::MenuPageNext)]
+    #[case(MouseEventKind::ScrollUp, ReedlineEvent::MenuPagePrevious)]
+    #[case(MouseEventKind::Moved, ReedlineEvent::Mouse)]
+    fn mouse_events_control_the_menu(
+        #[case] kind: MouseEventKind,
+        #[case] expected: ReedlineEvent,
+    ) {
+        use crossterm::event::MouseEvent;
+
+        let mut emacs = Emacs::default();
+        let mouse = ReedlineRawEvent::convert_from(Event::Mouse(MouseEvent {
+            kind,
+            column: 4,
+            row: 2,
+            modifiers: KeyModifiers::NONE,
+        }))
+        .unwrap();
+
+        assert_eq!(emacs.parse_event(mouse), expected);
+    }
 
     #[test]
     fn ctrl_l_leads_to_clear_screen_event() {
@@ -196,6 +304,23 @@ mod test {
         assert_eq!(result, ReedlineEvent::ClearScreen);
     }
 
+    #[test]
+    fn tab_moves_between_snippet_placeholders() {
+        let mut emacs = Emacs::default();
+        for (modifiers, code, command) in [
+            (KeyModifiers::NONE, KeyCode::Tab, EditCommand::NextTabStop),
+            (
+                KeyModifiers::SHIFT,
+                KeyCode::BackTab,
+                EditCommand::PrevTabStop,
+            ),
+        ] {
+            let event =
+                ReedlineRawEvent::convert_from(Event::Key(KeyEvent::new(code, modifiers))).unwrap();
+            assert_eq!(emacs.parse_event(event), ReedlineEvent::Edit(vec![command]));
+        }
+    }
+
     #[test]
     fn overriding_default_keybindings_works() {
         let mut keybindings = default_emacs_keybindings();
@@ -280,4 +405,135 @@ mod test {
             ReedlineEvent::Edit(vec![EditCommand::InsertChar('😀')])
         );
     }
+
+    fn key(modifier: KeyModifiers, c: char) -> ReedlineRawEvent {
+        ReedlineRawEvent::convert_from(Event::Key(KeyEvent::new(KeyCode::Char(c), modifier)))
+            .unwrap()
+    }
+
+    #[test]
+    fn key_sequence_fires_only_when_complete() {
+        let mut emacs = Emacs::default();
+
+        assert_eq!(
+            emacs.parse_event(key(KeyModifiers::CONTROL, 'x')),
+            ReedlineEvent::None
+        );
+        assert_eq!(
+            emacs.parse_event(key(KeyModifiers::CONTROL, 'e')),
+            ReedlineEvent::OpenEditor
+        );
+        // The sequence is reset after it fired
+        assert_eq!(
+            emacs.parse_event(key(KeyModifiers::CONTROL, 'e')),
+            ReedlineEvent::UntilFound(vec![
+                ReedlineEvent::HistoryHintComplete,
+                ReedlineEvent::Edit(vec![EditCommand::MoveToLineEnd]),
+            ])
+        );
+    }
+
+    #[test]
+    fn keybindings_can_be_listed() {
+        let emacs = Emacs::default();
+
+        assert!(emacs.keybindings().iter().any(|binding| binding
+            == (
+                KeyModifiers::CONTROL,
+                KeyCode::Char('c'),
+                &ReedlineEvent::CtrlC
+            )));
+        let sequences = emacs.keybindings().iter_sequences().collect::<Vec<_>>();
+        assert_eq!(sequences.len(), 1);
+        assert_eq!(sequences[0].0.len(), 2);
+        assert_eq!(sequences[0].1, &ReedlineEvent::OpenEditor);
+    }
+
+    #[test]
+    fn broken_key_sequence_handles_the_keys_literally() {
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(
+            KeyModifiers::CONTROL,
+            KeyCode::Char('x'),
+            ReedlineEvent::Esc,
+        );
+        let mut emacs = Emacs::new(keybindings);
+
+        assert_eq!(
+            emacs.parse_event(key(KeyModifiers::CONTROL, 'x')),
+            ReedlineEvent::None
+        );
+        assert_eq!(
+            emacs.parse_event(key(KeyModifiers::NONE, 'a')),
+            ReedlineEvent::Multiple(vec![
+                ReedlineEvent::Esc,
+                ReedlineEvent::Edit(vec![EditCommand::InsertChar('a')]),
+            ])
+        );
+    }
+
+    #[test]
+    fn broken_key_sequence_can_discard_the_keys() {
+        let mut emacs = Emacs::default().with_sequence_timeout(None, KeySequenceFallback::Discard);
+
+        emacs.parse_event(key(KeyModifiers::CONTROL, 'x'));
+        assert_eq!(
+            emacs.parse_event(key(KeyModifiers::NONE, 'a')),
+            ReedlineEvent::Edit(vec![EditCommand::InsertChar('a')])
+        );
+    }
+
+    #[test]
+    fn key_sequence_timeout_abandons_the_sequence() {
+        let mut emacs = Emacs::default()
+            .with_sequence_timeout(Some(Duration::ZERO), KeySequenceFallback::Literal);
+        assert_eq!(emacs.timeout(), None);
+
+        emacs.parse_event(key(KeyModifiers::NONE, 'a'));
+        assert_eq!(emacs.timeout(), None);
+
+        emacs.parse_event(key(KeyModifiers::CONTROL, 'x'));
+        assert_eq!(emacs.timeout(), Some(Duration::ZERO));
+        // `Ctrl-X` is not bound on its own
+        assert_eq!(emacs.handle_timeout(), ReedlineEvent::None);
+        assert_eq!(emacs.timeout(), None);
+
+        emacs.parse_event(key(KeyModifiers::CONTROL, 'x'));
+        assert_eq!(
+            emacs.parse_event(key(KeyModifiers::CONTROL, 'e')),
+            ReedlineEvent::UntilFound(vec![
+                ReedlineEvent::HistoryHintComplete,
+                ReedlineEvent::Edit(vec![EditCommand::MoveToLineEnd]),
+            ])
+        );
+    }
+
+    #[rstest]
+    #[case(KeyModifiers::CONTROL, KeyCode::Char('w'), "cd ")]
+    #[case(KeyModifiers::ALT, KeyCode::Backspace, "cd /usr/local/")]
+    #[case(KeyModifiers::CONTROL, KeyCode::Backspace, "cd /usr/local/")]
+    fn word_deletion_boundaries(
+        #[case] modifiers: KeyModifiers,
+        #[case] code: KeyCode,
+        #[case] expected: &str,
+    ) {
+        let mut emacs = Emacs::default();
+        let event =
+            ReedlineRawEvent::convert_from(Event::Key(KeyEvent::new(code, modifiers))).unwrap();
+        let commands = match emacs.parse_event(event) {
+            ReedlineEvent::Edit(commands) => commands,
+            other => panic!("expected an edit event, got {other:?}"),
+        };
+
+        let mut editor = Editor::default();
+        editor.set_buffer(
+            "cd /usr/local/bin".to_string(),
+            UndoBehavior::CreateUndoPoint,
+        );
+        for command in &commands {
+            editor.run_edit_command(command);
+        }
+
+        assert_eq!(editor.get_buffer(), expected);
+    }
 }
@@ -2,20 +2,41 @@ use {
     crate::{enums::ReedlineEvent, EditCommand},
     crossterm::event::{KeyCode, KeyModifiers},
     serde::{Deserialize, Serialize},
-    std::collections::HashMap,
+    std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    },
 };
 
+/// A key together with the modifiers held while pressing it
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
 pub struct KeyCombination {
+    /// The modifiers, e.g. `Ctrl`
     pub modifier: KeyModifiers,
+    /// The pressed key
     pub key_code: KeyCode,
 }
 
+/// What happens to the keys of an incomplete key sequence once it is abandoned
+///
+/// A sequence is abandoned when its timeout expires or when the next key does not continue it.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum KeySequenceFallback {
+    /// Handle each held back key on its own with the single key bindings (default)
+    #[default]
+    Literal,
+    /// Drop the held back keys
+    Discard,
+}
+
 /// Main definition of editor keybindings
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Keybindings {
     /// Defines a keybinding for a reedline event
     pub bindings: HashMap<KeyCombination, ReedlineEvent>,
+    /// Defines a keybinding for a sequence of keys, e.g. `Ctrl-X Ctrl-E`
+    #[serde(default)]
+    pub sequence_bindings: HashMap<Vec<KeyCombination>, ReedlineEvent>,
 }
 
 impl Default for Keybindings {
@@ -29,6 +50,7 @@ impl Keybindings {
     pub fn new() -> Self {
         Self {
             bindings: HashMap::new(),
+            sequence_bindings: HashMap::new(),
         }
     }
 
@@ -81,6 +103,205 @@ impl Keybindings {
     pub fn get_keybindings(&self) -> &HashMap<KeyCombination, ReedlineEvent> {
         &self.bindings
     }
+
+    /// Iterate over the single key bindings, e.g. to list them in a help screen
+    ///
+    /// The bindings are returned in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (KeyModifiers, KeyCode, &ReedlineEvent)> {
+        self.bindings
+            .iter()
+            .map(|(combination, event)| (combination.modifier, combination.key_code, event))
+    }
+
+    /// Adds a keybinding for a sequence of keys, e.g. `Ctrl-X Ctrl-E`
+    ///
+    /// Only the complete sequence fires the `command`. The keys that start a sequence
+    /// are held back until the sequence is complete or abandoned.
+    /// Sequences are recognized by [`Emacs`](crate::Emacs) and in the insert mode of
+    /// [`Vi`](crate::Vi).
+    ///
+    /// # Panics
+    ///
+    /// If `sequence` is empty or `command` is an empty [`ReedlineEvent::UntilFound`]
+    pub fn add_sequence_binding(&mut self, sequence: Vec<KeyCombination>, command: ReedlineEvent) {
+        assert!(
+            !sequence.is_empty(),
+            "A key sequence needs at least one key"
+        );
+        if let ReedlineEvent::UntilFound(subcommands) = &command {
+            assert!(
+                !subcommands.is_empty(),
+                "UntilFound should contain a series of potential events to handle"
+            );
+        }
+
+        self.sequence_bindings.insert(sequence, command);
+    }
+
+    /// Find the keybinding of a complete key sequence
+    pub fn find_sequence_binding(&self, sequence: &[KeyCombination]) -> Option<ReedlineEvent> {
+        self.sequence_bindings.get(sequence).cloned()
+    }
+
+    /// Whether `keys` are the beginning of a longer bound key sequence
+    pub fn is_sequence_prefix(&self, keys: &[KeyCombination]) -> bool {
+        self.sequence_bindings
+            .keys()
+            .any(|sequence| sequence.len() > keys.len() && sequence.starts_with(keys))
+    }
+
+    /// Remove the keybinding of a key sequence
+    ///
+    /// Returns `Some(ReedlineEvent)` if the key sequence was previously bound to a particular [`ReedlineEvent`]
+    pub fn remove_sequence_binding(
+        &mut self,
+        sequence: &[KeyCombination],
+    ) -> Option<ReedlineEvent> {
+        self.sequence_bindings.remove(sequence)
+    }
+
+    /// Get assigned key sequence bindings
+    pub fn get_sequence_bindings(&self) -> &HashMap<Vec<KeyCombination>, ReedlineEvent> {
+        &self.sequence_bindings
+    }
+
+    /// Iterate over the key sequence bindings with their full sequence of keys
+    ///
+    /// The bindings are returned in no particular order.
+    pub fn iter_sequences(&self) -> impl Iterator<Item = (&[KeyCombination], &ReedlineEvent)> {
+        self.sequence_bindings
+            .iter()
+            .map(|(sequence, event)| (sequence.as_slice(), event))
+    }
+}
+
+/// A key handed on by a [`KeySequenceState`]
+pub(crate) enum SequenceOutput {
+    /// The event bound to a completed key sequence
+    Event(ReedlineEvent),
+    /// A key that is not part of a sequence, parsed on its own by the edit mode
+    Key(KeyCombination),
+}
+
+/// The keys held back while they start a bound key sequence
+pub(crate) struct KeySequenceState {
+    pending_keys: Vec<KeyCombination>,
+    pending_since: Option<Instant>,
+    timeout: Option<Duration>,
+    fallback: KeySequenceFallback,
+}
+
+impl KeySequenceState {
+    pub(crate) const fn new() -> Self {
+        Self {
+            pending_keys: Vec::new(),
+            pending_since: None,
+            timeout: None,
+            fallback: KeySequenceFallback::Literal,
+        }
+    }
+
+    /// Abandon an incomplete sequence after `timeout`, the `fallback` decides what
+    /// happens to its keys
+    pub(crate) fn set_timeout(&mut self, timeout: Option<Duration>, fallback: KeySequenceFallback) {
+        self.timeout = timeout;
+        self.fallback = fallback;
+    }
+
+    /// Time left until the incomplete sequence is abandoned
+    pub(crate) fn time_left(&self) -> Option<Duration> {
+        let timeout = self.timeout?;
+        let since = self.pending_since?;
+        Some(timeout.saturating_sub(since.elapsed()))
+    }
+
+    fn is_expired(&self) -> bool {
+        match (self.timeout, self.pending_since) {
+            (Some(timeout), Some(since)) => since.elapsed() >= timeout,
+            _ => false,
+        }
+    }
+
+    /// Abandons the sequence if its timeout has expired
+    pub(crate) fn handle_timeout(&mut self) -> Vec<SequenceOutput> {
+        if self.is_expired() {
+            self.abandon()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Resolves the keys of an incomplete key sequence according to the fallback
+    fn abandon(&mut self) -> Vec<SequenceOutput> {
+        self.pending_since = None;
+        let keys = std::mem::take(&mut self.pending_keys);
+        match self.fallback {
+            KeySequenceFallback::Literal => keys.into_iter().map(SequenceOutput::Key).collect(),
+            KeySequenceFallback::Discard => Vec::new(),
+        }
+    }
+
+    /// Holds the key back while it continues a sequence of the `keybindings`
+    pub(crate) fn push(
+        &mut self,
+        keybindings: &Keybindings,
+        key: KeyCombination,
+    ) -> Vec<SequenceOutput> {
+        let mut outputs = self.handle_timeout();
+
+        self.pending_keys.push(key);
+        if let Some(event) = keybindings.find_sequence_binding(&self.pending_keys) {
+            self.pending_keys.clear();
+            self.pending_since = None;
+            outputs.push(SequenceOutput::Event(event));
+        } else if keybindings.is_sequence_prefix(&self.pending_keys) {
+            if self.pending_since.is_none() {
+                self.pending_since = Some(Instant::now());
+            }
+        } else {
+            let key = self.pending_keys.pop().expect("the key was just pushed");
+            if !self.pending_keys.is_empty() {
+                outputs.extend(self.abandon());
+            }
+            // The key that broke the sequence may start a new one
+            if keybindings.is_sequence_prefix(std::slice::from_ref(&key)) {
+                self.pending_keys.push(key);
+                self.pending_since = Some(Instant::now());
+            } else {
+                outputs.push(SequenceOutput::Key(key));
+            }
+        }
+
+        outputs
+    }
+}
+
+/// The key combination a key event is bound with
+pub(crate) fn key_combination(modifier: KeyModifiers, code: KeyCode) -> KeyCombination {
+    // Note. The modifier can also be a combination of modifiers, for
+    // example:
+    //     KeyModifiers::CONTROL | KeyModifiers::ALT
+    //     KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT
+    //
+    // Mixed modifiers are used by non american keyboards that have extra
+    // keys like 'alt gr'. Keep this in mind if in the future there are
+    // cases where an event is not being captured
+    let key_code = match (modifier, code) {
+        (KeyModifiers::NONE, code) => code,
+        (_, KeyCode::Char(c)) => KeyCode::Char(c.to_ascii_lowercase()),
+        (_, code) => code,
+    };
+    KeyCombination { modifier, key_code }
+}
+
+/// Combines the events of several keys into one
+pub(crate) fn combine_events(mut events: Vec<ReedlineEvent>) -> ReedlineEvent {
+    events.retain(|event| *event != ReedlineEvent::None);
+    match events.len() {
+        0 => ReedlineEvent::None,
+        1 => events.remove(0),
+        _ => ReedlineEvent::Multiple(events),
+    }
 }
 
 pub fn edit_bind(command: EditCommand) -> ReedlineEvent {
@@ -103,6 +324,23 @@ pub fn add_common_control_bindings(kb: &mut Keybindings) {
     kb.add_binding(KM::CONTROL, KC::Char('r'), ReedlineEvent::SearchHistory);
     kb.add_binding(KM::CONTROL, KC::Char('o'), ReedlineEvent::OpenEditor);
 }
+/// Returns the default keybindings of the history search
+///
+/// They take precedence over the keybindings of the edit mode while the search is
+/// active:
+/// `Ctrl-R` steps to older matches, `Ctrl-S` to newer ones and `Ctrl-G` cancels the
+/// search like `Esc`
+pub fn default_history_search_keybindings() -> Keybindings {
+    use KeyCode as KC;
+    use KeyModifiers as KM;
+
+    let mut kb = Keybindings::new();
+    kb.add_binding(KM::CONTROL, KC::Char('r'), ReedlineEvent::SearchHistory);
+    kb.add_binding(KM::CONTROL, KC::Char('s'), ReedlineEvent::NextHistory);
+    kb.add_binding(KM::CONTROL, KC::Char('g'), ReedlineEvent::Esc);
+    kb
+}
+
 /// Add the arrow navigation and its `Ctrl` variants
 pub fn add_common_navigation_bindings(kb: &mut Keybindings) {
     use EditCommand as EC;
@@ -120,6 +358,9 @@ pub fn add_common_navigation_bindings(kb: &mut Keybindings) {
         KC::Down,
         ReedlineEvent::UntilFound(vec![ReedlineEvent::MenuDown, ReedlineEvent::Down]),
     );
+    // Alt Up and Down cycle through older history hints
+    kb.add_binding(KM::ALT, KC::Up, ReedlineEvent::HistoryHintNext);
+    kb.add_binding(KM::ALT, KC::Down, ReedlineEvent::HistoryHintPrevious);
     kb.add_binding(
         KM::NONE,
         KC::Left,
@@ -182,7 +423,12 @@ pub fn add_common_navigation_bindings(kb: &mut Keybindings) {
 
 /// Add basic functionality to edit
 ///
-/// `Delete`, `Backspace` and the basic variants do delete words
+/// `Delete`, `Backspace` and the basic variants do delete words.
+/// `Ctrl+W` deletes a whitespace delimited WORD while `Ctrl+Backspace` stops at
+/// word boundaries like punctuation or path separators.
+/// `Shift+Delete` deletes the entry selected in the history menu from the history.
+/// `Tab` and `Shift+Tab` move between the placeholders of an inserted snippet, a
+/// `Tab` binding that opens a menu should try [`EditCommand::NextTabStop`] first.
 pub fn add_common_edit_bindings(kb: &mut Keybindings) {
     use EditCommand as EC;
     use KeyCode as KC;
@@ -191,7 +437,16 @@ pub fn add_common_edit_bindings(kb: &mut Keybindings) {
     kb.add_binding(KM::NONE, KC::Delete, edit_bind(EC::Delete));
     kb.add_binding(KM::CONTROL, KC::Backspace, edit_bind(EC::BackspaceWord));
     kb.add_binding(KM::CONTROL, KC::Delete, edit_bind(EC::DeleteWord));
+    kb.add_binding(KM::SHIFT, KC::Delete, ReedlineEvent::MenuDelete);
+    kb.add_binding(KM::NONE, KC::Tab, edit_bind(EC::NextTabStop));
+    kb.add_binding(KM::SHIFT, KC::BackTab, edit_bind(EC::PrevTabStop));
     // Base commands should not affect cut buffer
     kb.add_binding(KM::CONTROL, KC::Char('h'), edit_bind(EC::Backspace));
-    kb.add_binding(KM::CONTROL, KC::Char('w'), edit_bind(EC::BackspaceWord));
+    kb.add_binding(KM::CONTROL, KC::Char('w'), edit_bind(EC::BackspaceBigWord));
+    // Inserts a space without expanding an abbreviation
+    kb.add_binding(
+        KM::CONTROL,
+        KC::Char(' '),
+        edit_bind(EC::InsertString(" ".to_string())),
+    );
 }
@@ -4,8 +4,11 @@ mod emacs;
 mod keybindings;
 mod vi;
 
+pub(crate) use base::parse_mouse_event;
 pub use base::EditMode;
 pub use cursors::CursorConfig;
 pub use emacs::{default_emacs_keybindings, Emacs};
-pub use keybindings::Keybindings;
+pub use keybindings::{
+    default_history_search_keybindings, KeyCombination, KeySequenceFallback, Keybindings,
+};
 pub use vi::{default_vi_insert_keybindings, default_vi_normal_keybindings, Vi};
@@ -11,6 +11,10 @@ where
             let _ = input.next();
             Some(Command::Delete)
         }
+        Some('y') => {
+            let _ = input.next();
+            Some(Command::Yank)
+        }
         Some('p') => {
             let _ = input.next();
             Some(Command::PasteAfter)
@@ -90,6 +94,7 @@ where
 pub enum Command {
     Incomplete,
     Delete,
+    Yank,
     DeleteChar,
     ReplaceChar(char),
     SubstituteCharWithInsert,
@@ -114,15 +119,56 @@ impl Command {
         match self {
             Command::Delete => Some('d'),
             Command::Change => Some('c'),
+            Command::Yank => Some('y'),
             _ => None,
         }
     }
 
     pub fn requires_motion(&self) -> bool {
-        matches!(self, Command::Delete | Command::Change)
+        matches!(self, Command::Delete | Command::Change | Command::Yank)
+    }
+
+    /// Whether the command modifies the buffer and is repeated by `.`
+    pub fn is_change(&self) -> bool {
+        !matches!(
+            self,
+            Command::Incomplete
+                | Command::Yank
+                | Command::Undo
+                | Command::HistorySearch
+                | Command::RepeatLastAction
+        )
+    }
+
+    /// Whether the command reads or writes a register selected with `"x`
+    pub fn uses_register(&self) -> bool {
+        matches!(
+            self,
+            Command::Delete
+                | Command::Yank
+                | Command::Change
+                | Command::DeleteChar
+                | Command::SubstituteCharWithInsert
+                | Command::PasteAfter
+                | Command::PasteBefore
+                | Command::ChangeToLineEnd
+                | Command::DeleteToEnd
+                | Command::RewriteCurrentLine
+        )
     }
 
-    pub fn to_reedline(&self, vi_state: &mut Vi) -> Vec<ReedlineOption> {
+    /// Whether a count repeats the inserted text (`3ifoo`) instead of the command itself (`3cw`)
+    pub fn repeats_insert(&self) -> bool {
+        matches!(
+            self,
+            Command::EnterViInsert
+                | Command::EnterViAppend
+                | Command::AppendToEnd
+                | Command::PrependToStart
+        )
+    }
+
+    pub fn to_reedline(&self) -> Vec<ReedlineOption> {
         match self {
             Self::EnterViInsert => vec![ReedlineOption::Event(ReedlineEvent::Repaint)],
             Self::EnterViAppend => vec![ReedlineOption::Edit(EditCommand::MoveRight)],
@@ -142,11 +188,11 @@ impl Command {
             Self::HistorySearch => vec![ReedlineOption::Event(ReedlineEvent::SearchHistory)],
             Self::Switchcase => vec![ReedlineOption::Edit(EditCommand::SwitchcaseChar)],
             // Mark a command as incomplete whenever a motion is required to finish the command
-            Self::Delete | Self::Change | Self::Incomplete => vec![ReedlineOption::Incomplete],
-            Command::RepeatLastAction => match &vi_state.previous {
-                Some(event) => vec![ReedlineOption::Event(event.clone())],
-                None => vec![],
-            },
+            Self::Delete | Self::Change | Self::Yank | Self::Incomplete => {
+                vec![ReedlineOption::Incomplete]
+            }
+            // Replayed from `Vi::last_change` by the parser
+            Self::RepeatLastAction => vec![],
         }
     }
 
@@ -192,8 +238,10 @@ impl Command {
                 Motion::Start => Some(vec![ReedlineOption::Edit(EditCommand::CutFromLineStart)]),
                 Motion::Left => Some(vec![ReedlineOption::Edit(EditCommand::Backspace)]),
                 Motion::Right => Some(vec![ReedlineOption::Edit(EditCommand::Delete)]),
-                Motion::Up => None,
-                Motion::Down => None,
+                Motion::Up | Motion::Down => None,
+                Motion::MatchingBracket => Some(vec![ReedlineOption::Edit(
+                    EditCommand::CutToMatchingBracket,
+                )]),
                 Motion::ReplayCharSearch => vi_state
                     .last_char_search
                     .as_ref()
@@ -202,6 +250,33 @@ impl Command {
                     .last_char_search
                     .as_ref()
                     .map(|char_search| vec![ReedlineOption::Edit(char_search.reverse().to_cut())]),
+                Motion::TextObject(text_object) => Some(vec![ReedlineOption::Edit(
+                    EditCommand::CutTextObject(*text_object),
+                )]),
+            },
+            // Only whole lines, words, the line before and after the cursor, text objects
+            // and brackets can be yanked so far
+            Self::Yank => match motion {
+                Motion::Line => Some(vec![ReedlineOption::Edit(EditCommand::CopyCurrentLine)]),
+                Motion::NextWord => {
+                    Some(vec![ReedlineOption::Edit(EditCommand::CopyWordRightToNext)])
+                }
+                Motion::NextBigWord => Some(vec![ReedlineOption::Edit(
+                    EditCommand::CopyBigWordRightToNext,
+                )]),
+                Motion::End => Some(vec![ReedlineOption::Edit(EditCommand::CopyToLineEnd)]),
+                Motion::PreviousWord => Some(vec![ReedlineOption::Edit(EditCommand::CopyWordLeft)]),
+                Motion::PreviousBigWord => {
+                    Some(vec![ReedlineOption::Edit(EditCommand::CopyBigWordLeft)])
+                }
+                Motion::Start => Some(vec![ReedlineOption::Edit(EditCommand::CopyFromLineStart)]),
+                Motion::MatchingBracket => Some(vec![ReedlineOption::Edit(
+                    EditCommand::CopyToMatchingBracket,
+                )]),
+                Motion::TextObject(text_object) => Some(vec![ReedlineOption::Edit(
+                    EditCommand::CopyTextObject(*text_object),
+                )]),
+                _ => None,
             },
             Self::Change => {
                 let op = match motion {
@@ -249,8 +324,10 @@ impl Command {
                     }
                     Motion::Left => Some(vec![ReedlineOption::Edit(EditCommand::Backspace)]),
                     Motion::Right => Some(vec![ReedlineOption::Edit(EditCommand::Delete)]),
-                    Motion::Up => None,
-                    Motion::Down => None,
+                    Motion::Up | Motion::Down => None,
+                    Motion::MatchingBracket => Some(vec![ReedlineOption::Edit(
+                        EditCommand::CutToMatchingBracket,
+                    )]),
                     Motion::ReplayCharSearch => vi_state
                         .last_char_search
                         .as_ref()
@@ -260,6 +337,9 @@ impl Command {
                             vec![ReedlineOption::Edit(char_search.reverse().to_cut())]
                         })
                     }
+                    Motion::TextObject(text_object) => Some(vec![ReedlineOption::Edit(
+                        EditCommand::CutTextObject(*text_object),
+                    )]),
                 };
                 // Semihack: Append `Repaint` to ensure the mode change gets displayed
                 op.map(|mut vec| {
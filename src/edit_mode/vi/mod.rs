@@ -3,14 +3,22 @@ mod motion;
 mod parser;
 mod vi_keybindings;
 
+use std::time::{Duration, Instant};
+
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 pub use vi_keybindings::{default_vi_insert_keybindings, default_vi_normal_keybindings};
 
 use self::motion::ViCharSearch;
 
-use super::EditMode;
+use super::{parse_mouse_event, EditMode};
 use crate::{
-    edit_mode::{keybindings::Keybindings, vi::parser::parse},
+    edit_mode::{
+        keybindings::{
+            combine_events, key_combination, KeyCombination, KeySequenceFallback, KeySequenceState,
+            Keybindings, SequenceOutput,
+        },
+        vi::parser::parse,
+    },
     enums::{EditCommand, ReedlineEvent, ReedlineRawEvent},
     PromptEditMode, PromptViMode,
 };
@@ -19,6 +27,105 @@ use crate::{
 enum ViMode {
     Normal,
     Insert,
+    /// Selecting characters
+    Visual,
+    /// Selecting whole lines
+    VisualLine,
+}
+
+/// Key sequence typed in insert mode to switch to normal mode
+struct InsertEscape {
+    sequence: Vec<char>,
+    timeout: Duration,
+    pending: Vec<char>,
+    pending_since: Option<Instant>,
+}
+
+impl InsertEscape {
+    fn is_expired(&self) -> bool {
+        self.pending_since
+            .map_or(false, |since| since.elapsed() >= self.timeout)
+    }
+
+    /// Returns the pending characters, to be inserted as regular text
+    fn take_pending(&mut self) -> Vec<char> {
+        self.pending_since = None;
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// Last buffer modifying command, repeated by `.` in normal mode
+#[derive(Debug, Clone, PartialEq)]
+struct ViChange {
+    /// Events of a single execution of the command
+    events: Vec<ReedlineEvent>,
+    count: usize,
+    /// Edits typed in the insert mode entered by the command
+    inserted: Vec<EditCommand>,
+    /// Whether the count applies to the inserted text (`3ifoo`) rather than the command (`3cw`)
+    repeats_insert: bool,
+    /// Still in the insert mode entered by the command
+    recording: bool,
+}
+
+impl ViChange {
+    fn new(
+        events: Vec<ReedlineEvent>,
+        count: usize,
+        repeats_insert: bool,
+        recording: bool,
+    ) -> Self {
+        Self {
+            events,
+            count,
+            inserted: Vec::new(),
+            repeats_insert,
+            recording,
+        }
+    }
+
+    fn record_insert(&mut self, event: &ReedlineEvent) {
+        match event {
+            ReedlineEvent::Edit(commands) => self.inserted.extend(commands.iter().cloned()),
+            ReedlineEvent::Multiple(events) => {
+                for event in events {
+                    self.record_insert(event);
+                }
+            }
+            ReedlineEvent::Enter
+            | ReedlineEvent::Submit
+            | ReedlineEvent::SubmitOrNewline
+            | ReedlineEvent::SubmitAndKeep => {
+                self.recording = false;
+            }
+            _ => {}
+        }
+    }
+
+    /// Replays the change, a given count replaces the one it was recorded with
+    fn to_reedline_event(&self, count: Option<usize>) -> ReedlineEvent {
+        let count = count.unwrap_or(self.count);
+        let (command_count, insert_count) = if self.repeats_insert {
+            (1, count)
+        } else {
+            (count, 1)
+        };
+
+        let mut events = std::iter::repeat(self.events.iter().cloned())
+            .take(command_count)
+            .flatten()
+            .collect::<Vec<_>>();
+        if !self.inserted.is_empty() {
+            events.extend(
+                std::iter::repeat(ReedlineEvent::Edit(self.inserted.clone())).take(insert_count),
+            );
+        }
+        ReedlineEvent::Multiple(events)
+    }
+}
+
+fn insert_chars(chars: Vec<char>) -> ReedlineEvent {
+    ReedlineEvent::Edit(chars.into_iter().map(EditCommand::InsertChar).collect())
 }
 
 /// This parses incoming input `Event`s like a Vi-Style editor
@@ -27,9 +134,12 @@ pub struct Vi {
     insert_keybindings: Keybindings,
     normal_keybindings: Keybindings,
     mode: ViMode,
-    previous: Option<ReedlineEvent>,
+    last_change: Option<ViChange>,
     // last f, F, t, T motion for ; and ,
     last_char_search: Option<ViCharSearch>,
+    insert_escape: Option<InsertEscape>,
+    // keys that start a key sequence of the insert mode, held back until it is complete
+    key_sequence: KeySequenceState,
 }
 
 impl Default for Vi {
@@ -39,8 +149,10 @@ impl Default for Vi {
             normal_keybindings: default_vi_normal_keybindings(),
             cache: Vec::new(),
             mode: ViMode::Insert,
-            previous: None,
+            last_change: None,
             last_char_search: None,
+            insert_escape: None,
+            key_sequence: KeySequenceState::new(),
         }
     }
 }
@@ -54,11 +166,277 @@ impl Vi {
             ..Default::default()
         }
     }
+
+    /// The keybindings used in insert mode
+    pub fn insert_keybindings(&self) -> &Keybindings {
+        &self.insert_keybindings
+    }
+
+    /// The keybindings used in normal mode
+    ///
+    /// The built-in Vi commands and motions, e.g. `dw`, are parsed separately and
+    /// not part of these keybindings.
+    pub fn normal_keybindings(&self) -> &Keybindings {
+        &self.normal_keybindings
+    }
+
+    /// Vi builder that abandons an incomplete key sequence of the insert mode keybindings
+    /// after `timeout`
+    ///
+    /// Without a timeout (default) the keys of a sequence wait for the next key.
+    /// The `fallback` decides what happens to the held back keys of an abandoned sequence.
+    #[must_use]
+    pub fn with_sequence_timeout(
+        mut self,
+        timeout: Option<Duration>,
+        fallback: KeySequenceFallback,
+    ) -> Self {
+        self.key_sequence.set_timeout(timeout, fallback);
+        self
+    }
+
+    /// Vi builder with a key sequence that leaves insert mode, e.g. `jk`
+    ///
+    /// Characters that could start the sequence are held back until the sequence
+    /// is complete or doesn't match anymore. If the sequence is not completed
+    /// within the `timeout` after its first character, the held back characters
+    /// are inserted as regular text.
+    /// The sequence is disabled by default. An empty sequence disables it.
+    #[must_use]
+    pub fn with_insert_escape_sequence(mut self, sequence: &str, timeout: Duration) -> Self {
+        self.insert_escape = (!sequence.is_empty()).then(|| InsertEscape {
+            sequence: sequence.chars().collect(),
+            timeout,
+            pending: Vec::new(),
+            pending_since: None,
+        });
+        self
+    }
+
+    /// Checks the character typed in insert mode against the escape sequence
+    fn parse_insert_escape(&mut self, c: char) -> ReedlineEvent {
+        let escape = match self.insert_escape.as_mut() {
+            Some(escape) => escape,
+            None => return insert_chars(vec![c]),
+        };
+
+        let mut inserted = if escape.is_expired() {
+            escape.take_pending()
+        } else {
+            Vec::new()
+        };
+
+        escape.pending.push(c);
+        while !escape.sequence.starts_with(&escape.pending) {
+            inserted.push(escape.pending.remove(0));
+        }
+        if escape.pending.is_empty() {
+            escape.pending_since = None;
+        } else if escape.pending_since.is_none() || !inserted.is_empty() {
+            escape.pending_since = Some(Instant::now());
+        }
+
+        if escape.pending == escape.sequence {
+            escape.take_pending();
+            self.cache.clear();
+            self.mode = ViMode::Normal;
+
+            let mut events = vec![ReedlineEvent::Esc, ReedlineEvent::Repaint];
+            if !inserted.is_empty() {
+                events.insert(0, insert_chars(inserted));
+            }
+            ReedlineEvent::Multiple(events)
+        } else if inserted.is_empty() {
+            ReedlineEvent::None
+        } else {
+            insert_chars(inserted)
+        }
+    }
 }
 
 impl EditMode for Vi {
     fn parse_event(&mut self, event: ReedlineRawEvent) -> ReedlineEvent {
-        match event.into() {
+        let inserting = self.mode == ViMode::Insert;
+        let event = self.parse_raw_event(event.into());
+        if inserting {
+            self.record_insert(&event);
+        }
+        event
+    }
+
+    fn edit_mode(&self) -> PromptEditMode {
+        match self.mode {
+            ViMode::Normal => PromptEditMode::Vi(PromptViMode::Normal),
+            ViMode::Insert => PromptEditMode::Vi(PromptViMode::Insert),
+            ViMode::Visual | ViMode::VisualLine => PromptEditMode::Vi(PromptViMode::Visual),
+        }
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        let escape_timeout = self.insert_escape.as_ref().and_then(|escape| {
+            let since = escape.pending_since?;
+            Some(escape.timeout.saturating_sub(since.elapsed()))
+        });
+        match (escape_timeout, self.key_sequence.time_left()) {
+            (Some(escape_timeout), Some(sequence_timeout)) => {
+                Some(escape_timeout.min(sequence_timeout))
+            }
+            (escape_timeout, sequence_timeout) => escape_timeout.or(sequence_timeout),
+        }
+    }
+
+    fn handle_timeout(&mut self) -> ReedlineEvent {
+        let outputs = self.key_sequence.handle_timeout();
+        let sequence_event = self.resolve_insert_keys(outputs);
+        let escape_event = match self.insert_escape.as_mut() {
+            Some(escape) if escape.is_expired() => insert_chars(escape.take_pending()),
+            _ => ReedlineEvent::None,
+        };
+        let event = combine_events(vec![sequence_event, escape_event]);
+        self.record_insert(&event);
+        event
+    }
+}
+
+impl Vi {
+    /// Adds the edits made in insert mode to the change that entered it
+    fn record_insert(&mut self, event: &ReedlineEvent) {
+        if let Some(change) = self.last_change.as_mut() {
+            if change.recording {
+                change.record_insert(event);
+                change.recording &= self.mode == ViMode::Insert;
+            }
+        }
+    }
+
+    fn parse_raw_event(&mut self, event: Event) -> ReedlineEvent {
+        if self.mode == ViMode::Insert {
+            if let Event::Key(KeyEvent {
+                code, modifiers, ..
+            }) = event
+            {
+                let outputs = self
+                    .key_sequence
+                    .push(&self.insert_keybindings, key_combination(modifiers, code));
+                return self.resolve_insert_keys(outputs);
+            }
+        }
+
+        self.parse_insert_key(event)
+    }
+
+    /// Parses the keys handed on by the key sequences of the insert mode one by one
+    fn resolve_insert_keys(&mut self, outputs: Vec<SequenceOutput>) -> ReedlineEvent {
+        let events = outputs
+            .into_iter()
+            .map(|output| match output {
+                SequenceOutput::Event(event) => event,
+                SequenceOutput::Key(KeyCombination { modifier, key_code }) => {
+                    self.parse_insert_key(Event::Key(KeyEvent::new(key_code, modifier)))
+                }
+            })
+            .collect();
+        combine_events(events)
+    }
+
+    fn parse_insert_key(&mut self, event: Event) -> ReedlineEvent {
+        if self.mode == ViMode::Insert {
+            if let Some(escape) = self.insert_escape.as_mut() {
+                if let Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: modifier @ (KeyModifiers::NONE | KeyModifiers::SHIFT),
+                    ..
+                }) = event
+                {
+                    if self
+                        .insert_keybindings
+                        .find_binding(modifier, KeyCode::Char(c))
+                        .is_none()
+                    {
+                        let c = if modifier == KeyModifiers::SHIFT {
+                            c.to_ascii_uppercase()
+                        } else {
+                            c
+                        };
+                        return self.parse_insert_escape(c);
+                    }
+                } else if !escape.pending.is_empty() {
+                    // Any other key inserts the held back characters before its own action
+                    let inserted = insert_chars(escape.take_pending());
+                    return ReedlineEvent::Multiple(vec![inserted, self.parse_vi_event(event)]);
+                }
+            }
+        }
+
+        self.parse_vi_event(event)
+    }
+
+    /// Selects from the cursor on, `v` and `V` switch between selecting characters
+    /// and lines or leave the visual mode when it already selects them
+    fn start_visual(&mut self, linewise: bool) -> ReedlineEvent {
+        let mode = if linewise {
+            ViMode::VisualLine
+        } else {
+            ViMode::Visual
+        };
+        if self.mode == mode {
+            self.mode = ViMode::Normal;
+            return ReedlineEvent::Edit(vec![EditCommand::ClearSelection]);
+        }
+
+        self.mode = mode;
+        ReedlineEvent::Edit(vec![if linewise {
+            EditCommand::StartLineSelection
+        } else {
+            EditCommand::StartSelection
+        }])
+    }
+
+    /// In visual mode the operators act on the selection and the motions extend it
+    fn parse_visual_char(&mut self, c: char) -> ReedlineEvent {
+        if self.cache.is_empty() {
+            match c {
+                'd' | 'x' => {
+                    self.mode = ViMode::Normal;
+                    return ReedlineEvent::Edit(vec![EditCommand::CutSelection]);
+                }
+                'y' => {
+                    self.mode = ViMode::Normal;
+                    return ReedlineEvent::Edit(vec![EditCommand::CopySelection]);
+                }
+                'c' | 's' => {
+                    self.mode = ViMode::Insert;
+                    return ReedlineEvent::Multiple(vec![
+                        ReedlineEvent::Edit(vec![EditCommand::ChangeSelection]),
+                        ReedlineEvent::Repaint,
+                    ]);
+                }
+                'v' | 'V' => return self.start_visual(c == 'V'),
+                _ => {}
+            }
+        }
+
+        self.cache.push(c);
+        let res = parse(&mut self.cache.iter().peekable());
+        if !res.is_valid() {
+            self.cache.clear();
+            ReedlineEvent::None
+        } else if res.is_complete() {
+            // Other commands don't apply to a selection
+            let event = if res.is_motion() {
+                res.to_reedline_event(self)
+            } else {
+                ReedlineEvent::None
+            };
+            self.cache.clear();
+            event
+        } else {
+            ReedlineEvent::None
+        }
+    }
+
+    fn parse_vi_event(&mut self, event: Event) -> ReedlineEvent {
+        match event {
             Event::Key(KeyEvent {
                 code, modifiers, ..
             }) => match (self.mode, modifiers, code) {
@@ -71,11 +449,15 @@ impl EditMode for Vi {
                     {
                         event
                     } else if modifier == KeyModifiers::NONE || modifier == KeyModifiers::SHIFT {
-                        self.cache.push(if modifier == KeyModifiers::SHIFT {
+                        let c = if modifier == KeyModifiers::SHIFT {
                             c.to_ascii_uppercase()
                         } else {
                             c
-                        });
+                        };
+                        if self.cache.is_empty() && (c == 'v' || c == 'V') {
+                            return self.start_visual(c == 'V');
+                        }
+                        self.cache.push(c);
 
                         let res = parse(&mut self.cache.iter().peekable());
 
@@ -134,6 +516,27 @@ impl EditMode for Vi {
                             }
                         })
                 }
+                (
+                    ViMode::Visual | ViMode::VisualLine,
+                    modifier @ (KeyModifiers::NONE | KeyModifiers::SHIFT),
+                    KeyCode::Char(c),
+                ) => {
+                    let c = if modifier == KeyModifiers::SHIFT {
+                        c.to_ascii_uppercase()
+                    } else {
+                        c
+                    };
+                    self.parse_visual_char(c)
+                }
+                (ViMode::Visual | ViMode::VisualLine, KeyModifiers::NONE, KeyCode::Esc) => {
+                    self.cache.clear();
+                    self.mode = ViMode::Normal;
+                    ReedlineEvent::Multiple(vec![
+                        ReedlineEvent::Edit(vec![EditCommand::ClearSelection]),
+                        ReedlineEvent::Esc,
+                        ReedlineEvent::Repaint,
+                    ])
+                }
                 (_, KeyModifiers::NONE, KeyCode::Esc) => {
                     self.cache.clear();
                     self.mode = ViMode::Normal;
@@ -143,7 +546,7 @@ impl EditMode for Vi {
                     self.mode = ViMode::Insert;
                     ReedlineEvent::Enter
                 }
-                (ViMode::Normal, _, _) => self
+                (ViMode::Normal | ViMode::Visual | ViMode::VisualLine, _, _) => self
                     .normal_keybindings
                     .find_binding(modifiers, code)
                     .unwrap_or(ReedlineEvent::None),
@@ -153,7 +556,7 @@ impl EditMode for Vi {
                     .unwrap_or(ReedlineEvent::None),
             },
 
-            Event::Mouse(_) => ReedlineEvent::Mouse,
+            Event::Mouse(event) => parse_mouse_event(event),
             Event::Resize(width, height) => ReedlineEvent::Resize(width, height),
             Event::FocusGained => ReedlineEvent::None,
             Event::FocusLost => ReedlineEvent::None,
@@ -162,13 +565,6 @@ impl EditMode for Vi {
             )]),
         }
     }
-
-    fn edit_mode(&self) -> PromptEditMode {
-        match self.mode {
-            ViMode::Normal => PromptEditMode::Vi(PromptViMode::Normal),
-            ViMode::Insert => PromptEditMode::Vi(PromptViMode::Insert),
-        }
-    }
 }
 
 #[cfg(test)]
@@ -176,6 +572,324 @@ mod test {
     use super::*;
     use pretty_assertions::assert_eq;
 
+    fn char_event(c: char) -> ReedlineRawEvent {
+        ReedlineRawEvent::convert_from(Event::Key(KeyEvent::new(
+            KeyCode::Char(c),
+            KeyModifiers::NONE,
+        )))
+        .unwrap()
+    }
+
+    fn key_event(modifier: KeyModifiers, c: char) -> ReedlineRawEvent {
+        ReedlineRawEvent::convert_from(Event::Key(KeyEvent::new(KeyCode::Char(c), modifier)))
+            .unwrap()
+    }
+
+    fn vi_with_insert_sequence() -> Vi {
+        let mut insert_keybindings = default_vi_insert_keybindings();
+        insert_keybindings.add_sequence_binding(
+            vec![
+                KeyCombination {
+                    modifier: KeyModifiers::CONTROL,
+                    key_code: KeyCode::Char('x'),
+                },
+                KeyCombination {
+                    modifier: KeyModifiers::CONTROL,
+                    key_code: KeyCode::Char('e'),
+                },
+            ],
+            ReedlineEvent::OpenEditor,
+        );
+        Vi::new(insert_keybindings, default_vi_normal_keybindings())
+    }
+
+    #[test]
+    fn key_sequence_fires_in_insert_mode() {
+        let mut vi = vi_with_insert_sequence();
+
+        assert_eq!(
+            vi.parse_event(key_event(KeyModifiers::CONTROL, 'x')),
+            ReedlineEvent::None
+        );
+        assert_eq!(
+            vi.parse_event(key_event(KeyModifiers::CONTROL, 'e')),
+            ReedlineEvent::OpenEditor
+        );
+        // A broken sequence handles the keys one by one
+        vi.parse_event(key_event(KeyModifiers::CONTROL, 'x'));
+        assert_eq!(
+            vi.parse_event(key_event(KeyModifiers::SHIFT, 'A')),
+            ReedlineEvent::Edit(vec![EditCommand::InsertChar('A')])
+        );
+    }
+
+    #[test]
+    fn key_sequence_timeout_abandons_the_sequence_in_insert_mode() {
+        let mut vi = vi_with_insert_sequence()
+            .with_sequence_timeout(Some(Duration::ZERO), KeySequenceFallback::Literal)
+            .with_insert_escape_sequence("jk", Duration::from_secs(60));
+        assert_eq!(vi.timeout(), None);
+
+        vi.parse_event(key_event(KeyModifiers::CONTROL, 'x'));
+        assert_eq!(vi.timeout(), Some(Duration::ZERO));
+        assert_eq!(vi.handle_timeout(), ReedlineEvent::None);
+        assert_eq!(vi.timeout(), None);
+
+        // The keys after the sequence still reach the insert escape sequence
+        vi.parse_event(char_event('j'));
+        assert_eq!(
+            vi.parse_event(char_event('k')),
+            ReedlineEvent::Multiple(vec![ReedlineEvent::Esc, ReedlineEvent::Repaint])
+        );
+    }
+
+    #[test]
+    fn insert_escape_sequence_leaves_insert_mode() {
+        let mut vi = Vi::default().with_insert_escape_sequence("jk", Duration::from_secs(60));
+
+        assert_eq!(vi.parse_event(char_event('j')), ReedlineEvent::None);
+        assert!(vi.timeout().is_some());
+        assert_eq!(
+            vi.parse_event(char_event('k')),
+            ReedlineEvent::Multiple(vec![ReedlineEvent::Esc, ReedlineEvent::Repaint])
+        );
+        assert_eq!(vi.mode, ViMode::Normal);
+        assert_eq!(vi.timeout(), None);
+    }
+
+    #[test]
+    fn insert_escape_sequence_mismatch_inserts_chars() {
+        let mut vi = Vi::default().with_insert_escape_sequence("jk", Duration::from_secs(60));
+
+        assert_eq!(
+            vi.parse_event(char_event('a')),
+            ReedlineEvent::Edit(vec![EditCommand::InsertChar('a')])
+        );
+        assert_eq!(vi.parse_event(char_event('j')), ReedlineEvent::None);
+        // The second `j` may still start the sequence
+        assert_eq!(
+            vi.parse_event(char_event('j')),
+            ReedlineEvent::Edit(vec![EditCommand::InsertChar('j')])
+        );
+        assert_eq!(
+            vi.parse_event(char_event('x')),
+            ReedlineEvent::Edit(vec![
+                EditCommand::InsertChar('j'),
+                EditCommand::InsertChar('x')
+            ])
+        );
+        assert_eq!(vi.mode, ViMode::Insert);
+    }
+
+    #[test]
+    fn insert_escape_sequence_timeout_inserts_chars() {
+        let mut vi = Vi::default().with_insert_escape_sequence("jk", Duration::ZERO);
+
+        assert_eq!(vi.parse_event(char_event('j')), ReedlineEvent::None);
+        assert_eq!(vi.timeout(), Some(Duration::ZERO));
+        assert_eq!(
+            vi.handle_timeout(),
+            ReedlineEvent::Edit(vec![EditCommand::InsertChar('j')])
+        );
+        assert_eq!(vi.handle_timeout(), ReedlineEvent::None);
+
+        // An expired `j` is inserted before checking the next character
+        assert_eq!(vi.parse_event(char_event('j')), ReedlineEvent::None);
+        assert_eq!(
+            vi.parse_event(char_event('k')),
+            ReedlineEvent::Edit(vec![
+                EditCommand::InsertChar('j'),
+                EditCommand::InsertChar('k')
+            ])
+        );
+        assert_eq!(vi.mode, ViMode::Insert);
+    }
+
+    #[test]
+    fn insert_escape_sequence_flushed_by_other_keys() {
+        let mut vi = Vi::default().with_insert_escape_sequence("jk", Duration::from_secs(60));
+        let enter = ReedlineRawEvent::convert_from(Event::Key(KeyEvent::new(
+            KeyCode::Enter,
+            KeyModifiers::NONE,
+        )))
+        .unwrap();
+
+        vi.parse_event(char_event('j'));
+        assert_eq!(
+            vi.parse_event(enter),
+            ReedlineEvent::Multiple(vec![
+                ReedlineEvent::Edit(vec![EditCommand::InsertChar('j')]),
+                ReedlineEvent::Enter
+            ])
+        );
+    }
+
+    fn normal_mode_vi() -> Vi {
+        Vi {
+            mode: ViMode::Normal,
+            ..Default::default()
+        }
+    }
+
+    fn type_keys(vi: &mut Vi, keys: &str) -> ReedlineEvent {
+        keys.chars()
+            .map(|c| {
+                let modifiers = if c.is_ascii_uppercase() {
+                    KeyModifiers::SHIFT
+                } else {
+                    KeyModifiers::NONE
+                };
+                let event = Event::Key(KeyEvent::new(KeyCode::Char(c), modifiers));
+                vi.parse_event(ReedlineRawEvent::convert_from(event).unwrap())
+            })
+            .last()
+            .unwrap_or(ReedlineEvent::None)
+    }
+
+    fn esc_event() -> ReedlineRawEvent {
+        ReedlineRawEvent::convert_from(Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)))
+            .unwrap()
+    }
+
+    #[test]
+    fn repeat_last_change_with_counts() {
+        let mut vi = normal_mode_vi();
+        let cut_char = ReedlineEvent::Edit(vec![EditCommand::CutChar]);
+
+        type_keys(&mut vi, "2x");
+        assert_eq!(
+            type_keys(&mut vi, "."),
+            ReedlineEvent::Multiple(vec![cut_char.clone(), cut_char.clone()])
+        );
+        assert_eq!(
+            type_keys(&mut vi, "3."),
+            ReedlineEvent::Multiple(vec![cut_char.clone(), cut_char.clone(), cut_char])
+        );
+    }
+
+    #[test]
+    fn repeat_last_change_replays_inserted_text() {
+        let mut vi = normal_mode_vi();
+
+        type_keys(&mut vi, "cwab");
+        vi.parse_event(esc_event());
+        assert_eq!(vi.mode, ViMode::Normal);
+
+        assert_eq!(
+            type_keys(&mut vi, "."),
+            ReedlineEvent::Multiple(vec![
+                ReedlineEvent::Edit(vec![EditCommand::CutWordRightToNext]),
+                ReedlineEvent::Repaint,
+                ReedlineEvent::Edit(vec![
+                    EditCommand::InsertChar('a'),
+                    EditCommand::InsertChar('b')
+                ]),
+            ])
+        );
+        assert_eq!(vi.mode, ViMode::Normal);
+    }
+
+    #[test]
+    fn repeat_last_change_repeats_insert_count() {
+        let mut vi = normal_mode_vi();
+
+        type_keys(&mut vi, "ia");
+        vi.parse_event(esc_event());
+
+        assert_eq!(
+            type_keys(&mut vi, "2."),
+            ReedlineEvent::Multiple(vec![
+                ReedlineEvent::Repaint,
+                ReedlineEvent::Edit(vec![EditCommand::InsertChar('a')]),
+                ReedlineEvent::Edit(vec![EditCommand::InsertChar('a')]),
+            ])
+        );
+    }
+
+    #[test]
+    fn repeat_last_change_ignores_motions_and_undo() {
+        let mut vi = normal_mode_vi();
+
+        assert_eq!(type_keys(&mut vi, "."), ReedlineEvent::None);
+
+        type_keys(&mut vi, "dw");
+        type_keys(&mut vi, "w");
+        type_keys(&mut vi, "u");
+        type_keys(&mut vi, "yiw");
+        assert_eq!(
+            type_keys(&mut vi, "."),
+            ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![
+                EditCommand::CutWordRightToNext
+            ])])
+        );
+    }
+
+    #[test]
+    fn visual_mode_operators_act_on_the_selection() {
+        let mut vi = normal_mode_vi();
+        let move_word = ReedlineEvent::Edit(vec![EditCommand::MoveWordRightStart]);
+
+        assert_eq!(
+            type_keys(&mut vi, "v"),
+            ReedlineEvent::Edit(vec![EditCommand::StartSelection])
+        );
+        assert_eq!(vi.mode, ViMode::Visual);
+        assert!(matches!(
+            vi.edit_mode(),
+            PromptEditMode::Vi(PromptViMode::Visual)
+        ));
+        assert_eq!(
+            type_keys(&mut vi, "2w"),
+            ReedlineEvent::Multiple(vec![move_word.clone(), move_word])
+        );
+        // Other commands don't apply to the selection
+        assert_eq!(type_keys(&mut vi, "p"), ReedlineEvent::None);
+        assert_eq!(
+            type_keys(&mut vi, "d"),
+            ReedlineEvent::Edit(vec![EditCommand::CutSelection])
+        );
+        assert_eq!(vi.mode, ViMode::Normal);
+
+        type_keys(&mut vi, "V");
+        assert_eq!(vi.mode, ViMode::VisualLine);
+        assert_eq!(
+            type_keys(&mut vi, "c"),
+            ReedlineEvent::Multiple(vec![
+                ReedlineEvent::Edit(vec![EditCommand::ChangeSelection]),
+                ReedlineEvent::Repaint
+            ])
+        );
+        assert_eq!(vi.mode, ViMode::Insert);
+    }
+
+    #[test]
+    fn visual_mode_is_left_with_esc_or_its_key() {
+        let mut vi = normal_mode_vi();
+
+        type_keys(&mut vi, "v");
+        assert_eq!(
+            type_keys(&mut vi, "V"),
+            ReedlineEvent::Edit(vec![EditCommand::StartLineSelection])
+        );
+        assert_eq!(vi.mode, ViMode::VisualLine);
+        assert_eq!(
+            type_keys(&mut vi, "V"),
+            ReedlineEvent::Edit(vec![EditCommand::ClearSelection])
+        );
+        assert_eq!(vi.mode, ViMode::Normal);
+
+        type_keys(&mut vi, "v");
+        assert_eq!(
+            vi.parse_event(esc_event()),
+            ReedlineEvent::Multiple(vec![
+                ReedlineEvent::Edit(vec![EditCommand::ClearSelection]),
+                ReedlineEvent::Esc,
+                ReedlineEvent::Repaint
+            ])
+        );
+        assert_eq!(vi.mode, ViMode::Normal);
+    }
+
     #[test]
     fn esc_leads_to_normal_mode_test() {
         let mut vi = Vi::default();
@@ -1,6 +1,6 @@
 use std::iter::Peekable;
 
-use crate::{EditCommand, ReedlineEvent, Vi};
+use crate::{EditCommand, ReedlineEvent, TextObject, Vi};
 
 use super::parser::{ParseResult, ReedlineOption};
 
@@ -108,6 +108,21 @@ where
             let _ = input.next();
             ParseResult::Valid(Motion::ReverseCharSearch)
         }
+        Some('%') => {
+            let _ = input.next();
+            ParseResult::Valid(Motion::MatchingBracket)
+        }
+        // Text objects only follow an operator like `c`, `d` or `y`
+        Some('i' | 'a') if command_char.is_some() => {
+            let inner = input.next() == Some(&'i');
+            match input.next() {
+                Some(&c) => match parse_text_object(inner, c) {
+                    Some(text_object) => ParseResult::Valid(Motion::TextObject(text_object)),
+                    None => ParseResult::Invalid,
+                },
+                None => ParseResult::Incomplete,
+            }
+        }
         ch if ch == command_char.as_ref().as_ref() && command_char.is_some() => {
             let _ = input.next();
             ParseResult::Valid(Motion::Line)
@@ -117,6 +132,30 @@ where
     }
 }
 
+fn parse_text_object(inner: bool, c: char) -> Option<TextObject> {
+    let pair = |open, close| {
+        if inner {
+            TextObject::InnerPair(open, close)
+        } else {
+            TextObject::AroundPair(open, close)
+        }
+    };
+    let text_object = match c {
+        'w' if inner => TextObject::InnerWord,
+        'w' => TextObject::AroundWord,
+        'W' if inner => TextObject::InnerBigWord,
+        'W' => TextObject::AroundBigWord,
+        '(' | ')' | 'b' => pair('(', ')'),
+        '[' | ']' => pair('[', ']'),
+        '{' | '}' | 'B' => pair('{', '}'),
+        '<' | '>' => pair('<', '>'),
+        '"' | '\'' | '`' if inner => TextObject::InnerQuote(c),
+        '"' | '\'' | '`' => TextObject::AroundQuote(c),
+        _ => return None,
+    };
+    Some(text_object)
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum Motion {
     Left,
@@ -138,6 +177,8 @@ pub enum Motion {
     LeftBefore(char),
     ReplayCharSearch,
     ReverseCharSearch,
+    MatchingBracket,
+    TextObject(TextObject),
 }
 
 impl Motion {
@@ -166,7 +207,11 @@ impl Motion {
             Motion::NextBigWordEnd => vec![ReedlineOption::Edit(EditCommand::MoveBigWordRightEnd)],
             Motion::PreviousWord => vec![ReedlineOption::Edit(EditCommand::MoveWordLeft)],
             Motion::PreviousBigWord => vec![ReedlineOption::Edit(EditCommand::MoveBigWordLeft)],
-            Motion::Line => vec![], // Placeholder as unusable standalone motion
+            Motion::MatchingBracket => {
+                vec![ReedlineOption::Edit(EditCommand::MoveToMatchingBracket)]
+            }
+            // Placeholders as unusable standalone motions
+            Motion::Line | Motion::TextObject(_) => vec![],
             Motion::Start => vec![ReedlineOption::Edit(EditCommand::MoveToLineStart)],
             Motion::End => vec![ReedlineOption::Edit(EditCommand::MoveToLineEnd)],
             Motion::RightUntil(ch) => {
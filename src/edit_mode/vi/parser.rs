@@ -1,5 +1,6 @@
 use super::command::{parse_command, Command};
 use super::motion::{parse_motion, Motion};
+use super::ViChange;
 use crate::{EditCommand, ReedlineEvent, Vi};
 use std::iter::Peekable;
 
@@ -39,6 +40,7 @@ impl<T> ParseResult<T> {
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct ParsedViSequence {
+    register: Option<char>,
     multiplier: Option<usize>,
     command: Option<Command>,
     count: Option<usize>,
@@ -50,6 +52,11 @@ impl ParsedViSequence {
         !self.motion.is_invalid()
     }
 
+    /// Whether the sequence only moves the cursor, without a command
+    pub fn is_motion(&self) -> bool {
+        self.command.is_none()
+    }
+
     pub fn is_complete(&self) -> bool {
         match (&self.command, &self.motion) {
             (None, ParseResult::Valid(_)) => true,
@@ -111,23 +118,26 @@ impl ParsedViSequence {
 
     pub fn to_reedline_event(&self, vi_state: &mut Vi) -> ReedlineEvent {
         match (&self.multiplier, &self.command, &self.count, &self.motion) {
+            (_, Some(Command::RepeatLastAction), None, ParseResult::Incomplete) => vi_state
+                .last_change
+                .as_ref()
+                .map_or(ReedlineEvent::None, |change| {
+                    change.to_reedline_event(self.multiplier)
+                }),
             (_, Some(command), None, ParseResult::Incomplete) => {
-                let events = self.apply_multiplier(Some(command.to_reedline(vi_state)));
-                match &events {
-                    ReedlineEvent::None => {}
-                    event => vi_state.previous = Some(event.clone()),
-                }
-                events
+                let options = self.with_register(command, command.to_reedline());
+                self.record_change(command, &options, vi_state);
+                self.apply_multiplier(Some(options))
             }
             // This case handles all combinations of commands and motions that could exist
             (_, Some(command), _, ParseResult::Valid(motion)) => {
-                let events =
-                    self.apply_multiplier(command.to_reedline_with_motion(motion, vi_state));
-                match &events {
-                    ReedlineEvent::None => {}
-                    event => vi_state.previous = Some(event.clone()),
+                let options = command
+                    .to_reedline_with_motion(motion, vi_state)
+                    .map(|options| self.with_register(command, options));
+                if let Some(options) = &options {
+                    self.record_change(command, options, vi_state);
                 }
-                events
+                self.apply_multiplier(options)
             }
             (_, None, _, ParseResult::Valid(motion)) => {
                 self.apply_multiplier(Some(motion.to_reedline(vi_state)))
@@ -135,6 +145,43 @@ impl ParsedViSequence {
             _ => ReedlineEvent::None,
         }
     }
+
+    /// Prefix the command with the selection of its register, for every repetition
+    fn with_register(
+        &self,
+        command: &Command,
+        mut options: Vec<ReedlineOption>,
+    ) -> Vec<ReedlineOption> {
+        if let Some(register) = self.register.filter(|_| command.uses_register()) {
+            options.insert(
+                0,
+                ReedlineOption::Edit(EditCommand::SelectRegister(register)),
+            );
+        }
+        options
+    }
+
+    /// Remember a buffer modifying command so it can be repeated with `.`
+    fn record_change(&self, command: &Command, options: &[ReedlineOption], vi_state: &mut Vi) {
+        if !command.is_change() {
+            return;
+        }
+
+        let events = options
+            .iter()
+            .cloned()
+            .filter_map(ReedlineOption::into_reedline_event)
+            .collect::<Vec<_>>();
+
+        if !events.is_empty() && !events.contains(&ReedlineEvent::None) {
+            vi_state.last_change = Some(ViChange::new(
+                events,
+                self.total_multiplier(),
+                command.repeats_insert(),
+                self.enters_insert_mode(),
+            ));
+        }
+    }
 }
 
 fn parse_number<'iter, I>(input: &mut Peekable<I>) -> Option<usize>
@@ -161,16 +208,32 @@ where
     }
 }
 
+/// Parses a `"x` prefix selecting the register of the following command
+fn parse_register<'iter, I>(input: &mut Peekable<I>) -> Option<char>
+where
+    I: Iterator<Item = &'iter char>,
+{
+    match input.peek() {
+        Some('"') => {
+            let _ = input.next();
+            input.next().copied()
+        }
+        _ => None,
+    }
+}
+
 pub fn parse<'iter, I>(input: &mut Peekable<I>) -> ParsedViSequence
 where
     I: Iterator<Item = &'iter char>,
 {
+    let register = parse_register(input);
     let multiplier = parse_number(input);
     let command = parse_command(input);
     let count = parse_number(input);
     let motion = parse_motion(input, command.as_ref().and_then(Command::whole_line_char));
 
     ParsedViSequence {
+        register,
         multiplier,
         command,
         count,
@@ -181,6 +244,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::TextObject;
     use pretty_assertions::assert_eq;
     use rstest::rstest;
 
@@ -188,6 +252,25 @@ mod tests {
         parse(&mut input.iter().peekable())
     }
 
+    #[rstest]
+    #[case(&['"'], true, false)]
+    #[case(&['"', 'a'], true, false)]
+    #[case(&['"', 'a', 'd'], true, false)]
+    #[case(&['"', 'a', 'd', 'd'], true, true)]
+    #[case(&['c', 'i'], true, false)]
+    #[case(&['d', 'a', 'x'], false, false)]
+    #[case(&['y', 'i', 'W'], true, true)]
+    fn test_text_object_parsing(
+        #[case] input: &[char],
+        #[case] is_valid: bool,
+        #[case] is_complete: bool,
+    ) {
+        let output = vi_parse(input);
+
+        assert_eq!(output.is_valid(), is_valid);
+        assert_eq!(output.is_complete(), is_complete);
+    }
+
     #[test]
     fn test_delete_word() {
         let input = ['d', 'w'];
@@ -196,6 +279,7 @@ mod tests {
         assert_eq!(
             output,
             ParsedViSequence {
+                register: None,
                 multiplier: None,
                 command: Some(Command::Delete),
                 count: None,
@@ -214,6 +298,7 @@ mod tests {
         assert_eq!(
             output,
             ParsedViSequence {
+                register: None,
                 multiplier: Some(2),
                 command: Some(Command::Delete),
                 count: None,
@@ -232,6 +317,7 @@ mod tests {
         assert_eq!(
             output,
             ParsedViSequence {
+                register: None,
                 multiplier: Some(2),
                 command: Some(Command::Delete),
                 count: Some(2),
@@ -250,6 +336,7 @@ mod tests {
         assert_eq!(
             output,
             ParsedViSequence {
+                register: None,
                 multiplier: Some(2),
                 command: Some(Command::Delete),
                 count: Some(20),
@@ -268,6 +355,7 @@ mod tests {
         assert_eq!(
             output,
             ParsedViSequence {
+                register: None,
                 multiplier: Some(2),
                 command: Some(Command::Delete),
                 count: None,
@@ -286,6 +374,7 @@ mod tests {
         assert_eq!(
             output,
             ParsedViSequence {
+                register: None,
                 multiplier: None,
                 command: Some(Command::Delete),
                 count: None,
@@ -304,6 +393,7 @@ mod tests {
         assert_eq!(
             output,
             ParsedViSequence {
+                register: None,
                 multiplier: Some(2),
                 command: Some(Command::Delete),
                 count: None,
@@ -321,6 +411,7 @@ mod tests {
         assert_eq!(
             output,
             ParsedViSequence {
+                register: None,
                 multiplier: None,
                 command: Some(Command::Incomplete),
                 count: None,
@@ -340,6 +431,7 @@ mod tests {
         assert_eq!(
             output,
             ParsedViSequence {
+                register: None,
                 multiplier: None,
                 command: None,
                 count: None,
@@ -358,6 +450,7 @@ mod tests {
         assert_eq!(
             output,
             ParsedViSequence {
+                register: None,
                 multiplier: None,
                 command: Some(Command::ReplaceChar('k')),
                 count: None,
@@ -377,6 +470,7 @@ mod tests {
         assert_eq!(
             output,
             ParsedViSequence {
+                register: None,
                 multiplier: Some(2),
                 command: None,
                 count: None,
@@ -395,6 +489,7 @@ mod tests {
         assert_eq!(
             output,
             ParsedViSequence {
+                register: None,
                 multiplier: Some(2),
                 command: None,
                 count: None,
@@ -421,6 +516,12 @@ mod tests {
         ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::MoveWordRightStart])]))]
     #[case(&['W'],
         ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::MoveBigWordRightStart])]))]
+    #[case(&['%'],
+        ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::MoveToMatchingBracket])]))]
+    #[case(&['B'],
+        ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::MoveBigWordLeft])]))]
+    #[case(&['E'],
+        ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::MoveBigWordRightEnd])]))]
     #[case(&['2', 'l'], ReedlineEvent::Multiple(vec![
         ReedlineEvent::UntilFound(vec![
                 ReedlineEvent::HistoryHintComplete,
@@ -456,6 +557,41 @@ mod tests {
     #[case(&['d', 'e'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::CutWordRight])]))]
     #[case(&['d', 'b'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::CutWordLeft])]))]
     #[case(&['d', 'B'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::CutBigWordLeft])]))]
+    #[case(&['d', 'E'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::CutBigWordRight])]))]
+    #[case(&['c', 'W'], ReedlineEvent::Multiple(vec![
+        ReedlineEvent::Edit(vec![EditCommand::CutBigWordRightToNext]),
+        ReedlineEvent::Repaint]))]
+    #[case(&['d', 'a', 'w'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![
+        EditCommand::CutTextObject(TextObject::AroundWord)])]))]
+    #[case(&['d', 'i', 'b'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![
+        EditCommand::CutTextObject(TextObject::InnerPair('(', ')'))])]))]
+    #[case(&['c', 'i', '"'], ReedlineEvent::Multiple(vec![
+        ReedlineEvent::Edit(vec![EditCommand::CutTextObject(TextObject::InnerQuote('"'))]),
+        ReedlineEvent::Repaint]))]
+    #[case(&['y', 'a', '}'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![
+        EditCommand::CopyTextObject(TextObject::AroundPair('{', '}'))])]))]
+    #[case(&['y', 'w'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::CopyWordRightToNext])]))]
+    #[case(&['y', 'W'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::CopyBigWordRightToNext])]))]
+    #[case(&['y', '$'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::CopyToLineEnd])]))]
+    #[case(&['y', 'b'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::CopyWordLeft])]))]
+    #[case(&['y', 'B'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::CopyBigWordLeft])]))]
+    #[case(&['y', '0'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::CopyFromLineStart])]))]
+    #[case(&['y', 'l'], ReedlineEvent::None)]
+    #[case(&['d', '%'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::CutToMatchingBracket])]))]
+    #[case(&['c', '%'], ReedlineEvent::Multiple(vec![
+        ReedlineEvent::Edit(vec![EditCommand::CutToMatchingBracket]),
+        ReedlineEvent::Repaint]))]
+    #[case(&['y', '%'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::CopyToMatchingBracket])]))]
+    #[case(&['y', 'y'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::CopyCurrentLine])]))]
+    #[case(&['"', 'a', 'y', 'y'], ReedlineEvent::Multiple(vec![
+        ReedlineEvent::Edit(vec![EditCommand::SelectRegister('a')]),
+        ReedlineEvent::Edit(vec![EditCommand::CopyCurrentLine])]))]
+    #[case(&['"', 'a', '2', 'p'], ReedlineEvent::Multiple(vec![
+        ReedlineEvent::Edit(vec![EditCommand::SelectRegister('a')]),
+        ReedlineEvent::Edit(vec![EditCommand::PasteCutBufferAfter]),
+        ReedlineEvent::Edit(vec![EditCommand::SelectRegister('a')]),
+        ReedlineEvent::Edit(vec![EditCommand::PasteCutBufferAfter])]))]
+    #[case(&['"', 'a', 'w'], ReedlineEvent::Multiple(vec![ReedlineEvent::Edit(vec![EditCommand::MoveWordRightStart])]))]
     fn test_reedline_move(#[case] input: &[char], #[case] expected: ReedlineEvent) {
         let mut vi = Vi::default();
         let res = vi_parse(input);
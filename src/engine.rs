@@ -1,7 +1,13 @@
-use crossterm::event::{DisableBracketedPaste, EnableBracketedPaste};
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+};
 use crossterm::execute;
 
-use crate::{enums::ReedlineRawEvent, CursorConfig};
+use crate::{
+    enums::ReedlineRawEvent,
+    menu_functions::{columns_of_values, complete_common_prefix, insert_suggestion},
+    CursorConfig,
+};
 #[cfg(feature = "bashisms")]
 use crate::{
     history::SearchFilter,
@@ -9,7 +15,7 @@ use crate::{
 };
 #[cfg(feature = "external_printer")]
 use {
-    crate::external_printer::ExternalPrinter,
+    crate::external_printer::{ExternalPrinter, InputPoller},
     crossbeam::channel::TryRecvError,
     std::io::{Error, ErrorKind},
 };
@@ -17,28 +23,42 @@ use {
     crate::{
         completion::{Completer, DefaultCompleter},
         core_editor::Editor,
-        edit_mode::{EditMode, Emacs},
-        enums::{EventStatus, ReedlineEvent},
+        edit_mode::{default_history_search_keybindings, EditMode, Emacs, Keybindings},
+        enums::{EditCommandObserver, EventAction, EventHook, EventStatus, ReedlineEvent},
         highlighter::SimpleMatchHighlighter,
         hinter::Hinter,
         history::{
             FileBackedHistory, History, HistoryCursor, HistoryItem, HistoryItemId,
             HistoryNavigationQuery, HistorySessionId, SearchDirection, SearchQuery,
         },
-        painting::{Painter, PromptLines},
-        prompt::{PromptEditMode, PromptHistorySearchStatus},
+        menu::menu_consumes_char,
+        painting::{
+            clip_to_width, strip_ansi, ColorLevel, LineWrapMode, Painter, PromptLines, RenderPass,
+            RenderPipeline, StyledText,
+        },
+        prompt::{PromptEditMode, PromptHistorySearchStatus, TransientPrompt},
         result::{ReedlineError, ReedlineErrorVariants},
         utils::text_manipulation,
-        EditCommand, ExampleHighlighter, Highlighter, LineBuffer, Menu, MenuEvent, Prompt,
-        PromptHistorySearch, ReedlineMenu, Signal, UndoBehavior, ValidationResult, Validator,
+        CharClass, Clipboard, CommandStatus, EditCommand, ExampleHighlighter, Highlighter,
+        HistorySearchIndicator, LineBuffer, Menu, MenuEvent, MenuTabBehavior, Prompt,
+        PromptHistorySearch, QuotedInsertMode, ReedlineMenu, SetBufferUndo, Signal, TimedSignal,
+        UndoBehavior, ValidationResult, Validator,
     },
     crossterm::{
-        cursor::{SetCursorStyle, Show},
+        cursor::{self, SetCursorStyle, Show},
         event,
         event::{Event, KeyCode, KeyEvent, KeyModifiers},
         terminal, QueueableCommand, Result,
     },
-    std::{fs::File, io, io::Write, process::Command, time::Duration, time::SystemTime},
+    nu_ansi_term::Style,
+    std::{
+        collections::HashMap,
+        fs::File,
+        io,
+        io::Write,
+        process::Command,
+        time::{Duration, Instant, SystemTime},
+    },
 };
 
 // The POLL_WAIT is used to specify for how long the POLL should wait for
@@ -52,6 +72,9 @@ const POLL_WAIT: u64 = 10;
 // a paste. 10 events in 10 milliseconds is conservative enough (unlikely somebody
 // will type more than 10 characters in 10 milliseconds)
 const EVENTS_THRESHOLD: usize = 10;
+// A macro is played at most this many times in a row, so a mistyped count can't
+// keep the line editor busy
+const MAX_MACRO_REPEAT: usize = 1000;
 
 /// Determines if inputs should be used to extend the regular line buffer,
 /// traverse the history in the standard prompt or edit the search string in the
@@ -97,16 +120,28 @@ pub struct Reedline {
     history: Box<dyn History>,
     history_cursor: HistoryCursor,
     history_session_id: Option<HistorySessionId>,
+    // the reverse history search is not restricted to `history_session_id`
+    history_search_all_sessions: bool,
+    // recalled entries place the cursor where it was when they were accepted
+    history_restores_cursor: bool,
     // none if history doesn't support this
     history_last_run_id: Option<HistoryItemId>,
     history_exclusion_prefix: Option<String>,
     history_excluded_item: Option<HistoryItem>,
     history_cursor_on_excluded: bool,
+    // the history cursor was positioned by `ReedlineEvent::InsertLastCommand`
+    history_cursor_on_last_command: bool,
     input_mode: InputMode,
 
     // Validator
     validator: Option<Box<dyn Validator>>,
 
+    // Status of the previous command shown by the prompt, and whether its duration is
+    // measured from the time its line was accepted once the next prompt is painted
+    last_command_status: Option<CommandStatus>,
+    measure_command_duration: bool,
+    line_accepted_at: Option<Instant>,
+
     // Stdout
     painter: Painter,
 
@@ -117,6 +152,18 @@ pub struct Reedline {
     completer: Box<dyn Completer>,
     quick_completions: bool,
     partial_completions: bool,
+    accept_unambiguous_prefix: bool,
+    inline_completions: bool,
+    // The line left by the last inline completion with ambiguous candidates
+    inline_completion_line: Option<(String, usize)>,
+    menu_tab_behavior: MenuTabBehavior,
+
+    // The next key is inserted literally
+    quoted_insert_pending: bool,
+    quoted_insert_mode: QuotedInsertMode,
+
+    // Words expanded when they are followed by a space or the line is submitted
+    abbreviations: HashMap<String, String>,
 
     // Highlight the edit buffer
     highlighter: Box<dyn Highlighter>,
@@ -124,10 +171,38 @@ pub struct Reedline {
     // Showcase hints based on various strategies (history, language-completion, spellcheck, etc)
     hinter: Option<Box<dyn Hinter>>,
     hide_hints: bool,
+    hide_hints_in_menus: bool,
+
+    // Order and selection of the layers composed into the painted buffer
+    render_pipeline: RenderPipeline,
+    // Style of the selected text, e.g. in the vi visual mode
+    selection_style: Style,
 
     // Use ansi coloring or not
     use_ansi_coloring: bool,
 
+    // Text generators for `EditCommand::InsertGenerated`
+    text_generators: Vec<Box<dyn Fn() -> String + Send>>,
+
+    // Called with every edit command run over the buffer
+    edit_command_observer: Option<EditCommandObserver>,
+
+    // Called in order with every event before it is handled
+    event_hooks: Vec<EventHook>,
+
+    // Recorded keyboard macros by register, the recording in progress and the
+    // registers being played
+    macros: HashMap<char, Vec<ReedlineEvent>>,
+    macro_recording: Option<(char, Vec<ReedlineEvent>)>,
+    playing_macros: Vec<char>,
+
+    // Replaces the prompt indicator during the history search
+    history_search_indicator: Option<HistorySearchIndicator>,
+    // Take precedence over the edit mode during the history search
+    history_search_keybindings: Keybindings,
+    // The direction the history search stepped in last
+    history_search_direction: SearchDirection,
+
     // Engine Menus
     menus: Vec<ReedlineMenu>,
 
@@ -137,11 +212,37 @@ pub struct Reedline {
     // Use different cursors depending on the current edit mode
     cursor_shapes: Option<CursorConfig>,
 
+    // Turn on BracketedPaste while reading a line
+    bracketed_paste: bool,
+
     // Indicate if global terminal have enabled BracketedPaste
     bracket_paste_enabled: bool,
 
+    // Turn on the mouse capture while reading a line, and whether it is on
+    mouse_capture: bool,
+    mouse_capture_enabled: bool,
+
+    // Cursor position where the last read timed out, the next one continues on the
+    // painted line unless something moved the cursor in between
+    paused_at: Option<(u16, u16)>,
+
+    // Line submitted with `SubmitAndKeep`, restored by the next read
+    kept_line: Option<LineBuffer>,
+
+    // Minimum time between two repaints caused by input events
+    repaint_interval: Option<Duration>,
+    last_repaint: Option<Instant>,
+
+    // Idle time after an edit before the active menu asks the completer again
+    completion_debounce: Option<Duration>,
+    // Last edit whose completions haven't been requested by the active menu yet
+    pending_menu_edit: Option<Instant>,
+
     #[cfg(feature = "external_printer")]
     external_printer: Option<ExternalPrinter<String>>,
+    // Waits for input and external messages at once, started with the first wait
+    #[cfg(feature = "external_printer")]
+    input_poller: Option<InputPoller>,
 }
 
 struct BufferEditor {
@@ -149,6 +250,55 @@ struct BufferEditor {
     extension: String,
 }
 
+/// Guard created at the start of [`Reedline::read_line`] that restores the terminal
+/// if the host panics while reedline is in control of it.
+///
+/// On a regular return the terminal is restored by `read_line` itself, so the
+/// guard only acts while unwinding.
+struct TerminalGuard<W: Write> {
+    out: W,
+    reset_cursor_shape: bool,
+    disable_bracketed_paste: bool,
+    disable_mouse_capture: bool,
+}
+
+impl<W: Write> TerminalGuard<W> {
+    fn new(
+        out: W,
+        reset_cursor_shape: bool,
+        disable_bracketed_paste: bool,
+        disable_mouse_capture: bool,
+    ) -> Self {
+        Self {
+            out,
+            reset_cursor_shape,
+            disable_bracketed_paste,
+            disable_mouse_capture,
+        }
+    }
+}
+
+impl<W: Write> Drop for TerminalGuard<W> {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            return;
+        }
+
+        let _ignore = terminal::disable_raw_mode();
+        if self.reset_cursor_shape {
+            let _ignore = self.out.queue(SetCursorStyle::DefaultUserShape);
+            let _ignore = self.out.queue(Show);
+        }
+        if self.disable_bracketed_paste {
+            let _ignore = self.out.queue(DisableBracketedPaste);
+        }
+        if self.disable_mouse_capture {
+            let _ignore = self.out.queue(DisableMouseCapture);
+        }
+        let _ignore = self.out.flush();
+    }
+}
+
 impl Drop for Reedline {
     fn drop(&mut self) {
         if self.cursor_shapes.is_some() {
@@ -165,6 +315,9 @@ impl Drop for Reedline {
         if self.bracket_paste_enabled {
             let _ = execute!(io::stdout(), DisableBracketedPaste);
         }
+        if self.mouse_capture_enabled {
+            let _ = execute!(io::stdout(), DisableMouseCapture);
+        }
     }
 }
 
@@ -172,16 +325,20 @@ impl Reedline {
     const FILTERED_ITEM_ID: HistoryItemId = HistoryItemId(i64::MAX);
 
     /// Create a new [`Reedline`] engine with a local [`History`] that is not synchronized to a file.
+    ///
+    /// Every engine gets its own history session id from
+    /// [`Reedline::create_history_session_id`], which is stored with the entries it adds
+    /// to the history.
     #[must_use]
     pub fn create() -> Self {
         let history = Box::<FileBackedHistory>::default();
-        let painter = Painter::new(std::io::BufWriter::new(std::io::stderr()));
+        let painter = Painter::new(std::io::BufWriter::new(Box::new(std::io::stderr())));
         let buffer_highlighter = Box::<ExampleHighlighter>::default();
         let completer = Box::<DefaultCompleter>::default();
         let hinter = None;
         let validator = None;
         let edit_mode = Box::<Emacs>::default();
-        let hist_session_id = None;
+        let hist_session_id = Self::create_history_session_id();
 
         Reedline {
             editor: Editor::default(),
@@ -191,27 +348,63 @@ impl Reedline {
                 hist_session_id,
             ),
             history_session_id: hist_session_id,
+            history_search_all_sessions: true,
+            history_restores_cursor: false,
             history_last_run_id: None,
             history_exclusion_prefix: None,
             history_excluded_item: None,
             history_cursor_on_excluded: false,
+            history_cursor_on_last_command: false,
             input_mode: InputMode::Regular,
             painter,
             edit_mode,
             completer,
             quick_completions: false,
             partial_completions: false,
+            accept_unambiguous_prefix: false,
+            inline_completions: false,
+            inline_completion_line: None,
+            abbreviations: HashMap::new(),
+            menu_tab_behavior: MenuTabBehavior::default(),
+            quoted_insert_pending: false,
+            quoted_insert_mode: QuotedInsertMode::default(),
             highlighter: buffer_highlighter,
             hinter,
             hide_hints: false,
+            hide_hints_in_menus: true,
+            render_pipeline: RenderPipeline::default(),
+            selection_style: Style::new().reverse(),
             validator,
+            last_command_status: None,
+            measure_command_duration: false,
+            line_accepted_at: None,
             use_ansi_coloring: true,
+            history_search_indicator: None,
+            history_search_keybindings: default_history_search_keybindings(),
+            history_search_direction: SearchDirection::Backward,
+            text_generators: Vec::new(),
+            edit_command_observer: None,
+            event_hooks: Vec::new(),
+            macros: HashMap::new(),
+            macro_recording: None,
+            playing_macros: Vec::new(),
             menus: Vec::new(),
             buffer_editor: None,
             cursor_shapes: None,
+            bracketed_paste: true,
             bracket_paste_enabled: false,
+            mouse_capture: false,
+            mouse_capture_enabled: false,
+            paused_at: None,
+            kept_line: None,
+            repaint_interval: None,
+            last_repaint: None,
+            completion_debounce: None,
+            pending_menu_edit: None,
             #[cfg(feature = "external_printer")]
             external_printer: None,
+            #[cfg(feature = "external_printer")]
+            input_poller: None,
         }
     }
 
@@ -225,6 +418,18 @@ impl Reedline {
         Some(HistorySessionId::new(nanos))
     }
 
+    /// A builder that configures whether [`Reedline::read_line`] turns on bracketed paste
+    ///
+    /// With bracketed paste the terminal marks pasted text, so it is inserted as a whole,
+    /// including newlines, instead of being handled like typed keys where a newline accepts
+    /// the line. It is enabled by default and turned off again when `read_line` returns.
+    /// Disable it if the application handles pasting itself.
+    #[must_use]
+    pub fn with_bracketed_paste(mut self, bracketed_paste: bool) -> Self {
+        self.bracketed_paste = bracketed_paste;
+        self
+    }
+
     /// Enable BracketedPaste feature.
     pub fn enable_bracketed_paste(&mut self) -> Result<()> {
         let res = execute!(io::stdout(), EnableBracketedPaste);
@@ -243,11 +448,33 @@ impl Reedline {
         res
     }
 
-    /// Return the previously generated history session id
+    /// A builder that configures whether [`Reedline::read_line`] turns on the mouse capture
+    ///
+    /// With the mouse capture a click on a menu value selects it and a click on the
+    /// selected value accepts it. The scroll wheel scrolls the menu by pages. It is
+    /// disabled by default, as the terminal doesn't select text with the mouse while
+    /// the mouse is captured, and turned off again when `read_line` returns.
+    #[must_use]
+    pub fn with_mouse_capture(mut self, mouse_capture: bool) -> Self {
+        self.mouse_capture = mouse_capture;
+        self
+    }
+
+    /// Return the history session id, generated by [`Reedline::create`] unless it was
+    /// replaced
     pub fn get_history_session_id(&self) -> Option<HistorySessionId> {
         self.history_session_id
     }
 
+    /// The history session id the reverse history search is restricted to
+    fn history_search_session_id(&self) -> Option<HistorySessionId> {
+        if self.history_search_all_sessions {
+            None
+        } else {
+            self.history_session_id
+        }
+    }
+
     /// Set a new history session id
     /// This should be used in situations where the user initially did not have a history_session_id
     /// and then later realized they want to have one without restarting the application.
@@ -256,6 +483,25 @@ impl Reedline {
         Ok(())
     }
 
+    /// Set the exit status of the command run for the last accepted line, to be shown by
+    /// [`Prompt::render_prompt_status`]
+    ///
+    /// Without a `duration` the time from accepting the line until the next prompt is
+    /// painted is used. The status is cleared when the next line is accepted.
+    pub fn set_last_command_status(&mut self, exit_code: i32, duration: Option<Duration>) {
+        self.measure_command_duration = duration.is_none();
+        self.last_command_status = Some(CommandStatus {
+            exit_code,
+            duration: duration.unwrap_or_default(),
+        });
+    }
+
+    /// The status set with [`Reedline::set_last_command_status`], if it wasn't cleared
+    /// by accepting a line since
+    pub fn last_command_status(&self) -> Option<CommandStatus> {
+        self.last_command_status
+    }
+
     /// A builder to include a [`Hinter`] in your instance of the Reedline engine
     /// # Example
     /// ```rust
@@ -285,6 +531,32 @@ impl Reedline {
         self
     }
 
+    /// A builder that hides the hint while a menu is active (default: true)
+    ///
+    /// The hint can't be completed while it is hidden. It is shown again once the
+    /// menu is deactivated, if it still applies to the buffer.
+    #[must_use]
+    pub fn with_hide_hints_in_menus(mut self, hide_hints_in_menus: bool) -> Self {
+        self.hide_hints_in_menus = hide_hints_in_menus;
+        self
+    }
+
+    /// A builder that configures the order of the layers painted over the buffer,
+    /// and which of them are enabled. See [`RenderPipeline`] for the default order
+    #[must_use]
+    pub fn with_render_pipeline(mut self, render_pipeline: RenderPipeline) -> Self {
+        self.render_pipeline = render_pipeline;
+        self
+    }
+
+    /// A builder that sets the style of the selected text, e.g. in the vi visual mode
+    /// or the placeholder of a snippet (default: reversed colors)
+    #[must_use]
+    pub fn with_selection_style(mut self, selection_style: Style) -> Self {
+        self.selection_style = selection_style;
+        self
+    }
+
     /// A builder to configure the tab completion
     /// # Example
     /// ```rust
@@ -324,6 +596,119 @@ impl Reedline {
         self
     }
 
+    /// Turn on bash style two stage completions
+    ///
+    /// Activating a menu first tries to complete the line without showing the menu:
+    /// a single candidate is accepted right away and otherwise the common prefix of
+    /// all candidates is inserted. Only when the candidates remain ambiguous, without
+    /// any prefix left to extend, the menu is shown so the following presses cycle
+    /// through the candidates.
+    #[must_use]
+    pub fn with_accept_unambiguous_prefix(mut self, accept_unambiguous_prefix: bool) -> Self {
+        self.accept_unambiguous_prefix = accept_unambiguous_prefix;
+        self
+    }
+
+    /// Turn on readline style completions that never show a menu
+    ///
+    /// Activating a menu of completions, e.g. with Tab, completes the line in place
+    /// with the suggestions of the completer instead, whether the menu is registered
+    /// or not: a single candidate is accepted and otherwise the common prefix of all
+    /// candidates is inserted. Activating it again while the candidates remain
+    /// ambiguous prints them below the prompt and the editing continues on a new
+    /// prompt. History menus and menus with their own completer are shown as usual.
+    #[must_use]
+    pub fn with_inline_completions(mut self, inline_completions: bool) -> Self {
+        self.inline_completions = inline_completions;
+        self
+    }
+
+    /// A builder that sets the abbreviations expanded while typing, like the
+    /// abbreviations of fish
+    ///
+    /// When the word before the cursor is one of the abbreviations as a whole, typing
+    /// a space or submitting the line replaces it with its expansion, e.g. `gco` with
+    /// `git checkout`. The expansion is a separate undo step.
+    /// Inserting a space with [`EditCommand::InsertString`], bound to Ctrl-Space by
+    /// default, keeps the abbreviation as it is typed.
+    #[must_use]
+    pub fn with_abbreviations(mut self, abbreviations: HashMap<String, String>) -> Self {
+        self.abbreviations = abbreviations;
+        self
+    }
+
+    /// A builder that configures what activating an already active menu does
+    ///
+    /// With Tab bound to activate the menu or move to the next entry, this selects
+    /// whether Tab moves to the next entry (the default), accepts the selection or
+    /// extends the common prefix first. See [`MenuTabBehavior`] for the details.
+    #[must_use]
+    pub fn with_menu_tab_behavior(mut self, menu_tab_behavior: MenuTabBehavior) -> Self {
+        self.menu_tab_behavior = menu_tab_behavior;
+        self
+    }
+
+    /// A builder that configures how [`ReedlineEvent::QuotedInsert`] inserts a control
+    /// key, as the control character itself (the default) or in caret notation
+    #[must_use]
+    pub fn with_quoted_insert_mode(mut self, quoted_insert_mode: QuotedInsertMode) -> Self {
+        self.quoted_insert_mode = quoted_insert_mode;
+        self
+    }
+
+    /// A builder that configures how lines wider than the terminal are displayed
+    ///
+    /// See [`LineWrapMode`] for the available modes, the default is [`LineWrapMode::Wrap`]
+    #[must_use]
+    pub fn with_line_wrap_mode(mut self, line_wrap_mode: LineWrapMode) -> Self {
+        self.painter.set_line_wrap_mode(line_wrap_mode);
+        self
+    }
+
+    /// A builder that limits how many lines of a multiline buffer are displayed
+    ///
+    /// Longer buffers are folded to a window of lines centered on the cursor and the
+    /// lines at the edges of the window are replaced by `… N lines hidden …` markers.
+    /// At least three lines are displayed. Only the display is folded, the whole buffer
+    /// stays editable and is submitted unchanged. The lines are counted before they
+    /// wrap in the terminal. An active menu is painted below the folded buffer, so the
+    /// rows freed by the fold are available to the menu. `None` (the default) shows the
+    /// whole buffer.
+    #[must_use]
+    pub fn with_max_rendered_lines(mut self, max_rendered_lines: Option<u16>) -> Self {
+        self.painter.set_max_rendered_lines(max_rendered_lines);
+        self
+    }
+
+    /// A builder that limits how often the input events repaint the prompt and buffer
+    ///
+    /// All the pending input events are always processed before a single repaint.
+    /// With an interval set, a repaint is additionally delayed until the interval has
+    /// passed since the previous one, merging the changes of fast input (e.g. key
+    /// autorepeat) into fewer repaints. `None` (the default) repaints as soon as the
+    /// queued events have been handled.
+    ///
+    /// Messages from the external printer are checked on every iteration of the event
+    /// loop and printed right away, independent of this interval.
+    #[must_use]
+    pub fn with_repaint_interval(mut self, repaint_interval: Option<Duration>) -> Self {
+        self.repaint_interval = repaint_interval;
+        self
+    }
+
+    /// A builder that delays the completions of the active menu while typing
+    ///
+    /// With an interval set, an edit while a menu is shown doesn't call the completer
+    /// right away. The menu keeps showing the previous values until no edit happened
+    /// for the interval, or until another event (e.g. moving in the menu or accepting
+    /// a value) needs the current values. This keeps typing responsive with expensive
+    /// completers. `None` (the default) updates the values after every edit.
+    #[must_use]
+    pub fn with_completion_debounce(mut self, completion_debounce: Option<Duration>) -> Self {
+        self.completion_debounce = completion_debounce;
+        self
+    }
+
     /// A builder which enables or disables the use of ansi coloring in the prompt
     /// and in the command line syntax highlighting.
     #[must_use]
@@ -332,6 +717,53 @@ impl Reedline {
         self
     }
 
+    /// A builder that sets the colors the terminal is able to display
+    ///
+    /// By default the level is detected from the `NO_COLOR`, `COLORTERM` and `TERM`
+    /// environment variables. RGB and 256 colors beyond the level are replaced by the
+    /// nearest color of the level, [`ColorLevel::NoColor`] removes all styling.
+    #[must_use]
+    pub fn with_color_level(mut self, color_level: ColorLevel) -> Self {
+        self.painter.set_color_level(color_level);
+        self
+    }
+
+    /// A builder that sets the number of columns between two tab stops
+    ///
+    /// Tabs in the prompt, the buffer and the hint advance to the next multiple of
+    /// `tab_width` screen columns. A tab that would cross the right edge of the terminal
+    /// moves to the next row. Defaults to 8.
+    #[must_use]
+    pub fn with_tab_width(mut self, tab_width: usize) -> Self {
+        self.painter.set_tab_width(tab_width);
+        self
+    }
+
+    /// A builder that renders the prompt indicator of the history search
+    ///
+    /// The indicator replaces [`Prompt::render_prompt_history_search_indicator`] and
+    /// has access to the search term, its status and the position of the shown match,
+    /// e.g. to render `(i-search [3/50]): `. Indicators wider than half of the terminal
+    /// are clipped and ANSI escapes are removed if the coloring is turned off.
+    #[must_use]
+    pub fn with_history_search_indicator(
+        mut self,
+        history_search_indicator: HistorySearchIndicator,
+    ) -> Self {
+        self.history_search_indicator = Some(history_search_indicator);
+        self
+    }
+
+    /// A builder that sets the keybindings of the history search
+    ///
+    /// While the search is active they take precedence over the keybindings of the
+    /// edit mode. Defaults to [`default_history_search_keybindings`](crate::default_history_search_keybindings).
+    #[must_use]
+    pub fn with_history_search_keybindings(mut self, keybindings: Keybindings) -> Self {
+        self.history_search_keybindings = keybindings;
+        self
+    }
+
     /// A builder that configures the highlighter for your instance of the Reedline engine
     /// # Example
     /// ```rust
@@ -395,6 +827,19 @@ impl Reedline {
         self
     }
 
+    /// A builder that places the cursor of a command line recalled from the history
+    /// where it was when the line was accepted
+    ///
+    /// The position is stored by histories supporting
+    /// [`HistoryItem::cursor_position`], like
+    /// `SqliteBackedHistory`. Entries without a stored
+    /// position put the cursor at the end of the line, as it is by default.
+    #[must_use]
+    pub fn with_history_cursor_restore(mut self, history_restores_cursor: bool) -> Self {
+        self.history_restores_cursor = history_restores_cursor;
+        self
+    }
+
     /// A builder that configures the validator for your instance of the Reedline engine
     /// # Example
     /// ```rust
@@ -435,12 +880,92 @@ impl Reedline {
     }
 
     /// A builder which configures the edit mode for your instance of the Reedline engine
+    ///
+    /// Consecutive kills are merged into one kill ring entry only in the Emacs mode,
+    /// the cuts of the other modes are kept apart
     #[must_use]
     pub fn with_edit_mode(mut self, edit_mode: Box<dyn EditMode>) -> Self {
+        self.editor
+            .set_kill_coalescing(matches!(edit_mode.edit_mode(), PromptEditMode::Emacs));
         self.edit_mode = edit_mode;
         self
     }
 
+    /// A builder that registers a text generator, e.g. for timestamps or UUIDs
+    ///
+    /// The generators are indexed in the order they are registered, starting at 0.
+    /// [`EditCommand::InsertGenerated`] with the index inserts the generated text at
+    /// the cursor as an undoable edit.
+    #[must_use]
+    pub fn with_text_generator(mut self, generator: Box<dyn Fn() -> String + Send>) -> Self {
+        self.text_generators.push(generator);
+        self
+    }
+
+    /// A builder that observes the edit commands run over the buffer
+    ///
+    /// The observer is called with every [`EditCommand`] dispatched to the buffer,
+    /// including the commands that leave it unchanged, e.g. a `Backspace` at the
+    /// start of the line. [`EditCommand::InsertGenerated`] is observed as is, not as
+    /// the text it inserts. A recorded sequence can be played back with
+    /// [`Reedline::run_edit_commands`], which makes it possible to build keyboard
+    /// macros on top of this hook.
+    ///
+    /// # Example
+    /// ```rust
+    /// use reedline::{EditCommand, Reedline};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let recorded = Arc::new(Mutex::new(Vec::new()));
+    /// let recorder = Arc::clone(&recorded);
+    /// let mut line_editor = Reedline::create().with_edit_command_observer(Box::new(
+    ///     move |command: &EditCommand| recorder.lock().unwrap().push(command.clone()),
+    /// ));
+    ///
+    /// line_editor.run_edit_commands(&[EditCommand::InsertString("ls".into())]);
+    /// assert_eq!(
+    ///     *recorded.lock().unwrap(),
+    ///     vec![EditCommand::InsertString("ls".into())]
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_edit_command_observer(mut self, observer: EditCommandObserver) -> Self {
+        self.edit_command_observer = Some(observer);
+        self
+    }
+
+    /// A builder that appends a hook intercepting the events before they are handled
+    ///
+    /// The hooks are called in the order they were added with the [`Editor`] and every
+    /// event produced by the edit mode. The editor gives access to the buffer and the
+    /// cursor and runs [`EditCommand`]s. An [`EventAction`] passes the event on to the
+    /// next hook, replaces it with other events, that only the following hooks see,
+    /// or swallows it. This makes it possible to log, remap or inject events, e.g. to
+    /// play back a recorded macro.
+    ///
+    /// # Example
+    /// ```rust
+    /// use reedline::{EditCommand, EventAction, Reedline, ReedlineEvent};
+    ///
+    /// // Ignore the requests to clear the screen unless the cursor is at the start and
+    /// // list the directory when submitting an empty line
+    /// let mut line_editor = Reedline::create().with_event_hook(Box::new(|editor, event| {
+    ///     match event {
+    ///         ReedlineEvent::ClearScreen if editor.insertion_point() > 0 => EventAction::Swallow,
+    ///         ReedlineEvent::Enter if editor.is_empty() => {
+    ///             editor.run_edit_command(&EditCommand::InsertString("ls".into()));
+    ///             EventAction::Pass
+    ///         }
+    ///         _ => EventAction::Pass,
+    ///     }
+    /// }));
+    /// ```
+    #[must_use]
+    pub fn with_event_hook(mut self, hook: EventHook) -> Self {
+        self.event_hooks.push(hook);
+        self
+    }
+
     /// A builder that appends a menu to the engine
     #[must_use]
     pub fn with_menu(mut self, menu: ReedlineMenu) -> Self {
@@ -462,6 +987,74 @@ impl Reedline {
         self
     }
 
+    /// A builder that sets whether the reverse history search spans the entries of all
+    /// sessions (default) or only those of the session set with
+    /// [`Reedline::with_history_session_id`]
+    ///
+    /// The up/down history navigation stays restricted to the session either way.
+    /// Without a history session id every query spans all sessions.
+    #[must_use]
+    pub fn with_history_search_across_sessions(mut self, across_sessions: bool) -> Self {
+        self.history_search_all_sessions = across_sessions;
+        self
+    }
+
+    /// A builder that sets how many kills are kept to cycle through with
+    /// [`EditCommand::YankPop`], 60 by default
+    ///
+    /// A size of 0 disables the kill ring.
+    #[must_use]
+    pub fn with_kill_ring_size(mut self, size: usize) -> Self {
+        self.editor.set_kill_ring_size(size);
+        self
+    }
+
+    /// A builder that sets the clipboard used by cut, copy and paste
+    ///
+    /// By default the system clipboard is used if the `system_clipboard` feature is
+    /// enabled, otherwise a clipboard local to this instance.
+    ///
+    /// ```rust
+    /// use reedline::{LocalClipboard, Reedline};
+    ///
+    /// let line_editor = Reedline::create().with_clipboard(Box::new(LocalClipboard::new()));
+    /// ```
+    #[must_use]
+    pub fn with_clipboard(mut self, clipboard: Box<dyn Clipboard>) -> Self {
+        self.editor.set_clipboard(clipboard);
+        self
+    }
+
+    /// A builder that sets the predicate deciding which characters belong to a word
+    ///
+    /// The word movements and deletions consult it instead of the Unicode word
+    /// boundaries used by default, e.g. to make `-` part of a word:
+    ///
+    /// ```rust
+    /// use reedline::{CharClass, Reedline};
+    ///
+    /// let line_editor = Reedline::create().with_word_char_class(|c| match c {
+    ///     '-' => CharClass::Word,
+    ///     c => CharClass::of(c),
+    /// });
+    /// ```
+    #[must_use]
+    pub fn with_word_char_class(mut self, word_char_class: fn(char) -> CharClass) -> Self {
+        self.editor.set_word_char_class(Some(word_char_class));
+        self
+    }
+
+    /// A builder that limits the number of edits that can be undone
+    ///
+    /// The oldest edits are dropped once the limit is exceeded. Without a limit (default)
+    /// the undo history of an entry grows with every edit.
+    /// A size of 0 disables undo and redo.
+    #[must_use]
+    pub fn with_undo_stack_size(mut self, size: usize) -> Self {
+        self.editor.set_undo_stack_size(size);
+        self
+    }
+
     /// A builder that enables reedline changing the cursor shape based on the current edit mode.
     /// The current implementation sets the cursor shape when drawing the prompt.
     /// Do not use this if the cursor shape is set elsewhere, e.g. in the terminal settings or by ansi escape sequences.
@@ -532,10 +1125,57 @@ impl Reedline {
         &mut *self.history
     }
 
+    /// Names of the registered menus in the order they were added
+    pub fn menu_names(&self) -> Vec<&str> {
+        self.menus.iter().map(|menu| menu.name()).collect()
+    }
+
+    /// Mutable access to the registered menu called `name`
+    ///
+    /// Returns `None` if no menu with that name was added. The menu can be
+    /// reconfigured at runtime by replacing its boxed menu, e.g. with a
+    /// [`ColumnarMenu`](crate::ColumnarMenu) built with other columns or styles.
+    pub fn menu_mut(&mut self, name: &str) -> Option<&mut ReedlineMenu> {
+        self.menus.iter_mut().find(|menu| menu.name() == name)
+    }
+
     /// Update the underlying [`History`] to/from disk
+    ///
+    /// Writes the pending local entries and picks up the entries written by other
+    /// sessions in the meantime, so the following navigation and search see them.
+    /// The history is only as fresh as the last sync: [`FileBackedHistory`] reads the
+    /// file during the sync, while `SqliteBackedHistory`
+    /// writes and queries the shared database right away.
+    ///
+    /// The buffer that is currently edited is not saved. An ongoing history navigation
+    /// keeps the entry it shows as the edited buffer and starts again from the newest
+    /// entry, an ongoing history search is repeated on the synced entries.
     pub fn sync_history(&mut self) -> std::io::Result<()> {
-        // TODO: check for interactions in the non-submitting events
-        self.history.sync()
+        self.history.sync()?;
+
+        // The ids of the entries may have changed with the sync
+        self.history_cursor_on_last_command = false;
+        match self.input_mode {
+            InputMode::Regular => {}
+            InputMode::HistoryTraversal => {
+                if let Some(string) = self.history_cursor.string_at_cursor() {
+                    self.editor
+                        .set_buffer(string, UndoBehavior::CreateUndoPoint);
+                }
+                self.input_mode = InputMode::Regular;
+            }
+            InputMode::HistorySearch => {
+                self.history_cursor = HistoryCursor::new(
+                    self.history_cursor.get_navigation(),
+                    self.history_search_session_id(),
+                );
+                self.history_cursor
+                    .back(self.history.as_ref())
+                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+            }
+        }
+
+        Ok(())
     }
 
     /// Check if any commands have been run.
@@ -568,13 +1208,69 @@ impl Reedline {
     /// Returns a [`crossterm::Result`] in which the `Err` type is [`crossterm::ErrorKind`]
     /// to distinguish I/O errors and the `Ok` variant wraps a [`Signal`] which
     /// handles user inputs.
+    ///
+    /// If the host panics while the line is being read, raw mode is disabled,
+    /// the cursor shape is reset and bracketed paste is turned off during unwinding
+    /// so the terminal is not left in a broken state.
     pub fn read_line(&mut self, prompt: &dyn Prompt) -> Result<Signal> {
+        self.read_line_until(prompt, None)
+            .map(|signal| signal.expect("reading without a deadline never times out"))
+    }
+
+    /// Wait for input like [`Reedline::read_line`], but return
+    /// [`TimedSignal::Timeout`] if no entry was finished within `timeout`.
+    ///
+    /// The partially edited line, including the cursor position, is kept and the next
+    /// call to `read_line_timeout` or [`Reedline::read_line`] continues editing it where it
+    /// is painted. This lets a REPL wake up periodically, e.g. to check a channel, while
+    /// the user is typing.
+    ///
+    /// The `timeout` is a deadline counted from the call, typing doesn't extend it.
+    ///
+    /// Output of the REPL between the calls is best sent through an
+    /// `ExternalPrinter` (`external_printer` feature), which prints it above the prompt. Output
+    /// written to the terminal directly starts in the painted line, the next call notices
+    /// the moved cursor and paints the prompt again below it.
+    pub fn read_line_timeout(
+        &mut self,
+        prompt: &dyn Prompt,
+        timeout: Duration,
+    ) -> Result<TimedSignal> {
+        let signal = self.read_line_until(prompt, Some(Instant::now() + timeout))?;
+        Ok(signal.map_or(TimedSignal::Timeout, TimedSignal::Signal))
+    }
+
+    /// Sets up the terminal around [`Reedline::read_line_helper`], `None` if the
+    /// `deadline` passed
+    fn read_line_until(
+        &mut self,
+        prompt: &dyn Prompt,
+        deadline: Option<Instant>,
+    ) -> Result<Option<Signal>> {
+        if self.bracketed_paste && !self.bracket_paste_enabled {
+            // Terminals without support just deliver pasted text as typed keys
+            let _ = self.enable_bracketed_paste();
+        }
+        if self.mouse_capture && !self.mouse_capture_enabled {
+            self.mouse_capture_enabled = execute!(io::stdout(), EnableMouseCapture).is_ok();
+        }
+        let _guard = TerminalGuard::new(
+            io::stdout(),
+            self.cursor_shapes.is_some(),
+            self.bracket_paste_enabled,
+            self.mouse_capture_enabled,
+        );
         terminal::enable_raw_mode()?;
 
-        let result = self.read_line_helper(prompt);
+        let result = self.read_line_helper(prompt, deadline);
 
-        #[cfg(not(target_os = "windows"))]
-        self.disable_bracketed_paste()?;
+        if self.bracket_paste_enabled {
+            self.disable_bracketed_paste()?;
+        }
+        if self.mouse_capture_enabled {
+            execute!(io::stdout(), DisableMouseCapture)?;
+            self.mouse_capture_enabled = false;
+        }
         terminal::disable_raw_mode()?;
         result
     }
@@ -609,16 +1305,60 @@ impl Reedline {
         Ok(())
     }
 
-    /// Helper implementing the logic for [`Reedline::read_line()`] to be wrapped
-    /// in a `raw_mode` context.
-    fn read_line_helper(&mut self, prompt: &dyn Prompt) -> Result<Signal> {
-        self.painter.initialize_prompt_position()?;
-        self.hide_hints = false;
+    /// Sets the duration of the last command status to the time since its line was
+    /// accepted, if the application didn't give one
+    fn measure_last_command(&mut self) {
+        if std::mem::take(&mut self.measure_command_duration) {
+            if let (Some(status), Some(accepted_at)) =
+                (self.last_command_status.as_mut(), self.line_accepted_at)
+            {
+                status.duration = accepted_at.elapsed();
+            }
+        }
+    }
 
-        self.repaint(prompt)?;
+    /// Puts the segment the prompt renders for the last command status before the
+    /// left prompt
+    fn add_status_segment(&self, prompt: &dyn Prompt, lines: &mut PromptLines) {
+        if let Some(segment) = self
+            .last_command_status
+            .and_then(|status| prompt.render_prompt_status(&status))
+        {
+            lines.prompt_str_left = format!("{segment}{}", lines.prompt_str_left).into();
+        }
+    }
+
+    /// Start the buffer with the line kept by [`ReedlineEvent::SubmitAndKeep`], selected
+    fn restore_kept_line(&mut self) {
+        if let Some(line_buffer) = self.kept_line.take() {
+            self.editor.set_line_buffer_as_baseline(line_buffer);
+            self.editor.select_all();
+        }
+    }
+
+    /// Helper implementing the logic for [`Reedline::read_line()`] to be wrapped
+    /// in a `raw_mode` context.
+    fn read_line_helper(
+        &mut self,
+        prompt: &dyn Prompt,
+        deadline: Option<Instant>,
+    ) -> Result<Option<Signal>> {
+        match self.paused_at.take() {
+            Some(position) if cursor::position()? == position => {}
+            Some(_) => self.painter.initialize_prompt_position()?,
+            None => {
+                self.painter.initialize_prompt_position()?;
+                self.measure_last_command();
+            }
+        }
+        self.restore_kept_line();
+        self.hide_hints = false;
+
+        self.repaint(prompt)?;
 
         let mut crossterm_events: Vec<ReedlineRawEvent> = vec![];
         let mut reedline_events: Vec<ReedlineEvent> = vec![];
+        let mut repaint_pending = false;
 
         loop {
             let mut paste_enter_state = false;
@@ -629,16 +1369,41 @@ impl Reedline {
                 let messages = Self::external_messages(external_printer)?;
                 if !messages.is_empty() {
                     // print the message(s)
-                    self.painter.print_external_message(
-                        messages,
-                        self.editor.line_buffer(),
-                        prompt,
-                    )?;
+                    self.painter.print_external_message(messages)?;
                     self.repaint(prompt)?;
+                    repaint_pending = false;
                 }
             }
 
-            if event::poll(Duration::from_millis(100))? {
+            let time_left = match deadline {
+                Some(deadline) => match time_until(deadline, Instant::now()) {
+                    Some(time_left) => Some(time_left),
+                    None => {
+                        if repaint_pending {
+                            self.repaint(prompt)?;
+                        }
+                        self.paused_at = Some(cursor::position()?);
+                        return Ok(None);
+                    }
+                },
+                None => None,
+            };
+
+            // A delayed repaint, input held back by the edit mode or the deadline
+            // shortens the wait for new events
+            let poll_timeout = match self.time_until_repaint() {
+                Some(remaining) if repaint_pending => remaining,
+                _ => Duration::from_millis(100),
+            };
+            let poll_timeout = self
+                .edit_mode
+                .timeout()
+                .into_iter()
+                .chain(self.time_until_menu_edit())
+                .chain(time_left)
+                .fold(poll_timeout, Duration::min);
+
+            if self.poll_input(poll_timeout)? {
                 let mut latest_resize = None;
 
                 // There could be multiple events queued up!
@@ -688,7 +1453,7 @@ impl Reedline {
                 // (Text should only be `EditCommand::InsertChar`s)
                 let mut last_edit_commands = None;
                 for event in crossterm_events.drain(..) {
-                    match (&mut last_edit_commands, self.edit_mode.parse_event(event)) {
+                    match (&mut last_edit_commands, self.parse_event(event)) {
                         (None, ReedlineEvent::Edit(ec)) => {
                             last_edit_commands = Some(ec);
                         }
@@ -710,16 +1475,35 @@ impl Reedline {
                 }
             };
 
+            match self.edit_mode.handle_timeout() {
+                ReedlineEvent::None => {}
+                event => reedline_events.push(event),
+            }
+
+            if self.refresh_background_completions() {
+                repaint_pending = true;
+            }
+
+            if self.time_until_menu_edit() == Some(Duration::ZERO) && self.flush_menu_edit() {
+                repaint_pending = true;
+            }
+
+            if !self.event_hooks.is_empty() {
+                reedline_events = self.apply_event_hooks(std::mem::take(&mut reedline_events));
+            }
+
             for event in reedline_events.drain(..) {
-                match self.handle_event(prompt, event)? {
+                let status = self.handle_event(prompt, event)?;
+                self.write_clipboard_output()?;
+                match status {
                     EventStatus::Exits(signal) => {
                         // Move the cursor below the input area, for external commands or new read_line call
                         self.painter.move_cursor_to_end()?;
-                        return Ok(signal);
+                        return Ok(Some(signal));
                     }
                     EventStatus::Handled => {
                         if !paste_enter_state {
-                            self.repaint(prompt)?;
+                            repaint_pending = true;
                         }
                     }
                     EventStatus::Inapplicable => {
@@ -727,10 +1511,101 @@ impl Reedline {
                     }
                 }
             }
+
+            // All the queued events are handled before painting once
+            if repaint_pending && self.time_until_repaint().is_none() {
+                self.repaint(prompt)?;
+                repaint_pending = false;
+            }
+        }
+    }
+
+    /// Passes the events through the event hooks in their order
+    fn apply_event_hooks(&mut self, mut events: Vec<ReedlineEvent>) -> Vec<ReedlineEvent> {
+        for hook in &mut self.event_hooks {
+            let mut hooked = Vec::with_capacity(events.len());
+            for event in events {
+                match hook(&mut self.editor, &event) {
+                    EventAction::Pass => hooked.push(event),
+                    EventAction::Replace(replacement) => hooked.extend(replacement),
+                    EventAction::Swallow => {}
+                }
+            }
+            events = hooked;
+        }
+        events
+    }
+
+    /// Refreshes the active menu with the completions computed in the background
+    /// since the last check, keeping its selection while more of them stream in.
+    /// Returns true if the menu has to be repainted
+    fn refresh_background_completions(&mut self) -> bool {
+        match self.menus.iter_mut().find(|menu| menu.is_active()) {
+            Some(menu) => menu.poll_completions(self.completer.as_mut()),
+            None => {
+                // There is no menu to show them in
+                self.completer.poll_completions();
+                false
+            }
+        }
+    }
+
+    /// Time left until the completions of the pending menu edit are due, zero if they
+    /// are overdue. `None` without a pending edit
+    fn time_until_menu_edit(&self) -> Option<Duration> {
+        let edited = self.pending_menu_edit?;
+        let debounce = self.completion_debounce.unwrap_or_default();
+
+        Some(debounce.saturating_sub(edited.elapsed()))
+    }
+
+    /// Updates the values of the active menu with the pending edit. Returns true if
+    /// the menu has to be repainted
+    fn flush_menu_edit(&mut self) -> bool {
+        if self.pending_menu_edit.take().is_none() {
+            return false;
+        }
+
+        match self.menus.iter_mut().find(|menu| menu.is_active()) {
+            Some(menu) => {
+                // Handled right away, so a following menu event doesn't replace it
+                menu.menu_event(MenuEvent::Edit(false));
+                menu.update_working_details(
+                    &mut self.editor,
+                    self.completer.as_mut(),
+                    self.history.as_ref(),
+                    &self.painter,
+                );
+                true
+            }
+            None => false,
         }
     }
 
+    /// Time left until the configured repaint interval has passed since the last
+    /// repaint. `None` if a repaint can happen right away
+    fn time_until_repaint(&self) -> Option<Duration> {
+        let interval = self.repaint_interval?;
+        let elapsed = self.last_repaint?.elapsed();
+
+        interval
+            .checked_sub(elapsed)
+            .filter(|remaining| !remaining.is_zero())
+    }
+
     fn handle_event(&mut self, prompt: &dyn Prompt, event: ReedlineEvent) -> Result<EventStatus> {
+        // The events of a macro being played were already recorded with the macro
+        if self.playing_macros.is_empty() {
+            if let Some((_, events)) = self.macro_recording.as_mut() {
+                if !matches!(
+                    event,
+                    ReedlineEvent::StartMacroRecording(_) | ReedlineEvent::StopMacroRecording
+                ) {
+                    events.push(event.clone());
+                }
+            }
+        }
+
         if self.input_mode == InputMode::HistorySearch {
             self.handle_history_search_event(event)
         } else {
@@ -768,6 +1643,20 @@ impl Reedline {
                 self.input_mode = InputMode::Regular;
                 Ok(EventStatus::Exits(Signal::CtrlC))
             }
+            ReedlineEvent::ExitOnEmpty => {
+                if self.editor.is_empty() {
+                    self.input_mode = InputMode::Regular;
+                    self.editor.reset_undo_stack();
+                    Ok(EventStatus::Exits(Signal::CtrlD))
+                } else {
+                    Ok(EventStatus::Inapplicable)
+                }
+            }
+            ReedlineEvent::ClearAndReprint => {
+                self.input_mode = InputMode::Regular;
+                self.clear_and_reprint()?;
+                Ok(EventStatus::Handled)
+            }
             ReedlineEvent::ClearScreen => {
                 self.painter.clear_screen()?;
                 Ok(EventStatus::Handled)
@@ -777,12 +1666,23 @@ impl Reedline {
                 Ok(EventStatus::Handled)
             }
             ReedlineEvent::Enter
+            | ReedlineEvent::MenuSubmit
             | ReedlineEvent::HistoryHintComplete
             | ReedlineEvent::Submit
-            | ReedlineEvent::SubmitOrNewline => {
+            | ReedlineEvent::SubmitOrNewline
+            | ReedlineEvent::SubmitAndKeep => {
                 if let Some(string) = self.history_cursor.string_at_cursor() {
+                    // The cursor is placed at the start of the match
+                    let match_start = match self.history_cursor.get_navigation() {
+                        HistoryNavigationQuery::SubstringSearch(substring) => {
+                            string.find(&substring).unwrap_or(string.len())
+                        }
+                        _ => string.len(),
+                    };
                     self.editor
                         .set_buffer(string, UndoBehavior::CreateUndoPoint);
+                    self.editor
+                        .run_edit_command(&EditCommand::MoveToPosition(match_start));
                 }
 
                 self.input_mode = InputMode::Regular;
@@ -796,22 +1696,21 @@ impl Reedline {
                 self.run_history_commands(&commands);
                 Ok(EventStatus::Handled)
             }
-            ReedlineEvent::Mouse => Ok(EventStatus::Handled),
-            ReedlineEvent::Resize(width, height) => {
-                self.painter.handle_resize(width, height);
-                Ok(EventStatus::Inapplicable)
-            }
+            ReedlineEvent::Mouse | ReedlineEvent::MenuClick(_, _) => Ok(EventStatus::Handled),
+            ReedlineEvent::Resize(width, height) => Ok(self.handle_resize(width, height)),
             ReedlineEvent::Repaint => {
                 // A handled Event causes a repaint
                 Ok(EventStatus::Handled)
             }
             ReedlineEvent::PreviousHistory | ReedlineEvent::Up | ReedlineEvent::SearchHistory => {
+                self.history_search_direction = SearchDirection::Backward;
                 self.history_cursor
                     .back(self.history.as_ref())
                     .expect("todo: error handling");
                 Ok(EventStatus::Handled)
             }
             ReedlineEvent::NextHistory | ReedlineEvent::Down => {
+                self.history_search_direction = SearchDirection::Forward;
                 self.history_cursor
                     .forward(self.history.as_ref())
                     .expect("todo: error handling");
@@ -833,7 +1732,10 @@ impl Reedline {
             | ReedlineEvent::Multiple(_)
             | ReedlineEvent::None
             | ReedlineEvent::HistoryHintWordComplete
+            | ReedlineEvent::HistoryHintNext
+            | ReedlineEvent::HistoryHintPrevious
             | ReedlineEvent::OpenEditor
+            | ReedlineEvent::InsertLastCommand
             | ReedlineEvent::Menu(_)
             | ReedlineEvent::MenuNext
             | ReedlineEvent::MenuPrevious
@@ -842,7 +1744,20 @@ impl Reedline {
             | ReedlineEvent::MenuLeft
             | ReedlineEvent::MenuRight
             | ReedlineEvent::MenuPageNext
-            | ReedlineEvent::MenuPagePrevious => Ok(EventStatus::Inapplicable),
+            | ReedlineEvent::MenuPagePrevious
+            | ReedlineEvent::MenuToggleDocumentation
+            | ReedlineEvent::MenuDocumentationUp
+            | ReedlineEvent::MenuDocumentationDown
+            | ReedlineEvent::MenuDelete
+            | ReedlineEvent::MenuAccept
+            | ReedlineEvent::PlayMacro(_)
+            | ReedlineEvent::PlayMacroTimes(_, _) => Ok(EventStatus::Inapplicable),
+            // The next key was already taken when the event was parsed
+            ReedlineEvent::QuotedInsert => Ok(EventStatus::Handled),
+            ReedlineEvent::StartMacroRecording(register) => {
+                Ok(self.start_macro_recording(register))
+            }
+            ReedlineEvent::StopMacroRecording => Ok(self.stop_macro_recording()),
         }
     }
 
@@ -851,13 +1766,31 @@ impl Reedline {
         prompt: &dyn Prompt,
         event: ReedlineEvent,
     ) -> io::Result<EventStatus> {
+        // Any other event may need the values of the latest edit
+        if !matches!(
+            event,
+            ReedlineEvent::Edit(_)
+                | ReedlineEvent::None
+                | ReedlineEvent::Repaint
+                | ReedlineEvent::Resize(..)
+        ) {
+            self.flush_menu_edit();
+        }
+
         match event {
             ReedlineEvent::Menu(name) => {
                 if self.active_menu().is_none() {
+                    // Menus with their own completer are still shown
+                    let shows_menu = self.menus.iter().any(|menu| {
+                        menu.name() == name && !matches!(menu, ReedlineMenu::EngineCompleter(_))
+                    });
+                    if self.inline_completions && !shows_menu {
+                        return self.complete_inline();
+                    }
                     if let Some(menu) = self.menus.iter_mut().find(|menu| menu.name() == name) {
                         menu.menu_event(MenuEvent::Activate(self.quick_completions));
 
-                        if self.quick_completions && menu.can_quick_complete() {
+                        if self.accept_unambiguous_prefix {
                             menu.update_values(
                                 &mut self.editor,
                                 self.completer.as_mut(),
@@ -865,6 +1798,39 @@ impl Reedline {
                             );
 
                             if menu.get_values().len() == 1 {
+                                menu.accept_selected_value(
+                                    &mut self.editor,
+                                    self.completer.as_mut(),
+                                    self.history.as_ref(),
+                                );
+                                return Ok(EventStatus::Handled);
+                            }
+
+                            // The common prefix may already be typed in full, in that
+                            // case the candidates are ambiguous and the menu stays open
+                            let buffer = self.editor.get_buffer().to_string();
+                            if menu.can_partially_complete(
+                                true,
+                                &mut self.editor,
+                                self.completer.as_mut(),
+                                self.history.as_ref(),
+                            ) && self.editor.get_buffer() != buffer
+                            {
+                                menu.menu_event(MenuEvent::Deactivate);
+                            }
+
+                            return Ok(EventStatus::Handled);
+                        }
+
+                        // The menu was activated expecting the values to be updated here
+                        if self.quick_completions {
+                            menu.update_values(
+                                &mut self.editor,
+                                self.completer.as_mut(),
+                                self.history.as_ref(),
+                            );
+
+                            if menu.get_values().len() == 1 && menu.can_quick_complete() {
                                 return self.handle_editor_event(prompt, ReedlineEvent::Enter);
                             }
                         }
@@ -883,7 +1849,7 @@ impl Reedline {
                         return Ok(EventStatus::Handled);
                     }
                 }
-                Ok(EventStatus::Inapplicable)
+                Ok(self.reactivate_menu(&name))
             }
             ReedlineEvent::MenuNext => {
                 self.active_menu()
@@ -941,6 +1907,45 @@ impl Reedline {
                         Ok(EventStatus::Handled)
                     })
             }
+            ReedlineEvent::MenuToggleDocumentation => {
+                self.active_menu()
+                    .map_or(Ok(EventStatus::Inapplicable), |menu| {
+                        menu.menu_event(MenuEvent::ToggleDocumentation);
+                        Ok(EventStatus::Handled)
+                    })
+            }
+            ReedlineEvent::MenuDocumentationUp => {
+                self.active_menu()
+                    .map_or(Ok(EventStatus::Inapplicable), |menu| {
+                        menu.menu_event(MenuEvent::DocumentationUp);
+                        Ok(EventStatus::Handled)
+                    })
+            }
+            ReedlineEvent::MenuDocumentationDown => {
+                self.active_menu()
+                    .map_or(Ok(EventStatus::Inapplicable), |menu| {
+                        menu.menu_event(MenuEvent::DocumentationDown);
+                        Ok(EventStatus::Handled)
+                    })
+            }
+            ReedlineEvent::MenuDelete => self.delete_history_menu_value(),
+            ReedlineEvent::MenuAccept => {
+                match self.menus.iter_mut().find(|menu| menu.is_active()) {
+                    Some(menu) => {
+                        menu.accept_selected_value(
+                            &mut self.editor,
+                            self.completer.as_mut(),
+                            self.history.as_ref(),
+                        );
+                        Ok(EventStatus::Handled)
+                    }
+                    None => Ok(EventStatus::Inapplicable),
+                }
+            }
+            ReedlineEvent::MenuSubmit => {
+                self.deactivate_menus();
+                self.handle_editor_event(prompt, ReedlineEvent::Enter)
+            }
             ReedlineEvent::HistoryHintComplete => {
                 if let Some(hinter) = self.hinter.as_mut() {
                     let current_hint = hinter.complete_hint();
@@ -969,6 +1974,26 @@ impl Reedline {
                 }
                 Ok(EventStatus::Inapplicable)
             }
+            ReedlineEvent::HistoryHintNext | ReedlineEvent::HistoryHintPrevious => {
+                let hints_shown = self.hints_active()
+                    && self.editor.is_cursor_at_buffer_end()
+                    && self.active_menu().is_none();
+                match self.hinter.as_mut() {
+                    Some(hinter) if hints_shown => {
+                        let cycled = if matches!(event, ReedlineEvent::HistoryHintNext) {
+                            hinter.next_hint()
+                        } else {
+                            hinter.previous_hint()
+                        };
+                        Ok(if cycled {
+                            EventStatus::Handled
+                        } else {
+                            EventStatus::Inapplicable
+                        })
+                    }
+                    _ => Ok(EventStatus::Inapplicable),
+                }
+            }
             ReedlineEvent::Esc => {
                 self.deactivate_menus();
                 Ok(EventStatus::Handled)
@@ -988,6 +2013,19 @@ impl Reedline {
                 self.editor.reset_undo_stack();
                 Ok(EventStatus::Exits(Signal::CtrlC))
             }
+            ReedlineEvent::ExitOnEmpty => {
+                if self.editor.is_empty() {
+                    self.editor.reset_undo_stack();
+                    Ok(EventStatus::Exits(Signal::CtrlD))
+                } else {
+                    Ok(EventStatus::Inapplicable)
+                }
+            }
+            ReedlineEvent::ClearAndReprint => {
+                self.deactivate_menus();
+                self.clear_and_reprint()?;
+                Ok(EventStatus::Handled)
+            }
             ReedlineEvent::ClearScreen => {
                 self.deactivate_menus();
                 self.painter.clear_screen()?;
@@ -998,13 +2036,21 @@ impl Reedline {
                 self.painter.clear_scrollback()?;
                 Ok(EventStatus::Handled)
             }
-            ReedlineEvent::Enter | ReedlineEvent::Submit | ReedlineEvent::SubmitOrNewline
+            // Submitting with an open menu picks its value, bind `MenuSubmit` to
+            // submit the line instead
+            ReedlineEvent::Enter
+            | ReedlineEvent::Submit
+            | ReedlineEvent::SubmitOrNewline
+            | ReedlineEvent::SubmitAndKeep
                 if self.menus.iter().any(|menu| menu.is_active()) =>
             {
                 for menu in self.menus.iter_mut() {
                     if menu.is_active() {
-                        menu.replace_in_buffer(&mut self.editor);
-                        menu.menu_event(MenuEvent::Deactivate);
+                        menu.accept_selected_value(
+                            &mut self.editor,
+                            self.completer.as_mut(),
+                            self.history.as_ref(),
+                        );
 
                         return Ok(EventStatus::Handled);
                     }
@@ -1016,11 +2062,14 @@ impl Reedline {
                 if let Some(event) = self.parse_bang_command() {
                     return self.handle_editor_event(prompt, event);
                 }
+                self.expand_abbreviation();
 
                 let buffer = self.editor.get_buffer().to_string();
                 match self.validator.as_mut().map(|v| v.validate(&buffer)) {
                     None | Some(ValidationResult::Complete) => Ok(self.submit_buffer(prompt)?),
-                    Some(ValidationResult::Incomplete) => {
+                    Some(
+                        ValidationResult::Incomplete | ValidationResult::IncompleteWithReason(_),
+                    ) => {
                         self.run_edit_commands(&[EditCommand::InsertNewline]);
 
                         Ok(EventStatus::Handled)
@@ -1032,13 +2081,26 @@ impl Reedline {
                 if let Some(event) = self.parse_bang_command() {
                     return self.handle_editor_event(prompt, event);
                 }
+                self.expand_abbreviation();
                 Ok(self.submit_buffer(prompt)?)
             }
+            ReedlineEvent::SubmitAndKeep => {
+                #[cfg(feature = "bashisms")]
+                if let Some(event) = self.parse_bang_command() {
+                    return self.handle_editor_event(prompt, event);
+                }
+                self.expand_abbreviation();
+                let line_buffer = self.editor.line_buffer().clone();
+                let status = self.submit_buffer(prompt)?;
+                self.kept_line = Some(line_buffer);
+                Ok(status)
+            }
             ReedlineEvent::SubmitOrNewline => {
                 #[cfg(feature = "bashisms")]
                 if let Some(event) = self.parse_bang_command() {
                     return self.handle_editor_event(prompt, event);
                 }
+                self.expand_abbreviation();
                 let cursor_position_in_buffer = self.editor.insertion_point();
                 let buffer = self.editor.get_buffer().to_string();
                 if cursor_position_in_buffer < buffer.len() {
@@ -1047,7 +2109,9 @@ impl Reedline {
                 }
                 match self.validator.as_mut().map(|v| v.validate(&buffer)) {
                     None | Some(ValidationResult::Complete) => Ok(self.submit_buffer(prompt)?),
-                    Some(ValidationResult::Incomplete) => {
+                    Some(
+                        ValidationResult::Incomplete | ValidationResult::IncompleteWithReason(_),
+                    ) => {
                         self.run_edit_commands(&[EditCommand::InsertNewline]);
 
                         Ok(EventStatus::Handled)
@@ -1059,12 +2123,49 @@ impl Reedline {
                 Ok(EventStatus::Exits(Signal::Success(host_command)))
             }
             ReedlineEvent::Edit(commands) => {
-                self.run_edit_commands(&commands);
+                // Moving between placeholders lets another event handle the key when
+                // there are none, e.g. to open the completion menu
+                if !self.editor.has_tab_stops()
+                    && !commands.is_empty()
+                    && commands.iter().all(|command| {
+                        matches!(command, EditCommand::NextTabStop | EditCommand::PrevTabStop)
+                    })
+                {
+                    return Ok(EventStatus::Inapplicable);
+                }
+                if let Some(menu) = self.active_menu() {
+                    // Typed characters consumed by the menu don't edit the buffer
+                    let menu_input = commands
+                        .iter()
+                        .map(|command| match command {
+                            EditCommand::InsertChar(c) if menu_consumes_char(menu, *c) => Some(*c),
+                            _ => None,
+                        })
+                        .collect::<Option<Vec<char>>>();
+
+                    if let Some(menu_input) = menu_input.filter(|input| !input.is_empty()) {
+                        for c in menu_input {
+                            menu.menu_event(MenuEvent::Input(c));
+                        }
+                        return Ok(EventStatus::Handled);
+                    }
+                }
+
+                // Keys typed in quick succession arrive in one batch, so every space
+                // of it can end an abbreviation
+                for command in &commands {
+                    if let EditCommand::InsertChar(' ') = command {
+                        self.expand_abbreviation();
+                    }
+                    self.run_edit_commands(std::slice::from_ref(command));
+                }
                 if let Some(menu) = self.menus.iter_mut().find(|men| men.is_active()) {
-                    if self.quick_completions && menu.can_quick_complete() {
+                    let quick_complete = self.quick_completions && menu.can_quick_complete();
+                    if quick_complete {
                         match commands.first() {
                             Some(&EditCommand::Backspace)
                             | Some(&EditCommand::BackspaceWord)
+                            | Some(&EditCommand::BackspaceBigWord)
                             | Some(&EditCommand::MoveToLineStart) => {
                                 menu.menu_event(MenuEvent::Deactivate)
                             }
@@ -1076,7 +2177,7 @@ impl Reedline {
                                     self.history.as_ref(),
                                 );
                                 if let Some(&EditCommand::Complete) = commands.first() {
-                                    if menu.get_values().len() == 1 {
+                                    if menu.get_values().len() == 1 && menu.can_quick_complete() {
                                         return self
                                             .handle_editor_event(prompt, ReedlineEvent::Enter);
                                     } else if self.partial_completions
@@ -1095,17 +2196,27 @@ impl Reedline {
                     }
                     if self.editor.line_buffer().get_buffer().is_empty() {
                         menu.menu_event(MenuEvent::Deactivate);
+                        self.pending_menu_edit = None;
+                    } else if self.completion_debounce.is_some() && !quick_complete {
+                        self.pending_menu_edit = Some(Instant::now());
                     } else {
-                        menu.menu_event(MenuEvent::Edit(self.quick_completions));
+                        menu.menu_event(MenuEvent::Edit(quick_complete));
                     }
                 }
                 Ok(EventStatus::Handled)
             }
             ReedlineEvent::OpenEditor => self.open_editor().map(|_| EventStatus::Handled),
-            ReedlineEvent::Resize(width, height) => {
-                self.painter.handle_resize(width, height);
-                Ok(EventStatus::Inapplicable)
+            ReedlineEvent::StartMacroRecording(register) => {
+                Ok(self.start_macro_recording(register))
             }
+            ReedlineEvent::StopMacroRecording => Ok(self.stop_macro_recording()),
+            ReedlineEvent::PlayMacro(register) => self.play_macro(prompt, register, 1),
+            ReedlineEvent::PlayMacroTimes(register, times) => {
+                self.play_macro(prompt, register, times)
+            }
+            // The next key was already taken when the event was parsed
+            ReedlineEvent::QuotedInsert => Ok(EventStatus::Handled),
+            ReedlineEvent::Resize(width, height) => Ok(self.handle_resize(width, height)),
             ReedlineEvent::Repaint => {
                 // A handled Event causes a repaint
                 Ok(EventStatus::Handled)
@@ -1138,6 +2249,10 @@ impl Reedline {
                 self.enter_history_search();
                 Ok(EventStatus::Handled)
             }
+            ReedlineEvent::InsertLastCommand => {
+                self.insert_last_command();
+                Ok(EventStatus::Handled)
+            }
             ReedlineEvent::Multiple(events) => {
                 let mut latest_signal = EventStatus::Inapplicable;
                 for event in events {
@@ -1173,10 +2288,171 @@ impl Reedline {
                 // Exhausting the event handlers is still considered handled
                 Ok(EventStatus::Inapplicable)
             }
+            ReedlineEvent::MenuClick(column, row) => Ok(self.click_menu(column, row)),
             ReedlineEvent::None | ReedlineEvent::Mouse => Ok(EventStatus::Inapplicable),
         }
     }
 
+    /// Selects the value of the active menu at the clicked screen position and accepts
+    /// it if it was already selected
+    fn click_menu(&mut self, column: u16, row: u16) -> EventStatus {
+        let menu_row = match self.painter.menu_start_row() {
+            Some(start) if row >= start => row - start,
+            _ => return EventStatus::Inapplicable,
+        };
+        let screen_width = self.painter.screen_width();
+        let menu = match self.menus.iter_mut().find(|menu| menu.is_active()) {
+            Some(menu) => menu,
+            None => return EventStatus::Inapplicable,
+        };
+
+        match menu.select_at(column, menu_row, screen_width) {
+            Some(true) => {
+                menu.accept_selected_value(
+                    &mut self.editor,
+                    self.completer.as_mut(),
+                    self.history.as_ref(),
+                );
+                EventStatus::Handled
+            }
+            Some(false) => EventStatus::Handled,
+            None => EventStatus::Inapplicable,
+        }
+    }
+
+    /// Completes the line in place with the suggestions of the completer, see
+    /// [`Reedline::with_inline_completions`]
+    fn complete_inline(&mut self) -> io::Result<EventStatus> {
+        let line = (
+            self.editor.get_buffer().to_string(),
+            self.editor.insertion_point(),
+        );
+        let mut suggestions = self.completer.complete(&line.0, line.1);
+        let ambiguous = suggestions.len() > 1;
+        if ambiguous {
+            complete_common_prefix(&mut self.editor, &suggestions);
+        } else if let Some(suggestion) = suggestions.pop() {
+            insert_suggestion(&mut self.editor, suggestion);
+        }
+
+        let listed = ambiguous
+            && self.editor.get_buffer() == line.0
+            && self.inline_completion_line == Some(line);
+        self.inline_completion_line = ambiguous.then(|| {
+            (
+                self.editor.get_buffer().to_string(),
+                self.editor.insertion_point(),
+            )
+        });
+        if listed {
+            let values: Vec<String> = suggestions
+                .into_iter()
+                .map(|suggestion| suggestion.value)
+                .collect();
+            let lines = columns_of_values(&values, self.painter.screen_width().into());
+            self.painter.print_below(&lines)?;
+        }
+
+        Ok(EventStatus::Handled)
+    }
+
+    /// Applies the [`MenuTabBehavior`] when the menu called `name` is activated again
+    fn reactivate_menu(&mut self, name: &str) -> EventStatus {
+        let menu = match self.menus.iter_mut().find(|menu| menu.is_active()) {
+            Some(menu) if menu.name() == name => menu,
+            _ => return EventStatus::Inapplicable,
+        };
+
+        match self.menu_tab_behavior {
+            MenuTabBehavior::NextEntry => EventStatus::Inapplicable,
+            MenuTabBehavior::AcceptSelection => {
+                menu.accept_selected_value(
+                    &mut self.editor,
+                    self.completer.as_mut(),
+                    self.history.as_ref(),
+                );
+                EventStatus::Handled
+            }
+            MenuTabBehavior::PartialComplete => {
+                let buffer = self.editor.get_buffer().to_string();
+                if menu.can_partially_complete(
+                    false,
+                    &mut self.editor,
+                    self.completer.as_mut(),
+                    self.history.as_ref(),
+                ) && self.editor.get_buffer() != buffer
+                {
+                    EventStatus::Handled
+                } else {
+                    EventStatus::Inapplicable
+                }
+            }
+        }
+    }
+
+    /// Deletes the entry selected in the active history menu from the history, once
+    /// the menu confirms it
+    fn delete_history_menu_value(&mut self) -> io::Result<EventStatus> {
+        let menu = match self.active_menu() {
+            Some(menu @ ReedlineMenu::HistoryMenu(_)) => menu,
+            _ => return Ok(EventStatus::Inapplicable),
+        };
+        let id = match menu.get_selected_value().and_then(|value| value.history_id) {
+            Some(id) => id,
+            None => return Ok(EventStatus::Inapplicable),
+        };
+        if !menu.confirm_delete() {
+            return Ok(EventStatus::Handled);
+        }
+
+        let to_io_error = |err| io::Error::new(io::ErrorKind::Other, err);
+        // The ids of the later entries move down with some histories, e.g. with
+        // `FileBackedHistory`, so the last run command is looked up again
+        let last_run = self
+            .history_last_run_id
+            .filter(|last_run_id| last_run_id.0 > id.0 && *last_run_id != Self::FILTERED_ITEM_ID)
+            .and_then(|last_run_id| self.history.load(last_run_id).ok());
+        self.history.delete(id).map_err(to_io_error)?;
+
+        if self.history_last_run_id == Some(id) {
+            self.history_last_run_id = None;
+        } else if let Some(last_run) = last_run {
+            let last_run_id = last_run.id.expect("loaded entries have an id");
+            self.history_last_run_id = [last_run_id, HistoryItemId::new(last_run_id.0 - 1)]
+                .into_iter()
+                .find(|candidate| {
+                    self.history.load(*candidate).map_or(false, |item| {
+                        item.command_line == last_run.command_line
+                            && item.start_timestamp == last_run.start_timestamp
+                    })
+                });
+        }
+        self.history_cursor_on_last_command = false;
+
+        if let Some(menu) = self.active_menu() {
+            menu.menu_event(MenuEvent::Refresh);
+        }
+        Ok(EventStatus::Handled)
+    }
+
+    /// Lays out the buffer and the active menu again for the new terminal size. The
+    /// whole screen area of the prompt is painted from scratch afterwards
+    fn handle_resize(&mut self, width: u16, height: u16) -> EventStatus {
+        self.painter.handle_resize(width, height);
+        if let Some(menu) = self.menus.iter_mut().find(|menu| menu.is_active()) {
+            // A pending menu event is handled first, the refresh then places the
+            // selection in the columns fitting the new width
+            menu.update_working_details(
+                &mut self.editor,
+                self.completer.as_mut(),
+                self.history.as_ref(),
+                &self.painter,
+            );
+            menu.menu_event(MenuEvent::Refresh);
+        }
+        EventStatus::Handled
+    }
+
     fn active_menu(&mut self) -> Option<&mut ReedlineMenu> {
         self.menus.iter_mut().find(|menu| menu.is_active())
     }
@@ -1193,13 +2469,16 @@ impl Reedline {
         }
         if self.input_mode != InputMode::HistoryTraversal {
             self.input_mode = InputMode::HistoryTraversal;
-            self.history_cursor = HistoryCursor::new(
-                self.get_history_navigation_based_on_line_buffer(),
-                self.get_history_session_id(),
-            );
+            // Continue from the command inserted by `insert_last_command`
+            if !std::mem::take(&mut self.history_cursor_on_last_command) {
+                self.history_cursor = HistoryCursor::new(
+                    self.get_history_navigation_based_on_line_buffer(),
+                    self.get_history_session_id(),
+                );
 
-            if self.history_excluded_item.is_some() {
-                self.history_cursor_on_excluded = true;
+                if self.history_excluded_item.is_some() {
+                    self.history_cursor_on_excluded = true;
+                }
             }
         }
 
@@ -1208,13 +2487,15 @@ impl Reedline {
                 .back(self.history.as_ref())
                 .expect("todo: error handling");
         }
-        self.update_buffer_from_history();
-        self.editor.move_to_start(UndoBehavior::HistoryNavigation);
-        self.editor
-            .move_to_line_end(UndoBehavior::HistoryNavigation);
+        if !self.update_buffer_from_history() {
+            self.editor.move_to_start(UndoBehavior::HistoryNavigation);
+            self.editor
+                .move_to_line_end(UndoBehavior::HistoryNavigation);
+        }
     }
 
     fn next_history(&mut self) {
+        self.history_cursor_on_last_command = false;
         if self.input_mode != InputMode::HistoryTraversal {
             self.input_mode = InputMode::HistoryTraversal;
             self.history_cursor = HistoryCursor::new(
@@ -1242,8 +2523,32 @@ impl Reedline {
         if self.history_cursor.string_at_cursor().is_none() && !self.history_cursor_on_excluded {
             self.input_mode = InputMode::Regular;
         }
-        self.update_buffer_from_history();
-        self.editor.move_to_end(UndoBehavior::HistoryNavigation);
+        if !self.update_buffer_from_history() {
+            self.editor.move_to_end(UndoBehavior::HistoryNavigation);
+        }
+    }
+
+    /// Replaces the buffer with the newest history entry without entering the history
+    /// traversal. A following [`ReedlineEvent::PreviousHistory`] continues with the
+    /// entry before it, as long as the buffer was not edited in between
+    fn insert_last_command(&mut self) {
+        let mut history_cursor = HistoryCursor::new(
+            HistoryNavigationQuery::Normal(LineBuffer::default()),
+            self.get_history_session_id(),
+        );
+        history_cursor
+            .back(self.history.as_ref())
+            .expect("todo: error handling");
+
+        if let Some(command) = history_cursor.string_at_cursor() {
+            self.input_mode = InputMode::Regular;
+            self.history_cursor = history_cursor;
+            self.history_cursor_on_excluded = false;
+            self.history_cursor_on_last_command = true;
+            self.editor
+                .set_buffer(command, UndoBehavior::CreateUndoPoint);
+            self.editor.move_to_end(UndoBehavior::MoveCursor);
+        }
     }
 
     /// Enable the search and navigation through the history from the line buffer prompt
@@ -1271,18 +2576,51 @@ impl Reedline {
     ///
     /// This mode uses a separate prompt and handles keybindings slightly differently!
     fn enter_history_search(&mut self) {
+        self.history_cursor_on_last_command = false;
         self.history_cursor = HistoryCursor::new(
             HistoryNavigationQuery::SubstringSearch("".to_string()),
-            self.get_history_session_id(),
+            self.history_search_session_id(),
         );
+        self.history_search_direction = SearchDirection::Backward;
         self.input_mode = InputMode::HistorySearch;
     }
 
+    /// Parses a terminal event with the history search keybindings while the search
+    /// is active, falling back to the edit mode
+    fn parse_event(&mut self, event: ReedlineRawEvent) -> ReedlineEvent {
+        if self.quoted_insert_pending {
+            if let Some((modifiers, code)) = event.key() {
+                self.quoted_insert_pending = false;
+                return self.quoted_insert_mode.insert(modifiers, code);
+            }
+        }
+
+        let history_search_event = if self.input_mode == InputMode::HistorySearch {
+            event.key().and_then(|(modifiers, code)| {
+                self.history_search_keybindings
+                    .find_binding(modifiers, code)
+            })
+        } else {
+            None
+        };
+        let event = history_search_event.unwrap_or_else(|| self.edit_mode.parse_event(event));
+
+        // Set right away, the next key may be parsed before the event is handled
+        if requests_quoted_insert(&event) {
+            self.quoted_insert_pending = true;
+        }
+        event
+    }
+
     /// Dispatches the applicable [`EditCommand`] actions for editing the history search string.
     ///
     /// Only modifies internal state, does not perform regular output!
     fn run_history_commands(&mut self, commands: &[EditCommand]) {
         for command in commands {
+            if matches!(command, EditCommand::InsertChar(_) | EditCommand::Backspace) {
+                // A changed search term searches backward from the most recent entry
+                self.history_search_direction = SearchDirection::Backward;
+            }
             match command {
                 EditCommand::InsertChar(c) => {
                     let navigation = self.history_cursor.get_navigation();
@@ -1290,12 +2628,12 @@ impl Reedline {
                         substring.push(*c);
                         self.history_cursor = HistoryCursor::new(
                             HistoryNavigationQuery::SubstringSearch(substring),
-                            self.get_history_session_id(),
+                            self.history_search_session_id(),
                         );
                     } else {
                         self.history_cursor = HistoryCursor::new(
                             HistoryNavigationQuery::SubstringSearch(String::from(*c)),
-                            self.get_history_session_id(),
+                            self.history_search_session_id(),
                         );
                     }
                     self.history_cursor
@@ -1310,7 +2648,7 @@ impl Reedline {
 
                         self.history_cursor = HistoryCursor::new(
                             HistoryNavigationQuery::SubstringSearch(new_substring.to_string()),
-                            self.get_history_session_id(),
+                            self.history_search_session_id(),
                         );
                         self.history_cursor
                             .back(self.history.as_mut())
@@ -1328,57 +2666,143 @@ impl Reedline {
     ///
     /// When using the up/down traversal or fish/zsh style prefix search update the main line buffer accordingly.
     /// Not used for the separate modal reverse search!
-    fn update_buffer_from_history(&mut self) {
+    /// Returns true if the cursor was placed at the position stored with the entry
+    fn update_buffer_from_history(&mut self) -> bool {
         match self.history_cursor.get_navigation() {
-            _ if self.history_cursor_on_excluded => self.editor.set_buffer(
-                self.history_excluded_item
-                    .as_ref()
-                    .unwrap()
-                    .command_line
-                    .clone(),
-                UndoBehavior::HistoryNavigation,
-            ),
+            _ if self.history_cursor_on_excluded => {
+                let entry = self.history_excluded_item.as_ref().unwrap();
+                let (buffer, cursor_position) = (entry.command_line.clone(), entry.cursor_position);
+                self.set_buffer_from_history(buffer, cursor_position)
+            }
             HistoryNavigationQuery::Normal(original) => {
                 if let Some(buffer_to_paint) = self.history_cursor.string_at_cursor() {
-                    self.editor
-                        .set_buffer(buffer_to_paint, UndoBehavior::HistoryNavigation);
+                    let cursor_position = self.history_cursor.cursor_position_at_cursor();
+                    self.set_buffer_from_history(buffer_to_paint, cursor_position)
                 } else {
                     // Hack
                     self.editor
                         .set_line_buffer(original, UndoBehavior::HistoryNavigation);
+                    false
                 }
             }
             HistoryNavigationQuery::PrefixSearch(prefix) => {
                 if let Some(prefix_result) = self.history_cursor.string_at_cursor() {
-                    self.editor
-                        .set_buffer(prefix_result, UndoBehavior::HistoryNavigation);
+                    let cursor_position = self.history_cursor.cursor_position_at_cursor();
+                    self.set_buffer_from_history(prefix_result, cursor_position)
                 } else {
                     self.editor
                         .set_buffer(prefix, UndoBehavior::HistoryNavigation);
+                    false
                 }
             }
             HistoryNavigationQuery::SubstringSearch(_) => todo!(),
         }
     }
 
-    /// Executes [`EditCommand`] actions by modifying the internal state appropriately. Does not output itself.
-    pub fn run_edit_commands(&mut self, commands: &[EditCommand]) {
-        if self.input_mode == InputMode::HistoryTraversal {
-            if matches!(
-                self.history_cursor.get_navigation(),
-                HistoryNavigationQuery::Normal(_)
-            ) {
-                if let Some(string) = self.history_cursor.string_at_cursor() {
-                    self.editor
-                        .set_buffer(string, UndoBehavior::HistoryNavigation);
-                }
+    /// Sets the buffer to a recalled entry, with the cursor at its stored position if
+    /// enabled and valid. Returns true if the stored position was used
+    fn set_buffer_from_history(&mut self, buffer: String, cursor_position: Option<usize>) -> bool {
+        match cursor_position
+            .filter(|position| self.history_restores_cursor && buffer.is_char_boundary(*position))
+        {
+            Some(position) => {
+                let mut line_buffer = LineBuffer::from(buffer.as_str());
+                line_buffer.set_insertion_point(position);
+                self.editor
+                    .set_line_buffer(line_buffer, UndoBehavior::HistoryNavigation);
+                true
+            }
+            None => {
+                self.editor
+                    .set_buffer(buffer, UndoBehavior::HistoryNavigation);
+                false
+            }
+        }
+    }
+
+    /// Replaces the buffer with the given string and places the cursor at its end
+    ///
+    /// With [`SetBufferUndo::Reset`] (the default) the new buffer is the baseline of
+    /// the undo stack, while [`SetBufferUndo::UndoPoint`] lets the user undo back to
+    /// the previous buffer.
+    /// Setting the buffer ends any history navigation. The next history navigation
+    /// starts from the new buffer, e.g. as a prefix search. Does not output itself.
+    pub fn set_buffer(&mut self, buffer: String, undo: SetBufferUndo) {
+        self.input_mode = InputMode::Regular;
+        self.history_cursor_on_last_command = false;
+
+        match undo {
+            SetBufferUndo::Reset => self.editor.set_buffer_as_baseline(buffer),
+            SetBufferUndo::UndoPoint => self
+                .editor
+                .set_buffer(buffer, UndoBehavior::CreateUndoPoint),
+        }
+    }
+
+    /// Replaces the word before the cursor with its expansion if it is an abbreviation
+    ///
+    /// The word has to be the whole token, so nothing but whitespace may follow the
+    /// cursor
+    fn expand_abbreviation(&mut self) {
+        if self.abbreviations.is_empty() {
+            return;
+        }
+
+        let buffer = self.editor.get_buffer();
+        let cursor = self.editor.insertion_point();
+        if buffer[cursor..]
+            .chars()
+            .next()
+            .map_or(false, |c| !c.is_whitespace())
+        {
+            return;
+        }
+        let start = buffer[..cursor]
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(index, c)| index + c.len_utf8());
+
+        if let Some(expansion) = self.abbreviations.get(&buffer[start..cursor]) {
+            let mut line_buffer = self.editor.line_buffer().clone();
+            line_buffer.replace_range(start..cursor, expansion);
+            line_buffer.set_insertion_point(start + expansion.len());
+            self.editor
+                .set_line_buffer(line_buffer, UndoBehavior::CreateUndoPoint);
+        }
+    }
+
+    /// Executes [`EditCommand`] actions by modifying the internal state appropriately. Does not output itself.
+    pub fn run_edit_commands(&mut self, commands: &[EditCommand]) {
+        self.history_cursor_on_last_command = false;
+        if self.input_mode == InputMode::HistoryTraversal {
+            if matches!(
+                self.history_cursor.get_navigation(),
+                HistoryNavigationQuery::Normal(_)
+            ) {
+                if let Some(string) = self.history_cursor.string_at_cursor() {
+                    self.editor
+                        .set_buffer(string, UndoBehavior::HistoryNavigation);
+                }
             }
             self.input_mode = InputMode::Regular;
         }
 
         // Run the commands over the edit buffer
         for command in commands {
-            self.editor.run_edit_command(command);
+            if let Some(observer) = self.edit_command_observer.as_mut() {
+                observer(command);
+            }
+
+            match command {
+                EditCommand::InsertGenerated(index) => {
+                    if let Some(generator) = self.text_generators.get(*index) {
+                        self.editor
+                            .run_edit_command(&EditCommand::InsertString(generator()));
+                    }
+                }
+                _ => self.editor.run_edit_command(command),
+            }
         }
     }
 
@@ -1402,13 +2826,82 @@ impl Reedline {
         }
     }
 
+    fn start_macro_recording(&mut self, register: char) -> EventStatus {
+        self.macro_recording = Some((register, Vec::new()));
+        EventStatus::Handled
+    }
+
+    fn stop_macro_recording(&mut self) -> EventStatus {
+        match self.macro_recording.take() {
+            Some((register, events)) => {
+                self.macros.insert(register, events);
+                EventStatus::Handled
+            }
+            None => EventStatus::Inapplicable,
+        }
+    }
+
+    /// Plays the events of a macro `times` times, at most [`MAX_MACRO_REPEAT`], as a
+    /// single undo point, stopping at the first event that exits the line editor
+    fn play_macro(
+        &mut self,
+        prompt: &dyn Prompt,
+        register: char,
+        times: usize,
+    ) -> io::Result<EventStatus> {
+        if self.playing_macros.contains(&register) {
+            return Ok(EventStatus::Inapplicable);
+        }
+        let events = match self.macros.get(&register) {
+            Some(events) => events.clone(),
+            None => return Ok(EventStatus::Inapplicable),
+        };
+
+        self.playing_macros.push(register);
+        let group_start = self.editor.start_undo_group();
+        let mut status = Ok(EventStatus::Handled);
+        let events = std::iter::repeat(events)
+            .take(times.min(MAX_MACRO_REPEAT))
+            .flatten();
+        for event in events {
+            match self.handle_event(prompt, event) {
+                Ok(EventStatus::Exits(signal)) => {
+                    status = Ok(EventStatus::Exits(signal));
+                    break;
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    status = Err(err);
+                    break;
+                }
+            }
+        }
+        self.editor.end_undo_group(group_start);
+        self.playing_macros.pop();
+
+        status
+    }
+
+    /// Writes the escape sequences of the clipboard, e.g. the OSC 52 sequence of a
+    /// cut, in order with the painting
+    fn write_clipboard_output(&mut self) -> io::Result<()> {
+        match self.editor.take_clipboard_output() {
+            Some(output) => self.painter.write_escape_sequence(&output),
+            None => Ok(()),
+        }
+    }
+
     /// Checks if hints should be displayed and are able to be completed
     fn hints_active(&self) -> bool {
-        !self.hide_hints && matches!(self.input_mode, InputMode::Regular)
+        !self.hide_hints
+            && self.render_pipeline.contains(RenderPass::Hint)
+            && matches!(self.input_mode, InputMode::Regular)
+            && !(self.hide_hints_in_menus && self.menus.iter().any(|menu| menu.is_active()))
     }
 
     /// Repaint of either the buffer or the parts for reverse history search
     fn repaint(&mut self, prompt: &dyn Prompt) -> io::Result<()> {
+        self.last_repaint = Some(Instant::now());
         // Repainting
         if self.input_mode == InputMode::HistorySearch {
             self.history_search_paint(prompt)
@@ -1550,11 +3043,33 @@ impl Reedline {
         }
     }
 
+    /// Whether ANSI coloring is enabled and the terminal displays any colors
+    fn ansi_coloring(&self) -> bool {
+        self.use_ansi_coloring && self.painter.color_level() != ColorLevel::NoColor
+    }
+
+    /// Renders the configured history search indicator, clipped to half of the terminal
+    fn render_history_search_indicator(
+        &self,
+        history_search: &PromptHistorySearch,
+    ) -> Option<String> {
+        let indicator = (self.history_search_indicator.as_ref()?)(history_search);
+        let indicator = if self.ansi_coloring() {
+            indicator
+        } else {
+            strip_ansi(&indicator)
+        };
+        let max_width = (self.painter.screen_width() / 2).max(1) as usize;
+
+        Some(clip_to_width(&indicator, max_width).into_owned())
+    }
+
     /// Repaint logic for the history reverse search
     ///
     /// Overwrites the prompt indicator and highlights the search string
     /// separately from the result buffer.
     fn history_search_paint(&mut self, prompt: &dyn Prompt) -> Result<()> {
+        let use_ansi_coloring = self.ansi_coloring();
         let navigation = self.history_cursor.get_navigation();
 
         if let HistoryNavigationQuery::SubstringSearch(substring) = navigation {
@@ -1565,20 +3080,34 @@ impl Reedline {
                     PromptHistorySearchStatus::Passing
                 };
 
-            let prompt_history_search = PromptHistorySearch::new(status, substring.clone());
+            let prompt_history_search = PromptHistorySearch::new(status, substring.clone())
+                .with_match_position(self.history_cursor.match_position(self.history.as_ref()))
+                .with_direction(self.history_search_direction);
+            let custom_indicator = self.render_history_search_indicator(&prompt_history_search);
 
             let res_string = self.history_cursor.string_at_cursor().unwrap_or_default();
 
             // Highlight matches
-            let res_string = if self.use_ansi_coloring {
+            let res_string = if use_ansi_coloring {
+                let highlighter = &self.highlighter;
                 let match_highlighter = SimpleMatchHighlighter::new(substring);
-                let styled = match_highlighter.highlight(&res_string, 0);
+                let styled = self
+                    .render_pipeline
+                    .compose(&res_string, |pass| match pass {
+                        RenderPass::Highlight => {
+                            Some(highlighter.highlight(&res_string, res_string.len()))
+                        }
+                        RenderPass::SearchMatch => {
+                            Some(match_highlighter.highlight(&res_string, 0))
+                        }
+                        RenderPass::Selection | RenderPass::Hint => None,
+                    });
                 styled.render_simple()
             } else {
                 res_string
             };
 
-            let lines = PromptLines::new(
+            let mut lines = PromptLines::new(
                 prompt,
                 self.prompt_edit_mode(),
                 Some(prompt_history_search),
@@ -1586,13 +3115,17 @@ impl Reedline {
                 "",
                 "",
             );
+            if let Some(indicator) = custom_indicator {
+                lines.prompt_indicator = indicator.into();
+            }
+            self.add_status_segment(prompt, &mut lines);
 
             self.painter.repaint_buffer(
                 prompt,
                 &lines,
                 self.prompt_edit_mode(),
                 None,
-                self.use_ansi_coloring,
+                use_ansi_coloring,
                 &self.cursor_shapes,
             )?;
         }
@@ -1604,16 +3137,38 @@ impl Reedline {
     ///
     /// Includes the highlighting and hinting calls.
     fn buffer_paint(&mut self, prompt: &dyn Prompt) -> Result<()> {
+        let use_ansi_coloring = self.ansi_coloring();
+        let continuation_prompts = self.continuation_prompts(prompt);
         let cursor_position_in_buffer = self.editor.insertion_point();
         let buffer_to_paint = self.editor.get_buffer();
 
+        let highlighter = &self.highlighter;
+        // The placeholder of a snippet is shown like a selection, as typing replaces it
+        let selection = self
+            .editor
+            .selection_range()
+            .or_else(|| self.editor.selected_placeholder());
+        let selection_style = self.selection_style;
         let (before_cursor, after_cursor) = self
-            .highlighter
-            .highlight(buffer_to_paint, cursor_position_in_buffer)
-            .render_around_insertion_point(
+            .render_pipeline
+            .compose(buffer_to_paint, |pass| match pass {
+                RenderPass::Highlight => {
+                    Some(highlighter.highlight(buffer_to_paint, cursor_position_in_buffer))
+                }
+                RenderPass::Selection => selection.clone().map(|range| {
+                    let mut styled = StyledText::new();
+                    styled.push((Style::default(), buffer_to_paint[..range.start].to_string()));
+                    styled.push((selection_style, buffer_to_paint[range.clone()].to_string()));
+                    styled.push((Style::default(), buffer_to_paint[range.end..].to_string()));
+                    styled
+                }),
+                RenderPass::SearchMatch | RenderPass::Hint => None,
+            })
+            .render_around_insertion_point_with_indicators(
                 cursor_position_in_buffer,
                 prompt,
-                self.use_ansi_coloring,
+                &continuation_prompts,
+                use_ansi_coloring,
             );
 
         let hint: String = if self.hints_active() {
@@ -1622,7 +3177,7 @@ impl Reedline {
                     buffer_to_paint,
                     cursor_position_in_buffer,
                     self.history.as_ref(),
-                    self.use_ansi_coloring,
+                    use_ansi_coloring,
                 )
             })
         } else {
@@ -1632,7 +3187,7 @@ impl Reedline {
         // Needs to add return carriage to newlines because when not in raw mode
         // some OS don't fully return the carriage
 
-        let lines = PromptLines::new(
+        let mut lines = PromptLines::new(
             prompt,
             self.prompt_edit_mode(),
             None,
@@ -1640,6 +3195,7 @@ impl Reedline {
             &after_cursor,
             &hint,
         );
+        self.add_status_segment(prompt, &mut lines);
 
         // Updating the working details of the active menu
         for menu in self.menus.iter_mut() {
@@ -1660,7 +3216,7 @@ impl Reedline {
             &lines,
             self.prompt_edit_mode(),
             menu,
-            self.use_ansi_coloring,
+            use_ansi_coloring,
             &self.cursor_shapes,
         )
     }
@@ -1672,6 +3228,29 @@ impl Reedline {
         self
     }
 
+    /// The external printer the lines to print while editing are sent to, if any
+    ///
+    /// Clones of it or its [`ExternalPrinter::sender`] can be moved to other threads.
+    #[cfg(feature = "external_printer")]
+    pub fn external_printer(&self) -> Option<&ExternalPrinter<String>> {
+        self.external_printer.as_ref()
+    }
+
+    /// Waits up to `timeout` for terminal input, like [`event::poll`]
+    ///
+    /// With an external printer the wait also ends once a message is sent to it, so
+    /// the message is printed right away.
+    fn poll_input(&mut self, timeout: Duration) -> Result<bool> {
+        #[cfg(feature = "external_printer")]
+        if let Some(ref external_printer) = self.external_printer {
+            return self
+                .input_poller
+                .get_or_insert_with(InputPoller::new)
+                .poll(timeout, external_printer.receiver());
+        }
+        event::poll(timeout)
+    }
+
     #[cfg(feature = "external_printer")]
     fn external_messages(external_printer: &ExternalPrinter<String>) -> Result<Vec<String>> {
         let mut messages = Vec::new();
@@ -1696,14 +3275,44 @@ impl Reedline {
         Ok(messages)
     }
 
+    /// The continuation prompt of every line break in the buffer
+    ///
+    /// Each line gets the reason the validator gives for the input before it, so
+    /// the prompt always reflects the current buffer
+    fn continuation_prompts(&self, prompt: &dyn Prompt) -> Vec<String> {
+        let buffer = self.editor.get_buffer();
+        buffer
+            .match_indices('\n')
+            .map(|(index, _)| {
+                let reason = match self
+                    .validator
+                    .as_ref()
+                    .map(|v| v.validate(&buffer[..index]))
+                {
+                    Some(ValidationResult::IncompleteWithReason(reason)) => Some(reason),
+                    _ => None,
+                };
+                prompt
+                    .render_prompt_multiline_indicator_with_reason(reason.as_deref())
+                    .into_owned()
+            })
+            .collect()
+    }
+
     fn submit_buffer(&mut self, prompt: &dyn Prompt) -> io::Result<EventStatus> {
         let buffer = self.editor.get_buffer().to_string();
         self.hide_hints = true;
         // Additional repaint to show the content without hints etc.
-        self.repaint(prompt)?;
+        match TransientPrompt::from_prompt(prompt) {
+            Some(transient) => self.repaint(&transient)?,
+            None => self.repaint(prompt)?,
+        }
+        self.last_command_status = None;
+        self.line_accepted_at = Some(Instant::now());
         if !buffer.is_empty() {
             let mut entry = HistoryItem::from_command_line(&buffer);
             entry.session_id = self.get_history_session_id();
+            entry.cursor_position = Some(self.editor.insertion_point());
 
             if self
                 .history_exclusion_prefix
@@ -1725,6 +3334,32 @@ impl Reedline {
 
         Ok(EventStatus::Exits(Signal::Success(buffer)))
     }
+
+    /// Abandon the entry like `CtrlC` but keep reading on a fresh prompt
+    fn clear_and_reprint(&mut self) -> io::Result<()> {
+        self.painter.start_prompt_below()?;
+        self.run_edit_commands(&[EditCommand::Clear]);
+        self.editor.reset_undo_stack();
+        Ok(())
+    }
+}
+
+/// The event takes the next key literally, also as part of a group of events
+fn requests_quoted_insert(event: &ReedlineEvent) -> bool {
+    match event {
+        ReedlineEvent::QuotedInsert => true,
+        ReedlineEvent::Multiple(events) | ReedlineEvent::UntilFound(events) => {
+            events.iter().any(requests_quoted_insert)
+        }
+        _ => false,
+    }
+}
+
+/// Time left until the `deadline`, `None` once it has passed
+fn time_until(deadline: Instant, now: Instant) -> Option<Duration> {
+    deadline
+        .checked_duration_since(now)
+        .filter(|time_left| !time_left.is_zero())
 }
 
 #[test]
@@ -1732,3 +3367,1980 @@ fn thread_safe() {
     fn f<S: Send>(_: S) {}
     f(Reedline::create());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt::TestPrompt;
+    use rstest::rstest;
+
+    #[test]
+    fn terminal_guard_restores_terminal_on_panic() {
+        let mut out: Vec<u8> = Vec::new();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = TerminalGuard::new(&mut out, true, true, true);
+            panic!("forced panic inside the read_line loop");
+        }));
+
+        assert!(result.is_err());
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\x1b[0 q"), "cursor shape not reset: {out:?}");
+        assert!(out.contains("\x1b[?25h"), "cursor not shown: {out:?}");
+        assert!(
+            out.contains("\x1b[?2004l"),
+            "bracketed paste not disabled: {out:?}"
+        );
+        assert!(
+            out.contains("\x1b[?1000l"),
+            "mouse capture not disabled: {out:?}"
+        );
+    }
+
+    #[test]
+    fn terminal_guard_is_inert_without_panic() {
+        let mut out: Vec<u8> = Vec::new();
+        {
+            let _guard = TerminalGuard::new(&mut out, true, true, true);
+        }
+
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn insert_last_command_does_not_enter_history_traversal() {
+        let mut history = FileBackedHistory::default();
+        for command in ["first", "second", "third"] {
+            history
+                .save(HistoryItem::from_command_line(command))
+                .unwrap();
+        }
+        let mut reedline = Reedline::create().with_history(Box::new(history));
+
+        reedline.insert_last_command();
+        assert_eq!(reedline.current_buffer_contents(), "third");
+        assert_eq!(reedline.current_insertion_point(), "third".len());
+        assert_eq!(reedline.input_mode, InputMode::Regular);
+
+        reedline.previous_history();
+        assert_eq!(reedline.current_buffer_contents(), "second");
+    }
+
+    #[test]
+    fn insert_last_command_is_undoable() {
+        let mut history = FileBackedHistory::default();
+        history.save(HistoryItem::from_command_line("ls")).unwrap();
+        let mut reedline = Reedline::create().with_history(Box::new(history));
+
+        reedline.insert_last_command();
+        reedline.run_edit_commands(&[EditCommand::Undo]);
+        assert_eq!(reedline.current_buffer_contents(), "");
+    }
+
+    #[test]
+    fn insert_last_command_with_empty_history() {
+        let mut reedline = Reedline::create();
+        reedline.run_edit_commands(&[EditCommand::InsertString("typed".into())]);
+
+        reedline.insert_last_command();
+        assert_eq!(reedline.current_buffer_contents(), "typed");
+    }
+
+    #[test]
+    fn repaint_interval_delays_repaint() {
+        let mut reedline = Reedline::create();
+        assert_eq!(reedline.time_until_repaint(), None);
+
+        reedline.last_repaint = Some(Instant::now());
+        assert_eq!(reedline.time_until_repaint(), None);
+
+        reedline = reedline.with_repaint_interval(Some(Duration::from_secs(60)));
+        assert!(reedline.time_until_repaint().is_some());
+
+        reedline.last_repaint = Instant::now().checked_sub(Duration::from_secs(61));
+        assert_eq!(reedline.time_until_repaint(), None);
+    }
+
+    #[test]
+    fn completion_debounce_keeps_the_values_until_the_menu_needs_them() {
+        use crate::ColumnarMenu;
+
+        let completer = DefaultCompleter::new(vec!["hello".into(), "hex".into()]);
+        let mut reedline = Reedline::create()
+            .with_completer(Box::new(completer))
+            .with_menu(ReedlineMenu::EngineCompleter(Box::new(
+                ColumnarMenu::default(),
+            )))
+            .with_completion_debounce(Some(Duration::from_secs(60)));
+        let prompt = crate::DefaultPrompt::default();
+        handle_events(
+            &mut reedline,
+            vec![
+                ReedlineEvent::Resize(80, 24),
+                ReedlineEvent::Edit(vec![EditCommand::InsertString("he".into())]),
+                ReedlineEvent::Menu("columnar_menu".into()),
+            ],
+        );
+        reedline.repaint(&prompt).unwrap();
+        assert_eq!(reedline.menus[0].get_values().len(), 2);
+
+        // The typed character waits for the debounce interval
+        handle_events(
+            &mut reedline,
+            vec![ReedlineEvent::Edit(vec![EditCommand::InsertChar('l')])],
+        );
+        reedline.repaint(&prompt).unwrap();
+        assert_eq!(reedline.menus[0].get_values().len(), 2);
+        assert!(reedline.time_until_menu_edit().unwrap() > Duration::ZERO);
+
+        reedline.pending_menu_edit = Instant::now().checked_sub(Duration::from_secs(61));
+        assert_eq!(reedline.time_until_menu_edit(), Some(Duration::ZERO));
+
+        // Moving in the menu asks for the values of the latest edit
+        handle_events(&mut reedline, vec![ReedlineEvent::MenuNext]);
+        reedline.repaint(&prompt).unwrap();
+        assert_eq!(reedline.time_until_menu_edit(), None);
+        assert_eq!(reedline.menus[0].get_values().len(), 1);
+    }
+
+    #[test]
+    fn resize_keeps_the_menu_selection() {
+        use crate::ColumnarMenu;
+
+        let words = ["abc1", "abc2", "abc3", "abc4", "abc5", "abc6"];
+        let completer = DefaultCompleter::new(words.iter().map(|word| word.to_string()).collect());
+        let mut reedline = Reedline::create()
+            .with_completer(Box::new(completer))
+            .with_menu(ReedlineMenu::EngineCompleter(Box::new(
+                ColumnarMenu::default(),
+            )));
+        let prompt = crate::DefaultPrompt::default();
+        handle_events(
+            &mut reedline,
+            vec![
+                ReedlineEvent::Resize(80, 24),
+                ReedlineEvent::Edit(vec![EditCommand::InsertString("ab".into())]),
+                ReedlineEvent::Menu("columnar_menu".into()),
+            ],
+        );
+        reedline.repaint(&prompt).unwrap();
+        for _ in 0..4 {
+            handle_events(&mut reedline, vec![ReedlineEvent::MenuNext]);
+            reedline.repaint(&prompt).unwrap();
+        }
+        let selected = reedline.menus[0].get_selected_value().unwrap().value;
+
+        // The narrow terminal fits a single column
+        let status = reedline
+            .handle_editor_event(&prompt, ReedlineEvent::Resize(10, 24))
+            .unwrap();
+        assert!(matches!(status, EventStatus::Handled));
+        reedline.repaint(&prompt).unwrap();
+        assert_eq!(reedline.menus[0].get_values().len(), words.len());
+        assert_eq!(
+            reedline.menus[0].get_selected_value().unwrap().value,
+            selected
+        );
+    }
+
+    #[test]
+    fn recalled_history_restores_the_cursor() {
+        // The excluded entry is kept in memory with its cursor position
+        let mut reedline = Reedline::create().with_history_exclusion_prefix(Some(" ".into()));
+        handle_events(&mut reedline, vec![ReedlineEvent::Resize(80, 24)]);
+        reedline.run_edit_commands(&[
+            EditCommand::InsertString(" git commit -m".into()),
+            EditCommand::MoveWordLeft,
+        ]);
+        handle_events(&mut reedline, vec![ReedlineEvent::Enter, ReedlineEvent::Up]);
+        assert_eq!(reedline.current_buffer_contents(), " git commit -m");
+        assert_eq!(reedline.current_insertion_point(), 14);
+
+        reedline = reedline.with_history_cursor_restore(true);
+        reedline.run_edit_commands(&[EditCommand::MoveWordLeft]);
+        handle_events(&mut reedline, vec![ReedlineEvent::Enter, ReedlineEvent::Up]);
+        assert_eq!(reedline.current_buffer_contents(), " git commit -m");
+        assert_eq!(reedline.current_insertion_point(), 13);
+    }
+
+    #[test]
+    fn set_buffer_undo_policies() {
+        let mut reedline = Reedline::create();
+        reedline.run_edit_commands(&[EditCommand::InsertString("typed".into())]);
+
+        reedline.set_buffer("baseline".into(), SetBufferUndo::default());
+        assert_eq!(reedline.current_buffer_contents(), "baseline");
+        assert_eq!(reedline.current_insertion_point(), "baseline".len());
+        reedline.run_edit_commands(&[EditCommand::Undo]);
+        assert_eq!(reedline.current_buffer_contents(), "baseline");
+
+        reedline.set_buffer("undoable".into(), SetBufferUndo::UndoPoint);
+        reedline.run_edit_commands(&[EditCommand::Undo]);
+        assert_eq!(reedline.current_buffer_contents(), "baseline");
+    }
+
+    #[test]
+    fn set_buffer_leaves_history_traversal() {
+        let mut history = FileBackedHistory::default();
+        history.save(HistoryItem::from_command_line("cd")).unwrap();
+        let mut reedline = Reedline::create().with_history(Box::new(history));
+
+        reedline.previous_history();
+        assert_eq!(reedline.input_mode, InputMode::HistoryTraversal);
+
+        reedline.set_buffer("ls".into(), SetBufferUndo::Reset);
+        assert_eq!(reedline.input_mode, InputMode::Regular);
+        assert_eq!(reedline.current_buffer_contents(), "ls");
+    }
+
+    #[test]
+    fn menus_are_listed_and_reconfigurable_by_name() {
+        use crate::{ColumnarMenu, ListMenu};
+
+        let mut reedline = Reedline::create()
+            .with_menu(ReedlineMenu::EngineCompleter(Box::new(
+                ColumnarMenu::default().with_name("completion_menu"),
+            )))
+            .with_menu(ReedlineMenu::HistoryMenu(Box::new(
+                ListMenu::default().with_name("history_menu"),
+            )));
+
+        assert_eq!(
+            reedline.menu_names(),
+            vec!["completion_menu", "history_menu"]
+        );
+        assert!(reedline.menu_mut("unknown").is_none());
+
+        if let Some(ReedlineMenu::EngineCompleter(menu)) = reedline.menu_mut("completion_menu") {
+            *menu = Box::new(
+                ColumnarMenu::default()
+                    .with_name("completion_menu")
+                    .with_columns(2),
+            );
+        } else {
+            panic!("completion menu not found");
+        }
+        assert_eq!(
+            reedline.menu_names(),
+            vec!["completion_menu", "history_menu"]
+        );
+    }
+
+    fn words_completer(words: &[&str]) -> DefaultCompleter {
+        DefaultCompleter::new(words.iter().map(|word| word.to_string()).collect())
+    }
+
+    /// Engine of the completion tests: the `completer` with a columnar `menu`, set up
+    /// further by `configure`, and the `buffer` typed in
+    fn completion_engine(
+        completer: impl Completer + 'static,
+        menu: crate::ColumnarMenu,
+        configure: impl FnOnce(Reedline) -> Reedline,
+        buffer: &str,
+    ) -> Reedline {
+        let mut reedline = configure(
+            Reedline::create()
+                .with_completer(Box::new(completer))
+                .with_menu(ReedlineMenu::EngineCompleter(Box::new(menu))),
+        );
+        reedline.run_edit_commands(&[EditCommand::InsertString(buffer.into())]);
+        reedline
+    }
+
+    /// Activates the menu like a first Tab, with the values computed
+    fn show_menu(reedline: &mut Reedline) {
+        reedline
+            .handle_editor_event(
+                &crate::DefaultPrompt::default(),
+                ReedlineEvent::Menu("columnar_menu".into()),
+            )
+            .unwrap();
+        if let Some(menu) = reedline.menus.iter_mut().find(|menu| menu.is_active()) {
+            menu.update_values(
+                &mut reedline.editor,
+                reedline.completer.as_mut(),
+                reedline.history.as_ref(),
+            );
+        }
+    }
+
+    #[test]
+    fn two_stage_completion_accepts_single_candidate() {
+        let mut reedline = completion_engine(
+            words_completer(&["hello", "world"]),
+            crate::ColumnarMenu::default(),
+            |reedline| reedline.with_accept_unambiguous_prefix(true),
+            "he",
+        );
+        let prompt = crate::DefaultPrompt::default();
+
+        reedline
+            .handle_editor_event(&prompt, ReedlineEvent::Menu("columnar_menu".into()))
+            .unwrap();
+
+        assert_eq!(reedline.current_buffer_contents(), "hello");
+        assert!(reedline.active_menu().is_none());
+    }
+
+    #[test]
+    fn two_stage_completion_extends_shared_prefix() {
+        let mut reedline = completion_engine(
+            words_completer(&["batman", "batmobile"]),
+            crate::ColumnarMenu::default(),
+            |reedline| reedline.with_accept_unambiguous_prefix(true),
+            "ba",
+        );
+        let prompt = crate::DefaultPrompt::default();
+
+        reedline
+            .handle_editor_event(&prompt, ReedlineEvent::Menu("columnar_menu".into()))
+            .unwrap();
+
+        assert_eq!(reedline.current_buffer_contents(), "batm");
+        assert!(reedline.active_menu().is_none());
+    }
+
+    #[test]
+    fn two_stage_completion_cycles_ambiguous_candidates() {
+        let mut reedline = completion_engine(
+            words_completer(&["help", "hello"]),
+            crate::ColumnarMenu::default(),
+            |reedline| reedline.with_accept_unambiguous_prefix(true),
+            "hel",
+        );
+        let prompt = crate::DefaultPrompt::default();
+
+        reedline
+            .handle_editor_event(&prompt, ReedlineEvent::Menu("columnar_menu".into()))
+            .unwrap();
+        assert_eq!(reedline.current_buffer_contents(), "hel");
+        let menu = reedline
+            .active_menu()
+            .expect("menu is shown for ambiguous candidates");
+        assert_eq!(menu.get_values().len(), 2);
+
+        reedline
+            .handle_editor_event(&prompt, ReedlineEvent::Enter)
+            .unwrap();
+        assert_eq!(reedline.current_buffer_contents(), "hello");
+    }
+
+    #[rstest]
+    #[case::registered_menu(true)]
+    #[case::without_menu(false)]
+    fn inline_completion_never_shows_the_menu(#[case] registers_menu: bool) {
+        use crate::{ColumnarMenu, DefaultCompleter};
+
+        let words = ["batman", "batmobile", "hello"];
+        let completer = DefaultCompleter::new(words.iter().map(|word| word.to_string()).collect());
+        let mut reedline = Reedline::create()
+            .with_completer(Box::new(completer))
+            .with_inline_completions(true);
+        if registers_menu {
+            reedline = reedline.with_menu(ReedlineMenu::EngineCompleter(Box::new(
+                ColumnarMenu::default(),
+            )));
+        }
+        let output = crate::painting::TestOutput::default();
+        reedline.painter = output.painter();
+        let prompt = crate::DefaultPrompt::default();
+        let tab = || ReedlineEvent::Menu("columnar_menu".into());
+
+        handle_events(
+            &mut reedline,
+            vec![
+                ReedlineEvent::Resize(80, 24),
+                ReedlineEvent::Edit(vec![EditCommand::InsertString("ba".into())]),
+            ],
+        );
+        reedline.handle_editor_event(&prompt, tab()).unwrap();
+        assert_eq!(reedline.current_buffer_contents(), "batm");
+        assert!(reedline.active_menu().is_none());
+        assert_eq!(reedline.inline_completion_line, Some(("batm".into(), 4)));
+        assert!(!output.take().contains("batman"));
+
+        // The second press lists the candidates below the prompt and keeps the line
+        reedline.handle_editor_event(&prompt, tab()).unwrap();
+        assert_eq!(reedline.current_buffer_contents(), "batm");
+        assert!(reedline.active_menu().is_none());
+        assert_eq!(output.take(), "\x1b[24;1Hbatman     batmobile\r\n");
+
+        reedline.run_edit_commands(&[EditCommand::Clear, EditCommand::InsertString("he".into())]);
+        reedline.handle_editor_event(&prompt, tab()).unwrap();
+        assert_eq!(reedline.current_buffer_contents(), "hello");
+        assert_eq!(reedline.inline_completion_line, None);
+    }
+
+    struct AliasCompleter;
+
+    impl Completer for AliasCompleter {
+        fn complete(&mut self, line: &str, pos: usize) -> Vec<crate::Suggestion> {
+            use crate::{Span, Suggestion};
+
+            let suggestion = |value: &str, span: Span, recomplete: Option<Span>| Suggestion {
+                value: value.into(),
+                span,
+                recomplete,
+                ..Suggestion::default()
+            };
+
+            match &line[..pos] {
+                "ll" => vec![suggestion(
+                    "ls -la ",
+                    Span::new(0, 2),
+                    Some(Span::new(0, 7)),
+                )],
+                "sudo ll" => vec![suggestion(
+                    "ls -la ",
+                    Span::new(5, 7),
+                    Some(Span::new(5, 12)),
+                )],
+                "say" => vec![suggestion("echo", Span::new(0, 3), Some(Span::new(0, 4)))],
+                "ls -la " => vec![
+                    suggestion("src", Span::new(pos, pos), None),
+                    suggestion("target", Span::new(pos, pos), None),
+                ],
+                _ => vec![],
+            }
+        }
+    }
+
+    #[test]
+    fn quick_completion_is_configured_per_menu() {
+        use crate::ColumnarMenu;
+
+        let quick_completion_engine = |menu, buffer| {
+            let completer = DefaultCompleter::new_with_wordlen(
+                vec!["ls".into(), "checkout".into(), "cherry-pick".into()],
+                1,
+            );
+            let mut reedline = completion_engine(
+                completer,
+                menu,
+                |reedline| reedline.with_quick_completions(true),
+                buffer,
+            );
+            show_menu(&mut reedline);
+            reedline
+        };
+
+        let mut reedline = quick_completion_engine(ColumnarMenu::default(), "chec");
+        assert_eq!(reedline.current_buffer_contents(), "checkout");
+        assert!(reedline.active_menu().is_none());
+
+        let mut reedline =
+            quick_completion_engine(ColumnarMenu::default().with_quick_complete(false), "chec");
+        assert_eq!(reedline.current_buffer_contents(), "chec");
+        let values: Vec<&str> = reedline
+            .active_menu()
+            .expect("the menu is shown first")
+            .get_values()
+            .iter()
+            .map(|s| s.value.as_str())
+            .collect();
+        assert_eq!(values, ["checkout"]);
+
+        // Values shorter than the minimum width are shown in the menu
+        let menu = || ColumnarMenu::default().with_quick_complete_min_width(3);
+        let mut reedline = quick_completion_engine(menu(), "l");
+        assert_eq!(reedline.current_buffer_contents(), "l");
+        assert!(reedline.active_menu().is_some());
+        let reedline = quick_completion_engine(menu(), "chec");
+        assert_eq!(reedline.current_buffer_contents(), "checkout");
+    }
+
+    #[test]
+    fn recomplete_suggestion_shows_completions_of_expansion() {
+        let mut reedline = completion_engine(
+            AliasCompleter,
+            crate::ColumnarMenu::default(),
+            |reedline| reedline.with_accept_unambiguous_prefix(true),
+            "ll",
+        );
+        let prompt = crate::DefaultPrompt::default();
+
+        reedline
+            .handle_editor_event(&prompt, ReedlineEvent::Menu("columnar_menu".into()))
+            .unwrap();
+
+        assert_eq!(reedline.current_buffer_contents(), "ls -la ");
+        let menu = reedline
+            .active_menu()
+            .expect("menu shows the expanded completions");
+        let values: Vec<&str> = menu.get_values().iter().map(|s| s.value.as_str()).collect();
+        assert_eq!(values, vec!["src", "target"]);
+    }
+
+    #[test]
+    fn recomplete_completes_only_the_text_of_its_span() {
+        let mut reedline = completion_engine(
+            AliasCompleter,
+            crate::ColumnarMenu::default(),
+            |reedline| reedline.with_accept_unambiguous_prefix(true),
+            "sudo ll",
+        );
+        let prompt = crate::DefaultPrompt::default();
+        let tab = || ReedlineEvent::Menu("columnar_menu".into());
+
+        reedline.handle_editor_event(&prompt, tab()).unwrap();
+        assert_eq!(reedline.current_buffer_contents(), "sudo ls -la ");
+        let menu = reedline
+            .active_menu()
+            .expect("menu shows the expanded completions");
+        let values: Vec<&str> = menu.get_values().iter().map(|s| s.value.as_str()).collect();
+        assert_eq!(values, vec!["src", "target"]);
+
+        // The spans of the completions are moved back into the buffer
+        reedline
+            .handle_editor_event(&prompt, ReedlineEvent::Enter)
+            .unwrap();
+        assert_eq!(reedline.current_buffer_contents(), "sudo ls -la src");
+    }
+
+    #[test]
+    fn recomplete_stops_without_further_completions() {
+        let mut reedline = completion_engine(
+            AliasCompleter,
+            crate::ColumnarMenu::default(),
+            |reedline| reedline.with_accept_unambiguous_prefix(true),
+            "say",
+        );
+        let prompt = crate::DefaultPrompt::default();
+
+        reedline
+            .handle_editor_event(&prompt, ReedlineEvent::Menu("columnar_menu".into()))
+            .unwrap();
+
+        assert_eq!(reedline.current_buffer_contents(), "echo");
+        assert!(reedline.active_menu().is_none());
+    }
+
+    #[test]
+    fn history_search_indicator_shows_match_position() {
+        let mut history = FileBackedHistory::default();
+        for command in ["cargo build", "ls", "cargo test", "cargo run"] {
+            history
+                .save(HistoryItem::from_command_line(command))
+                .unwrap();
+        }
+        let mut reedline = Reedline::create()
+            .with_history(Box::new(history))
+            .with_ansi_colors(false)
+            .with_history_search_indicator(Box::new(|search| {
+                let (index, count) = search.match_position.unwrap_or((0, 0));
+                format!(
+                    "\x1b[1m(i-search [{index}/{count}]): {}\x1b[0m",
+                    search.term
+                )
+            }));
+        reedline.painter.handle_resize(80, 24);
+
+        reedline.history_cursor = HistoryCursor::new(
+            HistoryNavigationQuery::SubstringSearch("cargo".into()),
+            None,
+        );
+        reedline
+            .history_cursor
+            .back(reedline.history.as_ref())
+            .unwrap();
+        reedline
+            .history_cursor
+            .back(reedline.history.as_ref())
+            .unwrap();
+
+        let search = PromptHistorySearch::new(PromptHistorySearchStatus::Passing, "cargo".into())
+            .with_match_position(
+                reedline
+                    .history_cursor
+                    .match_position(reedline.history.as_ref()),
+            );
+        assert_eq!(
+            reedline.render_history_search_indicator(&search),
+            Some("(i-search [2/3]): cargo".to_string())
+        );
+
+        reedline.painter.handle_resize(20, 24);
+        assert_eq!(
+            reedline.render_history_search_indicator(&search),
+            Some("(i-search…".to_string())
+        );
+    }
+
+    #[test]
+    fn insert_generated_text() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let counter = AtomicUsize::new(0);
+        let mut reedline = Reedline::create().with_text_generator(Box::new(move || {
+            (counter.fetch_add(1, Ordering::SeqCst) + 1).to_string()
+        }));
+
+        reedline.run_edit_commands(&[EditCommand::InsertGenerated(0)]);
+        reedline.run_edit_commands(&[EditCommand::InsertChar(' ')]);
+        reedline.run_edit_commands(&[EditCommand::InsertGenerated(0)]);
+        assert_eq!(reedline.current_buffer_contents(), "1 2");
+
+        reedline.run_edit_commands(&[EditCommand::InsertGenerated(1)]);
+        assert_eq!(reedline.current_buffer_contents(), "1 2");
+
+        reedline.run_edit_commands(&[EditCommand::Undo]);
+        assert_eq!(reedline.current_buffer_contents(), "1 ");
+    }
+
+    #[test]
+    fn sync_history_picks_up_entries_of_other_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("history.txt");
+        let history = FileBackedHistory::with_file(100, file.clone()).unwrap();
+        let mut reedline = Reedline::create().with_history(Box::new(history));
+        reedline
+            .history_mut()
+            .save(HistoryItem::from_command_line("local"))
+            .unwrap();
+        reedline.sync_history().unwrap();
+
+        reedline.previous_history();
+        assert_eq!(reedline.input_mode, InputMode::HistoryTraversal);
+
+        let mut other = FileBackedHistory::with_file(100, file).unwrap();
+        other.save(HistoryItem::from_command_line("other")).unwrap();
+        other.sync().unwrap();
+
+        reedline.sync_history().unwrap();
+        assert_eq!(reedline.input_mode, InputMode::Regular);
+        assert_eq!(reedline.current_buffer_contents(), "local");
+
+        reedline.run_edit_commands(&[EditCommand::Clear]);
+        reedline.previous_history();
+        assert_eq!(reedline.current_buffer_contents(), "other");
+    }
+
+    #[test]
+    fn tab_in_active_menu_moves_to_next_entry_by_default() {
+        let mut reedline = completion_engine(
+            words_completer(&["hello", "help"]),
+            crate::ColumnarMenu::default(),
+            |reedline| reedline,
+            "hel",
+        );
+        show_menu(&mut reedline);
+        let prompt = crate::DefaultPrompt::default();
+
+        let status = reedline
+            .handle_editor_event(&prompt, ReedlineEvent::Menu("columnar_menu".into()))
+            .unwrap();
+
+        assert!(matches!(status, EventStatus::Inapplicable));
+        assert!(reedline.active_menu().is_some());
+    }
+
+    #[test]
+    fn tab_in_active_menu_accepts_selection() {
+        let mut reedline = completion_engine(
+            words_completer(&["hello", "help"]),
+            crate::ColumnarMenu::default(),
+            |reedline| reedline.with_menu_tab_behavior(MenuTabBehavior::AcceptSelection),
+            "hel",
+        );
+        show_menu(&mut reedline);
+        let prompt = crate::DefaultPrompt::default();
+
+        let status = reedline
+            .handle_editor_event(&prompt, ReedlineEvent::Menu("columnar_menu".into()))
+            .unwrap();
+
+        assert!(matches!(status, EventStatus::Handled));
+        assert_eq!(reedline.current_buffer_contents(), "hello");
+        assert!(reedline.active_menu().is_none());
+    }
+
+    #[test]
+    fn menu_accept_inserts_the_selection_without_submitting() {
+        let mut reedline = completion_engine(
+            words_completer(&["hello", "help"]),
+            crate::ColumnarMenu::default(),
+            |reedline| reedline,
+            "hel",
+        );
+        show_menu(&mut reedline);
+        let prompt = crate::DefaultPrompt::default();
+
+        let status = reedline
+            .handle_editor_event(&prompt, ReedlineEvent::MenuAccept)
+            .unwrap();
+        assert!(matches!(status, EventStatus::Handled));
+        assert_eq!(reedline.current_buffer_contents(), "hello");
+        assert!(reedline.active_menu().is_none());
+
+        // Without a menu it is left to the following events of the binding
+        let status = reedline
+            .handle_editor_event(&prompt, ReedlineEvent::MenuAccept)
+            .unwrap();
+        assert!(matches!(status, EventStatus::Inapplicable));
+    }
+
+    #[test]
+    fn menu_submit_submits_the_typed_line() {
+        let mut reedline = completion_engine(
+            words_completer(&["hello", "help"]),
+            crate::ColumnarMenu::default(),
+            |reedline| reedline,
+            "hel",
+        );
+        show_menu(&mut reedline);
+        let prompt = crate::DefaultPrompt::default();
+        handle_events(&mut reedline, vec![ReedlineEvent::Resize(80, 24)]);
+
+        let status = reedline
+            .handle_editor_event(&prompt, ReedlineEvent::MenuSubmit)
+            .unwrap();
+        assert!(matches!(
+            status,
+            EventStatus::Exits(Signal::Success(ref line)) if line == "hel"
+        ));
+        assert!(reedline.active_menu().is_none());
+    }
+
+    #[test]
+    fn tab_in_active_menu_extends_common_prefix_first() {
+        let mut reedline = completion_engine(
+            words_completer(&["batman", "batmobile"]),
+            crate::ColumnarMenu::default(),
+            |reedline| reedline.with_menu_tab_behavior(MenuTabBehavior::PartialComplete),
+            "ba",
+        );
+        show_menu(&mut reedline);
+        let prompt = crate::DefaultPrompt::default();
+
+        let status = reedline
+            .handle_editor_event(&prompt, ReedlineEvent::Menu("columnar_menu".into()))
+            .unwrap();
+        assert!(matches!(status, EventStatus::Handled));
+        assert_eq!(reedline.current_buffer_contents(), "batm");
+
+        let status = reedline
+            .handle_editor_event(&prompt, ReedlineEvent::Menu("columnar_menu".into()))
+            .unwrap();
+        assert!(matches!(status, EventStatus::Inapplicable));
+        assert_eq!(reedline.current_buffer_contents(), "batm");
+        assert!(reedline.active_menu().is_some());
+    }
+
+    #[test]
+    fn hints_are_hidden_while_a_menu_is_active() {
+        let mut reedline = completion_engine(
+            words_completer(&["hello", "help"]),
+            crate::ColumnarMenu::default(),
+            |reedline| reedline,
+            "hel",
+        );
+        show_menu(&mut reedline);
+        assert!(reedline.active_menu().is_some());
+        assert!(!reedline.hints_active());
+
+        let prompt = crate::DefaultPrompt::default();
+        reedline
+            .handle_editor_event(&prompt, ReedlineEvent::Esc)
+            .unwrap();
+        assert!(reedline.active_menu().is_none());
+        assert!(reedline.hints_active());
+
+        let mut reedline = completion_engine(
+            words_completer(&["hello", "help"]),
+            crate::ColumnarMenu::default(),
+            |reedline| reedline.with_hide_hints_in_menus(false),
+            "hel",
+        );
+        show_menu(&mut reedline);
+        assert!(reedline.hints_active());
+    }
+
+    #[test]
+    fn recorded_edit_commands_can_be_replayed() {
+        use std::sync::{Arc, Mutex};
+
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Arc::clone(&recorded);
+        let mut reedline = Reedline::create().with_edit_command_observer(Box::new(
+            move |command: &EditCommand| {
+                recorder.lock().unwrap().push(command.clone());
+            },
+        ));
+        let prompt = crate::DefaultPrompt::default();
+        for event in [
+            ReedlineEvent::Edit(vec![EditCommand::InsertString("git stats".into())]),
+            ReedlineEvent::Edit(vec![EditCommand::Backspace, EditCommand::MoveLeft]),
+            ReedlineEvent::Edit(vec![EditCommand::InsertChar('u')]),
+        ] {
+            reedline.handle_editor_event(&prompt, event).unwrap();
+        }
+        assert_eq!(reedline.current_buffer_contents(), "git staut");
+
+        let commands = recorded.lock().unwrap().clone();
+        assert_eq!(commands.len(), 4);
+
+        let mut replayed = Reedline::create();
+        replayed.run_edit_commands(&commands);
+        assert_eq!(replayed.current_buffer_contents(), "git staut");
+    }
+
+    #[test]
+    fn menu_delete_removes_the_selected_entry_from_the_history() {
+        use crate::ListMenu;
+
+        let mut reedline =
+            Reedline::create().with_menu(ReedlineMenu::HistoryMenu(Box::new(ListMenu::default())));
+        for command in ["ls", "cd /tmp", "ls", "pwd"] {
+            reedline
+                .history
+                .save(HistoryItem::from_command_line(command))
+                .unwrap();
+        }
+        let prompt = crate::DefaultPrompt::default();
+        handle_events(
+            &mut reedline,
+            vec![
+                ReedlineEvent::Resize(80, 24),
+                ReedlineEvent::Menu("search_menu".into()),
+            ],
+        );
+        reedline.repaint(&prompt).unwrap();
+        handle_events(&mut reedline, vec![ReedlineEvent::MenuNext]);
+        reedline.repaint(&prompt).unwrap();
+        assert_eq!(
+            reedline.menus[0].get_selected_value().unwrap().value,
+            "ls".to_string()
+        );
+
+        // Only the selected entry is deleted
+        reedline.history_last_run_id = Some(HistoryItemId::new(3));
+        handle_events(&mut reedline, vec![ReedlineEvent::MenuDelete]);
+        reedline.repaint(&prompt).unwrap();
+        assert_eq!(reedline.history.count_all().unwrap(), 3);
+        let values: Vec<_> = reedline.menus[0]
+            .get_values()
+            .iter()
+            .map(|suggestion| suggestion.value.as_str())
+            .collect();
+        assert_eq!(values, ["pwd", "cd /tmp", "ls"]);
+        assert_eq!(
+            reedline.menus[0].get_selected_value().unwrap().value,
+            "cd /tmp".to_string()
+        );
+
+        // The id of the last run command moved down with the later entries
+        let last_run_id = reedline.history_last_run_id.unwrap();
+        assert_eq!(last_run_id, HistoryItemId::new(2));
+        assert_eq!(
+            reedline.history.load(last_run_id).unwrap().command_line,
+            "pwd"
+        );
+    }
+
+    fn handle_events(reedline: &mut Reedline, events: Vec<ReedlineEvent>) {
+        let prompt = crate::DefaultPrompt::default();
+        for event in events {
+            reedline.handle_event(&prompt, event).unwrap();
+        }
+    }
+
+    fn with_gco_abbreviation(reedline: Reedline) -> Reedline {
+        reedline.with_abbreviations(HashMap::from([(
+            "gco".to_string(),
+            "git checkout".to_string(),
+        )]))
+    }
+
+    #[test]
+    fn abbreviation_is_expanded_by_a_space() {
+        let mut reedline = completion_engine(
+            words_completer(&[]),
+            crate::ColumnarMenu::default(),
+            with_gco_abbreviation,
+            "gco",
+        );
+        let space = || ReedlineEvent::Edit(vec![EditCommand::InsertChar(' ')]);
+        handle_events(&mut reedline, vec![space()]);
+        assert_eq!(reedline.current_buffer_contents(), "git checkout ");
+
+        // The space and the expansion are undone separately
+        let undo = || ReedlineEvent::Edit(vec![EditCommand::Undo]);
+        handle_events(&mut reedline, vec![undo()]);
+        assert_eq!(reedline.current_buffer_contents(), "git checkout");
+        handle_events(&mut reedline, vec![undo()]);
+        assert_eq!(reedline.current_buffer_contents(), "gco");
+
+        // Only whole words are expanded
+        for buffer in ["xgco", "echo a-gco", "gcox"] {
+            let mut reedline = completion_engine(
+                words_completer(&[]),
+                crate::ColumnarMenu::default(),
+                with_gco_abbreviation,
+                buffer,
+            );
+            handle_events(&mut reedline, vec![space()]);
+            assert_eq!(reedline.current_buffer_contents(), format!("{buffer} "));
+        }
+        let mut reedline = completion_engine(
+            words_completer(&[]),
+            crate::ColumnarMenu::default(),
+            with_gco_abbreviation,
+            "gcox",
+        );
+        reedline.run_edit_commands(&[EditCommand::MoveLeft]);
+        handle_events(&mut reedline, vec![space()]);
+        assert_eq!(reedline.current_buffer_contents(), "gco x");
+    }
+
+    #[test]
+    fn abbreviation_is_expanded_inside_a_merged_batch() {
+        let mut reedline = completion_engine(
+            words_completer(&[]),
+            crate::ColumnarMenu::default(),
+            with_gco_abbreviation,
+            "gc",
+        );
+        handle_events(
+            &mut reedline,
+            vec![ReedlineEvent::Edit(vec![
+                EditCommand::InsertChar('o'),
+                EditCommand::InsertChar(' '),
+                EditCommand::InsertChar('m'),
+            ])],
+        );
+        assert_eq!(reedline.current_buffer_contents(), "git checkout m");
+    }
+
+    #[test]
+    fn abbreviation_is_kept_with_a_literal_space() {
+        let mut reedline = completion_engine(
+            words_completer(&[]),
+            crate::ColumnarMenu::default(),
+            with_gco_abbreviation,
+            "echo gco",
+        );
+        handle_events(
+            &mut reedline,
+            vec![ReedlineEvent::Edit(vec![EditCommand::InsertString(
+                " ".into(),
+            )])],
+        );
+        assert_eq!(reedline.current_buffer_contents(), "echo gco ");
+    }
+
+    #[test]
+    fn abbreviation_is_expanded_on_submit() {
+        let mut reedline = completion_engine(
+            words_completer(&[]),
+            crate::ColumnarMenu::default(),
+            with_gco_abbreviation,
+            "echo; gco",
+        );
+        handle_events(&mut reedline, vec![ReedlineEvent::Resize(80, 24)]);
+        let result = reedline
+            .handle_event(&crate::DefaultPrompt::default(), ReedlineEvent::Enter)
+            .unwrap();
+
+        assert!(matches!(
+            result,
+            EventStatus::Exits(Signal::Success(buffer)) if buffer == "echo; git checkout"
+        ));
+    }
+
+    #[test]
+    fn macro_playback_is_undone_at_once() {
+        let mut reedline = Reedline::create();
+        handle_events(
+            &mut reedline,
+            vec![
+                ReedlineEvent::StartMacroRecording('a'),
+                ReedlineEvent::Edit(vec![EditCommand::InsertString("echo".into())]),
+                ReedlineEvent::Edit(vec![EditCommand::InsertChar(' ')]),
+                ReedlineEvent::StopMacroRecording,
+                ReedlineEvent::Edit(vec![EditCommand::InsertChar('|')]),
+                ReedlineEvent::PlayMacro('a'),
+            ],
+        );
+        assert_eq!(reedline.current_buffer_contents(), "echo |echo ");
+
+        handle_events(
+            &mut reedline,
+            vec![ReedlineEvent::Edit(vec![EditCommand::Undo])],
+        );
+        assert_eq!(reedline.current_buffer_contents(), "echo |");
+    }
+
+    #[test]
+    fn macro_is_played_repeatedly() {
+        let mut reedline = Reedline::create();
+        handle_events(
+            &mut reedline,
+            vec![
+                ReedlineEvent::StartMacroRecording('a'),
+                ReedlineEvent::Edit(vec![EditCommand::InsertString("ab".into())]),
+                ReedlineEvent::StopMacroRecording,
+                ReedlineEvent::PlayMacroTimes('a', 3),
+                ReedlineEvent::PlayMacroTimes('a', 0),
+            ],
+        );
+        assert_eq!(reedline.current_buffer_contents(), "abababab");
+
+        handle_events(
+            &mut reedline,
+            vec![ReedlineEvent::Edit(vec![EditCommand::Undo])],
+        );
+        assert_eq!(reedline.current_buffer_contents(), "ab");
+    }
+
+    #[test]
+    fn macro_repetitions_are_capped() {
+        let mut reedline = Reedline::create();
+        handle_events(
+            &mut reedline,
+            vec![
+                ReedlineEvent::StartMacroRecording('a'),
+                ReedlineEvent::Edit(vec![EditCommand::InsertChar('x')]),
+                ReedlineEvent::StopMacroRecording,
+                ReedlineEvent::Edit(vec![EditCommand::Clear]),
+                ReedlineEvent::PlayMacroTimes('a', usize::MAX),
+            ],
+        );
+        assert_eq!(
+            reedline.current_buffer_contents(),
+            "x".repeat(MAX_MACRO_REPEAT)
+        );
+    }
+
+    #[test]
+    fn recursive_macro_stops_playing_itself() {
+        let mut reedline = Reedline::create();
+        handle_events(
+            &mut reedline,
+            vec![
+                ReedlineEvent::StartMacroRecording('a'),
+                ReedlineEvent::Edit(vec![EditCommand::InsertChar('x')]),
+                ReedlineEvent::PlayMacro('a'),
+                ReedlineEvent::StopMacroRecording,
+                ReedlineEvent::PlayMacro('a'),
+                ReedlineEvent::PlayMacro('b'),
+            ],
+        );
+
+        assert_eq!(reedline.current_buffer_contents(), "xx");
+        assert_eq!(
+            reedline.macros[&'a'],
+            vec![
+                ReedlineEvent::Edit(vec![EditCommand::InsertChar('x')]),
+                ReedlineEvent::PlayMacro('a'),
+            ]
+        );
+    }
+
+    #[test]
+    fn cut_and_paste_use_the_custom_clipboard() {
+        use crate::{Clipboard, ClipboardMode};
+        use std::sync::{Arc, Mutex};
+
+        struct SharedClipboard(Arc<Mutex<String>>);
+
+        impl Clipboard for SharedClipboard {
+            fn set(&mut self, content: &str, _mode: ClipboardMode) -> std::io::Result<()> {
+                *self.0.lock().unwrap() = content.to_owned();
+                Ok(())
+            }
+
+            fn get(&mut self) -> std::io::Result<(String, ClipboardMode)> {
+                Ok((self.0.lock().unwrap().clone(), ClipboardMode::Normal))
+            }
+        }
+
+        let content = Arc::new(Mutex::new(String::new()));
+        let mut reedline =
+            Reedline::create().with_clipboard(Box::new(SharedClipboard(Arc::clone(&content))));
+
+        reedline.run_edit_commands(&[
+            EditCommand::InsertString("echo hello".into()),
+            EditCommand::CutWordLeft,
+        ]);
+        assert_eq!(*content.lock().unwrap(), "hello");
+
+        *content.lock().unwrap() = "world".into();
+        reedline.run_edit_commands(&[EditCommand::PasteCutBufferBefore]);
+        assert_eq!(reedline.current_buffer_contents(), "echo world");
+    }
+
+    /// Paints every character red
+    struct RedHighlighter;
+
+    impl Highlighter for RedHighlighter {
+        fn highlight(&self, line: &str, _cursor: usize) -> StyledText {
+            let mut styled = StyledText::new();
+            styled.push((Style::new().fg(nu_ansi_term::Color::Red), line.to_string()));
+            styled
+        }
+    }
+
+    #[rstest]
+    #[case(RenderPipeline::default(), true)]
+    #[case(RenderPipeline::new(vec![RenderPass::SearchMatch, RenderPass::Highlight]), false)]
+    fn search_matches_are_layered_with_the_highlighting(
+        #[case] render_pipeline: RenderPipeline,
+        #[case] match_on_top: bool,
+    ) {
+        let red = Style::new().fg(nu_ansi_term::Color::Red);
+        let green = Style::new().fg(nu_ansi_term::Color::Green);
+        let mut history = FileBackedHistory::default();
+        history
+            .save(HistoryItem::from_command_line("cargo build"))
+            .unwrap();
+        let output = crate::painting::TestOutput::default();
+        let mut reedline = Reedline::create()
+            .with_history(Box::new(history))
+            .with_highlighter(Box::new(RedHighlighter))
+            .with_render_pipeline(render_pipeline);
+        reedline.painter = output.painter();
+        reedline.painter.set_color_level(ColorLevel::TrueColor);
+        let prompt = crate::DefaultPrompt::default();
+        handle_events(
+            &mut reedline,
+            vec![ReedlineEvent::Resize(80, 24), ReedlineEvent::SearchHistory],
+        );
+        for c in "build".chars() {
+            handle_events(
+                &mut reedline,
+                vec![ReedlineEvent::Edit(vec![EditCommand::InsertChar(c)])],
+            );
+        }
+        output.take();
+        reedline.repaint(&prompt).unwrap();
+
+        let painted = output.take();
+        let expected = if match_on_top {
+            format!("{}{}", red.paint("cargo "), green.paint("build"))
+        } else {
+            red.paint("cargo build").to_string()
+        };
+        assert!(painted.contains(&expected), "{painted:?}");
+    }
+
+    #[rstest]
+    #[case(RenderPipeline::default(), true)]
+    #[case(RenderPipeline::new(vec![RenderPass::Selection, RenderPass::Highlight]), false)]
+    fn selection_is_layered_with_the_highlighting(
+        #[case] render_pipeline: RenderPipeline,
+        #[case] selection_on_top: bool,
+    ) {
+        let red = Style::new().fg(nu_ansi_term::Color::Red);
+        let selection_style = Style::new().fg(nu_ansi_term::Color::Blue);
+        let output = crate::painting::TestOutput::default();
+        let mut reedline = Reedline::create()
+            .with_highlighter(Box::new(RedHighlighter))
+            .with_selection_style(selection_style)
+            .with_render_pipeline(render_pipeline);
+        reedline.painter = output.painter();
+        reedline.painter.set_color_level(ColorLevel::TrueColor);
+        let prompt = crate::DefaultPrompt::default();
+        handle_events(
+            &mut reedline,
+            vec![
+                ReedlineEvent::Resize(80, 24),
+                ReedlineEvent::Edit(vec![
+                    EditCommand::InsertString("ls -la".into()),
+                    EditCommand::MoveToStart,
+                    EditCommand::StartSelection,
+                    EditCommand::MoveRight,
+                ]),
+            ],
+        );
+        output.take();
+        reedline.repaint(&prompt).unwrap();
+
+        let painted = output.take();
+        // The buffer is painted in two parts, before and after the cursor
+        let expected = if selection_on_top {
+            format!("{}{}", selection_style.paint("s"), red.paint(" -la"))
+        } else {
+            red.paint("s -la").to_string()
+        };
+        assert!(painted.contains(&expected), "{painted:?}");
+    }
+
+    #[test]
+    fn selected_placeholder_is_painted_with_the_selection_style() {
+        let output = crate::painting::TestOutput::default();
+        let selection_style = Style::new().fg(nu_ansi_term::Color::Red);
+        let mut reedline = Reedline::create().with_selection_style(selection_style);
+        reedline.painter = output.painter();
+        reedline.painter.set_color_level(ColorLevel::TrueColor);
+        let prompt = crate::DefaultPrompt::default();
+        handle_events(&mut reedline, vec![ReedlineEvent::Resize(80, 24)]);
+
+        reedline
+            .editor
+            .set_buffer("foo(arg1, arg2)".into(), UndoBehavior::CreateUndoPoint);
+        reedline.editor.set_tab_stops(vec![4..8, 10..14]);
+        output.take();
+        reedline.repaint(&prompt).unwrap();
+        assert!(output
+            .take()
+            .contains(&selection_style.paint("arg1").to_string()));
+
+        // Typed text replaces the placeholder and is painted without the style
+        handle_events(
+            &mut reedline,
+            vec![ReedlineEvent::Edit(vec![EditCommand::InsertChar('x')])],
+        );
+        reedline.repaint(&prompt).unwrap();
+        assert!(!output
+            .take()
+            .contains(&selection_style.prefix().to_string()));
+    }
+
+    #[test]
+    fn osc52_sequences_are_written_by_the_painter() {
+        let output = crate::painting::TestOutput::default();
+        let mut reedline =
+            Reedline::create().with_clipboard(Box::new(crate::Osc52Clipboard::new()));
+        reedline.painter = output.painter();
+
+        reedline.run_edit_commands(&[
+            EditCommand::InsertString("echo foo".into()),
+            EditCommand::CutWordLeft,
+        ]);
+        reedline.write_clipboard_output().unwrap();
+        assert_eq!(output.take(), "\x1b]52;c;Zm9v\x07");
+
+        // Pasting uses the cut buffer, the terminal clipboard can't be read
+        reedline.run_edit_commands(&[EditCommand::PasteCutBufferBefore]);
+        assert_eq!(reedline.current_buffer_contents(), "echo foo");
+    }
+
+    #[test]
+    fn exit_on_empty_only_exits_on_an_empty_line() {
+        let mut reedline = Reedline::create();
+        let prompt = crate::DefaultPrompt::default();
+        let ctrl_d = ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::ExitOnEmpty,
+            ReedlineEvent::Edit(vec![EditCommand::Delete]),
+        ]);
+        reedline.run_edit_commands(&[
+            EditCommand::InsertString("ls".into()),
+            EditCommand::MoveToStart,
+        ]);
+
+        let status = reedline
+            .handle_event(&prompt, ReedlineEvent::ExitOnEmpty)
+            .unwrap();
+        assert!(matches!(status, EventStatus::Inapplicable));
+        assert_eq!(reedline.current_buffer_contents(), "ls");
+
+        for expected in ["s", ""] {
+            let status = reedline.handle_event(&prompt, ctrl_d.clone()).unwrap();
+            assert!(matches!(status, EventStatus::Handled));
+            assert_eq!(reedline.current_buffer_contents(), expected);
+        }
+        let status = reedline.handle_event(&prompt, ctrl_d).unwrap();
+        assert!(matches!(status, EventStatus::Exits(Signal::CtrlD)));
+    }
+
+    #[test]
+    fn clear_and_reprint_keeps_the_entry_and_starts_an_empty_prompt_below() {
+        let output = crate::painting::TestOutput::default();
+        let mut reedline = Reedline::create();
+        reedline.painter = output.painter();
+        let prompt = crate::DefaultPrompt::default();
+        handle_events(
+            &mut reedline,
+            vec![
+                ReedlineEvent::Resize(80, 24),
+                ReedlineEvent::Edit(vec![EditCommand::InsertString("ls -l".into())]),
+            ],
+        );
+        reedline.repaint(&prompt).unwrap();
+        output.take();
+
+        let status = reedline
+            .handle_event(&prompt, ReedlineEvent::ClearAndReprint)
+            .unwrap();
+        assert!(matches!(status, EventStatus::Handled));
+        assert_eq!(reedline.current_buffer_contents(), "");
+        // The painted entry is left as it is, the screen scrolls up to make room for
+        // the prompt on the last row
+        assert_eq!(output.take(), "\x1b[1S\x1b[24;1H");
+        assert_eq!(reedline.painter.remaining_lines(), 1);
+
+        // The cleared line can't be brought back
+        handle_events(
+            &mut reedline,
+            vec![ReedlineEvent::Edit(vec![EditCommand::Undo])],
+        );
+        assert_eq!(reedline.current_buffer_contents(), "");
+    }
+
+    #[test]
+    fn background_completions_refresh_the_active_menu() {
+        use crate::{BackgroundCompleter, ColumnarMenu};
+
+        let completer = DefaultCompleter::new(vec!["hello".into(), "help".into()]);
+        let mut reedline = Reedline::create()
+            .with_completer(Box::new(BackgroundCompleter::new(Box::new(completer))))
+            .with_menu(ReedlineMenu::EngineCompleter(Box::new(
+                ColumnarMenu::default(),
+            )));
+        reedline.run_edit_commands(&[EditCommand::InsertString("hel".into())]);
+
+        let prompt = crate::DefaultPrompt::default();
+        reedline
+            .handle_editor_event(&prompt, ReedlineEvent::Menu("columnar_menu".into()))
+            .unwrap();
+        reedline.menus[0].update_values(
+            &mut reedline.editor,
+            reedline.completer.as_mut(),
+            reedline.history.as_ref(),
+        );
+        assert!(reedline.menus[0].get_values().is_empty());
+
+        let start = Instant::now();
+        while !reedline.refresh_background_completions() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        reedline.menus[0].update_values(
+            &mut reedline.editor,
+            reedline.completer.as_mut(),
+            reedline.history.as_ref(),
+        );
+        assert_eq!(reedline.menus[0].get_values().len(), 2);
+    }
+
+    #[rstest]
+    #[case::engine_completer(false)]
+    #[case::own_completer(true)]
+    fn streamed_completions_keep_the_menu_selection(#[case] own_completer: bool) {
+        use crate::{
+            ColumnarMenu, IncrementalCompleter, Span, StreamingCompleter, SuggestionSender,
+        };
+        use std::sync::mpsc::{channel, Receiver};
+
+        // Sends the batches of values it receives
+        struct ChannelCompleter(Receiver<Vec<&'static str>>);
+
+        impl StreamingCompleter for ChannelCompleter {
+            fn complete_stream(&mut self, _line: &str, pos: usize, sender: &SuggestionSender) {
+                while let Ok(values) = self.0.recv() {
+                    let suggestions = values
+                        .into_iter()
+                        .map(|value| crate::Suggestion::new(value, Span::new(0, pos)))
+                        .collect();
+                    if !sender.send(suggestions) {
+                        return;
+                    }
+                }
+            }
+        }
+
+        let (batches, receiver) = channel();
+        let completer = Box::new(IncrementalCompleter::new(Box::new(ChannelCompleter(
+            receiver,
+        ))));
+        let menu = Box::new(ColumnarMenu::default());
+        let mut reedline = if own_completer {
+            Reedline::create().with_menu(ReedlineMenu::WithCompleter { menu, completer })
+        } else {
+            Reedline::create()
+                .with_completer(completer)
+                .with_menu(ReedlineMenu::EngineCompleter(menu))
+        };
+        let prompt = crate::DefaultPrompt::default();
+        handle_events(
+            &mut reedline,
+            vec![
+                ReedlineEvent::Resize(80, 24),
+                ReedlineEvent::Edit(vec![EditCommand::InsertString("a".into())]),
+                ReedlineEvent::Menu("columnar_menu".into()),
+            ],
+        );
+        reedline.repaint(&prompt).unwrap();
+
+        let wait_for_batch = |reedline: &mut Reedline| {
+            let start = Instant::now();
+            while !reedline.refresh_background_completions() {
+                assert!(start.elapsed() < Duration::from_secs(5));
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            reedline.repaint(&prompt).unwrap();
+        };
+
+        batches.send(vec!["a1", "a2"]).unwrap();
+        wait_for_batch(&mut reedline);
+        handle_events(&mut reedline, vec![ReedlineEvent::MenuNext]);
+        reedline.repaint(&prompt).unwrap();
+        assert_eq!(reedline.menus[0].get_selected_value().unwrap().value, "a2");
+
+        batches.send(vec!["a3"]).unwrap();
+        wait_for_batch(&mut reedline);
+        assert_eq!(reedline.menus[0].get_values().len(), 3);
+        assert_eq!(reedline.menus[0].get_selected_value().unwrap().value, "a2");
+    }
+
+    #[test]
+    fn render_pipeline_without_hint_hides_hints() {
+        let reedline = Reedline::create();
+        assert!(reedline.hints_active());
+
+        let reedline =
+            reedline.with_render_pipeline(RenderPipeline::default().without(RenderPass::Hint));
+        assert!(!reedline.hints_active());
+    }
+
+    #[cfg(any(feature = "sqlite", feature = "sqlite-dynlib"))]
+    #[rstest]
+    #[case(None, "other session")]
+    #[case(Some(true), "other session")]
+    #[case(Some(false), "this session")]
+    fn history_search_across_sessions_keeps_navigation_in_session(
+        #[case] across_sessions: Option<bool>,
+        #[case] expected_match: &str,
+    ) {
+        let session = Some(HistorySessionId::new(1));
+        let mut history = crate::SqliteBackedHistory::in_memory().unwrap();
+        for (command, session_id) in [("this session", session), ("other session", None)] {
+            history
+                .save(HistoryItem {
+                    session_id,
+                    ..HistoryItem::from_command_line(command)
+                })
+                .unwrap();
+        }
+        let mut reedline = Reedline::create()
+            .with_history(Box::new(history))
+            .with_history_session_id(session);
+        if let Some(across_sessions) = across_sessions {
+            reedline = reedline.with_history_search_across_sessions(across_sessions);
+        }
+
+        reedline.previous_history();
+        assert_eq!(reedline.current_buffer_contents(), "this session");
+
+        reedline.enter_history_search();
+        reedline.run_history_commands(&[EditCommand::InsertChar('s')]);
+        assert_eq!(
+            reedline.history_cursor.string_at_cursor(),
+            Some(expected_match.to_string())
+        );
+    }
+
+    #[test]
+    fn every_engine_gets_a_history_session_id() {
+        let reedline = Reedline::create().with_history(Box::<FileBackedHistory>::default());
+
+        assert!(reedline.get_history_session_id().is_some());
+    }
+
+    #[test]
+    fn submit_and_keep_restores_the_line() {
+        use crate::{SearchDirection, SearchQuery};
+
+        let mut reedline = Reedline::create();
+        let prompt = crate::DefaultPrompt::default();
+        reedline
+            .handle_event(&prompt, ReedlineEvent::Resize(80, 24))
+            .unwrap();
+        reedline.run_edit_commands(&[
+            EditCommand::InsertString("cargo test".into()),
+            EditCommand::MoveLeft,
+        ]);
+
+        let status = reedline
+            .handle_event(&prompt, ReedlineEvent::SubmitAndKeep)
+            .unwrap();
+        assert!(
+            matches!(status, EventStatus::Exits(Signal::Success(ref line)) if line == "cargo test")
+        );
+        let entries = reedline
+            .history()
+            .search(SearchQuery::everything(SearchDirection::Forward, None))
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].command_line, "cargo test");
+
+        reedline.restore_kept_line();
+        assert_eq!(reedline.current_buffer_contents(), "cargo test");
+        assert_eq!(reedline.current_insertion_point(), 9);
+        assert_eq!(reedline.editor.selection_range(), Some(0..10));
+
+        reedline.run_edit_commands(&[EditCommand::MoveToStart]);
+        assert_eq!(reedline.editor.selection_range(), Some(0..10));
+        reedline.run_edit_commands(&[EditCommand::CutSelection]);
+        assert_eq!(reedline.current_buffer_contents(), "");
+    }
+
+    #[test]
+    fn history_hint_next_accepts_the_older_match() {
+        use crate::{DefaultHinter, HistoryItem};
+
+        let mut reedline = Reedline::create().with_hinter(Box::new(DefaultHinter::default()));
+        for command in ["git status", "git push"] {
+            reedline
+                .history_mut()
+                .save(HistoryItem::from_command_line(command))
+                .unwrap();
+        }
+        let prompt = crate::DefaultPrompt::default();
+        reedline
+            .handle_event(&prompt, ReedlineEvent::Resize(80, 24))
+            .unwrap();
+        reedline
+            .handle_event(
+                &prompt,
+                ReedlineEvent::Edit(vec![EditCommand::InsertString("git".into())]),
+            )
+            .unwrap();
+        // The hinter looks up the hints while painting
+        reedline.repaint(&prompt).unwrap();
+
+        let status = reedline
+            .handle_event(&prompt, ReedlineEvent::HistoryHintNext)
+            .unwrap();
+        assert!(matches!(status, EventStatus::Handled));
+        assert!(matches!(
+            reedline
+                .handle_event(&prompt, ReedlineEvent::HistoryHintNext)
+                .unwrap(),
+            EventStatus::Inapplicable
+        ));
+
+        reedline
+            .handle_event(&prompt, ReedlineEvent::HistoryHintComplete)
+            .unwrap();
+        assert_eq!(reedline.current_buffer_contents(), "git status");
+    }
+
+    #[test]
+    fn event_hooks_pass_replace_and_swallow_events() {
+        let mut reedline = Reedline::create()
+            .with_event_hook(Box::new(|editor, event| match event {
+                ReedlineEvent::Esc => EventAction::Swallow,
+                ReedlineEvent::Left => EventAction::Replace(vec![ReedlineEvent::Right]),
+                ReedlineEvent::Enter if editor.is_empty() => {
+                    EventAction::Replace(vec![ReedlineEvent::Enter, ReedlineEvent::ClearScreen])
+                }
+                _ => EventAction::Pass,
+            }))
+            // Later hooks see the replaced events
+            .with_event_hook(Box::new(|editor, event| {
+                if matches!(event, ReedlineEvent::Right) {
+                    editor.run_edit_command(&EditCommand::InsertChar('r'));
+                }
+                EventAction::Pass
+            }));
+
+        let events = reedline.apply_event_hooks(vec![
+            ReedlineEvent::Esc,
+            ReedlineEvent::Enter,
+            ReedlineEvent::Left,
+            ReedlineEvent::Up,
+        ]);
+
+        assert_eq!(
+            events,
+            [
+                ReedlineEvent::Enter,
+                ReedlineEvent::ClearScreen,
+                ReedlineEvent::Right,
+                ReedlineEvent::Up
+            ]
+        );
+        assert_eq!(reedline.current_buffer_contents(), "r");
+    }
+
+    struct QuoteValidator;
+
+    impl Validator for QuoteValidator {
+        fn validate(&self, line: &str) -> ValidationResult {
+            if line.matches('"').count() % 2 == 1 {
+                ValidationResult::IncompleteWithReason("quote".into())
+            } else if line.ends_with('\\') {
+                ValidationResult::Incomplete
+            } else {
+                ValidationResult::Complete
+            }
+        }
+    }
+
+    #[test]
+    fn incomplete_reason_is_shown_for_its_own_line() {
+        let prompt = TestPrompt {
+            shows_incomplete_reason: true,
+            ..TestPrompt::default()
+        };
+        let mut reedline = Reedline::create().with_validator(Box::new(QuoteValidator));
+        let insert = |text: &str| ReedlineEvent::Edit(vec![EditCommand::InsertString(text.into())]);
+
+        // Validators without a reason get the generic continuation prompt
+        handle_events(
+            &mut reedline,
+            vec![
+                ReedlineEvent::Resize(80, 24),
+                insert("echo \\"),
+                ReedlineEvent::Enter,
+                insert("\"a"),
+                ReedlineEvent::Enter,
+            ],
+        );
+        assert_eq!(reedline.current_buffer_contents(), "echo \\\n\"a\n");
+        assert_eq!(
+            reedline.continuation_prompts(&prompt),
+            vec!["::: ", "quote> "]
+        );
+
+        let (before_cursor, _) = StyledText {
+            buffer: vec![(Style::default(), reedline.current_buffer_contents().into())],
+        }
+        .render_around_insertion_point_with_indicators(
+            reedline.current_buffer_contents().len(),
+            &prompt,
+            &reedline.continuation_prompts(&prompt),
+            false,
+        );
+        assert_eq!(before_cursor, "echo \\\n::: \"a\nquote> ");
+
+        // Nothing is left over for the next input
+        handle_events(&mut reedline, vec![ReedlineEvent::CtrlC, insert("a\nb")]);
+        assert_eq!(reedline.continuation_prompts(&prompt), vec!["::: "]);
+    }
+
+    #[test]
+    fn submit_repaints_with_the_transient_prompt() {
+        let prompt = TestPrompt {
+            left: "~/src".into(),
+            transient: Some("$ ".into()),
+            ..TestPrompt::default()
+        };
+        let full_indicator = prompt.render_prompt_indicator(PromptEditMode::Default);
+        let output = crate::painting::TestOutput::default();
+        let mut reedline = Reedline::create();
+        reedline.painter = output.painter();
+        for event in [
+            ReedlineEvent::Resize(80, 24),
+            ReedlineEvent::Edit(vec![EditCommand::InsertString("ls".into())]),
+        ] {
+            reedline.handle_event(&prompt, event).unwrap();
+        }
+        reedline.repaint(&prompt).unwrap();
+        assert!(output.take().contains(full_indicator.as_ref()));
+
+        let status = reedline
+            .handle_event(&prompt, ReedlineEvent::Submit)
+            .unwrap();
+        assert!(matches!(status, EventStatus::Exits(Signal::Success(ref line)) if line == "ls"));
+        let painted = output.take();
+        assert!(painted.contains("$ "));
+        assert!(painted.contains("ls"));
+        assert!(!painted.contains(full_indicator.as_ref()));
+    }
+
+    #[test]
+    fn command_status_is_shown_until_the_next_line_is_accepted() {
+        let prompt = TestPrompt {
+            shows_failed_status: true,
+            ..TestPrompt::default()
+        };
+        let mut reedline = Reedline::create();
+        let status_segment = |reedline: &Reedline| {
+            let mut lines = PromptLines::new(&prompt, PromptEditMode::Default, None, "", "", "");
+            let left = lines.prompt_str_left.to_string();
+            reedline.add_status_segment(&prompt, &mut lines);
+            lines
+                .prompt_str_left
+                .strip_suffix(&left)
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(status_segment(&reedline), "");
+
+        reedline.set_last_command_status(0, Some(Duration::from_secs(1)));
+        assert_eq!(status_segment(&reedline), "");
+
+        reedline.set_last_command_status(2, Some(Duration::from_secs(1)));
+        assert_eq!(
+            reedline.last_command_status(),
+            Some(CommandStatus {
+                exit_code: 2,
+                duration: Duration::from_secs(1)
+            })
+        );
+        assert_eq!(status_segment(&reedline), "[2] ");
+
+        handle_events(
+            &mut reedline,
+            vec![ReedlineEvent::Resize(80, 24), ReedlineEvent::Enter],
+        );
+        assert_eq!(reedline.last_command_status(), None);
+        assert_eq!(status_segment(&reedline), "");
+    }
+
+    #[test]
+    fn command_duration_is_measured_from_the_accepted_line() {
+        let mut reedline = Reedline::create();
+        reedline.line_accepted_at = Instant::now().checked_sub(Duration::from_secs(5));
+
+        reedline.set_last_command_status(1, None);
+        reedline.measure_last_command();
+        let duration = reedline.last_command_status().unwrap().duration;
+        assert!(duration >= Duration::from_secs(5));
+
+        // Measured once, when the next prompt is painted
+        reedline.measure_last_command();
+        assert_eq!(reedline.last_command_status().unwrap().duration, duration);
+
+        reedline.set_last_command_status(1, Some(Duration::from_millis(20)));
+        reedline.measure_last_command();
+        assert_eq!(
+            reedline.last_command_status().unwrap().duration,
+            Duration::from_millis(20)
+        );
+    }
+
+    #[cfg(feature = "external_printer")]
+    #[test]
+    fn external_messages_end_the_wait_for_input() {
+        let printer = ExternalPrinter::default();
+        let mut reedline = Reedline::create().with_external_printer(printer.clone());
+
+        let sender = reedline.external_printer().unwrap().sender();
+        std::thread::spawn(move || sender.send("first\nsecond".to_string()))
+            .join()
+            .unwrap()
+            .unwrap();
+
+        let start = Instant::now();
+        assert!(!reedline.poll_input(Duration::from_secs(10)).unwrap());
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert_eq!(
+            Reedline::external_messages(&printer).unwrap(),
+            ["first", "second"]
+        );
+    }
+
+    fn quoted_insert_engine(mode: QuotedInsertMode) -> Reedline {
+        quoted_insert_engine_with_binding(mode, ReedlineEvent::QuotedInsert)
+    }
+
+    fn quoted_insert_engine_with_binding(mode: QuotedInsertMode, event: ReedlineEvent) -> Reedline {
+        use crate::{default_emacs_keybindings, Emacs};
+
+        let mut keybindings = default_emacs_keybindings();
+        keybindings.add_binding(KeyModifiers::CONTROL, KeyCode::Char('v'), event);
+        Reedline::create()
+            .with_edit_mode(Box::new(Emacs::new(keybindings)))
+            .with_quoted_insert_mode(mode)
+    }
+
+    fn type_quoted(reedline: &mut Reedline, modifiers: KeyModifiers, code: KeyCode) {
+        let key = |modifiers: KeyModifiers, code: KeyCode| {
+            ReedlineRawEvent::convert_from(Event::Key(KeyEvent::new(code, modifiers))).unwrap()
+        };
+        // Both keys are parsed before the events are handled, like a fast typist's
+        let events = vec![
+            reedline.parse_event(key(KeyModifiers::CONTROL, KeyCode::Char('v'))),
+            reedline.parse_event(key(modifiers, code)),
+        ];
+        handle_events(reedline, events);
+    }
+
+    #[test]
+    fn quoted_insert_inserts_the_next_key_literally() {
+        let mut reedline = quoted_insert_engine(QuotedInsertMode::Raw);
+
+        type_quoted(&mut reedline, KeyModifiers::NONE, KeyCode::Tab);
+        type_quoted(&mut reedline, KeyModifiers::CONTROL, KeyCode::Char('a'));
+        type_quoted(&mut reedline, KeyModifiers::NONE, KeyCode::Esc);
+        type_quoted(&mut reedline, KeyModifiers::NONE, KeyCode::Left);
+        assert_eq!(reedline.current_buffer_contents(), "\t\x01\x1b");
+
+        // Only a single key is quoted
+        let left = ReedlineRawEvent::convert_from(Event::Key(KeyEvent::new(
+            KeyCode::Left,
+            KeyModifiers::NONE,
+        )))
+        .unwrap();
+        assert_eq!(
+            reedline.parse_event(left),
+            ReedlineEvent::UntilFound(vec![ReedlineEvent::MenuLeft, ReedlineEvent::Left])
+        );
+    }
+
+    #[test]
+    fn timeout_counts_from_the_deadline() {
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(100);
+
+        assert_eq!(
+            time_until(deadline, start),
+            Some(Duration::from_millis(100))
+        );
+        // Input in between doesn't move the deadline
+        assert_eq!(
+            time_until(deadline, start + Duration::from_millis(60)),
+            Some(Duration::from_millis(40))
+        );
+        assert_eq!(time_until(deadline, deadline), None);
+        assert_eq!(
+            time_until(deadline, start + Duration::from_millis(150)),
+            None
+        );
+    }
+
+    #[rstest]
+    #[case::multiple(ReedlineEvent::Multiple(vec![ReedlineEvent::QuotedInsert]))]
+    #[case::until_found(ReedlineEvent::UntilFound(vec![
+        ReedlineEvent::QuotedInsert,
+        ReedlineEvent::Edit(vec![EditCommand::InsertChar('v')]),
+    ]))]
+    fn quoted_insert_works_inside_groups_of_events(#[case] event: ReedlineEvent) {
+        let mut reedline = quoted_insert_engine_with_binding(QuotedInsertMode::Raw, event);
+
+        type_quoted(&mut reedline, KeyModifiers::NONE, KeyCode::Tab);
+        assert_eq!(reedline.current_buffer_contents(), "\t");
+    }
+
+    #[test]
+    fn quoted_insert_can_use_caret_notation() {
+        let mut reedline = quoted_insert_engine(QuotedInsertMode::CaretNotation);
+
+        type_quoted(&mut reedline, KeyModifiers::NONE, KeyCode::Tab);
+        type_quoted(&mut reedline, KeyModifiers::CONTROL, KeyCode::Char('['));
+        type_quoted(&mut reedline, KeyModifiers::NONE, KeyCode::Backspace);
+        type_quoted(&mut reedline, KeyModifiers::NONE, KeyCode::Char('x'));
+        assert_eq!(reedline.current_buffer_contents(), "^I^[^?x");
+    }
+
+    #[test]
+    fn history_search_steps_through_matches_and_accepts_at_the_match() {
+        let mut history = FileBackedHistory::default();
+        for command in ["echo cargo", "ls", "git log", "cargo test"] {
+            history
+                .save(HistoryItem::from_command_line(command))
+                .unwrap();
+        }
+        let mut reedline = Reedline::create().with_history(Box::new(history));
+        reedline.run_edit_commands(&[EditCommand::InsertString("draft".into())]);
+        let key = |modifiers: KeyModifiers, code: KeyCode| {
+            ReedlineRawEvent::convert_from(Event::Key(KeyEvent::new(code, modifiers))).unwrap()
+        };
+        let ctrl = |c: char| key(KeyModifiers::CONTROL, KeyCode::Char(c));
+
+        let press = |reedline: &mut Reedline, key: ReedlineRawEvent| {
+            let event = reedline.parse_event(key);
+            handle_events(reedline, vec![event]);
+        };
+
+        press(&mut reedline, ctrl('r'));
+        for c in "carg".chars() {
+            press(&mut reedline, key(KeyModifiers::NONE, KeyCode::Char(c)));
+        }
+        assert_eq!(
+            reedline.history_cursor.string_at_cursor().as_deref(),
+            Some("cargo test")
+        );
+
+        press(&mut reedline, ctrl('r'));
+        assert_eq!(
+            reedline.history_cursor.string_at_cursor().as_deref(),
+            Some("echo cargo")
+        );
+        assert_eq!(reedline.history_search_direction, SearchDirection::Backward);
+
+        press(&mut reedline, ctrl('s'));
+        assert_eq!(
+            reedline.history_cursor.string_at_cursor().as_deref(),
+            Some("cargo test")
+        );
+        assert_eq!(reedline.history_search_direction, SearchDirection::Forward);
+
+        // Cancelling keeps the original buffer
+        press(&mut reedline, ctrl('g'));
+        assert_eq!(reedline.input_mode, InputMode::Regular);
+        assert_eq!(reedline.current_buffer_contents(), "draft");
+
+        press(&mut reedline, ctrl('r'));
+        for c in "cargo".chars() {
+            press(&mut reedline, key(KeyModifiers::NONE, KeyCode::Char(c)));
+        }
+        press(&mut reedline, ctrl('r'));
+        handle_events(&mut reedline, vec![ReedlineEvent::Enter]);
+        assert_eq!(reedline.input_mode, InputMode::Regular);
+        assert_eq!(reedline.current_buffer_contents(), "echo cargo");
+        assert_eq!(reedline.current_insertion_point(), 5);
+    }
+
+    fn type_vi_keys(reedline: &mut Reedline, keys: &[KeyCode]) {
+        for code in keys {
+            let modifiers = match code {
+                KeyCode::Char(c) if c.is_uppercase() => KeyModifiers::SHIFT,
+                _ => KeyModifiers::NONE,
+            };
+            let key = ReedlineRawEvent::convert_from(Event::Key(KeyEvent::new(*code, modifiers)))
+                .unwrap();
+            let event = reedline.parse_event(key);
+            handle_events(reedline, vec![event]);
+        }
+    }
+
+    #[rstest]
+    #[case::cut_chars("abcd", "xxP", "bcd")]
+    #[case::delete_words("ab cd d", "dwdwp", "dcd ")]
+    fn vi_cuts_are_not_merged(#[case] line: &str, #[case] keys: &str, #[case] expected: &str) {
+        use crate::Vi;
+
+        let mut reedline = Reedline::create().with_edit_mode(Box::new(Vi::default()));
+        handle_events(
+            &mut reedline,
+            vec![ReedlineEvent::Edit(vec![EditCommand::InsertString(
+                line.into(),
+            )])],
+        );
+        type_vi_keys(&mut reedline, &[KeyCode::Esc, KeyCode::Char('0')]);
+        type_vi_keys(
+            &mut reedline,
+            &keys.chars().map(KeyCode::Char).collect::<Vec<_>>(),
+        );
+
+        // Each cut replaces the unnamed register, like in Vim
+        assert_eq!(reedline.current_buffer_contents(), expected);
+    }
+
+    #[test]
+    fn vi_visual_mode_deletes_the_selection() {
+        use crate::Vi;
+
+        let mut reedline = Reedline::create().with_edit_mode(Box::new(Vi::default()));
+        handle_events(
+            &mut reedline,
+            vec![ReedlineEvent::Edit(vec![EditCommand::InsertString(
+                "hello world".into(),
+            )])],
+        );
+
+        type_vi_keys(
+            &mut reedline,
+            &[
+                KeyCode::Esc,
+                KeyCode::Char('0'),
+                KeyCode::Char('v'),
+                KeyCode::Char('e'),
+            ],
+        );
+        assert!(matches!(
+            reedline.prompt_edit_mode(),
+            PromptEditMode::Vi(crate::PromptViMode::Visual)
+        ));
+        assert_eq!(reedline.editor.selection_range(), Some(0..5));
+
+        type_vi_keys(&mut reedline, &[KeyCode::Char('d')]);
+        assert_eq!(reedline.current_buffer_contents(), " world");
+        assert_eq!(reedline.editor.selection_range(), None);
+    }
+}
@@ -1,4 +1,5 @@
-use crossterm::event::{Event, KeyEvent, KeyEventKind};
+use crate::Editor;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 use strum_macros::EnumIter;
@@ -14,6 +15,15 @@ pub enum Signal {
     CtrlD, // End terminal session
 }
 
+/// Valid ways how `Reedline::read_line_timeout()` can return
+#[derive(Debug)]
+pub enum TimedSignal {
+    /// Entry ended like with `Reedline::read_line()`
+    Signal(Signal),
+    /// No entry was finished within the timeout, the next call resumes editing it
+    Timeout,
+}
+
 /// Editing actions which can be mapped to key bindings.
 ///
 /// Executed by `Reedline::run_edit_commands()`
@@ -31,6 +41,15 @@ pub enum EditCommand {
     /// Move to the end of the current line
     MoveToLineEnd,
 
+    /// Move to the partner of the bracket under the cursor, or of the next bracket on the line
+    MoveToMatchingBracket,
+
+    /// Move to the next placeholder of an inserted snippet, selecting its default text
+    NextTabStop,
+
+    /// Move to the previous placeholder of an inserted snippet, selecting its default text
+    PrevTabStop,
+
     /// Move one character to the left
     MoveLeft,
 
@@ -67,6 +86,10 @@ pub enum EditCommand {
     /// Insert a string at the current insertion point
     InsertString(String),
 
+    /// Insert the string returned by the text generator registered at the index,
+    /// see [`Reedline::with_text_generator`](crate::Reedline::with_text_generator)
+    InsertGenerated(usize),
+
     /// Inserts the system specific new line character
     ///
     /// - On Unix systems LF (`"\n"`)
@@ -91,15 +114,40 @@ pub enum EditCommand {
     /// Backspace delete a word from the current insertion point
     BackspaceWord,
 
+    /// Backspace delete a WORD (whitespace delimited) from the current insertion point
+    BackspaceBigWord,
+
     /// Delete in-place a word from the current insertion point
     DeleteWord,
 
+    /// Delete in-place a WORD (whitespace delimited) from the current insertion point
+    DeleteBigWord,
+
     /// Clear the current buffer
     Clear,
 
-    /// Clear to the end of the current line
+    /// Clear from the insertion point to the end of the current logical line
+    ///
+    /// The newline ending the line is kept. The removed text is stored in the cut
+    /// buffer (the kill ring of readline) and can be pasted again
     ClearToLineEnd,
 
+    /// Clear from the start of the current logical line to the insertion point
+    ///
+    /// The newline before the line is kept. The removed text is stored in the cut
+    /// buffer (the kill ring of readline) and can be pasted again
+    ClearToLineStart,
+
+    /// Clear from the insertion point to the end of the buffer, crossing newlines
+    ///
+    /// The removed text is stored in the cut buffer
+    ClearToBufferEnd,
+
+    /// Clear from the start of the buffer to the insertion point, crossing newlines
+    ///
+    /// The removed text is stored in the cut buffer
+    ClearToBufferStart,
+
     /// Insert completion: entire completion if there is only one possibility, or else up to shared prefix.
     Complete,
 
@@ -142,21 +190,35 @@ pub enum EditCommand {
     /// Paste the cut buffer in front of the insertion point (vi `p`)
     PasteCutBufferAfter,
 
-    /// Upper case the current word
+    /// Replace the text just pasted with the next older entry of the kill ring (Emacs `Alt+Y`)
+    ///
+    /// Only works right after a paste or another yank-pop
+    YankPop,
+
+    /// Upper case the text up to the end of the current word and move behind it
     UppercaseWord,
 
-    /// Lower case the current word
+    /// Lower case the text up to the end of the current word and move behind it
     LowercaseWord,
 
     /// Capitalize the current character
     CapitalizeChar,
 
+    /// Capitalize the text up to the end of the current word and move behind it
+    CapitalizeWord,
+
     /// Switch the case of the current character
     SwitchcaseChar,
 
     /// Swap the current word with the word to the right
     SwapWords,
 
+    /// Swap the word before the cursor with the word after it and move behind both,
+    /// like Emacs `transpose-words`
+    ///
+    /// At the end of the buffer the last two words are swapped.
+    TransposeWords,
+
     /// Swap the current grapheme/character with the one to the right
     SwapGraphemes,
 
@@ -189,6 +251,96 @@ pub enum EditCommand {
 
     /// CutUntil left before char
     MoveLeftBefore(char),
+
+    /// Cut the span of a Vi text object, e.g. the inner part of a pair of parentheses
+    #[strum(disabled)]
+    CutTextObject(TextObject),
+
+    /// Copy the span of a Vi text object into the cut buffer
+    #[strum(disabled)]
+    CopyTextObject(TextObject),
+
+    /// Copy the current line into the cut buffer (vi `yy`)
+    CopyCurrentLine,
+
+    /// Copy from the cursor to the start of the next word into the cut buffer (vi `yw`)
+    CopyWordRightToNext,
+
+    /// Copy from the cursor to the start of the next WORD into the cut buffer (vi `yW`)
+    CopyBigWordRightToNext,
+
+    /// Copy from the cursor to the end of the current line into the cut buffer (vi `y$`)
+    CopyToLineEnd,
+
+    /// Copy from the start of the word left of the cursor to the cursor into the cut
+    /// buffer, the cursor moves to the start of the word (vi `yb`)
+    CopyWordLeft,
+
+    /// Copy from the start of the WORD left of the cursor to the cursor into the cut
+    /// buffer, the cursor moves to the start of the WORD (vi `yB`)
+    CopyBigWordLeft,
+
+    /// Copy from the start of the current line to the cursor into the cut buffer, the
+    /// cursor moves to the start of the line (vi `y0`)
+    CopyFromLineStart,
+
+    /// Cut from the cursor to the partner of the bracket under the cursor, or of the
+    /// next bracket on the line, both ends included (vi `d%`)
+    CutToMatchingBracket,
+
+    /// Copy from the cursor to the partner of the bracket under the cursor, or of the
+    /// next bracket on the line, both ends included (vi `y%`)
+    CopyToMatchingBracket,
+
+    /// Select the register the next cut, copy or paste uses (vi `"x`)
+    SelectRegister(char),
+
+    /// Start selecting the text between the cursor position and the cursor as it
+    /// moves (vi `v`), an active selection keeps its start and selects characters
+    StartSelection,
+
+    /// Start selecting the whole lines between the cursor position and the cursor
+    /// as it moves (vi `V`), an active selection keeps its start and selects lines
+    StartLineSelection,
+
+    /// Drop the selection, leaving the text unchanged
+    ClearSelection,
+
+    /// Cut the selected text into the cut buffer
+    ///
+    /// Cutting the last lines of a linewise selection also removes the line break
+    /// before them (vi `d` in visual mode)
+    CutSelection,
+
+    /// Cut the selected text into the cut buffer to replace it
+    ///
+    /// A linewise selection leaves an empty line behind (vi `c` in visual mode)
+    ChangeSelection,
+
+    /// Copy the selected text into the cut buffer
+    CopySelection,
+}
+
+/// A Vi text object: a span of the buffer around the cursor, selected by `i` (inner)
+/// or `a` (around) and what it encloses
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum TextObject {
+    /// `iw`: the word, the run of punctuation or the whitespace under the cursor
+    InnerWord,
+    /// `aw`: the word with its trailing whitespace, or leading whitespace if there is none
+    AroundWord,
+    /// `iW`: the whitespace delimited WORD under the cursor
+    InnerBigWord,
+    /// `aW`: the WORD with its trailing whitespace, or leading whitespace if there is none
+    AroundBigWord,
+    /// `i(`: the text between the innermost pair of delimiters enclosing the cursor
+    InnerPair(char, char),
+    /// `a(`: the innermost pair of delimiters enclosing the cursor, delimiters included
+    AroundPair(char, char),
+    /// `i"`: the text between the quotes around the cursor on the current line
+    InnerQuote(char),
+    /// `a"`: the quotes with their text and trailing whitespace
+    AroundQuote(char),
 }
 
 impl Display for EditCommand {
@@ -198,6 +350,9 @@ impl Display for EditCommand {
             EditCommand::MoveToLineStart => write!(f, "MoveToLineStart"),
             EditCommand::MoveToEnd => write!(f, "MoveToEnd"),
             EditCommand::MoveToLineEnd => write!(f, "MoveToLineEnd"),
+            EditCommand::MoveToMatchingBracket => write!(f, "MoveToMatchingBracket"),
+            EditCommand::NextTabStop => write!(f, "NextTabStop"),
+            EditCommand::PrevTabStop => write!(f, "PrevTabStop"),
             EditCommand::MoveLeft => write!(f, "MoveLeft"),
             EditCommand::MoveRight => write!(f, "MoveRight"),
             EditCommand::MoveWordLeft => write!(f, "MoveWordLeft"),
@@ -210,6 +365,7 @@ impl Display for EditCommand {
             EditCommand::MoveToPosition(_) => write!(f, "MoveToPosition  Value: <int>"),
             EditCommand::InsertChar(_) => write!(f, "InsertChar  Value: <char>"),
             EditCommand::InsertString(_) => write!(f, "InsertString Value: <string>"),
+            EditCommand::InsertGenerated(_) => write!(f, "InsertGenerated Value: <int>"),
             EditCommand::InsertNewline => write!(f, "InsertNewline"),
             EditCommand::ReplaceChar(_) => write!(f, "ReplaceChar <char>"),
             EditCommand::ReplaceChars(_, _) => write!(f, "ReplaceChars <int> <string>"),
@@ -217,9 +373,14 @@ impl Display for EditCommand {
             EditCommand::Delete => write!(f, "Delete"),
             EditCommand::CutChar => write!(f, "CutChar"),
             EditCommand::BackspaceWord => write!(f, "BackspaceWord"),
+            EditCommand::BackspaceBigWord => write!(f, "BackspaceBigWord"),
             EditCommand::DeleteWord => write!(f, "DeleteWord"),
+            EditCommand::DeleteBigWord => write!(f, "DeleteBigWord"),
             EditCommand::Clear => write!(f, "Clear"),
             EditCommand::ClearToLineEnd => write!(f, "ClearToLineEnd"),
+            EditCommand::ClearToLineStart => write!(f, "ClearToLineStart"),
+            EditCommand::ClearToBufferEnd => write!(f, "ClearToBufferEnd"),
+            EditCommand::ClearToBufferStart => write!(f, "ClearToBufferStart"),
             EditCommand::Complete => write!(f, "Complete"),
             EditCommand::CutCurrentLine => write!(f, "CutCurrentLine"),
             EditCommand::CutFromStart => write!(f, "CutFromStart"),
@@ -234,11 +395,14 @@ impl Display for EditCommand {
             EditCommand::CutBigWordRightToNext => write!(f, "CutBigWordRightToNext"),
             EditCommand::PasteCutBufferBefore => write!(f, "PasteCutBufferBefore"),
             EditCommand::PasteCutBufferAfter => write!(f, "PasteCutBufferAfter"),
+            EditCommand::YankPop => write!(f, "YankPop"),
             EditCommand::UppercaseWord => write!(f, "UppercaseWord"),
             EditCommand::LowercaseWord => write!(f, "LowercaseWord"),
             EditCommand::SwitchcaseChar => write!(f, "SwitchcaseChar"),
             EditCommand::CapitalizeChar => write!(f, "CapitalizeChar"),
+            EditCommand::CapitalizeWord => write!(f, "CapitalizeWord"),
             EditCommand::SwapWords => write!(f, "SwapWords"),
+            EditCommand::TransposeWords => write!(f, "TransposeWords"),
             EditCommand::SwapGraphemes => write!(f, "SwapGraphemes"),
             EditCommand::Undo => write!(f, "Undo"),
             EditCommand::Redo => write!(f, "Redo"),
@@ -250,6 +414,24 @@ impl Display for EditCommand {
             EditCommand::CutLeftBefore(_) => write!(f, "CutLeftBefore Value: <char>"),
             EditCommand::MoveLeftUntil(_) => write!(f, "MoveLeftUntil Value: <char>"),
             EditCommand::MoveLeftBefore(_) => write!(f, "MoveLeftBefore Value: <char>"),
+            EditCommand::CutTextObject(_) => write!(f, "CutTextObject Value: <TextObject>"),
+            EditCommand::CopyTextObject(_) => write!(f, "CopyTextObject Value: <TextObject>"),
+            EditCommand::CopyCurrentLine => write!(f, "CopyCurrentLine"),
+            EditCommand::CopyWordRightToNext => write!(f, "CopyWordRightToNext"),
+            EditCommand::CopyBigWordRightToNext => write!(f, "CopyBigWordRightToNext"),
+            EditCommand::CopyToLineEnd => write!(f, "CopyToLineEnd"),
+            EditCommand::CopyWordLeft => write!(f, "CopyWordLeft"),
+            EditCommand::CopyBigWordLeft => write!(f, "CopyBigWordLeft"),
+            EditCommand::CopyFromLineStart => write!(f, "CopyFromLineStart"),
+            EditCommand::CutToMatchingBracket => write!(f, "CutToMatchingBracket"),
+            EditCommand::CopyToMatchingBracket => write!(f, "CopyToMatchingBracket"),
+            EditCommand::SelectRegister(_) => write!(f, "SelectRegister Value: <char>"),
+            EditCommand::StartSelection => write!(f, "StartSelection"),
+            EditCommand::StartLineSelection => write!(f, "StartLineSelection"),
+            EditCommand::ClearSelection => write!(f, "ClearSelection"),
+            EditCommand::CutSelection => write!(f, "CutSelection"),
+            EditCommand::ChangeSelection => write!(f, "ChangeSelection"),
+            EditCommand::CopySelection => write!(f, "CopySelection"),
         }
     }
 }
@@ -264,6 +446,9 @@ impl EditCommand {
             | EditCommand::MoveToEnd
             | EditCommand::MoveToLineStart
             | EditCommand::MoveToLineEnd
+            | EditCommand::MoveToMatchingBracket
+            | EditCommand::NextTabStop
+            | EditCommand::PrevTabStop
             | EditCommand::MoveToPosition(_)
             | EditCommand::MoveLeft
             | EditCommand::MoveRight
@@ -277,7 +462,22 @@ impl EditCommand {
             | EditCommand::MoveRightUntil(_)
             | EditCommand::MoveRightBefore(_)
             | EditCommand::MoveLeftUntil(_)
-            | EditCommand::MoveLeftBefore(_) => EditType::MoveCursor,
+            | EditCommand::MoveLeftBefore(_)
+            // Copying leaves the text unchanged and moves to the start of the text object
+            | EditCommand::CopyTextObject(_)
+            | EditCommand::CopyCurrentLine
+            | EditCommand::CopyWordRightToNext
+            | EditCommand::CopyBigWordRightToNext
+            | EditCommand::CopyToLineEnd
+            | EditCommand::CopyWordLeft
+            | EditCommand::CopyBigWordLeft
+            | EditCommand::CopyFromLineStart
+            | EditCommand::CopyToMatchingBracket
+            | EditCommand::CopySelection
+            | EditCommand::SelectRegister(_)
+            | EditCommand::StartSelection
+            | EditCommand::StartLineSelection
+            | EditCommand::ClearSelection => EditType::MoveCursor,
 
             // Text edits
             EditCommand::InsertChar(_)
@@ -285,13 +485,19 @@ impl EditCommand {
             | EditCommand::Delete
             | EditCommand::CutChar
             | EditCommand::InsertString(_)
+            | EditCommand::InsertGenerated(_)
             | EditCommand::InsertNewline
             | EditCommand::ReplaceChar(_)
             | EditCommand::ReplaceChars(_, _)
             | EditCommand::BackspaceWord
+            | EditCommand::BackspaceBigWord
             | EditCommand::DeleteWord
+            | EditCommand::DeleteBigWord
             | EditCommand::Clear
             | EditCommand::ClearToLineEnd
+            | EditCommand::ClearToLineStart
+            | EditCommand::ClearToBufferEnd
+            | EditCommand::ClearToBufferStart
             | EditCommand::Complete
             | EditCommand::CutCurrentLine
             | EditCommand::CutFromStart
@@ -306,22 +512,100 @@ impl EditCommand {
             | EditCommand::CutBigWordRightToNext
             | EditCommand::PasteCutBufferBefore
             | EditCommand::PasteCutBufferAfter
+            | EditCommand::YankPop
             | EditCommand::UppercaseWord
             | EditCommand::LowercaseWord
             | EditCommand::SwitchcaseChar
             | EditCommand::CapitalizeChar
+            | EditCommand::CapitalizeWord
             | EditCommand::SwapWords
+            | EditCommand::TransposeWords
             | EditCommand::SwapGraphemes
             | EditCommand::CutRightUntil(_)
             | EditCommand::CutRightBefore(_)
             | EditCommand::CutLeftUntil(_)
-            | EditCommand::CutLeftBefore(_) => EditType::EditText,
+            | EditCommand::CutLeftBefore(_)
+            | EditCommand::CutTextObject(_)
+            | EditCommand::CutToMatchingBracket
+            | EditCommand::CutSelection
+            | EditCommand::ChangeSelection => EditType::EditText,
 
             EditCommand::Undo | EditCommand::Redo => EditType::UndoRedo,
         }
     }
 }
 
+/// Observes the edit commands run over the buffer, see
+/// [`Reedline::with_edit_command_observer`](crate::Reedline::with_edit_command_observer)
+pub type EditCommandObserver = Box<dyn FnMut(&EditCommand) + Send>;
+
+/// What an [`EventHook`] does with a [`ReedlineEvent`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventAction {
+    /// Handle the event unchanged
+    Pass,
+    /// Handle the given events instead of the event. Additional events are injected
+    /// by replacing the event with itself followed by them
+    Replace(Vec<ReedlineEvent>),
+    /// Drop the event
+    Swallow,
+}
+
+/// Intercepts the events before they are handled, see
+/// [`Reedline::with_event_hook`](crate::Reedline::with_event_hook)
+pub type EventHook = Box<dyn FnMut(&mut Editor, &ReedlineEvent) -> EventAction + Send>;
+
+/// How [`ReedlineEvent::QuotedInsert`] inserts a control character, see
+/// [`Reedline::with_quoted_insert_mode`](crate::Reedline::with_quoted_insert_mode)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum QuotedInsertMode {
+    /// The control character itself is inserted, e.g. a tab for Tab or `\x01` for Ctrl-A
+    ///
+    /// Like in readline, the buffer keeps the character and it is shown in caret
+    /// notation, except for tabs
+    #[default]
+    Raw,
+    /// The control character is inserted in caret notation, e.g. `^I` for Tab or `^[`
+    /// for Escape
+    CaretNotation,
+}
+
+impl QuotedInsertMode {
+    /// The event inserting the key literally
+    pub(crate) fn insert(self, modifiers: KeyModifiers, code: KeyCode) -> ReedlineEvent {
+        let c = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => {
+                control_character(c).unwrap_or(c)
+            }
+            KeyCode::Char(c) => c,
+            KeyCode::Tab => '\t',
+            KeyCode::Enter => '\r',
+            KeyCode::Esc => '\x1b',
+            KeyCode::Backspace => '\x7f',
+            _ => return ReedlineEvent::None,
+        };
+
+        match self {
+            QuotedInsertMode::CaretNotation if c.is_ascii_control() => {
+                ReedlineEvent::Edit(vec![EditCommand::InsertString(format!(
+                    "^{}",
+                    (c as u8 ^ 0x40) as char
+                ))])
+            }
+            _ => ReedlineEvent::Edit(vec![EditCommand::InsertChar(c)]),
+        }
+    }
+}
+
+/// The control character typed with Ctrl and `c`, e.g. `\x01` for Ctrl-A
+fn control_character(c: char) -> Option<char> {
+    match c.to_ascii_uppercase() {
+        upper @ '@'..='_' => Some((upper as u8 ^ 0x40) as char),
+        '?' => Some('\x7f'),
+        _ => None,
+    }
+}
+
 /// Specifies the types of edit commands, used to simplify grouping edits
 /// to mark undo behavior
 #[derive(PartialEq, Eq)]
@@ -339,6 +623,10 @@ pub enum EditType {
 #[derive(Debug)]
 pub enum UndoBehavior {
     /// Character insertion, tracking the character inserted
+    ///
+    /// Consecutive insertions form a single undo step. Inserting whitespace after a word
+    /// or anything after a newline starts a new step, as does any other edit or cursor
+    /// movement in between.
     InsertCharacter(char),
     /// Backspace command, tracking the deleted character (left of cursor)
     /// Warning: this does not track the whole grapheme, just the character
@@ -357,6 +645,17 @@ pub enum UndoBehavior {
     UndoRedo,
 }
 
+/// Defines how [`Reedline::set_buffer`](crate::Reedline::set_buffer) treats the undo stack
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SetBufferUndo {
+    /// The new buffer becomes the start of the undo stack, so the previous buffer
+    /// can't be restored with undo
+    #[default]
+    Reset,
+    /// The new buffer is added as an undo point, so undo restores the previous buffer
+    UndoPoint,
+}
+
 impl UndoBehavior {
     /// Return if the current operation should start a new undo set, or be
     /// combined with the previous operation
@@ -399,6 +698,13 @@ pub enum ReedlineEvent {
     /// Complete a single token/word of the history hint
     HistoryHintWordComplete,
 
+    /// Replace the shown history hint with the next older history entry starting with
+    /// the line
+    HistoryHintNext,
+
+    /// Go back to the newer history hint shown before [`ReedlineEvent::HistoryHintNext`]
+    HistoryHintPrevious,
+
     /// Handle EndOfLine event
     ///
     /// Expected Behavior:
@@ -417,6 +723,22 @@ pub enum ReedlineEvent {
     /// Bubble up [`Signal::CtrlC`]
     CtrlC,
 
+    /// Exit with [`Signal::CtrlD`] if the line is empty
+    ///
+    /// Does nothing on a non-empty line, so it can be combined with other events in
+    /// [`ReedlineEvent::UntilFound`]
+    ExitOnEmpty,
+
+    /// Abandon the current entry without returning from `read_line`
+    ///
+    /// Expected behavior:
+    ///
+    /// Leave the painted entry on screen
+    /// Run [`EditCommand::Clear`]
+    /// Clear the current undo
+    /// Repaint the prompt on the next line
+    ClearAndReprint,
+
     /// Clears the screen and sets prompt to first line
     ClearScreen,
 
@@ -434,12 +756,22 @@ pub enum ReedlineEvent {
     /// Submit at the end of the *complete* text, otherwise newline
     SubmitOrNewline,
 
+    /// Unconditionally submit the buffer and start the next [`Reedline::read_line`](crate::Reedline::read_line)
+    /// with the same text and cursor position, the whole text selected until the next edit
+    SubmitAndKeep,
+
     /// Esc event
     Esc,
 
     /// Mouse
     Mouse, // Fill in details later
 
+    /// Selects the menu value painted at the column and row of the screen, or accepts
+    /// it if it is already selected. Left clicks are sent as this event when mouse
+    /// capture is enabled with
+    /// [`Reedline::with_mouse_capture`](crate::Reedline::with_mouse_capture)
+    MenuClick(u16, u16),
+
     /// trigger terminal resize
     Resize(u16, u16),
 
@@ -470,6 +802,10 @@ pub enum ReedlineEvent {
     /// Search the history for a string
     SearchHistory,
 
+    /// Replace the buffer with the most recent history entry, without navigating
+    /// through the history
+    InsertLastCommand,
+
     /// In vi mode multiple reedline events can be chained while parsing the
     /// command or movement characters
     Multiple(Vec<ReedlineEvent>),
@@ -504,11 +840,61 @@ pub enum ReedlineEvent {
     /// Move to the previous history page
     MenuPagePrevious,
 
+    /// Show or hide the documentation of the selected menu value
+    MenuToggleDocumentation,
+
+    /// Scroll up the documentation of the selected menu value
+    MenuDocumentationUp,
+
+    /// Scroll down the documentation of the selected menu value
+    MenuDocumentationDown,
+
+    /// Delete the entry selected in the active history menu from the history
+    MenuDelete,
+
+    /// Insert the selected value of the active menu and close the menu, without
+    /// submitting the line
+    ///
+    /// Unlike [`ReedlineEvent::Enter`] it doesn't apply without an active menu, so
+    /// `UntilFound([MenuAccept, Enter])` picks the value or else submits the line.
+    MenuAccept,
+
+    /// Close the active menu, leaving its selected value out, and handle
+    /// [`ReedlineEvent::Enter`], e.g. to submit the line as typed with a single key
+    MenuSubmit,
+
     /// Way to bind the execution of a whole command (directly returning from [`crate::Reedline::read_line()`]) to a keybinding
     ExecuteHostCommand(String),
 
     /// Open text editor
     OpenEditor,
+
+    /// Starts recording the following events into the macro register, replacing a
+    /// recording that is still in progress
+    StartMacroRecording(char),
+
+    /// Stops the recording and stores the recorded events in its register
+    StopMacroRecording,
+
+    /// Plays the events recorded in the macro register
+    ///
+    /// The changes of the whole macro are undone at once. A macro that plays itself,
+    /// directly or through another macro, is stopped at that point
+    PlayMacro(char),
+
+    /// Plays the events recorded in the macro register the given number of times,
+    /// at most 1000 times, undone at once like [`ReedlineEvent::PlayMacro`]
+    PlayMacroTimes(char, usize),
+
+    /// Inserts the next key literally instead of looking up its binding, like the
+    /// quoted-insert of readline
+    ///
+    /// Control keys are inserted as configured with
+    /// [`Reedline::with_quoted_insert_mode`](crate::Reedline::with_quoted_insert_mode),
+    /// keys without a character, like the arrows, insert nothing. The next key is taken
+    /// as soon as the binding is pressed, also when the event is part of
+    /// [`ReedlineEvent::Multiple`] or [`ReedlineEvent::UntilFound`]
+    QuotedInsert,
 }
 
 impl Display for ReedlineEvent {
@@ -517,15 +903,21 @@ impl Display for ReedlineEvent {
             ReedlineEvent::None => write!(f, "None"),
             ReedlineEvent::HistoryHintComplete => write!(f, "HistoryHintComplete"),
             ReedlineEvent::HistoryHintWordComplete => write!(f, "HistoryHintWordComplete"),
+            ReedlineEvent::HistoryHintNext => write!(f, "HistoryHintNext"),
+            ReedlineEvent::HistoryHintPrevious => write!(f, "HistoryHintPrevious"),
             ReedlineEvent::CtrlD => write!(f, "CtrlD"),
             ReedlineEvent::CtrlC => write!(f, "CtrlC"),
+            ReedlineEvent::ExitOnEmpty => write!(f, "ExitOnEmpty"),
+            ReedlineEvent::ClearAndReprint => write!(f, "ClearAndReprint"),
             ReedlineEvent::ClearScreen => write!(f, "ClearScreen"),
             ReedlineEvent::ClearScrollback => write!(f, "ClearScrollback"),
             ReedlineEvent::Enter => write!(f, "Enter"),
             ReedlineEvent::Submit => write!(f, "Submit"),
             ReedlineEvent::SubmitOrNewline => write!(f, "SubmitOrNewline"),
+            ReedlineEvent::SubmitAndKeep => write!(f, "SubmitAndKeep"),
             ReedlineEvent::Esc => write!(f, "Esc"),
             ReedlineEvent::Mouse => write!(f, "Mouse"),
+            ReedlineEvent::MenuClick(_, _) => write!(f, "MenuClick"),
             ReedlineEvent::Resize(_, _) => write!(f, "Resize <int> <int>"),
             ReedlineEvent::Edit(_) => write!(
                 f,
@@ -539,6 +931,7 @@ impl Display for ReedlineEvent {
             ReedlineEvent::Left => write!(f, "Left"),
             ReedlineEvent::NextHistory => write!(f, "NextHistory"),
             ReedlineEvent::SearchHistory => write!(f, "SearchHistory"),
+            ReedlineEvent::InsertLastCommand => write!(f, "InsertLastCommand"),
             ReedlineEvent::Multiple(_) => write!(f, "Multiple[ {{ ReedLineEvents, }} ]"),
             ReedlineEvent::UntilFound(_) => write!(f, "UntilFound [ {{ ReedLineEvents, }} ]"),
             ReedlineEvent::Menu(_) => write!(f, "Menu Name: <string>"),
@@ -550,8 +943,19 @@ impl Display for ReedlineEvent {
             ReedlineEvent::MenuRight => write!(f, "MenuRight"),
             ReedlineEvent::MenuPageNext => write!(f, "MenuPageNext"),
             ReedlineEvent::MenuPagePrevious => write!(f, "MenuPagePrevious"),
+            ReedlineEvent::MenuToggleDocumentation => write!(f, "MenuToggleDocumentation"),
+            ReedlineEvent::MenuDocumentationUp => write!(f, "MenuDocumentationUp"),
+            ReedlineEvent::MenuDocumentationDown => write!(f, "MenuDocumentationDown"),
+            ReedlineEvent::MenuDelete => write!(f, "MenuDelete"),
+            ReedlineEvent::MenuAccept => write!(f, "MenuAccept"),
+            ReedlineEvent::MenuSubmit => write!(f, "MenuSubmit"),
             ReedlineEvent::ExecuteHostCommand(_) => write!(f, "ExecuteHostCommand"),
             ReedlineEvent::OpenEditor => write!(f, "OpenEditor"),
+            ReedlineEvent::StartMacroRecording(_) => write!(f, "StartMacroRecording"),
+            ReedlineEvent::StopMacroRecording => write!(f, "StopMacroRecording"),
+            ReedlineEvent::PlayMacro(_) => write!(f, "PlayMacro"),
+            ReedlineEvent::PlayMacroTimes(_, _) => write!(f, "PlayMacroTimes"),
+            ReedlineEvent::QuotedInsert => write!(f, "QuotedInsert"),
         }
     }
 }
@@ -599,4 +1003,14 @@ impl ReedlineRawEvent {
     pub fn into(self) -> Event {
         self.inner
     }
+
+    /// The modifiers and the code of a key press
+    pub(crate) fn key(&self) -> Option<(KeyModifiers, KeyCode)> {
+        match self.inner {
+            Event::Key(KeyEvent {
+                code, modifiers, ..
+            }) => Some((modifiers, code)),
+            _ => None,
+        }
+    }
 }
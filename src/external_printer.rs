@@ -7,8 +7,14 @@
 //! ```
 #[cfg(feature = "external_printer")]
 use {
-    crossbeam::channel::{bounded, Receiver, SendError, Sender},
-    std::fmt::Display,
+    crossbeam::channel::{bounded, unbounded, Receiver, Select, SendError, Sender},
+    crossterm::event,
+    std::{
+        fmt::Display,
+        io::{self, Error, ErrorKind},
+        thread,
+        time::{Duration, Instant},
+    },
 };
 
 #[cfg(feature = "external_printer")]
@@ -67,3 +73,131 @@ where
         Self::new(EXTERNAL_PRINTER_DEFAULT_CAPACITY)
     }
 }
+
+/// Waits for terminal input on its own thread, so that the wait also ends once a
+/// message is sent to the printer
+///
+/// The thread only polls for input and leaves the events to be read by the engine.
+/// It ends when the poller is dropped.
+#[cfg(feature = "external_printer")]
+pub(crate) struct InputPoller {
+    requests: Sender<Duration>,
+    results: Receiver<io::Result<bool>>,
+    // Whether the thread still polls for a previous request
+    pending: bool,
+}
+
+#[cfg(feature = "external_printer")]
+impl InputPoller {
+    pub(crate) fn new() -> Self {
+        Self::with_poll(event::poll)
+    }
+
+    fn with_poll(poll: fn(Duration) -> io::Result<bool>) -> Self {
+        let (requests, request_receiver) = unbounded::<Duration>();
+        let (result_sender, results) = unbounded();
+        thread::spawn(move || {
+            for timeout in request_receiver {
+                if result_sender.send(poll(timeout)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            requests,
+            results,
+            pending: false,
+        }
+    }
+
+    /// Blocks up to `timeout` until there is terminal input or a message in
+    /// `messages`. Returns whether there is input, like [`event::poll`]
+    pub(crate) fn poll<T>(
+        &mut self,
+        timeout: Duration,
+        messages: &Receiver<T>,
+    ) -> io::Result<bool> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if !messages.is_empty() {
+                return Ok(false);
+            }
+            if !self.pending {
+                let time_left = deadline.saturating_duration_since(Instant::now());
+                self.requests.send(time_left).map_err(poller_stopped)?;
+                self.pending = true;
+            }
+
+            let mut select = Select::new();
+            select.recv(messages);
+            let results = select.recv(&self.results);
+            match select.ready_deadline(deadline) {
+                Ok(index) if index == results => {
+                    self.pending = false;
+                    let input = self.results.recv().map_err(poller_stopped)??;
+                    // A request left over from an earlier call may end before the deadline
+                    if input || Instant::now() >= deadline {
+                        return Ok(input);
+                    }
+                }
+                _ => return Ok(false),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "external_printer")]
+fn poller_stopped<E>(_: E) -> Error {
+    Error::new(ErrorKind::Other, "the input polling thread stopped")
+}
+
+#[cfg(all(test, feature = "external_printer"))]
+mod tests {
+    use super::*;
+
+    fn no_input(timeout: Duration) -> io::Result<bool> {
+        thread::sleep(timeout);
+        Ok(false)
+    }
+
+    #[test]
+    fn a_message_ends_the_wait_for_input() {
+        let printer = ExternalPrinter::default();
+        let mut poller = InputPoller::with_poll(no_input);
+
+        let sender = printer.sender();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            sender.send("message".to_string())
+        });
+
+        let start = Instant::now();
+        assert!(!poller
+            .poll(Duration::from_secs(10), printer.receiver())
+            .unwrap());
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert_eq!(printer.get_line().as_deref(), Some("message"));
+    }
+
+    #[test]
+    fn input_ends_the_wait() {
+        let printer = ExternalPrinter::<String>::default();
+        let mut poller = InputPoller::with_poll(|_| Ok(true));
+
+        assert!(poller
+            .poll(Duration::from_secs(10), printer.receiver())
+            .unwrap());
+    }
+
+    #[test]
+    fn the_wait_ends_at_the_timeout() {
+        let printer = ExternalPrinter::<String>::default();
+        let mut poller = InputPoller::with_poll(no_input);
+
+        let start = Instant::now();
+        assert!(!poller
+            .poll(Duration::from_millis(50), printer.receiver())
+            .unwrap());
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+}
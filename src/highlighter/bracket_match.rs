@@ -0,0 +1,159 @@
+use crate::highlighter::Highlighter;
+use crate::utils::brackets::bracket_partners;
+use crate::StyledText;
+use nu_ansi_term::{Color, Style};
+
+/// Highlights the bracket at the cursor together with its matching partner
+///
+/// Wraps another highlighter whose styling is kept for the rest of the buffer.
+/// The bracket under the cursor, or right before it, is looked up first. `()`, `[]`
+/// and `{}` are matched accounting for nesting, brackets inside quotes or escaped
+/// with a backslash are skipped, with the quoting rules of the
+/// [`BalanceValidator`](crate::BalanceValidator).
+/// A bracket without a partner is highlighted with the mismatch style.
+///
+/// Default style:
+///
+/// - matching brackets: bold cyan foreground
+/// - unbalanced bracket: bold red foreground
+///
+/// # Example
+///
+/// ```rust
+/// use reedline::{BracketMatchHighlighter, ExampleHighlighter, Reedline};
+///
+/// let highlighter = BracketMatchHighlighter::new(Box::new(ExampleHighlighter::default()));
+/// let mut line_editor = Reedline::create().with_highlighter(Box::new(highlighter));
+/// ```
+pub struct BracketMatchHighlighter {
+    highlighter: Box<dyn Highlighter>,
+    match_style: Style,
+    mismatch_style: Style,
+}
+
+impl Highlighter for BracketMatchHighlighter {
+    fn highlight(&self, line: &str, cursor: usize) -> StyledText {
+        let mut styled_text = self.highlighter.highlight(line, cursor);
+
+        let brackets = bracket_partners(line);
+        let at = |position: usize| brackets.iter().find(|(index, _)| *index == position);
+
+        let bracket = at(cursor).or_else(|| {
+            line[..cursor.min(line.len())]
+                .char_indices()
+                .next_back()
+                .and_then(|(index, _)| at(index))
+        });
+
+        let (mut positions, style) = match bracket {
+            Some((index, Some(partner))) => (vec![*index, *partner], self.match_style),
+            Some((index, None)) => (vec![*index], self.mismatch_style),
+            None => return styled_text,
+        };
+
+        let mut brackets_text = StyledText::new();
+        let mut next_index = 0;
+        positions.sort_unstable();
+        for position in positions {
+            brackets_text.push((Style::default(), line[next_index..position].to_string()));
+            // Brackets are single byte characters
+            brackets_text.push((style, line[position..position + 1].to_string()));
+            next_index = position + 1;
+        }
+        brackets_text.push((Style::default(), line[next_index..].to_string()));
+
+        styled_text.overlay(&brackets_text);
+        styled_text
+    }
+}
+
+impl BracketMatchHighlighter {
+    /// Construct the highlighter on top of the syntax coloring of `highlighter`
+    pub fn new(highlighter: Box<dyn Highlighter>) -> Self {
+        Self {
+            highlighter,
+            match_style: Style::new().fg(Color::Cyan).bold(),
+            mismatch_style: Style::new().fg(Color::Red).bold(),
+        }
+    }
+
+    /// Update the style of a pair of matching brackets
+    #[must_use]
+    pub fn with_match_style(mut self, match_style: Style) -> Self {
+        self.match_style = match_style;
+        self
+    }
+
+    /// Update the style of a bracket without partner
+    #[must_use]
+    pub fn with_mismatch_style(mut self, mismatch_style: Style) -> Self {
+        self.mismatch_style = mismatch_style;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::highlighter::{restyled_bytes, PlainHighlighter};
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn highlighted(line: &str, cursor: usize) -> Vec<(usize, Style)> {
+        let base = Style::new().fg(Color::Green);
+        let highlighter = BracketMatchHighlighter::new(Box::new(PlainHighlighter(base)));
+
+        restyled_bytes(&highlighter, base, line, cursor)
+    }
+
+    #[rstest]
+    #[case("(a)", 0, vec![0, 2])]
+    #[case("(a)", 3, vec![0, 2])]
+    #[case("f(a, [b])", 5, vec![5, 7])]
+    #[case("f(a, [b])", 9, vec![1, 8])]
+    #[case("{ (x) }", 0, vec![0, 6])]
+    #[case("(\")\")", 0, vec![0, 4])]
+    #[case("('(')", 4, vec![0, 4])]
+    #[case("('\\')", 0, vec![0, 4])]
+    fn matching_brackets(#[case] line: &str, #[case] cursor: usize, #[case] expected: Vec<usize>) {
+        let match_style = Style::new().fg(Color::Cyan).bold();
+
+        assert_eq!(
+            highlighted(line, cursor),
+            expected
+                .into_iter()
+                .map(|index| (index, match_style))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[rstest]
+    #[case("(a", 0, 0)]
+    #[case("a)", 2, 1)]
+    #[case("(]", 2, 1)]
+    #[case("\\(a)", 4, 3)]
+    fn unbalanced_brackets(#[case] line: &str, #[case] cursor: usize, #[case] expected: usize) {
+        let mismatch_style = Style::new().fg(Color::Red).bold();
+
+        assert_eq!(highlighted(line, cursor), vec![(expected, mismatch_style)]);
+    }
+
+    #[rstest]
+    #[case("abc", 1)]
+    #[case("\"(\"", 1)]
+    #[case("", 0)]
+    fn no_bracket_at_cursor(#[case] line: &str, #[case] cursor: usize) {
+        assert_eq!(highlighted(line, cursor), vec![]);
+    }
+
+    #[test]
+    fn custom_styles() {
+        let match_style = Style::new().underline();
+        let highlighter =
+            BracketMatchHighlighter::new(Box::new(PlainHighlighter(Style::new().fg(Color::Green))))
+                .with_match_style(match_style);
+
+        let styled = highlighter.highlight("[]", 0);
+        assert_eq!(styled.buffer, vec![(match_style, "[]".to_string())]);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::highlighter::Highlighter;
+use crate::StyledText;
+use nu_ansi_term::Style;
+
+/// Combines the styling of several highlighters
+///
+/// The highlighters are applied in order and later ones win: wherever a highlighter
+/// styles a character with anything but [`Style::default()`] that style replaces the
+/// style given by the highlighters before it. Characters left with the default
+/// style keep the earlier styling, so a highlighter adding a few marks on top of the
+/// syntax coloring should leave the rest of the line unstyled.
+///
+/// Without any highlighter the buffer is shown unstyled.
+///
+/// # Example
+///
+/// ```rust
+/// use reedline::{
+///     CompositeHighlighter, ExampleHighlighter, Reedline, SimpleMatchHighlighter,
+/// };
+///
+/// let highlighter = CompositeHighlighter::default()
+///     .with_highlighter(Box::new(ExampleHighlighter::default()))
+///     .with_highlighter(Box::new(SimpleMatchHighlighter::new("TODO".into())));
+/// let mut line_editor = Reedline::create().with_highlighter(Box::new(highlighter));
+/// ```
+#[derive(Default)]
+pub struct CompositeHighlighter {
+    highlighters: Vec<Box<dyn Highlighter>>,
+}
+
+impl Highlighter for CompositeHighlighter {
+    fn highlight(&self, line: &str, cursor: usize) -> StyledText {
+        let mut highlighters = self.highlighters.iter();
+        let mut styled_text = match highlighters.next() {
+            Some(highlighter) => highlighter.highlight(line, cursor),
+            None => {
+                let mut styled_text = StyledText::new();
+                styled_text.push((Style::default(), line.to_string()));
+                return styled_text;
+            }
+        };
+
+        for highlighter in highlighters {
+            styled_text.overlay(&highlighter.highlight(line, cursor));
+        }
+        styled_text
+    }
+}
+
+impl CompositeHighlighter {
+    /// Construct the highlighter applying `highlighters` in order
+    pub fn new(highlighters: Vec<Box<dyn Highlighter>>) -> Self {
+        Self { highlighters }
+    }
+
+    /// Add a highlighter applied after the current ones
+    #[must_use]
+    pub fn with_highlighter(mut self, highlighter: Box<dyn Highlighter>) -> Self {
+        self.highlighters.push(highlighter);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::highlighter::PlainHighlighter;
+    use crate::SimpleMatchHighlighter;
+    use nu_ansi_term::Color;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn later_highlighters_win_where_they_style() {
+        let base = Style::new().fg(Color::Blue);
+        let first_match = Style::new().fg(Color::Green);
+        let second_match = Style::new().on(Color::Red);
+        let highlighter = CompositeHighlighter::new(vec![
+            Box::new(PlainHighlighter(base)),
+            Box::new(SimpleMatchHighlighter::new("bc".into()).with_match_style(first_match)),
+            Box::new(SimpleMatchHighlighter::new("cd".into()).with_match_style(second_match)),
+        ]);
+
+        assert_eq!(
+            highlighter.highlight("abcde", 0).buffer,
+            vec![
+                (base, "a".to_string()),
+                (first_match, "b".to_string()),
+                (second_match, "cd".to_string()),
+                (base, "e".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_highlighter_leaves_the_line_unstyled() {
+        let highlighter = CompositeHighlighter::default();
+
+        assert_eq!(
+            highlighter.highlight("abc", 0).buffer,
+            vec![(Style::default(), "abc".to_string())]
+        );
+    }
+}
@@ -1,15 +1,208 @@
+mod bracket_match;
+mod composite;
 mod example;
 mod simple_match;
+mod whitespace;
 
 use crate::StyledText;
+#[cfg(test)]
+use nu_ansi_term::Style;
 
+pub use bracket_match::BracketMatchHighlighter;
+pub use composite::CompositeHighlighter;
 pub use example::ExampleHighlighter;
 pub use simple_match::SimpleMatchHighlighter;
+pub use whitespace::WhitespaceHighlighter;
 /// The syntax highlighting trait. Implementers of this trait will take in the current string and then
 /// return a `StyledText` object, which represents the contents of the original line as styled strings
 pub trait Highlighter: Send {
     /// The action that will handle the current buffer as a line and return the corresponding `StyledText` for the buffer
     ///
     /// Cursor position as byte offsets in the string
+    ///
+    /// Multiline buffers are passed as a single string with `\n` separating the
+    /// logical lines. [`BufferLines`] can be used to style every line on its own.
     fn highlight(&self, line: &str, cursor: usize) -> StyledText;
 }
+
+/// The logical lines of a buffer together with their byte offsets
+///
+/// Lines are separated by `\n`, which is not part of the line content. Every
+/// byte of the buffer belongs either to a line or to a separator so the lines
+/// can be styled independently and pushed to a [`StyledText`] together with the
+/// separators, keeping the byte offsets of the buffer intact.
+///
+/// ```
+/// use reedline::BufferLines;
+///
+/// let lines = BufferLines::new("SELECT 1;\nls -la");
+///
+/// assert_eq!(lines.len(), 2);
+/// assert_eq!(lines.line(1), Some((10, "ls -la")));
+/// assert_eq!(lines.line_column(13), (1, 3));
+/// ```
+pub struct BufferLines<'a> {
+    buffer: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> BufferLines<'a> {
+    /// Split `buffer` into its logical lines
+    pub fn new(buffer: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(buffer.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+
+        Self { buffer, starts }
+    }
+
+    /// Number of logical lines, an empty buffer has a single empty line
+    pub fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// A buffer always contains at least one line
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Start offset and content of the line at `index`
+    pub fn line(&self, index: usize) -> Option<(usize, &'a str)> {
+        let start = *self.starts.get(index)?;
+        let end = self
+            .starts
+            .get(index + 1)
+            .map_or(self.buffer.len(), |next| next - 1);
+
+        Some((start, &self.buffer[start..end]))
+    }
+
+    /// Iterator over the start offset and content of every line
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &'a str)> + '_ {
+        (0..self.len()).filter_map(move |index| self.line(index))
+    }
+
+    /// Line index and byte column of a byte offset in the buffer
+    ///
+    /// Offsets past the end of the buffer are placed at the end of the last line.
+    /// The offset of a separator belongs to the end of the line it terminates.
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.buffer.len());
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+
+        (line, offset - self.starts[line])
+    }
+}
+
+/// Highlighter for the tests that paints the whole line in one style
+#[cfg(test)]
+pub(crate) struct PlainHighlighter(pub(crate) Style);
+
+#[cfg(test)]
+impl Highlighter for PlainHighlighter {
+    fn highlight(&self, line: &str, _cursor: usize) -> StyledText {
+        let mut styled_text = StyledText::new();
+        styled_text.push((self.0, line.to_string()));
+        styled_text
+    }
+}
+
+/// Byte offsets that `highlighter` styles differently than `base`, with their style
+#[cfg(test)]
+pub(crate) fn restyled_bytes(
+    highlighter: &dyn Highlighter,
+    base: Style,
+    line: &str,
+    cursor: usize,
+) -> Vec<(usize, Style)> {
+    let mut index = 0;
+    let mut found = vec![];
+    for (style, text) in highlighter.highlight(line, cursor).buffer {
+        if style != base {
+            found.extend((index..index + text.len()).map(|index| (index, style)));
+        }
+        index += text.len();
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DefaultPrompt, Prompt};
+    use nu_ansi_term::{Color, Style};
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("", vec![(0, "")])]
+    #[case("abc", vec![(0, "abc")])]
+    #[case("abc\n", vec![(0, "abc"), (4, "")])]
+    #[case("a\nbc\n\nd", vec![(0, "a"), (2, "bc"), (5, ""), (6, "d")])]
+    fn lines_keep_their_offsets(#[case] buffer: &str, #[case] expected: Vec<(usize, &str)>) {
+        let lines = BufferLines::new(buffer);
+
+        assert_eq!(lines.iter().collect::<Vec<_>>(), expected);
+        assert_eq!(lines.len(), expected.len());
+    }
+
+    #[rstest]
+    #[case(0, (0, 0))]
+    #[case(3, (0, 3))]
+    #[case(4, (1, 0))]
+    #[case(6, (1, 2))]
+    #[case(100, (1, 2))]
+    fn offsets_map_to_line_and_column(#[case] offset: usize, #[case] expected: (usize, usize)) {
+        let lines = BufferLines::new("abc\nde");
+
+        assert_eq!(lines.line_column(offset), expected);
+    }
+
+    struct PerLineHighlighter;
+
+    impl Highlighter for PerLineHighlighter {
+        fn highlight(&self, line: &str, _cursor: usize) -> StyledText {
+            let mut styled_text = StyledText::new();
+            for (index, (_, content)) in BufferLines::new(line).iter().enumerate() {
+                if index > 0 {
+                    styled_text.push((Style::new(), "\n".to_string()));
+                }
+                let color = if index == 0 {
+                    Color::Blue
+                } else {
+                    Color::Green
+                };
+                styled_text.push((Style::new().fg(color), content.to_string()));
+            }
+            styled_text
+        }
+    }
+
+    #[test]
+    fn lines_are_styled_independently() {
+        let buffer = "SELECT 1;\nls";
+        let styled = PerLineHighlighter.highlight(buffer, 0);
+        assert_eq!(styled.raw_string(), buffer);
+
+        let prompt = DefaultPrompt::default();
+        let (before, after) = styled.render_around_insertion_point(buffer.len(), &prompt, true);
+
+        let multiline_indicator = Style::new()
+            .fg(prompt.get_prompt_multiline_color())
+            .paint(format!("\n{}", prompt.render_prompt_multiline_indicator()))
+            .to_string();
+        assert_eq!(
+            before,
+            format!(
+                "{}{}{}",
+                Color::Blue.paint("SELECT 1;"),
+                multiline_indicator,
+                Color::Green.paint("ls")
+            )
+        );
+        assert_eq!(after, "");
+    }
+}
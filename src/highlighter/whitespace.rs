@@ -0,0 +1,152 @@
+use crate::highlighter::{BufferLines, Highlighter};
+use crate::StyledText;
+use nu_ansi_term::{Color, Style};
+
+/// Marks trailing whitespace and tab characters in the buffer
+///
+/// Wraps another highlighter whose styling is kept for the rest of the buffer.
+/// The whitespace at the end of every logical line and every literal tab get a
+/// distinct style. While the cursor is at the end of a line its trailing whitespace
+/// is left alone, so typing the space between two words doesn't flash.
+///
+/// Default style:
+///
+/// - trailing whitespace: red background
+/// - tabs: dark gray background
+///
+/// # Example
+///
+/// ```rust
+/// use reedline::{ExampleHighlighter, Reedline, WhitespaceHighlighter};
+///
+/// let highlighter = WhitespaceHighlighter::new(Box::new(ExampleHighlighter::default()));
+/// let mut line_editor = Reedline::create()
+///     .with_highlighter(Box::new(highlighter))
+///     .with_tab_width(4);
+/// ```
+pub struct WhitespaceHighlighter {
+    highlighter: Box<dyn Highlighter>,
+    trailing_style: Style,
+    tab_style: Style,
+}
+
+impl Highlighter for WhitespaceHighlighter {
+    fn highlight(&self, line: &str, cursor: usize) -> StyledText {
+        let mut styled_text = self.highlighter.highlight(line, cursor);
+
+        let trailing: Vec<_> = BufferLines::new(line)
+            .iter()
+            .filter_map(|(start, content)| {
+                let end = start + content.len();
+                let trailing_start = start + content.trim_end().len();
+                (trailing_start < end && cursor != end).then_some(trailing_start..end)
+            })
+            .collect();
+
+        let mut whitespace_text = StyledText::new();
+        for (index, c) in line.char_indices() {
+            let style = if c == '\t' {
+                self.tab_style
+            } else if trailing.iter().any(|range| range.contains(&index)) {
+                self.trailing_style
+            } else {
+                Style::default()
+            };
+            whitespace_text.push((style, c.to_string()));
+        }
+
+        styled_text.overlay(&whitespace_text);
+        styled_text
+    }
+}
+
+impl WhitespaceHighlighter {
+    /// Construct the highlighter on top of the syntax coloring of `highlighter`
+    pub fn new(highlighter: Box<dyn Highlighter>) -> Self {
+        Self {
+            highlighter,
+            trailing_style: Style::new().on(Color::Red),
+            tab_style: Style::new().on(Color::DarkGray),
+        }
+    }
+
+    /// Update the style of the whitespace at the end of a line
+    #[must_use]
+    pub fn with_trailing_style(mut self, trailing_style: Style) -> Self {
+        self.trailing_style = trailing_style;
+        self
+    }
+
+    /// Update the style of a tab character
+    #[must_use]
+    pub fn with_tab_style(mut self, tab_style: Style) -> Self {
+        self.tab_style = tab_style;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::highlighter::{restyled_bytes, PlainHighlighter};
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn highlighted(line: &str, cursor: usize) -> Vec<(usize, Style)> {
+        let base = Style::new().fg(Color::Green);
+        let highlighter = WhitespaceHighlighter::new(Box::new(PlainHighlighter(base)));
+
+        restyled_bytes(&highlighter, base, line, cursor)
+    }
+
+    #[rstest]
+    #[case("ls  ", 0, vec![2, 3])]
+    #[case("ls  ", 4, vec![])]
+    #[case("a \nb  ", 5, vec![1, 4, 5])]
+    #[case("a \nb  ", 2, vec![4, 5])]
+    #[case("a b", 0, vec![])]
+    #[case("", 0, vec![])]
+    fn trailing_whitespace(
+        #[case] line: &str,
+        #[case] cursor: usize,
+        #[case] expected: Vec<usize>,
+    ) {
+        let trailing_style = Style::new().on(Color::Red);
+
+        assert_eq!(
+            highlighted(line, cursor),
+            expected
+                .into_iter()
+                .map(|index| (index, trailing_style))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn tabs_are_marked_everywhere() {
+        let trailing_style = Style::new().on(Color::Red);
+        let tab_style = Style::new().on(Color::DarkGray);
+
+        assert_eq!(
+            highlighted("a\tb \t", 0),
+            vec![(1, tab_style), (3, trailing_style), (4, tab_style)]
+        );
+    }
+
+    #[test]
+    fn custom_styles() {
+        let tab_style = Style::new().reverse();
+        let highlighter =
+            WhitespaceHighlighter::new(Box::new(PlainHighlighter(Style::new().fg(Color::Green))))
+                .with_tab_style(tab_style);
+
+        let styled = highlighter.highlight("a\t", 2);
+        assert_eq!(
+            styled.buffer,
+            vec![
+                (Style::new().fg(Color::Green), "a".to_string()),
+                (tab_style, "\t".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use super::next_hint_word;
+use crate::{Completer, Hinter, History, Suggestion};
+use nu_ansi_term::{Color, Style};
+use std::cmp::Reverse;
+
+/// A hinter that shows the rest of the best completion as a hint
+///
+/// The completer is asked for the suggestions of the line while the cursor is at its
+/// end. The suggestion with the highest [`Suggestion::score`], the first one of equal
+/// scores, becomes the hint if it continues the word before the cursor: its span has to
+/// end at the cursor and its value has to start with the typed text.
+///
+/// To stay responsive the completer is only called once the word before the cursor
+/// has the minimum number of characters, and not again while the typed characters
+/// follow the shown hint.
+///
+/// # Example
+///
+/// ```rust
+/// use reedline::{CompleterHinter, DefaultCompleter, Reedline};
+///
+/// let completer = DefaultCompleter::new(vec!["hello".into(), "world".into()]);
+/// let line_editor = Reedline::create().with_hinter(Box::new(
+///     CompleterHinter::new(Box::new(completer)).with_min_chars(2),
+/// ));
+/// ```
+pub struct CompleterHinter {
+    completer: Box<dyn Completer>,
+    style: Style,
+    min_chars: usize,
+    current_hint: String,
+    // The line the current hint belongs to
+    hinted_line: Option<String>,
+}
+
+impl Hinter for CompleterHinter {
+    fn handle(
+        &mut self,
+        line: &str,
+        pos: usize,
+        _history: &dyn History,
+        use_ansi_coloring: bool,
+    ) -> String {
+        self.update_hint(line, pos);
+
+        if use_ansi_coloring && !self.current_hint.is_empty() {
+            self.style.paint(&self.current_hint).to_string()
+        } else {
+            self.current_hint.clone()
+        }
+    }
+
+    fn complete_hint(&self) -> String {
+        self.current_hint.clone()
+    }
+
+    fn next_hint_token(&self) -> String {
+        next_hint_word(&self.current_hint)
+    }
+}
+
+impl CompleterHinter {
+    /// Creates a hinter showing the completions of `completer`
+    pub fn new(completer: Box<dyn Completer>) -> Self {
+        Self {
+            completer,
+            style: Style::new().fg(Color::LightGray),
+            min_chars: 1,
+            current_hint: String::new(),
+            hinted_line: None,
+        }
+    }
+
+    /// A builder that sets the style applied to the hint as part of the buffer
+    #[must_use]
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// A builder that sets the number of characters the word before the cursor needs
+    /// before the completer is asked for a hint (default: 1)
+    #[must_use]
+    pub fn with_min_chars(mut self, min_chars: usize) -> Self {
+        self.min_chars = min_chars;
+        self
+    }
+
+    /// Updates the hint for the line and returns it unstyled
+    pub(crate) fn update_hint(&mut self, line: &str, pos: usize) -> &str {
+        self.current_hint = self.hint(line, pos);
+        self.hinted_line = Some(line.to_string());
+        &self.current_hint
+    }
+
+    // The hint is painted after the buffer, so it is only given at the end of it
+    fn hint(&mut self, line: &str, pos: usize) -> String {
+        if pos != line.len() {
+            return String::new();
+        }
+        let word = line.rsplit(char::is_whitespace).next().unwrap_or_default();
+        if word.is_empty() || word.chars().count() < self.min_chars {
+            return String::new();
+        }
+
+        // Typing the start of the hint keeps the rest of it
+        if let Some(typed) = self
+            .hinted_line
+            .as_deref()
+            .and_then(|hinted_line| line.strip_prefix(hinted_line))
+        {
+            if let Some(rest) = self.current_hint.strip_prefix(typed) {
+                if !rest.is_empty() {
+                    return rest.to_string();
+                }
+            }
+        }
+
+        self.completer
+            .complete(line, pos)
+            .into_iter()
+            .enumerate()
+            .max_by_key(|(index, suggestion)| (suggestion.score, Reverse(*index)))
+            .and_then(|(_, suggestion)| extension(&suggestion, line, pos))
+            .unwrap_or_default()
+    }
+}
+
+/// The part of the value of `suggestion` after the text typed before the cursor, if the
+/// suggestion continues it
+fn extension(suggestion: &Suggestion, line: &str, pos: usize) -> Option<String> {
+    let typed = line.get(suggestion.span.start..pos)?;
+    if suggestion.span.end != pos || !suggestion.value.starts_with(typed) {
+        return None;
+    }
+    Some(suggestion.value[typed.len()..].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FileBackedHistory, Span};
+    use pretty_assertions::assert_eq;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    // Completes the word before the cursor with the values and counts its calls
+    struct CountingCompleter {
+        values: Vec<(&'static str, Option<i64>)>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Completer for CountingCompleter {
+        fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let start = line[..pos].rfind(' ').map_or(0, |index| index + 1);
+            self.values
+                .iter()
+                .map(|(value, score)| Suggestion {
+                    value: value.to_string(),
+                    span: Span::new(start, pos),
+                    score: *score,
+                    ..Suggestion::default()
+                })
+                .collect()
+        }
+    }
+
+    fn hinter(values: Vec<(&'static str, Option<i64>)>) -> (CompleterHinter, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let completer = CountingCompleter {
+            values,
+            calls: calls.clone(),
+        };
+        (CompleterHinter::new(Box::new(completer)), calls)
+    }
+
+    #[test]
+    fn highest_ranked_suggestion_is_the_hint() {
+        let (mut hinter, _) = hinter(vec![
+            ("checkout", Some(1)),
+            ("cherry-pick", Some(5)),
+            ("cherry", Some(5)),
+        ]);
+        let history = FileBackedHistory::default();
+
+        assert_eq!(hinter.handle("git ch", 6, &history, false), "erry-pick");
+        assert_eq!(hinter.complete_hint(), "erry-pick");
+        assert_eq!(hinter.next_hint_token(), "erry");
+    }
+
+    #[test]
+    fn hint_needs_a_suggestion_extending_the_typed_word() {
+        // The top suggestion doesn't start with the typed word
+        let (mut hinter, _) = hinter(vec![("commit", Some(2)), ("checkout", None)]);
+        let history = FileBackedHistory::default();
+
+        assert_eq!(hinter.handle("git ch", 6, &history, false), "");
+        // The cursor isn't at the end of the line
+        assert_eq!(hinter.handle("git co x", 6, &history, false), "");
+    }
+
+    #[test]
+    fn completer_is_called_only_when_needed() {
+        let (hinter, calls) = hinter(vec![("checkout", None)]);
+        let mut hinter = hinter.with_min_chars(2);
+        let history = FileBackedHistory::default();
+
+        assert_eq!(hinter.handle("git c", 5, &history, false), "");
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        assert_eq!(hinter.handle("git ch", 6, &history, false), "eckout");
+        // Repaints and typing the hint reuse it
+        assert_eq!(hinter.handle("git ch", 6, &history, false), "eckout");
+        assert_eq!(hinter.handle("git chec", 8, &history, false), "kout");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // A diverging character asks the completer again
+        assert_eq!(hinter.handle("git checx", 9, &history, false), "");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn hint_is_styled() {
+        let style = Style::new().italic();
+        let (hinter, _) = hinter(vec![("checkout", None)]);
+        let mut hinter = hinter.with_style(style);
+
+        assert_eq!(
+            hinter.handle("ch", 2, &FileBackedHistory::default(), true),
+            style.paint("eckout").to_string()
+        );
+    }
+}
@@ -1,13 +1,58 @@
-use crate::{history::SearchQuery, Hinter, History};
+use super::{next_hint_word, CompleterHinter};
+use crate::{
+    history::{SearchFilter, SearchQuery},
+    Completer, Hinter, History, HistoryItem,
+};
 use nu_ansi_term::{Color, Style};
 
+// Older history entries that can be cycled through for the same line
+const HISTORY_HINT_CANDIDATES: i64 = 100;
+
+/// A source of the hints shown by the [`DefaultHinter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HintSource {
+    /// The most recent history entry starting with the line
+    History,
+    /// The best value of the completer set with [`DefaultHinter::with_completer`]
+    /// that continues the word before the cursor, picked like the [`CompleterHinter`]
+    /// does
+    Completer,
+}
+
 /// A hinter that use the completions or the history to show a hint to the user
 ///
+/// The sources are asked in order and the first non-empty hint is shown.
+/// By default only the history is used.
+///
 /// Similar to `fish` autosuggestins
+///
+/// # Example
+///
+/// ```rust
+/// use reedline::{DefaultCompleter, DefaultHinter, HintSource};
+///
+/// let completer = DefaultCompleter::new(vec!["hello".into(), "world".into()]);
+/// let hinter = DefaultHinter::default()
+///     .with_completer(Box::new(completer))
+///     .with_sources(vec![HintSource::History, HintSource::Completer]);
+/// ```
 pub struct DefaultHinter {
     style: Style,
+    completer_style: Option<Style>,
     current_hint: String,
+    current_style: Style,
     min_chars: usize,
+    multiline: bool,
+    sources: Vec<HintSource>,
+    completer: Option<CompleterHinter>,
+    current_source: Option<HintSource>,
+    // History hints for `history_line`, newest first, and the one that is shown.
+    // `history_newest` is the newest entry when they were searched, to search again
+    // once the history changed
+    history_hints: Vec<String>,
+    history_line: Option<String>,
+    history_newest: Option<HistoryItem>,
+    history_index: usize,
 }
 
 impl Hinter for DefaultHinter {
@@ -18,27 +63,38 @@ impl Hinter for DefaultHinter {
         history: &dyn History,
         use_ansi_coloring: bool,
     ) -> String {
-        self.current_hint = if line.chars().count() >= self.min_chars {
-            history
-                .search(SearchQuery::last_with_prefix(
-                    line.to_string(),
-                    history.session(),
-                ))
-                .expect("todo: error handling")
-                .get(0)
-                .map_or_else(String::new, |entry| {
-                    entry
-                        .command_line
-                        .get(line.len()..)
-                        .unwrap_or_default()
-                        .to_string()
-                })
-        } else {
-            String::new()
-        };
+        self.current_hint = String::new();
+        self.current_style = self.style;
+        self.current_source = None;
+
+        if line.chars().count() >= self.min_chars {
+            for source in self.sources.clone() {
+                let hint = match source {
+                    HintSource::History => self.history_hint(line, history),
+                    HintSource::Completer => self.completer_hint(line, pos),
+                };
+
+                if !hint.is_empty() {
+                    self.current_hint = hint;
+                    self.current_source = Some(source);
+                    if source == HintSource::Completer {
+                        self.current_style = self.completer_style.unwrap_or(self.style);
+                    }
+                    break;
+                }
+            }
+        }
+
+        self.keep_first_hint_line();
 
         if use_ansi_coloring && !self.current_hint.is_empty() {
-            self.style.paint(&self.current_hint).to_string()
+            // Every line is styled on its own so the style is not lost when the
+            // painter splits the hint in lines
+            self.current_hint
+                .split('\n')
+                .map(|line| self.current_style.paint(line).to_string())
+                .collect::<Vec<String>>()
+                .join("\n")
         } else {
             self.current_hint.clone()
         }
@@ -49,21 +105,29 @@ impl Hinter for DefaultHinter {
     }
 
     fn next_hint_token(&self) -> String {
-        let mut reached_content = false;
-        let result: String = self
-            .current_hint
-            .chars()
-            .take_while(|c| match (c.is_whitespace(), reached_content) {
-                (true, true) => false,
-                (true, false) => true,
-                (false, true) => true,
-                (false, false) => {
-                    reached_content = true;
-                    true
-                }
-            })
-            .collect();
-        result
+        next_hint_word(&self.current_hint)
+    }
+
+    fn next_hint(&mut self) -> bool {
+        if self.current_source != Some(HintSource::History)
+            || self.history_index + 1 >= self.history_hints.len()
+        {
+            return false;
+        }
+        self.history_index += 1;
+        self.current_hint = self.history_hints[self.history_index].clone();
+        self.keep_first_hint_line();
+        true
+    }
+
+    fn previous_hint(&mut self) -> bool {
+        if self.current_source != Some(HintSource::History) || self.history_index == 0 {
+            return false;
+        }
+        self.history_index -= 1;
+        self.current_hint = self.history_hints[self.history_index].clone();
+        self.keep_first_hint_line();
+        true
     }
 }
 
@@ -71,8 +135,18 @@ impl Default for DefaultHinter {
     fn default() -> Self {
         DefaultHinter {
             style: Style::new().fg(Color::LightGray),
+            completer_style: None,
             current_hint: String::new(),
+            current_style: Style::new().fg(Color::LightGray),
             min_chars: 1,
+            multiline: false,
+            sources: vec![HintSource::History],
+            completer: None,
+            current_source: None,
+            history_hints: vec![],
+            history_line: None,
+            history_newest: None,
+            history_index: 0,
         }
     }
 }
@@ -91,4 +165,333 @@ impl DefaultHinter {
         self.min_chars = min_chars;
         self
     }
+
+    /// A builder that allows hints spanning multiple lines
+    ///
+    /// By default only the hint for the current line is shown. With multiline hints the
+    /// remaining lines of a multiline history entry are shown below the buffer and the
+    /// full entry is inserted when accepting the hint.
+    #[must_use]
+    pub fn with_multiline(mut self, multiline: bool) -> Self {
+        self.multiline = multiline;
+        self
+    }
+
+    /// A builder that sets the completer used by [`HintSource::Completer`]
+    #[must_use]
+    pub fn with_completer(mut self, completer: Box<dyn Completer>) -> Self {
+        self.completer = Some(CompleterHinter::new(completer));
+        self
+    }
+
+    /// A builder that sets the sources of the hint, asked in the given order
+    #[must_use]
+    pub fn with_sources(mut self, sources: Vec<HintSource>) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// A builder that sets the style of the hints coming from the completer
+    ///
+    /// When it isn't set, they use the style of the history hints
+    #[must_use]
+    pub fn with_completer_style(mut self, completer_style: Style) -> Self {
+        self.completer_style = Some(completer_style);
+        self
+    }
+
+    // The candidates are kept while the line and the history stay the same, so the
+    // hint picked with `next_hint` and `previous_hint` survives repaints
+    fn history_hint(&mut self, line: &str, history: &dyn History) -> String {
+        let newest = history
+            .search(SearchQuery::last_with_search(SearchFilter::anything(
+                history.session(),
+            )))
+            .expect("todo: error handling")
+            .pop();
+        if self.history_line.as_deref() != Some(line) || self.history_newest != newest {
+            let query = SearchQuery {
+                limit: Some(HISTORY_HINT_CANDIDATES),
+                ..SearchQuery::last_with_prefix(line.to_string(), history.session())
+            };
+            let mut hints: Vec<String> = vec![];
+            for entry in history.search(query).expect("todo: error handling") {
+                let hint = entry.command_line.get(line.len()..).unwrap_or_default();
+                if !hints.iter().any(|seen| seen == hint) {
+                    hints.push(hint.to_string());
+                }
+            }
+            self.history_hints = hints;
+            self.history_line = Some(line.to_string());
+            self.history_newest = newest;
+            self.history_index = 0;
+        }
+
+        self.history_hints
+            .get(self.history_index)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn keep_first_hint_line(&mut self) {
+        if !self.multiline {
+            if let Some(end) = self.current_hint.find(['\n', '\r']) {
+                self.current_hint.truncate(end);
+            }
+        }
+    }
+
+    fn completer_hint(&mut self, line: &str, pos: usize) -> String {
+        self.completer
+            .as_mut()
+            .map(|completer| completer.update_hint(line, pos).to_string())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DefaultCompleter, FileBackedHistory, HistoryItem, Span, Suggestion};
+    use pretty_assertions::assert_eq;
+
+    fn history() -> FileBackedHistory {
+        let mut history = FileBackedHistory::default();
+        history
+            .save(HistoryItem::from_command_line("if true {\n  echo yes\n}"))
+            .unwrap();
+        history
+    }
+
+    #[test]
+    fn hints_stop_at_line_end_by_default() {
+        let mut hinter = DefaultHinter::default();
+
+        assert_eq!(hinter.handle("if", 2, &history(), false), " true {");
+        assert_eq!(hinter.complete_hint(), " true {");
+    }
+
+    #[test]
+    fn multiline_hints() {
+        let mut hinter = DefaultHinter::default().with_multiline(true);
+
+        assert_eq!(
+            hinter.handle("if", 2, &history(), false),
+            " true {\n  echo yes\n}"
+        );
+        assert_eq!(hinter.complete_hint(), " true {\n  echo yes\n}");
+    }
+
+    #[test]
+    fn multiline_hints_are_styled_per_line() {
+        let style = Style::new().dimmed();
+        let mut hinter = DefaultHinter::default()
+            .with_style(style)
+            .with_multiline(true);
+
+        assert_eq!(
+            hinter.handle("if", 2, &history(), true),
+            format!(
+                "{}\n{}\n{}",
+                style.paint(" true {"),
+                style.paint("  echo yes"),
+                style.paint("}")
+            )
+        );
+    }
+
+    fn chained_hinter() -> DefaultHinter {
+        let completer = DefaultCompleter::new(vec!["iffy".into(), "echo".into()]);
+        DefaultHinter::default()
+            .with_completer(Box::new(completer))
+            .with_sources(vec![HintSource::History, HintSource::Completer])
+    }
+
+    #[test]
+    fn history_hint_takes_precedence() {
+        let mut hinter = chained_hinter();
+
+        assert_eq!(hinter.handle("if", 2, &history(), false), " true {");
+    }
+
+    #[test]
+    fn completer_hint_is_the_fallback() {
+        let mut hinter = chained_hinter();
+
+        assert_eq!(hinter.handle("ls; ec", 6, &history(), false), "ho");
+        assert_eq!(hinter.complete_hint(), "ho");
+        assert_eq!(hinter.next_hint_token(), "ho");
+    }
+
+    #[test]
+    fn completer_hint_is_ranked_like_the_completer_hinter() {
+        struct ScoredCompleter;
+
+        impl Completer for ScoredCompleter {
+            fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+                [("checkout", 1), ("cherry", 5)]
+                    .into_iter()
+                    .map(|(value, score)| Suggestion {
+                        value: value.into(),
+                        span: Span::new(0, pos),
+                        score: Some(score),
+                        ..Suggestion::default()
+                    })
+                    .filter(|suggestion| suggestion.value.starts_with(line))
+                    .collect()
+            }
+        }
+
+        let mut hinter = DefaultHinter::default()
+            .with_completer(Box::new(ScoredCompleter))
+            .with_sources(vec![HintSource::Completer]);
+        let mut completer_hinter = CompleterHinter::new(Box::new(ScoredCompleter));
+
+        assert_eq!(hinter.handle("ch", 2, &history(), false), "erry");
+        assert_eq!(completer_hinter.handle("ch", 2, &history(), false), "erry");
+    }
+
+    #[test]
+    fn completer_hint_needs_cursor_at_end() {
+        let mut hinter = chained_hinter();
+
+        assert_eq!(hinter.handle("ec x", 2, &history(), false), "");
+    }
+
+    #[test]
+    fn completer_hint_without_completer_source() {
+        let completer = DefaultCompleter::new(vec!["echo".into()]);
+        let mut hinter = DefaultHinter::default().with_completer(Box::new(completer));
+
+        assert_eq!(hinter.handle("ec", 2, &history(), false), "");
+    }
+
+    #[test]
+    fn completer_hints_have_their_own_style() {
+        let history_style = Style::new().dimmed();
+        let completer_style = Style::new().italic();
+        let mut hinter = chained_hinter()
+            .with_style(history_style)
+            .with_completer_style(completer_style);
+
+        assert_eq!(
+            hinter.handle("ec", 2, &history(), true),
+            completer_style.paint("ho").to_string()
+        );
+        assert_eq!(
+            hinter.handle("if", 2, &history(), true),
+            history_style.paint(" true {").to_string()
+        );
+    }
+
+    fn cycling_history() -> FileBackedHistory {
+        let mut history = FileBackedHistory::default();
+        for command in ["git status", "git commit", "ls", "git status", "git push"] {
+            history
+                .save(HistoryItem::from_command_line(command))
+                .unwrap();
+        }
+        history
+    }
+
+    #[test]
+    fn history_hints_cycle_through_older_matches() {
+        let history = cycling_history();
+        let mut hinter = DefaultHinter::default();
+
+        assert_eq!(hinter.handle("git", 3, &history, false), " push");
+        assert!(!hinter.previous_hint());
+
+        // Duplicates are skipped
+        let mut hints = vec![];
+        while hinter.next_hint() {
+            hints.push(hinter.handle("git", 3, &history, false));
+        }
+        assert_eq!(hints, [" status", " commit"]);
+        assert_eq!(hinter.complete_hint(), " commit");
+
+        assert!(hinter.previous_hint());
+        assert_eq!(hinter.handle("git", 3, &history, false), " status");
+        assert_eq!(hinter.complete_hint(), " status");
+    }
+
+    #[test]
+    fn changing_the_line_resets_the_cycled_hint() {
+        let history = cycling_history();
+        let mut hinter = DefaultHinter::default();
+
+        hinter.handle("git", 3, &history, false);
+        assert!(hinter.next_hint());
+        assert_eq!(hinter.handle("git ", 4, &history, false), "push");
+        assert_eq!(hinter.handle("git", 3, &history, false), " push");
+    }
+
+    #[test]
+    fn saving_to_the_history_resets_the_cycled_hint() {
+        let mut history = cycling_history();
+        let mut hinter = DefaultHinter::default();
+
+        hinter.handle("git", 3, &history, false);
+        assert!(hinter.next_hint());
+        assert_eq!(hinter.handle("git", 3, &history, false), " status");
+
+        history
+            .save(HistoryItem::from_command_line("git fetch"))
+            .unwrap();
+        assert_eq!(hinter.handle("git", 3, &history, false), " fetch");
+    }
+
+    #[test]
+    fn saving_to_a_full_history_resets_the_cycled_hint() {
+        let mut history = FileBackedHistory::new(2);
+        for command in ["git status", "git push"] {
+            history
+                .save(HistoryItem::from_command_line(command))
+                .unwrap();
+        }
+        let mut hinter = DefaultHinter::default();
+
+        hinter.handle("git", 3, &history, false);
+        assert!(hinter.next_hint());
+        history
+            .save(HistoryItem::from_command_line("git fetch"))
+            .unwrap();
+        assert_eq!(hinter.handle("git", 3, &history, false), " fetch");
+    }
+
+    #[test]
+    fn completer_hints_do_not_cycle() {
+        let mut hinter = chained_hinter();
+
+        hinter.handle("ls; ec", 6, &history(), false);
+        assert!(!hinter.next_hint());
+    }
+
+    #[test]
+    fn next_hint_token_follows_word_boundaries() {
+        let mut history = FileBackedHistory::default();
+        history
+            .save(HistoryItem::from_command_line("cd ~/src/reedline && ls"))
+            .unwrap();
+        let mut hinter = DefaultHinter::default();
+
+        // Accepting the next token repeatedly walks through the hint
+        let mut line = "cd".to_string();
+        let mut tokens = vec![];
+        loop {
+            hinter.handle(&line, line.len(), &history, false);
+            let token = hinter.next_hint_token();
+            if token.is_empty() {
+                break;
+            }
+            line.push_str(&token);
+            tokens.push(token);
+        }
+
+        assert_eq!(
+            tokens,
+            vec![" ~", "/", "src", "/", "reedline", " &", "&", " ls"]
+        );
+        assert_eq!(line, "cd ~/src/reedline && ls");
+    }
 }
@@ -1,13 +1,24 @@
+mod completer;
 mod default;
-pub use default::DefaultHinter;
+pub use completer::CompleterHinter;
+pub use default::{DefaultHinter, HintSource};
 
-use crate::History;
+use crate::{History, LineBuffer};
+
+/// The hint up to the end of its next word, using the same word boundaries as
+/// [`EditCommand::MoveWordRight`](crate::EditCommand::MoveWordRight)
+pub(crate) fn next_hint_word(hint: &str) -> String {
+    let mut buffer = LineBuffer::from(hint);
+    buffer.set_insertion_point(0);
+    hint[..buffer.word_right_index()].to_string()
+}
 /// A trait that's responsible for returning the hint for the current line and position
 /// Hints are often shown in-line as part of the buffer, showing the user text they can accept or ignore
 pub trait Hinter: Send {
     /// Handle the hinting duty by using the line, position, and current history
     ///
-    /// Returns the formatted output to show the user
+    /// Returns the formatted output to show the user. A hint containing newlines is
+    /// painted over multiple lines below the current line
     fn handle(
         &mut self,
         line: &str,
@@ -20,6 +31,22 @@ pub trait Hinter: Send {
     fn complete_hint(&self) -> String;
 
     /// Return the first semantic token of the hint
-    /// for incremental completion
+    /// for incremental completion with [`ReedlineEvent::HistoryHintWordComplete`](crate::ReedlineEvent::HistoryHintWordComplete)
     fn next_hint_token(&self) -> String;
+
+    /// Replace the current hint with the next, older, candidate for the same line
+    /// with [`ReedlineEvent::HistoryHintNext`](crate::ReedlineEvent::HistoryHintNext)
+    ///
+    /// Returns `false` if there is no other candidate. The following call of
+    /// [`Hinter::handle`] with the same line shows the new hint
+    fn next_hint(&mut self) -> bool {
+        false
+    }
+
+    /// Go back to the previous candidate before [`Hinter::next_hint`]
+    ///
+    /// Returns `false` if the first candidate is already shown
+    fn previous_hint(&mut self) -> bool {
+        false
+    }
 }
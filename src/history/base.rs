@@ -1,6 +1,12 @@
-use super::HistoryItemId;
-use crate::{core_editor::LineBuffer, HistoryItem, HistorySessionId, Result};
+use super::{readline, HistoryItemId};
+use crate::{
+    core_editor::LineBuffer,
+    result::{ReedlineError, ReedlineErrorVariants},
+    HistoryItem, HistorySessionId, Result,
+};
 use chrono::Utc;
+use regex::{Regex, RegexBuilder};
+use std::{borrow::Cow, io::BufWriter, path::Path};
 
 /// Browsing modes for a [`History`]
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -26,10 +32,70 @@ pub enum CommandLineSearch {
     ///
     /// Useful to gather statistics
     Exact(String),
+    /// Command line matches the regular expression, e.g. `git.*rebase`
+    ///
+    /// An invalid expression makes the search return an error
+    Regex(String),
+}
+
+/// A [`CommandLineSearch`] prepared to be matched against command lines
+pub(crate) struct CommandLineMatcher<'a> {
+    search: &'a CommandLineSearch,
+    case_sensitive: bool,
+    regex: Option<Regex>,
+}
+
+impl<'a> CommandLineMatcher<'a> {
+    /// Compiles the regular expression of a [`CommandLineSearch::Regex`]
+    pub(crate) fn new(search: &'a CommandLineSearch, case_sensitive: bool) -> Result<Self> {
+        let regex = match search {
+            CommandLineSearch::Regex(pattern) => Some(
+                RegexBuilder::new(pattern)
+                    .case_insensitive(!case_sensitive)
+                    .build()
+                    .map_err(|err| ReedlineError(ReedlineErrorVariants::InvalidRegex(err)))?,
+            ),
+            _ => None,
+        };
+
+        Ok(Self {
+            search,
+            case_sensitive,
+            regex,
+        })
+    }
+
+    pub(crate) fn is_match(&self, command_line: &str) -> bool {
+        if let Some(regex) = &self.regex {
+            return regex.is_match(command_line);
+        }
+
+        let command_line = fold_case(command_line, self.case_sensitive);
+        match self.search {
+            CommandLineSearch::Prefix(prefix) => {
+                command_line.starts_with(&*fold_case(prefix, self.case_sensitive))
+            }
+            CommandLineSearch::Substring(substring) => {
+                command_line.contains(&*fold_case(substring, self.case_sensitive))
+            }
+            CommandLineSearch::Exact(exact) => {
+                command_line == fold_case(exact, self.case_sensitive)
+            }
+            CommandLineSearch::Regex(_) => false,
+        }
+    }
+}
+
+fn fold_case(text: &str, case_sensitive: bool) -> Cow<'_, str> {
+    if case_sensitive {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(text.to_lowercase())
+    }
 }
 
 /// Defines how to traverse the history when executing a [`SearchQuery`]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SearchDirection {
     /// From the most recent entry backward
     Backward,
@@ -53,6 +119,14 @@ pub struct SearchFilter {
     pub exit_successful: Option<bool>,
     /// Filter on the session id
     pub session: Option<HistorySessionId>,
+    /// Whether [`SearchFilter::command_line`] distinguishes upper and lower case
+    ///
+    /// `None` (the default) keeps the native matching of the backend: the plain searches of
+    /// `SqliteBackedHistory` ignore the case of ASCII
+    /// characters, everything else is case-sensitive.
+    /// `Some(false)` only ignores the case of ASCII characters in
+    /// `SqliteBackedHistory` as well.
+    pub case_sensitive: Option<bool>,
 }
 
 impl SearchFilter {
@@ -76,6 +150,7 @@ impl SearchFilter {
             cwd_prefix: None,
             exit_successful: None,
             session,
+            case_sensitive: None,
         }
     }
 }
@@ -149,6 +224,42 @@ impl SearchQuery {
             filter: SearchFilter::anything(session),
         }
     }
+
+    /// Only get the entries started after `time`
+    ///
+    /// The bound is exclusive and means the same for both search directions.
+    /// Entries without a timestamp are excluded, histories that don't store timestamps
+    /// return an error
+    #[must_use]
+    pub fn after(mut self, time: impl Into<chrono::DateTime<Utc>>) -> Self {
+        let time = time.into();
+        match self.direction {
+            SearchDirection::Forward => self.start_time = Some(time),
+            // `end_time` is inclusive, timestamps are stored with millisecond precision
+            SearchDirection::Backward => {
+                self.end_time = Some(time + chrono::Duration::milliseconds(1))
+            }
+        }
+        self
+    }
+
+    /// Only get the entries started before `time`
+    ///
+    /// The bound is exclusive and means the same for both search directions.
+    /// Entries without a timestamp are excluded, histories that don't store timestamps
+    /// return an error
+    #[must_use]
+    pub fn before(mut self, time: impl Into<chrono::DateTime<Utc>>) -> Self {
+        let time = time.into();
+        match self.direction {
+            // `end_time` is inclusive, timestamps are stored with millisecond precision
+            SearchDirection::Forward => {
+                self.end_time = Some(time - chrono::Duration::milliseconds(1))
+            }
+            SearchDirection::Backward => self.start_time = Some(time),
+        }
+        self
+    }
 }
 
 /// Represents a history file or database
@@ -186,6 +297,41 @@ pub trait History: Send {
     fn sync(&mut self) -> std::io::Result<()>;
     /// get the history session id
     fn session(&self) -> Option<HistorySessionId>;
+
+    /// Import the entries of a GNU readline history file, like `~/.bash_history`
+    ///
+    /// The entries are saved in order, so the history applies its rules for
+    /// duplicates and capacity. The `#<epoch>` timestamps written by bash with
+    /// `HISTTIMEFORMAT` become the start timestamps of the entries.
+    /// Lines that can't be read are skipped.
+    ///
+    /// Returns the number of entries read from the file
+    fn import_readline(&mut self, path: &Path) -> Result<usize> {
+        let content = std::fs::read(path)
+            .map_err(|err| ReedlineError(ReedlineErrorVariants::IOError(err)))?;
+        let entries = readline::parse_history(&content);
+        let count = entries.len();
+        for (start_timestamp, command_line) in entries {
+            self.save(HistoryItem {
+                start_timestamp,
+                ..HistoryItem::from_command_line(command_line)
+            })?;
+        }
+        Ok(count)
+    }
+
+    /// Export all entries in chronological order as a GNU readline history file
+    ///
+    /// With `timestamps` every entry is preceded by a `#<epoch>` comment line like bash
+    /// writes with `HISTTIMEFORMAT`, which keeps multiline entries together
+    fn export_readline(&self, path: &Path, timestamps: bool) -> Result<()> {
+        let entries = self.search(SearchQuery::everything(SearchDirection::Forward, None))?;
+        std::fs::File::create(path)
+            .and_then(|file| {
+                readline::write_history(&mut BufWriter::new(file), &entries, timestamps)
+            })
+            .map_err(|err| ReedlineError(ReedlineErrorVariants::IOError(err)))
+    }
 }
 
 #[cfg(test)]
@@ -207,12 +353,14 @@ mod test {
             cwd: Some(cwd.to_string()),
             duration: Some(Duration::from_millis(1000)),
             exit_status: Some(exit_status),
+            cursor_position: None,
             more_info: None,
         }
     }
     use std::time::Duration;
 
     use super::*;
+    use chrono::TimeZone;
     fn create_filled_example_history() -> Result<Box<dyn History>> {
         #[cfg(any(feature = "sqlite", feature = "sqlite-dynlib"))]
         let mut history = crate::SqliteBackedHistory::in_memory()?;
@@ -257,6 +405,52 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn cursor_position_is_stored() -> Result<()> {
+        let mut history = create_filled_example_history()?;
+        let saved = history.save(HistoryItem {
+            cursor_position: Some(3),
+            ..create_item(1, "/home/me", "git commit", 0)
+        })?;
+        let loaded = history.load(saved.id.unwrap())?;
+
+        // The history file only keeps the command lines
+        let expected = if IS_FILE_BASED { None } else { Some(3) };
+        assert_eq!(loaded.cursor_position, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn cursor_position_survives_reopening_the_history() -> Result<()> {
+        let tmp = tempfile::tempdir().unwrap();
+        let item = HistoryItem {
+            cursor_position: Some(3),
+            ..HistoryItem::from_command_line("git commit")
+        };
+
+        #[cfg(any(feature = "sqlite", feature = "sqlite-dynlib"))]
+        {
+            let file = tmp.path().join("history.db");
+            let saved = crate::SqliteBackedHistory::with_file(file.clone())?.save(item.clone())?;
+            let history = crate::SqliteBackedHistory::with_file(file)?;
+            assert_eq!(history.load(saved.id.unwrap())?, saved);
+            assert_eq!(saved.cursor_position, Some(3));
+        }
+
+        // The history file only keeps the command lines
+        let file = tmp.path().join("history.txt");
+        crate::FileBackedHistory::with_file(10, file.clone())
+            .unwrap()
+            .save(item)?;
+        let history = crate::FileBackedHistory::with_file(10, file).unwrap();
+        let loaded = history.load(HistoryItemId::new(0))?;
+        assert_eq!(loaded.command_line, "git commit");
+        assert_eq!(loaded.cursor_position, None);
+
+        tmp.close().unwrap();
+        Ok(())
+    }
+
     fn search_returned(
         history: &dyn History,
         res: Vec<HistoryItem>,
@@ -357,6 +551,73 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn search_time_range() -> Result<()> {
+        let mut history = create_filled_example_history()?;
+        let at = |hour| Utc.with_ymd_and_hms(2023, 6, 1, hour, 0, 0).unwrap();
+        for (hour, cmd) in [
+            (8, "make"),
+            (9, "make check"),
+            (10, "make test"),
+            (11, "make install"),
+            (12, "make clean"),
+        ] {
+            history.save(HistoryItem {
+                start_timestamp: Some(at(hour)),
+                ..create_item(1, "/home/me", cmd, 0)
+            })?;
+        }
+
+        for (direction, expected) in [
+            (SearchDirection::Forward, vec!["make test", "make install"]),
+            (SearchDirection::Backward, vec!["make install", "make test"]),
+        ] {
+            let res = history.search(
+                SearchQuery::everything(direction, None)
+                    .after(at(9))
+                    .before(at(12)),
+            );
+            if IS_FILE_BASED {
+                assert!(res.is_err());
+                continue;
+            }
+            let commands: Vec<_> = res?.into_iter().map(|e| e.command_line).collect();
+            assert_eq!(commands, expected);
+        }
+
+        for (direction, expected) in [
+            (SearchDirection::Forward, vec!["make install", "make clean"]),
+            (
+                SearchDirection::Backward,
+                vec!["make clean", "make install"],
+            ),
+        ] {
+            let res = history.search(
+                SearchQuery::everything(direction, None).after(std::time::SystemTime::from(at(10))),
+            );
+            if IS_FILE_BASED {
+                assert!(res.is_err());
+                continue;
+            }
+            let commands: Vec<_> = res?.into_iter().map(|e| e.command_line).collect();
+            assert_eq!(commands, expected);
+        }
+
+        for (direction, expected) in [
+            (SearchDirection::Forward, vec!["make", "make check"]),
+            (SearchDirection::Backward, vec!["make check", "make"]),
+        ] {
+            let res = history.search(SearchQuery::everything(direction, None).before(at(10)));
+            if IS_FILE_BASED {
+                assert!(res.is_err());
+                continue;
+            }
+            let commands: Vec<_> = res?.into_iter().map(|e| e.command_line).collect();
+            assert_eq!(commands, expected);
+        }
+        Ok(())
+    }
+
     // test that clear() works as expected across multiple instances of History
     #[test]
     fn clear_history_with_backing_file() -> Result<()> {
@@ -394,4 +655,47 @@ mod test {
 
         Ok(())
     }
+
+    // test that delete() removes the entry from the backing file as well
+    #[test]
+    fn delete_with_backing_file() -> Result<()> {
+        #[cfg(any(feature = "sqlite", feature = "sqlite-dynlib"))]
+        fn open_history(dir: &std::path::Path) -> Box<dyn History> {
+            Box::new(crate::SqliteBackedHistory::with_file(dir.join("history.db")).unwrap())
+        }
+
+        #[cfg(not(any(feature = "sqlite", feature = "sqlite-dynlib")))]
+        fn open_history(dir: &std::path::Path) -> Box<dyn History> {
+            Box::new(crate::FileBackedHistory::with_file(100, dir.join("history.txt")).unwrap())
+        }
+
+        let tmp = tempfile::tempdir().unwrap();
+        let mut history = open_history(tmp.path());
+        history.save(create_item(1, "/home/me", "cd ~/Downloads", 0))?;
+        history.save(create_item(1, "/home/me/Downloads", "unzp foo.zip", 1))?;
+        history.save(create_item(1, "/home/me/Downloads", "ls", 0))?;
+        drop(history);
+
+        let mut history = open_history(tmp.path());
+        let typo = history.search(SearchQuery::last_with_search(
+            SearchFilter::from_text_search(
+                CommandLineSearch::Exact("unzp foo.zip".to_string()),
+                None,
+            ),
+        ))?;
+        history.delete(typo[0].id.unwrap())?;
+        assert_eq!(history.count_all()?, 2);
+        drop(history);
+
+        let history = open_history(tmp.path());
+        let commands: Vec<_> = history
+            .search(SearchQuery::everything(SearchDirection::Forward, None))?
+            .into_iter()
+            .map(|e| e.command_line)
+            .collect();
+        assert_eq!(commands, vec!["cd ~/Downloads", "ls"]);
+
+        tmp.close().unwrap();
+        Ok(())
+    }
 }
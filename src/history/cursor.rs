@@ -91,6 +91,35 @@ impl HistoryCursor {
         self.current.as_ref().map(|e| e.command_line.to_string())
     }
 
+    /// Returns the insertion point stored with the entry (if present) at the cursor
+    pub fn cursor_position_at_cursor(&self) -> Option<usize> {
+        self.current.as_ref().and_then(|e| e.cursor_position)
+    }
+
+    /// Position of the entry at the cursor among all the entries matching the query,
+    /// counted from the most recent one, together with the number of matching entries
+    pub fn match_position(&self, history: &dyn History) -> Option<(usize, usize)> {
+        let current = self.current.as_ref()?;
+        let count = |start_id| {
+            history.count(SearchQuery {
+                start_id,
+                end_id: None,
+                start_time: None,
+                end_time: None,
+                direction: SearchDirection::Forward,
+                limit: None,
+                filter: SearchFilter {
+                    not_command_line: None,
+                    ..self.get_search_filter()
+                },
+            })
+        };
+
+        let newer = count(current.id).ok()?;
+        let total = count(None).ok()?;
+        Some((newer as usize + 1, total as usize))
+    }
+
     /// Poll the current [`HistoryNavigationQuery`] mode
     pub fn get_navigation(&self) -> HistoryNavigationQuery {
         self.query.clone()
@@ -374,6 +403,129 @@ mod tests {
         Ok(())
     }
 
+    fn search_texts(
+        hist: &dyn History,
+        search: CommandLineSearch,
+        case_sensitive: bool,
+    ) -> Vec<String> {
+        let mut query = SearchQuery::everything(SearchDirection::Forward, None);
+        query.filter = SearchFilter {
+            case_sensitive: Some(case_sensitive),
+            ..SearchFilter::from_text_search(search, None)
+        };
+        hist.search(query)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.command_line)
+            .collect()
+    }
+
+    #[test]
+    fn regex_search_works() -> Result<()> {
+        let (mut hist, _) = create_history();
+        add_text_entries(
+            hist.as_mut(),
+            &[
+                "git rebase -i main",
+                "git status",
+                "git pull --rebase",
+                "cargo test",
+            ],
+        );
+
+        assert_eq!(
+            search_texts(&*hist, CommandLineSearch::Regex("git.*rebase".into()), true),
+            vec!["git rebase -i main", "git pull --rebase"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn regex_search_respects_limit() -> Result<()> {
+        let (mut hist, _) = create_history();
+        add_text_entries(hist.as_mut(), &["ls -a", "cd", "ls -l", "ls"]);
+
+        let query = |limit| SearchQuery {
+            limit,
+            filter: SearchFilter::from_text_search(CommandLineSearch::Regex("^ls".into()), None),
+            ..SearchQuery::everything(SearchDirection::Backward, None)
+        };
+        let res = hist.search(query(Some(2)))?;
+        let texts: Vec<_> = res.iter().map(|e| e.command_line.as_str()).collect();
+        assert_eq!(texts, vec!["ls", "ls -l"]);
+        assert_eq!(hist.count(query(None))?, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_regex_is_an_error() {
+        let (mut hist, _) = create_history();
+        add_text_entries(hist.as_mut(), &["git status"]);
+
+        let query = SearchQuery {
+            filter: SearchFilter::from_text_search(CommandLineSearch::Regex("git(".into()), None),
+            ..SearchQuery::everything(SearchDirection::Forward, None)
+        };
+        assert!(hist.search(query).is_err());
+    }
+
+    #[test]
+    fn case_insensitive_search_works() -> Result<()> {
+        let (mut hist, _) = create_history();
+        add_text_entries(hist.as_mut(), &["Cargo Build", "cargo build", "make"]);
+
+        assert_eq!(
+            search_texts(&*hist, CommandLineSearch::Prefix("cargo".into()), true),
+            vec!["cargo build"]
+        );
+        assert_eq!(
+            search_texts(&*hist, CommandLineSearch::Substring("BUILD".into()), false),
+            vec!["Cargo Build", "cargo build"]
+        );
+        assert_eq!(
+            search_texts(&*hist, CommandLineSearch::Regex("^CARGO".into()), false),
+            vec!["Cargo Build", "cargo build"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn default_search_keeps_the_case_handling_of_the_backend() -> Result<()> {
+        let (mut hist, _) = create_history();
+        add_text_entries(hist.as_mut(), &["Cargo Build", "cargo build"]);
+
+        let query = SearchQuery {
+            filter: SearchFilter::from_text_search(CommandLineSearch::Prefix("cargo".into()), None),
+            ..SearchQuery::everything(SearchDirection::Forward, None)
+        };
+        let texts: Vec<_> = hist
+            .search(query)?
+            .into_iter()
+            .map(|e| e.command_line)
+            .collect();
+        #[cfg(any(feature = "sqlite", feature = "sqlite-dynlib"))]
+        assert_eq!(texts, vec!["Cargo Build", "cargo build"]);
+        #[cfg(not(any(feature = "sqlite", feature = "sqlite-dynlib")))]
+        assert_eq!(texts, vec!["cargo build"]);
+        Ok(())
+    }
+
+    #[test]
+    fn substring_search_matches_wildcards_literally() -> Result<()> {
+        let (mut hist, _) = create_history();
+        add_text_entries(hist.as_mut(), &["echo 100%", "echo 1000", "echo a_b"]);
+
+        assert_eq!(
+            search_texts(&*hist, CommandLineSearch::Substring("0%".into()), true),
+            vec!["echo 100%"]
+        );
+        assert_eq!(
+            search_texts(&*hist, CommandLineSearch::Substring("_".into()), true),
+            vec!["echo a_b"]
+        );
+        Ok(())
+    }
+
     #[test]
     fn writes_to_new_file() -> Result<()> {
         use tempfile::tempdir;
@@ -509,6 +661,145 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn ignore_all_duplicates_moves_entry_to_the_end() -> Result<()> {
+        let mut hist = FileBackedHistory::new(5).with_duplicates(HistoryDuplicates::IgnoreAll);
+        add_text_entries(&mut hist, &["ls", "cd", "ls", "make", "cd"]);
+
+        assert_eq!(get_all_entry_texts(&hist), vec!["ls", "make", "cd"]);
+        Ok(())
+    }
+
+    #[test]
+    fn duplicates_are_kept_by_default() -> Result<()> {
+        let mut hist = FileBackedHistory::new(5);
+        add_text_entries(&mut hist, &["ls", "ls", "cd", "ls"]);
+
+        assert_eq!(get_all_entry_texts(&hist), vec!["ls", "cd", "ls"]);
+        Ok(())
+    }
+
+    #[test]
+    fn least_frequent_entries_are_evicted() -> Result<()> {
+        let mut hist = FileBackedHistory::new(3)
+            .with_duplicates(HistoryDuplicates::IgnoreAll)
+            .with_eviction_policy(EvictionPolicy::LeastFrequent);
+        add_text_entries(&mut hist, &["ls", "cd", "ls", "make", "git", "ls", "cd"]);
+        assert_eq!(get_all_entry_texts(&hist), vec!["git", "ls", "cd"]);
+
+        // "git" was used once, unlike "ls" and "cd"
+        add_text_entries(&mut hist, &["vim"]);
+        assert_eq!(get_all_entry_texts(&hist), vec!["ls", "cd", "vim"]);
+        Ok(())
+    }
+
+    #[test]
+    fn full_history_without_eviction_is_an_error() -> Result<()> {
+        let mut hist = FileBackedHistory::new(2).with_eviction_policy(EvictionPolicy::None);
+        add_text_entries(&mut hist, &["ls", "cd"]);
+
+        assert!(hist.save(HistoryItem::from_command_line("make")).is_err());
+        assert_eq!(get_all_entry_texts(&hist), vec!["ls", "cd"]);
+        Ok(())
+    }
+
+    #[test]
+    fn eviction_policy_applies_to_the_loaded_file() -> Result<()> {
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+        std::fs::write(&histfile, "ls\ncd\nls\nmake\nls\ngit\ncd\n").unwrap();
+
+        {
+            let hist = FileBackedHistory::with_file_and_eviction_policy(
+                4,
+                histfile.clone(),
+                EvictionPolicy::LeastFrequent,
+            )
+            .unwrap();
+            // "make" and "git" are dropped before the older "cd" used twice
+            assert_eq!(get_all_entry_texts(&hist), vec!["ls", "ls", "ls", "cd"]);
+        }
+        assert_eq!(
+            std::fs::read_to_string(&histfile).unwrap(),
+            "ls\nls\nls\ncd\n"
+        );
+
+        assert!(FileBackedHistory::with_file_and_eviction_policy(
+            3,
+            histfile,
+            EvictionPolicy::None
+        )
+        .is_err());
+
+        tmp.close().unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn commands_dropped_from_the_file_are_counted_anew() -> Result<()> {
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+        let mut hist = FileBackedHistory::with_file_and_eviction_policy(
+            2,
+            histfile.clone(),
+            EvictionPolicy::LeastFrequent,
+        )
+        .unwrap();
+        add_text_entries(&mut hist, &["git", "git", "git"]);
+        hist.sync().unwrap();
+
+        // Another session fills the file with commands it uses more often
+        std::fs::write(&histfile, "ls\nls\nls\ncd\ncd\ncd\n").unwrap();
+        hist.sync().unwrap();
+        assert_eq!(get_all_entry_texts(&hist), vec!["cd", "cd"]);
+
+        add_text_entries(&mut hist, &["git", "vim"]);
+        assert_eq!(get_all_entry_texts(&hist), vec!["cd", "vim"]);
+
+        tmp.close().unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn deduplicates_existing_file() -> Result<()> {
+        use tempfile::tempdir;
+
+        let tmp = tempdir().unwrap();
+        let histfile = tmp.path().join(".history");
+        std::fs::write(&histfile, "ls\nls\ncd\nls\ncd\n").unwrap();
+
+        {
+            let hist = FileBackedHistory::with_file(10, histfile.clone())
+                .unwrap()
+                .with_duplicates(HistoryDuplicates::IgnoreConsecutive);
+            assert_eq!(get_all_entry_texts(&hist), vec!["ls", "cd", "ls", "cd"]);
+            // As `hist` goes out of scope and get's dropped, its contents are flushed to disk
+        }
+        assert_eq!(
+            std::fs::read_to_string(&histfile).unwrap(),
+            "ls\ncd\nls\ncd\n"
+        );
+
+        {
+            let mut hist = FileBackedHistory::with_file(10, histfile.clone())
+                .unwrap()
+                .with_duplicates(HistoryDuplicates::IgnoreAll);
+            add_text_entries(&mut hist, &["make", "ls"]);
+            assert_eq!(get_all_entry_texts(&hist), vec!["cd", "make", "ls"]);
+        }
+        assert_eq!(
+            std::fs::read_to_string(&histfile).unwrap(),
+            "cd\nmake\nls\n"
+        );
+
+        tmp.close().unwrap();
+        Ok(())
+    }
+
     #[test]
     fn concurrent_histories_dont_erase_eachother() -> Result<()> {
         use tempfile::tempdir;
@@ -1,5 +1,5 @@
 use super::{
-    base::CommandLineSearch, History, HistoryItem, HistoryItemId, SearchDirection, SearchQuery,
+    base::CommandLineMatcher, History, HistoryItem, HistoryItemId, SearchDirection, SearchQuery,
 };
 use crate::{
     result::{ReedlineError, ReedlineErrorVariants},
@@ -7,7 +7,7 @@ use crate::{
 };
 
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, HashSet, VecDeque},
     fs::OpenOptions,
     io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write},
     ops::{Deref, DerefMut},
@@ -18,6 +18,87 @@ use std::{
 pub const HISTORY_SIZE: usize = 1000;
 pub const NEWLINE_ESCAPE: &str = "<\\n>";
 
+/// How [`FileBackedHistory`] handles entries that are already present in the history
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HistoryDuplicates {
+    /// Keep every entry, only skipping a new entry identical to the one just before it (default)
+    #[default]
+    Keep,
+    /// Also collapse runs of identical entries found in the history file
+    IgnoreConsecutive,
+    /// Keep only the most recent occurrence of each entry, moving a repeated entry to the end
+    /// (similar to zsh's `HIST_IGNORE_ALL_DUPS`)
+    IgnoreAll,
+}
+
+impl HistoryDuplicates {
+    fn dedup(self, entries: VecDeque<String>) -> VecDeque<String> {
+        match self {
+            HistoryDuplicates::Keep => entries,
+            HistoryDuplicates::IgnoreConsecutive => {
+                let mut entries = Vec::from(entries);
+                entries.dedup();
+                entries.into()
+            }
+            HistoryDuplicates::IgnoreAll => {
+                let mut seen = HashSet::new();
+                let mut entries: VecDeque<_> = entries
+                    .into_iter()
+                    .rev()
+                    .filter(|entry| seen.insert(entry.clone()))
+                    .collect();
+                entries.make_contiguous().reverse();
+                entries
+            }
+        }
+    }
+}
+
+/// Which entries [`FileBackedHistory`] drops when it is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Drop the oldest entry (default)
+    #[default]
+    Oldest,
+    /// Drop the entry of the command used the least often, the oldest one among equally
+    /// used commands. Uses are counted while saving and when loading the history file
+    ///
+    /// The history file only holds the command lines, so the counts are not persisted.
+    /// A loaded file counts one use per entry, which only keeps the uses of earlier
+    /// sessions with [`HistoryDuplicates::Keep`].
+    LeastFrequent,
+    /// Drop nothing, saving to a full history is an error
+    None,
+}
+
+impl EvictionPolicy {
+    /// Ascending indices of the `count` entries to drop to make room for new ones,
+    /// `None` if nothing may be dropped
+    fn victims(
+        self,
+        entries: &VecDeque<String>,
+        use_counts: &HashMap<String, usize>,
+        count: usize,
+    ) -> Option<Vec<usize>> {
+        let count = count.min(entries.len());
+        match self {
+            _ if count == 0 => Some(vec![]),
+            EvictionPolicy::Oldest => Some((0..count).collect()),
+            EvictionPolicy::LeastFrequent => {
+                let mut indices: Vec<usize> = (0..entries.len()).collect();
+                indices.sort_by_key(|index| {
+                    let uses = use_counts.get(&entries[*index]).copied().unwrap_or(0);
+                    (uses, *index)
+                });
+                indices.truncate(count);
+                indices.sort_unstable();
+                Some(indices)
+            }
+            EvictionPolicy::None => None,
+        }
+    }
+}
+
 /// Stateful history that allows up/down-arrow browsing with an internal cursor.
 ///
 /// Can optionally be associated with a newline separated history file using the [`FileBackedHistory::with_file()`] constructor.
@@ -31,6 +112,10 @@ pub struct FileBackedHistory {
     file: Option<PathBuf>,
     len_on_disk: usize, // Keep track what was previously written to disk
     session: Option<HistorySessionId>,
+    duplicates: HistoryDuplicates,
+    eviction_policy: EvictionPolicy,
+    // Uses of every command, only tracked for `EvictionPolicy::LeastFrequent`
+    use_counts: HashMap<String, usize>,
 }
 
 impl Default for FileBackedHistory {
@@ -61,15 +146,39 @@ impl History for FileBackedHistory {
             .map_or(true, |previous| previous != &entry)
             && !entry.is_empty()
         {
-            if self.entries.len() == self.capacity {
-                // History is "full", so we delete the oldest entry first,
-                // before adding a new one.
-                self.entries.pop_front();
-                self.len_on_disk = self.len_on_disk.saturating_sub(1);
+            if self.duplicates == HistoryDuplicates::IgnoreAll {
+                if let Some(idx) = self.entries.iter().position(|previous| previous == &entry) {
+                    self.entries.remove(idx);
+                    if idx < self.len_on_disk {
+                        self.len_on_disk -= 1;
+                    }
+                }
             }
+            if self.entries.len() >= self.capacity {
+                // History is "full", so we delete an entry first, before adding a new one.
+                let index = self
+                    .eviction_policy
+                    .victims(&self.entries, &self.use_counts, 1)
+                    .and_then(|victims| victims.first().copied())
+                    .ok_or(ReedlineError(ReedlineErrorVariants::OtherHistoryError(
+                        "The history is full",
+                    )))?;
+                if let Some(evicted) = self.entries.remove(index) {
+                    if !self.entries.contains(&evicted) {
+                        self.use_counts.remove(&evicted);
+                    }
+                }
+                if index < self.len_on_disk {
+                    self.len_on_disk -= 1;
+                }
+            }
+            self.count_use(&entry);
             self.entries.push_back(entry.to_string());
             Some(HistoryItemId::new((self.entries.len() - 1) as i64))
         } else {
+            if !entry.is_empty() {
+                self.count_use(&entry);
+            }
             None
         };
         Ok(FileBackedHistory::construct_entry(entry_id, entry))
@@ -138,13 +247,19 @@ impl History for FileBackedHistory {
         } else {
             intrinsic_limit as usize
         };
+        let matcher = query
+            .filter
+            .command_line
+            .as_ref()
+            .map(|search| {
+                CommandLineMatcher::new(search, query.filter.case_sensitive.unwrap_or(true))
+            })
+            .transpose()?;
         let filter = |(idx, cmd): (usize, &String)| {
-            if !match &query.filter.command_line {
-                Some(CommandLineSearch::Prefix(p)) => cmd.starts_with(p),
-                Some(CommandLineSearch::Substring(p)) => cmd.contains(p),
-                Some(CommandLineSearch::Exact(p)) => cmd == p,
-                None => true,
-            } {
+            if !matcher
+                .as_ref()
+                .map_or(true, |matcher| matcher.is_match(cmd))
+            {
                 return None;
             }
             if let Some(str) = &query.filter.not_command_line {
@@ -186,6 +301,7 @@ impl History for FileBackedHistory {
 
     fn clear(&mut self) -> Result<()> {
         self.entries.clear();
+        self.use_counts.clear();
         self.len_on_disk = 0;
 
         if let Some(file) = &self.file {
@@ -197,22 +313,46 @@ impl History for FileBackedHistory {
         Ok(())
     }
 
-    fn delete(&mut self, _h: super::HistoryItemId) -> Result<()> {
-        Err(ReedlineError(
-            ReedlineErrorVariants::HistoryFeatureUnsupported {
-                history: "FileBackedHistory",
-                feature: "removing entries",
-            },
-        ))
+    /// Removes the entry, rewriting the history file without it if it was written
+    ///
+    /// The ids of the later entries move down by one
+    fn delete(&mut self, h: super::HistoryItemId) -> Result<()> {
+        let index = usize::try_from(h.0)
+            .ok()
+            .filter(|index| *index < self.entries.len())
+            .ok_or(ReedlineError(ReedlineErrorVariants::OtherHistoryError(
+                "Item does not exist",
+            )))?;
+        let entry = self
+            .entries
+            .remove(index)
+            .expect("the index is within the entries");
+        if !self.entries.contains(&entry) {
+            self.use_counts.remove(&entry);
+        }
+
+        if index < self.len_on_disk {
+            self.len_on_disk -= 1;
+            self.remove_from_file(index, &entry)
+                .map_err(|err| ReedlineError(ReedlineErrorVariants::IOError(err)))?;
+        }
+        Ok(())
     }
 
     /// Writes unwritten history contents to disk.
     ///
-    /// If file would exceed `capacity` truncates the oldest entries.
+    /// If file would exceed `capacity` drops entries following the [`EvictionPolicy`],
+    /// with [`EvictionPolicy::None`] nothing is written and an error is returned instead.
+    /// Unless duplicates are kept, the file is rewritten without them.
     fn sync(&mut self) -> std::io::Result<()> {
         if let Some(fname) = &self.file {
             // The unwritten entries
             let own_entries = self.entries.range(self.len_on_disk..);
+            // Duplicates may span the file and the unwritten entries, and other policies
+            // than dropping the oldest entries may drop any of them, so they are merged
+            // and the whole file is rewritten
+            let merge = self.duplicates != HistoryDuplicates::Keep
+                || self.eviction_policy != EvictionPolicy::Oldest;
 
             if let Some(base_dir) = fname.parent() {
                 std::fs::create_dir_all(base_dir)?;
@@ -232,7 +372,38 @@ impl History for FileBackedHistory {
                     .lines()
                     .map(|o| o.map(|i| decode_entry(&i)))
                     .collect::<std::io::Result<VecDeque<_>>>()?;
-                if from_file.len() + own_entries.len() > self.capacity {
+                if merge {
+                    if self.eviction_policy == EvictionPolicy::LeastFrequent {
+                        let mut file_counts: HashMap<&String, usize> = HashMap::new();
+                        for entry in &from_file {
+                            *file_counts.entry(entry).or_default() += 1;
+                        }
+                        for (entry, count) in file_counts {
+                            let use_count = self.use_counts.entry(entry.clone()).or_default();
+                            *use_count = (*use_count).max(count);
+                        }
+                    }
+                    from_file.extend(own_entries.clone().cloned());
+                    let all_entries = self.duplicates.dedup(from_file);
+                    let excess = all_entries.len().saturating_sub(self.capacity);
+                    let victims = self
+                        .eviction_policy
+                        .victims(&all_entries, &self.use_counts, excess)
+                        .ok_or_else(|| {
+                            std::io::Error::new(
+                                std::io::ErrorKind::Other,
+                                "The history file is full",
+                            )
+                        })?;
+                    let mut victims = victims.into_iter().peekable();
+                    let all_entries = all_entries
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(index, _)| victims.next_if_eq(index).is_none())
+                        .map(|(_, entry)| entry)
+                        .collect();
+                    (all_entries, true)
+                } else if from_file.len() + own_entries.len() > self.capacity {
                     (
                         from_file.split_off(from_file.len() - (self.capacity - own_entries.len())),
                         true,
@@ -254,9 +425,11 @@ impl History for FileBackedHistory {
                 } else {
                     writer.seek(SeekFrom::End(0))?;
                 }
-                for line in own_entries {
-                    writer.write_all(encode_entry(line).as_bytes())?;
-                    writer.write_all("\n".as_bytes())?;
+                if !merge {
+                    for line in own_entries {
+                        writer.write_all(encode_entry(line).as_bytes())?;
+                        writer.write_all("\n".as_bytes())?;
+                    }
                 }
                 writer.flush()?;
             }
@@ -266,9 +439,16 @@ impl History for FileBackedHistory {
                 file.set_len(file_len)?;
             }
 
-            let own_entries = self.entries.drain(self.len_on_disk..);
-            foreign_entries.extend(own_entries);
+            if !merge {
+                let own_entries = self.entries.drain(self.len_on_disk..);
+                foreign_entries.extend(own_entries);
+            }
             self.entries = foreign_entries;
+            if self.eviction_policy == EvictionPolicy::LeastFrequent {
+                // Commands that were dropped from the file are not counted anymore
+                let kept: HashSet<&String> = self.entries.iter().collect();
+                self.use_counts.retain(|entry, _| kept.contains(entry));
+            }
 
             self.len_on_disk = self.entries.len();
         }
@@ -296,7 +476,40 @@ impl FileBackedHistory {
             file: None,
             len_on_disk: 0,
             session: None,
+            duplicates: HistoryDuplicates::default(),
+            eviction_policy: EvictionPolicy::default(),
+            use_counts: HashMap::new(),
+        }
+    }
+
+    /// A builder that sets which entries are dropped when the history is full
+    ///
+    /// The history file is only loaded with this policy when it is passed to
+    /// [`FileBackedHistory::with_file_and_eviction_policy()`]
+    #[must_use]
+    pub fn with_eviction_policy(mut self, eviction_policy: EvictionPolicy) -> Self {
+        self.eviction_policy = eviction_policy;
+        self.use_counts.clear();
+        if eviction_policy == EvictionPolicy::LeastFrequent {
+            for entry in &self.entries {
+                *self.use_counts.entry(entry.clone()).or_default() += 1;
+            }
         }
+        self
+    }
+
+    /// A builder that sets how duplicate entries are handled
+    ///
+    /// Entries that were already loaded from the history file are deduplicated right away,
+    /// the file itself is cleaned up on the next [`History::sync()`]
+    #[must_use]
+    pub fn with_duplicates(mut self, duplicates: HistoryDuplicates) -> Self {
+        self.duplicates = duplicates;
+        let unwritten = self.entries.split_off(self.len_on_disk);
+        self.entries = duplicates.dedup(std::mem::take(&mut self.entries));
+        self.len_on_disk = self.entries.len();
+        self.entries.extend(duplicates.dedup(unwritten));
+        self
     }
 
     /// Creates a new history with an associated history file.
@@ -308,7 +521,20 @@ impl FileBackedHistory {
     /// **Side effects:** creates all nested directories to the file
     ///
     pub fn with_file(capacity: usize, file: PathBuf) -> std::io::Result<Self> {
-        let mut hist = Self::new(capacity);
+        Self::with_file_and_eviction_policy(capacity, file, EvictionPolicy::default())
+    }
+
+    /// Creates a new history with an associated history file, dropping entries with the
+    /// given [`EvictionPolicy`] when it is full
+    ///
+    /// The policy also applies to a history file holding more than `capacity` entries.
+    /// With [`EvictionPolicy::None`] loading such a file is an error.
+    pub fn with_file_and_eviction_policy(
+        capacity: usize,
+        file: PathBuf,
+        eviction_policy: EvictionPolicy,
+    ) -> std::io::Result<Self> {
+        let mut hist = Self::new(capacity).with_eviction_policy(eviction_policy);
         if let Some(base_dir) = file.parent() {
             std::fs::create_dir_all(base_dir)?;
         }
@@ -317,6 +543,12 @@ impl FileBackedHistory {
         Ok(hist)
     }
 
+    fn count_use(&mut self, entry: &str) {
+        if self.eviction_policy == EvictionPolicy::LeastFrequent {
+            *self.use_counts.entry(entry.to_string()).or_default() += 1;
+        }
+    }
+
     // this history doesn't store any info except command line
     fn construct_entry(id: Option<HistoryItemId>, command_line: String) -> HistoryItem {
         HistoryItem {
@@ -328,9 +560,49 @@ impl FileBackedHistory {
             cwd: None,
             duration: None,
             exit_status: None,
+            cursor_position: None,
             more_info: None,
         }
     }
+
+    /// Rewrites the history file without the entry at `index`, or without the last
+    /// occurrence of the entry if the file was changed since it was read
+    fn remove_from_file(&self, index: usize, entry: &str) -> std::io::Result<()> {
+        let fname = match &self.file {
+            Some(fname) => fname,
+            None => return Ok(()),
+        };
+
+        let mut f_lock =
+            fd_lock::RwLock::new(OpenOptions::new().write(true).read(true).open(fname)?);
+        let mut writer_guard = f_lock.write()?;
+        let mut lines = BufReader::new(writer_guard.deref())
+            .lines()
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        let entry = encode_entry(entry);
+        let position = if lines.get(index) == Some(&entry) {
+            Some(index)
+        } else {
+            lines.iter().rposition(|line| line == &entry)
+        };
+        if let Some(position) = position {
+            lines.remove(position);
+            {
+                let mut writer = BufWriter::new(writer_guard.deref_mut());
+                writer.rewind()?;
+                for line in &lines {
+                    writer.write_all(line.as_bytes())?;
+                    writer.write_all("\n".as_bytes())?;
+                }
+                writer.flush()?;
+            }
+            let file = writer_guard.deref_mut();
+            let file_len = file.stream_position()?;
+            file.set_len(file_len)?;
+        }
+        Ok(())
+    }
 }
 
 impl Drop for FileBackedHistory {
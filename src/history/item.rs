@@ -96,6 +96,8 @@ pub struct HistoryItem<ExtraInfo: HistoryItemExtraInfo = IgnoreAllExtraInfo> {
     pub duration: Option<Duration>,
     /// the exit status of the command
     pub exit_status: Option<i64>,
+    /// the insertion point in the command line when it was accepted
+    pub cursor_position: Option<usize>,
     /// arbitrary additional information that might be interesting
     pub more_info: Option<ExtraInfo>,
 }
@@ -112,6 +114,7 @@ impl HistoryItem {
             cwd: None,
             duration: None,
             exit_status: None,
+            cursor_position: None,
             more_info: None,
         }
     }
@@ -2,6 +2,7 @@ mod base;
 mod cursor;
 mod file_backed;
 mod item;
+mod readline;
 #[cfg(any(feature = "sqlite", feature = "sqlite-dynlib"))]
 mod sqlite_backed;
 #[cfg(any(feature = "sqlite", feature = "sqlite-dynlib"))]
@@ -13,4 +14,4 @@ pub use base::{
 pub use cursor::HistoryCursor;
 pub use item::{HistoryItem, HistoryItemId, HistorySessionId};
 
-pub use file_backed::{FileBackedHistory, HISTORY_SIZE};
+pub use file_backed::{EvictionPolicy, FileBackedHistory, HistoryDuplicates, HISTORY_SIZE};
@@ -0,0 +1,177 @@
+use super::HistoryItem;
+use chrono::{DateTime, TimeZone, Utc};
+use std::io::{self, Write};
+
+/// Parses the content of a GNU readline history file, like `~/.bash_history`
+///
+/// Every line is an entry. With `HISTTIMEFORMAT` set, bash writes a `#<epoch>` comment
+/// before every entry. Then all the lines up to the next timestamp belong to the entry,
+/// like bash reads multiline entries written with `lithist`.
+/// Lines that aren't valid UTF-8 and empty entries are skipped.
+pub(crate) fn parse_history(content: &[u8]) -> Vec<(Option<DateTime<Utc>>, String)> {
+    let mut entries: Vec<(Option<DateTime<Utc>>, String)> = Vec::new();
+    // Whether the last entry follows a timestamp and collects the lines after it
+    let mut timestamped = false;
+
+    for line in content.split(|&byte| byte == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let line = match std::str::from_utf8(line) {
+            Ok(line) => line,
+            Err(_) => continue,
+        };
+
+        if let Some(seconds) = parse_timestamp(line) {
+            entries.push((Utc.timestamp_opt(seconds, 0).single(), String::new()));
+            timestamped = true;
+            continue;
+        }
+
+        match entries.last_mut() {
+            Some((_, command_line)) if timestamped && !command_line.is_empty() => {
+                command_line.push('\n');
+                command_line.push_str(line);
+            }
+            Some((_, command_line)) if timestamped => command_line.push_str(line),
+            _ => entries.push((None, line.to_string())),
+        }
+    }
+
+    for (_, command_line) in &mut entries {
+        command_line.truncate(command_line.trim_end_matches('\n').len());
+    }
+    entries.retain(|(_, command_line)| !command_line.trim().is_empty());
+    entries
+}
+
+/// Writes `entries` in the GNU readline history format
+///
+/// With `timestamps` every entry is preceded by a `#<epoch>` comment, so multiline
+/// entries are read back as a whole. Entries without a timestamp take the one of the
+/// entry before them. Without timestamps every line of an entry is written as an
+/// entry of its own.
+pub(crate) fn write_history(
+    writer: &mut impl Write,
+    entries: &[HistoryItem],
+    timestamps: bool,
+) -> io::Result<()> {
+    let mut last_timestamp = 0;
+    for entry in entries {
+        if timestamps {
+            if let Some(timestamp) = entry.start_timestamp {
+                last_timestamp = timestamp.timestamp();
+            }
+            writeln!(writer, "#{last_timestamp}")?;
+        }
+        for line in entry.command_line.lines() {
+            writeln!(writer, "{line}")?;
+        }
+    }
+    writer.flush()
+}
+
+fn parse_timestamp(line: &str) -> Option<i64> {
+    let digits = line.strip_prefix('#')?;
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    // Timestamps too large to be represented still separate the entries
+    Some(digits.parse().unwrap_or(i64::MAX))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn command_lines(entries: &[(Option<DateTime<Utc>>, String)]) -> Vec<&str> {
+        entries
+            .iter()
+            .map(|(_, command_line)| command_line.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn plain_history_has_one_entry_per_line() {
+        let entries = parse_history(b"ls\r\n\ncd ~\n#not a timestamp\n");
+
+        assert_eq!(command_lines(&entries), ["ls", "cd ~", "#not a timestamp"]);
+        assert!(entries.iter().all(|(timestamp, _)| timestamp.is_none()));
+    }
+
+    #[test]
+    fn timestamps_group_the_following_lines() {
+        let entries =
+            parse_history(b"ls\n#1700000000\nfor i in 1 2\ndo echo $i\ndone\n#1700000060\npwd\n");
+
+        assert_eq!(
+            command_lines(&entries),
+            ["ls", "for i in 1 2\ndo echo $i\ndone", "pwd"]
+        );
+        assert_eq!(entries[0].0, None);
+        assert_eq!(entries[2].0.map(|t| t.timestamp()), Some(1_700_000_060));
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let entries = parse_history(b"ls\n\xff\xfe\n#99999999999999999999\nls -a\n#1\n\n");
+
+        assert_eq!(command_lines(&entries), ["ls", "ls -a"]);
+        assert_eq!(entries[1].0, None);
+    }
+
+    fn item(command_line: &str, timestamp: Option<i64>) -> HistoryItem {
+        let mut item = HistoryItem::from_command_line(command_line);
+        item.start_timestamp = timestamp.map(|seconds| Utc.timestamp_opt(seconds, 0).unwrap());
+        item
+    }
+
+    #[test]
+    fn written_history_is_read_back() {
+        let entries = [
+            item("ls", Some(1_700_000_000)),
+            item("echo a\necho b", None),
+            item("pwd", Some(1_700_000_060)),
+        ];
+
+        let mut plain = vec![];
+        write_history(&mut plain, &entries, false).unwrap();
+        assert_eq!(String::from_utf8_lossy(&plain), "ls\necho a\necho b\npwd\n");
+
+        let mut timestamped = vec![];
+        write_history(&mut timestamped, &entries, true).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&timestamped),
+            "#1700000000\nls\n#1700000000\necho a\necho b\n#1700000060\npwd\n"
+        );
+        assert_eq!(
+            command_lines(&parse_history(&timestamped)),
+            ["ls", "echo a\necho b", "pwd"]
+        );
+    }
+
+    #[test]
+    fn import_applies_the_history_rules() {
+        use crate::{FileBackedHistory, History, HistoryDuplicates, SearchDirection, SearchQuery};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".bash_history");
+        std::fs::write(&path, "ls\ncd ~\nls\npwd\n#1700000000\nexit\n").unwrap();
+
+        let mut history = FileBackedHistory::new(3).with_duplicates(HistoryDuplicates::IgnoreAll);
+        assert_eq!(history.import_readline(&path).unwrap(), 5);
+
+        let exported = dir.path().join("exported");
+        history.export_readline(&exported, false).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&exported).unwrap(),
+            "ls\npwd\nexit\n"
+        );
+        assert_eq!(
+            history
+                .search(SearchQuery::everything(SearchDirection::Forward, None))
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use super::{
-    base::{CommandLineSearch, SearchDirection, SearchQuery},
+    base::{CommandLineMatcher, CommandLineSearch, SearchDirection, SearchQuery},
     History, HistoryItem, HistoryItemId, HistorySessionId,
 };
 use crate::{
@@ -39,6 +39,9 @@ fn deserialize_history_item(row: &rusqlite::Row) -> rusqlite::Result<HistoryItem
             .get::<&str, Option<i64>>("duration_ms")?
             .map(|e| Duration::from_millis(e as u64)),
         exit_status: row.get("exit_status")?,
+        cursor_position: row
+            .get::<&str, Option<i64>>("cursor_position")?
+            .map(|e| e as usize),
         more_info: x
             .map(|x| {
                 serde_json::from_str(&x).map_err(|e| {
@@ -60,8 +63,8 @@ impl History for SqliteBackedHistory {
             .db
             .prepare(
                 "insert into history
-                               (id,  start_timestamp,  command_line,  session_id,  hostname,  cwd,  duration_ms,  exit_status,  more_info)
-                        values (:id, :start_timestamp, :command_line, :session_id, :hostname, :cwd, :duration_ms, :exit_status, :more_info)
+                               (id,  start_timestamp,  command_line,  session_id,  hostname,  cwd,  duration_ms,  exit_status,  cursor_position,  more_info)
+                        values (:id, :start_timestamp, :command_line, :session_id, :hostname, :cwd, :duration_ms, :exit_status, :cursor_position, :more_info)
                     on conflict (history.id) do update set
                         start_timestamp = excluded.start_timestamp,
                         command_line = excluded.command_line,
@@ -70,6 +73,7 @@ impl History for SqliteBackedHistory {
                         cwd = excluded.cwd,
                         duration_ms = excluded.duration_ms,
                         exit_status = excluded.exit_status,
+                        cursor_position = excluded.cursor_position,
                         more_info = excluded.more_info
                     returning id",
             )
@@ -84,6 +88,7 @@ impl History for SqliteBackedHistory {
                     ":cwd": entry.cwd,
                     ":duration_ms": entry.duration.map(|e| e.as_millis() as i64),
                     ":exit_status": entry.exit_status,
+                    ":cursor_position": entry.cursor_position.map(|e| e as i64),
                     ":more_info": entry.more_info.as_ref().map(|e| serde_json::to_string(e).unwrap())
                 },
                 |row| row.get(0),
@@ -104,7 +109,16 @@ impl History for SqliteBackedHistory {
     }
 
     fn count(&self, query: SearchQuery) -> Result<i64> {
-        let (query, params) = self.construct_query(&query, "coalesce(count(*), 0)");
+        if let Some(matcher) = regex_matcher(&query)? {
+            // regular expressions are not supported by SQLite, count in memory instead
+            let results = self.search_unfiltered(&query)?;
+            let count = results
+                .iter()
+                .filter(|item| matcher.is_match(&item.command_line))
+                .count();
+            return Ok(count as i64);
+        }
+        let (query, params) = self.construct_query(&query, "coalesce(count(*), 0)", true);
         let params_borrow: Vec<(&str, &dyn ToSql)> = params.iter().map(|e| (e.0, &*e.1)).collect();
         let result: i64 = self
             .db
@@ -116,7 +130,16 @@ impl History for SqliteBackedHistory {
     }
 
     fn search(&self, query: SearchQuery) -> Result<Vec<HistoryItem>> {
-        let (query, params) = self.construct_query(&query, "*");
+        if let Some(matcher) = regex_matcher(&query)? {
+            // regular expressions are not supported by SQLite, filter in memory instead
+            let results = self.search_unfiltered(&query)?;
+            return Ok(results
+                .into_iter()
+                .filter(|item| matcher.is_match(&item.command_line))
+                .take(query.limit.map_or(usize::MAX, |limit| limit as usize))
+                .collect());
+        }
+        let (query, params) = self.construct_query(&query, "*", true);
         let params_borrow: Vec<(&str, &dyn ToSql)> = params.iter().map(|e| (e.0, &*e.1)).collect();
         let results: Vec<HistoryItem> = self
             .db
@@ -183,6 +206,24 @@ fn map_sqlite_err(err: rusqlite::Error) -> ReedlineError {
     )))
 }
 
+/// Matcher for the searches that can not be expressed as an SQL `where` clause
+fn regex_matcher(query: &SearchQuery) -> Result<Option<CommandLineMatcher<'_>>> {
+    match &query.filter.command_line {
+        Some(search @ CommandLineSearch::Regex(_)) => Ok(Some(CommandLineMatcher::new(
+            search,
+            query.filter.case_sensitive.unwrap_or(true),
+        )?)),
+        _ => Ok(None),
+    }
+}
+
+/// Escapes the wildcards of a `like` pattern, to be used with `escape '\'`
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
 type BoxedNamedParams<'a> = Vec<(&'static str, Box<dyn ToSql + 'a>)>;
 
 impl SqliteBackedHistory {
@@ -240,7 +281,8 @@ impl SqliteBackedHistory {
             cwd text,
             duration_ms integer,
             exit_status integer,
-            more_info text
+            more_info text,
+            cursor_position integer
         ) strict;
         create index if not exists idx_history_time on history(start_timestamp);
         create index if not exists idx_history_cwd on history(cwd); -- suboptimal for many hosts
@@ -251,13 +293,44 @@ impl SqliteBackedHistory {
         ",
         )
         .map_err(map_sqlite_err)?;
+        // Databases created before the cursor position was stored lack its column
+        let has_cursor_position: bool = db
+            .query_row(
+                "select count(*) > 0 from pragma_table_info('history') where name = 'cursor_position'",
+                params![],
+                |r| r.get(0),
+            )
+            .map_err(map_sqlite_err)?;
+        if !has_cursor_position {
+            db.execute(
+                "alter table history add column cursor_position integer",
+                params![],
+            )
+            .map_err(map_sqlite_err)?;
+        }
         Ok(SqliteBackedHistory { db, session: None })
     }
 
+    /// Runs the query without the limit, leaving the command line filtering to the caller
+    fn search_unfiltered(&self, query: &SearchQuery) -> Result<Vec<HistoryItem>> {
+        let (query, params) = self.construct_query(query, "*", false);
+        let params_borrow: Vec<(&str, &dyn ToSql)> = params.iter().map(|e| (e.0, &*e.1)).collect();
+        let results: Vec<HistoryItem> = self
+            .db
+            .prepare(&query)
+            .map_err(map_sqlite_err)?
+            .query_map(&params_borrow[..], deserialize_history_item)
+            .map_err(map_sqlite_err)?
+            .collect::<rusqlite::Result<Vec<HistoryItem>>>()
+            .map_err(map_sqlite_err)?;
+        Ok(results)
+    }
+
     fn construct_query<'a>(
         &self,
         query: &'a SearchQuery,
         select_expression: &str,
+        apply_limit: bool,
     ) -> (String, BoxedNamedParams<'a>) {
         // TODO: this whole function could be done with less allocs
         let (is_asc, asc) = match query.direction {
@@ -268,17 +341,17 @@ impl SqliteBackedHistory {
         let mut params: BoxedNamedParams = vec![];
         if let Some(start) = query.start_time {
             wheres.push(if is_asc {
-                "timestamp_start > :start_time"
+                "start_timestamp > :start_time"
             } else {
-                "timestamp_start < :start_time"
+                "start_timestamp < :start_time"
             });
             params.push((":start_time", Box::new(start.timestamp_millis())))
         }
         if let Some(end) = query.end_time {
             wheres.push(if is_asc {
-                ":end_time >= timestamp_start"
+                ":end_time >= start_timestamp"
             } else {
-                ":end_time <= timestamp_start"
+                ":end_time <= start_timestamp"
             });
             params.push((":end_time", Box::new(end.timestamp_millis())));
         }
@@ -298,7 +371,7 @@ impl SqliteBackedHistory {
             });
             params.push((":end_id", Box::new(end.0)));
         }
-        let limit = match query.limit {
+        let limit = match query.limit.filter(|_| apply_limit) {
             Some(l) => {
                 params.push((":limit", Box::new(l)));
                 "limit :limit"
@@ -306,14 +379,35 @@ impl SqliteBackedHistory {
             None => "",
         };
         if let Some(command_line) = &query.filter.command_line {
-            // TODO: escape %
             let command_line_like = match command_line {
-                CommandLineSearch::Exact(e) => e.to_string(),
-                CommandLineSearch::Prefix(prefix) => format!("{prefix}%"),
-                CommandLineSearch::Substring(cont) => format!("%{cont}%"),
+                CommandLineSearch::Exact(e) => Some(escape_like(e)),
+                CommandLineSearch::Prefix(prefix) => Some(format!("{}%", escape_like(prefix))),
+                CommandLineSearch::Substring(cont) => Some(format!("%{}%", escape_like(cont))),
+                // filtered in memory by the caller
+                CommandLineSearch::Regex(_) => None,
             };
-            wheres.push("command_line like :command_line");
-            params.push((":command_line", Box::new(command_line_like)));
+            if let Some(command_line_like) = command_line_like {
+                wheres.push("command_line like :command_line escape '\\'");
+                params.push((":command_line", Box::new(command_line_like)));
+            }
+            // `like` ignores the case of ASCII characters
+            if query.filter.case_sensitive == Some(true) {
+                match command_line {
+                    CommandLineSearch::Exact(e) => {
+                        wheres.push("command_line = :command_text");
+                        params.push((":command_text", Box::new(e)));
+                    }
+                    CommandLineSearch::Prefix(prefix) => {
+                        wheres.push("instr(command_line, :command_text) = 1");
+                        params.push((":command_text", Box::new(prefix)));
+                    }
+                    CommandLineSearch::Substring(cont) => {
+                        wheres.push("instr(command_line, :command_text) > 0");
+                        params.push((":command_text", Box::new(cont)));
+                    }
+                    CommandLineSearch::Regex(_) => {}
+                }
+            }
         }
 
         if let Some(str) = &query.filter.not_command_line {
@@ -348,11 +442,17 @@ impl SqliteBackedHistory {
         if wheres.is_empty() {
             wheres = "true".to_string();
         }
+        // entries saved with an explicit timestamp may not be in the order of their ids
+        let order = if query.start_time.is_some() || query.end_time.is_some() {
+            format!("start_timestamp {asc}, id {asc}")
+        } else {
+            format!("id {asc}")
+        };
         let query = format!(
             "select {select_expression} from history
         where
         {wheres}
-        order by id {asc} {limit}"
+        order by {order} {limit}"
         );
         (query, params)
     }
@@ -177,10 +177,11 @@
 //!
 //! ## Crate features
 //!
-//! - `clipboard`: Enable support to use the `SystemClipboard`. Enabling this feature will return a `SystemClipboard` instead of a local clipboard when calling `get_default_clipboard()`.
+//! - `system_clipboard`: Enable support to use the `SystemClipboard`. Enabling this feature makes cut and paste use the system clipboard instead of a local clipboard. Other clipboards can be set with `Reedline::with_clipboard()`.
 //! - `bashisms`: Enable support for special text sequences that recall components from the history. e.g. `!!` and `!$`. For use in shells like `bash` or [`nushell`](https://nushell.sh).
 //! - `sqlite`: Provides the `SqliteBackedHistory` to store richer information in the history. Statically links the required sqlite version.
 //! - `sqlite-dynlib`: Alternative to the feature `sqlite`. Will not statically link. Requires `sqlite >= 3.38` to link dynamically!
+//! - `file_completer`: Provides the `FileCompleter` to complete filesystem paths.
 //! - `external_printer`: **Experimental:** Thread-safe `ExternalPrinter` handle to print lines from concurrently running threads.
 //!
 //! ## Are we prompt yet? (Development status)
@@ -227,13 +228,21 @@
 // #![deny(warnings)]
 mod core_editor;
 pub use core_editor::Editor;
-pub use core_editor::LineBuffer;
+#[cfg(feature = "system_clipboard")]
+pub use core_editor::SystemClipboard;
+pub use core_editor::{
+    CharClass, Clipboard, ClipboardMode, LineBuffer, LocalClipboard, Osc52Clipboard,
+    DEFAULT_OSC52_MAX_SIZE,
+};
 
 mod enums;
-pub use enums::{EditCommand, ReedlineEvent, ReedlineRawEvent, Signal, UndoBehavior};
+pub use enums::{
+    EditCommand, EditCommandObserver, EventAction, EventHook, QuotedInsertMode, ReedlineEvent,
+    ReedlineRawEvent, SetBufferUndo, Signal, TextObject, TimedSignal, UndoBehavior,
+};
 
 mod painting;
-pub use painting::{Painter, StyledText};
+pub use painting::{ColorLevel, LineWrapMode, Painter, RenderPass, RenderPipeline, StyledText};
 
 mod engine;
 pub use engine::Reedline;
@@ -245,38 +254,48 @@ mod history;
 #[cfg(any(feature = "sqlite", feature = "sqlite-dynlib"))]
 pub use history::SqliteBackedHistory;
 pub use history::{
-    CommandLineSearch, FileBackedHistory, History, HistoryItem, HistoryItemId,
-    HistoryNavigationQuery, HistorySessionId, SearchDirection, SearchFilter, SearchQuery,
-    HISTORY_SIZE,
+    CommandLineSearch, EvictionPolicy, FileBackedHistory, History, HistoryDuplicates, HistoryItem,
+    HistoryItemId, HistoryNavigationQuery, HistorySessionId, SearchDirection, SearchFilter,
+    SearchQuery, HISTORY_SIZE,
 };
 
 mod prompt;
 pub use prompt::{
-    DefaultPrompt, DefaultPromptSegment, Prompt, PromptEditMode, PromptHistorySearch,
-    PromptHistorySearchStatus, PromptViMode,
+    CommandStatus, DefaultPrompt, DefaultPromptSegment, HistorySearchIndicator, Prompt,
+    PromptEditMode, PromptHistorySearch, PromptHistorySearchStatus, PromptViMode,
 };
 
 mod edit_mode;
 pub use edit_mode::{
-    default_emacs_keybindings, default_vi_insert_keybindings, default_vi_normal_keybindings,
-    CursorConfig, EditMode, Emacs, Keybindings, Vi,
+    default_emacs_keybindings, default_history_search_keybindings, default_vi_insert_keybindings,
+    default_vi_normal_keybindings, CursorConfig, EditMode, Emacs, KeyCombination,
+    KeySequenceFallback, Keybindings, Vi,
 };
 
 mod highlighter;
-pub use highlighter::{ExampleHighlighter, Highlighter, SimpleMatchHighlighter};
+pub use highlighter::{
+    BracketMatchHighlighter, BufferLines, CompositeHighlighter, ExampleHighlighter, Highlighter,
+    SimpleMatchHighlighter, WhitespaceHighlighter,
+};
 
 mod completion;
-pub use completion::{Completer, DefaultCompleter, Span, Suggestion};
+#[cfg(feature = "file_completer")]
+pub use completion::FileCompleter;
+pub use completion::{
+    Append, BackgroundCompleter, Completer, DefaultCompleter, IncrementalCompleter, Snippet, Span,
+    StreamingCompleter, Suggestion, SuggestionSender,
+};
 
 mod hinter;
-pub use hinter::{DefaultHinter, Hinter};
+pub use hinter::{CompleterHinter, DefaultHinter, HintSource, Hinter};
 
 mod validator;
-pub use validator::{DefaultValidator, ValidationResult, Validator};
+pub use validator::{BalanceValidator, DefaultValidator, ValidationResult, Validator};
 
 mod menu;
 pub use menu::{
-    menu_functions, ColumnarMenu, ListMenu, Menu, MenuEvent, MenuTextStyle, ReedlineMenu,
+    menu_functions, ColumnarMenu, ListMenu, Menu, MenuEvent, MenuTabBehavior, MenuTextStyle,
+    ReedlineMenu,
 };
 
 mod utils;
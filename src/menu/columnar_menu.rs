@@ -1,9 +1,25 @@
-use super::{menu_functions::find_common_string, Menu, MenuEvent, MenuTextStyle};
+use super::{
+    menu_functions::{
+        complete_common_prefix, find_match, find_quoted_token, insert_snippet, pad_to_width,
+        quote_aware_replacement, replace_in_buffer, truncate_to_width, wrap_exact, wrap_text,
+        Alignment, QuoteHandling, QuotedToken, SortOrder,
+    },
+    Menu, MenuEvent, MenuTextStyle,
+};
 use crate::{
-    core_editor::Editor, menu_functions::string_difference, painting::Painter, Completer,
-    Suggestion, UndoBehavior,
+    core_editor::Editor,
+    menu_functions::string_difference,
+    painting::{
+        expand_tab_stops, keep_first_columns, line_width, restyle_after_escapes, strip_ansi,
+        Painter,
+    },
+    Append, Completer, Suggestion,
 };
 use nu_ansi_term::{ansi::RESET, Style};
+use std::{borrow::Cow, cell::Cell};
+
+/// Rows of values kept when the menu doesn't fit in the available lines
+const DEFAULT_MIN_ROWS: u16 = 3;
 
 /// Default values used as reference for the menu. These values are set during
 /// the initial declaration of the menu and are always kept as reference for the
@@ -13,6 +29,9 @@ struct DefaultColumnDetails {
     pub columns: u16,
     /// Column width
     pub col_width: Option<usize>,
+    /// Width for each individual column. Columns beyond the end of the list
+    /// use the last entry. Empty when the uniform `col_width` is used
+    pub col_widths: Vec<usize>,
     /// Column padding
     pub col_padding: usize,
 }
@@ -22,6 +41,7 @@ impl Default for DefaultColumnDetails {
         Self {
             columns: 4,
             col_width: None,
+            col_widths: Vec::new(),
             col_padding: 2,
         }
     }
@@ -35,6 +55,10 @@ struct ColumnDetails {
     pub columns: u16,
     /// Column width
     pub col_width: usize,
+    /// Width of every column when per-column widths are configured
+    pub col_widths: Vec<usize>,
+    /// Width of the documentation panel
+    pub documentation_width: usize,
 }
 
 /// Menu to present suggestions in a columnar fashion
@@ -49,13 +73,20 @@ pub struct ColumnarMenu {
     /// Default column details that are set when creating the menu
     /// These values are the reference for the working details
     default_details: DefaultColumnDetails,
-    /// Number of minimum rows that are displayed when
-    /// the required lines is larger than the available lines
-    min_rows: u16,
+    /// Number of rows reserved for the menu, even with fewer values
+    /// Without it up to `DEFAULT_MIN_ROWS` rows of values are kept when the
+    /// required lines is larger than the available lines
+    min_rows: Option<u16>,
+    /// Maximum number of rows used by the values, the rest is reached by scrolling
+    max_rows: Option<u16>,
     /// Working column details keep changing based on the collected values
     working_details: ColumnDetails,
     /// Menu cached values
     values: Vec<Suggestion>,
+    /// Maximum number of values kept from the completer
+    max_suggestions: Option<usize>,
+    /// Values dropped from the end of the sorted values to keep the maximum
+    hidden_values: Vec<Suggestion>,
     /// column position of the cursor. Starts from 0
     col_pos: u16,
     /// row position in the menu. Starts from 0
@@ -68,9 +99,66 @@ pub struct ColumnarMenu {
     longest_suggestion: usize,
     /// String collected after the menu is activated
     input: Option<String>,
+    /// Line given to the completer, used to find the typed text of each value
+    completed_line: String,
     /// Calls the completer using only the line buffer difference difference
     /// after the menu was activated
     only_buffer_difference: bool,
+    /// How the completion is inserted when the token under the cursor is quoted
+    quote_handling: QuoteHandling,
+    /// Shows the documentation of the selected value below the menu
+    show_documentation: bool,
+    /// Maximum number of documentation lines shown at once
+    documentation_rows: u16,
+    /// First documentation line shown in the documentation panel
+    documentation_offset: usize,
+    /// Shows the description of the selected value in the documentation panel
+    /// instead of next to the values
+    description_preview: bool,
+    /// Adds the extra lines of the selected value to the description preview
+    preview_extra: bool,
+    /// Shows the whole selected value in the documentation panel
+    value_preview: bool,
+    /// Index of the `extra` element used as score to sort the values
+    sort_by_extra_score: Option<usize>,
+    /// Order of the values returned by the completer
+    sort_order: SortOrder,
+    /// Sorts the values descending by their score
+    score_sort: bool,
+    /// Treats the descriptions of the values as text that carries its own ANSI styling
+    styled_descriptions: bool,
+    /// Tab stops used to expand the tabs found in the values
+    tab_width: usize,
+    /// Wraps the descriptions onto several lines instead of truncating them
+    description_wrap: bool,
+    /// Message shown when there are no values
+    no_records_msg: String,
+    /// Style of the message shown when there are no values
+    /// The selected text style is used when it is not set
+    no_records_style: Option<Style>,
+    /// Message shown while the completer is still computing the values
+    loading_msg: String,
+    /// The completer had not finished computing the values at the last update
+    loading: bool,
+    /// Values were appended since the last layout, they don't have to be queried
+    values_appended: bool,
+    /// Number of value rows printed by the last call to `menu_string`
+    /// It is the size of a page when moving the cursor by pages
+    visible_rows: Cell<u16>,
+    /// Number of values skipped by the last call to `menu_string`
+    skipped_values: Cell<usize>,
+    /// Shows the position of the selected value when not all values fit
+    position_indicator: bool,
+    /// Moving past the first or last value continues at the other end of the menu
+    wrap_around: bool,
+    /// A single value is inserted without showing the menu
+    quick_complete: bool,
+    /// Minimum width of a single value to be inserted without showing the menu
+    quick_complete_min_width: usize,
+    /// Alignment of the values in their cells
+    value_alignment: Alignment,
+    /// Alignment of the descriptions next to the values
+    description_alignment: Alignment,
 }
 
 impl Default for ColumnarMenu {
@@ -80,16 +168,46 @@ impl Default for ColumnarMenu {
             active: false,
             color: MenuTextStyle::default(),
             default_details: DefaultColumnDetails::default(),
-            min_rows: 3,
+            min_rows: None,
+            max_rows: None,
             working_details: ColumnDetails::default(),
             values: Vec::new(),
+            max_suggestions: None,
+            hidden_values: Vec::new(),
             col_pos: 0,
             row_pos: 0,
             marker: "| ".to_string(),
             event: None,
             longest_suggestion: 0,
             input: None,
+            completed_line: String::new(),
             only_buffer_difference: false,
+            quote_handling: QuoteHandling::default(),
+            show_documentation: false,
+            documentation_rows: 5,
+            documentation_offset: 0,
+            description_preview: false,
+            preview_extra: false,
+            value_preview: false,
+            sort_by_extra_score: None,
+            sort_order: SortOrder::None,
+            score_sort: false,
+            styled_descriptions: false,
+            tab_width: 1,
+            description_wrap: false,
+            no_records_msg: "NO RECORDS FOUND".to_string(),
+            no_records_style: None,
+            loading_msg: "LOADING...".to_string(),
+            loading: false,
+            values_appended: false,
+            visible_rows: Cell::new(0),
+            skipped_values: Cell::new(0),
+            position_indicator: false,
+            wrap_around: true,
+            quick_complete: true,
+            quick_complete_min_width: 0,
+            value_alignment: Alignment::Left,
+            description_alignment: Alignment::Left,
         }
     }
 }
@@ -117,6 +235,17 @@ impl ColumnarMenu {
         self
     }
 
+    /// Menu builder with the style of the part of the values that matches the typed text
+    ///
+    /// The typed text is the part of the buffer replaced by the value. It is looked
+    /// for in the value, ignoring the case if needed. Values without such a match
+    /// are printed with the normal text style
+    #[must_use]
+    pub fn with_match_text_style(mut self, match_text_style: Style) -> Self {
+        self.color.match_style = Some(match_text_style);
+        self
+    }
+
     /// Menu builder with new value for text style
     #[must_use]
     pub fn with_description_text_style(mut self, description_text_style: Style) -> Self {
@@ -124,6 +253,95 @@ impl ColumnarMenu {
         self
     }
 
+    /// Menu builder that treats [`Suggestion::description`] as pre-styled text
+    ///
+    /// Descriptions are expected to contain their own ANSI escape sequences, so they
+    /// are printed without the description text style. The selected text style is
+    /// applied on top of them when their value is selected. Their width is measured
+    /// ignoring the escape sequences and they are truncated on a visible character,
+    /// keeping every escape sequence intact. Without ANSI coloring the escape
+    /// sequences are removed.
+    #[must_use]
+    pub fn with_styled_descriptions(mut self, styled_descriptions: bool) -> Self {
+        self.styled_descriptions = styled_descriptions;
+        self
+    }
+
+    /// Menu builder that wraps long descriptions instead of truncating them
+    ///
+    /// The description is broken at whitespace, or inside words longer than the
+    /// available width, and continues on the following lines indented past the
+    /// values. The selected value is highlighted on all its lines. Descriptions set
+    /// as pre-styled with [`ColumnarMenu::with_styled_descriptions`] are still
+    /// truncated.
+    #[must_use]
+    pub fn with_description_wrap(mut self, description_wrap: bool) -> Self {
+        self.description_wrap = description_wrap;
+        self
+    }
+
+    /// Menu builder with the alignment of the values in their cells
+    ///
+    /// Right aligned values end at the widest value of their column, like the numbers
+    /// of a table. The highlight of the selected value covers the padding before it.
+    #[must_use]
+    pub fn with_value_alignment(mut self, value_alignment: Alignment) -> Self {
+        self.value_alignment = value_alignment;
+        self
+    }
+
+    /// Menu builder with the alignment of the descriptions shown next to the values
+    ///
+    /// Right aligned descriptions end at the right edge of the menu, every line of a
+    /// wrapped description on its own
+    #[must_use]
+    pub fn with_description_alignment(mut self, description_alignment: Alignment) -> Self {
+        self.description_alignment = description_alignment;
+        self
+    }
+
+    /// Menu builder with the tab stops used to print values that contain tabs
+    ///
+    /// Tabs are expanded with whitespace up to the next multiple of `tab_width`
+    /// columns so the column layout stays aligned. The default of 1 replaces every tab
+    /// with a single space, the width given to
+    /// [`Reedline::with_tab_width`](crate::Reedline::with_tab_width) lines the values
+    /// up with the buffer. The value inserted in the buffer keeps its tabs.
+    #[must_use]
+    pub fn with_tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width.max(1);
+        self
+    }
+
+    /// Menu builder with the message shown when the completer returns no values
+    ///
+    /// Defaults to `NO RECORDS FOUND`
+    #[must_use]
+    pub fn with_no_records_msg(mut self, no_records_msg: String) -> Self {
+        self.no_records_msg = no_records_msg;
+        self
+    }
+
+    /// Menu builder with the message shown while the completer computes the values
+    /// in the background, see [`Completer::is_loading`]
+    ///
+    /// Defaults to `LOADING...`
+    #[must_use]
+    pub fn with_loading_msg(mut self, loading_msg: String) -> Self {
+        self.loading_msg = loading_msg;
+        self
+    }
+
+    /// Menu builder with the style of the message shown when there are no values
+    ///
+    /// The style is only applied with ANSI coloring. When it isn't set, the message
+    /// uses the selected text style
+    #[must_use]
+    pub fn with_no_records_text_style(mut self, no_records_text_style: Style) -> Self {
+        self.no_records_style = Some(no_records_text_style);
+        self
+    }
+
     /// Menu builder with new columns value
     #[must_use]
     pub fn with_columns(mut self, columns: u16) -> Self {
@@ -138,6 +356,21 @@ impl ColumnarMenu {
         self
     }
 
+    /// Menu builder with a width for each column
+    ///
+    /// The i-th entry sets the width of the i-th column; columns beyond the end of
+    /// the list use the last entry. A column is still widened when one of its values
+    /// (plus the column padding) does not fit, and columns are dropped while the
+    /// total width exceeds the screen width.
+    /// These widths take precedence over [`ColumnarMenu::with_column_width`]. An
+    /// empty list restores the uniform column width behavior. When suggestions
+    /// have descriptions the menu uses a single column and these widths are ignored.
+    #[must_use]
+    pub fn with_column_widths(mut self, col_widths: Vec<usize>) -> Self {
+        self.default_details.col_widths = col_widths;
+        self
+    }
+
     /// Menu builder with new column width value
     #[must_use]
     pub fn with_column_padding(mut self, col_padding: usize) -> Self {
@@ -158,12 +391,197 @@ impl ColumnarMenu {
         self.only_buffer_difference = only_buffer_difference;
         self
     }
+
+    /// Menu builder that shows the documentation of the selected value
+    ///
+    /// The [`Suggestion::documentation`] is word wrapped in a bordered panel printed
+    /// below the values. The panel is part of the lines required by the menu and is
+    /// left out when the selected value has no documentation or when the screen has
+    /// no space for it. It can be toggled with
+    /// [`ReedlineEvent::MenuToggleDocumentation`](crate::ReedlineEvent::MenuToggleDocumentation)
+    /// and scrolled with [`ReedlineEvent::MenuDocumentationUp`](crate::ReedlineEvent::MenuDocumentationUp)
+    /// and [`ReedlineEvent::MenuDocumentationDown`](crate::ReedlineEvent::MenuDocumentationDown)
+    #[must_use]
+    pub fn with_documentation(mut self, show_documentation: bool) -> Self {
+        self.show_documentation = show_documentation;
+        self
+    }
+
+    /// Menu builder that previews the description of the selected value below the menu
+    ///
+    /// The values keep their columnar layout without descriptions next to them. The
+    /// [`Suggestion::description`] of the selected value is word wrapped in the bordered
+    /// panel used for the documentation, before the documentation if it is shown, and
+    /// follows the selection. The panel shows up to
+    /// [`ColumnarMenu::with_documentation_rows`] lines at once.
+    #[must_use]
+    pub fn with_description_preview(mut self, description_preview: bool) -> Self {
+        self.description_preview = description_preview;
+        self
+    }
+
+    /// Menu builder that shows the [`Suggestion::extra`] lines of the selected value
+    /// below the menu
+    ///
+    /// The lines are word wrapped in the bordered panel used for the documentation,
+    /// after the description if it is previewed, and the rows of the panel are part of
+    /// the lines required by the menu. Values without extra lines show no panel for them.
+    #[must_use]
+    pub fn with_preview_extra(mut self, preview_extra: bool) -> Self {
+        self.preview_extra = preview_extra;
+        self
+    }
+
+    /// Menu builder that previews the whole selected value below the menu
+    ///
+    /// Values wider than their column, e.g. long pipelines when the menu is used as a
+    /// history menu, are hard to read in the list. The preview shows the selected value
+    /// in full, wrapped to the bordered panel used for the documentation with its
+    /// whitespace kept, before the description preview and the documentation. It
+    /// follows the selection and shows up to [`ColumnarMenu::with_documentation_rows`]
+    /// lines at once.
+    #[must_use]
+    pub fn with_value_preview(mut self, value_preview: bool) -> Self {
+        self.value_preview = value_preview;
+        self
+    }
+
+    /// Menu builder with the maximum number of values kept from the completer
+    ///
+    /// The values past the maximum are dropped after sorting, and a
+    /// `(+N more, refine your query)` line below the values tells how many were left
+    /// out. Partial completions still use the prefix common to all values. Defaults
+    /// to no limit
+    #[must_use]
+    pub fn with_max_suggestions(mut self, max_suggestions: usize) -> Self {
+        self.max_suggestions = Some(max_suggestions.max(1));
+        self
+    }
+
+    /// Menu builder that shows a `12/340` counter line with the position of the
+    /// selected value when the values don't fit in the available lines
+    ///
+    /// The counter takes one of the lines available to the values and uses the
+    /// description style.
+    #[must_use]
+    pub fn with_position_indicator(mut self, position_indicator: bool) -> Self {
+        self.position_indicator = position_indicator;
+        self
+    }
+
+    /// Menu builder with the behavior at the edges of the menu
+    ///
+    /// With `true` (the default) moving past the first or last value, row or column
+    /// continues at the other end. With `false` the cursor stops at the edge instead.
+    #[must_use]
+    pub fn with_wrap_around(mut self, wrap_around: bool) -> Self {
+        self.wrap_around = wrap_around;
+        self
+    }
+
+    /// Menu builder that inserts a single value without showing the menu, when
+    /// [`Reedline::with_quick_completions`](crate::Reedline::with_quick_completions)
+    /// is enabled
+    ///
+    /// It is enabled by default. With `false` the menu is always shown first.
+    #[must_use]
+    pub fn with_quick_complete(mut self, quick_complete: bool) -> Self {
+        self.quick_complete = quick_complete;
+        self
+    }
+
+    /// Menu builder with the minimum width a single value needs to be inserted without
+    /// showing the menu
+    ///
+    /// Shorter values are shown in the menu, so a short match isn't inserted by surprise
+    #[must_use]
+    pub fn with_quick_complete_min_width(mut self, min_width: usize) -> Self {
+        self.quick_complete_min_width = min_width;
+        self
+    }
+
+    /// Menu builder with the number of rows always reserved for the menu
+    ///
+    /// The menu takes at least these rows, even when it has fewer values, so the
+    /// prompt doesn't move while the values change. Without it the menu takes the
+    /// rows of its values and keeps at most 3 of them when the screen is full.
+    #[must_use]
+    pub fn with_min_rows(mut self, min_rows: u16) -> Self {
+        self.min_rows = Some(min_rows);
+        self
+    }
+
+    /// Menu builder with the maximum number of rows used by the values
+    ///
+    /// The values past the last row are reached by moving the selection, which
+    /// scrolls the menu. With `None` (the default) the menu grows to fit its values
+    /// in the available lines.
+    #[must_use]
+    pub fn with_max_rows(mut self, max_rows: Option<u16>) -> Self {
+        self.max_rows = max_rows.map(|max_rows| max_rows.max(1));
+        self
+    }
+
+    /// Menu builder with the maximum number of documentation lines shown at once,
+    /// without counting the borders of the panel
+    #[must_use]
+    pub fn with_documentation_rows(mut self, documentation_rows: u16) -> Self {
+        self.documentation_rows = documentation_rows.max(1);
+        self
+    }
+
+    /// Menu builder that sorts the values by a score found in [`Suggestion::extra`]
+    ///
+    /// The element at `index` is parsed as a number and the values are sorted from the
+    /// highest to the lowest score. Values with a missing or non numeric score are
+    /// placed last. Values with the same score keep the order of the completer.
+    #[must_use]
+    pub fn with_sort_by_extra_score(mut self, index: usize) -> Self {
+        self.sort_by_extra_score = Some(index);
+        self
+    }
+
+    /// Menu builder with the order of the values returned by the completer
+    ///
+    /// With [`SortOrder::None`] (the default) the values keep the order of the
+    /// completer. When combined with [`ColumnarMenu::with_sort_by_extra_score`] the
+    /// values are sorted by score and values with the same score follow this order
+    #[must_use]
+    pub fn with_sort(mut self, sort_order: SortOrder) -> Self {
+        self.sort_order = sort_order;
+        self
+    }
+
+    /// Menu builder that sorts the values descending by [`Suggestion::score`]
+    ///
+    /// Values without a score are placed after the scored ones, keeping their order.
+    /// The score sort runs after [`ColumnarMenu::with_sort`] and before
+    /// [`ColumnarMenu::with_sort_by_extra_score`]
+    #[must_use]
+    pub fn with_score_sort(mut self, score_sort: bool) -> Self {
+        self.score_sort = score_sort;
+        self
+    }
+
+    /// Menu builder with the handling of quotes around the token being completed
+    ///
+    /// With [`QuoteHandling::Ignore`] (the default) the span returned by the
+    /// completer is replaced as is
+    #[must_use]
+    pub fn with_quote_handling(mut self, quote_handling: QuoteHandling) -> Self {
+        self.quote_handling = quote_handling;
+        self
+    }
 }
 
 // Menu functionality
 impl ColumnarMenu {
     /// Move menu cursor to the next element
     fn move_next(&mut self) {
+        if !self.wrap_around && self.index() + 1 >= self.get_values().len() {
+            return;
+        }
+
         let mut new_col = self.col_pos + 1;
         let mut new_row = self.row_pos;
 
@@ -188,6 +606,10 @@ impl ColumnarMenu {
 
     /// Move menu cursor to the previous element
     fn move_previous(&mut self) {
+        if !self.wrap_around && self.index() == 0 {
+            return;
+        }
+
         let new_col = self.col_pos.checked_sub(1);
 
         let (new_col, new_row) = match new_col {
@@ -215,6 +637,8 @@ impl ColumnarMenu {
     fn move_up(&mut self) {
         self.row_pos = if let Some(new_row) = self.row_pos.checked_sub(1) {
             new_row
+        } else if !self.wrap_around {
+            self.row_pos
         } else {
             let new_row = self.get_rows().saturating_sub(1);
             let index = new_row * self.get_cols() + self.col_pos;
@@ -226,18 +650,16 @@ impl ColumnarMenu {
         }
     }
 
-    /// Move menu cursor left
+    /// Move menu cursor down
     fn move_down(&mut self) {
         let new_row = self.row_pos + 1;
-        self.row_pos = if new_row >= self.get_rows() {
+        let index = new_row * self.get_cols() + self.col_pos;
+        self.row_pos = if new_row < self.get_rows() && index < self.values.len() as u16 {
+            new_row
+        } else if self.wrap_around {
             0
         } else {
-            let index = new_row * self.get_cols() + self.col_pos;
-            if index >= self.values.len() as u16 {
-                0
-            } else {
-                new_row
-            }
+            self.row_pos
         }
     }
 
@@ -245,7 +667,7 @@ impl ColumnarMenu {
     fn move_left(&mut self) {
         self.col_pos = if let Some(row) = self.col_pos.checked_sub(1) {
             row
-        } else if self.index() + 1 == self.values.len() {
+        } else if !self.wrap_around || self.index() + 1 == self.values.len() {
             0
         } else {
             self.get_cols().saturating_sub(1)
@@ -255,13 +677,33 @@ impl ColumnarMenu {
     /// Move menu cursor element
     fn move_right(&mut self) {
         let new_col = self.col_pos + 1;
-        self.col_pos = if new_col >= self.get_cols() || self.index() + 2 > self.values.len() {
+        self.col_pos = if new_col < self.get_cols() && self.index() + 2 <= self.values.len() {
+            new_col
+        } else if self.wrap_around {
             0
         } else {
-            new_col
+            self.col_pos
+        }
+    }
+
+    /// Move menu cursor one page down, stopping at the last value
+    fn move_next_page(&mut self) {
+        let page = self.visible_rows.get().max(1);
+        let last_index = self.get_values().len().saturating_sub(1) as u16;
+        let last_row = last_index / self.get_cols();
+
+        self.row_pos = (self.row_pos + page).min(last_row);
+        if self.index() > last_index as usize {
+            self.col_pos = last_index % self.get_cols();
         }
     }
 
+    /// Move menu cursor one page up, stopping at the first row
+    fn move_previous_page(&mut self) {
+        let page = self.visible_rows.get().max(1);
+        self.row_pos = self.row_pos.saturating_sub(page);
+    }
+
     /// Menu index based on column and row position
     fn index(&self) -> usize {
         let index = self.row_pos * self.get_cols() + self.col_pos;
@@ -295,6 +737,198 @@ impl ColumnarMenu {
         self.working_details.col_width
     }
 
+    /// Returns the working width of the given column
+    fn get_column_width(&self, column: u16) -> usize {
+        self.working_details
+            .col_widths
+            .get(column as usize)
+            .copied()
+            .unwrap_or_else(|| self.get_width())
+    }
+
+    /// Width of every column for the given number of columns, using the configured
+    /// per-column widths widened to fit the values found in each column
+    fn column_widths(&self, columns: u16) -> Vec<usize> {
+        let columns = columns.max(1) as usize;
+        let last = self.default_details.col_widths.len().saturating_sub(1);
+
+        let mut widths: Vec<usize> = (0..columns)
+            .map(|column| self.default_details.col_widths[column.min(last)])
+            .collect();
+
+        for (index, suggestion) in self.get_values().iter().enumerate() {
+            let width = &mut widths[index % columns];
+            *width = (*width).max(
+                line_width(&self.display_value(&suggestion.value))
+                    + self.default_details.col_padding,
+            );
+        }
+
+        widths
+    }
+
+    /// Description of a value printed next to it, unless it is previewed in the panel
+    fn inline_description<'a>(&self, suggestion: &'a Suggestion) -> Option<&'a String> {
+        if self.description_preview {
+            None
+        } else {
+            suggestion.description.as_ref()
+        }
+    }
+
+    /// Previews and documentation of the selected value wrapped to the documentation panel
+    fn documentation_lines(&self) -> Vec<String> {
+        let suggestion = match self.get_values().get(self.index()) {
+            Some(suggestion) => suggestion,
+            None => return Vec::new(),
+        };
+        let width = self.documentation_text_width();
+
+        let mut sections: Vec<Vec<String>> = Vec::new();
+        if self.value_preview {
+            sections.push(wrap_exact(&self.display_value(&suggestion.value), width));
+        }
+        // The extra lines follow the description in the same section
+        let mut preview: Vec<String> = Vec::new();
+        if self.description_preview {
+            preview.extend(
+                suggestion
+                    .description
+                    .iter()
+                    .flat_map(|description| wrap_text(description, width)),
+            );
+        }
+        if self.preview_extra {
+            preview.extend(
+                suggestion
+                    .extra
+                    .iter()
+                    .flatten()
+                    .flat_map(|extra| wrap_text(extra, width)),
+            );
+        }
+        sections.push(preview);
+        if self.show_documentation {
+            if let Some(documentation) = &suggestion.documentation {
+                sections.push(wrap_text(documentation, width));
+            }
+        }
+
+        // The sections are separated by an empty line
+        sections.retain(|lines| !lines.is_empty());
+        sections.join(&String::new())
+    }
+
+    /// Width available for the documentation text inside the panel borders
+    fn documentation_text_width(&self) -> usize {
+        self.working_details
+            .documentation_width
+            .saturating_sub(4)
+            .max(1)
+    }
+
+    /// Lines used by the documentation panel, including its borders
+    fn documentation_panel_rows(&self) -> u16 {
+        match self.documentation_lines().len() {
+            0 => 0,
+            lines => lines.min(self.documentation_rows as usize) as u16 + 2,
+        }
+    }
+
+    /// Creates the bordered panel with the visible documentation lines
+    fn documentation_panel(&self, use_ansi_coloring: bool) -> String {
+        let width = self.documentation_text_width();
+        let border = "─".repeat(width + 2);
+        let (prefix, suffix) = if use_ansi_coloring {
+            (self.color.description_style.prefix().to_string(), RESET)
+        } else {
+            (String::new(), "")
+        };
+
+        let lines: String = self
+            .documentation_lines()
+            .iter()
+            .skip(self.documentation_offset)
+            .take(self.documentation_rows as usize)
+            .map(|line| {
+                format!(
+                    "│ {prefix}{line}{suffix}{:pad$} │\r\n",
+                    "",
+                    pad = width.saturating_sub(line_width(line))
+                )
+            })
+            .collect();
+
+        format!("┌{border}┐\r\n{lines}└{border}┘\r\n")
+    }
+
+    /// Line showing the position of the selected value among all values
+    fn position_indicator_line(&self, use_ansi_coloring: bool) -> String {
+        let position = format!("{}/{}", self.index() + 1, self.get_values().len());
+        if use_ansi_coloring {
+            format!(
+                "{}{position}{RESET}\r\n",
+                self.color.description_style.prefix()
+            )
+        } else {
+            format!("{position}\r\n")
+        }
+    }
+
+    /// Line telling how many values were left out by the maximum number of values
+    fn hidden_values_line(&self, use_ansi_coloring: bool) -> String {
+        let hidden = format!("(+{} more, refine your query)", self.hidden_values.len());
+        if use_ansi_coloring {
+            format!(
+                "{}{hidden}{RESET}\r\n",
+                self.color.description_style.prefix()
+            )
+        } else {
+            format!("{hidden}\r\n")
+        }
+    }
+
+    /// Drops the values past the maximum number of values, keeping them aside for
+    /// partial completions
+    fn truncate_values(&mut self) {
+        self.hidden_values = match self.max_suggestions {
+            Some(max_suggestions) if self.values.len() > max_suggestions => {
+                self.values.split_off(max_suggestions)
+            }
+            _ => Vec::new(),
+        };
+    }
+
+    /// Sorts the values by their value and score if it was requested
+    fn sort_values(&mut self) {
+        self.sort_order.sort(&mut self.values);
+
+        if self.score_sort {
+            // `sort_by_key` is stable, so values with the same score keep their order
+            self.values
+                .sort_by_key(|suggestion| std::cmp::Reverse(suggestion.score));
+        }
+
+        if let Some(index) = self.sort_by_extra_score {
+            let score = |suggestion: &Suggestion| {
+                suggestion
+                    .extra
+                    .as_ref()
+                    .and_then(|extra| extra.get(index))
+                    .and_then(|score| score.trim().parse::<f64>().ok())
+                    .filter(|score| !score.is_nan())
+            };
+
+            // `sort_by` is stable, so values with the same score keep their order
+            self.values.sort_by(|a, b| match (score(a), score(b)) {
+                (Some(a), Some(b)) => b.partial_cmp(&a).unwrap_or(std::cmp::Ordering::Equal),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            });
+        }
+    }
+
     /// Reset menu position
     fn reset_position(&mut self) {
         self.col_pos = 0;
@@ -302,16 +936,19 @@ impl ColumnarMenu {
     }
 
     fn no_records_msg(&self, use_ansi_coloring: bool) -> String {
-        let msg = "NO RECORDS FOUND";
+        let msg = if self.loading {
+            &self.loading_msg
+        } else {
+            &self.no_records_msg
+        };
+
         if use_ansi_coloring {
-            format!(
-                "{}{}{}",
-                self.color.selected_text_style.prefix(),
-                msg,
-                RESET
-            )
+            let style = self
+                .no_records_style
+                .unwrap_or(self.color.selected_text_style);
+            format!("{}{}{}", style.prefix(), msg, RESET)
         } else {
-            msg.to_string()
+            msg.clone()
         }
     }
 
@@ -329,6 +966,157 @@ impl ColumnarMenu {
         }
     }
 
+    /// Value as printed in the menu, with its tabs expanded
+    fn display_value<'value>(&self, value: &'value str) -> Cow<'value, str> {
+        expand_tab_stops(value, self.tab_width, usize::MAX, &mut 0)
+    }
+
+    /// Text printed before a description to style it
+    fn description_prefix(&self) -> String {
+        if self.styled_descriptions {
+            String::new()
+        } else {
+            self.color.description_style.prefix().to_string()
+        }
+    }
+
+    /// Single line description truncated to `width` visible characters
+    fn description_text(&self, description: &str, width: usize, use_ansi_coloring: bool) -> String {
+        let description = description.replace('\n', " ");
+        if !self.styled_descriptions {
+            truncate_to_width(&description, width).to_string()
+        } else if use_ansi_coloring {
+            keep_first_columns(&description, width)
+        } else {
+            keep_first_columns(&strip_ansi(&description), width)
+        }
+    }
+
+    /// Value with the part matching the typed text highlighted. The `base` style is
+    /// restored after the match
+    fn highlight_match(&self, value: &str, suggestion: &Suggestion, base: Style) -> String {
+        let range = self.color.match_style.and_then(|match_style| {
+            let typed = self
+                .completed_line
+                .get(suggestion.span.start..suggestion.span.end)?;
+            find_match(value, typed).map(|range| (match_style, range))
+        });
+
+        match range {
+            Some((match_style, range)) => format!(
+                "{}{}{}{}{}{}",
+                &value[..range.start],
+                match_style.prefix(),
+                &value[range.clone()],
+                RESET,
+                base.prefix(),
+                &value[range.end..],
+            ),
+            None => value.to_string(),
+        }
+    }
+
+    /// Description lines printed next to a value, wrapped to `width` columns when
+    /// the description wrap is enabled. There is always at least one line
+    fn description_lines(
+        &self,
+        description: &str,
+        width: usize,
+        use_ansi_coloring: bool,
+    ) -> Vec<String> {
+        let lines = if self.description_wrap && !self.styled_descriptions {
+            let lines = wrap_text(description, width);
+            if lines.is_empty() {
+                vec![String::new()]
+            } else {
+                lines
+            }
+        } else {
+            vec![self.description_text(description, width, use_ansi_coloring)]
+        };
+
+        match self.description_alignment {
+            Alignment::Left => lines,
+            alignment => lines
+                .into_iter()
+                .map(|line| {
+                    let padding = alignment.padding(line_width(&line), width);
+                    format!("{:padding$}{line}", "")
+                })
+                .collect(),
+        }
+    }
+
+    /// Width left for the descriptions next to the values
+    fn description_width(&self) -> usize {
+        self.get_width()
+            .saturating_sub(self.longest_suggestion + self.default_details.col_padding)
+    }
+
+    /// Number of lines printed for a value
+    fn value_lines(&self, suggestion: &Suggestion) -> u16 {
+        match self.inline_description(suggestion) {
+            Some(description) if self.description_wrap => self
+                .description_lines(description, self.description_width(), true)
+                .len() as u16,
+            _ => 1,
+        }
+    }
+
+    /// Lines needed to print all the values
+    fn value_lines_required(&self) -> u16 {
+        if self.wraps_descriptions() {
+            self.get_values()
+                .iter()
+                .map(|suggestion| self.value_lines(suggestion))
+                .sum()
+        } else {
+            self.get_rows()
+        }
+    }
+
+    /// Limits the lines of the values to the maximum rows of the menu
+    fn capped_value_lines(&self, lines: u16) -> u16 {
+        self.max_rows.map_or(lines, |max_rows| lines.min(max_rows))
+    }
+
+    /// Values printed with their descriptions wrapped onto several lines
+    fn wraps_descriptions(&self) -> bool {
+        self.description_wrap
+            && self
+                .get_values()
+                .iter()
+                .any(|suggestion| self.inline_description(suggestion).is_some())
+    }
+
+    /// Number of values skipped and printed so the selected value and its wrapped
+    /// description fit in the available lines
+    fn wrapped_values_window(&self, available_lines: u16) -> (usize, usize) {
+        let lines: Vec<u16> = self
+            .get_values()
+            .iter()
+            .map(|suggestion| self.value_lines(suggestion))
+            .collect();
+        let selected = self.index().min(lines.len().saturating_sub(1));
+
+        let mut skip = 0;
+        while skip < selected && lines[skip..=selected].iter().sum::<u16>() > available_lines {
+            skip += 1;
+        }
+
+        let mut used_lines = 0;
+        let taken = lines[skip..]
+            .iter()
+            .take_while(|value_lines| {
+                used_lines += **value_lines;
+                used_lines <= available_lines
+            })
+            .count()
+            .max(selected + 1 - skip);
+
+        (skip, taken)
+    }
+
     /// Creates default string that represents one suggestion from the menu
     fn create_string(
         &self,
@@ -338,95 +1126,144 @@ impl ColumnarMenu {
         empty_space: usize,
         use_ansi_coloring: bool,
     ) -> String {
+        let value = self.display_value(&suggestion.value);
+        // The style of the suggestion is only used for the rows that aren't selected
+        let text_style = suggestion.style.unwrap_or(self.color.text_style);
+        // Whitespace that aligns the value in its cell, the column without the padding
+        // between the columns. Next to descriptions the cells are as wide as the
+        // longest value
+        let value_width = line_width(&value);
+        let cell_width =
+            (empty_space + value_width).saturating_sub(self.default_details.col_padding);
+        let lead = self.value_alignment.padding(value_width, cell_width);
+        let described_lead = self
+            .value_alignment
+            .padding(value_width, self.longest_suggestion);
         if use_ansi_coloring {
             if index == self.index() {
-                if let Some(description) = &suggestion.description {
+                if let Some(description) = self.inline_description(suggestion) {
                     let left_text_size = self.longest_suggestion + self.default_details.col_padding;
                     let right_text_size = self.get_width().saturating_sub(left_text_size);
-                    format!(
-                        "{}{:max$}{}{}{}",
-                        self.color.selected_text_style.prefix(),
-                        &suggestion.value,
-                        description
-                            .chars()
-                            .take(right_text_size)
-                            .collect::<String>()
-                            .replace('\n', " "),
-                        RESET,
-                        self.end_of_line(column),
-                        max = left_text_size,
-                    )
+                    let styled_value =
+                        self.highlight_match(&value, suggestion, self.color.selected_text_style);
+                    let selected_prefix = self.color.selected_text_style.prefix().to_string();
+                    let lines: Vec<String> = self
+                        .description_lines(description, right_text_size, true)
+                        .iter()
+                        .enumerate()
+                        .map(|(line_index, line)| {
+                            // The selection is shown on top of the styles of the description
+                            let line = if self.styled_descriptions {
+                                restyle_after_escapes(line, &selected_prefix)
+                            } else {
+                                line.clone()
+                            };
+                            format!(
+                                "{}{}{}{}",
+                                selected_prefix,
+                                left_column(
+                                    &styled_value,
+                                    line_index,
+                                    described_lead,
+                                    left_text_size
+                                ),
+                                line,
+                                RESET,
+                            )
+                        })
+                        .collect();
+                    format!("{}{}", lines.join("\r\n"), self.end_of_line(column))
                 } else {
                     format!(
-                        "{}{}{}{:>empty$}{}",
+                        "{}{:lead$}{}{}{:>empty$}{}",
                         self.color.selected_text_style.prefix(),
-                        &suggestion.value,
+                        "",
+                        self.highlight_match(&value, suggestion, self.color.selected_text_style),
                         RESET,
                         "",
                         self.end_of_line(column),
-                        empty = empty_space,
+                        empty = empty_space - lead,
                     )
                 }
-            } else if let Some(description) = &suggestion.description {
+            } else if let Some(description) = self.inline_description(suggestion) {
                 let left_text_size = self.longest_suggestion + self.default_details.col_padding;
                 let right_text_size = self.get_width().saturating_sub(left_text_size);
-                format!(
-                    "{}{:max$}{}{}{}{}{}",
-                    self.color.text_style.prefix(),
-                    &suggestion.value,
-                    RESET,
-                    self.color.description_style.prefix(),
-                    description
-                        .chars()
-                        .take(right_text_size)
-                        .collect::<String>()
-                        .replace('\n', " "),
-                    RESET,
-                    self.end_of_line(column),
-                    max = left_text_size,
-                )
+                let styled_value = self.highlight_match(&value, suggestion, text_style);
+                let lines: Vec<String> = self
+                    .description_lines(description, right_text_size, true)
+                    .iter()
+                    .enumerate()
+                    .map(|(line_index, line)| {
+                        format!(
+                            "{}{}{}{}{}{}",
+                            text_style.prefix(),
+                            left_column(&styled_value, line_index, described_lead, left_text_size),
+                            RESET,
+                            self.description_prefix(),
+                            line,
+                            RESET,
+                        )
+                    })
+                    .collect();
+                format!("{}{}", lines.join("\r\n"), self.end_of_line(column))
             } else {
                 format!(
-                    "{}{}{}{}{:>empty$}{}{}",
-                    self.color.text_style.prefix(),
-                    &suggestion.value,
+                    "{}{:lead$}{}{}{}{:>empty$}{}{}",
+                    text_style.prefix(),
+                    "",
+                    self.highlight_match(&value, suggestion, text_style),
                     RESET,
                     self.color.description_style.prefix(),
                     "",
                     RESET,
                     self.end_of_line(column),
-                    empty = empty_space,
+                    empty = empty_space - lead,
                 )
             }
         } else {
             // If no ansi coloring is found, then the selection word is the line in uppercase
             let marker = if index == self.index() { ">" } else { "" };
 
-            let line = if let Some(description) = &suggestion.description {
-                format!(
-                    "{}{:max$}{}{}",
-                    marker,
-                    &suggestion.value,
-                    description
-                        .chars()
-                        .take(empty_space)
-                        .collect::<String>()
-                        .replace('\n', " "),
-                    self.end_of_line(column),
-                    max = self.longest_suggestion
-                        + self
-                            .default_details
-                            .col_padding
-                            .saturating_sub(marker.len()),
-                )
+            let line = if let Some(description) = self.inline_description(suggestion) {
+                let left_text_size = self.longest_suggestion
+                    + self
+                        .default_details
+                        .col_padding
+                        .saturating_sub(marker.len());
+                let width = if self.description_wrap {
+                    self.description_width()
+                } else {
+                    empty_space
+                };
+                let lines: Vec<String> = self
+                    .description_lines(description, width, false)
+                    .iter()
+                    .enumerate()
+                    .map(|(line_index, line)| {
+                        let marker = if line_index == 0 {
+                            marker.to_string()
+                        } else {
+                            " ".repeat(marker.len())
+                        };
+                        format!(
+                            "{}{}{}",
+                            marker,
+                            left_column(&value, line_index, described_lead, left_text_size),
+                            line,
+                        )
+                    })
+                    .collect();
+                format!("{}{}", lines.join("\r\n"), self.end_of_line(column))
             } else {
+                let lead = lead.saturating_sub(marker.len());
                 format!(
-                    "{}{}{:>empty$}{}",
+                    "{}{:lead$}{}{:>empty$}{}",
                     marker,
-                    &suggestion.value,
+                    "",
+                    &value,
                     "",
                     self.end_of_line(column),
-                    empty = empty_space.saturating_sub(marker.len()),
+                    empty = empty_space.saturating_sub(marker.len() + lead),
                 )
             };
 
@@ -439,7 +1276,22 @@ impl ColumnarMenu {
     }
 }
 
-impl Menu for ColumnarMenu {
+/// Text printed before a line of description, the padded value on the first line and
+/// whitespace that indents the wrapped lines. The value can contain escape sequences.
+/// `lead` columns of whitespace align it
+fn left_column(value: &str, line_index: usize, lead: usize, width: usize) -> String {
+    if line_index == 0 {
+        format!(
+            "{:lead$}{}",
+            "",
+            pad_to_width(value, width.saturating_sub(lead))
+        )
+    } else {
+        " ".repeat(width)
+    }
+}
+
+impl Menu for ColumnarMenu {
     /// Menu name
     fn name(&self) -> &str {
         self.name.as_str()
@@ -455,9 +1307,19 @@ impl Menu for ColumnarMenu {
         self.active
     }
 
-    /// The columnar menu can to quick complete if there is only one element
+    /// The columnar menu can to quick complete if there is only one element, unless it
+    /// was disabled, the element is shorter than the minimum width or other values
+    /// were left out
     fn can_quick_complete(&self) -> bool {
-        true
+        self.quick_complete
+            && self.hidden_values.is_empty()
+            && match self.get_values() {
+                [suggestion] => {
+                    line_width(&self.display_value(&suggestion.value))
+                        >= self.quick_complete_min_width
+                }
+                _ => true,
+            }
     }
 
     /// The columnar menu can try to find the common string and replace it
@@ -474,37 +1336,24 @@ impl Menu for ColumnarMenu {
             self.update_values(editor, completer);
         }
 
-        let values = self.get_values();
-        if let (Some(Suggestion { value, span, .. }), Some(index)) = find_common_string(values) {
-            let index = index.min(value.len());
-            let matching = &value[0..index];
-
-            // make sure that the partial completion does not overwrite user entered input
-            let extends_input = matching.starts_with(&editor.get_buffer()[span.start..span.end]);
-
-            if !matching.is_empty() && extends_input {
-                let mut line_buffer = editor.line_buffer().clone();
-                line_buffer.replace_range(span.start..span.end, matching);
-
-                let offset = if matching.len() < (span.end - span.start) {
-                    line_buffer
-                        .insertion_point()
-                        .saturating_sub((span.end - span.start) - matching.len())
-                } else {
-                    line_buffer.insertion_point() + matching.len() - (span.end - span.start)
-                };
-
-                line_buffer.set_insertion_point(offset);
-                editor.set_line_buffer(line_buffer, UndoBehavior::CreateUndoPoint);
-
-                // The values need to be updated because the spans need to be
-                // recalculated for accurate replacement in the string
-                self.update_values(editor, completer);
-
-                true
-            } else {
-                false
-            }
+        // The values left out by the maximum number of values share the common string too
+        let all_values: Vec<Suggestion>;
+        let values = if self.hidden_values.is_empty() {
+            self.get_values()
+        } else {
+            all_values = self
+                .values
+                .iter()
+                .chain(&self.hidden_values)
+                .cloned()
+                .collect();
+            &all_values
+        };
+        if complete_common_prefix(editor, values) {
+            // The values need to be updated because the spans need to be
+            // recalculated for accurate replacement in the string
+            self.update_values(editor, completer);
+            true
         } else {
             false
         }
@@ -524,13 +1373,28 @@ impl Menu for ColumnarMenu {
         self.event = Some(event);
     }
 
+    fn append_values(&mut self, values: Vec<Suggestion>, loading: bool) {
+        self.values.append(&mut self.hidden_values);
+        self.values.extend(values);
+        self.loading = loading;
+        self.sort_values();
+        self.truncate_values();
+
+        self.values_appended = true;
+        self.menu_event(MenuEvent::Refresh);
+    }
+
     /// Updates menu values
     fn update_values(&mut self, editor: &mut Editor, completer: &mut dyn Completer) {
         if self.only_buffer_difference {
             if let Some(old_string) = &self.input {
                 let (start, input) = string_difference(editor.get_buffer(), old_string);
                 if !input.is_empty() {
+                    self.completed_line = input.to_string();
                     self.values = completer.complete(input, start);
+                    self.loading = completer.is_loading();
+                    self.sort_values();
+                    self.truncate_values();
                     self.reset_position();
                 }
             }
@@ -542,6 +1406,10 @@ impl Menu for ColumnarMenu {
             // position is maintain in the line buffer.
             let trimmed_buffer = editor.get_buffer().replace('\n', " ");
             self.values = completer.complete(trimmed_buffer.as_str(), editor.insertion_point());
+            self.loading = completer.is_loading();
+            self.completed_line = trimmed_buffer;
+            self.sort_values();
+            self.truncate_values();
             self.reset_position();
         }
     }
@@ -555,6 +1423,15 @@ impl Menu for ColumnarMenu {
         painter: &Painter,
     ) {
         if let Some(event) = self.event.take() {
+            // The selection is taken from the layout it was made in, the number of
+            // columns may change below
+            let selected_index = self.index();
+            // The layout fits the refreshed values
+            let values_appended = std::mem::take(&mut self.values_appended);
+            if matches!(event, MenuEvent::Refresh) && !values_appended {
+                self.update_values(editor, completer);
+            }
+
             // The working value for the menu are updated first before executing any of the
             // menu events
             //
@@ -563,22 +1440,46 @@ impl Menu for ColumnarMenu {
             let exist_description = self
                 .get_values()
                 .iter()
-                .any(|suggestion| suggestion.description.is_some());
+                .any(|suggestion| self.inline_description(suggestion).is_some());
+            self.working_details.documentation_width = painter.screen_width() as usize;
+
+            // Changing the selection shows its documentation from the beginning
+            if !matches!(
+                event,
+                MenuEvent::DocumentationUp | MenuEvent::DocumentationDown
+            ) {
+                self.documentation_offset = 0;
+            }
 
             if exist_description {
                 self.working_details.columns = 1;
                 self.working_details.col_width = painter.screen_width() as usize;
 
                 self.longest_suggestion = self.get_values().iter().fold(0, |prev, suggestion| {
-                    if prev >= suggestion.value.len() {
+                    if prev >= line_width(&self.display_value(&suggestion.value)) {
                         prev
                     } else {
-                        suggestion.value.len()
+                        line_width(&self.display_value(&suggestion.value))
                     }
                 });
+                self.working_details.col_widths = Vec::new();
+            } else if !self.default_details.col_widths.is_empty() {
+                // Dropping columns until the configured widths fit in the screen
+                let mut columns = self.default_details.columns.max(1);
+                let mut widths = self.column_widths(columns);
+                while columns > 1 && widths.iter().sum::<usize>() > painter.screen_width() as usize
+                {
+                    columns -= 1;
+                    widths = self.column_widths(columns);
+                }
+
+                self.working_details.columns = columns;
+                self.working_details.col_width = widths.iter().copied().max().unwrap_or(0);
+                self.working_details.col_widths = widths;
             } else {
                 let max_width = self.get_values().iter().fold(0, |acc, suggestion| {
-                    let str_len = suggestion.value.len() + self.default_details.col_padding;
+                    let str_len = line_width(&self.display_value(&suggestion.value))
+                        + self.default_details.col_padding;
                     if str_len > acc {
                         str_len
                     } else {
@@ -611,6 +1512,7 @@ impl Menu for ColumnarMenu {
                 } else {
                     self.working_details.columns = possible_cols;
                 }
+                self.working_details.col_widths = Vec::new();
             }
 
             match event {
@@ -642,8 +1544,28 @@ impl Menu for ColumnarMenu {
                 MenuEvent::MoveDown => self.move_down(),
                 MenuEvent::MoveLeft => self.move_left(),
                 MenuEvent::MoveRight => self.move_right(),
-                MenuEvent::PreviousPage | MenuEvent::NextPage => {
-                    // The columnar menu doest have the concept of pages, yet
+                MenuEvent::NextPage => self.move_next_page(),
+                MenuEvent::PreviousPage => self.move_previous_page(),
+                MenuEvent::Input(_) => {
+                    // The columnar menu leaves the typed text to the line buffer
+                }
+                MenuEvent::Refresh => {
+                    let index = selected_index.min(self.get_values().len().saturating_sub(1));
+                    self.row_pos = (index / self.get_cols() as usize) as u16;
+                    self.col_pos = (index % self.get_cols() as usize) as u16;
+                }
+                MenuEvent::ToggleDocumentation => {
+                    self.show_documentation = !self.show_documentation;
+                }
+                MenuEvent::DocumentationUp => {
+                    self.documentation_offset = self.documentation_offset.saturating_sub(1);
+                }
+                MenuEvent::DocumentationDown => {
+                    let last_offset = self
+                        .documentation_lines()
+                        .len()
+                        .saturating_sub(self.documentation_rows as usize);
+                    self.documentation_offset = (self.documentation_offset + 1).min(last_offset);
                 }
             }
         }
@@ -651,32 +1573,53 @@ impl Menu for ColumnarMenu {
 
     /// The buffer gets replaced in the Span location
     fn replace_in_buffer(&self, editor: &mut Editor) {
-        if let Some(Suggestion {
-            mut value,
-            span,
-            append_whitespace,
-            ..
-        }) = self.get_value()
-        {
-            let start = span.start.min(editor.line_buffer().len());
-            let end = span.end.min(editor.line_buffer().len());
-            if append_whitespace {
-                value.push(' ');
+        if let Some(suggestion) = self.get_value() {
+            let append = suggestion.append_policy();
+            let Suggestion {
+                value,
+                span,
+                snippet,
+                ..
+            } = suggestion;
+            // The snippet is inserted as it is, the quoting is left to its text
+            if let Some(snippet) = snippet {
+                insert_snippet(editor, span, &snippet);
+                return;
             }
-            let mut line_buffer = editor.line_buffer().clone();
-            line_buffer.replace_range(start..end, &value);
-
-            let mut offset = line_buffer.insertion_point();
-            offset = offset.saturating_add(value.len());
-            offset = offset.saturating_sub(end.saturating_sub(start));
-            line_buffer.set_insertion_point(offset);
-            editor.set_line_buffer(line_buffer, UndoBehavior::CreateUndoPoint);
+            let buffer = editor.get_buffer();
+            let pos = editor.insertion_point();
+            let (mut span, mut value) =
+                quote_aware_replacement(buffer, pos, span, &value, self.quote_handling);
+            // The appended text goes after the closing quote, not inside the quotes
+            if self.quote_handling == QuoteHandling::Inside
+                && matches!(append, Append::Space | Append::Char(_))
+            {
+                if let Some(QuotedToken {
+                    quote,
+                    close: Some(close),
+                    ..
+                }) = find_quoted_token(buffer, pos)
+                {
+                    if span.end == close {
+                        span.end += quote.len_utf8();
+                        value.push(quote);
+                    }
+                }
+            }
+            replace_in_buffer(editor, span, &value, append);
         }
     }
 
     /// Minimum rows that should be displayed by the menu
     fn min_rows(&self) -> u16 {
-        self.get_rows().min(self.min_rows)
+        let min_rows = self
+            .min_rows
+            .unwrap_or_else(|| self.get_rows().min(DEFAULT_MIN_ROWS));
+        self.capped_value_lines(min_rows)
+    }
+
+    fn get_selected_value(&self) -> Option<Suggestion> {
+        self.get_value()
     }
 
     /// Gets values from filler that will be displayed in the menu
@@ -685,27 +1628,113 @@ impl Menu for ColumnarMenu {
     }
 
     fn menu_required_lines(&self, _terminal_columns: u16) -> u16 {
-        self.get_rows()
+        let value_lines = self.capped_value_lines(
+            self.value_lines_required()
+                .max(self.min_rows.unwrap_or_default()),
+        );
+
+        let hidden_values_lines = u16::from(!self.hidden_values.is_empty());
+
+        value_lines + hidden_values_lines + self.documentation_panel_rows()
+    }
+
+    fn select_at(&mut self, column: u16, row: u16, _terminal_columns: u16) -> Option<bool> {
+        let skipped_values = self.skipped_values.get();
+        // Only the rows of values painted by the last call to `menu_string` can be clicked
+        let visible_values = (self.visible_rows.get() * self.get_cols()) as usize;
+        let index = if self.wraps_descriptions() {
+            // Every value takes its own lines in a single column
+            let mut lines = 0;
+            self.get_values()
+                .iter()
+                .enumerate()
+                .skip(skipped_values)
+                .take(visible_values)
+                .find(|(_, suggestion)| {
+                    lines += self.value_lines(suggestion);
+                    row < lines
+                })
+                .map(|(index, _)| index)?
+        } else if row < self.visible_rows.get() {
+            let mut end = 0;
+            let clicked_column = (0..self.get_cols()).find(|&index| {
+                end += self.get_column_width(index);
+                (column as usize) < end
+            })?;
+            skipped_values + (row * self.get_cols() + clicked_column) as usize
+        } else {
+            return None;
+        };
+        if index >= self.get_values().len() {
+            return None;
+        }
+
+        let was_selected = index == self.index();
+        self.row_pos = (index / self.get_cols() as usize) as u16;
+        self.col_pos = (index % self.get_cols() as usize) as u16;
+        Some(was_selected)
     }
 
     fn menu_string(&self, available_lines: u16, use_ansi_coloring: bool) -> String {
         if self.get_values().is_empty() {
             self.no_records_msg(use_ansi_coloring)
         } else {
+            // The documentation panel is only printed if the values keep at least one line
+            let documentation_rows = self.documentation_panel_rows();
+            let (available_lines, documentation) =
+                if documentation_rows > 0 && available_lines > documentation_rows {
+                    (
+                        available_lines - documentation_rows,
+                        self.documentation_panel(use_ansi_coloring),
+                    )
+                } else {
+                    (available_lines, String::new())
+                };
+            let available_lines = self.capped_value_lines(available_lines);
+
+            // The position indicator takes a line from the values if they overflow
+            let value_lines = self.value_lines_required();
+            let position_indicator =
+                self.position_indicator && value_lines > available_lines && available_lines > 1;
+            let available_lines = if position_indicator {
+                available_lines - 1
+            } else {
+                available_lines
+            };
+            // So does the line telling about the values left out
+            let hidden_values = !self.hidden_values.is_empty() && available_lines > 1;
+            let available_lines = if hidden_values {
+                available_lines - 1
+            } else {
+                available_lines
+            };
+
             // The skip values represent the number of lines that should be skipped
-            // while printing the menu
-            let skip_values = if self.row_pos >= available_lines {
-                let skip_lines = self.row_pos.saturating_sub(available_lines) + 1;
-                (skip_lines * self.get_cols()) as usize
+            // while printing the menu. The selected row is always kept visible, also
+            // after a page jump
+            let (skip_values, available_values) = if self.wraps_descriptions() {
+                // Wrapped values use a single column and take several lines each
+                self.wrapped_values_window(available_lines)
             } else {
-                0
+                let skip_values = if self.row_pos >= available_lines {
+                    let skip_lines = self.row_pos.saturating_sub(available_lines) + 1;
+                    (skip_lines * self.get_cols()) as usize
+                } else {
+                    0
+                };
+                (skip_values, (available_lines * self.get_cols()) as usize)
             };
+            let visible_values =
+                available_values.min(self.get_values().len().saturating_sub(skip_values));
+            self.visible_rows
+                .set((visible_values as u16 + self.get_cols() - 1) / self.get_cols());
+            self.skipped_values.set(skip_values);
 
             // It seems that crossterm prefers to have a complete string ready to be printed
             // rather than looping through the values and printing multiple things
             // This reduces the flickering when printing the menu
-            let available_values = (available_lines * self.get_cols()) as usize;
-            self.get_values()
+            let mut menu_string: String = self
+                .get_values()
                 .iter()
                 .skip(skip_values)
                 .take(available_values)
@@ -714,18 +1743,44 @@ impl Menu for ColumnarMenu {
                     // Correcting the enumerate index based on the number of skipped values
                     let index = index + skip_values;
                     let column = index as u16 % self.get_cols();
-                    let empty_space = self.get_width().saturating_sub(suggestion.value.len());
+                    let empty_space = self
+                        .get_column_width(column)
+                        .saturating_sub(line_width(&self.display_value(&suggestion.value)));
 
                     self.create_string(suggestion, index, column, empty_space, use_ansi_coloring)
                 })
-                .collect()
+                .collect();
+
+            if position_indicator {
+                if !menu_string.ends_with("\r\n") {
+                    menu_string.push_str("\r\n");
+                }
+                menu_string.push_str(&self.position_indicator_line(use_ansi_coloring));
+            }
+
+            if hidden_values {
+                if !menu_string.ends_with("\r\n") {
+                    menu_string.push_str("\r\n");
+                }
+                menu_string.push_str(&self.hidden_values_line(use_ansi_coloring));
+            }
+
+            if !documentation.is_empty() {
+                if !menu_string.ends_with("\r\n") {
+                    menu_string.push_str("\r\n");
+                }
+                menu_string.push_str(&documentation);
+            }
+
+            menu_string
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::Span;
+    use crate::{Span, UndoBehavior};
+    use rstest::rstest;
 
     use super::*;
 
@@ -805,13 +1860,368 @@ mod tests {
     fn fake_suggestion(name: &str, pos: usize) -> Suggestion {
         Suggestion {
             value: name.to_string(),
-            description: None,
-            extra: None,
             span: Span { start: 0, end: pos },
-            append_whitespace: false,
+            ..Suggestion::default()
         }
     }
 
+    fn painter_with_size(width: u16, height: u16) -> Painter {
+        let mut painter = Painter::new(std::io::BufWriter::new(Box::new(std::io::stderr())));
+        painter.handle_resize(width, height);
+        painter
+    }
+
+    #[test]
+    fn test_per_column_widths() {
+        let mut completer = FakeCompleter::new(&["a", "bb", "c", "dd"]);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(2)
+            .with_column_widths(vec![4, 10]);
+        let mut editor = Editor::default();
+        let painter = painter_with_size(80, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+
+        assert_eq!(
+            menu.menu_string(10, false),
+            ">A  bb        \r\nc   dd        \r\n"
+        );
+    }
+
+    #[test]
+    fn test_wide_characters_use_display_width() {
+        let mut completer = FakeCompleter::new(&["文件", "ab", "é", "abc"]);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(2)
+            .with_column_width(Some(5));
+        let mut editor = Editor::default();
+        let painter = painter_with_size(80, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+
+        assert_eq!(menu.menu_string(10, false), ">文件ab   \r\né    abc  \r\n");
+    }
+
+    #[test]
+    fn test_right_aligned_values_use_display_width() {
+        let mut completer = FakeCompleter::new(&["文件", "ab", "é", "abc"]);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(2)
+            .with_column_width(Some(5))
+            .with_value_alignment(Alignment::Right);
+        let mut editor = Editor::default();
+        let painter = painter_with_size(80, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+
+        assert_eq!(menu.menu_string(10, false), ">文件 ab  \r\n  é  abc  \r\n");
+
+        // The highlight covers the padding before the value
+        menu.menu_event(MenuEvent::NextElement);
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+        let selected = menu.color.selected_text_style.prefix().to_string();
+        assert_eq!(
+            menu.create_string(&menu.get_values()[1], 1, 1, 3, true),
+            format!("{selected} ab{RESET}  \r\n")
+        );
+    }
+
+    #[test]
+    fn test_values_with_tabs_keep_the_grid() {
+        let mut completer = FakeCompleter::new(&["a\tb", "cc", "dd", "e\tf"]);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(2)
+            .with_column_width(Some(7))
+            .with_tab_width(4);
+        let mut editor = Editor::default();
+        let painter = painter_with_size(80, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+
+        assert_eq!(
+            menu.menu_string(10, false),
+            ">A   B cc     \r\ndd     e   f  \r\n"
+        );
+
+        // The original value is inserted
+        menu.replace_in_buffer(&mut editor);
+        assert_eq!(editor.get_buffer(), "a\tb");
+    }
+
+    #[test]
+    fn test_per_column_widths_clamp_and_fit_values() {
+        let mut completer = FakeCompleter::new(&["a", "bb", "longer_value"]);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(3)
+            .with_column_widths(vec![4]);
+        let mut editor = Editor::default();
+        let painter = painter_with_size(80, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+        // The layout is computed from the values collected by the previous event
+        menu.menu_event(MenuEvent::Edit(true));
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+
+        // The last width is reused for the extra columns and widened for long values
+        assert_eq!(menu.working_details.col_widths, vec![4, 4, 14]);
+    }
+
+    #[test]
+    fn test_per_column_widths_drop_columns_to_fit_screen() {
+        let mut completer = FakeCompleter::new(&["a", "b", "c"]);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(3)
+            .with_column_widths(vec![10]);
+        let mut editor = Editor::default();
+        let painter = painter_with_size(25, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+
+        assert_eq!(menu.get_cols(), 2);
+    }
+
+    #[test]
+    fn test_move_by_pages() {
+        let mut completer = FakeCompleter::new(&["a", "b", "c", "d", "e", "f", "g"]);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(2)
+            .with_column_width(Some(4));
+        let mut editor = Editor::default();
+        let painter = painter_with_size(80, 24);
+        let mut move_menu = |menu: &mut ColumnarMenu, event: MenuEvent| {
+            menu.menu_event(event);
+            menu.update_working_details(&mut editor, &mut completer, &painter);
+            menu.get_value().unwrap().value
+        };
+
+        move_menu(&mut menu, MenuEvent::Activate(false));
+        // The page size is taken from the rows printed by the last menu string
+        menu.menu_string(2, false);
+
+        assert_eq!(move_menu(&mut menu, MenuEvent::NextPage), "e");
+        assert_eq!(menu.menu_string(2, false), "c   d   \r\n>E  f   \r\n");
+        assert_eq!(move_menu(&mut menu, MenuEvent::MoveRight), "f");
+        // The last page is clamped to the last value
+        assert_eq!(move_menu(&mut menu, MenuEvent::NextPage), "g");
+        assert_eq!(move_menu(&mut menu, MenuEvent::NextPage), "g");
+        assert_eq!(menu.menu_string(2, false), "e   f   \r\n>G  ");
+        assert_eq!(move_menu(&mut menu, MenuEvent::PreviousPage), "c");
+        assert_eq!(move_menu(&mut menu, MenuEvent::PreviousPage), "a");
+    }
+
+    #[rstest]
+    #[case(true, MenuEvent::NextElement, "a")]
+    #[case(false, MenuEvent::NextElement, "e")]
+    #[case(true, MenuEvent::MoveDown, "a")]
+    #[case(false, MenuEvent::MoveDown, "e")]
+    #[case(true, MenuEvent::MoveRight, "e")]
+    #[case(false, MenuEvent::MoveRight, "e")]
+    fn test_wrap_around_at_the_end(
+        #[case] wrap_around: bool,
+        #[case] event: MenuEvent,
+        #[case] expected: &str,
+    ) {
+        let mut completer = FakeCompleter::new(&["a", "b", "c", "d", "e"]);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(2)
+            .with_column_width(Some(4))
+            .with_wrap_around(wrap_around);
+        let mut editor = Editor::default();
+        let painter = painter_with_size(80, 24);
+        let mut move_menu = |menu: &mut ColumnarMenu, event: MenuEvent| {
+            menu.menu_event(event);
+            menu.update_working_details(&mut editor, &mut completer, &painter);
+            menu.get_value().unwrap().value
+        };
+
+        move_menu(&mut menu, MenuEvent::Activate(false));
+        for _ in 0..4 {
+            move_menu(&mut menu, MenuEvent::NextElement);
+        }
+        assert_eq!(move_menu(&mut menu, event), expected);
+    }
+
+    #[rstest]
+    #[case(true, MenuEvent::PreviousElement, "e")]
+    #[case(false, MenuEvent::PreviousElement, "a")]
+    #[case(true, MenuEvent::MoveUp, "e")]
+    #[case(false, MenuEvent::MoveUp, "a")]
+    #[case(true, MenuEvent::MoveLeft, "b")]
+    #[case(false, MenuEvent::MoveLeft, "a")]
+    fn test_wrap_around_at_the_start(
+        #[case] wrap_around: bool,
+        #[case] event: MenuEvent,
+        #[case] expected: &str,
+    ) {
+        let mut completer = FakeCompleter::new(&["a", "b", "c", "d", "e"]);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(2)
+            .with_column_width(Some(4))
+            .with_wrap_around(wrap_around);
+        let mut editor = Editor::default();
+        let painter = painter_with_size(80, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+        menu.menu_event(event);
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+        assert_eq!(menu.get_value().unwrap().value, expected);
+    }
+
+    #[test]
+    fn test_select_at() {
+        let mut completer = FakeCompleter::new(&["a", "b", "c", "d", "e", "f", "g"]);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(2)
+            .with_column_width(Some(4));
+        let mut editor = Editor::default();
+        let painter = painter_with_size(80, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+        menu.menu_string(2, false);
+
+        assert_eq!(menu.select_at(5, 1, 80), Some(false));
+        assert_eq!(menu.get_value().unwrap().value, "d");
+        assert_eq!(menu.select_at(6, 1, 80), Some(true));
+        // Outside of the columns and below the painted rows
+        assert_eq!(menu.select_at(8, 0, 80), None);
+        assert_eq!(menu.select_at(0, 2, 80), None);
+
+        // The clicked row is relative to the first painted value
+        menu.menu_event(MenuEvent::NextPage);
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+        assert_eq!(menu.menu_string(2, false), "e   f   \r\n>G  ");
+        assert_eq!(menu.select_at(4, 0, 80), Some(false));
+        assert_eq!(menu.get_value().unwrap().value, "f");
+        assert_eq!(menu.select_at(4, 1, 80), None);
+    }
+
+    #[test]
+    fn test_position_indicator() {
+        let mut completer = FakeCompleter::new(&["a", "b", "c", "d", "e"]);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(1)
+            .with_column_width(Some(4))
+            .with_position_indicator(true);
+        let mut editor = Editor::default();
+        let painter = painter_with_size(80, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+        assert_eq!(menu.menu_string(3, false), ">A  \r\nb   \r\n1/5\r\n");
+
+        for _ in 0..2 {
+            menu.menu_event(MenuEvent::NextElement);
+            menu.update_working_details(&mut editor, &mut completer, &painter);
+        }
+        assert_eq!(menu.menu_string(3, false), "b   \r\n>C  \r\n3/5\r\n");
+        assert_eq!(
+            menu.menu_string(3, true).lines().last(),
+            Some(format!("{}3/5{RESET}", menu.color.description_style.prefix()).as_str())
+        );
+
+        // Without overflow there is no indicator
+        assert_eq!(
+            menu.menu_string(5, false),
+            "a   \r\nb   \r\n>C  \r\nd   \r\ne   \r\n"
+        );
+    }
+
+    #[test]
+    fn max_suggestions_keeps_the_first_sorted_values() {
+        let mut completer = FakeCompleter::new(&["e", "d", "c", "b", "a"]);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(1)
+            .with_column_width(Some(4))
+            .with_sort(SortOrder::Alphabetical)
+            .with_max_suggestions(2);
+        let mut editor = Editor::default();
+        let painter = painter_with_size(80, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+        assert_eq!(menu.menu_required_lines(80), 3);
+        assert_eq!(
+            menu.menu_string(10, false),
+            ">A  \r\nb   \r\n(+3 more, refine your query)\r\n"
+        );
+        assert!(!menu.can_quick_complete());
+
+        // All values fit, no line is shown
+        menu.update_values(&mut editor, &mut FakeCompleter::new(&["b", "a"]));
+        assert_eq!(menu.menu_required_lines(80), 2);
+        assert!(!menu.menu_string(10, false).contains("more"));
+    }
+
+    #[test]
+    fn partial_completion_uses_the_values_left_out() {
+        let mut completer = FakeCompleter::new(&["abcx", "abcy", "abcz"]);
+        let mut menu = ColumnarMenu::default().with_max_suggestions(1);
+        let mut editor = Editor::default();
+        editor.set_buffer("ab".into(), UndoBehavior::CreateUndoPoint);
+
+        assert!(menu.can_partially_complete(false, &mut editor, &mut completer));
+        assert_eq!(editor.get_buffer(), "abc");
+    }
+
+    #[rstest]
+    #[case(&["a"], None, None, 1, 1)]
+    #[case(&["a", "b", "c", "d", "e"], None, None, 5, 3)]
+    #[case(&["a"], Some(4), None, 4, 4)]
+    #[case(&["a", "b", "c", "d", "e"], Some(4), None, 5, 4)]
+    #[case(&["a", "b", "c", "d", "e"], None, Some(2), 2, 2)]
+    #[case(&["a"], Some(4), Some(2), 2, 2)]
+    fn test_min_and_max_rows(
+        #[case] values: &[&str],
+        #[case] min_rows: Option<u16>,
+        #[case] max_rows: Option<u16>,
+        #[case] required_lines: u16,
+        #[case] expected_min_rows: u16,
+    ) {
+        let mut completer = FakeCompleter::new(values);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(1)
+            .with_max_rows(max_rows);
+        if let Some(min_rows) = min_rows {
+            menu = menu.with_min_rows(min_rows);
+        }
+        let mut editor = Editor::default();
+        let painter = painter_with_size(80, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+        assert_eq!(menu.menu_required_lines(80), required_lines);
+        assert_eq!(menu.min_rows(), expected_min_rows);
+    }
+
+    #[test]
+    fn test_max_rows_scrolls_the_values() {
+        let mut completer = FakeCompleter::new(&["a", "b", "c", "d", "e"]);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(1)
+            .with_column_width(Some(4))
+            .with_max_rows(Some(2));
+        let mut editor = Editor::default();
+        let painter = painter_with_size(80, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut editor, &mut completer, &painter);
+        assert_eq!(menu.menu_string(10, false), ">A  \r\nb   \r\n");
+
+        for _ in 0..2 {
+            menu.menu_event(MenuEvent::NextElement);
+            menu.update_working_details(&mut editor, &mut completer, &painter);
+        }
+        assert_eq!(menu.menu_string(10, false), "b   \r\n>C  \r\n");
+    }
+
     #[test]
     fn test_menu_replace_backtick() {
         // https://github.com/nushell/nushell/issues/7885
@@ -832,4 +2242,796 @@ mod tests {
             "cursor should be at the end after completion"
         );
     }
+
+    #[rstest]
+    #[case(Append::None, "cat `file1.txt`", 14)]
+    #[case(Append::Space, "cat `file1.txt `", 15)]
+    #[case(Append::Char('/'), "cat `file1.txt/`", 15)]
+    #[case(Append::MoveAfter('`'), "cat `file1.txt`", 15)]
+    #[case(Append::MoveAfter('"'), "cat `file1.txt`", 14)]
+    fn test_menu_replace_append(
+        #[case] append: Append,
+        #[case] expected_buffer: &str,
+        #[case] expected_cursor: usize,
+    ) {
+        struct AppendCompleter(Append);
+
+        impl Completer for AppendCompleter {
+            fn complete(&mut self, _line: &str, pos: usize) -> Vec<Suggestion> {
+                vec![Suggestion::new("file1.txt", Span::new(5, pos)).with_append(self.0)]
+            }
+        }
+
+        let mut completer = AppendCompleter(append);
+        let mut menu = ColumnarMenu::default().with_name("testmenu");
+        let mut editor = Editor::default();
+
+        // The cursor is before the closing backtick
+        editor.set_buffer("cat `fi`".to_string(), UndoBehavior::CreateUndoPoint);
+        editor.run_edit_command(&crate::EditCommand::MoveLeft);
+
+        menu.update_values(&mut editor, &mut completer);
+        menu.replace_in_buffer(&mut editor);
+
+        assert_eq!(editor.get_buffer(), expected_buffer);
+        assert_eq!(editor.insertion_point(), expected_cursor);
+    }
+
+    #[rstest]
+    #[case(ColumnarMenu::default(), false, "NO RECORDS FOUND".to_string())]
+    #[case(
+        ColumnarMenu::default().with_no_records_msg("sin resultados".into()),
+        false,
+        "sin resultados".to_string()
+    )]
+    #[case(
+        ColumnarMenu::default(),
+        true,
+        format!("{}NO RECORDS FOUND{RESET}", MenuTextStyle::default().selected_text_style.prefix())
+    )]
+    #[case(
+        ColumnarMenu::default()
+            .with_no_records_msg("empty".into())
+            .with_no_records_text_style(Style::new().italic()),
+        true,
+        format!("{}empty{RESET}", Style::new().italic().prefix())
+    )]
+    fn test_no_records_msg(
+        #[case] menu: ColumnarMenu,
+        #[case] use_ansi_coloring: bool,
+        #[case] expected: String,
+    ) {
+        assert_eq!(menu.menu_string(10, use_ansi_coloring), expected);
+    }
+
+    struct LoadingCompleter;
+
+    impl Completer for LoadingCompleter {
+        fn complete(&mut self, _line: &str, _pos: usize) -> Vec<Suggestion> {
+            vec![]
+        }
+
+        fn is_loading(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn loading_msg_is_shown_while_completer_is_loading() {
+        let mut menu = ColumnarMenu::default().with_loading_msg("wait".into());
+        let mut editor = Editor::default();
+
+        menu.update_values(&mut editor, &mut LoadingCompleter);
+        assert_eq!(menu.menu_string(10, false), "wait");
+
+        menu.update_values(&mut editor, &mut FakeCompleter::new(&[]));
+        assert_eq!(menu.menu_string(10, false), "NO RECORDS FOUND");
+    }
+
+    #[rstest]
+    #[case("help", "{match}hel{RESET}{text}p")]
+    #[case("shell", "s{match}hel{RESET}{text}l")]
+    #[case("HELLO", "{match}HEL{RESET}{text}LO")]
+    #[case("abc", "abc")]
+    fn test_highlight_match(#[case] value: &str, #[case] expected: &str) {
+        let match_style = Style::new().underline();
+        let menu = {
+            let mut menu = ColumnarMenu::default().with_match_text_style(match_style);
+            let mut editor = Editor::default();
+            editor.set_buffer("hel".into(), UndoBehavior::CreateUndoPoint);
+            menu.update_values(&mut editor, &mut FakeCompleter::new(&[value]));
+            menu
+        };
+        let text_style = menu.color.text_style;
+
+        assert_eq!(
+            menu.create_string(&menu.get_values()[0], 1, 0, 0, true),
+            format!(
+                "{}{}{RESET}{}{RESET}\r\n",
+                text_style.prefix(),
+                expected
+                    .replace("{match}", &match_style.prefix().to_string())
+                    .replace("{RESET}", RESET)
+                    .replace("{text}", &text_style.prefix().to_string()),
+                menu.color.description_style.prefix(),
+            )
+        );
+    }
+
+    #[rstest]
+    #[case(1, Some(Style::new().bold()), Style::new().bold())]
+    #[case(1, None, MenuTextStyle::default().text_style)]
+    #[case(0, Some(Style::new().bold()), MenuTextStyle::default().selected_text_style)]
+    fn test_suggestion_style(
+        #[case] index: usize,
+        #[case] style: Option<Style>,
+        #[case] expected_style: Style,
+    ) {
+        let menu = ColumnarMenu::default();
+        let suggestion = Suggestion {
+            style,
+            ..fake_suggestion("dir", 0)
+        };
+
+        assert!(menu
+            .create_string(&suggestion, index, 0, 0, true)
+            .starts_with(&format!("{}dir{RESET}", expected_style.prefix())));
+    }
+
+    struct DescribedCompleter;
+
+    impl Completer for DescribedCompleter {
+        fn complete(&mut self, _line: &str, pos: usize) -> Vec<Suggestion> {
+            vec![
+                Suggestion {
+                    description: Some("one two three four".into()),
+                    ..fake_suggestion("cmd", pos)
+                },
+                Suggestion {
+                    description: Some("list".into()),
+                    ..fake_suggestion("ls", pos)
+                },
+            ]
+        }
+    }
+
+    fn wrapped_description_menu() -> (ColumnarMenu, Editor, Painter) {
+        let mut menu = ColumnarMenu::default().with_description_wrap(true);
+        let mut editor = Editor::default();
+        let painter = painter_with_size(14, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut editor, &mut DescribedCompleter, &painter);
+        menu.menu_event(MenuEvent::Edit(true));
+        menu.update_working_details(&mut editor, &mut DescribedCompleter, &painter);
+        (menu, editor, painter)
+    }
+
+    #[test]
+    fn test_description_wrap() {
+        let (mut menu, mut editor, painter) = wrapped_description_menu();
+
+        assert_eq!(menu.menu_required_lines(14), 4);
+        assert_eq!(
+            menu.menu_string(10, false),
+            ">CMD ONE TWO\r\n     THREE\r\n     FOUR\r\nls   list\r\n"
+        );
+
+        // The previous values are skipped when the wrapped lines don't fit
+        menu.menu_event(MenuEvent::MoveDown);
+        menu.update_working_details(&mut editor, &mut DescribedCompleter, &painter);
+        assert_eq!(menu.menu_string(2, false), ">LS  LIST\r\n");
+    }
+
+    #[test]
+    fn test_description_wrap_highlights_all_selected_lines() {
+        let (menu, _, _) = wrapped_description_menu();
+        let selected = menu.color.selected_text_style.prefix().to_string();
+
+        let menu_string = menu.menu_string(10, true);
+        let lines: Vec<&str> = menu_string.split("\r\n").collect();
+
+        assert_eq!(
+            &lines[..3],
+            &[
+                format!("{selected}cmd  one two{RESET}"),
+                format!("{selected}     three{RESET}"),
+                format!("{selected}     four{RESET}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_right_aligned_values_and_descriptions() {
+        let mut menu = ColumnarMenu::default()
+            .with_description_wrap(true)
+            .with_value_alignment(Alignment::Right)
+            .with_description_alignment(Alignment::Right);
+        let mut editor = Editor::default();
+        let painter = painter_with_size(14, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut editor, &mut DescribedCompleter, &painter);
+        menu.menu_event(MenuEvent::Edit(true));
+        menu.update_working_details(&mut editor, &mut DescribedCompleter, &painter);
+
+        assert_eq!(
+            menu.menu_string(10, false),
+            ">CMD   ONE TWO\r\n         THREE\r\n          FOUR\r\n ls       list\r\n"
+        );
+    }
+
+    struct DocumentedCompleter;
+
+    impl Completer for DocumentedCompleter {
+        fn complete(&mut self, _line: &str, pos: usize) -> Vec<Suggestion> {
+            vec![
+                Suggestion {
+                    documentation: Some("one two three four five six".into()),
+                    ..fake_suggestion("first", pos)
+                },
+                fake_suggestion("second", pos),
+            ]
+        }
+    }
+
+    fn documentation_menu(menu: ColumnarMenu) -> ColumnarMenu {
+        let mut menu = menu.with_columns(1).with_column_width(Some(10));
+        let mut editor = Editor::default();
+        let painter = painter_with_size(14, 24);
+
+        menu.menu_event(MenuEvent::Activate(false));
+        menu.update_working_details(&mut editor, &mut DocumentedCompleter, &painter);
+        menu.menu_event(MenuEvent::Edit(true));
+        menu.update_working_details(&mut editor, &mut DocumentedCompleter, &painter);
+        menu
+    }
+
+    #[test]
+    fn test_documentation_panel() {
+        let menu = documentation_menu(ColumnarMenu::default().with_documentation(true));
+
+        // 2 rows with values and 3 wrapped documentation lines with borders
+        assert_eq!(menu.menu_required_lines(14), 7);
+        assert_eq!(
+            menu.menu_string(20, false),
+            [
+                ">FIRST    \r\n",
+                "second    \r\n",
+                "┌────────────┐\r\n",
+                "│ one two    │\r\n",
+                "│ three four │\r\n",
+                "│ five six   │\r\n",
+                "└────────────┘\r\n",
+            ]
+            .concat()
+        );
+    }
+
+    struct PreviewCompleter;
+
+    impl Completer for PreviewCompleter {
+        fn complete(&mut self, _line: &str, pos: usize) -> Vec<Suggestion> {
+            vec![
+                Suggestion {
+                    description: Some("lists the files".into()),
+                    extra: Some(vec!["ls -la".into()]),
+                    ..fake_suggestion("ls", pos)
+                },
+                Suggestion {
+                    description: Some("changes the directory".into()),
+                    documentation: Some("cd <dir>".into()),
+                    ..fake_suggestion("cd", pos)
+                },
+            ]
+        }
+    }
+
+    #[test]
+    fn test_description_preview() {
+        let mut editor = Editor::default();
+        let painter = painter_with_size(14, 24);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(2)
+            .with_column_width(Some(4))
+            .with_description_preview(true)
+            .with_preview_extra(true)
+            .with_documentation(true);
+        let mut update = |menu: &mut ColumnarMenu, event: MenuEvent| {
+            menu.menu_event(event);
+            menu.update_working_details(&mut editor, &mut PreviewCompleter, &painter);
+        };
+        update(&mut menu, MenuEvent::Activate(false));
+
+        // The values keep their columns and the panel follows the selection
+        assert_eq!(menu.menu_required_lines(14), 6);
+        assert_eq!(
+            menu.menu_string(20, false),
+            [
+                ">LS cd  \r\n",
+                "┌────────────┐\r\n",
+                "│ lists the  │\r\n",
+                "│ files      │\r\n",
+                "│ ls -la     │\r\n",
+                "└────────────┘\r\n",
+            ]
+            .concat()
+        );
+
+        update(&mut menu, MenuEvent::NextElement);
+        assert_eq!(
+            menu.menu_string(20, false),
+            [
+                "ls  >CD \r\n",
+                "┌────────────┐\r\n",
+                "│ changes    │\r\n",
+                "│ the        │\r\n",
+                "│ directory  │\r\n",
+                "│            │\r\n",
+                "│ cd <dir>   │\r\n",
+                "└────────────┘\r\n",
+            ]
+            .concat()
+        );
+    }
+
+    struct ExtraCompleter;
+
+    impl Completer for ExtraCompleter {
+        fn complete(&mut self, _line: &str, pos: usize) -> Vec<Suggestion> {
+            vec![
+                Suggestion {
+                    extra: Some(vec!["fn(a: u8)".into(), "-> u8".into()]),
+                    ..fake_suggestion("add", pos)
+                },
+                fake_suggestion("sub", pos),
+            ]
+        }
+    }
+
+    #[test]
+    fn test_preview_extra() {
+        let mut editor = Editor::default();
+        let painter = painter_with_size(14, 24);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(2)
+            .with_column_width(Some(4))
+            .with_preview_extra(true);
+        let mut update = |menu: &mut ColumnarMenu, event: MenuEvent| {
+            menu.menu_event(event);
+            menu.update_working_details(&mut editor, &mut ExtraCompleter, &painter);
+        };
+        update(&mut menu, MenuEvent::Activate(false));
+
+        assert_eq!(menu.menu_required_lines(14), 5);
+        assert_eq!(
+            menu.menu_string(20, false),
+            [
+                ">ADDsub \r\n",
+                "┌────────────┐\r\n",
+                "│ fn(a: u8)  │\r\n",
+                "│ -> u8      │\r\n",
+                "└────────────┘\r\n",
+            ]
+            .concat()
+        );
+
+        // Without extra lines the menu is left as it is
+        update(&mut menu, MenuEvent::NextElement);
+        assert_eq!(menu.menu_required_lines(14), 1);
+        assert_eq!(menu.menu_string(20, false), "add  >SUB \r\n");
+    }
+
+    #[test]
+    fn test_value_preview() {
+        let mut editor = Editor::default();
+        let painter = painter_with_size(14, 24);
+        let mut completer = FakeCompleter::new(&["git log  | head", "ls"]);
+        let mut menu = ColumnarMenu::default()
+            .with_columns(1)
+            .with_value_preview(true);
+        let mut update = |menu: &mut ColumnarMenu, event: MenuEvent| {
+            menu.menu_event(event);
+            menu.update_working_details(&mut editor, &mut completer, &painter);
+        };
+        update(&mut menu, MenuEvent::Activate(false));
+
+        assert_eq!(
+            menu.menu_string(20, false),
+            [
+                ">GIT LOG  | HEAD\r\n",
+                "ls            \r\n",
+                "┌────────────┐\r\n",
+                "│ git log  | │\r\n",
+                "│  head      │\r\n",
+                "└────────────┘\r\n",
+            ]
+            .concat()
+        );
+
+        update(&mut menu, MenuEvent::NextElement);
+        assert!(menu.menu_string(20, false).contains("│ ls         │"));
+    }
+
+    #[test]
+    fn test_documentation_panel_scroll_and_toggle() {
+        let mut editor = Editor::default();
+        let painter = painter_with_size(14, 24);
+        let mut menu = documentation_menu(
+            ColumnarMenu::default()
+                .with_documentation(true)
+                .with_documentation_rows(2),
+        );
+        assert_eq!(menu.menu_required_lines(14), 6);
+
+        for _ in 0..3 {
+            menu.menu_event(MenuEvent::DocumentationDown);
+            menu.update_working_details(&mut editor, &mut DocumentedCompleter, &painter);
+        }
+        assert!(menu
+            .menu_string(20, false)
+            .contains("│ three four │\r\n│ five six   │"));
+
+        menu.menu_event(MenuEvent::DocumentationUp);
+        menu.update_working_details(&mut editor, &mut DocumentedCompleter, &painter);
+        assert!(menu
+            .menu_string(20, false)
+            .contains("│ one two    │\r\n│ three four │"));
+
+        menu.menu_event(MenuEvent::ToggleDocumentation);
+        menu.update_working_details(&mut editor, &mut DocumentedCompleter, &painter);
+        assert_eq!(menu.menu_required_lines(14), 2);
+    }
+
+    #[test]
+    fn test_documentation_panel_needs_space_and_documentation() {
+        let mut editor = Editor::default();
+        let painter = painter_with_size(14, 24);
+        let mut menu = documentation_menu(ColumnarMenu::default().with_documentation(true));
+
+        // Not enough lines for the panel
+        assert_eq!(menu.menu_string(3, false), ">FIRST    \r\nsecond    \r\n");
+
+        // The selected value has no documentation
+        menu.menu_event(MenuEvent::NextElement);
+        menu.update_working_details(&mut editor, &mut DocumentedCompleter, &painter);
+        assert_eq!(menu.menu_required_lines(14), 2);
+    }
+
+    struct ScoredCompleter;
+
+    impl Completer for ScoredCompleter {
+        fn complete(&mut self, _line: &str, pos: usize) -> Vec<Suggestion> {
+            [
+                ("none", None),
+                ("low", Some("1")),
+                ("text", Some("high")),
+                ("high", Some("10.5")),
+                ("also_low", Some("1")),
+                ("negative", Some("-2")),
+            ]
+            .into_iter()
+            .map(|(value, score)| Suggestion {
+                extra: score.map(|score| vec!["ignored".to_string(), score.to_string()]),
+                ..fake_suggestion(value, pos)
+            })
+            .collect()
+        }
+    }
+
+    #[rstest]
+    #[case(SortOrder::None, &["beta", "Alpha", "alpha", "Beta", "gamma"])]
+    #[case(SortOrder::Alphabetical, &["Alpha", "Beta", "alpha", "beta", "gamma"])]
+    #[case(
+        SortOrder::AlphabeticalCaseInsensitive,
+        &["Alpha", "alpha", "beta", "Beta", "gamma"]
+    )]
+    fn test_sort_order(#[case] sort_order: SortOrder, #[case] expected: &[&str]) {
+        let mut completer = FakeCompleter::new(&["beta", "Alpha", "alpha", "Beta", "gamma"]);
+        let mut menu = ColumnarMenu::default().with_sort(sort_order);
+        let mut editor = Editor::default();
+
+        menu.update_values(&mut editor, &mut completer);
+
+        let values: Vec<&str> = menu
+            .get_values()
+            .iter()
+            .map(|suggestion| suggestion.value.as_str())
+            .collect();
+        assert_eq!(values, expected);
+
+        // The selected value is taken from the sorted values
+        menu.replace_in_buffer(&mut editor);
+        assert_eq!(editor.get_buffer(), expected[0]);
+    }
+
+    #[test]
+    fn test_sort_by_extra_score() {
+        let mut menu = ColumnarMenu::default().with_sort_by_extra_score(1);
+        let mut editor = Editor::default();
+
+        menu.update_values(&mut editor, &mut ScoredCompleter);
+
+        let values: Vec<&str> = menu
+            .get_values()
+            .iter()
+            .map(|suggestion| suggestion.value.as_str())
+            .collect();
+        assert_eq!(
+            values,
+            vec!["high", "low", "also_low", "negative", "none", "text"]
+        );
+    }
+
+    struct RankedCompleter;
+
+    impl Completer for RankedCompleter {
+        fn complete(&mut self, _line: &str, pos: usize) -> Vec<Suggestion> {
+            [
+                ("unranked", None),
+                ("weak", Some(-3)),
+                ("best", Some(10)),
+                ("other_unranked", None),
+                ("good", Some(4)),
+                ("also_good", Some(4)),
+            ]
+            .into_iter()
+            .map(|(value, score)| Suggestion {
+                score,
+                ..Suggestion::new(value, Span::new(0, pos))
+            })
+            .collect()
+        }
+    }
+
+    #[rstest]
+    #[case(false, &["unranked", "weak", "best", "other_unranked", "good", "also_good"])]
+    #[case(true, &["best", "good", "also_good", "weak", "unranked", "other_unranked"])]
+    fn test_score_sort(#[case] score_sort: bool, #[case] expected: &[&str]) {
+        let mut menu = ColumnarMenu::default().with_score_sort(score_sort);
+        let mut editor = Editor::default();
+
+        menu.update_values(&mut editor, &mut RankedCompleter);
+
+        let values: Vec<&str> = menu
+            .get_values()
+            .iter()
+            .map(|suggestion| suggestion.value.as_str())
+            .collect();
+        assert_eq!(values, expected);
+    }
+
+    #[rstest]
+    #[case(false, true, 6, "\u{1b}[32mfn")]
+    #[case(true, true, 5, "\u{1b}[32mfn(a:\u{1b}[0m")]
+    #[case(true, true, 20, "\u{1b}[32mfn(a: i32)\u{1b}[0m")]
+    #[case(true, false, 5, "fn(a:")]
+    fn test_styled_description_text(
+        #[case] styled_descriptions: bool,
+        #[case] use_ansi_coloring: bool,
+        #[case] width: usize,
+        #[case] expected: &str,
+    ) {
+        let menu = ColumnarMenu::default().with_styled_descriptions(styled_descriptions);
+
+        assert_eq!(
+            menu.description_text("\u{1b}[32mfn(a: i32)\u{1b}[0m", width, use_ansi_coloring),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_styled_description_skips_description_style() {
+        let mut menu = ColumnarMenu::default().with_styled_descriptions(true);
+        menu.working_details.col_width = 12;
+        menu.longest_suggestion = 3;
+        let suggestion = Suggestion {
+            description: Some("\u{1b}[32mi32\u{1b}[0m".into()),
+            ..fake_suggestion("abc", 0)
+        };
+
+        assert_eq!(
+            menu.create_string(&suggestion, 1, 0, 0, true),
+            format!(
+                "{}abc  {RESET}\u{1b}[32mi32\u{1b}[0m{RESET}\r\n",
+                menu.color.text_style.prefix()
+            )
+        );
+    }
+
+    #[test]
+    fn test_styled_description_keeps_the_selected_style() {
+        let mut menu = ColumnarMenu::default().with_styled_descriptions(true);
+        menu.working_details.col_width = 12;
+        menu.longest_suggestion = 3;
+        let suggestion = Suggestion {
+            description: Some("\u{1b}[32mi32\u{1b}[0m".into()),
+            ..fake_suggestion("abc", 0)
+        };
+        let selected = menu.color.selected_text_style.prefix();
+
+        assert_eq!(
+            menu.create_string(&suggestion, 0, 0, 0, true),
+            format!("{selected}abc  \u{1b}[32m{selected}i32\u{1b}[0m{selected}{RESET}\r\n")
+        );
+    }
+
+    struct WordCompleter(&'static str);
+
+    impl Completer for WordCompleter {
+        fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+            let start = line[..pos].rfind(' ').map(|index| index + 1).unwrap_or(0);
+            vec![Suggestion {
+                value: self.0.to_string(),
+                span: Span { start, end: pos },
+                ..Suggestion::default()
+            }]
+        }
+    }
+
+    /// Completes the whole word around the cursor, also the text after it
+    struct WholeWordCompleter(Vec<&'static str>);
+
+    impl Completer for WholeWordCompleter {
+        fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+            let start = line[..pos].rfind(' ').map(|index| index + 1).unwrap_or(0);
+            let end = line[pos..]
+                .find(' ')
+                .map(|index| pos + index)
+                .unwrap_or(line.len());
+            self.0
+                .iter()
+                .map(|value| Suggestion {
+                    value: value.to_string(),
+                    span: Span { start, end },
+                    ..Suggestion::default()
+                })
+                .collect()
+        }
+    }
+
+    fn editor_with_cursor(buffer: &str, cursor: usize) -> Editor {
+        let mut editor = Editor::default();
+        editor.set_buffer(buffer.to_string(), UndoBehavior::CreateUndoPoint);
+        let mut line_buffer = editor.line_buffer().clone();
+        line_buffer.set_insertion_point(cursor);
+        editor.set_line_buffer(line_buffer, UndoBehavior::CreateUndoPoint);
+        editor
+    }
+
+    #[rstest]
+    #[case("fooBAR", 3, "foo", "foo", 3)]
+    #[case("echo fooBAR rest", 8, "foobar", "echo foobar rest", 11)]
+    #[case("echo fBAR", 6, "foo", "echo foo", 8)]
+    fn test_replace_span_after_cursor(
+        #[case] buffer: &str,
+        #[case] cursor: usize,
+        #[case] value: &'static str,
+        #[case] expected_buffer: &str,
+        #[case] expected_cursor: usize,
+    ) {
+        let mut completer = WholeWordCompleter(vec![value]);
+        let mut menu = ColumnarMenu::default();
+        let mut editor = editor_with_cursor(buffer, cursor);
+
+        menu.update_values(&mut editor, &mut completer);
+        menu.replace_in_buffer(&mut editor);
+
+        assert_eq!(editor.get_buffer(), expected_buffer);
+        assert_eq!(editor.insertion_point(), expected_cursor);
+    }
+
+    #[test]
+    fn test_partial_completion_replaces_span_after_cursor() {
+        let mut completer = WholeWordCompleter(vec!["foobar1", "foobar2"]);
+        let mut menu = ColumnarMenu::default();
+        let mut editor = editor_with_cursor("echo fooBAR rest", 8);
+
+        assert!(menu.can_partially_complete(false, &mut editor, &mut completer));
+        assert_eq!(editor.get_buffer(), "echo foobar rest");
+        assert_eq!(editor.insertion_point(), 11);
+
+        // The typed text before the cursor is not overwritten
+        let mut editor = editor_with_cursor("echo fxBAR", 7);
+        assert!(!menu.can_partially_complete(false, &mut editor, &mut completer));
+        assert_eq!(editor.get_buffer(), "echo fxBAR");
+    }
+
+    #[rstest]
+    #[case(QuoteHandling::Inside, "cat 'fi' rest", 7, "cat 'file.txt' rest", 13)]
+    #[case(
+        QuoteHandling::Inside,
+        "cat \"fi\" rest",
+        7,
+        "cat \"file.txt\" rest",
+        13
+    )]
+    #[case(QuoteHandling::Inside, "cat `fi` rest", 7, "cat `file.txt` rest", 13)]
+    #[case(QuoteHandling::Inside, "cat 'fi", 7, "cat 'file.txt", 13)]
+    #[case(QuoteHandling::Extend, "cat 'fi' rest", 7, "cat 'file.txt' rest", 14)]
+    #[case(
+        QuoteHandling::Extend,
+        "cat \"fi\" rest",
+        7,
+        "cat \"file.txt\" rest",
+        14
+    )]
+    #[case(QuoteHandling::Extend, "cat `fi` rest", 7, "cat `file.txt` rest", 14)]
+    #[case(QuoteHandling::Extend, "cat `fi", 7, "cat `file.txt`", 14)]
+    #[case(QuoteHandling::Ignore, "cat fi rest", 6, "cat file.txt rest", 12)]
+    fn test_menu_replace_quoted_token(
+        #[case] quote_handling: QuoteHandling,
+        #[case] buffer: &str,
+        #[case] cursor: usize,
+        #[case] expected_buffer: &str,
+        #[case] expected_cursor: usize,
+    ) {
+        let mut completer = WordCompleter("file.txt");
+        let mut menu = ColumnarMenu::default().with_quote_handling(quote_handling);
+        let mut editor = Editor::default();
+        editor.set_buffer(buffer.to_string(), UndoBehavior::CreateUndoPoint);
+        let mut line_buffer = editor.line_buffer().clone();
+        line_buffer.set_insertion_point(cursor);
+        editor.set_line_buffer(line_buffer, UndoBehavior::CreateUndoPoint);
+
+        menu.update_values(&mut editor, &mut completer);
+        menu.replace_in_buffer(&mut editor);
+
+        assert_eq!(editor.get_buffer(), expected_buffer);
+        assert_eq!(editor.insertion_point(), expected_cursor);
+    }
+
+    #[rstest]
+    #[case(QuoteHandling::Inside, Append::None, "cat 'fi'", "cat 'file.txt'", 13)]
+    #[case(
+        QuoteHandling::Inside,
+        Append::Space,
+        "cat 'fi'",
+        "cat 'file.txt' ",
+        15
+    )]
+    #[case(
+        QuoteHandling::Inside,
+        Append::Char('/'),
+        "cat \"fi\" rest",
+        "cat \"file.txt\"/ rest",
+        15
+    )]
+    #[case(QuoteHandling::Inside, Append::Space, "cat 'fi", "cat 'file.txt ", 14)]
+    #[case(
+        QuoteHandling::Extend,
+        Append::Space,
+        "cat 'fi'",
+        "cat 'file.txt' ",
+        15
+    )]
+    fn test_menu_replace_quoted_token_append(
+        #[case] quote_handling: QuoteHandling,
+        #[case] append: Append,
+        #[case] buffer: &str,
+        #[case] expected_buffer: &str,
+        #[case] expected_cursor: usize,
+    ) {
+        struct AppendCompleter(Append);
+
+        impl Completer for AppendCompleter {
+            fn complete(&mut self, _line: &str, pos: usize) -> Vec<Suggestion> {
+                vec![Suggestion::new("file.txt", Span::new(4, pos)).with_append(self.0)]
+            }
+        }
+
+        let mut completer = AppendCompleter(append);
+        let mut menu = ColumnarMenu::default().with_quote_handling(quote_handling);
+        let mut editor = Editor::default();
+        editor.set_buffer(buffer.to_string(), UndoBehavior::CreateUndoPoint);
+        let mut line_buffer = editor.line_buffer().clone();
+        line_buffer.set_insertion_point(7);
+        editor.set_line_buffer(line_buffer, UndoBehavior::CreateUndoPoint);
+
+        menu.update_values(&mut editor, &mut completer);
+        menu.replace_in_buffer(&mut editor);
+
+        assert_eq!(editor.get_buffer(), expected_buffer);
+        assert_eq!(editor.insertion_point(), expected_cursor);
+    }
 }
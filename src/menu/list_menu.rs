@@ -1,21 +1,25 @@
-use crate::{core_editor::Editor, UndoBehavior};
+use crate::core_editor::Editor;
 
 use {
     super::{
-        menu_functions::{parse_selection_char, string_difference},
+        menu_functions::{
+            find_match, fuzzy_match, insert_suggestion, parse_selection_char, string_difference,
+        },
         Menu, MenuEvent, MenuTextStyle,
     },
     crate::{
-        painting::{estimate_single_line_wraps, Painter},
-        Completer, Suggestion,
+        painting::{estimate_single_line_wraps, line_width, Painter},
+        Completer, Span, Suggestion,
     },
     nu_ansi_term::{ansi::RESET, Style},
     std::iter::Sum,
-    unicode_width::UnicodeWidthStr,
 };
 
 const SELECTION_CHAR: char = '!';
 
+/// Default number of values the fuzzy search looks through
+const DEFAULT_FUZZY_SEARCH_LIMIT: usize = 1000;
+
 struct Page {
     size: usize,
     full: bool,
@@ -77,6 +81,16 @@ pub struct ListMenu {
     /// Calls the completer using only the line buffer difference difference
     /// after the menu was activated
     only_buffer_difference: bool,
+    /// Matches the values fuzzily instead of by substring
+    fuzzy_search: bool,
+    /// Number of values the fuzzy search looks through
+    fuzzy_search_limit: usize,
+    /// Text the values were searched for, without the selection marker
+    query: String,
+    /// Deleting the selected value has to be requested twice
+    delete_confirmation: bool,
+    /// The deletion of the selected value was requested once
+    delete_requested: bool,
 }
 
 impl Default for ListMenu {
@@ -97,6 +111,11 @@ impl Default for ListMenu {
             event: None,
             input: None,
             only_buffer_difference: true,
+            fuzzy_search: false,
+            fuzzy_search_limit: DEFAULT_FUZZY_SEARCH_LIMIT,
+            query: String::new(),
+            delete_confirmation: false,
+            delete_requested: false,
         }
     }
 }
@@ -131,6 +150,14 @@ impl ListMenu {
         self
     }
 
+    /// Menu builder with new value for the style of the characters matching the
+    /// typed text. The matches are not highlighted when it is not set
+    #[must_use]
+    pub fn with_match_text_style(mut self, match_text_style: Style) -> Self {
+        self.color.match_style = Some(match_text_style);
+        self
+    }
+
     /// Menu builder with new page size
     #[must_use]
     pub fn with_page_size(mut self, page_size: usize) -> Self {
@@ -155,6 +182,40 @@ impl ListMenu {
         self
     }
 
+    /// Menu builder that matches the values fuzzily, like `gtrb` matches `git rebase`
+    ///
+    /// The values are the first values of the completer, asked with an empty line, that
+    /// contain the typed characters in order, see [`ListMenu::with_fuzzy_search_limit`].
+    /// They are sorted by how well they match, favoring contiguous characters and
+    /// characters at the start of words. Without it the completer searches the values
+    /// containing the typed text
+    #[must_use]
+    pub fn with_fuzzy_search(mut self, fuzzy_search: bool) -> Self {
+        self.fuzzy_search = fuzzy_search;
+        self
+    }
+
+    /// Menu builder with the number of values the fuzzy search looks through, e.g.
+    /// the most recent history entries. Defaults to 1000
+    ///
+    /// They are requested with [`Completer::partial_complete`], so a completer can
+    /// avoid loading all of its values
+    #[must_use]
+    pub fn with_fuzzy_search_limit(mut self, limit: usize) -> Self {
+        self.fuzzy_search_limit = limit;
+        self
+    }
+
+    /// Menu builder that asks to confirm the deletion of the selected value
+    ///
+    /// The first [`ReedlineEvent::MenuDelete`](crate::ReedlineEvent::MenuDelete) marks the
+    /// value in the banner, it is only deleted if the event is repeated right away
+    #[must_use]
+    pub fn with_delete_confirmation(mut self, delete_confirmation: bool) -> Self {
+        self.delete_confirmation = delete_confirmation;
+        self
+    }
+
     /// Menu builder with max entry lines
     #[must_use]
     pub fn with_max_entry_lines(mut self, max_lines: u16) -> Self {
@@ -226,7 +287,7 @@ impl ListMenu {
                                     &suggestion.value,
                                     //  to account for the index and the indicator e.g. 0: XXXX
                                     painter.screen_width().saturating_sub(
-                                        self.indicator().width() as u16 + count_digits(lines),
+                                        line_width(self.indicator()) as u16 + count_digits(lines),
                                     ),
                                 );
 
@@ -266,13 +327,19 @@ impl ListMenu {
         };
 
         let full_page = if page.full { "[FULL]" } else { "" };
+        let delete = if self.delete_requested {
+            "  [DELETE? repeat to confirm]"
+        } else {
+            ""
+        };
         let status_bar = format!(
-            "Page {}: records {} - {}  total: {}  {}",
+            "Page {}: records {} - {}  total: {}  {}{}",
             self.page + 1,
             value_before,
             values_until,
             self.total_values(),
             full_page,
+            delete,
         );
 
         if use_ansi_coloring {
@@ -301,6 +368,38 @@ impl ListMenu {
         }
     }
 
+    /// Value with the characters matching the typed text highlighted. The text style
+    /// of the row is restored after every match
+    fn highlight_match(&self, value: &str, index: usize) -> String {
+        let match_style = match self.color.match_style {
+            Some(match_style) => match_style,
+            None => return value.to_string(),
+        };
+        let positions = if self.fuzzy_search {
+            fuzzy_match(value, &self.query).map_or_else(Vec::new, |(_, positions)| positions)
+        } else {
+            find_match(value, &self.query).map_or_else(Vec::new, |range| {
+                value[range.clone()]
+                    .char_indices()
+                    .map(|(offset, _)| range.start + offset)
+                    .collect()
+            })
+        };
+
+        let mut highlighted = String::with_capacity(value.len());
+        for (offset, c) in value.char_indices() {
+            if positions.binary_search(&offset).is_ok() {
+                highlighted.push_str(&match_style.prefix().to_string());
+                highlighted.push(c);
+                highlighted.push_str(RESET);
+                highlighted.push_str(&self.text_style(index));
+            } else {
+                highlighted.push(c);
+            }
+        }
+        highlighted
+    }
+
     /// Creates default string that represents one line from a menu
     fn create_string(
         &self,
@@ -381,6 +480,7 @@ impl Menu for ListMenu {
 
     /// Selects what type of event happened with the menu
     fn menu_event(&mut self, event: MenuEvent) {
+        self.delete_requested = false;
         match &event {
             MenuEvent::Activate(_) => self.active = true,
             MenuEvent::Deactivate => {
@@ -414,6 +514,7 @@ impl Menu for ListMenu {
 
         let parsed = parse_selection_char(input, SELECTION_CHAR);
         self.update_row_pos(parsed.index);
+        self.query = parsed.remainder.to_string();
 
         // If there are no row selector and the menu has an Edit event, this clears
         // the position together with the pages vector
@@ -432,12 +533,34 @@ impl Menu for ListMenu {
                 .unwrap_or(self.page_size);
 
             completer.partial_complete(input, start, skip, take)
+        } else if self.fuzzy_search {
+            self.query_size = None;
+            let span = Span::new(start, start + input.len());
+            let mut values: Vec<Suggestion> = completer
+                .partial_complete("", start, 0, self.fuzzy_search_limit)
+                .into_iter()
+                .filter_map(|suggestion| {
+                    let (score, _) = fuzzy_match(&suggestion.value, parsed.remainder)?;
+                    Some(Suggestion {
+                        span,
+                        score: Some(score),
+                        ..suggestion
+                    })
+                })
+                .collect();
+            // `sort_by_key` is stable, so values with the same score keep their order
+            values.sort_by_key(|suggestion| std::cmp::Reverse(suggestion.score));
+            values
         } else {
             self.query_size = None;
             completer.complete(input, start)
         }
     }
 
+    fn get_selected_value(&self) -> Option<Suggestion> {
+        self.get_value()
+    }
+
     /// Gets values from cached values that will be displayed in the menu
     fn get_values(&self) -> &[Suggestion] {
         if self.query_size.is_some() {
@@ -467,26 +590,8 @@ impl Menu for ListMenu {
 
     /// The buffer gets cleared with the actual value
     fn replace_in_buffer(&self, editor: &mut Editor) {
-        if let Some(Suggestion {
-            mut value,
-            span,
-            append_whitespace,
-            ..
-        }) = self.get_value()
-        {
-            let buffer_len = editor.line_buffer().len();
-            let start = span.start.min(buffer_len);
-            let end = span.end.min(buffer_len);
-            if append_whitespace {
-                value.push(' ');
-            }
-            let mut line_buffer = editor.line_buffer().clone();
-            line_buffer.replace_range(start..end, &value);
-
-            let mut offset = line_buffer.insertion_point();
-            offset += value.len().saturating_sub(end.saturating_sub(start));
-            line_buffer.set_insertion_point(offset);
-            editor.set_line_buffer(line_buffer, UndoBehavior::CreateUndoPoint);
+        if let Some(suggestion) = self.get_value() {
+            insert_suggestion(editor, suggestion);
         }
     }
 
@@ -587,12 +692,67 @@ impl Menu for ListMenu {
                     }
                     self.update_values(editor, completer);
                 }
+                MenuEvent::Input(_) => {
+                    // The list menu leaves the typed text to the line buffer
+                }
+                MenuEvent::Refresh => {
+                    self.update_values(editor, completer);
+                    // The last value of the page was deleted
+                    if self.get_values().is_empty() && self.page > 0 {
+                        self.page -= 1;
+                        self.update_values(editor, completer);
+                    }
+                    // The page shrinks when no later values fill the gap
+                    let printable_entries = self.printable_entries(painter);
+                    if let Some(page) = self.pages.get_mut(self.page) {
+                        page.size = page.size.min(printable_entries);
+                    }
+                    let last_row = self.get_values().len().saturating_sub(1) as u16;
+                    self.row_position = self.row_position.min(last_row);
+                }
+                MenuEvent::ToggleDocumentation
+                | MenuEvent::DocumentationUp
+                | MenuEvent::DocumentationDown => {
+                    // The list menu doesn't show the documentation of the values
+                }
             }
 
             self.event = None;
         }
     }
 
+    fn confirm_delete(&mut self) -> bool {
+        if !self.delete_confirmation || self.delete_requested {
+            self.delete_requested = false;
+            true
+        } else {
+            self.delete_requested = true;
+            false
+        }
+    }
+
+    fn select_at(&mut self, _column: u16, row: u16, terminal_columns: u16) -> Option<bool> {
+        let page_size = self.pages.get(self.page)?.size;
+        let mut lines = 0;
+        let index = self
+            .get_values()
+            .iter()
+            .take(page_size)
+            .enumerate()
+            .position(|(index, suggestion)| {
+                lines += self.number_of_lines(
+                    &suggestion.value,
+                    terminal_columns
+                        .saturating_sub(line_width(self.indicator()) as u16 + count_digits(index)),
+                );
+                row < lines
+            })?;
+
+        let was_selected = index == self.index();
+        self.row_position = index as u16;
+        Some(was_selected)
+    }
+
     /// Calculates the real required lines for the menu considering how many lines
     /// wrap the terminal and if an entry is larger than the remaining lines
     fn menu_required_lines(&self, terminal_columns: u16) -> u16 {
@@ -603,7 +763,7 @@ impl Menu for ListMenu {
                 + self.number_of_lines(
                     &suggestion.value,
                     terminal_columns.saturating_sub(
-                        self.indicator().width() as u16 + count_digits(entry_index),
+                        line_width(self.indicator()) as u16 + count_digits(entry_index),
                     ),
                 );
             entry_index += 1;
@@ -623,7 +783,11 @@ impl Menu for ListMenu {
                     .enumerate()
                     .map(|(index, suggestion)| {
                         // Final string with colors
-                        let line = &suggestion.value;
+                        let line = &if use_ansi_coloring {
+                            self.highlight_match(&suggestion.value, index)
+                        } else {
+                            suggestion.value.clone()
+                        };
                         let line = if line.lines().count() > self.max_lines as usize {
                             let lines = line
                                 .lines()
@@ -703,6 +867,7 @@ fn count_digits(mut n: usize) -> u16 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::UndoBehavior;
 
     #[test]
     fn number_of_lines_test() {
@@ -737,4 +902,136 @@ mod tests {
         // There is an extra line showing ...
         assert_eq!(res, 4);
     }
+
+    #[test]
+    fn select_at_counts_wrapped_lines() {
+        let mut menu = ListMenu {
+            values: ["a long entry", "b", "c"]
+                .iter()
+                .map(|value| Suggestion {
+                    value: value.to_string(),
+                    ..Suggestion::default()
+                })
+                .collect(),
+            pages: vec![Page {
+                size: 3,
+                full: false,
+            }],
+            ..Default::default()
+        };
+
+        // The first entry wraps onto a second line with the indicator and the index
+        assert_eq!(menu.select_at(0, 1, 12), Some(true));
+        assert_eq!(menu.select_at(0, 2, 12), Some(false));
+        assert_eq!(menu.get_value().unwrap().value, "b");
+        assert_eq!(menu.select_at(0, 4, 12), None);
+    }
+
+    #[test]
+    fn replace_in_buffer_replaces_span_after_cursor() {
+        let menu = ListMenu {
+            values: vec![Suggestion {
+                value: "foobar".to_string(),
+                span: Span::new(5, 11),
+                ..Suggestion::default()
+            }],
+            ..Default::default()
+        };
+        let mut editor = Editor::default();
+        editor.set_buffer(
+            "echo fooBAR rest".to_string(),
+            UndoBehavior::CreateUndoPoint,
+        );
+        let mut line_buffer = editor.line_buffer().clone();
+        line_buffer.set_insertion_point(8);
+        editor.set_line_buffer(line_buffer, UndoBehavior::CreateUndoPoint);
+
+        menu.replace_in_buffer(&mut editor);
+
+        assert_eq!(editor.get_buffer(), "echo foobar rest");
+        assert_eq!(editor.insertion_point(), 11);
+    }
+
+    #[test]
+    fn delete_waits_for_confirmation() {
+        let page = Page {
+            size: 0,
+            full: false,
+        };
+        assert!(ListMenu::default().confirm_delete());
+
+        let mut menu = ListMenu::default().with_delete_confirmation(true);
+        assert!(!menu.confirm_delete());
+        assert!(menu
+            .banner_message(&page, false)
+            .ends_with("[DELETE? repeat to confirm]"));
+        assert!(menu.confirm_delete());
+        assert!(!menu.banner_message(&page, false).contains("DELETE"));
+
+        // Any other menu event cancels the request
+        assert!(!menu.confirm_delete());
+        menu.menu_event(MenuEvent::NextElement);
+        assert!(!menu.confirm_delete());
+    }
+
+    fn fuzzy_menu(query: &str) -> ListMenu {
+        fuzzy_menu_with_limit(query, DEFAULT_FUZZY_SEARCH_LIMIT)
+    }
+
+    fn fuzzy_menu_with_limit(query: &str, limit: usize) -> ListMenu {
+        use crate::{
+            completion::history::HistoryCompleter, FileBackedHistory, History, HistoryItem,
+        };
+
+        let mut history = FileBackedHistory::default();
+        for command in ["git rebase main", "cargo test", "git status", "grep -rb x"] {
+            history
+                .save(HistoryItem::from_command_line(command))
+                .unwrap();
+        }
+        let mut editor = Editor::default();
+        editor.set_buffer(query.to_string(), UndoBehavior::CreateUndoPoint);
+
+        let mut menu = ListMenu::default()
+            .with_only_buffer_difference(false)
+            .with_fuzzy_search(true)
+            .with_fuzzy_search_limit(limit)
+            .with_match_text_style(Style::new().underline());
+        menu.update_values(&mut editor, &mut HistoryCompleter::new(&history));
+        menu
+    }
+
+    #[test]
+    fn fuzzy_search_orders_values_by_score() {
+        let menu = fuzzy_menu("gtrb");
+        let values: Vec<&str> = menu.values.iter().map(|s| s.value.as_str()).collect();
+
+        assert_eq!(values, ["git rebase main"]);
+
+        let menu = fuzzy_menu("gs");
+        let values: Vec<&str> = menu.values.iter().map(|s| s.value.as_str()).collect();
+        assert_eq!(values, ["git status", "git rebase main", "cargo test"]);
+    }
+
+    #[test]
+    fn fuzzy_search_looks_through_the_most_recent_values() {
+        let menu = fuzzy_menu_with_limit("gs", 2);
+        let values: Vec<&str> = menu.values.iter().map(|s| s.value.as_str()).collect();
+
+        assert_eq!(values, ["git status"]);
+    }
+
+    #[test]
+    fn fuzzy_matches_are_highlighted() {
+        let menu = fuzzy_menu("gtrb");
+        let underline = Style::new().underline().prefix().to_string();
+        let base = menu.text_style(1);
+
+        assert_eq!(
+            menu.highlight_match("git rebase", 1),
+            format!(
+                "{underline}g{RESET}{base}i{underline}t{RESET}{base} {underline}r{RESET}{base}e{underline}b{RESET}{base}ase"
+            )
+        );
+    }
 }
@@ -1,5 +1,11 @@
 //! Collection of common functions that can be used to create menus
-use crate::Suggestion;
+use crate::{
+    core_editor::Editor,
+    painting::{grapheme_width, line_width},
+    Append, Snippet, Span, Suggestion, UndoBehavior,
+};
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
 
 /// Index result obtained from parsing a string with an index marker
 /// For example, the next string:
@@ -245,9 +251,784 @@ pub fn string_difference<'a>(new_string: &'a str, old_string: &str) -> (usize, &
     }
 }
 
+/// Splits the text in lines that fit in the given width, breaking at whitespace
+///
+/// Line breaks in the text are kept and words wider than the width are split.
+pub(crate) fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word = word;
+            while line_width(word) > width {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                let split = word
+                    .grapheme_indices(true)
+                    .scan(0, |acc, (index, grapheme)| {
+                        *acc += grapheme_width(grapheme);
+                        Some((index, *acc))
+                    })
+                    .find(|(_, acc)| *acc > width)
+                    .map(|(index, _)| index)
+                    .unwrap_or(word.len())
+                    .max(word.graphemes(true).next().map(str::len).unwrap_or(0));
+                lines.push(word[..split].to_string());
+                word = &word[split..];
+            }
+
+            if word.is_empty() {
+                continue;
+            }
+            if line.is_empty() {
+                line.push_str(word);
+            } else if line_width(&line) + 1 + line_width(word) <= width {
+                line.push(' ');
+                line.push_str(word);
+            } else {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            }
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Splits the text in lines that fit in the given width, keeping every character
+///
+/// Unlike [`wrap_text`] the lines are broken at the width instead of at whitespace,
+/// so the whitespace of the text is shown as it is. Line breaks in the text are kept.
+pub(crate) fn wrap_exact(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        let mut columns = 0;
+        for grapheme in paragraph.graphemes(true) {
+            let grapheme_width = grapheme_width(grapheme);
+            if columns + grapheme_width > width && !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+                columns = 0;
+            }
+            line.push_str(grapheme);
+            columns += grapheme_width;
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Finds the range of the value that matches the typed text
+///
+/// An exact match is looked for first, then a match ignoring the case the way
+/// [`matches_smart_case`] does. The first occurrence is returned, so a prefix match
+/// is preferred.
+pub(crate) fn find_match(value: &str, typed: &str) -> Option<Range<usize>> {
+    if typed.is_empty() {
+        return None;
+    }
+
+    find_with_case(value, typed, true).or_else(|| find_with_case(value, typed, false))
+}
+
+/// Byte range of the candidate matched by a query
+pub type MatchRange = Range<usize>;
+
+/// Matches `query` in `candidate` with smart case
+///
+/// A query in lowercase matches ignoring the case, comparing the Unicode case
+/// folding of both strings, so `"straße"` matches `"STRASSE"`. A query containing
+/// an uppercase letter only matches with the exact case. Returns the byte range of
+/// the first match in `candidate`, ready to be highlighted. An empty query matches
+/// the start of every candidate.
+///
+/// # Example
+///
+/// ```rust
+/// use reedline::menu_functions::matches_smart_case;
+///
+/// assert_eq!(matches_smart_case("read", "README.md"), Some(0..4));
+/// assert_eq!(matches_smart_case("Read", "README.md"), None);
+/// assert_eq!(matches_smart_case("straße", "STRASSE"), Some(0..7));
+/// ```
+pub fn matches_smart_case(query: &str, candidate: &str) -> Option<MatchRange> {
+    if query.is_empty() {
+        return Some(0..0);
+    }
+
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    find_with_case(candidate, query, case_sensitive)
+}
+
+/// Finds the first match of a non empty `query` in `text`, comparing the case folded
+/// characters unless `case_sensitive` is set
+fn find_with_case(text: &str, query: &str, case_sensitive: bool) -> Option<Range<usize>> {
+    if case_sensitive {
+        text.find(query).map(|start| start..start + query.len())
+    } else {
+        find_ignoring_case(text, query)
+    }
+}
+
+/// Full case folding of a character, e.g. `ß` and `ẞ` fold to `ss`
+///
+/// The lowercase form of the uppercase form of the lowercase character matches the
+/// full case folding of Unicode, apart from a few characters like the Cherokee ones.
+fn fold_case(c: char) -> impl Iterator<Item = char> {
+    c.to_lowercase()
+        .flat_map(char::to_uppercase)
+        .flat_map(char::to_lowercase)
+}
+
+/// Finds the first match of `query` in `text` comparing their case folded characters
+///
+/// The folded form of a character can be longer than the character, so the match
+/// is mapped back to whole characters of `text`.
+fn find_ignoring_case(text: &str, query: &str) -> Option<Range<usize>> {
+    let query: Vec<char> = query.chars().flat_map(fold_case).collect();
+    if query.is_empty() {
+        return None;
+    }
+
+    // Every folded character with the byte range of the character it comes from
+    let folded: Vec<(char, Range<usize>)> = text
+        .char_indices()
+        .flat_map(|(index, c)| {
+            fold_case(c).map(move |folded| (folded, index..index + c.len_utf8()))
+        })
+        .collect();
+    let starts_char =
+        |position: usize| position == 0 || folded[position - 1].1.start != folded[position].1.start;
+    let ends_char = |position: usize| {
+        folded
+            .get(position + 1)
+            .map_or(true, |(_, next)| next.start != folded[position].1.start)
+    };
+
+    (0..=folded.len().checked_sub(query.len())?)
+        .filter(|&start| starts_char(start) && ends_char(start + query.len() - 1))
+        .find(|&start| {
+            folded[start..start + query.len()]
+                .iter()
+                .map(|(c, _)| *c)
+                .eq(query.iter().copied())
+        })
+        .map(|start| folded[start].1.start..folded[start + query.len() - 1].1.end)
+}
+
+// Scores of the fuzzy matcher
+const FUZZY_MATCH: i64 = 16;
+const FUZZY_WORD_START: i64 = 8;
+const FUZZY_CONSECUTIVE: i64 = 8;
+const FUZZY_GAP: i64 = 1;
+
+/// Fuzzy matches `query` as a subsequence of `value`, ignoring case
+///
+/// Returns the score of the best match together with the byte offsets of the matched
+/// characters. Characters matched right after the previous one and at the start of a
+/// word score higher, every character skipped between two matches lowers the score.
+/// An empty query matches every value with a score of 0.
+pub(crate) fn fuzzy_match(value: &str, query: &str) -> Option<(i64, Vec<usize>)> {
+    let value: Vec<(usize, char)> = value.char_indices().collect();
+    let query: Vec<char> = query.chars().collect();
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    if query.len() > value.len() {
+        return None;
+    }
+
+    let same = |a: char, b: char| a == b || a.to_lowercase().eq(b.to_lowercase());
+    let word_start = |index: usize| {
+        let c = value[index].1;
+        match index.checked_sub(1).map(|previous| value[previous].1) {
+            None => true,
+            Some(previous) => {
+                (!previous.is_alphanumeric() && c.is_alphanumeric())
+                    || (previous.is_lowercase() && c.is_uppercase())
+            }
+        }
+    };
+    let char_score = |index: usize| {
+        FUZZY_MATCH
+            + if word_start(index) {
+                FUZZY_WORD_START
+            } else {
+                0
+            }
+    };
+
+    // `scores[i][j]` is the best score with the `i`th query character matched at the
+    // `j`th value character, `from[i][j]` where the previous query character matched
+    let mut scores = vec![vec![None; value.len()]; query.len()];
+    let mut from = vec![vec![0; value.len()]; query.len()];
+    for (j, &(_, c)) in value.iter().enumerate() {
+        if same(c, query[0]) {
+            scores[0][j] = Some(char_score(j));
+        }
+    }
+    for i in 1..query.len() {
+        // Best score of a previous match at least two characters back, with its gap
+        let mut best_gap: Option<(i64, usize)> = None;
+        for j in i..value.len() {
+            best_gap = best_gap.map(|(score, k)| (score - FUZZY_GAP, k));
+            if j >= 2 {
+                if let Some(score) = scores[i - 1][j - 2] {
+                    if best_gap.map_or(true, |(best, _)| score - FUZZY_GAP > best) {
+                        best_gap = Some((score - FUZZY_GAP, j - 2));
+                    }
+                }
+            }
+            if !same(value[j].1, query[i]) {
+                continue;
+            }
+            let consecutive = scores[i - 1][j - 1].map(|score| (score + FUZZY_CONSECUTIVE, j - 1));
+            let previous = match (consecutive, best_gap) {
+                (Some(a), Some(b)) => Some(if a.0 >= b.0 { a } else { b }),
+                (a, b) => a.or(b),
+            };
+            if let Some((score, k)) = previous {
+                scores[i][j] = Some(score + char_score(j));
+                from[i][j] = k;
+            }
+        }
+    }
+
+    let last = query.len() - 1;
+    let (mut j, score) = scores[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, score)| score.map(|score| (j, score)))
+        .max_by_key(|&(j, score)| (score, std::cmp::Reverse(j)))?;
+    let mut positions = vec![value[j].0];
+    for i in (1..=last).rev() {
+        j = from[i][j];
+        positions.push(value[j].0);
+    }
+    positions.reverse();
+
+    Some((score, positions))
+}
+
+/// Keeps the leading characters of the text that fit in `width` display columns
+///
+/// Wide characters take two columns and are dropped if only one column is left
+pub(crate) fn truncate_to_width(text: &str, width: usize) -> &str {
+    let mut columns = 0;
+    let end = text
+        .grapheme_indices(true)
+        .find(|(_, grapheme)| {
+            columns += grapheme_width(grapheme);
+            columns > width
+        })
+        .map_or(text.len(), |(index, _)| index);
+
+    &text[..end]
+}
+
+/// Pads the text with trailing whitespace until it takes `width` display columns
+///
+/// Escape sequences styling the text take no columns
+pub(crate) fn pad_to_width(text: &str, width: usize) -> String {
+    format!(
+        "{text}{:pad$}",
+        "",
+        pad = width.saturating_sub(line_width(text))
+    )
+}
+
+/// Characters recognized as quotes by [`find_quoted_token`]
+const QUOTES: [char; 3] = ['"', '\'', '`'];
+
+/// Alignment of a text in its cell of a menu
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    /// The text starts at the left edge of the cell
+    #[default]
+    Left,
+    /// The text ends at the right edge of the cell, like the numbers of a table
+    Right,
+}
+
+impl Alignment {
+    /// Columns of whitespace before a text `text_width` columns wide to align it in a
+    /// cell `width` columns wide
+    pub(crate) fn padding(self, text_width: usize, width: usize) -> usize {
+        match self {
+            Alignment::Left => 0,
+            Alignment::Right => width.saturating_sub(text_width),
+        }
+    }
+}
+
+/// Order of the values shown by a menu
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    /// The values keep the order of the completer
+    #[default]
+    None,
+    /// The values are sorted alphabetically, uppercase letters before lowercase ones
+    Alphabetical,
+    /// The values are sorted alphabetically ignoring the case. Values that only
+    /// differ in their case keep the order of the completer
+    AlphabeticalCaseInsensitive,
+}
+
+impl SortOrder {
+    /// Sorts the suggestions by their value. The sort is stable
+    pub fn sort(&self, suggestions: &mut [Suggestion]) {
+        match self {
+            SortOrder::None => {}
+            SortOrder::Alphabetical => suggestions.sort_by(|a, b| a.value.cmp(&b.value)),
+            SortOrder::AlphabeticalCaseInsensitive => {
+                suggestions.sort_by_cached_key(|suggestion| suggestion.value.to_lowercase());
+            }
+        }
+    }
+}
+
+/// Defines how a completion is inserted when the token under the cursor is quoted
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteHandling {
+    /// The span returned by the completer is replaced as is
+    #[default]
+    Ignore,
+    /// The completion is inserted between the quotes of the token. The quotes are
+    /// kept and the cursor is placed before the closing quote, unless a space or
+    /// character is appended: it goes after the closing quote, followed by the cursor
+    Inside,
+    /// The completion replaces the token including its quotes and is wrapped in
+    /// the same quotes. The cursor is placed after the closing quote
+    Extend,
+}
+
+/// Quote that encloses a position in the buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotedToken {
+    /// Character used to open the quote
+    pub quote: char,
+    /// Index of the opening quote
+    pub open: usize,
+    /// Index of the closing quote, if the quote is closed
+    pub close: Option<usize>,
+}
+
+/// Finds the quote in which the position is located
+///
+/// Single quotes, double quotes and backticks are recognized. Quote characters
+/// found inside a different kind of quote are treated as regular characters
+/// and escape sequences are not interpreted.
+///
+/// ## Example usage
+/// ```
+/// use reedline::menu_functions::{find_quoted_token, QuotedToken};
+///
+/// let res = find_quoted_token("cat 'my file' rest", 8);
+/// assert_eq!(
+///     res,
+///     Some(QuotedToken {
+///         quote: '\'',
+///         open: 4,
+///         close: Some(12),
+///     })
+/// );
+/// assert_eq!(find_quoted_token("cat 'my file' rest", 15), None);
+/// ```
+pub fn find_quoted_token(line: &str, pos: usize) -> Option<QuotedToken> {
+    let pos = pos.min(line.len());
+    let mut open: Option<(usize, char)> = None;
+    for (index, c) in line[..pos].char_indices() {
+        match open {
+            Some((_, quote)) if c == quote => open = None,
+            None if QUOTES.contains(&c) => open = Some((index, c)),
+            _ => {}
+        }
+    }
+
+    open.map(|(open, quote)| QuotedToken {
+        quote,
+        open,
+        close: line[pos..].find(quote).map(|index| index + pos),
+    })
+}
+
+/// Adjusts the replaced span and the replacement value of a completion to the
+/// quotes of the token under the cursor, following the [`QuoteHandling`]
+///
+/// Quotes already surrounding the value are removed before it is inserted, so
+/// completers that quote their values don't duplicate the quotes.
+///
+/// ## Example usage
+/// ```
+/// use reedline::menu_functions::{quote_aware_replacement, QuoteHandling};
+/// use reedline::Span;
+///
+/// let line = "cat 'fi' rest";
+/// let (span, value) =
+///     quote_aware_replacement(line, 7, Span::new(4, 7), "file.txt", QuoteHandling::Inside);
+/// assert_eq!((span, value.as_str()), (Span::new(5, 7), "file.txt"));
+///
+/// let (span, value) =
+///     quote_aware_replacement(line, 7, Span::new(4, 7), "file.txt", QuoteHandling::Extend);
+/// assert_eq!((span, value.as_str()), (Span::new(4, 8), "'file.txt'"));
+/// ```
+pub fn quote_aware_replacement(
+    line: &str,
+    pos: usize,
+    span: Span,
+    value: &str,
+    handling: QuoteHandling,
+) -> (Span, String) {
+    let token = match (handling, find_quoted_token(line, pos)) {
+        (QuoteHandling::Ignore, _) | (_, None) => return (span, value.to_string()),
+        (_, Some(token)) => token,
+    };
+
+    let unquoted = value
+        .strip_prefix(token.quote)
+        .map(|value| value.strip_suffix(token.quote).unwrap_or(value))
+        .unwrap_or(value);
+
+    match handling {
+        QuoteHandling::Inside => {
+            let start = span.start.max(token.open + 1);
+            let end = match token.close {
+                Some(close) => span.end.min(close),
+                None => span.end,
+            }
+            .max(start);
+
+            (Span::new(start, end), unquoted.to_string())
+        }
+        _ => {
+            let start = span.start.min(token.open);
+            let end = match token.close {
+                Some(close) => span.end.max(close + 1),
+                None => span.end,
+            };
+
+            (
+                Span::new(start, end),
+                format!("{}{}{}", token.quote, unquoted, token.quote),
+            )
+        }
+    }
+}
+
+/// Replaces the `span` of the buffer with `value` followed by what `append` adds, and
+/// places the cursor after the inserted text
+pub(crate) fn replace_in_buffer(editor: &mut Editor, span: Span, value: &str, append: Append) {
+    let mut line_buffer = editor.line_buffer().clone();
+    let end = span.end.min(line_buffer.len());
+    let start = span.start.min(end);
+
+    let mut value = value.to_string();
+    match append {
+        Append::Space => value.push(' '),
+        Append::Char(c) => value.push(c),
+        Append::None | Append::MoveAfter(_) => {}
+    }
+    line_buffer.replace_range(start..end, &value);
+
+    // The replaced span can extend past the cursor
+    let mut insertion_point = start + value.len();
+    if let Append::MoveAfter(c) = append {
+        if line_buffer.get_buffer()[insertion_point..].starts_with(c) {
+            insertion_point += c.len_utf8();
+        }
+    }
+    line_buffer.set_insertion_point(insertion_point);
+    editor.set_line_buffer(line_buffer, UndoBehavior::CreateUndoPoint);
+}
+
+/// Inserts the suggestion in place of its span, as a snippet if it has one
+pub(crate) fn insert_suggestion(editor: &mut Editor, suggestion: Suggestion) {
+    let append = suggestion.append_policy();
+    let Suggestion {
+        value,
+        span,
+        snippet,
+        ..
+    } = suggestion;
+    match snippet {
+        Some(snippet) => insert_snippet(editor, span, &snippet),
+        None => replace_in_buffer(editor, span, &value, append),
+    }
+}
+
+/// Extends the typed text to the common prefix of the `values`. Returns whether the
+/// buffer changed
+pub(crate) fn complete_common_prefix(editor: &mut Editor, values: &[Suggestion]) -> bool {
+    if let (Some(Suggestion { value, span, .. }), Some(index)) = find_common_string(values) {
+        let index = index.min(value.len());
+        let matching = &value[0..index];
+
+        let buffer = editor.get_buffer();
+        let end = span.end.min(buffer.len());
+        let start = span.start.min(end);
+        // make sure that the partial completion does not overwrite user entered input.
+        // The text of the span after the cursor is replaced as well
+        let typed_end = editor.insertion_point().clamp(start, end);
+        let extends_input = matching.starts_with(&buffer[start..typed_end]);
+
+        if !matching.is_empty() && extends_input {
+            let mut line_buffer = editor.line_buffer().clone();
+            line_buffer.replace_range(start..end, matching);
+            line_buffer.set_insertion_point(start + matching.len());
+            editor.set_line_buffer(line_buffer, UndoBehavior::CreateUndoPoint);
+            return true;
+        }
+    }
+    false
+}
+
+/// Replaces the `span` of the buffer with the text of the `snippet` and places the
+/// cursor on its first placeholder, or after the text if it has none
+pub(crate) fn insert_snippet(editor: &mut Editor, span: Span, snippet: &Snippet) {
+    let mut line_buffer = editor.line_buffer().clone();
+    let end = span.end.min(line_buffer.len());
+    let start = span.start.min(end);
+
+    line_buffer.replace_range(start..end, &snippet.text);
+    line_buffer.set_insertion_point(start + snippet.text.len());
+    editor.set_line_buffer(line_buffer, UndoBehavior::CreateUndoPoint);
+    editor.set_tab_stops(
+        snippet
+            .tab_stops
+            .iter()
+            .map(|stop| start + stop.start..start + stop.end)
+            .collect(),
+    );
+}
+
+/// Lays out the values in columns that fit in `width`, filled from top to bottom
+/// like the completions listed by readline
+pub(crate) fn columns_of_values(values: &[String], width: usize) -> Vec<String> {
+    let column_width = values
+        .iter()
+        .map(|value| line_width(value))
+        .max()
+        .unwrap_or(0)
+        + 2;
+    let columns = (width / column_width).max(1);
+    let rows = (values.len() + columns - 1) / columns;
+
+    (0..rows)
+        .map(|row| {
+            let line: String = values
+                .iter()
+                .skip(row)
+                .step_by(rows)
+                .map(|value| {
+                    let padding = column_width - line_width(value);
+                    format!("{}{}", value, " ".repeat(padding))
+                })
+                .collect();
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use nu_ansi_term::Style;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("ls  -la", 4, vec!["ls  ", "-la"])]
+    #[case("a\nbcd", 2, vec!["a", "bc", "d"])]
+    #[case("日本語", 3, vec!["日", "本", "語"])]
+    #[case("", 3, vec![])]
+    #[case("e\u{301}e\u{301}e\u{301}", 2, vec!["e\u{301}e\u{301}", "e\u{301}"])]
+    #[case("\u{2764}\u{fe0f}ab", 3, vec!["\u{2764}\u{fe0f}a", "b"])]
+    fn test_wrap_exact(#[case] text: &str, #[case] width: usize, #[case] expected: Vec<&str>) {
+        assert_eq!(wrap_exact(text, width), expected);
+    }
+
+    #[rstest]
+    #[case(&["one", "two", "three"], 80, &["one    two    three"])]
+    #[case(&["one", "two", "three", "four", "five"], 15, &["one    four", "two    five", "three"])]
+    #[case(&["a_long_value", "b"], 5, &["a_long_value", "b"])]
+    #[case(&[], 80, &[])]
+    #[case(&["\u{2764}\u{fe0f}", "ab"], 80, &["\u{2764}\u{fe0f}  ab"])]
+    fn test_columns_of_values(
+        #[case] values: &[&str],
+        #[case] width: usize,
+        #[case] expected: &[&str],
+    ) {
+        let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        assert_eq!(columns_of_values(&values, width), expected);
+    }
+
+    #[rstest]
+    #[case("cat 'fi", 7, Some(('\'', 4, None)))]
+    #[case("cat \"fi\" rest", 7, Some(('"', 4, Some(7))))]
+    #[case("cat `fi` rest", 6, Some(('`', 4, Some(7))))]
+    #[case("cat \"it's\"", 8, Some(('"', 4, Some(9))))]
+    #[case("cat 'a' b", 8, None)]
+    #[case("cat fi", 6, None)]
+    fn quoted_token_test(
+        #[case] line: &str,
+        #[case] pos: usize,
+        #[case] expected: Option<(char, usize, Option<usize>)>,
+    ) {
+        let expected = expected.map(|(quote, open, close)| QuotedToken { quote, open, close });
+        assert_eq!(find_quoted_token(line, pos), expected);
+    }
+
+    #[test]
+    fn quote_aware_replacement_strips_quoted_values() {
+        let (span, value) = quote_aware_replacement(
+            "cat \"my",
+            7,
+            Span::new(4, 7),
+            "\"my file\"",
+            QuoteHandling::Inside,
+        );
+        assert_eq!(span, Span::new(5, 7));
+        assert_eq!(value, "my file");
+
+        let (span, value) = quote_aware_replacement(
+            "cat \"my",
+            7,
+            Span::new(4, 7),
+            "\"my file\"",
+            QuoteHandling::Extend,
+        );
+        assert_eq!(span, Span::new(4, 7));
+        assert_eq!(value, "\"my file\"");
+    }
+
+    #[rstest]
+    #[case("short text", 20, &["short text"])]
+    #[case("some words to wrap", 10, &["some words", "to wrap"])]
+    #[case("first\n\nthird", 10, &["first", "", "third"])]
+    #[case("abcdefghij", 4, &["abcd", "efgh", "ij"])]
+    #[case("a abcdefg", 4, &["a", "abcd", "efg"])]
+    #[case("e\u{301}e\u{301}e\u{301}", 2, &["e\u{301}e\u{301}", "e\u{301}"])]
+    fn wrap_text_test(#[case] text: &str, #[case] width: usize, #[case] expected: &[&str]) {
+        assert_eq!(wrap_text(text, width), expected);
+    }
+
+    #[rstest]
+    #[case("abcdef", 3, "abc", "abcdef")]
+    #[case("文件名", 4, "文件", "文件名")]
+    #[case("文件名", 3, "文", "文件名")]
+    #[case("é", 3, "é", "é  ")]
+    #[case("文", 4, "文", "文  ")]
+    #[case("\u{2764}\u{fe0f}ab", 2, "\u{2764}\u{fe0f}", "\u{2764}\u{fe0f}ab")]
+    fn width_aware_truncate_and_pad(
+        #[case] text: &str,
+        #[case] width: usize,
+        #[case] truncated: &str,
+        #[case] padded: &str,
+    ) {
+        assert_eq!(truncate_to_width(text, width), truncated);
+        assert_eq!(pad_to_width(text, width), padded);
+    }
+
+    #[test]
+    fn padding_ignores_escape_sequences() {
+        let styled = Style::new().bold().paint("ab").to_string();
+        assert_eq!(pad_to_width(&styled, 4), format!("{styled}  "));
+    }
+
+    #[rstest]
+    #[case("git rebase", "gtrb", Some(vec![0, 2, 4, 6]))]
+    #[case("git rebase", "GR", Some(vec![0, 4]))]
+    #[case("ls -la", "la", Some(vec![4, 5]))]
+    #[case("git rebase", "xyz", None)]
+    #[case("git", "", Some(vec![]))]
+    #[case("", "", Some(vec![]))]
+    fn fuzzy_match_positions(
+        #[case] value: &str,
+        #[case] query: &str,
+        #[case] expected: Option<Vec<usize>>,
+    ) {
+        assert_eq!(
+            fuzzy_match(value, query).map(|(_, positions)| positions),
+            expected
+        );
+    }
+
+    #[rstest]
+    // Contiguous matches
+    #[case("git status", "git stash", "stat")]
+    // Matches at the start of words
+    #[case("cargo build", "echo cargbuild", "cb")]
+    // Shorter gaps
+    #[case("make test", "makefile test", "mt")]
+    fn fuzzy_match_ranking(#[case] better: &str, #[case] worse: &str, #[case] query: &str) {
+        let score = |value| fuzzy_match(value, query).map(|(score, _)| score);
+        assert!(
+            score(better) > score(worse),
+            "{:?} <= {:?}",
+            score(better),
+            score(worse)
+        );
+    }
+
+    #[rstest]
+    #[case("checkout", "che", Some(0..3))]
+    #[case("git-checkout", "check", Some(4..9))]
+    #[case("Checkout", "che", Some(0..3))]
+    #[case("checkout", "", None)]
+    #[case("checkout", "xyz", None)]
+    #[case("İstanbul", "i̇st", Some(0..4))]
+    #[case("STRASSE", "straße", Some(0..7))]
+    #[case("Straße", "STRAS", None)]
+    fn find_match_test(
+        #[case] value: &str,
+        #[case] typed: &str,
+        #[case] expected: Option<Range<usize>>,
+    ) {
+        assert_eq!(find_match(value, typed), expected);
+    }
+
+    #[rstest]
+    #[case("read", "README.md", Some(0..4))]
+    #[case("md", "README.md", Some(7..9))]
+    #[case("Read", "README.md", None)]
+    #[case("READ", "README.md", Some(0..4))]
+    #[case("", "README.md", Some(0..0))]
+    #[case("straße", "STRASSE", Some(0..7))]
+    #[case("strasse", "Straße", Some(0..7))]
+    #[case("stras", "Straße", None)]
+    #[case("größe", "GRÖẞE", Some(0..8))]
+    #[case("ὀδυσσεύς", "ὈΔΥΣΣΕΎΣ", Some(0..17))]
+    #[case("i̇zmi̇r", "İZMİR", Some(0..7))]
+    #[case("izmir", "İZMİR", None)]
+    #[case("x", "README.md", None)]
+    fn matches_smart_case_test(
+        #[case] query: &str,
+        #[case] candidate: &str,
+        #[case] expected: Option<MatchRange>,
+    ) {
+        assert_eq!(matches_smart_case(query, candidate), expected);
+    }
+
+    #[test]
+    fn insert_snippet_selects_the_first_placeholder() {
+        let mut editor = Editor::default();
+        editor.set_buffer("echo fo rest".to_string(), UndoBehavior::CreateUndoPoint);
+        let snippet = Snippet::parse("foo(${1:arg})$0");
+
+        insert_snippet(&mut editor, Span::new(5, 7), &snippet);
+        assert_eq!(editor.get_buffer(), "echo foo(arg) rest");
+        assert_eq!(editor.insertion_point(), 9);
+
+        editor.run_edit_command(&crate::EditCommand::InsertChar('x'));
+        editor.run_edit_command(&crate::EditCommand::NextTabStop);
+        assert_eq!(editor.get_buffer(), "echo foo(x) rest");
+        assert_eq!(editor.insertion_point(), 11);
+    }
 
     #[test]
     fn parse_row_test() {
@@ -487,10 +1268,8 @@ mod tests {
             .into_iter()
             .map(|s| Suggestion {
                 value: s.into(),
-                description: None,
-                extra: None,
                 span: Span::new(0, s.len()),
-                append_whitespace: false,
+                ..Suggestion::default()
             })
             .collect();
         let res = find_common_string(&input);
@@ -506,10 +1285,8 @@ mod tests {
             .into_iter()
             .map(|s| Suggestion {
                 value: s.into(),
-                description: None,
-                extra: None,
                 span: Span::new(0, s.len()),
-                append_whitespace: false,
+                ..Suggestion::default()
             })
             .collect();
         let res = find_common_string(&input);
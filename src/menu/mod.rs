@@ -4,11 +4,33 @@ pub mod menu_functions;
 
 use crate::core_editor::Editor;
 use crate::History;
-use crate::{completion::history::HistoryCompleter, painting::Painter, Completer, Suggestion};
+use crate::{
+    completion::history::HistoryCompleter, painting::Painter, Completer, Span, Suggestion,
+};
 pub use columnar_menu::ColumnarMenu;
 pub use list_menu::ListMenu;
 use nu_ansi_term::{Color, Style};
 
+/// Defines what activating a menu does while it is already active, see
+/// [`Reedline::with_menu_tab_behavior`](crate::Reedline::with_menu_tab_behavior)
+///
+/// Tab is usually bound to `ReedlineEvent::UntilFound` with
+/// `ReedlineEvent::Menu` first followed by `ReedlineEvent::MenuNext`, while
+/// Shift+Tab is bound to `ReedlineEvent::MenuPrevious` and keeps moving to the
+/// previous entry with every behavior.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MenuTabBehavior {
+    /// The event is left to the following events of the binding, e.g. to select
+    /// the next entry
+    #[default]
+    NextEntry,
+    /// The selected entry is inserted in the buffer and the menu is closed
+    AcceptSelection,
+    /// The common prefix of the entries is inserted first, once there is nothing
+    /// left to extend the event is left to the following events of the binding
+    PartialComplete,
+}
+
 /// Struct to store the menu style
 pub struct MenuTextStyle {
     /// Text style for selected text in a menu
@@ -17,6 +39,9 @@ pub struct MenuTextStyle {
     pub text_style: Style,
     /// Text style for the item description
     pub description_style: Style,
+    /// Text style for the part of a value that matches the typed text.
+    /// The match is not highlighted when it is not set
+    pub match_style: Option<Style>,
 }
 
 impl Default for MenuTextStyle {
@@ -25,6 +50,7 @@ impl Default for MenuTextStyle {
             selected_text_style: Color::Green.bold().reverse(),
             text_style: Color::DarkGray.normal(),
             description_style: Color::Yellow.normal(),
+            match_style: None,
         }
     }
 }
@@ -56,6 +82,19 @@ pub enum MenuEvent {
     NextPage,
     /// Move to previous page
     PreviousPage,
+    /// Shows or hides the documentation of the selected value
+    ToggleDocumentation,
+    /// Scrolls up the documentation of the selected value
+    DocumentationUp,
+    /// Scrolls down the documentation of the selected value
+    DocumentationDown,
+    /// Character typed while the menu is active. Only sent when the menu consumes the
+    /// character, see [`Menu::wants_text_input`] and [`Menu::wants_digit_selection`]
+    Input(char),
+    /// Query the values again keeping the selection, e.g. after the selected value was
+    /// deleted, the terminal was resized or more completions arrived. The selection
+    /// moves to the previous value if it is past the last one
+    Refresh,
 }
 
 /// Trait that defines how a menu will be printed by the painter
@@ -74,6 +113,9 @@ pub trait Menu: Send {
 
     /// A menu may not be allowed to quick complete because it needs to stay
     /// active even with one element
+    ///
+    /// It is checked again once the values are updated, so a menu can also refuse
+    /// to quick complete depending on its only value
     fn can_quick_complete(&self) -> bool;
 
     /// The completion menu can try to find the common string and replace it
@@ -119,6 +161,65 @@ pub trait Menu: Send {
 
     /// Gets cached values from menu that will be displayed
     fn get_values(&self) -> &[Suggestion];
+
+    /// Whether the menu consumes the printable characters typed while it is active,
+    /// e.g. to filter its values. These characters are sent to the menu as
+    /// [`MenuEvent::Input`] instead of being inserted in the line buffer
+    fn wants_text_input(&self) -> bool {
+        false
+    }
+
+    /// Whether the menu consumes the digits typed while it is active, e.g. to select
+    /// a value by its number. These digits are sent to the menu as [`MenuEvent::Input`]
+    /// instead of being inserted in the line buffer
+    fn wants_digit_selection(&self) -> bool {
+        false
+    }
+
+    /// The value that [`Menu::replace_in_buffer`] would insert, if the menu
+    /// supports selecting values
+    fn get_selected_value(&self) -> Option<Suggestion> {
+        None
+    }
+
+    /// Selects the value painted at `column` and `row`, with the row counted from the
+    /// first row of the menu, e.g. when it is clicked with the mouse
+    ///
+    /// Returns `None` if there is no value at that position, otherwise whether the
+    /// value was already selected. The position is the one of the last painted menu
+    fn select_at(&mut self, _column: u16, _row: u16, _terminal_columns: u16) -> Option<bool> {
+        None
+    }
+
+    /// Whether the selected value may be deleted now, e.g. a history entry with
+    /// [`ReedlineEvent::MenuDelete`](crate::ReedlineEvent::MenuDelete)
+    ///
+    /// Menus that confirm deletions return `false` for the first request and mark the
+    /// value, repeating the request without another menu event in between confirms it
+    fn confirm_delete(&mut self) -> bool {
+        true
+    }
+
+    /// Appends the values a streaming completer found since the last update, keeping
+    /// the selection. `loading` tells if more values are still to come
+    ///
+    /// By default the menu asks the completer for all the values again, like with
+    /// [`MenuEvent::Refresh`]
+    fn append_values(&mut self, _values: Vec<Suggestion>, _loading: bool) {
+        self.menu_event(MenuEvent::Refresh);
+    }
+}
+
+/// Decides if a character typed while the menu is active is sent to the menu
+///
+/// The precedence is:
+/// 1. Digits go to the menu if it [wants digit selection](Menu::wants_digit_selection)
+/// 2. Printable characters, including digits, go to the menu if it
+///    [wants text input](Menu::wants_text_input)
+/// 3. Any other character edits the line buffer
+pub(crate) fn menu_consumes_char(menu: &dyn Menu, c: char) -> bool {
+    (c.is_ascii_digit() && menu.wants_digit_selection())
+        || (!c.is_control() && menu.wants_text_input())
 }
 
 /// Allowed menus in Reedline
@@ -153,6 +254,59 @@ impl ReedlineMenu {
         }
     }
 
+    /// Inserts the selected value in the buffer and deactivates the menu
+    ///
+    /// When the value asks to [recomplete](Suggestion::recomplete), the menu is
+    /// activated again with the completions of the text of its span at the new cursor
+    /// position. Nothing is done if the insertion left the buffer untouched or no
+    /// completions follow, so an expansion can't keep offering itself.
+    pub(crate) fn accept_selected_value(
+        &mut self,
+        editor: &mut Editor,
+        completer: &mut dyn Completer,
+        history: &dyn History,
+    ) {
+        let recomplete = self
+            .get_selected_value()
+            .and_then(|suggestion| suggestion.recomplete);
+        let buffer = editor.get_buffer().to_string();
+
+        self.replace_in_buffer(editor);
+        self.menu_event(MenuEvent::Deactivate);
+
+        if let Some(span) = recomplete.filter(|_| editor.get_buffer() != buffer) {
+            let mut completer = SpanCompleter { completer, span };
+            self.menu_event(MenuEvent::Activate(false));
+            self.update_values(editor, &mut completer, history);
+            if self.get_values().is_empty() {
+                self.menu_event(MenuEvent::Deactivate);
+            }
+        }
+    }
+
+    /// Shows the completions computed in the background since the last check, if the
+    /// menu is completed by a background completer. Returns true if the menu has to be
+    /// repainted
+    pub(crate) fn poll_completions(&mut self, completer: &mut dyn Completer) -> bool {
+        let (menu, completer): (_, &mut dyn Completer) = match self {
+            Self::EngineCompleter(menu) => (menu, completer),
+            Self::HistoryMenu(_) => return false,
+            Self::WithCompleter {
+                menu,
+                completer: own_completer,
+            } => (menu, own_completer.as_mut()),
+        };
+        if !completer.poll_completions() {
+            return false;
+        }
+
+        match completer.take_new_suggestions() {
+            Some(values) => menu.append_values(values, completer.is_loading()),
+            None => menu.menu_event(MenuEvent::Refresh),
+        }
+        true
+    }
+
     pub(crate) fn can_partially_complete(
         &mut self,
         values_updated: bool,
@@ -221,6 +375,37 @@ impl ReedlineMenu {
     }
 }
 
+/// Completer that only completes the text of `span` in the line, for the suggestions
+/// asking to [recomplete](Suggestion::recomplete)
+struct SpanCompleter<'completer> {
+    completer: &'completer mut dyn Completer,
+    span: Span,
+}
+
+impl Completer for SpanCompleter<'_> {
+    fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+        let end = self.span.end.min(line.len());
+        let start = self.span.start.min(end);
+        let text = match line.get(start..end) {
+            Some(text) => text,
+            None => return vec![],
+        };
+
+        self.completer
+            .complete(text, pos.clamp(start, end) - start)
+            .into_iter()
+            .map(|suggestion| Suggestion {
+                span: Span::new(suggestion.span.start + start, suggestion.span.end + start),
+                ..suggestion
+            })
+            .collect()
+    }
+
+    fn is_loading(&self) -> bool {
+        self.completer.is_loading()
+    }
+}
+
 impl Menu for ReedlineMenu {
     fn name(&self) -> &str {
         self.as_ref().name()
@@ -312,4 +497,122 @@ impl Menu for ReedlineMenu {
     fn get_values(&self) -> &[Suggestion] {
         self.as_ref().get_values()
     }
+
+    fn wants_text_input(&self) -> bool {
+        self.as_ref().wants_text_input()
+    }
+
+    fn wants_digit_selection(&self) -> bool {
+        self.as_ref().wants_digit_selection()
+    }
+
+    fn get_selected_value(&self) -> Option<Suggestion> {
+        self.as_ref().get_selected_value()
+    }
+
+    fn select_at(&mut self, column: u16, row: u16, terminal_columns: u16) -> Option<bool> {
+        self.as_mut().select_at(column, row, terminal_columns)
+    }
+
+    fn confirm_delete(&mut self) -> bool {
+        self.as_mut().confirm_delete()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    struct InputMenu {
+        text_input: bool,
+        digit_selection: bool,
+    }
+
+    impl Menu for InputMenu {
+        fn name(&self) -> &str {
+            "input_menu"
+        }
+
+        fn indicator(&self) -> &str {
+            ""
+        }
+
+        fn is_active(&self) -> bool {
+            true
+        }
+
+        fn menu_event(&mut self, _event: MenuEvent) {}
+
+        fn can_quick_complete(&self) -> bool {
+            false
+        }
+
+        fn can_partially_complete(
+            &mut self,
+            _values_updated: bool,
+            _editor: &mut Editor,
+            _completer: &mut dyn Completer,
+        ) -> bool {
+            false
+        }
+
+        fn update_values(&mut self, _editor: &mut Editor, _completer: &mut dyn Completer) {}
+
+        fn update_working_details(
+            &mut self,
+            _editor: &mut Editor,
+            _completer: &mut dyn Completer,
+            _painter: &Painter,
+        ) {
+        }
+
+        fn replace_in_buffer(&self, _editor: &mut Editor) {}
+
+        fn menu_required_lines(&self, _terminal_columns: u16) -> u16 {
+            0
+        }
+
+        fn menu_string(&self, _available_lines: u16, _use_ansi_coloring: bool) -> String {
+            String::new()
+        }
+
+        fn min_rows(&self) -> u16 {
+            0
+        }
+
+        fn get_values(&self) -> &[Suggestion] {
+            &[]
+        }
+
+        fn wants_text_input(&self) -> bool {
+            self.text_input
+        }
+
+        fn wants_digit_selection(&self) -> bool {
+            self.digit_selection
+        }
+    }
+
+    #[rstest]
+    #[case(false, false, 'a', false)]
+    #[case(false, false, '1', false)]
+    #[case(false, true, 'a', false)]
+    #[case(false, true, '1', true)]
+    #[case(true, false, 'a', true)]
+    #[case(true, false, '1', true)]
+    #[case(true, true, '\t', false)]
+    fn char_routing(
+        #[case] text_input: bool,
+        #[case] digit_selection: bool,
+        #[case] c: char,
+        #[case] expected: bool,
+    ) {
+        let menu = ReedlineMenu::EngineCompleter(Box::new(InputMenu {
+            text_input,
+            digit_selection,
+        }));
+
+        assert_eq!(menu_consumes_char(&menu, c), expected);
+    }
 }
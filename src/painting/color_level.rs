@@ -0,0 +1,288 @@
+use super::utils::ansi_tokens;
+use std::borrow::Cow;
+
+/// The colors a terminal is able to display
+///
+/// Colors of the prompt, the highlighter, the hints and the menus that the terminal
+/// can't display are replaced by the nearest color it can display when painting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorLevel {
+    /// No colors or other styles at all
+    NoColor,
+    /// The 16 colors of the basic ANSI palette
+    Ansi16,
+    /// The 256 colors of the extended ANSI palette
+    Ansi256,
+    /// 24-bit RGB colors
+    TrueColor,
+}
+
+impl Default for ColorLevel {
+    fn default() -> Self {
+        Self::detect()
+    }
+}
+
+impl ColorLevel {
+    /// Detects the color level from the `NO_COLOR`, `COLORTERM` and `TERM` environment
+    /// variables
+    pub fn detect() -> Self {
+        if std::env::var_os("NO_COLOR").map_or(false, |value| !value.is_empty()) {
+            return Self::NoColor;
+        }
+        Self::from_env(
+            std::env::var("COLORTERM").ok().as_deref(),
+            std::env::var("TERM").ok().as_deref(),
+        )
+    }
+
+    fn from_env(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return Self::TrueColor;
+        }
+        match term {
+            // Without `TERM`, e.g. on Windows, nothing is known about the terminal
+            None => Self::TrueColor,
+            Some("dumb") => Self::NoColor,
+            Some(term) if term.contains("direct") => Self::TrueColor,
+            Some(term) if term.contains("256color") => Self::Ansi256,
+            Some(_) => Self::Ansi16,
+        }
+    }
+
+    /// Replaces the colors in the SGR escape sequences of `text` with colors of this level
+    pub(crate) fn apply<'a>(self, text: &'a str) -> Cow<'a, str> {
+        if self == Self::TrueColor || !text.contains('\x1b') {
+            return Cow::Borrowed(text);
+        }
+
+        Cow::Owned(
+            ansi_tokens(text)
+                .into_iter()
+                .map(|(token, is_escape)| {
+                    if is_escape {
+                        self.apply_to_escape(token)
+                    } else {
+                        Cow::Borrowed(token)
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Replaces the colors of a single escape sequence, other sequences are kept
+    pub(crate) fn apply_to_escape(self, escape: &str) -> Cow<'_, str> {
+        let params = match escape
+            .strip_prefix("\x1b[")
+            .and_then(|s| s.strip_suffix('m'))
+        {
+            Some(params) if self != Self::TrueColor => params,
+            _ => return Cow::Borrowed(escape),
+        };
+        if self == Self::NoColor {
+            return Cow::Borrowed("");
+        }
+
+        let params: Vec<&str> = params.split(';').collect();
+        let mut converted: Vec<String> = Vec::with_capacity(params.len());
+        let mut index = 0;
+        while index < params.len() {
+            let target = params[index];
+            let color = match (target, params.get(index + 1)) {
+                ("38" | "48" | "58", Some(&"2")) => {
+                    index += 5;
+                    params.get(index - 3..index).and_then(|rgb| {
+                        let rgb = Rgb(parse(rgb[0])?, parse(rgb[1])?, parse(rgb[2])?);
+                        Some(PaletteColor::Rgb(rgb))
+                    })
+                }
+                ("38" | "48" | "58", Some(&"5")) => {
+                    index += 3;
+                    params
+                        .get(index - 1)
+                        .and_then(|value| parse(value))
+                        .map(PaletteColor::Indexed)
+                }
+                _ => {
+                    converted.push(target.to_owned());
+                    index += 1;
+                    continue;
+                }
+            };
+            // Malformed colors are dropped
+            if let Some(color) = color {
+                converted.extend(self.color_params(target, color));
+            }
+        }
+
+        if converted.is_empty() {
+            Cow::Borrowed("")
+        } else {
+            Cow::Owned(format!("\x1b[{}m", converted.join(";")))
+        }
+    }
+
+    fn color_params(self, target: &str, color: PaletteColor) -> Option<String> {
+        match self {
+            Self::Ansi256 => {
+                let index = match color {
+                    PaletteColor::Rgb(rgb) => rgb.nearest_256(),
+                    PaletteColor::Indexed(index) => index,
+                };
+                Some(format!("{target};5;{index}"))
+            }
+            Self::Ansi16 => {
+                let index = match color {
+                    PaletteColor::Indexed(index) if index < 16 => index,
+                    PaletteColor::Indexed(index) => Rgb::of_index(index).nearest_16(),
+                    PaletteColor::Rgb(rgb) => rgb.nearest_16(),
+                };
+                let (base, bright_base) = match target {
+                    "38" => (30, 90),
+                    "48" => (40, 100),
+                    // The basic palette has no underline colors
+                    _ => return None,
+                };
+                Some(if index < 8 {
+                    (base + index).to_string()
+                } else {
+                    (bright_base + index - 8).to_string()
+                })
+            }
+            Self::NoColor | Self::TrueColor => None,
+        }
+    }
+}
+
+fn parse(value: &str) -> Option<u8> {
+    value.parse().ok()
+}
+
+enum PaletteColor {
+    Rgb(Rgb),
+    Indexed(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rgb(u8, u8, u8);
+
+// The basic palette as displayed by xterm
+const ANSI_16: [Rgb; 16] = [
+    Rgb(0, 0, 0),
+    Rgb(205, 0, 0),
+    Rgb(0, 205, 0),
+    Rgb(205, 205, 0),
+    Rgb(0, 0, 238),
+    Rgb(205, 0, 205),
+    Rgb(0, 205, 205),
+    Rgb(229, 229, 229),
+    Rgb(127, 127, 127),
+    Rgb(255, 0, 0),
+    Rgb(0, 255, 0),
+    Rgb(255, 255, 0),
+    Rgb(92, 92, 255),
+    Rgb(255, 0, 255),
+    Rgb(0, 255, 255),
+    Rgb(255, 255, 255),
+];
+
+// Channel values of the 6x6x6 color cube of the extended palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl Rgb {
+    fn of_index(index: u8) -> Self {
+        match index {
+            0..=15 => ANSI_16[index as usize],
+            16..=231 => {
+                let index = index - 16;
+                Rgb(
+                    CUBE_LEVELS[(index / 36) as usize],
+                    CUBE_LEVELS[(index / 6 % 6) as usize],
+                    CUBE_LEVELS[(index % 6) as usize],
+                )
+            }
+            _ => {
+                let gray = 8 + 10 * (index - 232);
+                Rgb(gray, gray, gray)
+            }
+        }
+    }
+
+    fn distance(self, other: Rgb) -> u32 {
+        let channel = |a: u8, b: u8| (i32::from(a) - i32::from(b)).unsigned_abs().pow(2);
+        channel(self.0, other.0) + channel(self.1, other.1) + channel(self.2, other.2)
+    }
+
+    fn nearest_16(self) -> u8 {
+        (0..16)
+            .min_by_key(|&index| self.distance(ANSI_16[index as usize]))
+            .unwrap_or_default()
+    }
+
+    fn nearest_256(self) -> u8 {
+        let cube_level = |value: u8| {
+            (0..6)
+                .min_by_key(|&level| (i32::from(CUBE_LEVELS[level]) - i32::from(value)).abs())
+                .unwrap_or_default() as u8
+        };
+        let cube = 16 + 36 * cube_level(self.0) + 6 * cube_level(self.1) + cube_level(self.2);
+
+        let average = (u32::from(self.0) + u32::from(self.1) + u32::from(self.2)) / 3;
+        let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+
+        [cube, gray]
+            .into_iter()
+            .min_by_key(|&index| self.distance(Rgb::of_index(index)))
+            .unwrap_or(cube)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(Some("truecolor"), Some("xterm"), ColorLevel::TrueColor)]
+    #[case(None, Some("xterm-256color"), ColorLevel::Ansi256)]
+    #[case(None, Some("xterm-direct"), ColorLevel::TrueColor)]
+    #[case(None, Some("xterm"), ColorLevel::Ansi16)]
+    #[case(None, Some("dumb"), ColorLevel::NoColor)]
+    #[case(None, None, ColorLevel::TrueColor)]
+    fn color_level_is_detected(
+        #[case] colorterm: Option<&str>,
+        #[case] term: Option<&str>,
+        #[case] expected: ColorLevel,
+    ) {
+        assert_eq!(ColorLevel::from_env(colorterm, term), expected);
+    }
+
+    #[rstest]
+    #[case(ColorLevel::TrueColor, "\x1b[1;38;2;255;0;0mred\x1b[0m")]
+    #[case(ColorLevel::Ansi256, "\x1b[1;38;5;196mred\x1b[0m")]
+    #[case(ColorLevel::Ansi16, "\x1b[1;91mred\x1b[0m")]
+    #[case(ColorLevel::NoColor, "red")]
+    fn rgb_colors_are_downgraded(#[case] level: ColorLevel, #[case] expected: &str) {
+        assert_eq!(level.apply("\x1b[1;38;2;255;0;0mred\x1b[0m"), expected);
+    }
+
+    #[rstest]
+    #[case("\x1b[48;5;236m", "\x1b[40m")]
+    #[case("\x1b[38;5;4m", "\x1b[34m")]
+    #[case("\x1b[48;2;250;250;250;4m", "\x1b[107;4m")]
+    #[case("\x1b[58;2;1;2;3m", "")]
+    #[case("\x1b[2K", "\x1b[2K")]
+    fn escapes_are_converted_to_the_basic_palette(#[case] escape: &str, #[case] expected: &str) {
+        assert_eq!(ColorLevel::Ansi16.apply_to_escape(escape), expected);
+    }
+
+    #[rstest]
+    #[case(Rgb(0, 0, 0), 16)]
+    #[case(Rgb(255, 255, 255), 231)]
+    #[case(Rgb(128, 128, 128), 244)]
+    #[case(Rgb(95, 135, 175), 67)]
+    fn nearest_extended_palette_color(#[case] rgb: Rgb, #[case] expected: u8) {
+        assert_eq!(rgb.nearest_256(), expected);
+    }
+}
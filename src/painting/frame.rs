@@ -0,0 +1,285 @@
+use super::{
+    utils::{ansi_tokens, expand_tab_stops, grapheme_width},
+    ColorLevel,
+};
+
+const SGR_RESET: &str = "\x1b[0m";
+
+/// A row of the screen as laid out by a [`Frame`]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct FrameRow {
+    // Styled content, starting with the styles that were active at the start of the row
+    content: String,
+    width: usize,
+}
+
+impl FrameRow {
+    pub(crate) fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Visible columns of the row
+    pub(crate) fn width(&self) -> usize {
+        self.width
+    }
+}
+
+/// The screen rows painted for the prompt, the buffer and the menu
+///
+/// Text is wrapped into rows the way the terminal would wrap it. Every row carries the
+/// styles active at its start, so it can be painted on its own and compared with the
+/// row painted at the same position before.
+#[derive(Debug, Clone)]
+pub(crate) struct Frame {
+    rows: Vec<FrameRow>,
+    columns: usize,
+    tab_width: usize,
+    color_level: ColorLevel,
+    // SGR sequences active at the end of the last row
+    style: String,
+}
+
+impl Frame {
+    pub(crate) fn new(columns: u16, tab_width: usize, color_level: ColorLevel) -> Self {
+        Self {
+            rows: vec![FrameRow::default()],
+            columns: columns.max(1).into(),
+            tab_width,
+            color_level,
+            style: String::new(),
+        }
+    }
+
+    /// Number of rows in the frame
+    pub(crate) fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Appends ANSI styled text, wrapping it at the screen width
+    ///
+    /// Tabs advance to the next multiple of the tab width and move to the next row if
+    /// they don't fit in the current one.
+    /// Colors are adjusted to the color level of the frame
+    pub(crate) fn push_str(&mut self, text: &str) {
+        let mut column = self.last_row().width;
+        let text = expand_tab_stops(text, self.tab_width, self.columns, &mut column);
+        for (token, is_escape) in ansi_tokens(&text) {
+            if is_escape {
+                let token = self.color_level.apply_to_escape(token);
+                let token = token.as_ref();
+                if token.starts_with("\x1b[") && token.ends_with('m') {
+                    if token == SGR_RESET || token == "\x1b[m" {
+                        self.style.clear();
+                    } else {
+                        self.style.push_str(token);
+                    }
+                }
+                self.last_row().content.push_str(token);
+                continue;
+            }
+
+            match token {
+                "\n" | "\r\n" => self.new_row(),
+                "\r" => {}
+                _ => {
+                    let width = grapheme_width(token);
+                    let row_width = self.last_row().width;
+                    if row_width > 0 && row_width + width > self.columns {
+                        self.new_row();
+                    }
+                    let row = self.last_row();
+                    row.content.push_str(token);
+                    row.width += width;
+                }
+            }
+        }
+    }
+
+    /// Position of the next character as row and column
+    ///
+    /// Like the terminal cursor it moves to the next row once a row is full
+    pub(crate) fn cursor(&self) -> (usize, usize) {
+        let row = self.rows.len() - 1;
+        match self.rows[row].width {
+            width if width >= self.columns => (row + 1, 0),
+            width => (row, width),
+        }
+    }
+
+    /// Adds empty rows until the frame has at least `rows` rows
+    pub(crate) fn extend_to(&mut self, rows: usize) {
+        while self.rows.len() < rows {
+            self.rows.push(FrameRow::default());
+        }
+    }
+
+    /// Continues the frame with unstyled text at the start of `row`, dropping the rows from
+    /// there on
+    pub(crate) fn continue_at_row(&mut self, row: usize) {
+        self.rows.truncate(row);
+        self.extend_to(row);
+        self.style.clear();
+        self.new_row();
+    }
+
+    /// Removes the rows after the first `rows` rows
+    pub(crate) fn truncate(&mut self, rows: usize) {
+        self.rows.truncate(rows);
+    }
+
+    /// Removes the first `rows` rows, keeping at least one row
+    ///
+    /// The remaining rows start with the styles active at their start, so styled text
+    /// continues on them like in the full frame
+    pub(crate) fn skip_rows(&mut self, rows: usize) {
+        let rows = rows.min(self.rows.len() - 1);
+        self.rows.drain(..rows);
+    }
+
+    /// Places `text` on `row` starting at `column`, if the row is not wider than that
+    pub(crate) fn overlay(&mut self, row: usize, column: usize, text: &str) {
+        if let Some(row) = self.rows.get_mut(row) {
+            if row.width <= column {
+                row.content.push_str(SGR_RESET);
+                row.content
+                    .extend(std::iter::repeat(' ').take(column - row.width));
+                row.content.push_str(&self.color_level.apply(text));
+                row.width = column + super::utils::line_width(text);
+            }
+        }
+    }
+
+    /// The rows that differ from the rows at the same position in the `previous` frame
+    pub(crate) fn changed_rows<'a>(
+        &'a self,
+        previous: Option<&'a Frame>,
+    ) -> impl Iterator<Item = (usize, &'a FrameRow)> {
+        self.rows.iter().enumerate().filter(move |(index, row)| {
+            previous.and_then(|previous| previous.rows.get(*index)) != Some(*row)
+        })
+    }
+
+    fn last_row(&mut self) -> &mut FrameRow {
+        self.rows
+            .last_mut()
+            .expect("a frame always has at least one row")
+    }
+
+    fn new_row(&mut self) {
+        self.rows.push(FrameRow {
+            content: self.style.clone(),
+            width: 0,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn contents(frame: &Frame) -> Vec<&str> {
+        frame.rows.iter().map(FrameRow::content).collect()
+    }
+
+    #[test]
+    fn text_wraps_at_the_screen_width() {
+        let mut frame = Frame::new(4, 8, ColorLevel::TrueColor);
+        frame.push_str("> abcdef\r\ngh");
+
+        assert_eq!(contents(&frame), ["> ab", "cdef", "gh"]);
+        assert_eq!(frame.cursor(), (2, 2));
+    }
+
+    #[test]
+    fn cursor_moves_to_the_next_row_after_a_full_row() {
+        let mut frame = Frame::new(4, 8, ColorLevel::TrueColor);
+        frame.push_str("abcd");
+        assert_eq!(frame.cursor(), (1, 0));
+
+        // A newline after a full row does not leave an empty row
+        frame.push_str("\nef");
+        assert_eq!(contents(&frame), ["abcd", "ef"]);
+    }
+
+    #[test]
+    fn wide_characters_are_not_split() {
+        let mut frame = Frame::new(5, 8, ColorLevel::TrueColor);
+        frame.push_str("abc日本");
+
+        assert_eq!(contents(&frame), ["abc日", "本"]);
+        assert_eq!(frame.rows[0].width(), 5);
+    }
+
+    #[test]
+    fn tabs_advance_to_the_next_tab_stop() {
+        let mut frame = Frame::new(10, 4, ColorLevel::TrueColor);
+        frame.push_str("a\tb\tc\td");
+
+        // The last tab would cross the right edge and moves to the next row, the
+        // rest of the row is filled with whitespace
+        assert_eq!(contents(&frame), ["a   b   c ", "    d"]);
+        assert_eq!(frame.cursor(), (1, 5));
+    }
+
+    #[test]
+    fn rows_carry_the_active_style() {
+        let mut frame = Frame::new(3, 8, ColorLevel::TrueColor);
+        frame.push_str("\x1b[1mabcd\x1b[0mef\ngh");
+
+        assert_eq!(contents(&frame), ["\x1b[1mabc", "\x1b[1md\x1b[0mef", "gh"]);
+    }
+
+    #[test]
+    fn styled_text_straddling_the_right_edge_keeps_its_style() {
+        let mut frame = Frame::new(6, 4, ColorLevel::TrueColor);
+        frame.push_str("> \x1b[31mabc日\tcd\tx\x1b[0m");
+
+        // The wide character and the tab don't fit at the end of the rows
+        assert_eq!(
+            contents(&frame),
+            ["> \x1b[31mabc", "\x1b[31m日  cd", "\x1b[31m    x\x1b[0m"]
+        );
+
+        frame.skip_rows(2);
+        assert_eq!(contents(&frame), ["\x1b[31m    x\x1b[0m"]);
+        frame.skip_rows(5);
+        assert_eq!(frame.len(), 1);
+    }
+
+    #[test]
+    fn menu_rows_replace_the_rows_after_the_cursor() {
+        let mut frame = Frame::new(10, 8, ColorLevel::TrueColor);
+        frame.push_str("> \x1b[32mfoo\nbar");
+        frame.continue_at_row(1);
+        frame.push_str("menu");
+
+        assert_eq!(contents(&frame), ["> \x1b[32mfoo", "menu"]);
+    }
+
+    #[test]
+    fn overlay_is_placed_after_padding() {
+        let mut frame = Frame::new(10, 8, ColorLevel::TrueColor);
+        frame.push_str("> a\nlonger line");
+        frame.overlay(0, 7, "rp");
+        frame.overlay(1, 7, "rp");
+
+        assert_eq!(contents(&frame), ["> a\x1b[0m    rp", "longer lin", "e"]);
+        assert_eq!(frame.rows[0].width(), 9);
+    }
+
+    #[test]
+    fn only_changed_rows_are_repainted() {
+        let mut previous = Frame::new(10, 8, ColorLevel::TrueColor);
+        previous.push_str("> foo\nbar\nbaz");
+        let mut frame = Frame::new(10, 8, ColorLevel::TrueColor);
+        frame.push_str("> foo\nqux\nbaz\nnew");
+
+        let changed: Vec<usize> = frame
+            .changed_rows(Some(&previous))
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(changed, [1, 3]);
+        assert_eq!(frame.changed_rows(None).count(), 4);
+    }
+}
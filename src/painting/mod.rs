@@ -1,9 +1,19 @@
+mod color_level;
+mod frame;
 mod painter;
 mod prompt_lines;
+mod render_pipeline;
 mod styled_text;
 mod utils;
 
-pub use painter::Painter;
+pub use color_level::ColorLevel;
+#[cfg(test)]
+pub(crate) use painter::TestOutput;
+pub use painter::{LineWrapMode, Painter};
 pub(crate) use prompt_lines::PromptLines;
+pub use render_pipeline::{RenderPass, RenderPipeline};
 pub use styled_text::StyledText;
-pub(crate) use utils::estimate_single_line_wraps;
+pub(crate) use utils::{
+    clip_to_width, estimate_single_line_wraps, expand_tab_stops, grapheme_width,
+    keep_first_columns, line_width, restyle_after_escapes, strip_ansi,
+};
@@ -1,53 +1,70 @@
-use crate::{CursorConfig, PromptEditMode, PromptViMode};
+use crate::{CursorConfig, PromptEditMode};
 
+use super::utils::estimate_required_lines;
 use {
-    super::utils::{coerce_crlf, line_width},
+    super::{frame::Frame, utils::line_width, ColorLevel},
     crate::{
         menu::{Menu, ReedlineMenu},
         painting::PromptLines,
         Prompt,
     },
     crossterm::{
-        cursor::{self, MoveTo, RestorePosition, SavePosition},
+        cursor::{self, MoveTo},
         style::{Attribute, Print, ResetColor, SetAttribute, SetForegroundColor},
         terminal::{self, Clear, ClearType, ScrollUp},
         QueueableCommand, Result,
     },
     std::io::Write,
 };
-#[cfg(feature = "external_printer")]
-use {crate::LineBuffer, crossterm::cursor::MoveUp};
-
-// Returns a string that skips N number of lines with the next offset of lines
-// An offset of 0 would return only one line after skipping the required lines
-fn skip_buffer_lines(string: &str, skip: usize, offset: Option<usize>) -> &str {
-    let mut matches = string.match_indices('\n');
-    let index = if skip == 0 {
-        0
-    } else {
-        matches
-            .clone()
-            .nth(skip - 1)
-            .map(|(index, _)| index + 1)
-            .unwrap_or(string.len())
-    };
-
-    let limit = match offset {
-        Some(offset) => {
-            let offset = skip + offset;
-            matches
-                .nth(offset)
-                .map(|(index, _)| index)
-                .unwrap_or(string.len())
-        }
-        None => string.len(),
-    };
 
-    string[index..limit].trim_end_matches('\n')
+/// Defines how the painter displays a line that is wider than the terminal
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineWrapMode {
+    /// The line wraps into the following rows of the terminal
+    #[default]
+    Wrap,
+    /// The line stays on the row of the prompt. Only the part around the cursor is
+    /// shown and the clipped edges are marked with `…`. Buffers with multiple lines
+    /// still wrap
+    HorizontalScroll,
 }
 
+/// Default number of columns between two tab stops
+pub(crate) const DEFAULT_TAB_WIDTH: usize = 8;
+
 /// the type used by crossterm operations
-pub type W = std::io::BufWriter<std::io::Stderr>;
+pub type W = std::io::BufWriter<Box<dyn Write + Send>>;
+
+/// Output of a painter that tests can read back
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct TestOutput(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl TestOutput {
+    /// Painter writing to this output
+    pub(crate) fn painter(&self) -> Painter {
+        Painter::new(std::io::BufWriter::new(Box::new(self.clone())))
+    }
+
+    /// Takes the output written so far
+    pub(crate) fn take(&self) -> String {
+        let output = std::mem::take(&mut *self.0.lock().unwrap());
+        String::from_utf8_lossy(&output).into_owned()
+    }
+}
+
+#[cfg(test)]
+impl Write for TestOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
 
 /// Implementation of the output to the terminal
 pub struct Painter {
@@ -57,6 +74,14 @@ pub struct Painter {
     terminal_size: (u16, u16),
     last_required_lines: u16,
     large_buffer: bool,
+    line_wrap_mode: LineWrapMode,
+    max_rendered_lines: Option<u16>,
+    color_level: ColorLevel,
+    tab_width: usize,
+    // The frame painted last together with the row it starts at
+    last_frame: Option<(u16, Frame)>,
+    // Screen row the last painted menu starts at
+    menu_start_row: Option<u16>,
 }
 
 impl Painter {
@@ -67,9 +92,52 @@ impl Painter {
             terminal_size: (0, 0),
             last_required_lines: 0,
             large_buffer: false,
+            line_wrap_mode: LineWrapMode::default(),
+            max_rendered_lines: None,
+            color_level: ColorLevel::default(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            last_frame: None,
+            menu_start_row: None,
         }
     }
 
+    /// Screen row of the first row of the menu painted with the buffer, if any
+    pub(crate) fn menu_start_row(&self) -> Option<u16> {
+        self.menu_start_row
+    }
+
+    /// Sets the maximum number of buffer lines that are displayed
+    pub(crate) fn set_max_rendered_lines(&mut self, max_rendered_lines: Option<u16>) {
+        self.max_rendered_lines = max_rendered_lines;
+    }
+
+    /// Sets how lines wider than the terminal are displayed
+    pub(crate) fn set_line_wrap_mode(&mut self, line_wrap_mode: LineWrapMode) {
+        self.line_wrap_mode = line_wrap_mode;
+    }
+
+    /// Sets the colors the terminal is able to display
+    pub(crate) fn set_color_level(&mut self, color_level: ColorLevel) {
+        self.color_level = color_level;
+        self.last_frame = None;
+    }
+
+    /// The colors the terminal is able to display
+    pub(crate) fn color_level(&self) -> ColorLevel {
+        self.color_level
+    }
+
+    /// Sets the number of columns between two tab stops
+    pub(crate) fn set_tab_width(&mut self, tab_width: usize) {
+        self.tab_width = tab_width.max(1);
+        self.last_frame = None;
+    }
+
+    /// Number of columns between two tab stops, tabs advance to the next multiple of it
+    pub fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
     /// Height of the current terminal window
     pub fn screen_height(&self) -> u16 {
         self.terminal_size.1
@@ -118,6 +186,7 @@ impl Painter {
             new_row
         };
         self.prompt_start_row = new_row;
+        self.last_frame = None;
         Ok(())
     }
 
@@ -128,6 +197,9 @@ impl Painter {
     /// prompt should scroll up and how much space is required to print all the
     /// lines for the buffer
     ///
+    /// Buffers that fit on the screen are laid out as a [`Frame`] and only the rows
+    /// that changed since the previous repaint are painted again.
+    ///
     /// Note. The `ScrollUp` operation in `crossterm` deletes lines from the top of
     /// the screen.
     pub(crate) fn repaint_buffer(
@@ -140,13 +212,34 @@ impl Painter {
         cursor_config: &Option<CursorConfig>,
     ) -> Result<()> {
         self.stdout.queue(cursor::Hide)?;
+        self.menu_start_row = None;
 
         let screen_width = self.screen_width();
         let screen_height = self.screen_height();
 
+        let folded_lines = self
+            .max_rendered_lines
+            .and_then(|max_lines| lines.folded(max_lines));
+        let lines = folded_lines.as_ref().unwrap_or(lines);
+
+        let expanded_lines = lines.expanded_tabs(self.tab_width, screen_width);
+        let lines = expanded_lines.as_ref().unwrap_or(lines);
+
+        let scrolled_lines = match self.line_wrap_mode {
+            LineWrapMode::Wrap => None,
+            LineWrapMode::HorizontalScroll => lines.scrolled_horizontally(screen_width),
+        };
+        let lines = scrolled_lines.as_ref().unwrap_or(lines);
+
+        let (mut frame, mut cursor) = self.buffer_frame(prompt, lines, menu, use_ansi_coloring);
+
         // Lines and distance parameters
         let remaining_lines = self.remaining_lines();
-        let required_lines = lines.required_lines(screen_width, menu);
+        let required_lines = match menu {
+            Some(menu) => (cursor.0 + 1) as u16 + menu.menu_required_lines(screen_width),
+            None => frame.len() as u16,
+        }
+        .max(lines.required_lines(screen_width, menu));
 
         // Marking the painter state as larger buffer to avoid animations
         self.large_buffer = required_lines >= screen_height;
@@ -154,114 +247,57 @@ impl Painter {
         // Moving the start position of the cursor based on the size of the required lines
         if self.large_buffer {
             self.prompt_start_row = 0;
+            cursor = self.scroll_to_cursor(&mut frame, cursor, menu);
         } else if required_lines >= remaining_lines {
             let extra = required_lines.saturating_sub(remaining_lines);
             self.stdout.queue(ScrollUp(extra))?;
             self.prompt_start_row = self.prompt_start_row.saturating_sub(extra);
+            self.last_frame = None;
         }
 
-        // Moving the cursor to the start of the prompt
-        // from this position everything will be printed
-        self.stdout
-            .queue(cursor::MoveTo(0, self.prompt_start_row))?
-            .queue(Clear(ClearType::FromCursorDown))?;
-
-        if self.large_buffer {
-            self.print_large_buffer(prompt, lines, menu, use_ansi_coloring)?;
-        } else {
-            self.print_small_buffer(prompt, lines, menu, use_ansi_coloring)?;
+        if let Some(menu) = menu {
+            let starting_row = self.push_menu(&mut frame, cursor.0, menu, use_ansi_coloring);
+            self.menu_start_row = Some(starting_row);
         }
+        self.paint_frame(frame, cursor)?;
 
         // The last_required_lines is used to move the cursor at the end where stdout
         // can print without overwriting the things written during the painting
         self.last_required_lines = required_lines;
 
-        self.stdout.queue(RestorePosition)?;
-
-        if let Some(shapes) = cursor_config {
-            let shape = match &prompt_mode {
-                PromptEditMode::Emacs => shapes.emacs,
-                PromptEditMode::Vi(PromptViMode::Insert) => shapes.vi_insert,
-                PromptEditMode::Vi(PromptViMode::Normal) => shapes.vi_normal,
-                _ => None,
-            };
-            if let Some(shape) = shape {
-                self.stdout.queue(shape)?;
-            }
+        if let Some(shape) = cursor_config
+            .as_ref()
+            .and_then(|shapes| shapes.shape(&prompt_mode))
+        {
+            self.stdout.queue(shape)?;
         }
         self.stdout.queue(cursor::Show)?;
 
         self.stdout.flush()
     }
 
-    fn print_right_prompt(&mut self, lines: &PromptLines) -> Result<()> {
-        let prompt_length_right = line_width(&lines.prompt_str_right);
-        let start_position = self
-            .screen_width()
-            .saturating_sub(prompt_length_right as u16);
-        let screen_width = self.screen_width();
-        let input_width = lines.estimate_right_prompt_line_width(screen_width);
-
-        let mut row = self.prompt_start_row;
-        if lines.right_prompt_on_last_line {
-            row += lines.prompt_lines_with_wrap(screen_width);
-        }
-
-        if input_width <= start_position {
-            self.stdout
-                .queue(SavePosition)?
-                .queue(cursor::MoveTo(start_position, row))?
-                .queue(Print(&coerce_crlf(&lines.prompt_str_right)))?
-                .queue(RestorePosition)?;
-        }
-
-        Ok(())
-    }
-
-    fn print_menu(
-        &mut self,
-        menu: &dyn Menu,
-        lines: &PromptLines,
-        use_ansi_coloring: bool,
-    ) -> Result<()> {
-        let screen_width = self.screen_width();
-        let screen_height = self.screen_height();
-        let cursor_distance = lines.distance_from_prompt(screen_width);
-
-        // If there is not enough space to print the menu, then the starting
-        // drawing point for the menu will overwrite the last rows in the buffer
-        let starting_row = if cursor_distance >= screen_height.saturating_sub(1) {
-            screen_height.saturating_sub(menu.min_rows())
-        } else {
-            self.prompt_start_row + cursor_distance + 1
-        };
-
-        let remaining_lines = screen_height.saturating_sub(starting_row);
-        let menu_string = menu.menu_string(remaining_lines, use_ansi_coloring);
-        self.stdout
-            .queue(cursor::MoveTo(0, starting_row))?
-            .queue(Clear(ClearType::FromCursorDown))?
-            .queue(Print(menu_string.trim_end_matches('\n')))?;
-
-        Ok(())
-    }
-
-    fn print_small_buffer(
-        &mut self,
+    /// Lays out the prompt, the buffer and the hint, returning the frame and the
+    /// position of the cursor in it
+    ///
+    /// With a menu the hint is left out, the menu is added once the space for it is known
+    fn buffer_frame(
+        &self,
         prompt: &dyn Prompt,
         lines: &PromptLines,
         menu: Option<&ReedlineMenu>,
         use_ansi_coloring: bool,
-    ) -> Result<()> {
+    ) -> (Frame, (usize, usize)) {
+        let mut frame = Frame::new(self.screen_width(), self.tab_width, self.color_level);
+
         // print our prompt with color
         if use_ansi_coloring {
-            self.stdout
-                .queue(SetForegroundColor(prompt.get_prompt_color()))?
-                .queue(SetAttribute(Attribute::Bold))?;
+            frame.push_str(&format!(
+                "{}{}",
+                SetForegroundColor(prompt.get_prompt_color()),
+                SetAttribute(Attribute::Bold)
+            ));
         }
-
-        self.stdout
-            .queue(Print(&coerce_crlf(&lines.prompt_str_left)))?;
+        frame.push_str(&lines.prompt_str_left);
 
         let prompt_indicator = match menu {
             Some(menu) => menu.indicator(),
@@ -269,133 +305,161 @@ impl Painter {
         };
 
         if use_ansi_coloring {
-            self.stdout
-                .queue(SetForegroundColor(prompt.get_indicator_color()))?
-                .queue(SetAttribute(Attribute::Bold))?;
+            frame.push_str(&format!(
+                "{}{}",
+                SetForegroundColor(prompt.get_indicator_color()),
+                SetAttribute(Attribute::Bold)
+            ));
         }
-
-        self.stdout.queue(Print(&coerce_crlf(prompt_indicator)))?;
+        frame.push_str(prompt_indicator);
+        let indicator_row = frame.cursor().0;
 
         if use_ansi_coloring {
-            self.stdout
-                .queue(SetForegroundColor(prompt.get_prompt_right_color()))?
-                .queue(SetAttribute(Attribute::Bold))?;
+            frame.push_str(&ResetColor.to_string());
         }
 
-        self.print_right_prompt(lines)?;
-
-        if use_ansi_coloring {
-            self.stdout.queue(ResetColor)?;
+        frame.push_str(&lines.before_cursor);
+        let cursor = frame.cursor();
+        frame.push_str(&lines.after_cursor);
+        if menu.is_none() {
+            frame.push_str(&lines.hint);
         }
+        frame.extend_to(cursor.0 + 1);
 
-        self.stdout
-            .queue(Print(&lines.before_cursor))?
-            .queue(SavePosition)?
-            .queue(Print(&lines.after_cursor))?;
+        self.place_right_prompt(&mut frame, lines, indicator_row, prompt, use_ansi_coloring);
 
-        if let Some(menu) = menu {
-            self.print_menu(menu, lines, use_ansi_coloring)?;
-        } else {
-            self.stdout.queue(Print(&lines.hint))?;
+        (frame, cursor)
+    }
+
+    fn place_right_prompt(
+        &self,
+        frame: &mut Frame,
+        lines: &PromptLines,
+        indicator_row: usize,
+        prompt: &dyn Prompt,
+        use_ansi_coloring: bool,
+    ) {
+        let prompt_length_right = line_width(&lines.prompt_str_right);
+        let start_position = self
+            .screen_width()
+            .saturating_sub(prompt_length_right as u16);
+        let input_width = lines.estimate_right_prompt_line_width(self.screen_width());
+        if input_width > start_position {
+            return;
         }
 
-        Ok(())
+        let row = if lines.right_prompt_on_last_line {
+            indicator_row
+        } else {
+            0
+        };
+        let style = if use_ansi_coloring {
+            format!(
+                "{}{}",
+                SetForegroundColor(prompt.get_prompt_right_color()),
+                SetAttribute(Attribute::Bold)
+            )
+        } else {
+            String::new()
+        };
+        for (index, line) in lines.prompt_str_right.lines().enumerate() {
+            frame.overlay(
+                row + index,
+                start_position.into(),
+                &format!("{style}{line}"),
+            );
+        }
     }
 
-    fn print_large_buffer(
-        &mut self,
-        prompt: &dyn Prompt,
-        lines: &PromptLines,
+    /// Scrolls a frame that doesn't fit on the screen, so the cursor row and the minimum
+    /// rows of the menu below it are the last rows shown. Returns the position of the
+    /// cursor in the scrolled frame
+    ///
+    /// The rows keep their styles, so text styled before the first shown row continues
+    /// with the same style.
+    fn scroll_to_cursor(
+        &self,
+        frame: &mut Frame,
+        cursor: (usize, usize),
         menu: Option<&ReedlineMenu>,
+    ) -> (usize, usize) {
+        let menu_rows: usize = menu.map_or(0, |menu| menu.min_rows().into());
+        let skipped = (cursor.0 + 1 + menu_rows)
+            .saturating_sub(self.screen_height().into())
+            .min(cursor.0);
+        frame.skip_rows(skipped);
+        (cursor.0 - skipped, cursor.1)
+    }
+
+    /// Adds the menu below the cursor row, or over the last rows of the screen if there
+    /// is not enough space left. Returns the screen row the menu starts at
+    fn push_menu(
+        &self,
+        frame: &mut Frame,
+        cursor_row: usize,
+        menu: &dyn Menu,
         use_ansi_coloring: bool,
-    ) -> Result<()> {
-        let screen_width = self.screen_width();
+    ) -> u16 {
         let screen_height = self.screen_height();
-        let cursor_distance = lines.distance_from_prompt(screen_width);
-        let remaining_lines = screen_height.saturating_sub(cursor_distance);
-
-        // Calculating the total lines before the cursor
-        // The -1 in the total_lines_before is there because the at least one line of the prompt
-        // indicator is printed in the same line as the first line of the buffer
-        let prompt_lines = lines.prompt_lines_with_wrap(screen_width) as usize;
+        let cursor_distance = cursor_row as u16;
 
-        let prompt_indicator = match menu {
-            Some(menu) => menu.indicator(),
-            None => &lines.prompt_indicator,
+        let starting_row = if cursor_distance >= screen_height.saturating_sub(1) {
+            screen_height.saturating_sub(menu.min_rows())
+        } else {
+            self.prompt_start_row + cursor_distance + 1
         };
 
-        let prompt_indicator_lines = prompt_indicator.lines().count();
-        let before_cursor_lines = lines.before_cursor.lines().count();
-        let total_lines_before = prompt_lines + prompt_indicator_lines + before_cursor_lines - 1;
-
-        // Extra rows represent how many rows are "above" the visible area in the terminal
-        let extra_rows = (total_lines_before).saturating_sub(screen_height as usize);
+        let remaining_lines = screen_height.saturating_sub(starting_row);
+        let menu_string = menu.menu_string(remaining_lines, use_ansi_coloring);
+        frame.continue_at_row(starting_row.saturating_sub(self.prompt_start_row).into());
+        frame.push_str(menu_string.trim_end_matches('\n'));
+        starting_row
+    }
 
-        // print our prompt with color
-        if use_ansi_coloring {
+    /// Paints the rows of the frame that differ from the previously painted frame and
+    /// places the cursor
+    fn paint_frame(&mut self, mut frame: Frame, cursor: (usize, usize)) -> Result<()> {
+        let start_row = self.prompt_start_row;
+        let screen_width: usize = self.screen_width().into();
+        let screen_height: usize = self.screen_height().into();
+        let remaining_lines: usize = self.remaining_lines().into();
+        frame.truncate(remaining_lines);
+
+        let previous = self
+            .last_frame
+            .take()
+            .filter(|(row, _)| *row == start_row)
+            .map(|(_, frame)| frame);
+
+        for (index, row) in frame.changed_rows(previous.as_ref()) {
             self.stdout
-                .queue(SetForegroundColor(prompt.get_prompt_color()))?;
-        }
-
-        // In case the prompt is made out of multiple lines, the prompt is split by
-        // lines and only the required ones are printed
-        let prompt_skipped = skip_buffer_lines(&lines.prompt_str_left, extra_rows, None);
-        self.stdout.queue(Print(&coerce_crlf(prompt_skipped)))?;
-
-        if extra_rows == 0 {
-            self.print_right_prompt(lines)?;
+                .queue(MoveTo(0, start_row + index as u16))?
+                .queue(Print(row.content()))?
+                .queue(SetAttribute(Attribute::Reset))?;
+            if row.width() < screen_width {
+                self.stdout.queue(Clear(ClearType::UntilNewLine))?;
+            }
         }
 
-        // Adjusting extra_rows base on the calculated prompt line size
-        let extra_rows = extra_rows.saturating_sub(prompt_lines);
-
-        let indicator_skipped = skip_buffer_lines(prompt_indicator, extra_rows, None);
-        self.stdout.queue(Print(&coerce_crlf(indicator_skipped)))?;
-
-        if use_ansi_coloring {
-            self.stdout.queue(ResetColor)?;
+        // Without a previous frame anything could be left below the frame
+        let stale_rows = previous.map_or(true, |previous| previous.len() > frame.len());
+        if stale_rows && frame.len() < remaining_lines {
+            self.stdout
+                .queue(MoveTo(0, start_row + frame.len() as u16))?
+                .queue(Clear(ClearType::FromCursorDown))?;
         }
 
-        // The minimum number of lines from the menu are removed from the buffer if there is no more
-        // space to print the menu. This will only happen if the cursor is at the last line and
-        // it is a large buffer
-        let offset = menu.and_then(|menu| {
-            if cursor_distance >= screen_height.saturating_sub(1) {
-                let rows = lines
-                    .before_cursor
-                    .lines()
-                    .count()
-                    .saturating_sub(extra_rows)
-                    .saturating_sub(menu.min_rows() as usize);
-                Some(rows)
-            } else {
-                None
-            }
-        });
-
-        // Selecting the lines before the cursor that will be printed
-        let before_cursor_skipped = skip_buffer_lines(&lines.before_cursor, extra_rows, offset);
-        self.stdout.queue(Print(before_cursor_skipped))?;
-        self.stdout.queue(SavePosition)?;
+        let (row, column) = cursor;
+        let row = (usize::from(start_row) + row).min(screen_height.saturating_sub(1));
+        self.stdout.queue(MoveTo(column as u16, row as u16))?;
 
-        if let Some(menu) = menu {
-            // TODO: Also solve the difficult problem of displaying (parts of)
-            // the content after the cursor with the completion menu
-            self.print_menu(menu, lines, use_ansi_coloring)?;
-        } else {
-            // Selecting lines for the hint
-            // The -1 subtraction is done because the remaining lines consider the line where the
-            // cursor is located as a remaining line. That has to be removed to get the correct offset
-            // for the after-cursor and hint lines
-            let offset = remaining_lines.saturating_sub(1) as usize;
-            // Selecting lines after the cursor
-            let after_cursor_skipped = skip_buffer_lines(&lines.after_cursor, 0, Some(offset));
-            self.stdout.queue(Print(after_cursor_skipped))?;
-            // Hint lines
-            let hint_skipped = skip_buffer_lines(&lines.hint, 0, Some(offset));
-            self.stdout.queue(Print(hint_skipped))?;
-        }
+        self.last_frame = Some((start_row, frame));
+        Ok(())
+    }
 
+    /// Queues ANSI styled text with its colors adjusted to the color level
+    fn print_styled(&mut self, text: &str) -> Result<()> {
+        self.stdout.queue(Print(self.color_level.apply(text)))?;
         Ok(())
     }
 
@@ -405,13 +469,17 @@ impl Painter {
         let prev_prompt_row = self.prompt_start_row;
 
         self.terminal_size = (width, height);
+        // Terminals rewrap or cut off the painted rows
+        self.last_frame = None;
 
         if prev_prompt_row < height
             && height <= prev_terminal_size.1
-            && width == prev_terminal_size.0
+            && width >= prev_terminal_size.0
         {
-            // The terminal got smaller in height but the start of the prompt is still visible
-            // The width didn't change
+            // The terminal didn't get larger in height and the start of the prompt is
+            // still visible. The width didn't shrink, so the output above the prompt
+            // can't have wrapped into more rows and the prompt is painted again in
+            // place. Its rows left below the new layout are cleared by the repaint
             return;
         }
 
@@ -424,13 +492,23 @@ impl Painter {
         // - The terminal got smaller in height and the whole prompt is no longer visible
         //   - Note: if the the prompt contains multiple lines, this will leave a trail of
         //     previous prompts currently.
-        // - The width changed
+        // - The width shrank
         self.prompt_start_row = height.saturating_sub(1);
     }
 
     /// Writes `line` to the terminal with a following carriage return and newline
     pub(crate) fn paint_line(&mut self, line: &str) -> Result<()> {
-        self.stdout.queue(Print(line))?.queue(Print("\r\n"))?;
+        self.last_frame = None;
+        self.print_styled(line)?;
+        self.stdout.queue(Print("\r\n"))?;
+
+        self.stdout.flush()
+    }
+
+    /// Writes an escape sequence that leaves the cursor in place, e.g. to set the
+    /// clipboard of the terminal
+    pub(crate) fn write_escape_sequence(&mut self, sequence: &str) -> Result<()> {
+        self.stdout.queue(Print(sequence))?;
 
         self.stdout.flush()
     }
@@ -439,6 +517,7 @@ impl Painter {
     ///
     /// Also works in raw mode
     pub(crate) fn print_crlf(&mut self) -> Result<()> {
+        self.last_frame = None;
         self.stdout.queue(Print("\r\n"))?;
 
         self.stdout.flush()
@@ -472,6 +551,7 @@ impl Painter {
     // If the prompt is in the middle of a multiline buffer, then the output to stdout
     // could overwrite the buffer writing
     pub(crate) fn move_cursor_to_end(&mut self) -> Result<()> {
+        self.last_frame = None;
         let final_row = self.prompt_start_row + self.last_required_lines;
         let scroll = final_row.saturating_sub(self.screen_height() - 1);
         if scroll != 0 {
@@ -483,113 +563,224 @@ impl Painter {
         self.stdout.flush()
     }
 
-    /// Prints an external message
-    ///
-    /// This function doesn't flush the buffer. So buffer should be flushed
-    /// afterwards perhaps by repainting the prompt via `repaint_buffer()`.
-    #[cfg(feature = "external_printer")]
-    pub(crate) fn print_external_message(
-        &mut self,
-        messages: Vec<String>,
-        line_buffer: &LineBuffer,
-        prompt: &dyn Prompt,
-    ) -> Result<()> {
-        // adding 3 seems to be right for first line-wrap
-        let prompt_len = prompt.render_prompt_right().len() + 3;
-        let mut buffer_num_lines = 0_u16;
-        for (i, line) in line_buffer.get_buffer().lines().enumerate() {
-            let screen_lines = match i {
-                0 => {
-                    // the first line has to deal with the prompt
-                    let first_line_len = line.len() + prompt_len;
-                    // at least, it is one line
-                    ((first_line_len as u16) / (self.screen_width())) + 1
-                }
-                _ => {
-                    // the n-th line, no prompt, at least, it is one line
-                    ((line.len() as u16) / self.screen_width()) + 1
-                }
-            };
-            // count up screen-lines
-            buffer_num_lines = buffer_num_lines.saturating_add(screen_lines);
-        }
-        // move upward to start print if the line-buffer is more than one screen-line
-        if buffer_num_lines > 1 {
-            self.stdout.queue(MoveUp(buffer_num_lines - 1))?;
-        }
-        let erase_line = format!("\r{}\r", " ".repeat(self.screen_width().into()));
-        for line in messages {
-            self.stdout.queue(Print(&erase_line))?;
-            // Note: we don't use `print_line` here because we don't want to
-            // flush right now. The subsequent repaint of the prompt will cause
-            // immediate flush anyways. And if we flush here, every external
-            // print causes visible flicker.
-            self.stdout.queue(Print(line))?.queue(Print("\r\n"))?;
-            let new_start = self.prompt_start_row.saturating_add(1);
-            let height = self.screen_height();
-            if new_start >= height {
-                self.prompt_start_row = height - 1;
-            } else {
-                self.prompt_start_row = new_start;
-            }
+    /// Leaves the painted entry in place and starts the next prompt below it
+    pub(crate) fn start_prompt_below(&mut self) -> Result<()> {
+        self.move_cursor_to_end()?;
+        self.prompt_start_row = (self.prompt_start_row + self.last_required_lines)
+            .min(self.screen_height().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Prints `lines` below the painted entry, which stays in place, and starts the
+    /// next prompt below them
+    pub(crate) fn print_below(&mut self, lines: &[String]) -> Result<()> {
+        self.start_prompt_below()?;
+        self.queue_lines(lines)?;
+
+        self.stdout.flush()
+    }
+
+    // Prints the lines from the prompt start row on and moves the prompt below them
+    fn queue_lines(&mut self, lines: &[String]) -> Result<()> {
+        let screen_width = self.screen_width();
+        let last_row = self.screen_height().saturating_sub(1);
+        for line in lines {
+            self.print_styled(line)?;
+            self.stdout.queue(Print("\r\n"))?;
+            // Rows below the last one scroll the screen up
+            let rows = estimate_required_lines(line, screen_width).max(1);
+            self.prompt_start_row = self
+                .prompt_start_row
+                .saturating_add(rows as u16)
+                .min(last_row);
         }
         Ok(())
     }
+
+    /// Prints external messages above the prompt
+    ///
+    /// The prompt, the buffer and the menu are cleared and the messages printed in
+    /// their place. The prompt moves below the messages, so it has to be painted again
+    /// via `repaint_buffer()`, which also flushes the output.
+    #[cfg(feature = "external_printer")]
+    pub(crate) fn print_external_message(&mut self, messages: Vec<String>) -> Result<()> {
+        self.last_frame = None;
+        self.menu_start_row = None;
+        self.stdout
+            .queue(MoveTo(0, self.prompt_start_row))?
+            .queue(Clear(ClearType::FromCursorDown))?;
+
+        // Note: we don't use `print_line` here because we don't want to
+        // flush right now. The subsequent repaint of the prompt will cause
+        // immediate flush anyways. And if we flush here, every external
+        // print causes visible flicker.
+        self.queue_lines(&messages)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{prompt::TestPrompt, StyledText};
+    use nu_ansi_term::{Color, Style};
     use pretty_assertions::assert_eq;
 
-    #[test]
-    fn test_skip_lines() {
-        let string = "sentence1\nsentence2\nsentence3\n";
-
-        assert_eq!(skip_buffer_lines(string, 1, None), "sentence2\nsentence3");
-        assert_eq!(skip_buffer_lines(string, 2, None), "sentence3");
-        assert_eq!(skip_buffer_lines(string, 3, None), "");
-        assert_eq!(skip_buffer_lines(string, 4, None), "");
+    fn painter(width: u16, height: u16) -> Painter {
+        let mut painter = Painter::new(std::io::BufWriter::new(Box::new(std::io::stderr())));
+        painter.handle_resize(width, height);
+        painter
     }
 
-    #[test]
-    fn test_skip_lines_no_newline() {
-        let string = "sentence1";
+    /// The rows of the highlighted buffer as laid out by the painter, with the cursor
+    fn highlighted_rows(
+        painter: &Painter,
+        styled: &StyledText,
+        insertion_point: usize,
+    ) -> (Frame, (usize, usize)) {
+        let prompt = TestPrompt::default();
+        let (before, after) = styled.render_around_insertion_point(insertion_point, &prompt, true);
+        let lines = PromptLines::new(&prompt, PromptEditMode::Default, None, &before, &after, "");
+        painter.buffer_frame(&prompt, &lines, None, false)
+    }
 
-        assert_eq!(skip_buffer_lines(string, 0, None), "sentence1");
-        assert_eq!(skip_buffer_lines(string, 1, None), "");
+    fn contents(frame: &Frame) -> Vec<String> {
+        frame
+            .changed_rows(None)
+            .map(|(_, row)| row.content().to_string())
+            .collect()
     }
 
     #[test]
-    fn test_skip_lines_with_limit() {
-        let string = "sentence1\nsentence2\nsentence3\nsentence4\nsentence5";
+    fn highlighted_span_continues_after_the_wrap() {
+        let mut styled = StyledText::new();
+        styled.push((Style::new(), "ls ".into()));
+        styled.push((Style::new().fg(Color::Red), "日本\tx".into()));
 
-        assert_eq!(
-            skip_buffer_lines(string, 1, Some(1)),
-            "sentence2\nsentence3",
-        );
+        let (frame, cursor) = highlighted_rows(&painter(8, 10), &styled, 12);
 
+        let red = Style::new().fg(Color::Red).prefix().to_string();
+        let reset = Style::new().fg(Color::Red).suffix().to_string();
         assert_eq!(
-            skip_buffer_lines(string, 1, Some(2)),
-            "sentence2\nsentence3\nsentence4",
+            contents(&frame),
+            [
+                format!("> ls {red}日"),
+                format!("{red}本      "),
+                format!("{red}x{reset}")
+            ]
         );
+        assert_eq!(cursor, (2, 1));
+    }
+
+    #[test]
+    fn control_characters_are_shown_in_caret_notation() {
+        let mut styled = StyledText::new();
+        styled.push((Style::new(), "a\x1b[2J\x08b".into()));
 
+        let (frame, cursor) = highlighted_rows(&painter(20, 10), &styled, 8);
+
+        assert_eq!(contents(&frame), ["> a^[[2J^Hb"]);
+        assert_eq!(cursor, (0, 11));
+    }
+
+    #[test]
+    fn large_buffers_scroll_with_the_styles_of_the_hidden_rows() {
+        let mut styled = StyledText::new();
+        styled.push((
+            Style::new().fg(Color::Red),
+            "abcdefghijklmnopqrstuvwxyz".into(),
+        ));
+
+        let painter = painter(8, 3);
+        let (mut frame, cursor) = highlighted_rows(&painter, &styled, 26);
+        assert_eq!((frame.len(), cursor), (4, (3, 4)));
+
+        let cursor = painter.scroll_to_cursor(&mut frame, cursor, None);
+        let red = Style::new().fg(Color::Red).prefix().to_string();
+        let reset = Style::new().fg(Color::Red).suffix().to_string();
         assert_eq!(
-            skip_buffer_lines(string, 2, Some(1)),
-            "sentence3\nsentence4",
+            contents(&frame),
+            [
+                format!("{red}ghijklmn"),
+                format!("{red}opqrstuv"),
+                format!("{red}wxyz{reset}")
+            ]
         );
+        assert_eq!(cursor, (2, 4));
+    }
 
+    #[test]
+    fn widening_repaints_the_buffer_in_place() {
+        let output = TestOutput::default();
+        let mut painter = output.painter();
+        painter.handle_resize(10, 10);
+        let prompt = TestPrompt::default();
+        let lines = PromptLines::new(
+            &prompt,
+            PromptEditMode::Default,
+            None,
+            "abcdefghijklmnopqrstuvwxyz",
+            "",
+            "",
+        );
+        let repaint = |painter: &mut Painter| {
+            painter
+                .repaint_buffer(&prompt, &lines, PromptEditMode::Default, None, false, &None)
+                .unwrap();
+        };
+        painter.prompt_start_row = 2;
+        repaint(&mut painter);
         assert_eq!(
-            skip_buffer_lines(string, 1, Some(10)),
-            "sentence2\nsentence3\nsentence4\nsentence5",
+            painter.last_frame.as_ref().map(|(_, frame)| frame.len()),
+            Some(3)
         );
 
+        // The frame shrinks to one row at the same start row
+        painter.handle_resize(40, 10);
+        assert!(painter.last_frame.is_none());
+        output.take();
+        repaint(&mut painter);
+        assert_eq!(painter.prompt_start_row, 2);
         assert_eq!(
-            skip_buffer_lines(string, 0, Some(1)),
-            "sentence1\nsentence2",
+            painter.last_frame.as_ref().map(|(_, frame)| frame.len()),
+            Some(1)
         );
+        // The rows left over from the narrower frame are cleared below its end
+        let painted = output.take();
+        assert!(painted.contains("\x1b[4;1H\x1b[J"), "{painted:?}");
+
+        // Output above the prompt may wrap into more rows, so it moves to the bottom
+        painter.handle_resize(20, 10);
+        assert_eq!(painter.prompt_start_row, 9);
+    }
 
-        assert_eq!(skip_buffer_lines(string, 0, Some(0)), "sentence1",);
-        assert_eq!(skip_buffer_lines(string, 1, Some(0)), "sentence2",);
+    #[cfg(feature = "external_printer")]
+    #[test]
+    fn external_messages_replace_the_prompt_and_move_it_below() {
+        let output = TestOutput::default();
+        let mut painter = output.painter();
+        painter.handle_resize(20, 10);
+        let prompt = TestPrompt::default();
+        let lines = PromptLines::new(&prompt, PromptEditMode::Default, None, "one\ntwo", "", "");
+        painter.prompt_start_row = 2;
+        painter
+            .repaint_buffer(&prompt, &lines, PromptEditMode::Default, None, false, &None)
+            .unwrap();
+        output.take();
+
+        painter
+            .print_external_message(vec!["first".into(), "second".into()])
+            .unwrap();
+        painter.stdout.flush().unwrap();
+        assert_eq!(output.take(), "\x1b[3;1H\x1b[Jfirst\r\nsecond\r\n");
+        assert_eq!(painter.prompt_start_row, 4);
+        assert!(painter.last_frame.is_none());
+
+        // The whole buffer is painted again below the messages
+        painter
+            .repaint_buffer(&prompt, &lines, PromptEditMode::Default, None, false, &None)
+            .unwrap();
+        let painted = output.take();
+        assert!(painted.contains("\x1b[5;1H> one"), "{painted:?}");
+        assert!(painted.contains("\x1b[6;1Htwo"), "{painted:?}");
     }
 }
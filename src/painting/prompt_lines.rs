@@ -1,4 +1,7 @@
-use super::utils::{coerce_crlf, estimate_required_lines, line_width};
+use super::utils::{
+    coerce_crlf, estimate_required_lines, expand_tab_stops, keep_first_columns, keep_last_columns,
+    line_width,
+};
 use crate::{
     menu::{Menu, ReedlineMenu},
     prompt::PromptEditMode,
@@ -79,19 +82,174 @@ impl<'prompt> PromptLines<'prompt> {
         }
     }
 
-    /// Estimated distance of the cursor to the prompt.
-    /// This considers line wrapping
-    pub(crate) fn distance_from_prompt(&self, terminal_columns: u16) -> u16 {
-        let input = self.prompt_str_left.to_string() + &self.prompt_indicator + &self.before_cursor;
-        let lines = estimate_required_lines(&input, terminal_columns);
-        lines.saturating_sub(1) as u16
+    /// Folds a multiline buffer to `max_lines` logical lines centered on the cursor
+    ///
+    /// The lines at the edges of the shown window are replaced by markers with the
+    /// number of hidden lines. At least three lines are shown, so the cursor line is
+    /// never folded. Returns `None` if the buffer already fits.
+    pub(crate) fn folded(&self, max_lines: u16) -> Option<PromptLines<'prompt>> {
+        let max_lines = max_lines.max(3) as usize;
+        let before_lines: Vec<&str> = self.before_cursor.split('\n').collect();
+        let after_lines: Vec<&str> = self.after_cursor.split('\n').collect();
+        let cursor_line = before_lines.len() - 1;
+        let total_lines = cursor_line + after_lines.len();
+        if total_lines <= max_lines {
+            return None;
+        }
+
+        let start = cursor_line
+            .saturating_sub((max_lines - 1) / 2)
+            .min(total_lines - max_lines);
+        let end = start + max_lines;
+
+        let line = |index: usize| -> Cow<str> {
+            let content = if index < cursor_line {
+                before_lines[index]
+            } else {
+                after_lines[index - cursor_line]
+            };
+            let marker = |hidden: usize| {
+                // Keep the carriage return of the coerced line ending
+                let line_end = if content.ends_with('\r') { "\r" } else { "" };
+                Cow::Owned(format!("… {hidden} lines hidden …{line_end}"))
+            };
+
+            if index == start && start > 0 {
+                marker(start + 1)
+            } else if index == end - 1 && end < total_lines {
+                marker(total_lines - index)
+            } else {
+                Cow::Borrowed(content)
+            }
+        };
+
+        let mut before_cursor: Vec<Cow<str>> = (start..cursor_line).map(line).collect();
+        before_cursor.push(before_lines[cursor_line].into());
+        let mut after_cursor: Vec<Cow<str>> = vec![after_lines[0].into()];
+        after_cursor.extend((cursor_line + 1..end).map(line));
+
+        Some(Self {
+            prompt_str_left: self.prompt_str_left.clone(),
+            prompt_str_right: self.prompt_str_right.clone(),
+            prompt_indicator: self.prompt_indicator.clone(),
+            before_cursor: Cow::Owned(before_cursor.join("\n")),
+            after_cursor: Cow::Owned(after_cursor.join("\n")),
+            hint: self.hint.clone(),
+            right_prompt_on_last_line: self.right_prompt_on_last_line,
+        })
     }
 
-    /// Total lines that the prompt uses considering that it may wrap the screen
-    pub(crate) fn prompt_lines_with_wrap(&self, screen_width: u16) -> u16 {
+    /// Replaces the tabs with whitespace up to the next multiple of `tab_width` screen
+    /// columns, so the text is painted and measured the same way
+    ///
+    /// Returns `None` if there are no tabs.
+    pub(crate) fn expanded_tabs(
+        &self,
+        tab_width: usize,
+        screen_width: u16,
+    ) -> Option<PromptLines<'prompt>> {
+        let parts = [
+            &self.prompt_str_left,
+            &self.prompt_indicator,
+            &self.before_cursor,
+            &self.after_cursor,
+            &self.hint,
+        ];
+        if !parts
+            .iter()
+            .chain([&&self.prompt_str_right])
+            .any(|part| part.contains('\t'))
+        {
+            return None;
+        }
+
+        let screen_width = screen_width.into();
+        let mut column = 0;
+        let [prompt_str_left, prompt_indicator, before_cursor, after_cursor, hint] =
+            parts.map(|part| -> Cow<'prompt, str> {
+                expand_tab_stops(part, tab_width, screen_width, &mut column)
+                    .into_owned()
+                    .into()
+            });
+        let prompt_str_right =
+            expand_tab_stops(&self.prompt_str_right, tab_width, screen_width, &mut 0)
+                .into_owned()
+                .into();
+
+        Some(Self {
+            prompt_str_left,
+            prompt_str_right,
+            prompt_indicator,
+            before_cursor,
+            after_cursor,
+            hint,
+            right_prompt_on_last_line: self.right_prompt_on_last_line,
+        })
+    }
+
+    /// Clips a single line buffer to the row of the prompt, see
+    /// [`LineWrapMode::HorizontalScroll`](crate::LineWrapMode::HorizontalScroll)
+    ///
+    /// The text before the cursor is clipped from the left so the cursor stays
+    /// visible and the text after the cursor (and the hint) is clipped from the right.
+    /// The clipped edges are marked with `…`. Returns `None` if the buffer already
+    /// fits, spans multiple lines or there is no room next to the prompt.
+    pub(crate) fn scrolled_horizontally(&self, screen_width: u16) -> Option<PromptLines<'prompt>> {
+        const CLIP_INDICATOR: &str = "…";
+
+        if self.before_cursor.contains('\n')
+            || self.after_cursor.contains('\n')
+            || self.hint.contains('\n')
+        {
+            return None;
+        }
+
         let complete_prompt = self.prompt_str_left.to_string() + &self.prompt_indicator;
-        let lines = estimate_required_lines(&complete_prompt, screen_width);
-        lines.saturating_sub(1) as u16
+        let prompt_width = complete_prompt.lines().last().map_or(0, line_width);
+        // The last column is kept free so the terminal never wraps the line
+        let available = (screen_width as usize).saturating_sub(prompt_width + 1);
+        if available < 3 {
+            return None;
+        }
+
+        let before_width = line_width(&self.before_cursor);
+        let after_width = line_width(&self.after_cursor);
+        let hint_width = line_width(&self.hint);
+        if before_width + after_width + hint_width <= available {
+            return None;
+        }
+
+        // The cursor needs a column of its own after the text before it
+        let (before_cursor, before_width) = if before_width >= available {
+            let clipped = keep_last_columns(&self.before_cursor, available - 2);
+            (format!("{CLIP_INDICATOR}{clipped}"), available - 1)
+        } else {
+            (self.before_cursor.to_string(), before_width)
+        };
+
+        let right_width = available - before_width;
+        let (after_cursor, hint) = if after_width + hint_width <= right_width {
+            (self.after_cursor.to_string(), self.hint.to_string())
+        } else if after_width >= right_width {
+            let clipped = keep_first_columns(&self.after_cursor, right_width - 1);
+            (format!("{clipped}{CLIP_INDICATOR}"), String::new())
+        } else {
+            let clipped = keep_first_columns(&self.hint, right_width - 1 - after_width);
+            (
+                self.after_cursor.to_string(),
+                format!("{clipped}{CLIP_INDICATOR}"),
+            )
+        };
+
+        Some(Self {
+            prompt_str_left: self.prompt_str_left.clone(),
+            prompt_str_right: self.prompt_str_right.clone(),
+            prompt_indicator: self.prompt_indicator.clone(),
+            before_cursor: Cow::Owned(before_cursor),
+            after_cursor: Cow::Owned(after_cursor),
+            hint: Cow::Owned(hint),
+            right_prompt_on_last_line: self.right_prompt_on_last_line,
+        })
     }
 
     /// Estimated width of the line where right prompt will be rendered
@@ -137,3 +295,106 @@ impl<'prompt> PromptLines<'prompt> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prompt::TestPrompt, DefaultPrompt, DefaultPromptSegment, PromptViMode};
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn scrolled(before: &str, after: &str, hint: &str) -> Option<(String, String, String)> {
+        // Renders as `>: ` taking 3 columns, leaving 10 columns for the buffer on
+        // a terminal with 14 columns
+        let prompt = DefaultPrompt::new(
+            DefaultPromptSegment::Basic(">".to_string()),
+            DefaultPromptSegment::Empty,
+        );
+        let lines = PromptLines::new(
+            &prompt,
+            PromptEditMode::Vi(PromptViMode::Insert),
+            None,
+            before,
+            after,
+            hint,
+        );
+
+        lines.scrolled_horizontally(14).map(|lines| {
+            (
+                lines.before_cursor.to_string(),
+                lines.after_cursor.to_string(),
+                lines.hint.to_string(),
+            )
+        })
+    }
+
+    fn owned(before: &str, after: &str, hint: &str) -> Option<(String, String, String)> {
+        Some((before.to_string(), after.to_string(), hint.to_string()))
+    }
+
+    fn folded(before: &str, after: &str, max_lines: u16) -> Option<(String, String)> {
+        let prompt = DefaultPrompt::new(
+            DefaultPromptSegment::Basic(">".to_string()),
+            DefaultPromptSegment::Empty,
+        );
+        let lines = PromptLines::new(&prompt, PromptEditMode::Default, None, before, after, "");
+
+        lines.folded(max_lines).map(|lines| {
+            (
+                lines.before_cursor.replace('\r', ""),
+                lines.after_cursor.replace('\r', ""),
+            )
+        })
+    }
+
+    #[rstest]
+    #[case("a\nb", "c\nd", 4, None)]
+    #[case("a\nb\nc", "x\nd\ne\nf\ng", 5, Some(("a\nb\nc", "x\nd\n… 3 lines hidden …")))]
+    #[case("a", "x\nb\nc\nd\ne", 3, Some(("a", "x\nb\n… 3 lines hidden …")))]
+    #[case("a\nb\nc\nd\ne", "", 3, Some(("… 3 lines hidden …\nd\ne", "")))]
+    #[case("a\nb\nc", "x\nd\ne", 1, Some(("… 2 lines hidden …\nc", "x\n… 2 lines hidden …")))]
+    fn long_buffers_are_folded_around_the_cursor(
+        #[case] before: &str,
+        #[case] after: &str,
+        #[case] max_lines: u16,
+        #[case] expected: Option<(&str, &str)>,
+    ) {
+        let expected = expected.map(|(before, after)| (before.to_string(), after.to_string()));
+        assert_eq!(folded(before, after, max_lines), expected);
+    }
+
+    #[rstest]
+    #[case("abc", "def", "", None)]
+    #[case("0123456789abcdef", "", "", owned("…89abcdef", "", ""))]
+    #[case("", "0123456789abcdef", "", owned("", "012345678…", ""))]
+    #[case("0123", "456789abcdef", "", owned("0123", "45678…", ""))]
+    #[case("0123", "45", "6789abcdef", owned("0123", "45", "678…"))]
+    #[case("0123456789\nabcdef", "", "", None)]
+    fn long_lines_are_clipped_around_the_cursor(
+        #[case] before: &str,
+        #[case] after: &str,
+        #[case] hint: &str,
+        #[case] expected: Option<(String, String, String)>,
+    ) {
+        assert_eq!(scrolled(before, after, hint), expected);
+    }
+
+    #[rstest]
+    #[case("abc", "", 9)]
+    #[case("ab", "c\ndefghijk", 9)]
+    #[case("", "", 6)]
+    fn right_prompt_estimate_covers_the_first_line(
+        #[case] before: &str,
+        #[case] after: &str,
+        #[case] expected: u16,
+    ) {
+        let prompt = TestPrompt {
+            left: "left".into(),
+            ..TestPrompt::default()
+        };
+        let lines = PromptLines::new(&prompt, PromptEditMode::Default, None, before, after, "");
+
+        assert_eq!(lines.prompt_str_right, "");
+        assert_eq!(lines.estimate_right_prompt_line_width(80), expected);
+    }
+}
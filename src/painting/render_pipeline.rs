@@ -0,0 +1,190 @@
+use nu_ansi_term::Style;
+
+use super::StyledText;
+
+/// A layer composed into the painted buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderPass {
+    /// Styles the edited buffer and the history search result with the
+    /// [`Highlighter`](crate::Highlighter)
+    Highlight,
+    /// Styles the matches of the search string in the history search result
+    SearchMatch,
+    /// Styles the text selected in the edited buffer, e.g. in the vi visual mode
+    Selection,
+    /// Shows the suggestion of the [`Hinter`](crate::Hinter) after the cursor
+    Hint,
+}
+
+/// Order in which the render passes are composed when painting the buffer
+///
+/// The passes that style the text are applied in order, a later pass overrides the
+/// styles of the earlier ones wherever it sets a style. The selection is layered with
+/// the highlighting of the buffer, the search matches with the highlighting of the
+/// history search result. A pass missing from the
+/// pipeline is disabled. The hint is painted after the buffer, so only its presence
+/// matters and not its position.
+///
+/// The default order is [`RenderPass::Highlight`], [`RenderPass::SearchMatch`],
+/// [`RenderPass::Selection`], [`RenderPass::Hint`].
+///
+/// # Example
+///
+/// ```rust
+/// use reedline::{Reedline, RenderPass, RenderPipeline};
+///
+/// let pipeline = RenderPipeline::default().without(RenderPass::Hint);
+/// let mut line_editor = Reedline::create().with_render_pipeline(pipeline);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderPipeline {
+    passes: Vec<RenderPass>,
+}
+
+impl Default for RenderPipeline {
+    fn default() -> Self {
+        Self::new(vec![
+            RenderPass::Highlight,
+            RenderPass::SearchMatch,
+            RenderPass::Selection,
+            RenderPass::Hint,
+        ])
+    }
+}
+
+impl RenderPipeline {
+    /// Construct a pipeline running `passes` in the given order
+    ///
+    /// A pass listed more than once only runs at its first position
+    pub fn new(passes: Vec<RenderPass>) -> Self {
+        let mut unique = Vec::with_capacity(passes.len());
+        for pass in passes {
+            if !unique.contains(&pass) {
+                unique.push(pass);
+            }
+        }
+
+        Self { passes: unique }
+    }
+
+    /// Pipeline builder that disables `pass`
+    #[must_use]
+    pub fn without(mut self, pass: RenderPass) -> Self {
+        self.passes.retain(|p| *p != pass);
+        self
+    }
+
+    /// The enabled passes in the order they run
+    pub fn passes(&self) -> &[RenderPass] {
+        &self.passes
+    }
+
+    /// Whether `pass` is enabled
+    pub fn contains(&self, pass: RenderPass) -> bool {
+        self.passes.contains(&pass)
+    }
+
+    /// Styles `text` by running the passes in order
+    ///
+    /// `pass_output` returns the styling of a pass, or `None` if the pass doesn't
+    /// apply to the text. The first styling is used as is, the next ones are
+    /// overlaid on top of it. Without any styling the text is left unstyled.
+    pub(crate) fn compose(
+        &self,
+        text: &str,
+        mut pass_output: impl FnMut(RenderPass) -> Option<StyledText>,
+    ) -> StyledText {
+        let mut styled: Option<StyledText> = None;
+        for pass in &self.passes {
+            if let Some(output) = pass_output(*pass) {
+                match styled.as_mut() {
+                    Some(styled) => styled.overlay(&output),
+                    None => styled = Some(output),
+                }
+            }
+        }
+
+        styled.unwrap_or_else(|| {
+            let mut plain = StyledText::new();
+            plain.push((Style::default(), text.to_string()));
+            plain
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_ansi_term::Color;
+    use pretty_assertions::assert_eq;
+
+    fn styled(parts: &[(Style, &str)]) -> StyledText {
+        let mut styled = StyledText::new();
+        for (style, text) in parts {
+            styled.push((*style, text.to_string()));
+        }
+        styled
+    }
+
+    fn pass_output(pass: RenderPass) -> Option<StyledText> {
+        let red = Style::new().fg(Color::Red);
+        let green = Style::new().fg(Color::Green);
+
+        match pass {
+            RenderPass::Highlight => Some(styled(&[(red, "ls"), (Style::default(), " -la")])),
+            RenderPass::SearchMatch => Some(styled(&[
+                (Style::default(), "l"),
+                (green, "s -"),
+                (Style::default(), "la"),
+            ])),
+            RenderPass::Selection | RenderPass::Hint => None,
+        }
+    }
+
+    #[test]
+    fn later_passes_override_earlier_styles() {
+        let red = Style::new().fg(Color::Red);
+        let green = Style::new().fg(Color::Green);
+
+        let composed = RenderPipeline::default().compose("ls -la", pass_output);
+        assert_eq!(
+            composed.buffer,
+            styled(&[(red, "l"), (green, "s -"), (Style::default(), "la")]).buffer
+        );
+
+        let composed = RenderPipeline::new(vec![RenderPass::SearchMatch, RenderPass::Highlight])
+            .compose("ls -la", pass_output);
+        assert_eq!(
+            composed.buffer,
+            styled(&[(red, "ls"), (green, " -"), (Style::default(), "la")]).buffer
+        );
+    }
+
+    #[test]
+    fn disabled_passes_are_skipped() {
+        let pipeline = RenderPipeline::default()
+            .without(RenderPass::Highlight)
+            .without(RenderPass::SearchMatch)
+            .without(RenderPass::Selection);
+
+        assert_eq!(pipeline.passes(), &[RenderPass::Hint]);
+        assert_eq!(
+            pipeline.compose("ls -la", pass_output).buffer,
+            styled(&[(Style::default(), "ls -la")]).buffer
+        );
+    }
+
+    #[test]
+    fn repeated_passes_only_run_once() {
+        let pipeline = RenderPipeline::new(vec![
+            RenderPass::Hint,
+            RenderPass::Highlight,
+            RenderPass::Hint,
+        ]);
+
+        assert_eq!(
+            pipeline.passes(),
+            &[RenderPass::Hint, RenderPass::Highlight]
+        );
+    }
+}
@@ -2,7 +2,7 @@ use nu_ansi_term::Style;
 
 use crate::Prompt;
 
-use super::utils::strip_ansi;
+use super::utils::{caret_notation, strip_ansi};
 
 /// A representation of a buffer with styling, used for doing syntax highlighting
 pub struct StyledText {
@@ -38,21 +38,50 @@ impl StyledText {
         &self,
         insertion_point: usize,
         prompt: &dyn Prompt,
-        // multiline_prompt: &str,
+        use_ansi_coloring: bool,
+    ) -> (String, String) {
+        let multiline_prompt = prompt.render_prompt_multiline_indicator().into_owned();
+        let line_breaks = self
+            .buffer
+            .iter()
+            .map(|(_, text)| text.matches('\n').count());
+        self.render_around_insertion_point_with_indicators(
+            insertion_point,
+            prompt,
+            &vec![multiline_prompt; line_breaks.sum()],
+            use_ansi_coloring,
+        )
+    }
+
+    /// Like [`StyledText::render_around_insertion_point`], with the given continuation
+    /// prompt for each line break instead of the generic one of the prompt
+    pub(crate) fn render_around_insertion_point_with_indicators(
+        &self,
+        insertion_point: usize,
+        prompt: &dyn Prompt,
+        multiline_prompts: &[String],
         use_ansi_coloring: bool,
     ) -> (String, String) {
         let mut current_idx = 0;
         let mut left_string = String::new();
         let mut right_string = String::new();
+        let mut multiline_prompts = multiline_prompts.iter();
 
-        let multiline_prompt = prompt.render_prompt_multiline_indicator();
         let prompt_style = Style::new().fg(prompt.get_prompt_multiline_color());
 
         for pair in &self.buffer {
             if current_idx >= insertion_point {
-                right_string.push_str(&render_as_string(pair, &prompt_style, &multiline_prompt));
+                right_string.push_str(&render_as_string(
+                    pair,
+                    &prompt_style,
+                    &mut multiline_prompts,
+                ));
             } else if pair.1.len() + current_idx <= insertion_point {
-                left_string.push_str(&render_as_string(pair, &prompt_style, &multiline_prompt));
+                left_string.push_str(&render_as_string(
+                    pair,
+                    &prompt_style,
+                    &mut multiline_prompts,
+                ));
             } else if pair.1.len() + current_idx > insertion_point {
                 let offset = insertion_point - current_idx;
 
@@ -62,12 +91,12 @@ impl StyledText {
                 left_string.push_str(&render_as_string(
                     &(pair.0, left_side),
                     &prompt_style,
-                    &multiline_prompt,
+                    &mut multiline_prompts,
                 ));
                 right_string.push_str(&render_as_string(
                     &(pair.0, right_side),
                     &prompt_style,
-                    &multiline_prompt,
+                    &mut multiline_prompts,
                 ));
             }
             current_idx += pair.1.len();
@@ -81,13 +110,47 @@ impl StyledText {
     }
 
     /// Apply the ANSI style formatting to the full string.
+    ///
+    /// Control characters in the text are shown in caret notation, e.g. `^[`
     pub fn render_simple(&self) -> String {
         self.buffer
             .iter()
-            .map(|(style, text)| style.paint(text).to_string())
+            .map(|(style, text)| style.paint(caret_notation(text)).to_string())
             .collect()
     }
 
+    /// Overlays the styles of `other` on top of the current ones
+    ///
+    /// Both have to style the same text. Wherever `other` sets a style different
+    /// from the default one it wins, elsewhere the current style is kept.
+    pub fn overlay(&mut self, other: &StyledText) {
+        let mut styles: Vec<Style> = self
+            .buffer
+            .iter()
+            .flat_map(|(style, text)| std::iter::repeat(*style).take(text.len()))
+            .collect();
+        let mut index = 0;
+        for (style, text) in &other.buffer {
+            if *style != Style::default() {
+                for byte_style in styles.iter_mut().skip(index).take(text.len()) {
+                    *byte_style = *style;
+                }
+            }
+            index += text.len();
+        }
+
+        let text = self.raw_string();
+        let mut buffer: Vec<(Style, String)> = vec![];
+        for (index, c) in text.char_indices() {
+            let style = styles[index];
+            match buffer.last_mut() {
+                Some((last_style, last_text)) if *last_style == style => last_text.push(c),
+                _ => buffer.push((style, c.to_string())),
+            }
+        }
+        self.buffer = buffer;
+    }
+
     /// Get the unformatted text as a single continuous string.
     pub fn raw_string(&self) -> String {
         self.buffer.iter().map(|(_, str)| str.as_str()).collect()
@@ -97,15 +160,20 @@ impl StyledText {
 fn render_as_string(
     renderable: &(Style, String),
     prompt_style: &Style,
-    multiline_prompt: &str,
+    multiline_prompts: &mut std::slice::Iter<String>,
 ) -> String {
     let mut rendered = String::new();
-    let formatted_multiline_prompt = format!("\n{multiline_prompt}");
     for (line_number, line) in renderable.1.split('\n').enumerate() {
         if line_number != 0 {
-            rendered.push_str(&prompt_style.paint(&formatted_multiline_prompt).to_string());
+            let multiline_prompt = multiline_prompts.next().map_or("", String::as_str);
+            rendered.push_str(
+                &prompt_style
+                    .paint(format!("\n{multiline_prompt}"))
+                    .to_string(),
+            );
         }
-        rendered.push_str(&renderable.0.paint(line).to_string());
+        // Control characters typed literally must not reach the terminal as they are
+        rendered.push_str(&renderable.0.paint(caret_notation(line)).to_string());
     }
     rendered
 }
@@ -1,5 +1,6 @@
 use std::borrow::Cow;
-use unicode_width::UnicodeWidthStr;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
 
 /// Ensures input uses CRLF line endings.
 ///
@@ -32,6 +33,33 @@ pub(crate) fn coerce_crlf(input: &str) -> Cow<str> {
     result
 }
 
+/// Shows the control characters of the text in caret notation, e.g. `^[` for Escape
+///
+/// Written as they are, the terminal would interpret them, e.g. as the start of an
+/// escape sequence. Tabs and line breaks, also written as `\r\n`, are kept.
+pub(crate) fn caret_notation(text: &str) -> Cow<'_, str> {
+    let is_shown = |(index, c): (usize, char)| {
+        c.is_ascii_control()
+            && c != '\t'
+            && c != '\n'
+            && !(c == '\r' && text[index + 1..].starts_with('\n'))
+    };
+    if !text.char_indices().any(is_shown) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut shown = String::with_capacity(text.len() + 1);
+    for (index, c) in text.char_indices() {
+        if is_shown((index, c)) {
+            shown.push('^');
+            shown.push((c as u8 ^ 0x40) as char);
+        } else {
+            shown.push(c);
+        }
+    }
+    Cow::Owned(shown)
+}
+
 /// Returns string with the ANSI escape codes removed
 ///
 /// If parsing fails silently returns the input string
@@ -68,7 +96,186 @@ pub(crate) fn estimate_single_line_wraps(line: &str, terminal_columns: u16) -> u
 
 /// Compute the line width for ANSI escaped text
 pub(crate) fn line_width(line: &str) -> usize {
-    strip_ansi(line).width()
+    strip_ansi(line).graphemes(true).map(grapheme_width).sum()
+}
+
+/// Columns a grapheme cluster takes on the screen
+///
+/// Terminals draw a cluster, like a base character with combining marks or emoji
+/// joined by zero width joiners, as a single character as wide as its first character.
+/// Emoji presentation selectors and flags take two columns.
+pub(crate) fn grapheme_width(grapheme: &str) -> usize {
+    let mut chars = grapheme.chars();
+    let first = match chars.next() {
+        Some(first) => first,
+        None => return 0,
+    };
+    let width = first.width().unwrap_or(0);
+    let is_regional_indicator = ('\u{1f1e6}'..='\u{1f1ff}').contains(&first);
+    if is_regional_indicator || chars.any(|c| c == '\u{fe0f}') {
+        width.max(2)
+    } else {
+        width
+    }
+}
+
+/// Columns a tab at `column` advances to reach the next multiple of `tab_width`
+///
+/// Returns the padding that fills the rest of the row, when the tab would cross the
+/// right edge of the screen and moves to the next row like a wide character, together
+/// with the columns of the tab itself
+pub(crate) fn tab_columns(column: usize, tab_width: usize, screen_width: usize) -> (usize, usize) {
+    let tab_width = tab_width.max(1);
+    let screen_width = screen_width.max(1);
+    let column = if column >= screen_width { 0 } else { column };
+    let tab = tab_width - column % tab_width;
+    if column > 0 && column + tab > screen_width {
+        (screen_width - column, tab_width.min(screen_width))
+    } else {
+        (0, tab)
+    }
+}
+
+/// Replaces the tabs of ANSI escaped text with whitespace up to the next tab stop
+///
+/// `column` is the screen column the text starts at, it is advanced to the column the
+/// text ends at so the following text can continue from there
+pub(crate) fn expand_tab_stops<'a>(
+    text: &'a str,
+    tab_width: usize,
+    screen_width: usize,
+    column: &mut usize,
+) -> Cow<'a, str> {
+    let screen_width = screen_width.max(1);
+    let mut expanded = String::new();
+    for (token, is_escape) in ansi_tokens(text) {
+        if is_escape {
+            expanded.push_str(token);
+            continue;
+        }
+        match token {
+            "\t" => {
+                let (padding, tab) = tab_columns(*column, tab_width, screen_width);
+                expanded.extend(std::iter::repeat(' ').take(padding + tab));
+                *column = if padding > 0 {
+                    tab
+                } else {
+                    *column % screen_width + tab
+                };
+            }
+            "\r" | "\n" | "\r\n" => {
+                expanded.push_str(token);
+                *column = 0;
+            }
+            _ => {
+                let width = grapheme_width(token);
+                *column = if *column + width > screen_width {
+                    width
+                } else {
+                    *column + width
+                };
+                expanded.push_str(token);
+            }
+        }
+    }
+
+    if text.contains('\t') {
+        Cow::Owned(expanded)
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Splits ANSI escaped text in escape sequences and visible grapheme clusters
+pub(crate) fn ansi_tokens(line: &str) -> Vec<(&str, bool)> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices();
+    let mut text_start = 0;
+
+    while let Some((start, c)) = chars.next() {
+        if c != '\x1b' {
+            continue;
+        }
+        tokens.extend(line[text_start..start].graphemes(true).map(|g| (g, false)));
+
+        let mut end = start + c.len_utf8();
+        match chars.next() {
+            Some((index, '[')) => {
+                end = index + 1;
+                for (index, c) in chars.by_ref() {
+                    end = index + c.len_utf8();
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some((index, c)) => end = index + c.len_utf8(),
+            None => {}
+        }
+        tokens.push((&line[start..end], true));
+        text_start = end;
+    }
+    tokens.extend(line[text_start..].graphemes(true).map(|g| (g, false)));
+
+    tokens
+}
+
+/// Keeps the first `columns` visible columns of ANSI escaped text
+///
+/// All the escape sequences are kept so the styles of the clipped text stay intact
+pub(crate) fn keep_first_columns(line: &str, columns: usize) -> String {
+    let mut width = 0;
+    ansi_tokens(line)
+        .into_iter()
+        .filter(|(token, is_escape)| {
+            if *is_escape {
+                return true;
+            }
+            width += grapheme_width(token);
+            width <= columns
+        })
+        .map(|(token, _)| token)
+        .collect()
+}
+
+/// Keeps the last `columns` visible columns of ANSI escaped text
+///
+/// All the escape sequences are kept so the styles of the clipped text stay intact
+pub(crate) fn keep_last_columns(line: &str, columns: usize) -> String {
+    let skip = line_width(line).saturating_sub(columns);
+    let mut width = 0;
+    ansi_tokens(line)
+        .into_iter()
+        .filter(|(token, is_escape)| {
+            if *is_escape {
+                return true;
+            }
+            let start = width;
+            width += grapheme_width(token);
+            start >= skip
+        })
+        .map(|(token, _)| token)
+        .collect()
+}
+
+/// Repeats the style `prefix` after every escape sequence of ANSI escaped text
+///
+/// The style stays applied on top of the styles of the text, also after their resets
+pub(crate) fn restyle_after_escapes(line: &str, prefix: &str) -> String {
+    ansi_tokens(line)
+        .into_iter()
+        .flat_map(|(token, is_escape)| [token, if is_escape { prefix } else { "" }])
+        .collect()
+}
+
+/// Clips ANSI escaped text to `columns` visible columns, marking the clipped end with `…`
+pub(crate) fn clip_to_width(line: &str, columns: usize) -> Cow<'_, str> {
+    if line_width(line) <= columns {
+        Cow::Borrowed(line)
+    } else {
+        let clipped = keep_first_columns(line, columns.saturating_sub(1));
+        Cow::Owned(format!("{clipped}…"))
+    }
 }
 
 #[cfg(test)]
@@ -77,6 +284,13 @@ mod test {
     use pretty_assertions::assert_eq;
     use rstest::rstest;
 
+    #[rstest]
+    #[case("plain", "plain")]
+    #[case("\x1b[32mi32\x1b[0m", "\x1b[32m\x1b[1mi32\x1b[0m\x1b[1m")]
+    fn restyle_after_escapes_keeps_the_style_on_top(#[case] line: &str, #[case] expected: &str) {
+        assert_eq!(restyle_after_escapes(line, "\x1b[1m"), expected);
+    }
+
     #[rstest]
     #[case("sentence\nsentence", "sentence\r\nsentence")]
     #[case("sentence\r\nsentence", "sentence\r\nsentence")]
@@ -95,4 +309,82 @@ mod test {
             "Unnecessary allocation"
         )
     }
+
+    #[rstest]
+    #[case("abc", "abc")]
+    #[case("a\tb\nc\r\nd", "a\tb\nc\r\nd")]
+    #[case("\x1b[2J", "^[[2J")]
+    #[case("a\x08\x07\x7f\r", "a^H^G^?^M")]
+    fn caret_notation_shows_control_characters(#[case] text: &str, #[case] expected: &str) {
+        assert_eq!(caret_notation(text), expected);
+    }
+
+    #[rstest]
+    #[case("abc", 3)]
+    #[case("cafe\u{301}", 4)]
+    #[case("👨\u{200d}👩\u{200d}👧", 2)]
+    #[case("🇩🇪 ", 3)]
+    #[case("\u{2764}\u{fe0f}", 2)]
+    #[case("\x1b[31m日本\x1b[0m", 4)]
+    fn line_width_counts_grapheme_clusters(#[case] input: &str, #[case] expected: usize) {
+        assert_eq!(line_width(input), expected);
+    }
+
+    #[rstest]
+    #[case("a\tb", 0, "a   b", 5)]
+    #[case("\tx", 2, "  x", 5)]
+    #[case("\t", 9, "     ", 4)]
+    #[case("ab\ncd\te", 3, "ab\ncd  e", 5)]
+    #[case("\x1b[1m\tx", 0, "\x1b[1m    x", 5)]
+    fn test_expand_tab_stops(
+        #[case] input: &str,
+        #[case] start: usize,
+        #[case] expected: &str,
+        #[case] end: usize,
+    ) {
+        let mut column = start;
+
+        assert_eq!(expand_tab_stops(input, 4, 10, &mut column), expected);
+        assert_eq!(column, end);
+    }
+
+    #[rstest]
+    #[case("no tabs", 4, "no tabs")]
+    #[case("a\tb", 1, "a b")]
+    #[case("a\tb", 4, "a   b")]
+    #[case("abcd\tb", 4, "abcd    b")]
+    #[case("文\t\tb", 4, "文      b")]
+    fn tab_stops_without_a_screen_edge(
+        #[case] input: &str,
+        #[case] tab_width: usize,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(
+            expand_tab_stops(input, tab_width, usize::MAX, &mut 0),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case("abcdef", 3, "abc")]
+    #[case("abc", 5, "abc")]
+    #[case("a😇b", 2, "a")]
+    #[case("\x1b[31mabc\x1b[0mdef", 4, "\x1b[31mabc\x1b[0md")]
+    #[case("\x1b[31mabc\x1b[0mdef", 0, "\x1b[31m\x1b[0m")]
+    fn test_keep_first_columns(
+        #[case] input: &str,
+        #[case] columns: usize,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(keep_first_columns(input, columns), expected);
+    }
+
+    #[rstest]
+    #[case("abcdef", 3, "def")]
+    #[case("abc", 5, "abc")]
+    #[case("a😇b", 2, "b")]
+    #[case("\x1b[31mabc\x1b[0mdef", 4, "\x1b[31mc\x1b[0mdef")]
+    fn test_keep_last_columns(#[case] input: &str, #[case] columns: usize, #[case] expected: &str) {
+        assert_eq!(keep_last_columns(input, columns), expected);
+    }
 }
@@ -1,9 +1,11 @@
 use {
+    crate::SearchDirection,
     crossterm::style::Color,
     serde::{Deserialize, Serialize},
     std::{
         borrow::Cow,
         fmt::{Display, Formatter},
+        time::Duration,
     },
     strum_macros::EnumIter,
 };
@@ -24,12 +26,24 @@ pub enum PromptHistorySearchStatus {
 }
 
 /// A representation of the history search
+///
+/// Create it with [`PromptHistorySearch::new`] and its builders, further fields may
+/// be added without breaking the code building it
+#[non_exhaustive]
 pub struct PromptHistorySearch {
     /// The status of the search
     pub status: PromptHistorySearchStatus,
 
     /// The search term used during the search
     pub term: String,
+
+    /// Position of the shown match, counted from the most recent one, and the total
+    /// number of matches. `None` if no match is shown
+    pub match_position: Option<(usize, usize)>,
+
+    /// The direction the search stepped in last, backward to older matches or forward
+    /// to newer ones
+    pub direction: SearchDirection,
 }
 
 impl PromptHistorySearch {
@@ -38,10 +52,31 @@ impl PromptHistorySearch {
         PromptHistorySearch {
             status,
             term: search_term,
+            match_position: None,
+            direction: SearchDirection::Backward,
         }
     }
+
+    /// A builder that sets the position of the shown match and the number of matches
+    #[must_use]
+    pub const fn with_match_position(mut self, match_position: Option<(usize, usize)>) -> Self {
+        self.match_position = match_position;
+        self
+    }
+
+    /// A builder that sets the direction the search stepped in last
+    #[must_use]
+    pub const fn with_direction(mut self, direction: SearchDirection) -> Self {
+        self.direction = direction;
+        self
+    }
 }
 
+/// Renders the prompt indicator of the history search in place of
+/// [`Prompt::render_prompt_history_search_indicator`], see
+/// [`Reedline::with_history_search_indicator`](crate::Reedline::with_history_search_indicator)
+pub type HistorySearchIndicator = Box<dyn Fn(&PromptHistorySearch) -> String + Send>;
+
 /// Modes that the prompt can be in
 #[derive(Serialize, Deserialize, Clone, Debug, EnumIter)]
 pub enum PromptEditMode {
@@ -59,7 +94,11 @@ pub enum PromptEditMode {
 }
 
 /// The vi-specific modes that the prompt can be in
+///
+/// Prompts matching on it need a wildcard arm, so further modes can be added without
+/// breaking them. [`PromptViMode::Visual`] is the first of those additions.
 #[derive(Serialize, Deserialize, Clone, Debug, EnumIter, Default)]
+#[non_exhaustive]
 pub enum PromptViMode {
     /// The default mode
     #[default]
@@ -67,6 +106,9 @@ pub enum PromptViMode {
 
     /// Insertion mode
     Insert,
+
+    /// Visual mode, selecting text with the movements
+    Visual,
 }
 
 impl Display for PromptEditMode {
@@ -74,24 +116,73 @@ impl Display for PromptEditMode {
         match self {
             PromptEditMode::Default => write!(f, "Default"),
             PromptEditMode::Emacs => write!(f, "Emacs"),
-            PromptEditMode::Vi(_) => write!(f, "Vi_Normal\nVi_Insert"),
+            PromptEditMode::Vi(_) => write!(f, "Vi_Normal\nVi_Insert\nVi_Visual"),
             PromptEditMode::Custom(s) => write!(f, "Custom_{s}"),
         }
     }
 }
+/// Exit status and duration of the previous command, set by the application with
+/// [`Reedline::set_last_command_status`](crate::Reedline::set_last_command_status)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandStatus {
+    /// Exit code of the command, `0` on success
+    pub exit_code: i32,
+    /// Time the command took
+    pub duration: Duration,
+}
+
+impl CommandStatus {
+    /// Whether the command exited with a nonzero code
+    pub fn failed(&self) -> bool {
+        self.exit_code != 0
+    }
+}
+
 /// API to provide a custom prompt.
 ///
 /// Implementors have to provide [`str`]-based content which will be
 /// displayed before the `LineBuffer` is drawn.
 pub trait Prompt: Send {
-    /// Provide content off the right full prompt
-    fn render_prompt_left(&self) -> Cow<str>;
     /// Provide content off the left full prompt
-    fn render_prompt_right(&self) -> Cow<str>;
+    fn render_prompt_left(&self) -> Cow<str>;
+    /// Provide content off the right full prompt, empty by default
+    ///
+    /// It is right aligned on the first line of the prompt, or on the last line if
+    /// [`Prompt::right_prompt_on_last_line`] is set, and hidden while the input on that
+    /// line would reach into it.
+    fn render_prompt_right(&self) -> Cow<str> {
+        Cow::Borrowed("")
+    }
+    /// Provide a short prompt replacing the full prompt of a line once it is accepted
+    ///
+    /// The accepted line is repainted once with it, without the prompt indicator and
+    /// the right prompt, before it scrolls up into the terminal history. `None` by
+    /// default, which leaves the full prompt in place.
+    fn render_prompt_transient(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+    /// Provide a segment showing the exit status and duration of the previous command
+    ///
+    /// It is painted before the left prompt while a status is set, e.g. to show a red
+    /// exit code after a failed command. `None` by default, which shows nothing.
+    fn render_prompt_status(&self, _status: &CommandStatus) -> Option<Cow<'_, str>> {
+        None
+    }
     /// Render the prompt indicator (Last part of the prompt that changes based on the editor mode)
     fn render_prompt_indicator(&self, prompt_mode: PromptEditMode) -> Cow<str>;
     /// Indicator to show before explicit new lines
     fn render_prompt_multiline_indicator(&self) -> Cow<str>;
+    /// Indicator to show before explicit new lines of an input the validator found
+    /// incomplete
+    ///
+    /// The reason is the one of [`ValidationResult::IncompleteWithReason`], like an
+    /// open quote or bracket, to reflect it in the indicator. Falls back to
+    /// [`Prompt::render_prompt_multiline_indicator`] by default.
+    ///
+    /// [`ValidationResult::IncompleteWithReason`]: crate::ValidationResult::IncompleteWithReason
+    fn render_prompt_multiline_indicator_with_reason(&self, _reason: Option<&str>) -> Cow<'_, str> {
+        self.render_prompt_multiline_indicator()
+    }
     /// Render the prompt indicator for `Ctrl-R` history search
     fn render_prompt_history_search_indicator(
         &self,
@@ -1,4 +1,7 @@
-use crate::{Prompt, PromptEditMode, PromptHistorySearch, PromptHistorySearchStatus, PromptViMode};
+use crate::{
+    Prompt, PromptEditMode, PromptHistorySearch, PromptHistorySearchStatus, PromptViMode,
+    SearchDirection,
+};
 
 use {
     chrono::Local,
@@ -63,7 +66,9 @@ impl Prompt for DefaultPrompt {
         match edit_mode {
             PromptEditMode::Default | PromptEditMode::Emacs => DEFAULT_PROMPT_INDICATOR.into(),
             PromptEditMode::Vi(vi_mode) => match vi_mode {
-                PromptViMode::Normal => DEFAULT_VI_NORMAL_PROMPT_INDICATOR.into(),
+                PromptViMode::Normal | PromptViMode::Visual => {
+                    DEFAULT_VI_NORMAL_PROMPT_INDICATOR.into()
+                }
                 PromptViMode::Insert => DEFAULT_VI_INSERT_PROMPT_INDICATOR.into(),
             },
             PromptEditMode::Custom(str) => format!("({str})").into(),
@@ -82,11 +87,15 @@ impl Prompt for DefaultPrompt {
             PromptHistorySearchStatus::Passing => "",
             PromptHistorySearchStatus::Failing => "failing ",
         };
+        let direction = match history_search.direction {
+            SearchDirection::Backward => "reverse",
+            SearchDirection::Forward => "forward",
+        };
         // NOTE: magic strings, given there is logic on how these compose I am not sure if it
         // is worth extracting in to static constant
         Cow::Owned(format!(
-            "({}reverse-search: {}) ",
-            prefix, history_search.term
+            "({}{}-search: {}) ",
+            prefix, direction, history_search.term
         ))
     }
 }
@@ -1,8 +1,15 @@
 mod base;
 mod default;
+#[cfg(test)]
+mod test_prompt;
+mod transient;
 
 pub use base::{
-    Prompt, PromptEditMode, PromptHistorySearch, PromptHistorySearchStatus, PromptViMode,
+    CommandStatus, HistorySearchIndicator, Prompt, PromptEditMode, PromptHistorySearch,
+    PromptHistorySearchStatus, PromptViMode,
 };
 
 pub use default::{DefaultPrompt, DefaultPromptSegment};
+#[cfg(test)]
+pub(crate) use test_prompt::TestPrompt;
+pub(crate) use transient::TransientPrompt;
@@ -0,0 +1,77 @@
+use {
+    super::{CommandStatus, Prompt, PromptEditMode, PromptHistorySearch},
+    std::borrow::Cow,
+};
+
+/// Prompt with plain pieces for the tests, each one can be replaced
+///
+/// By default only the indicators are set: `> ` for the prompt, `::: ` for new lines
+/// and `? ` for the history search.
+#[derive(Clone)]
+pub(crate) struct TestPrompt {
+    pub(crate) left: String,
+    pub(crate) right: String,
+    pub(crate) indicator: String,
+    pub(crate) multiline_indicator: String,
+    /// Shown once a line is accepted
+    pub(crate) transient: Option<String>,
+    /// Shows `[<exit code>] ` after a failed command
+    pub(crate) shows_failed_status: bool,
+    /// Shows `<reason>> ` on the new lines of an incomplete input
+    pub(crate) shows_incomplete_reason: bool,
+}
+
+impl Default for TestPrompt {
+    fn default() -> Self {
+        Self {
+            left: String::new(),
+            right: String::new(),
+            indicator: "> ".into(),
+            multiline_indicator: "::: ".into(),
+            transient: None,
+            shows_failed_status: false,
+            shows_incomplete_reason: false,
+        }
+    }
+}
+
+impl Prompt for TestPrompt {
+    fn render_prompt_left(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.left)
+    }
+
+    fn render_prompt_right(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.right)
+    }
+
+    fn render_prompt_transient(&self) -> Option<Cow<'_, str>> {
+        self.transient.as_deref().map(Cow::Borrowed)
+    }
+
+    fn render_prompt_status(&self, status: &CommandStatus) -> Option<Cow<'_, str>> {
+        (self.shows_failed_status && status.failed())
+            .then(|| format!("[{}] ", status.exit_code).into())
+    }
+
+    fn render_prompt_indicator(&self, _prompt_mode: PromptEditMode) -> Cow<'_, str> {
+        Cow::Borrowed(&self.indicator)
+    }
+
+    fn render_prompt_multiline_indicator(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.multiline_indicator)
+    }
+
+    fn render_prompt_multiline_indicator_with_reason(&self, reason: Option<&str>) -> Cow<'_, str> {
+        match reason {
+            Some(reason) if self.shows_incomplete_reason => format!("{reason}> ").into(),
+            _ => self.render_prompt_multiline_indicator(),
+        }
+    }
+
+    fn render_prompt_history_search_indicator(
+        &self,
+        _history_search: PromptHistorySearch,
+    ) -> Cow<'_, str> {
+        Cow::Borrowed("? ")
+    }
+}
@@ -0,0 +1,94 @@
+use {
+    super::{Prompt, PromptEditMode, PromptHistorySearch},
+    crossterm::style::Color,
+    std::borrow::Cow,
+};
+
+/// Stand-in for a [`Prompt`] used to repaint an accepted line with the prompt's
+/// [`Prompt::render_prompt_transient`]
+pub(crate) struct TransientPrompt {
+    prompt: String,
+    multiline_indicator: String,
+    prompt_color: Color,
+    prompt_multiline_color: nu_ansi_term::Color,
+}
+
+impl TransientPrompt {
+    /// Returns `None` if the prompt keeps its full form after a line is accepted
+    pub(crate) fn from_prompt(prompt: &dyn Prompt) -> Option<Self> {
+        let transient = prompt.render_prompt_transient()?;
+
+        Some(Self {
+            prompt: transient.into_owned(),
+            multiline_indicator: prompt.render_prompt_multiline_indicator().into_owned(),
+            prompt_color: prompt.get_prompt_color(),
+            prompt_multiline_color: prompt.get_prompt_multiline_color(),
+        })
+    }
+}
+
+impl Prompt for TransientPrompt {
+    fn render_prompt_left(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.prompt)
+    }
+
+    fn render_prompt_indicator(&self, _prompt_mode: PromptEditMode) -> Cow<'_, str> {
+        Cow::Borrowed("")
+    }
+
+    fn render_prompt_multiline_indicator(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.multiline_indicator)
+    }
+
+    fn render_prompt_history_search_indicator(
+        &self,
+        _history_search: PromptHistorySearch,
+    ) -> Cow<'_, str> {
+        Cow::Borrowed("")
+    }
+
+    fn get_prompt_color(&self) -> Color {
+        self.prompt_color
+    }
+
+    fn get_prompt_multiline_color(&self) -> nu_ansi_term::Color {
+        self.prompt_multiline_color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt::TestPrompt;
+    use pretty_assertions::assert_eq;
+
+    fn full_prompt() -> TestPrompt {
+        TestPrompt {
+            left: "~/src".into(),
+            right: "main".into(),
+            ..TestPrompt::default()
+        }
+    }
+
+    #[test]
+    fn full_prompt_is_kept_by_default() {
+        assert!(TransientPrompt::from_prompt(&full_prompt()).is_none());
+    }
+
+    #[test]
+    fn transient_prompt_replaces_the_prompt_segments() {
+        let prompt = TestPrompt {
+            transient: Some("$ ".into()),
+            ..full_prompt()
+        };
+        let transient = TransientPrompt::from_prompt(&prompt).unwrap();
+
+        assert_eq!(transient.render_prompt_left(), "$ ");
+        assert_eq!(transient.render_prompt_right(), "");
+        assert_eq!(transient.render_prompt_indicator(PromptEditMode::Emacs), "");
+        assert_eq!(
+            transient.render_prompt_multiline_indicator(),
+            prompt.render_prompt_multiline_indicator()
+        );
+    }
+}
@@ -15,6 +15,8 @@ pub(crate) enum ReedlineErrorVariants {
         history: &'static str,
         feature: &'static str,
     },
+    #[error("invalid regex in history search: {0}")]
+    InvalidRegex(regex::Error),
     #[error("I/O error: {0}")]
     IOError(std::io::Error),
 }
@@ -0,0 +1,40 @@
+use super::quotes::{QuoteScanner, DEFAULT_QUOTES};
+
+/// The brackets outside of quotes and escapes together with the position of their
+/// partner, the quotes are scanned with the rules of [`QuoteScanner`]
+pub(crate) fn bracket_partners(line: &str) -> Vec<(usize, Option<usize>)> {
+    let mut brackets: Vec<(usize, Option<usize>)> = vec![];
+    // Open brackets waiting for their partner as (bracket, index in `brackets`)
+    let mut open: Vec<(char, usize)> = vec![];
+    let mut scanner = QuoteScanner::new(&DEFAULT_QUOTES);
+
+    for (index, c) in line.char_indices() {
+        if scanner.scan(c) {
+            match c {
+                '(' | '[' | '{' => {
+                    open.push((c, brackets.len()));
+                    brackets.push((index, None));
+                }
+                ')' | ']' | '}' => {
+                    let opening = match c {
+                        ')' => '(',
+                        ']' => '[',
+                        _ => '{',
+                    };
+                    match open.last() {
+                        Some((bracket, open_index)) if *bracket == opening => {
+                            let open_index = *open_index;
+                            open.pop();
+                            brackets[open_index].1 = Some(index);
+                            brackets.push((index, Some(brackets[open_index].0)));
+                        }
+                        _ => brackets.push((index, None)),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    brackets
+}
@@ -1,4 +1,6 @@
+pub(crate) mod brackets;
 mod query;
+pub(crate) mod quotes;
 pub(crate) mod text_manipulation;
 
 pub use query::{
@@ -1,6 +1,6 @@
 use crate::{
     default_emacs_keybindings, default_vi_insert_keybindings, default_vi_normal_keybindings,
-    EditCommand, Keybindings, PromptEditMode, ReedlineEvent,
+    EditCommand, Keybindings, PromptEditMode, ReedlineEvent, TextObject,
 };
 use crossterm::event::KeyCode;
 use std::fmt::{Display, Formatter};
@@ -108,7 +108,15 @@ pub fn get_reedline_reedline_events() -> Vec<String> {
 
 /// Return a `Vec<String>` of the Reedline [`EditCommand`]s
 pub fn get_reedline_edit_commands() -> Vec<String> {
-    EditCommand::iter().map(|edit| edit.to_string()).collect()
+    // The text object commands can't be iterated as a `TextObject` has no default
+    let text_object_commands = [
+        EditCommand::CutTextObject(TextObject::InnerWord),
+        EditCommand::CopyTextObject(TextObject::InnerWord),
+    ];
+    EditCommand::iter()
+        .chain(text_object_commands)
+        .map(|edit| edit.to_string())
+        .collect()
 }
 
 /// Get the default keybindings and return a `Vec<(String, String, String, String)>`
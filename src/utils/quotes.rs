@@ -0,0 +1,100 @@
+/// The quotes recognized by default: `'`, `"` and `` ` ``
+pub(crate) const DEFAULT_QUOTES: [char; 3] = ['\'', '"', '`'];
+
+/// Tracks the quotes and escapes while the characters of a line are fed one by one
+///
+/// Inside quotes, a quote of a different type doesn't end the string. A backslash
+/// escapes the following character, outside quotes and inside quotes other than
+/// single quotes. Like in POSIX shells, single-quoted text is taken literally, so a
+/// backslash can't escape the closing `'`.
+pub(crate) struct QuoteScanner<'a> {
+    quotes: &'a [char],
+    quote: Option<char>,
+    escaped: bool,
+}
+
+impl<'a> QuoteScanner<'a> {
+    pub(crate) fn new(quotes: &'a [char]) -> Self {
+        Self {
+            quotes,
+            quote: None,
+            escaped: false,
+        }
+    }
+
+    /// Feed the next character, returns whether it is outside quotes and escapes
+    ///
+    /// Quotes and the backslashes escaping a character are not outside themselves.
+    pub(crate) fn scan(&mut self, c: char) -> bool {
+        if self.escaped {
+            self.escaped = false;
+            return false;
+        }
+
+        match self.quote {
+            Some(quote) => {
+                if c == quote {
+                    self.quote = None;
+                } else if c == '\\' && quote != '\'' {
+                    self.escaped = true;
+                }
+                false
+            }
+            None if c == '\\' => {
+                self.escaped = true;
+                false
+            }
+            None if self.quotes.contains(&c) => {
+                self.quote = Some(c);
+                false
+            }
+            None => true,
+        }
+    }
+
+    /// Whether a quote is still open after the characters fed so far
+    pub(crate) fn in_quote(&self) -> bool {
+        self.quote.is_some()
+    }
+
+    /// Whether the characters fed so far end with a backslash escaping nothing yet
+    pub(crate) fn escaped(&self) -> bool {
+        self.escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("a 'b' c", "a  c")]
+    #[case("\"a 'b\" c", " c")]
+    #[case("a \\' b", "a  b")]
+    #[case("\"a \\\" b\" c", " c")]
+    #[case("'a \\' b", " b")]
+    #[case("`a \\` b` c", " c")]
+    fn only_text_outside_quotes_and_escapes_is_kept(#[case] line: &str, #[case] expected: &str) {
+        let mut scanner = QuoteScanner::new(&DEFAULT_QUOTES);
+        let outside: String = line.chars().filter(|c| scanner.scan(*c)).collect();
+
+        assert_eq!(outside, expected);
+    }
+
+    #[rstest]
+    #[case("echo 'a", true, false)]
+    #[case("echo 'a'", false, false)]
+    #[case("echo a \\", false, true)]
+    #[case("echo \"a \\", true, true)]
+    fn state_after_the_line(#[case] line: &str, #[case] in_quote: bool, #[case] escaped: bool) {
+        let mut scanner = QuoteScanner::new(&DEFAULT_QUOTES);
+        line.chars().for_each(|c| {
+            scanner.scan(c);
+        });
+
+        assert_eq!(scanner.in_quote(), in_quote);
+        assert_eq!(scanner.escaped(), escaped);
+    }
+}
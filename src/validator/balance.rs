@@ -0,0 +1,155 @@
+use crate::utils::quotes::{QuoteScanner, DEFAULT_QUOTES};
+use crate::{ValidationResult, Validator};
+
+/// A validator that asks for more input while brackets or quotes are still open
+///
+/// Tracks the nesting of the configured bracket pairs, `()`, `[]` and `{}` by
+/// default, and the configured quotes, `'`, `"` and `` ` `` by default. Delimiters
+/// inside quotes are ignored, so a quote of a different type doesn't end the
+/// string. A backslash escapes the following character, outside quotes and inside
+/// quotes other than single quotes. Like in POSIX shells, single-quoted text is
+/// taken literally, so a backslash can't escape the closing `'`.
+///
+/// The input is only [`ValidationResult::Incomplete`] if something is still open.
+/// A closing bracket without its opening partner is a syntax error for the
+/// application to report, so the input is [`ValidationResult::Complete`].
+///
+/// # Example
+///
+/// ```rust
+/// use reedline::{BalanceValidator, ValidationResult, Validator};
+///
+/// let validator = BalanceValidator::default().with_line_continuation(true);
+///
+/// assert!(matches!(validator.validate("echo (1 + 2"), ValidationResult::Incomplete));
+/// assert!(matches!(validator.validate("echo \"(\""), ValidationResult::Complete));
+/// assert!(matches!(validator.validate("echo 1 \\"), ValidationResult::Incomplete));
+/// ```
+pub struct BalanceValidator {
+    brackets: Vec<(char, char)>,
+    quotes: Vec<char>,
+    line_continuation: bool,
+}
+
+impl Default for BalanceValidator {
+    fn default() -> Self {
+        Self {
+            brackets: vec![('(', ')'), ('[', ']'), ('{', '}')],
+            quotes: DEFAULT_QUOTES.to_vec(),
+            line_continuation: false,
+        }
+    }
+}
+
+impl BalanceValidator {
+    /// A builder that sets the bracket pairs as (opening, closing) characters
+    #[must_use]
+    pub fn with_brackets(mut self, brackets: Vec<(char, char)>) -> Self {
+        self.brackets = brackets;
+        self
+    }
+
+    /// A builder that sets the characters that start and end a quoted string
+    #[must_use]
+    pub fn with_quotes(mut self, quotes: Vec<char>) -> Self {
+        self.quotes = quotes;
+        self
+    }
+
+    /// A builder that continues the input on a new line if it ends with a backslash
+    /// that isn't escaped itself (default: false)
+    #[must_use]
+    pub fn with_line_continuation(mut self, line_continuation: bool) -> Self {
+        self.line_continuation = line_continuation;
+        self
+    }
+}
+
+impl Validator for BalanceValidator {
+    fn validate(&self, line: &str) -> ValidationResult {
+        // Closing characters of the open brackets
+        let mut open: Vec<char> = vec![];
+        let mut scanner = QuoteScanner::new(&self.quotes);
+
+        for c in line.chars() {
+            if !scanner.scan(c) {
+                continue;
+            }
+
+            if let Some((_, closing)) = self.brackets.iter().find(|(o, _)| *o == c) {
+                open.push(*closing);
+            } else if self.brackets.iter().any(|(_, closing)| *closing == c) {
+                if open.last() != Some(&c) {
+                    return ValidationResult::Complete;
+                }
+                open.pop();
+            }
+        }
+
+        if scanner.in_quote() || !open.is_empty() || (scanner.escaped() && self.line_continuation) {
+            ValidationResult::Incomplete
+        } else {
+            ValidationResult::Complete
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("", false)]
+    #[case("ls -la", false)]
+    #[case("(([[]]))", false)]
+    #[case("(([[]]", true)]
+    #[case("{[]}{()}", false)]
+    #[case("echo 'hello", true)]
+    #[case("echo \"it's\"", false)]
+    #[case("echo `a \"b` c", false)]
+    #[case("echo \"(\"", false)]
+    #[case("echo \")\"", false)]
+    #[case("echo \\(", false)]
+    #[case("echo \"a \\\" b", true)]
+    #[case("echo \"a \\\" b\"", false)]
+    #[case("echo 'a \\' b", false)]
+    #[case("echo 'a \\'", false)]
+    #[case("echo 'a \\' 'b", true)]
+    #[case("echo `a \\` b", true)]
+    #[case("{[}]", false)]
+    #[case(")(", false)]
+    #[case("foo {\n  bar", true)]
+    #[case("echo 1 \\", false)]
+    fn test_default_balance(#[case] input: &str, #[case] incomplete: bool) {
+        let result = BalanceValidator::default().validate(input);
+
+        assert_eq!(matches!(result, ValidationResult::Incomplete), incomplete);
+    }
+
+    #[rstest]
+    #[case("echo 1 \\", true)]
+    #[case("echo 1 \\\\", false)]
+    #[case("echo 1", false)]
+    fn test_line_continuation(#[case] input: &str, #[case] incomplete: bool) {
+        let result = BalanceValidator::default()
+            .with_line_continuation(true)
+            .validate(input);
+
+        assert_eq!(matches!(result, ValidationResult::Incomplete), incomplete);
+    }
+
+    #[rstest]
+    #[case("<a", true)]
+    #[case("(a", false)]
+    #[case("'a", false)]
+    #[case("|a", true)]
+    fn test_custom_delimiters(#[case] input: &str, #[case] incomplete: bool) {
+        let result = BalanceValidator::default()
+            .with_brackets(vec![('<', '>')])
+            .with_quotes(vec!['|'])
+            .validate(input);
+
+        assert_eq!(matches!(result, ValidationResult::Incomplete), incomplete);
+    }
+}
@@ -1,4 +1,6 @@
+mod balance;
 mod default;
+pub use balance::BalanceValidator;
 pub use default::DefaultValidator;
 
 /// The syntax validation trait. Implementers of this trait will check to see if the current input
@@ -8,12 +10,22 @@ pub trait Validator: Send {
     fn validate(&self, line: &str) -> ValidationResult;
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 /// Whether or not the validation shows the input was complete
 pub enum ValidationResult {
     /// An incomplete input which may need to span multiple lines to be complete
     Incomplete,
 
+    /// An incomplete input, with the reason it is incomplete, like an open quote or
+    /// bracket
+    ///
+    /// The reason is passed to [`Prompt::render_prompt_multiline_indicator_with_reason`]
+    /// to show it in the continuation prompt. Every line break gets its own reason: to
+    /// paint the buffer the validator is asked again for the input before each of them.
+    ///
+    /// [`Prompt::render_prompt_multiline_indicator_with_reason`]: crate::Prompt::render_prompt_multiline_indicator_with_reason
+    IncompleteWithReason(String),
+
     /// An input that is complete as-is
     Complete,
 }